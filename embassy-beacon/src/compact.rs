@@ -0,0 +1,109 @@
+//! Compact trace records (`compact` feature) for links with little bandwidth. Timestamps are
+//! sent as the delta to the previous event of the same core, executor and task ids as the
+//! index into an id table built up during the session. The visor reconstructs the absolute
+//! values from the records in order.
+//!
+//! Records (between the usual trace line markers):
+//! - `{@<core>,<timestamp>}` absolute timestamp of the core (before its first delta)
+//! - `{=<index>,<id>}` new entry of the id table
+//! - `{<delta>,<core>,<event code>,<executor index>[,<task index>]}` event
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+/// Cores with delta encoded timestamps (events of other cores are published uncompressed)
+const MAX_CORES: usize = 4;
+
+/// Entries of the id table (once full, events with unlisted ids are published uncompressed)
+const ID_TABLE_SIZE: usize = 128;
+
+struct Encoder {
+    /// Timestamp of the latest event per core
+    last_time: [Option<u64>; MAX_CORES],
+    /// Executor and task ids by their index
+    ids: [u32; ID_TABLE_SIZE],
+    id_count: usize,
+}
+
+static ENCODER: Mutex<RefCell<Encoder>> = Mutex::new(RefCell::new(Encoder {
+    last_time: [None; MAX_CORES],
+    ids: [0; ID_TABLE_SIZE],
+    id_count: 0,
+}));
+
+impl Encoder {
+    /// Index of the id, None if it is not listed yet
+    fn index_of(&self, id: u32) -> Option<u8> {
+        self.ids[..self.id_count]
+            .iter()
+            .position(|&listed| listed == id)
+            .map(|index| index as u8)
+    }
+}
+
+/// Publish an event compactly through `publish_event(delta, core, indices)`. Timestamp syncs
+/// and new id table entries are published before. Everything is published within one
+/// critical section, so the records of an interrupting executor can not get in between.
+///
+/// Returns false if the event can not be encoded (publish the uncompressed event instead).
+pub fn publish<const N: usize>(
+    now: u64,
+    core_id: u32,
+    ids: [u32; N],
+    publish_event: impl FnOnce(u32, u8, [u8; N]),
+) -> bool {
+    let core = core_id as usize;
+    if core >= MAX_CORES {
+        return false;
+    }
+
+    critical_section::with(|cs| {
+        let encoder = &mut *ENCODER.borrow_ref_mut(cs);
+
+        let unlisted = ids
+            .iter()
+            .filter(|&&id| encoder.index_of(id).is_none())
+            .count();
+        if encoder.id_count + unlisted > ID_TABLE_SIZE {
+            return false;
+        }
+
+        let mut indices = [0; N];
+        for (index, id) in indices.iter_mut().zip(ids) {
+            *index = match encoder.index_of(id) {
+                Some(listed) => listed,
+                None => {
+                    let new_index = encoder.id_count;
+                    encoder.ids[new_index] = id;
+                    encoder.id_count += 1;
+                    publish!(
+                        "embassy executor tracer - {{={},{}}} - embassy executor tracer",
+                        new_index as u8,
+                        id
+                    );
+                    new_index as u8
+                }
+            };
+        }
+
+        // Absolute timestamp for the first event of the core, or a gap too large for a delta
+        let delta =
+            encoder.last_time[core].and_then(|last| u32::try_from(now.checked_sub(last)?).ok());
+        let delta = match delta {
+            Some(delta) => delta,
+            None => {
+                publish!(
+                    "embassy executor tracer - {{@{},{}}} - embassy executor tracer",
+                    core_id as u8,
+                    now
+                );
+                0
+            }
+        };
+        encoder.last_time[core] = Some(now);
+
+        publish_event(delta, core_id as u8, indices);
+        true
+    })
+}
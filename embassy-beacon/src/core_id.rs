@@ -21,3 +21,28 @@ pub fn core_id() -> u32 {
     //
     0
 }
+
+/// Returns the ids of the target's cores as a bit set (bit N set: core N exists), so cores
+/// with non-contiguous ids can be declared.
+#[cfg(feature = "topology")]
+#[allow(unreachable_code)]
+pub fn core_ids() -> u32 {
+    //
+    // 1. ESP32 via esp-hal (xtensa or riscv32): cores 0..COUNT
+    //
+    #[cfg(any(target_arch = "xtensa", target_arch = "riscv32"))]
+    {
+        return (1u32 << esp_hal::system::Cpu::COUNT) - 1;
+    }
+
+    //
+    // Fallback: Single core 0
+    //
+    1
+}
+
+/// Returns the number of cores of the target.
+#[cfg(feature = "topology")]
+pub fn core_count() -> u32 {
+    core_ids().count_ones()
+}
@@ -4,118 +4,597 @@ use embassy_time::Instant;
 
 mod core_id;
 
-/// Macro to choose which defmt level to use for publishing tracing events (e.g., info!, debug!, etc.) based on a feature flag.
+// Fails to compile when more than one defmt level feature is enabled, so a misconfiguration
+// does not publish every event at several levels
+const _: () = assert!(
+    cfg!(feature = "defmt-trace") as usize
+        + cfg!(feature = "defmt-debug") as usize
+        + cfg!(feature = "defmt-info") as usize
+        + cfg!(feature = "defmt-warn") as usize
+        + cfg!(feature = "defmt-error") as usize
+        <= 1,
+    "conflicting defmt level features: enable at most one of `defmt-trace`, `defmt-debug`, `defmt-info`, `defmt-warn` and `defmt-error`"
+);
+
+/// Macro to publish tracing events via defmt at the level chosen by the `defmt-*` feature.
+/// With the `semihosting` feature, events are additionally written to the host's stdout via ARM semihosting.
 macro_rules! publish {
     ($($arg:tt)*) => {
-        #[cfg(feature = "defmt-trace")]
+        #[cfg(feature = "semihosting")]
+        cortex_m_semihosting::hprintln!($($arg)*);
+
+        publish_defmt!($($arg)*);
+    };
+}
+
+// Exactly one `publish_defmt` is defined: the enabled level, else println (enabled by the
+// default `defmt` feature), else nothing
+#[cfg(feature = "defmt-trace")]
+macro_rules! publish_defmt {
+    ($($arg:tt)*) => {
         defmt::trace!($($arg)*);
+    };
+}
 
-        #[cfg(feature = "defmt-debug")]
+#[cfg(feature = "defmt-debug")]
+macro_rules! publish_defmt {
+    ($($arg:tt)*) => {
         defmt::debug!($($arg)*);
+    };
+}
 
-        #[cfg(feature = "defmt-info")]
+#[cfg(feature = "defmt-info")]
+macro_rules! publish_defmt {
+    ($($arg:tt)*) => {
         defmt::info!($($arg)*);
+    };
+}
 
-        #[cfg(feature = "defmt-warn")]
+#[cfg(feature = "defmt-warn")]
+macro_rules! publish_defmt {
+    ($($arg:tt)*) => {
         defmt::warn!($($arg)*);
+    };
+}
 
-        #[cfg(feature = "defmt-error")]
+#[cfg(feature = "defmt-error")]
+macro_rules! publish_defmt {
+    ($($arg:tt)*) => {
         defmt::error!($($arg)*);
+    };
+}
 
-        // because defmt-debug is default active
-        #[cfg(not(any(feature = "defmt-trace", feature = "defmt-debug", feature = "defmt-info", feature = "defmt-warn", feature = "defmt-error")))]
-        {
-            #[cfg(feature = "defmt-println")]
-            defmt::println!($($arg)*);
-        }
+#[cfg(all(
+    feature = "defmt-println",
+    not(any(
+        feature = "defmt-trace",
+        feature = "defmt-debug",
+        feature = "defmt-info",
+        feature = "defmt-warn",
+        feature = "defmt-error"
+    ))
+))]
+macro_rules! publish_defmt {
+    ($($arg:tt)*) => {
+        defmt::println!($($arg)*);
     };
 }
 
-#[unsafe(no_mangle)]
-fn _embassy_trace_poll_start(executor_id: u32) {
+#[cfg(not(any(
+    feature = "defmt-println",
+    feature = "defmt-trace",
+    feature = "defmt-debug",
+    feature = "defmt-info",
+    feature = "defmt-warn",
+    feature = "defmt-error"
+)))]
+macro_rules! publish_defmt {
+    // Nothing is published, but the arguments are still used (no unused variable warnings)
+    ($format:literal $(, $arg:expr)* $(,)?) => {
+        $(let _ = &$arg;)*
+    };
+}
+
+// Only the executor and task events are published compactly
+#[cfg(all(
+    feature = "compact",
+    any(
+        feature = "event-poll-start",
+        feature = "event-executor-idle",
+        feature = "event-task-new",
+        feature = "event-task-end",
+        feature = "event-task-exec-begin",
+        feature = "event-task-exec-end",
+        feature = "event-task-ready-begin"
+    )
+))]
+mod compact;
+
+#[cfg(any(feature = "channel-trace", feature = "timer-trace"))]
+mod polled_task;
+
+/// Version of the trace schema (event names, payloads, encodings) published by this beacon.
+/// Raised whenever a change would make an older visor misread the trace; the visor compares
+/// it with the version it understands.
+pub const TRACE_SCHEMA_VERSION: u32 = 1;
+
+/// Tracks if the startup header was already published
+static STARTUP_EVENTS_PUBLISHED: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(false);
+
+/// Timestamp ticks per second declared in the header (every event is timestamped in microseconds)
+const TIMESTAMP_TICK_HZ: u64 = 1_000_000;
+
+/// Firmware identifier, set at compile time of the firmware via the
+/// `EMBASSY_BEACON_FIRMWARE_ID` environment variable (e.g. a git hash)
+#[cfg(feature = "firmware-id")]
+const FIRMWARE_ID: &str = match option_env!("EMBASSY_BEACON_FIRMWARE_ID") {
+    Some(id) => id,
+    None => "unknown",
+};
+
+/// Publish the one-time startup header (before the first executor or task event): schema
+/// version and timestamp rate, plus the core count and ids (`topology`) and the firmware id
+/// (`firmware-id`). The firmware id is free text and therefore the last field.
+/// Uses plain load/store because not every target supports atomic swaps; publishing twice is harmless.
+fn publish_startup_events_once() {
+    use core::sync::atomic::Ordering;
+
+    if STARTUP_EVENTS_PUBLISHED.load(Ordering::Relaxed) {
+        return;
+    }
+    STARTUP_EVENTS_PUBLISHED.store(true, Ordering::Relaxed);
+
     let now = Instant::now().as_micros();
     let core_id = core_id::core_id();
+
+    // Always published, so the visor can tell an incompatible beacon from a corrupted link
+    #[cfg(all(feature = "topology", feature = "firmware-id"))]
     publish!(
-        "embassy executor tracer - [{}, {}, ExecutorPollStart, {}] - embassy executor tracer",
+        "embassy executor tracer - [{}, {}, Header, schema={}, tick_hz={}, cores={}, core_ids={:#b}, firmware={}] - embassy executor tracer",
         now,
         core_id,
-        executor_id
+        TRACE_SCHEMA_VERSION,
+        TIMESTAMP_TICK_HZ,
+        core_id::core_count(),
+        core_id::core_ids(),
+        FIRMWARE_ID
     );
-}
 
-#[unsafe(no_mangle)]
-fn _embassy_trace_executor_idle(executor_id: u32) {
-    let now = Instant::now().as_micros();
-    let core_id = core_id::core_id();
+    #[cfg(all(feature = "topology", not(feature = "firmware-id")))]
     publish!(
-        "embassy executor tracer - [{}, {}, ExecutorIdle, {}] - embassy executor tracer",
+        "embassy executor tracer - [{}, {}, Header, schema={}, tick_hz={}, cores={}, core_ids={:#b}] - embassy executor tracer",
         now,
         core_id,
-        executor_id
+        TRACE_SCHEMA_VERSION,
+        TIMESTAMP_TICK_HZ,
+        core_id::core_count(),
+        core_id::core_ids()
     );
-}
 
-#[unsafe(no_mangle)]
-fn _embassy_trace_task_new(executor_id: u32, task_id: u32) {
-    let now = Instant::now().as_micros();
-    let core_id = core_id::core_id();
+    #[cfg(all(not(feature = "topology"), feature = "firmware-id"))]
     publish!(
-        "embassy executor tracer - [{}, {}, TaskNew, {}, {}] - embassy executor tracer",
+        "embassy executor tracer - [{}, {}, Header, schema={}, tick_hz={}, firmware={}] - embassy executor tracer",
         now,
         core_id,
-        executor_id,
-        task_id
+        TRACE_SCHEMA_VERSION,
+        TIMESTAMP_TICK_HZ,
+        FIRMWARE_ID
     );
-}
 
-#[unsafe(no_mangle)]
-fn _embassy_trace_task_end(executor_id: u32, task_id: u32) {
-    let now = Instant::now().as_micros();
-    let core_id = core_id::core_id();
+    #[cfg(not(any(feature = "topology", feature = "firmware-id")))]
     publish!(
-        "embassy executor tracer - [{}, {}, TaskEnd, {}, {}] - embassy executor tracer",
+        "embassy executor tracer - [{}, {}, Header, schema={}, tick_hz={}] - embassy executor tracer",
         now,
         core_id,
-        executor_id,
-        task_id
+        TRACE_SCHEMA_VERSION,
+        TIMESTAMP_TICK_HZ
     );
 }
 
+#[unsafe(no_mangle)]
+fn _embassy_trace_poll_start(executor_id: u32) {
+    publish_startup_events_once();
+
+    #[cfg(feature = "event-poll-start")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        #[cfg(feature = "compact")]
+        if compact::publish(now, core_id, [executor_id], |delta, core, [executor]| {
+            publish!(
+                "embassy executor tracer - {{{},{},P,{}}} - embassy executor tracer",
+                delta,
+                core,
+                executor
+            );
+        }) {
+            return;
+        }
+
+        publish!(
+            "embassy executor tracer - [{}, {}, ExecutorPollStart, {}] - embassy executor tracer",
+            now,
+            core_id,
+            executor_id
+        );
+    }
+
+    // The hook must still exist for the executor, it just publishes nothing
+    #[cfg(not(feature = "event-poll-start"))]
+    let _ = executor_id;
+}
+
+#[unsafe(no_mangle)]
+fn _embassy_trace_executor_idle(executor_id: u32) {
+    #[cfg(feature = "event-executor-idle")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        #[cfg(feature = "compact")]
+        if compact::publish(now, core_id, [executor_id], |delta, core, [executor]| {
+            publish!(
+                "embassy executor tracer - {{{},{},I,{}}} - embassy executor tracer",
+                delta,
+                core,
+                executor
+            );
+        }) {
+            return;
+        }
+
+        publish!(
+            "embassy executor tracer - [{}, {}, ExecutorIdle, {}] - embassy executor tracer",
+            now,
+            core_id,
+            executor_id
+        );
+    }
+
+    #[cfg(not(feature = "event-executor-idle"))]
+    let _ = executor_id;
+}
+
+#[unsafe(no_mangle)]
+fn _embassy_trace_task_new(executor_id: u32, task_id: u32) {
+    publish_startup_events_once();
+
+    #[cfg(feature = "event-task-new")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        #[cfg(feature = "compact")]
+        if compact::publish(
+            now,
+            core_id,
+            [executor_id, task_id],
+            |delta, core, [executor, task]| {
+                publish!(
+                    "embassy executor tracer - {{{},{},N,{},{}}} - embassy executor tracer",
+                    delta,
+                    core,
+                    executor,
+                    task
+                );
+            },
+        ) {
+            return;
+        }
+
+        publish!(
+            "embassy executor tracer - [{}, {}, TaskNew, {}, {}] - embassy executor tracer",
+            now,
+            core_id,
+            executor_id,
+            task_id
+        );
+    }
+
+    #[cfg(not(feature = "event-task-new"))]
+    let _ = (executor_id, task_id);
+}
+
+#[unsafe(no_mangle)]
+fn _embassy_trace_task_end(executor_id: u32, task_id: u32) {
+    #[cfg(feature = "event-task-end")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        #[cfg(feature = "compact")]
+        if compact::publish(
+            now,
+            core_id,
+            [executor_id, task_id],
+            |delta, core, [executor, task]| {
+                publish!(
+                    "embassy executor tracer - {{{},{},E,{},{}}} - embassy executor tracer",
+                    delta,
+                    core,
+                    executor,
+                    task
+                );
+            },
+        ) {
+            return;
+        }
+
+        publish!(
+            "embassy executor tracer - [{}, {}, TaskEnd, {}, {}] - embassy executor tracer",
+            now,
+            core_id,
+            executor_id,
+            task_id
+        );
+    }
+
+    #[cfg(not(feature = "event-task-end"))]
+    let _ = (executor_id, task_id);
+}
+
 #[unsafe(no_mangle)]
 fn _embassy_trace_task_exec_begin(executor_id: u32, task_id: u32) {
-    let now = Instant::now().as_micros();
-    let core_id = core_id::core_id();
-    publish!(
-        "embassy executor tracer - [{}, {}, TaskExecBegin, {}, {}] - embassy executor tracer",
-        now,
-        core_id,
-        executor_id,
-        task_id
-    );
+    #[cfg(any(feature = "channel-trace", feature = "timer-trace"))]
+    polled_task::poll_begin(core_id::core_id(), executor_id, task_id);
+
+    #[cfg(feature = "event-task-exec-begin")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        #[cfg(feature = "compact")]
+        if compact::publish(
+            now,
+            core_id,
+            [executor_id, task_id],
+            |delta, core, [executor, task]| {
+                publish!(
+                    "embassy executor tracer - {{{},{},B,{},{}}} - embassy executor tracer",
+                    delta,
+                    core,
+                    executor,
+                    task
+                );
+            },
+        ) {
+            return;
+        }
+
+        publish!(
+            "embassy executor tracer - [{}, {}, TaskExecBegin, {}, {}] - embassy executor tracer",
+            now,
+            core_id,
+            executor_id,
+            task_id
+        );
+    }
+
+    #[cfg(not(feature = "event-task-exec-begin"))]
+    let _ = (executor_id, task_id);
 }
 
 #[unsafe(no_mangle)]
 fn _embassy_trace_task_exec_end(excutor_id: u32, task_id: u32) {
-    let now = Instant::now().as_micros();
-    let core_id = core_id::core_id();
-    publish!(
-        "embassy executor tracer - [{}, {}, TaskExecEnd, {}, {}] - embassy executor tracer",
-        now,
-        core_id,
-        excutor_id,
-        task_id
-    );
+    #[cfg(any(feature = "channel-trace", feature = "timer-trace"))]
+    polled_task::poll_end(core_id::core_id());
+
+    #[cfg(feature = "event-task-exec-end")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        #[cfg(feature = "compact")]
+        if compact::publish(
+            now,
+            core_id,
+            [excutor_id, task_id],
+            |delta, core, [executor, task]| {
+                publish!(
+                    "embassy executor tracer - {{{},{},X,{},{}}} - embassy executor tracer",
+                    delta,
+                    core,
+                    executor,
+                    task
+                );
+            },
+        ) {
+            return;
+        }
+
+        publish!(
+            "embassy executor tracer - [{}, {}, TaskExecEnd, {}, {}] - embassy executor tracer",
+            now,
+            core_id,
+            excutor_id,
+            task_id
+        );
+    }
+
+    #[cfg(not(feature = "event-task-exec-end"))]
+    let _ = (excutor_id, task_id);
 }
 
 #[unsafe(no_mangle)]
 fn _embassy_trace_task_ready_begin(executor_id: u32, task_id: u32) {
-    let now = Instant::now().as_micros();
-    let core_id = core_id::core_id();
+    #[cfg(feature = "event-task-ready-begin")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        #[cfg(feature = "compact")]
+        if compact::publish(
+            now,
+            core_id,
+            [executor_id, task_id],
+            |delta, core, [executor, task]| {
+                publish!(
+                    "embassy executor tracer - {{{},{},R,{},{}}} - embassy executor tracer",
+                    delta,
+                    core,
+                    executor,
+                    task
+                );
+            },
+        ) {
+            return;
+        }
+
+        publish!(
+            "embassy executor tracer - [{}, {}, TaskReadyBegin, {}, {}] - embassy executor tracer",
+            now,
+            core_id,
+            executor_id,
+            task_id
+        );
+    }
+
+    #[cfg(not(feature = "event-task-ready-begin"))]
+    let _ = (executor_id, task_id);
+}
+
+/// Publish the high-water mark of a task's stack usage in bytes (`event-task-stack` feature,
+/// compiles to nothing without it).
+///
+/// Embassy tasks run on the stack of their executor, so the beacon cannot measure this on its
+/// own. Call it periodically from target specific code that can (e.g. stack painting checked
+/// around the polls of the task) with the ids the executor passes to the trace hooks. Tasks
+/// without a report show "n/a" in the visor.
+pub fn report_task_stack_usage(executor_id: u32, task_id: u32, high_water_bytes: u32) {
+    #[cfg(feature = "event-task-stack")]
+    {
+        let now = Instant::now().as_micros();
+        let core_id = core_id::core_id();
+        publish!(
+            "embassy executor tracer - [{}, {}, TaskStackUsage, {}, {}, {}] - embassy executor tracer",
+            now,
+            core_id,
+            executor_id,
+            task_id,
+            high_water_bytes
+        );
+    }
+
+    #[cfg(not(feature = "event-task-stack"))]
+    let _ = (executor_id, task_id, high_water_bytes);
+}
+
+/// Publish a heartbeat every `interval`, forever (`heartbeat` feature).
+///
+/// A heartbeat carries nothing but the timestamp, so the visor keeps aligning its clock with
+/// the firmware's while no executor or task event happens (e.g. a sleeping firmware). Spawn it
+/// from a task of the firmware:
+///
+/// ```ignore
+/// #[embassy_executor::task]
+/// async fn heartbeat() {
+///     embassy_beacon::run_heartbeat(Duration::from_secs(1)).await
+/// }
+/// ```
+#[cfg(feature = "heartbeat")]
+pub async fn run_heartbeat(interval: embassy_time::Duration) -> ! {
+    let mut ticker = embassy_time::Ticker::every(interval);
+    loop {
+        publish!(
+            "embassy executor tracer - [{}, {}, Heartbeat, {}] - embassy executor tracer",
+            Instant::now().as_micros(),
+            core_id::core_id(),
+            interval.as_millis()
+        );
+        ticker.next().await;
+    }
+}
+
+/// Publish that the task being polled sent a message to the channel (`channel-trace` feature,
+/// compiles to nothing without it).
+///
+/// Call it next to the send, e.g. `embassy_beacon::trace_channel_send(&CHANNEL as *const _ as u32)`.
+/// Any id identifying the channel works, but with its address the visor shows the name of the
+/// static. Calls outside of a task poll (e.g. from an interrupt handler) are not published.
+pub fn trace_channel_send(channel_id: u32) {
+    #[cfg(feature = "channel-trace")]
+    {
+        let core_id = core_id::core_id();
+        if let Some((executor_id, task_id)) = polled_task::polled_task(core_id) {
+            publish!(
+                "embassy executor tracer - [{}, {}, ChannelSend, {}, {}, {}] - embassy executor tracer",
+                Instant::now().as_micros(),
+                core_id,
+                executor_id,
+                task_id,
+                channel_id
+            );
+        }
+    }
+
+    #[cfg(not(feature = "channel-trace"))]
+    let _ = channel_id;
+}
+
+/// Publish that the task being polled received a message from the channel (`channel-trace`
+/// feature, compiles to nothing without it). The counterpart of [`trace_channel_send`].
+pub fn trace_channel_receive(channel_id: u32) {
+    #[cfg(feature = "channel-trace")]
+    {
+        let core_id = core_id::core_id();
+        if let Some((executor_id, task_id)) = polled_task::polled_task(core_id) {
+            publish!(
+                "embassy executor tracer - [{}, {}, ChannelReceive, {}, {}, {}] - embassy executor tracer",
+                Instant::now().as_micros(),
+                core_id,
+                executor_id,
+                task_id,
+                channel_id
+            );
+        }
+    }
+
+    #[cfg(not(feature = "channel-trace"))]
+    let _ = channel_id;
+}
+
+/// Publish that the task being polled set a timer expiring at `expires_at` (`timer-trace`
+/// feature, compiles to nothing without it).
+///
+/// Call it where the task arms the timer, e.g. before `Timer::at(expires_at).await`. Any id
+/// identifying the timer works (e.g. a constant per call site); it connects the set to its
+/// [`trace_timer_fire`]. Calls outside of a task poll are not published.
+pub fn trace_timer_set(timer_id: u32, expires_at: Instant) {
+    #[cfg(feature = "timer-trace")]
+    {
+        let core_id = core_id::core_id();
+        if let Some((executor_id, task_id)) = polled_task::polled_task(core_id) {
+            publish!(
+                "embassy executor tracer - [{}, {}, TimerSet, {}, {}, {}, {}] - embassy executor tracer",
+                Instant::now().as_micros(),
+                core_id,
+                executor_id,
+                task_id,
+                timer_id,
+                expires_at.as_micros()
+            );
+        }
+    }
+
+    #[cfg(not(feature = "timer-trace"))]
+    let _ = (timer_id, expires_at);
+}
+
+/// Publish that the timer fired (`timer-trace` feature, compiles to nothing without it).
+///
+/// Unlike the set, this may be called from anywhere, e.g. the alarm interrupt of the time
+/// driver. Without fire reports the visor takes the expiry of the timer as its fire time.
+pub fn trace_timer_fire(timer_id: u32) {
+    #[cfg(feature = "timer-trace")]
     publish!(
-        "embassy executor tracer - [{}, {}, TaskReadyBegin, {}, {}] - embassy executor tracer",
-        now,
-        core_id,
-        executor_id,
-        task_id
+        "embassy executor tracer - [{}, {}, TimerFire, {}] - embassy executor tracer",
+        Instant::now().as_micros(),
+        core_id::core_id(),
+        timer_id
     );
+
+    #[cfg(not(feature = "timer-trace"))]
+    let _ = timer_id;
+}
+
+/// Wait like `Timer::at(expires_at)`, publishing the set of the timer (`timer-trace` feature,
+/// without it this is just the timer)
+pub async fn traced_timer_at(timer_id: u32, expires_at: Instant) {
+    trace_timer_set(timer_id, expires_at);
+    embassy_time::Timer::at(expires_at).await;
 }
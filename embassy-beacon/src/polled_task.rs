@@ -0,0 +1,75 @@
+//! Task polled on the core, to attribute the events the firmware reports itself (channel
+//! sends and receives with `channel-trace`, timers set with `timer-trace`) to their task.
+//! Embassy's channels and timers do not call into the beacon, so the firmware reports them
+//! with the channel or timer only; the exec begin/end hooks keep track of the polled task. A
+//! task of an interrupt executor can be polled while a task of the preempted one is still
+//! polled, so the polled tasks of a core form a stack.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+/// Cores whose polled task is tracked (reports on other cores are not published)
+const MAX_CORES: usize = 4;
+
+/// Nested polls tracked per core (thread mode plus interrupt executors)
+const MAX_NESTING: usize = 4;
+
+struct PolledTasks {
+    /// Executor and task id of the nested polls per core, innermost last
+    tasks: [[(u32, u32); MAX_NESTING]; MAX_CORES],
+    /// Number of nested polls per core (also the untracked ones beyond `MAX_NESTING`)
+    depth: [usize; MAX_CORES],
+}
+
+static POLLED: Mutex<RefCell<PolledTasks>> = Mutex::new(RefCell::new(PolledTasks {
+    tasks: [[(0, 0); MAX_NESTING]; MAX_CORES],
+    depth: [0; MAX_CORES],
+}));
+
+/// Record the start of a poll (from the exec begin hook)
+pub fn poll_begin(core_id: u32, executor_id: u32, task_id: u32) {
+    let core = core_id as usize;
+    if core >= MAX_CORES {
+        return;
+    }
+
+    critical_section::with(|cs| {
+        let polled = &mut *POLLED.borrow_ref_mut(cs);
+        let depth = polled.depth[core];
+        if depth < MAX_NESTING {
+            polled.tasks[core][depth] = (executor_id, task_id);
+        }
+        polled.depth[core] = depth + 1;
+    });
+}
+
+/// Record the end of the innermost poll (from the exec end hook)
+pub fn poll_end(core_id: u32) {
+    let core = core_id as usize;
+    if core >= MAX_CORES {
+        return;
+    }
+
+    critical_section::with(|cs| {
+        let polled = &mut *POLLED.borrow_ref_mut(cs);
+        polled.depth[core] = polled.depth[core].saturating_sub(1);
+    });
+}
+
+/// Executor and task id of the task polled on the core, None outside of a poll
+pub fn polled_task(core_id: u32) -> Option<(u32, u32)> {
+    let core = core_id as usize;
+    if core >= MAX_CORES {
+        return None;
+    }
+
+    critical_section::with(|cs| {
+        let polled = &*POLLED.borrow_ref(cs);
+        match polled.depth[core] {
+            0 => None,
+            depth if depth > MAX_NESTING => None,
+            depth => Some(polled.tasks[core][depth - 1]),
+        }
+    })
+}
@@ -0,0 +1,37 @@
+//! Builds of the beacon with the defmt level selection (`defmt-*` features)
+//!
+//! Not a trybuild case: trybuild compiles its cases with the features of the running test,
+//! and with two `defmt-*` features the beacon (and so this test) would not compile at all.
+
+use std::process::{Command, Output};
+
+const CONFLICT_MESSAGE: &str = "conflicting defmt level features";
+
+/// `cargo check` of the beacon with only the given features (in a target directory of its
+/// own, the one of the running test is locked)
+fn check_with_features(features: &str) -> Output {
+    Command::new(env!("CARGO"))
+        .args(["check", "--lib", "--quiet", "--no-default-features", "--features"])
+        .arg(features)
+        .arg("--manifest-path")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/target/defmt-levels"))
+        .output()
+        .expect("failed running cargo")
+}
+
+#[test]
+fn single_defmt_level() {
+    let output = check_with_features("defmt-info");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+}
+
+#[test]
+fn conflicting_defmt_levels() {
+    let output = check_with_features("defmt-debug,defmt-info");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains(CONFLICT_MESSAGE), "{}", stderr);
+}
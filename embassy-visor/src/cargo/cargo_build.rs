@@ -5,8 +5,8 @@ use serde::{Deserialize, Serialize};
 pub enum CargoBuildStatus {
     /// Indicates that the build process has completed successfully with the given executable path
     Success(Option<String>),
-    /// Indicates that the build process has failed
-    Failed,
+    /// Indicates that the build process has failed with the given compiler error messages
+    Failed(Vec<String>),
     /// Indicates that the build process was aborted
     Aborted,
 }
@@ -25,12 +25,22 @@ pub enum CargoBuildMessage {
         package_id: String,
         executable: Option<String>,
     },
+    #[serde(rename = "compiler-message")]
+    CompilerMessage { message: CompilerDiagnostic },
     #[serde(rename = "build-finished")]
     BuildFinished { success: bool },
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompilerDiagnostic {
+    level: String,
+    /// Human readable message (including ANSI colors)
+    rendered: Option<String>,
+}
+
 pub fn handle_cargo_build(build_tx: &Receiver<String>) -> CargoBuildStatus {
     let mut found_elf_path: Option<String> = None;
+    let mut error_messages = Vec::new();
     loop {
         match build_tx.recv() {
             Ok(line) => {
@@ -44,11 +54,18 @@ pub fn handle_cargo_build(build_tx: &Receiver<String>) -> CargoBuildStatus {
                                 found_elf_path = Some(exe_path);
                             }
                         }
+                        CargoBuildMessage::CompilerMessage { message } => {
+                            if message.level == "error"
+                                && let Some(rendered) = message.rendered
+                            {
+                                error_messages.push(rendered);
+                            }
+                        }
                         CargoBuildMessage::BuildFinished { success } => {
                             if success {
                                 return CargoBuildStatus::Success(found_elf_path);
                             } else {
-                                return CargoBuildStatus::Failed;
+                                return CargoBuildStatus::Failed(error_messages);
                             }
                         }
                     }
@@ -58,3 +75,37 @@ pub fn handle_cargo_build(build_tx: &Receiver<String>) -> CargoBuildStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_build_collects_errors() {
+        let (build_tx, build_rx) = crossbeam::channel::unbounded();
+        for line in [
+            r#"{"reason":"compiler-message","package_id":"app","message":{"level":"warning","rendered":"warning: unused variable\n"}}"#,
+            r#"{"reason":"compiler-message","package_id":"app","message":{"level":"error","rendered":"error[E0425]: cannot find value `x`\n"}}"#,
+            r#"{"reason":"build-finished","success":false}"#,
+        ] {
+            build_tx.send(line.to_string()).unwrap();
+        }
+
+        match handle_cargo_build(&build_rx) {
+            CargoBuildStatus::Failed(messages) => {
+                assert_eq!(messages, vec!["error[E0425]: cannot find value `x`\n"])
+            }
+            status => panic!("Unexpected build status: {:?}", status),
+        }
+    }
+
+    #[test]
+    fn test_closed_build_output_aborts() {
+        let (build_tx, build_rx) = crossbeam::channel::unbounded::<String>();
+        drop(build_tx);
+        assert!(matches!(
+            handle_cargo_build(&build_rx),
+            CargoBuildStatus::Aborted
+        ));
+    }
+}
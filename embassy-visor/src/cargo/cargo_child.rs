@@ -3,6 +3,8 @@ use std::process::{Command, Stdio};
 use anyhow::Context;
 use crossbeam::channel::Receiver;
 
+use crate::{cargo::process_tree::kill_process_tree, throughput::SOURCE_THROUGHPUT};
+
 pub struct CargoChildProcess {
     child: std::process::Child,
 
@@ -10,8 +12,10 @@ pub struct CargoChildProcess {
 }
 
 impl CargoChildProcess {
+    /// Terminate cargo with the runner and flasher it started (waits until cargo exited, so
+    /// restarting does not leave zombies behind)
     pub fn kill(mut self) -> anyhow::Result<()> {
-        self.child.kill().context("Tried to kill child process")?;
+        kill_process_tree(&mut self.child).context("Tried to kill child process")?;
 
         // Dropping this struct will close the stdout receiver channel and so the reading thread will end
 
@@ -60,7 +64,9 @@ fn read_to_channel_threaded<R: std::io::Read + Send + 'static>(
         let mut buffer = [0; 1024];
         loop {
             match reader.read(&mut buffer) {
+                Ok(0) => break, // End of output (process exited or was killed)
                 Ok(n) => {
+                    SOURCE_THROUGHPUT.add_bytes(n);
                     for &byte in &buffer[..n] {
                         if sender.send(byte).is_err() {
                             // Receiver has been dropped -> stop reading
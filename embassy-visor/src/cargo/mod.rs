@@ -1,2 +1,3 @@
 pub mod cargo_build;
-pub mod cargo_child;
\ No newline at end of file
+pub mod cargo_child;
+pub mod process_tree;
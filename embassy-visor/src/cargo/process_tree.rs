@@ -0,0 +1,222 @@
+//! Termination of `cargo run` together with everything it started. Killing only cargo leaves
+//! its runner (probe-rs, espflash, ...) running and holding the probe, especially on Windows
+//! where descendants are never killed with their parent. The descendants are looked up before
+//! the first signal, so they are still known once cargo is gone.
+
+use std::{
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+
+/// Time the process tree gets to exit after the polite termination request (lets the runner
+/// release the probe) before it is killed
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// Interval cargo is polled at for its exit
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Descendants of the process (children first, grandchildren after them) from the
+/// (pid, parent pid) pairs of all processes
+#[cfg_attr(not(unix), allow(dead_code))]
+pub fn descendants(root_pid: u32, processes: &[(u32, u32)]) -> Vec<u32> {
+    let mut descendants = Vec::new();
+    let mut parents = vec![root_pid];
+    while !parents.is_empty() {
+        let children: Vec<u32> = processes
+            .iter()
+            .filter(|(pid, parent)| {
+                parents.contains(parent) && *pid != root_pid && !descendants.contains(pid)
+            })
+            .map(|(pid, _)| *pid)
+            .collect();
+        descendants.extend(&children);
+        parents = children;
+    }
+    descendants
+}
+
+/// Parse the `ps -A -o pid= -o ppid=` output into (pid, parent pid) pairs
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_process_list(output: &str) -> Vec<(u32, u32)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().map(|field| field.parse().ok());
+            Some((fields.next()??, fields.next()??))
+        })
+        .collect()
+}
+
+/// Send the signal to the processes (errors of processes already gone are ignored)
+#[cfg(unix)]
+fn signal(signal: &str, pids: &[u32]) {
+    if pids.is_empty() {
+        return;
+    }
+    let _ = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .args(pids.iter().map(|pid| pid.to_string()))
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Processes of the list still running (a signal 0 only checks for the process)
+#[cfg(unix)]
+fn still_running(pids: &[u32]) -> Vec<u32> {
+    pids.iter()
+        .copied()
+        .filter(|pid| {
+            Command::new("kill")
+                .args(["-0", &pid.to_string()])
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        })
+        .collect()
+}
+
+/// Wait until the deadline for the processes to exit, returns the ones still running
+#[cfg(unix)]
+fn wait_for_exit(pids: &[u32], deadline: Instant) -> Vec<u32> {
+    let mut running = still_running(pids);
+    while !running.is_empty() && Instant::now() < deadline {
+        std::thread::sleep(EXIT_POLL_INTERVAL);
+        running = still_running(&running);
+    }
+    running
+}
+
+/// Wait up to the timeout for the child to exit, returns whether it did
+fn wait_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<bool> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if child.try_wait()?.is_some() {
+            return Ok(true);
+        }
+        std::thread::sleep(EXIT_POLL_INTERVAL);
+    }
+    Ok(child.try_wait()?.is_some())
+}
+
+/// Terminate the child and all its descendants: asked to terminate first, killed after the
+/// grace period. Returns once the child has exited and was reaped.
+#[cfg(unix)]
+pub fn kill_process_tree(child: &mut Child) -> anyhow::Result<()> {
+    let output = Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "ppid="])
+        .stderr(Stdio::null())
+        .output();
+    // Without a process list at least the child itself is terminated
+    let descendants = match output {
+        Ok(output) => descendants(
+            child.id(),
+            &parse_process_list(&String::from_utf8_lossy(&output.stdout)),
+        ),
+        Err(_) => Vec::new(),
+    };
+
+    let mut tree = descendants.clone();
+    tree.push(child.id());
+    let deadline = Instant::now() + TERMINATE_GRACE_PERIOD;
+    signal("TERM", &tree);
+    if !wait_timeout(child, TERMINATE_GRACE_PERIOD)? {
+        child.kill().context("Failed killing the child process")?;
+    }
+    // Cargo exits at once, the runner gets the rest of the grace period to release the probe.
+    // Descendants ignoring the request (or not done yet) do not outlive it.
+    signal("KILL", &wait_for_exit(&descendants, deadline));
+
+    child
+        .wait()
+        .context("Failed waiting for the child process")?;
+    Ok(())
+}
+
+/// Terminate the child and all its descendants (`taskkill /T`, which walks the tree like
+/// `ps` on Unix). Returns once the child has exited and was reaped.
+#[cfg(windows)]
+pub fn kill_process_tree(child: &mut Child) -> anyhow::Result<()> {
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if !wait_timeout(child, TERMINATE_GRACE_PERIOD)? {
+        child.kill().context("Failed killing the child process")?;
+    }
+
+    child
+        .wait()
+        .context("Failed waiting for the child process")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descendants() {
+        // cargo (10) --> runner (11) --> probe (12, 13); unrelated 20 --> 21
+        let processes = parse_process_list(
+            "    1     0\n   10     1\n   11    10\n   12    11\n   13    11\n   20     1\n   21    20\n garbage\n",
+        );
+        assert_eq!(processes.len(), 7);
+
+        assert_eq!(descendants(10, &processes), vec![11, 12, 13]);
+        assert_eq!(descendants(12, &processes), Vec::<u32>::new());
+        // a loop in the list (pid reused meanwhile) ends
+        assert_eq!(descendants(30, &[(31, 30), (30, 31)]), vec![31]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_kill_process_tree() {
+        use std::io::{BufRead, BufReader};
+
+        // shell (the child) with a sleeping grandchild, like cargo with its runner
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut grandchild = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut grandchild)
+            .unwrap();
+        let grandchild = grandchild.trim();
+
+        kill_process_tree(&mut child).unwrap();
+
+        // gone (or a zombie waiting for its new parent to reap it)
+        if let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", grandchild)) {
+            assert!(stat.contains(") Z "), "grandchild still running: {}", stat);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_descendants_get_grace_period() {
+        let released = std::env::temp_dir().join(format!("visor-released-{}", std::process::id()));
+        let _ = std::fs::remove_file(&released);
+
+        // the grandchild (like a runner releasing the probe) needs a moment after the request,
+        // while the shell (like cargo) exits at once
+        let script = format!(
+            "(trap 'sleep 0.3; touch {}; exit' TERM; while true; do sleep 0.05; done) & wait",
+            released.display()
+        );
+        let mut child = Command::new("sh").args(["-c", &script]).spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        kill_process_tree(&mut child).unwrap();
+
+        assert!(
+            released.exists(),
+            "grandchild killed within the grace period"
+        );
+        let _ = std::fs::remove_file(&released);
+    }
+}
@@ -0,0 +1,1091 @@
+//! Command line arguments of the visor.
+//!
+//! Visor specific options are extracted here, every other argument is passed through
+//! to `cargo run` (e.g. `--release`).
+
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::bail;
+use ratatui::style::Color;
+
+use crate::{
+    csv_log::Rotation,
+    device_source::DeviceSource,
+    net_source::NetSourceUrl,
+    tracing::{retention::RetentionPolicy, trace_data::TraceEventKind},
+    visualizer::time_unit::TimeUnit,
+};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VisorArgs {
+    /// Arguments passed through to `cargo run`
+    pub cargo_args: Vec<String>,
+
+    /// Config file with the option defaults (default: watchtower.toml if it exists)
+    pub config: Option<PathBuf>,
+
+    /// Additionally read trace/log lines from this file (e.g. OpenOCD/probe-rs semihosting output)
+    pub semihosting_file: Option<PathBuf>,
+
+    /// Additionally receive trace/log lines from a remote device (MQTT topic or WebSocket)
+    pub source: Option<NetSourceUrl>,
+
+    /// Further devices traced in the same dashboard, each as NAME=SOURCE (device ids 1, 2, ...)
+    pub devices: Vec<DeviceSource>,
+
+    /// Give up on a file or network source after this many failed attempts in a row (default:
+    /// keep retrying)
+    pub source_max_attempts: Option<u32>,
+
+    /// Read task and executor names from this ELF file instead of the one built by cargo (also
+    /// a linker map or nm output, `--symbols`)
+    pub elf: Option<PathBuf>,
+
+    /// Record every program line (before parsing) into this file
+    pub raw_log: Option<PathBuf>,
+
+    /// Append the statistics to rotating CSV files in this directory instead of running the TUI
+    pub csv_dir: Option<PathBuf>,
+
+    /// Serve the statistics as Prometheus metrics on this address
+    pub prometheus: Option<SocketAddr>,
+
+    /// Write the rendered dashboard (ANSI text) to this file on every refresh
+    pub dashboard_file: Option<PathBuf>,
+
+    /// Time between two writes of the dashboard file in milliseconds (default: 1000)
+    pub dashboard_interval_ms: Option<u64>,
+
+    /// When the CSV logger starts a new file (default: hourly)
+    pub rotate: Option<Rotation>,
+
+    /// Fixed width of the task name column (default: fit to the longest task name)
+    pub name_width: Option<u16>,
+
+    /// Indentation of the tasks below their executor
+    pub executor_indent: Option<u16>,
+
+    /// Start with executors merged by name across cores
+    pub merge_executors: Option<bool>,
+
+    /// Print a summary of the session after the TUI closes
+    pub summary: Option<bool>,
+
+    /// Print the statistics as JSON lines instead of running the TUI
+    pub headless: Option<bool>,
+
+    /// Keep the executors and tasks of previous boots when a firmware reset is detected
+    pub boot_generations: Option<bool>,
+
+    /// Let a task spawned again in its pool slot continue the statistics of the ended instance
+    pub continue_respawned_tasks: Option<bool>,
+
+    /// Let the update thread publish snapshots of the statistics, read without locking the model
+    pub stats_snapshots: Option<bool>,
+
+    /// Ignore the trace of a beacon with an unsupported trace schema version (instead of warning)
+    pub refuse_schema_mismatch: Option<bool>,
+
+    /// Do not show the CPU utilization in the core titles
+    pub hide_core_utilization: Option<bool>,
+
+    /// Start with the task CPU utilization relative to the time the task was active
+    pub active_only_cpu: Option<bool>,
+
+    /// Hide the tasks below this CPU utilization in percent (unless selected, pinned or
+    /// flagged)
+    pub min_task_cpu: Option<f32>,
+
+    /// Border color of cores that are not busy
+    pub core_idle_color: Option<Color>,
+
+    /// Preemptions shorter than this many microseconds are ignored
+    pub preemption_threshold_us: Option<u64>,
+
+    /// Tasks ready for this many milliseconds while their executor idles are lost wakeups
+    pub lost_wakeup_threshold_ms: Option<u64>,
+
+    /// Executors busy for this many milliseconds without idling are pegged
+    pub pegged_threshold_ms: Option<u64>,
+
+    /// Alternating polls of two tasks needed for a suspected ping-pong
+    pub ping_pong_min_switches: Option<u64>,
+
+    /// Longest average switch interval of a suspected ping-pong in microseconds
+    pub ping_pong_max_interval_us: Option<u64>,
+
+    /// Gap between the busiest and the idlest core flagged as imbalanced (percentage points)
+    pub core_imbalance_threshold_percent: Option<u64>,
+
+    /// Policy the state history is pruned with
+    pub history_retention: Option<RetentionPolicy>,
+
+    /// Maximum age of the retained history in seconds
+    pub history_max_age_s: Option<u64>,
+
+    /// Maximum number of retained history entries per task/executor
+    pub history_max_entries: Option<usize>,
+
+    /// Weight of a new CPU usage value in the displayed (smoothed) value
+    pub cpu_smoothing: Option<f32>,
+
+    /// Trailing window in ms the CPU utilization is averaged over (default: the whole history)
+    pub cpu_window_ms: Option<u64>,
+
+    /// Share of failing lines (in percent) above which a parse error warning is shown
+    pub parse_error_threshold: Option<f32>,
+
+    /// Most recent slice of the history shown by the time based views in seconds
+    pub view_window_s: Option<u64>,
+
+    /// State spans shorter than this many microseconds are merged into their neighbours in the
+    /// time based views (display only)
+    pub min_visible_span_us: Option<u64>,
+
+    /// Unit of the displayed durations
+    pub time_unit: Option<TimeUnit>,
+
+    /// Human names of the cores as (core id, name)
+    pub core_names: Vec<(u32, String)>,
+
+    /// Start marker of the trace records (default: the beacon's)
+    pub trace_prefix: Option<String>,
+
+    /// End marker of the trace records (default: the beacon's)
+    pub trace_suffix: Option<String>,
+
+    /// Event names of another tracer as (event, name in the trace line)
+    pub event_names: Vec<(TraceEventKind, String)>,
+
+    /// Task categories as (category name, task name pattern)
+    pub categories: Vec<(String, String)>,
+
+    /// Run the throughput self-test with this many trace events per second (no firmware run)
+    pub self_test_rate: Option<u64>,
+
+    /// Duration of the throughput self-test in seconds
+    pub self_test_duration_s: Option<u64>,
+
+    /// Step through this recording (written with `--raw-log`) instead of running the firmware
+    pub replay: Option<PathBuf>,
+}
+
+impl VisorArgs {
+    /// Parse the arguments (without the program name)
+    pub fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let mut parsed = VisorArgs::default();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            // Support both "--flag value" and "--flag=value"
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+
+            // "--flag" sets a switch, "--no-flag" clears it (e.g. one set in the config file)
+            if let Some(name) = flag.strip_prefix("--") {
+                let (name, value) = match name.strip_prefix("no-") {
+                    Some(name) => (name, false),
+                    None => (name, true),
+                };
+                if let Some(switch) = parsed.switch_mut(name) {
+                    *switch = Some(value);
+                    continue;
+                }
+            }
+
+            match flag {
+                "--config" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.config = Some(PathBuf::from(value));
+                }
+                "--semihosting-file" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.semihosting_file = Some(PathBuf::from(value));
+                }
+                "--source" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.source = Some(NetSourceUrl::parse(&value)?);
+                }
+                "--device" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.devices.push(DeviceSource::parse(&value)?);
+                }
+                "--source-max-attempts" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let attempts: u32 = parse_number(flag, &value)?;
+                    if attempts == 0 {
+                        bail!("{} must be greater than 0", flag);
+                    }
+                    parsed.source_max_attempts = Some(attempts);
+                }
+                "--elf" | "--symbols" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.elf = Some(PathBuf::from(value));
+                }
+                "--raw-log" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.raw_log = Some(PathBuf::from(value));
+                }
+                "--csv-dir" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.csv_dir = Some(PathBuf::from(value));
+                }
+                "--prometheus" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let Ok(addr) = value.parse() else {
+                        bail!(
+                            "{} expects an address with port (e.g. 127.0.0.1:9100), got '{}'",
+                            flag,
+                            value
+                        );
+                    };
+                    parsed.prometheus = Some(addr);
+                }
+                "--dashboard-file" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.dashboard_file = Some(PathBuf::from(value));
+                }
+                "--dashboard-interval-ms" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let interval_ms: u64 = parse_number(flag, &value)?;
+                    if interval_ms == 0 {
+                        bail!("{} must be greater than 0", flag);
+                    }
+                    parsed.dashboard_interval_ms = Some(interval_ms);
+                }
+                "--rotate" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let Some(rotation) = Rotation::from_arg(&value) else {
+                        bail!(
+                            "{} expects an interval (e.g. 30m, 2h) or a size (e.g. 100MB), got '{}'",
+                            flag,
+                            value
+                        );
+                    };
+                    parsed.rotate = Some(rotation);
+                }
+                "--name-width" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.name_width = Some(parse_number(flag, &value)?);
+                }
+                "--executor-indent" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.executor_indent = Some(parse_number(flag, &value)?);
+                }
+                "--min-task-cpu" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let percent: f32 = parse_number(flag, &value)?;
+                    if !(0.0..=100.0).contains(&percent) {
+                        bail!("{} must be in [0, 100], got {}", flag, value);
+                    }
+                    parsed.min_task_cpu = Some(percent);
+                }
+                "--core-idle-color" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let Ok(color) = value.parse::<Color>() else {
+                        bail!(
+                            "{} expects a color name (e.g. dark_gray) or #RRGGBB, got '{}'",
+                            flag,
+                            value
+                        );
+                    };
+                    parsed.core_idle_color = Some(color);
+                }
+                "--core-name" => {
+                    // One "ID=NAME" mapping per flag (e.g. "--core-name 0=APP --core-name 1=NET")
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.core_names.push(parse_core_name(&value)?);
+                }
+                "--preemption-threshold-us" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.preemption_threshold_us = Some(parse_number(flag, &value)?);
+                }
+                "--lost-wakeup-threshold-ms" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.lost_wakeup_threshold_ms = Some(parse_number(flag, &value)?);
+                }
+                "--pegged-threshold-ms" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.pegged_threshold_ms = Some(parse_number(flag, &value)?);
+                }
+                "--ping-pong-min-switches" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.ping_pong_min_switches = Some(parse_number(flag, &value)?);
+                }
+                "--ping-pong-max-interval-us" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.ping_pong_max_interval_us = Some(parse_number(flag, &value)?);
+                }
+                "--core-imbalance-threshold-percent" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let percent: u64 = parse_number(flag, &value)?;
+                    if !(1..=100).contains(&percent) {
+                        bail!("{} must be in 1..=100, got {}", flag, value);
+                    }
+                    parsed.core_imbalance_threshold_percent = Some(percent);
+                }
+                "--history-retention" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let Some(policy) = RetentionPolicy::from_arg(&value) else {
+                        bail!(
+                            "{} expects one of age, count, larger, got '{}'",
+                            flag,
+                            value
+                        );
+                    };
+                    parsed.history_retention = Some(policy);
+                }
+                "--history-max-age-s" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.history_max_age_s = Some(parse_number(flag, &value)?);
+                }
+                "--history-max-entries" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.history_max_entries = Some(parse_number(flag, &value)?);
+                }
+                "--cpu-smoothing" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let alpha: f32 = parse_number(flag, &value)?;
+                    if !(alpha > 0.0 && alpha <= 1.0) {
+                        bail!("{} must be in (0, 1], got {}", flag, value);
+                    }
+                    parsed.cpu_smoothing = Some(alpha);
+                }
+                "--cpu-window-ms" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let window_ms: u64 = parse_number(flag, &value)?;
+                    if window_ms == 0 {
+                        bail!("{} must be greater than 0", flag);
+                    }
+                    parsed.cpu_window_ms = Some(window_ms);
+                }
+                "--parse-error-threshold" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let percent: f32 = parse_number(flag, &value)?;
+                    if !(0.0..=100.0).contains(&percent) {
+                        bail!("{} must be in [0, 100], got {}", flag, value);
+                    }
+                    parsed.parse_error_threshold = Some(percent);
+                }
+                "--view-window-s" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let window_s: u64 = parse_number(flag, &value)?;
+                    if window_s == 0 {
+                        bail!("{} expects a window above 0 seconds", flag);
+                    }
+                    parsed.view_window_s = Some(window_s);
+                }
+                "--min-visible-span-us" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.min_visible_span_us = Some(parse_number(flag, &value)?);
+                }
+                "--time-unit" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let Some(unit) = TimeUnit::from_arg(&value) else {
+                        bail!("{} expects one of auto, us, ms, s, got '{}'", flag, value);
+                    };
+                    parsed.time_unit = Some(unit);
+                }
+                "--trace-prefix" => {
+                    parsed.trace_prefix = Some(parse_marker(
+                        flag,
+                        take_value(flag, inline_value, &mut iter)?,
+                    )?);
+                }
+                "--trace-suffix" => {
+                    parsed.trace_suffix = Some(parse_marker(
+                        flag,
+                        take_value(flag, inline_value, &mut iter)?,
+                    )?);
+                }
+                "--event-name" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.event_names.push(parse_event_name(&value)?);
+                }
+                "--category" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.categories.push(parse_category(&value)?);
+                }
+                "--self-test" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    let rate: u64 = parse_number(flag, &value)?;
+                    if rate == 0 {
+                        bail!("{} expects a rate above 0 events per second", flag);
+                    }
+                    parsed.self_test_rate = Some(rate);
+                }
+                "--self-test-duration-s" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.self_test_duration_s = Some(parse_number(flag, &value)?);
+                }
+                "--replay" => {
+                    let value = take_value(flag, inline_value, &mut iter)?;
+                    parsed.replay = Some(PathBuf::from(value));
+                }
+                _ => parsed.cargo_args.push(arg.clone()),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// On/off option of the given name (without the leading "--"), `None` until it is given
+    fn switch_mut(&mut self, name: &str) -> Option<&mut Option<bool>> {
+        let switch = match name {
+            "merge-executors" => &mut self.merge_executors,
+            "summary" => &mut self.summary,
+            "headless" => &mut self.headless,
+            "boot-generations" => &mut self.boot_generations,
+            "continue-respawned-tasks" => &mut self.continue_respawned_tasks,
+            "stats-snapshots" => &mut self.stats_snapshots,
+            "refuse-schema-mismatch" => &mut self.refuse_schema_mismatch,
+            "hide-core-utilization" => &mut self.hide_core_utilization,
+            "active-only-cpu" => &mut self.active_only_cpu,
+            _ => return None,
+        };
+        Some(switch)
+    }
+
+    /// Use the options of the config file for the ones not given on the command line.
+    /// The config file can not start the self-test or a replay.
+    pub fn with_defaults(self, file: VisorArgs) -> Self {
+        // Destructured so a new option can not be forgotten here
+        let VisorArgs {
+            cargo_args: _,
+            config: _,
+            self_test_rate: _,
+            self_test_duration_s: _,
+            replay: _,
+            semihosting_file,
+            source,
+            devices,
+            source_max_attempts,
+            elf,
+            raw_log,
+            csv_dir,
+            prometheus,
+            dashboard_file,
+            dashboard_interval_ms,
+            rotate,
+            name_width,
+            executor_indent,
+            merge_executors,
+            summary,
+            headless,
+            boot_generations,
+            continue_respawned_tasks,
+            stats_snapshots,
+            refuse_schema_mismatch,
+            hide_core_utilization,
+            active_only_cpu,
+            min_task_cpu,
+            core_idle_color,
+            preemption_threshold_us,
+            lost_wakeup_threshold_ms,
+            pegged_threshold_ms,
+            ping_pong_min_switches,
+            ping_pong_max_interval_us,
+            core_imbalance_threshold_percent,
+            history_retention,
+            history_max_age_s,
+            history_max_entries,
+            cpu_smoothing,
+            cpu_window_ms,
+            parse_error_threshold,
+            view_window_s,
+            min_visible_span_us,
+            time_unit,
+            core_names,
+            trace_prefix,
+            trace_suffix,
+            event_names,
+            categories,
+        } = file;
+
+        Self {
+            semihosting_file: self.semihosting_file.or(semihosting_file),
+            source: self.source.or(source),
+            devices: non_empty_or(self.devices, devices),
+            source_max_attempts: self.source_max_attempts.or(source_max_attempts),
+            elf: self.elf.or(elf),
+            raw_log: self.raw_log.or(raw_log),
+            csv_dir: self.csv_dir.or(csv_dir),
+            prometheus: self.prometheus.or(prometheus),
+            dashboard_file: self.dashboard_file.or(dashboard_file),
+            dashboard_interval_ms: self.dashboard_interval_ms.or(dashboard_interval_ms),
+            rotate: self.rotate.or(rotate),
+            name_width: self.name_width.or(name_width),
+            executor_indent: self.executor_indent.or(executor_indent),
+            merge_executors: self.merge_executors.or(merge_executors),
+            summary: self.summary.or(summary),
+            headless: self.headless.or(headless),
+            boot_generations: self.boot_generations.or(boot_generations),
+            continue_respawned_tasks: self.continue_respawned_tasks.or(continue_respawned_tasks),
+            stats_snapshots: self.stats_snapshots.or(stats_snapshots),
+            refuse_schema_mismatch: self.refuse_schema_mismatch.or(refuse_schema_mismatch),
+            hide_core_utilization: self.hide_core_utilization.or(hide_core_utilization),
+            active_only_cpu: self.active_only_cpu.or(active_only_cpu),
+            min_task_cpu: self.min_task_cpu.or(min_task_cpu),
+            core_idle_color: self.core_idle_color.or(core_idle_color),
+            preemption_threshold_us: self.preemption_threshold_us.or(preemption_threshold_us),
+            lost_wakeup_threshold_ms: self.lost_wakeup_threshold_ms.or(lost_wakeup_threshold_ms),
+            pegged_threshold_ms: self.pegged_threshold_ms.or(pegged_threshold_ms),
+            ping_pong_min_switches: self.ping_pong_min_switches.or(ping_pong_min_switches),
+            ping_pong_max_interval_us: self.ping_pong_max_interval_us.or(ping_pong_max_interval_us),
+            core_imbalance_threshold_percent: self
+                .core_imbalance_threshold_percent
+                .or(core_imbalance_threshold_percent),
+            history_retention: self.history_retention.or(history_retention),
+            history_max_age_s: self.history_max_age_s.or(history_max_age_s),
+            history_max_entries: self.history_max_entries.or(history_max_entries),
+            cpu_smoothing: self.cpu_smoothing.or(cpu_smoothing),
+            cpu_window_ms: self.cpu_window_ms.or(cpu_window_ms),
+            parse_error_threshold: self.parse_error_threshold.or(parse_error_threshold),
+            view_window_s: self.view_window_s.or(view_window_s),
+            min_visible_span_us: self.min_visible_span_us.or(min_visible_span_us),
+            time_unit: self.time_unit.or(time_unit),
+            core_names: non_empty_or(self.core_names, core_names),
+            trace_prefix: self.trace_prefix.or(trace_prefix),
+            trace_suffix: self.trace_suffix.or(trace_suffix),
+            event_names: non_empty_or(self.event_names, event_names),
+            categories: non_empty_or(self.categories, categories),
+            ..self
+        }
+    }
+}
+
+/// Get the value of a flag either from "--flag=value" or the following argument
+fn take_value<'a>(
+    flag: &str,
+    inline_value: Option<String>,
+    iter: &mut impl Iterator<Item = &'a String>,
+) -> anyhow::Result<String> {
+    match inline_value {
+        Some(value) => Ok(value),
+        None => match iter.next() {
+            Some(value) => Ok(value.clone()),
+            None => bail!("Missing value for argument '{}'", flag),
+        },
+    }
+}
+
+/// Parse a core name mapping "ID=NAME"
+fn parse_core_name(value: &str) -> anyhow::Result<(u32, String)> {
+    match value.split_once('=') {
+        Some((core_id, name)) if !name.is_empty() => {
+            Ok((parse_number("--core-name", core_id)?, name.to_string()))
+        }
+        _ => bail!("Invalid core name '{}' (expected ID=NAME)", value),
+    }
+}
+
+/// Parse a task category pattern "NAME=PATTERN"
+fn parse_category(value: &str) -> anyhow::Result<(String, String)> {
+    match value.split_once('=') {
+        Some((name, pattern))
+            if !name.is_empty() && !name.starts_with('-') && !pattern.is_empty() =>
+        {
+            Ok((name.to_string(), pattern.to_string()))
+        }
+        _ => bail!("Invalid task category '{}' (expected NAME=PATTERN)", value),
+    }
+}
+
+/// Parse an event name mapping "EVENT=NAME" (EVENT as published by the beacon)
+fn parse_event_name(value: &str) -> anyhow::Result<(TraceEventKind, String)> {
+    match value.split_once('=') {
+        Some((event, name)) if !name.is_empty() && !name.contains([',', '[', ']']) => {
+            match TraceEventKind::from_default_name(event) {
+                Some(kind) => Ok((kind, name.to_string())),
+                None => bail!("Unknown event '{}' in '{}'", event, value),
+            }
+        }
+        _ => bail!("Invalid event name '{}' (expected EVENT=NAME)", value),
+    }
+}
+
+/// Trace line markers must not be empty (every line would be a trace line)
+fn parse_marker(flag: &str, value: String) -> anyhow::Result<String> {
+    if value.is_empty() {
+        bail!("Empty value for argument '{}'", flag);
+    }
+    Ok(value)
+}
+
+/// Values from the command line, or from the config file when none were given
+fn non_empty_or<T>(cli: Vec<T>, file: Vec<T>) -> Vec<T> {
+    if cli.is_empty() { file } else { cli }
+}
+
+/// Parse the numeric value of a flag
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> anyhow::Result<T> {
+    match value.parse() {
+        Ok(number) => Ok(number),
+        Err(_) => bail!("Invalid number '{}' for argument '{}'", value, flag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cargo_args_passthrough() {
+        let parsed = VisorArgs::parse(&to_args(&["--release", "--bin", "app"])).unwrap();
+        assert_eq!(parsed.cargo_args, to_args(&["--release", "--bin", "app"]));
+        assert_eq!(parsed.semihosting_file, None);
+    }
+
+    #[test]
+    fn test_semihosting_file() {
+        let parsed =
+            VisorArgs::parse(&to_args(&["--release", "--semihosting-file", "out.txt"])).unwrap();
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+        assert_eq!(parsed.semihosting_file, Some(PathBuf::from("out.txt")));
+
+        let parsed = VisorArgs::parse(&to_args(&["--semihosting-file=out.txt"])).unwrap();
+        assert_eq!(parsed.semihosting_file, Some(PathBuf::from("out.txt")));
+
+        assert!(VisorArgs::parse(&to_args(&["--semihosting-file"])).is_err());
+    }
+
+    #[test]
+    fn test_config_file_precedence() {
+        let file = crate::config_file::ConfigFile::parse(
+            r#"
+            history-max-age-s = 60
+            name-width = 20
+            time-unit = "ms"
+
+            [core-names]
+            0 = "APP"
+            "#,
+        )
+        .unwrap()
+        .to_visor_args()
+        .unwrap();
+        let cli = VisorArgs::parse(&to_args(&[
+            "--history-max-age-s",
+            "5",
+            "--core-name",
+            "0=PRO",
+            "--release",
+        ]))
+        .unwrap();
+
+        let merged = cli.with_defaults(file);
+        // command line over config file
+        assert_eq!(merged.history_max_age_s, Some(5));
+        assert_eq!(merged.core_names, vec![(0, String::from("PRO"))]);
+        // config file over built-in default
+        assert_eq!(merged.name_width, Some(20));
+        assert_eq!(merged.time_unit, Some(TimeUnit::Millis));
+        // neither --> built-in default
+        assert_eq!(merged.executor_indent, None);
+        assert_eq!(merged.cargo_args, to_args(&["--release"]));
+    }
+
+    #[test]
+    fn test_switch_override() {
+        let file = crate::config_file::ConfigFile::parse(
+            r#"
+            headless = true
+            summary = true
+            "#,
+        )
+        .unwrap()
+        .to_visor_args()
+        .unwrap();
+        let cli = VisorArgs::parse(&to_args(&["--no-headless", "--merge-executors"])).unwrap();
+        assert_eq!(cli.headless, Some(false));
+        assert_eq!(cli.summary, None);
+        assert!(cli.cargo_args.is_empty());
+
+        let merged = cli.with_defaults(file);
+        // turned off on the command line, on in the config file
+        assert_eq!(merged.headless, Some(false));
+        assert_eq!(merged.summary, Some(true));
+        assert_eq!(merged.merge_executors, Some(true));
+        assert_eq!(merged.boot_generations, None);
+
+        // an unknown "--no-" flag is passed on to cargo
+        let parsed = VisorArgs::parse(&to_args(&["--no-default-features"])).unwrap();
+        assert_eq!(parsed.cargo_args, to_args(&["--no-default-features"]));
+    }
+
+    #[test]
+    fn test_categories() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--category",
+            "sensors=imu_*",
+            "--category",
+            "comms=*wifi*",
+            "--release",
+            "--category=sensors=baro_task",
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed.categories,
+            vec![
+                (String::from("sensors"), String::from("imu_*")),
+                (String::from("comms"), String::from("*wifi*")),
+                (String::from("sensors"), String::from("baro_task")),
+            ]
+        );
+        assert_eq!(parsed.cargo_args, vec!["--release"]);
+        assert!(VisorArgs::parse(&to_args(&["--category", "sensors"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--category", "=imu_*"])).is_err());
+    }
+
+    #[test]
+    fn test_self_test() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--self-test",
+            "100000",
+            "--self-test-duration-s=10",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.self_test_rate, Some(100_000));
+        assert_eq!(parsed.self_test_duration_s, Some(10));
+        assert!(VisorArgs::parse(&to_args(&["--self-test", "0"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--self-test", "fast"])).is_err());
+    }
+
+    #[test]
+    fn test_source() {
+        let parsed =
+            VisorArgs::parse(&to_args(&["--source", "mqtt://broker/fleet/dev42/trace"])).unwrap();
+        let source = parsed.source.unwrap();
+        assert_eq!(source.host, "broker");
+        assert_eq!(source.path, "fleet/dev42/trace");
+
+        assert!(VisorArgs::parse(&to_args(&["--source=serial:///dev/ttyUSB0"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--source"])).is_err());
+    }
+
+    #[test]
+    fn test_elf_override() {
+        let parsed = VisorArgs::parse(&to_args(&["--elf=target/app.debug", "--release"])).unwrap();
+        assert_eq!(parsed.elf, Some(PathBuf::from("target/app.debug")));
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+
+        let parsed = VisorArgs::parse(&to_args(&["--symbols", "firmware.map"])).unwrap();
+        assert_eq!(parsed.elf, Some(PathBuf::from("firmware.map")));
+    }
+
+    #[test]
+    fn test_raw_log() {
+        let parsed = VisorArgs::parse(&to_args(&["--raw-log", "raw.txt", "--release"])).unwrap();
+        assert_eq!(parsed.raw_log, Some(PathBuf::from("raw.txt")));
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+    }
+
+    #[test]
+    fn test_replay() {
+        let parsed = VisorArgs::parse(&to_args(&["--replay=raw.txt", "--elf", "app"])).unwrap();
+        assert_eq!(parsed.replay, Some(PathBuf::from("raw.txt")));
+        assert_eq!(parsed.elf, Some(PathBuf::from("app")));
+        assert!(VisorArgs::parse(&to_args(&["--replay"])).is_err());
+
+        // a replay in the config file would never run the firmware
+        let file = VisorArgs::parse(&to_args(&["--replay", "raw.txt"])).unwrap();
+        assert_eq!(VisorArgs::default().with_defaults(file).replay, None);
+    }
+
+    #[test]
+    fn test_csv_logging() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--csv-dir",
+            "logs",
+            "--rotate=30m",
+            "--release",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.csv_dir, Some(PathBuf::from("logs")));
+        assert_eq!(
+            parsed.rotate,
+            Some(Rotation::Interval(std::time::Duration::from_secs(1800)))
+        );
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+
+        assert!(VisorArgs::parse(&to_args(&["--rotate", "weekly"])).is_err());
+    }
+
+    #[test]
+    fn test_prometheus() {
+        let parsed = VisorArgs::parse(&to_args(&["--prometheus=0.0.0.0:9100"])).unwrap();
+        assert_eq!(
+            parsed.prometheus,
+            Some(SocketAddr::from(([0, 0, 0, 0], 9100)))
+        );
+        assert!(parsed.cargo_args.is_empty());
+
+        // the port is required
+        assert!(VisorArgs::parse(&to_args(&["--prometheus", "localhost"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--prometheus"])).is_err());
+    }
+
+    #[test]
+    fn test_source_max_attempts() {
+        let parsed = VisorArgs::parse(&to_args(&["--source-max-attempts", "20"])).unwrap();
+        assert_eq!(parsed.source_max_attempts, Some(20));
+        assert!(parsed.cargo_args.is_empty());
+
+        assert!(VisorArgs::parse(&to_args(&["--source-max-attempts=0"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--source-max-attempts=x"])).is_err());
+    }
+
+    #[test]
+    fn test_dashboard_file() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--dashboard-file",
+            "dashboard.ans",
+            "--dashboard-interval-ms=250",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.dashboard_file, Some(PathBuf::from("dashboard.ans")));
+        assert_eq!(parsed.dashboard_interval_ms, Some(250));
+        assert!(parsed.cargo_args.is_empty());
+
+        assert!(VisorArgs::parse(&to_args(&["--dashboard-interval-ms=0"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--dashboard-file"])).is_err());
+    }
+
+    #[test]
+    fn test_layout_args() {
+        let parsed =
+            VisorArgs::parse(&to_args(&["--name-width", "30", "--executor-indent=2"])).unwrap();
+        assert_eq!(parsed.name_width, Some(30));
+        assert_eq!(parsed.executor_indent, Some(2));
+        assert!(parsed.cargo_args.is_empty());
+        assert_eq!(parsed.merge_executors, None);
+
+        let parsed =
+            VisorArgs::parse(&to_args(&["--merge-executors", "--release", "--summary"])).unwrap();
+        assert_eq!(parsed.merge_executors, Some(true));
+        assert_eq!(parsed.summary, Some(true));
+        assert_eq!(parsed.headless, None);
+        assert_eq!(parsed.boot_generations, None);
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+
+        let parsed = VisorArgs::parse(&to_args(&["--boot-generations"])).unwrap();
+        assert_eq!(parsed.boot_generations, Some(true));
+        assert_eq!(parsed.continue_respawned_tasks, None);
+        assert_eq!(parsed.refuse_schema_mismatch, None);
+        assert!(parsed.cargo_args.is_empty());
+
+        let parsed = VisorArgs::parse(&to_args(&["--continue-respawned-tasks"])).unwrap();
+        assert_eq!(parsed.continue_respawned_tasks, Some(true));
+        assert_eq!(parsed.stats_snapshots, None);
+
+        let parsed = VisorArgs::parse(&to_args(&["--stats-snapshots"])).unwrap();
+        assert_eq!(parsed.stats_snapshots, Some(true));
+
+        let parsed = VisorArgs::parse(&to_args(&["--refuse-schema-mismatch"])).unwrap();
+        assert_eq!(parsed.refuse_schema_mismatch, Some(true));
+        assert!(parsed.cargo_args.is_empty());
+
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--hide-core-utilization",
+            "--core-idle-color",
+            "dark_gray",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.hide_core_utilization, Some(true));
+        assert_eq!(parsed.active_only_cpu, None);
+        assert_eq!(
+            VisorArgs::parse(&to_args(&["--active-only-cpu"]))
+                .unwrap()
+                .active_only_cpu,
+            Some(true)
+        );
+        assert_eq!(parsed.core_idle_color, Some(Color::DarkGray));
+        assert!(VisorArgs::parse(&to_args(&["--core-idle-color=#00ff00"])).is_ok());
+        assert!(VisorArgs::parse(&to_args(&["--core-idle-color", "greenish"])).is_err());
+
+        assert!(VisorArgs::parse(&to_args(&["--name-width", "wide"])).is_err());
+    }
+
+    #[test]
+    fn test_devices() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--device",
+            "board-b=mqtt://broker/fleet/b/trace",
+            "--device=net-core=probe2.log",
+        ]))
+        .unwrap();
+        let names: Vec<&str> = parsed.devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["board-b", "net-core"]);
+        assert!(parsed.cargo_args.is_empty());
+
+        assert!(VisorArgs::parse(&to_args(&["--device", "board-b"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--device"])).is_err());
+    }
+
+    #[test]
+    fn test_core_names() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--core-name",
+            "0=APP",
+            "--core-name",
+            "1=NET",
+            "--release",
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed.core_names,
+            vec![(0, String::from("APP")), (1, String::from("NET"))]
+        );
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+
+        // a cargo argument looking like a mapping is left to cargo
+        let parsed =
+            VisorArgs::parse(&to_args(&["--core-name", "0=APP", "1=x", "--release"])).unwrap();
+        assert_eq!(parsed.core_names, vec![(0, String::from("APP"))]);
+        assert_eq!(parsed.cargo_args, to_args(&["1=x", "--release"]));
+
+        let parsed = VisorArgs::parse(&to_args(&["--core-name=1=CM4"])).unwrap();
+        assert_eq!(parsed.core_names, vec![(1, String::from("CM4"))]);
+
+        assert!(VisorArgs::parse(&to_args(&["--core-name", "APP"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--core-name", "x=APP"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--core-name", "0="])).is_err());
+    }
+
+    #[test]
+    fn test_preemption_threshold() {
+        let parsed = VisorArgs::parse(&to_args(&["--preemption-threshold-us=50"])).unwrap();
+        assert_eq!(parsed.preemption_threshold_us, Some(50));
+        assert!(parsed.cargo_args.is_empty());
+
+        assert!(VisorArgs::parse(&to_args(&["--preemption-threshold-us", "-1"])).is_err());
+    }
+
+    #[test]
+    fn test_detection_thresholds() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--lost-wakeup-threshold-ms=250",
+            "--pegged-threshold-ms",
+            "5000",
+            "--ping-pong-min-switches=20",
+            "--ping-pong-max-interval-us=300",
+            "--core-imbalance-threshold-percent",
+            "40",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.lost_wakeup_threshold_ms, Some(250));
+        assert_eq!(parsed.pegged_threshold_ms, Some(5000));
+        assert_eq!(parsed.ping_pong_min_switches, Some(20));
+        assert_eq!(parsed.ping_pong_max_interval_us, Some(300));
+        assert_eq!(parsed.core_imbalance_threshold_percent, Some(40));
+        assert!(VisorArgs::parse(&to_args(&["--pegged-threshold-ms=1s"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--core-imbalance-threshold-percent=0"])).is_err());
+    }
+
+    #[test]
+    fn test_min_visible_span() {
+        let parsed = VisorArgs::parse(&to_args(&["--min-visible-span-us", "500"])).unwrap();
+        assert_eq!(parsed.min_visible_span_us, Some(500));
+        assert!(VisorArgs::parse(&to_args(&["--min-visible-span-us=short"])).is_err());
+    }
+
+    #[test]
+    fn test_min_task_cpu() {
+        let parsed = VisorArgs::parse(&to_args(&["--min-task-cpu", "0.5"])).unwrap();
+        assert_eq!(parsed.min_task_cpu, Some(0.5));
+        assert!(VisorArgs::parse(&to_args(&["--min-task-cpu=150"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--min-task-cpu=little"])).is_err());
+    }
+
+    #[test]
+    fn test_history_retention() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--history-retention",
+            "larger",
+            "--history-max-age-s=10",
+            "--history-max-entries",
+            "5000",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.history_retention, Some(RetentionPolicy::Larger));
+        assert_eq!(parsed.history_max_age_s, Some(10));
+        assert_eq!(parsed.history_max_entries, Some(5000));
+        assert!(parsed.cargo_args.is_empty());
+
+        assert!(VisorArgs::parse(&to_args(&["--history-retention=forever"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--history-max-entries=-1"])).is_err());
+    }
+
+    #[test]
+    fn test_time_unit() {
+        let parsed = VisorArgs::parse(&to_args(&["--time-unit", "ms", "--release"])).unwrap();
+        assert_eq!(parsed.time_unit, Some(TimeUnit::Millis));
+        assert_eq!(parsed.cargo_args, vec!["--release"]);
+        let parsed = VisorArgs::parse(&to_args(&["--time-unit=auto"])).unwrap();
+        assert_eq!(parsed.time_unit, Some(TimeUnit::Auto));
+        assert!(VisorArgs::parse(&to_args(&["--time-unit=min"])).is_err());
+    }
+
+    #[test]
+    fn test_cpu_smoothing() {
+        let parsed = VisorArgs::parse(&to_args(&["--cpu-smoothing", "0.3", "--release"])).unwrap();
+        assert_eq!(parsed.cpu_smoothing, Some(0.3));
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+
+        assert!(VisorArgs::parse(&to_args(&["--cpu-smoothing=0"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--cpu-smoothing=1.5"])).is_err());
+    }
+
+    #[test]
+    fn test_cpu_window() {
+        let parsed = VisorArgs::parse(&to_args(&["--cpu-window-ms", "1000"])).unwrap();
+        assert_eq!(parsed.cpu_window_ms, Some(1000));
+        assert!(parsed.cargo_args.is_empty());
+
+        assert!(VisorArgs::parse(&to_args(&["--cpu-window-ms=0"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--cpu-window-ms", "1s"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_error_threshold() {
+        let parsed = VisorArgs::parse(&to_args(&["--parse-error-threshold=2.5"])).unwrap();
+        assert_eq!(parsed.parse_error_threshold, Some(2.5));
+
+        assert!(VisorArgs::parse(&to_args(&["--parse-error-threshold=101"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--parse-error-threshold=-1"])).is_err());
+    }
+
+    #[test]
+    fn test_trace_protocol() {
+        let parsed = VisorArgs::parse(&to_args(&[
+            "--trace-prefix=<<rtic ",
+            "--trace-suffix",
+            " >>",
+            "--event-name",
+            "TaskExecBegin=enter",
+            "--event-name=TaskExecEnd=exit",
+            "--release",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.trace_prefix.as_deref(), Some("<<rtic "));
+        assert_eq!(parsed.trace_suffix.as_deref(), Some(" >>"));
+        assert_eq!(
+            parsed.event_names,
+            vec![
+                (TraceEventKind::TaskExecBegin, String::from("enter")),
+                (TraceEventKind::TaskExecEnd, String::from("exit")),
+            ]
+        );
+        assert_eq!(parsed.cargo_args, to_args(&["--release"]));
+
+        assert!(VisorArgs::parse(&to_args(&["--trace-prefix="])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--event-name", "TaskFoo=x"])).is_err());
+        assert!(VisorArgs::parse(&to_args(&["--event-name", "TaskNew=a,b"])).is_err());
+    }
+}
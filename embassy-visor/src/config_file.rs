@@ -0,0 +1,430 @@
+//! Per-project defaults of the command line options (`watchtower.toml`).
+//!
+//! Every key is named like its command line flag without the leading dashes. The values are
+//! checked by the command line parser, and options given on the command line take precedence.
+//!
+//! ```toml
+//! history-max-age-s = 60
+//! time-unit = "ms"
+//! core-idle-color = "dark_gray"
+//!
+//! [core-names]
+//! 0 = "APP"
+//! 1 = "NET"
+//!
+//! [[category]]
+//! name = "sensors"
+//! patterns = ["imu_*", "baro_*"]
+//!
+//! [[device]]
+//! name = "board-b"
+//! source = "mqtt://broker/fleet/b/trace"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{cli::VisorArgs, tracing::thresholds::THRESHOLDS};
+
+/// Config file looked up in the working directory (the firmware project)
+pub const CONFIG_FILE_NAME: &str = "watchtower.toml";
+
+/// Config file given on the command line (`--config`), instead of `watchtower.toml`
+pub static CONFIG_FILE_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    semihosting_file: Option<PathBuf>,
+    source: Option<String>,
+    source_max_attempts: Option<u32>,
+    #[serde(alias = "symbols")]
+    elf: Option<PathBuf>,
+    raw_log: Option<PathBuf>,
+    csv_dir: Option<PathBuf>,
+    prometheus: Option<String>,
+    dashboard_file: Option<PathBuf>,
+    dashboard_interval_ms: Option<u64>,
+    rotate: Option<String>,
+    name_width: Option<u16>,
+    executor_indent: Option<u16>,
+    merge_executors: bool,
+    summary: bool,
+    headless: bool,
+    boot_generations: bool,
+    continue_respawned_tasks: bool,
+    stats_snapshots: bool,
+    refuse_schema_mismatch: bool,
+    hide_core_utilization: bool,
+    active_only_cpu: bool,
+    min_task_cpu: Option<f32>,
+    core_idle_color: Option<String>,
+    preemption_threshold_us: Option<u64>,
+    lost_wakeup_threshold_ms: Option<u64>,
+    pegged_threshold_ms: Option<u64>,
+    ping_pong_min_switches: Option<u64>,
+    ping_pong_max_interval_us: Option<u64>,
+    core_imbalance_threshold_percent: Option<u64>,
+    history_retention: Option<String>,
+    history_max_age_s: Option<u64>,
+    history_max_entries: Option<usize>,
+    cpu_smoothing: Option<f32>,
+    cpu_window_ms: Option<u64>,
+    parse_error_threshold: Option<f32>,
+    view_window_s: Option<u64>,
+    min_visible_span_us: Option<u64>,
+    time_unit: Option<String>,
+    /// Core id -> name
+    core_names: BTreeMap<u32, String>,
+    trace_prefix: Option<String>,
+    trace_suffix: Option<String>,
+    /// Event (as published by the beacon) -> name in the trace line
+    event_names: BTreeMap<String, String>,
+    /// Task categories (an array of tables to keep their order)
+    #[serde(rename = "category")]
+    categories: Vec<CategoryEntry>,
+    /// Further devices (an array of tables, their order gives the device ids)
+    #[serde(rename = "device")]
+    devices: Vec<DeviceEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CategoryEntry {
+    name: String,
+    /// Task name patterns
+    patterns: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeviceEntry {
+    name: String,
+    /// Network source URL or file path
+    source: String,
+}
+
+impl ConfigFile {
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// The options as command line arguments ("--flag=value")
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut option = |flag: &str, value: Option<String>| {
+            if let Some(value) = value {
+                args.push(format!("--{}={}", flag, value));
+            }
+        };
+
+        let path = |path: &Option<PathBuf>| path.as_ref().map(|p| p.display().to_string());
+        option("semihosting-file", path(&self.semihosting_file));
+        option("source", self.source.clone());
+        option(
+            "source-max-attempts",
+            self.source_max_attempts.map(|v| v.to_string()),
+        );
+        option("elf", path(&self.elf));
+        option("raw-log", path(&self.raw_log));
+        option("csv-dir", path(&self.csv_dir));
+        option("prometheus", self.prometheus.clone());
+        option("dashboard-file", path(&self.dashboard_file));
+        option(
+            "dashboard-interval-ms",
+            self.dashboard_interval_ms.map(|v| v.to_string()),
+        );
+        option("rotate", self.rotate.clone());
+        option("name-width", self.name_width.map(|v| v.to_string()));
+        option(
+            "executor-indent",
+            self.executor_indent.map(|v| v.to_string()),
+        );
+        option("min-task-cpu", self.min_task_cpu.map(|v| v.to_string()));
+        option("core-idle-color", self.core_idle_color.clone());
+        option(
+            "preemption-threshold-us",
+            self.preemption_threshold_us.map(|v| v.to_string()),
+        );
+        option(
+            "lost-wakeup-threshold-ms",
+            self.lost_wakeup_threshold_ms.map(|v| v.to_string()),
+        );
+        option(
+            "pegged-threshold-ms",
+            self.pegged_threshold_ms.map(|v| v.to_string()),
+        );
+        option(
+            "ping-pong-min-switches",
+            self.ping_pong_min_switches.map(|v| v.to_string()),
+        );
+        option(
+            "ping-pong-max-interval-us",
+            self.ping_pong_max_interval_us.map(|v| v.to_string()),
+        );
+        option(
+            "core-imbalance-threshold-percent",
+            self.core_imbalance_threshold_percent.map(|v| v.to_string()),
+        );
+        option("history-retention", self.history_retention.clone());
+        option(
+            "history-max-age-s",
+            self.history_max_age_s.map(|v| v.to_string()),
+        );
+        option(
+            "history-max-entries",
+            self.history_max_entries.map(|v| v.to_string()),
+        );
+        option("cpu-smoothing", self.cpu_smoothing.map(|v| v.to_string()));
+        option("cpu-window-ms", self.cpu_window_ms.map(|v| v.to_string()));
+        option(
+            "parse-error-threshold",
+            self.parse_error_threshold.map(|v| v.to_string()),
+        );
+        option("view-window-s", self.view_window_s.map(|v| v.to_string()));
+        option(
+            "min-visible-span-us",
+            self.min_visible_span_us.map(|v| v.to_string()),
+        );
+        option("time-unit", self.time_unit.clone());
+        option("trace-prefix", self.trace_prefix.clone());
+        option("trace-suffix", self.trace_suffix.clone());
+        for (core_id, name) in &self.core_names {
+            option("core-name", Some(format!("{}={}", core_id, name)));
+        }
+        for (event, name) in &self.event_names {
+            option("event-name", Some(format!("{}={}", event, name)));
+        }
+        for category in &self.categories {
+            for pattern in &category.patterns {
+                option("category", Some(format!("{}={}", category.name, pattern)));
+            }
+        }
+        for device in &self.devices {
+            option("device", Some(format!("{}={}", device.name, device.source)));
+        }
+
+        let flags = [
+            ("--merge-executors", self.merge_executors),
+            ("--summary", self.summary),
+            ("--headless", self.headless),
+            ("--boot-generations", self.boot_generations),
+            ("--continue-respawned-tasks", self.continue_respawned_tasks),
+            ("--stats-snapshots", self.stats_snapshots),
+            ("--refuse-schema-mismatch", self.refuse_schema_mismatch),
+            ("--hide-core-utilization", self.hide_core_utilization),
+            ("--active-only-cpu", self.active_only_cpu),
+        ];
+        args.extend(
+            flags
+                .into_iter()
+                .filter(|(_, set)| *set)
+                .map(|(flag, _)| flag.to_string()),
+        );
+
+        args
+    }
+
+    /// The options checked like the command line arguments
+    pub fn to_visor_args(&self) -> anyhow::Result<VisorArgs> {
+        VisorArgs::parse(&self.to_args())
+    }
+}
+
+/// Options of the given config file, or of `watchtower.toml` in the working directory if it
+/// exists. None without a config file.
+pub fn load_config_args(path: Option<&Path>) -> anyhow::Result<Option<VisorArgs>> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(CONFIG_FILE_NAME).exists() => Path::new(CONFIG_FILE_NAME),
+        None => return Ok(None),
+    };
+
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed reading config file {}", path.display()))?;
+    let args = ConfigFile::parse(&content)
+        .and_then(|config| config.to_visor_args())
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+    Ok(Some(args))
+}
+
+/// Set top level keys of a config file, keeping everything else (comments, order, tables).
+/// A key not in the file yet is added after the last top level key.
+fn set_top_level_keys(content: &str, values: &[(&str, String)]) -> String {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let mut top_level_end = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+
+    let mut insert_at = lines[..top_level_end]
+        .iter()
+        .rposition(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map_or(0, |index| index + 1);
+    for (key, value) in values {
+        let line = format!("{} = {}", key, value);
+        let existing = lines[..top_level_end].iter().position(|l| {
+            l.trim_start()
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        });
+        match existing {
+            Some(index) => lines[index] = line,
+            None => {
+                lines.insert(insert_at, line);
+                insert_at += 1;
+                top_level_end += 1;
+            }
+        }
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+/// Save the current detection thresholds to the config file (the one given with `--config`
+/// or `watchtower.toml`, created if missing). Returns the path written to.
+pub fn save_thresholds() -> anyhow::Result<PathBuf> {
+    let path = CONFIG_FILE_PATH
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(CONFIG_FILE_NAME));
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed reading {}", path.display()));
+        }
+    };
+
+    let values: Vec<(&str, String)> = THRESHOLDS
+        .iter()
+        .map(|threshold| (threshold.key, threshold.get().to_string()))
+        .collect();
+    let content = set_top_level_keys(&content, &values);
+    // Never write a file that would fail on the next start
+    ConfigFile::parse(&content)
+        .and_then(|config| config.to_visor_args())
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+    std::fs::write(&path, content).with_context(|| format!("Failed writing {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        tracing::{retention::RetentionPolicy, trace_data::TraceEventKind},
+        visualizer::time_unit::TimeUnit,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_config_file_options() {
+        let config = ConfigFile::parse(
+            r#"
+            history-retention = "larger"
+            history-max-age-s = 60
+            cpu-smoothing = 0.5
+            time-unit = "ms"
+            merge-executors = true
+            trace-prefix = "trace=["
+
+            [core-names]
+            0 = "APP"
+            1 = "NET"
+
+            [event-names]
+            TaskNew = "spawn"
+
+            [[category]]
+            name = "sensors"
+            patterns = ["imu_*", "baro_*"]
+
+            [[category]]
+            name = "comms"
+            patterns = ["*wifi*"]
+
+            [[device]]
+            name = "board-b"
+            source = "board-b.log"
+            "#,
+        )
+        .unwrap();
+        let args = config.to_visor_args().unwrap();
+
+        assert_eq!(args.history_retention, Some(RetentionPolicy::Larger));
+        assert_eq!(args.history_max_age_s, Some(60));
+        assert_eq!(args.cpu_smoothing, Some(0.5));
+        assert_eq!(args.time_unit, Some(TimeUnit::Millis));
+        assert_eq!(args.merge_executors, Some(true));
+        assert_eq!(args.trace_prefix.as_deref(), Some("trace=["));
+        assert_eq!(
+            args.core_names,
+            vec![(0, String::from("APP")), (1, String::from("NET"))]
+        );
+        assert_eq!(
+            args.event_names,
+            vec![(TraceEventKind::TaskNew, String::from("spawn"))]
+        );
+        assert_eq!(
+            args.categories,
+            [
+                ("sensors", "imu_*"),
+                ("sensors", "baro_*"),
+                ("comms", "*wifi*")
+            ]
+            .map(|(name, pattern)| (String::from(name), String::from(pattern)))
+        );
+        assert_eq!(args.devices.len(), 1);
+        assert_eq!(args.devices[0].name, "board-b");
+        assert!(args.cargo_args.is_empty());
+    }
+
+    #[test]
+    fn test_set_top_level_keys() {
+        let content = "# project defaults\ntime-unit = \"ms\"\npegged-threshold-ms = 1000\n\n[core-names]\n0 = \"APP\"\n";
+        let updated = set_top_level_keys(
+            content,
+            &[
+                ("pegged-threshold-ms", String::from("2500")),
+                ("ping-pong-min-switches", String::from("20")),
+            ],
+        );
+        assert_eq!(
+            updated,
+            "# project defaults\ntime-unit = \"ms\"\npegged-threshold-ms = 2500\nping-pong-min-switches = 20\n\n[core-names]\n0 = \"APP\"\n"
+        );
+        let args = ConfigFile::parse(&updated)
+            .unwrap()
+            .to_visor_args()
+            .unwrap();
+        assert_eq!(args.pegged_threshold_ms, Some(2500));
+        assert_eq!(args.ping_pong_min_switches, Some(20));
+
+        // new file
+        assert_eq!(
+            set_top_level_keys("", &[("lost-wakeup-threshold-ms", String::from("50"))]),
+            "lost-wakeup-threshold-ms = 50\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_config_file() {
+        // unknown key
+        assert!(ConfigFile::parse("history-seconds = 10").is_err());
+        // wrong type
+        assert!(ConfigFile::parse("name-width = \"wide\"").is_err());
+        // checked like the command line
+        let config = ConfigFile::parse("time-unit = \"hours\"").unwrap();
+        assert!(config.to_visor_args().is_err());
+    }
+}
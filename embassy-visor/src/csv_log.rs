@@ -0,0 +1,285 @@
+//! Continuous CSV logging (`--csv-dir`): the headless mode appends the statistics of every
+//! refresh to CSV files in a directory instead of printing them, rolling over to a new file
+//! every interval or size (`--rotate`), e.g. for overnight runs.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+
+use crate::{
+    tracing::stats::instance_stats::InstanceStats,
+    visualizer::{core_display_name, task_state_label},
+};
+
+/// Columns of every file, one row per task and refresh
+const CSV_HEADER: &str = "unix_time_s,core_id,core,core_cpu_percent,executor_id,executor,executor_cpu_percent,task_id,task,state,task_cpu_percent,avg_wait_us,max_wait_us";
+
+/// When the CSV logger starts a new file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// After the file has been written to for this long
+    Interval(Duration),
+    /// Once the file has grown to this many bytes
+    Size(u64),
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation::Interval(Duration::from_secs(60 * 60))
+    }
+}
+
+impl Rotation {
+    /// Parse a rotation given as a duration ("90s", "30m", "2h") or a size ("512KB", "100MB",
+    /// "1GB"). None if the value is neither or zero.
+    pub fn from_arg(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        let split = value.find(|c: char| !c.is_ascii_digit())?;
+        let (number, unit) = value.split_at(split);
+        let number: u64 = number.parse().ok().filter(|n| *n > 0)?;
+
+        let rotation = match unit {
+            "s" => Rotation::Interval(Duration::from_secs(number)),
+            "m" => Rotation::Interval(Duration::from_secs(number.checked_mul(60)?)),
+            "h" => Rotation::Interval(Duration::from_secs(number.checked_mul(60 * 60)?)),
+            "kb" => Rotation::Size(number.checked_mul(1 << 10)?),
+            "mb" => Rotation::Size(number.checked_mul(1 << 20)?),
+            "gb" => Rotation::Size(number.checked_mul(1 << 30)?),
+            _ => return None,
+        };
+        Some(rotation)
+    }
+
+    /// Whether a file opened `age` ago with `size` bytes written is due to be rolled over
+    pub fn is_due(&self, age: Duration, size: u64) -> bool {
+        match *self {
+            Rotation::Interval(interval) => age >= interval,
+            Rotation::Size(max_size) => size >= max_size,
+        }
+    }
+}
+
+/// Quote a field if it contains a separator, quote or line break (e.g. `<A as Trait<B, C>>`)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Rows of the statistics (one per task), all with the same timestamp
+fn csv_rows(stats: &InstanceStats, unix_time: Duration) -> Vec<String> {
+    let mut rows = Vec::new();
+    for core in stats.core_stats.iter() {
+        for executor in core.executors.iter() {
+            for task in executor.tasks.iter() {
+                rows.push(format!(
+                    "{:.3},{},{},{:.2},0x{:X},{},{:.2},0x{:X},{},{},{:.2},{},{}",
+                    unix_time.as_secs_f64(),
+                    core.core_id,
+                    csv_field(&core_display_name(core.device_id, core.core_id)),
+                    core.cpu_utilization_percent,
+                    executor.executor_id,
+                    csv_field(&executor.name),
+                    executor.cpu_utilization_percent,
+                    task.task_id,
+                    csv_field(&task.name),
+                    task_state_label(&task.state),
+                    task.cpu_utilization_percent,
+                    task.avg_waiting_time.as_micros(),
+                    task.max_waiting_time.as_micros(),
+                ));
+            }
+        }
+    }
+    rows
+}
+
+/// Comment line above the header, naming the firmware the rows of the file were traced from
+fn firmware_line(firmware_id: Option<&str>) -> String {
+    format!("# firmware_id: {}", firmware_id.unwrap_or("unknown"))
+}
+
+/// File currently written to
+struct CsvFile {
+    writer: BufWriter<File>,
+    opened_at: Instant,
+    size: u64,
+    /// Firmware the rows belong to (a new file is started when it changes)
+    firmware_id: Option<String>,
+}
+
+/// Appends the statistics to rotating CSV files in a directory
+pub struct CsvLogger {
+    dir: PathBuf,
+    rotation: Rotation,
+    file: Option<CsvFile>,
+    /// Unix time (seconds) the logging started at, shared by the names of all its files
+    started_at: u64,
+    file_count: usize,
+}
+
+impl CsvLogger {
+    /// Logger writing into `dir` (created if missing). The first file is created with the
+    /// first refresh.
+    pub fn new(dir: &Path, rotation: Rotation) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed creating the CSV directory {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            rotation,
+            file: None,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            file_count: 0,
+        })
+    }
+
+    /// Start the next file (flushing the current one)
+    fn roll_over(&mut self, firmware_id: Option<&str>) -> anyhow::Result<()> {
+        self.flush()?;
+
+        self.file_count += 1;
+        let path = self.dir.join(format!(
+            "embassy-visor-stats-{}-{:04}.csv",
+            self.started_at, self.file_count
+        ));
+        let file = File::create(&path)
+            .with_context(|| format!("Failed creating the CSV file {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        let firmware_line = firmware_line(firmware_id);
+        writeln!(writer, "{}", firmware_line)?;
+        writeln!(writer, "{}", CSV_HEADER)?;
+        self.file = Some(CsvFile {
+            writer,
+            opened_at: Instant::now(),
+            size: (firmware_line.len() + CSV_HEADER.len()) as u64 + 2,
+            firmware_id: firmware_id.map(String::from),
+        });
+        Ok(())
+    }
+
+    /// Append the rows of one refresh, rolling over to a new file first if the current one is
+    /// due or the firmware changed. The rows are flushed right away, so an interrupted run
+    /// loses nothing.
+    pub fn append(&mut self, stats: &InstanceStats) -> anyhow::Result<()> {
+        let due = self.file.as_ref().is_none_or(|file| {
+            self.rotation.is_due(file.opened_at.elapsed(), file.size)
+                || file.firmware_id != stats.firmware_id
+        });
+        if due {
+            self.roll_over(stats.firmware_id.as_deref())?;
+        }
+
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let file = self.file.as_mut().expect("CSV file opened above");
+        for row in csv_rows(stats, unix_time) {
+            writeln!(file.writer, "{}", row)?;
+            file.size += row.len() as u64 + 1;
+        }
+        file.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flush the current file (e.g. when the run ends)
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.writer.flush().context("Failed writing the CSV file")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_rotation_boundary() {
+        assert_eq!(
+            Rotation::from_arg("30m"),
+            Some(Rotation::Interval(Duration::from_secs(1800)))
+        );
+        assert_eq!(
+            Rotation::from_arg("100MB"),
+            Some(Rotation::Size(100 * 1024 * 1024))
+        );
+        assert_eq!(
+            Rotation::from_arg("2h"),
+            Some(Rotation::Interval(Duration::from_secs(7200)))
+        );
+        for invalid in ["", "30", "0m", "-5m", "10 parsecs", "m"] {
+            assert_eq!(Rotation::from_arg(invalid), None, "{}", invalid);
+        }
+
+        // a file rolls over exactly at the boundary
+        let hourly = Rotation::Interval(Duration::from_secs(3600));
+        assert!(!hourly.is_due(Duration::from_secs(3599), u64::MAX));
+        assert!(hourly.is_due(Duration::from_secs(3600), 0));
+        let sized = Rotation::Size(1024);
+        assert!(!sized.is_due(Duration::MAX, 1023));
+        assert!(sized.is_due(Duration::ZERO, 1024));
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("app::blink"), "app::blink");
+        assert_eq!(
+            csv_field("<app::Pair<A, B> as Debug>::fmt"),
+            "\"<app::Pair<A, B> as Debug>::fmt\""
+        );
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_firmware_id_line() {
+        let dir = std::env::temp_dir().join(format!("embassy-visor-csv-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut logger = CsvLogger::new(&dir, Rotation::default()).unwrap();
+
+        // a new file for every firmware
+        let mut stats = InstanceStats::default();
+        logger.append(&stats).unwrap();
+        stats.firmware_id = Some(String::from("g1a2b3c4"));
+        logger.append(&stats).unwrap();
+        logger.append(&stats).unwrap();
+        logger.flush().unwrap();
+
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        let first_lines: Vec<Vec<String>> = files
+            .iter()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .unwrap()
+                    .lines()
+                    .take(2)
+                    .map(String::from)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            first_lines,
+            [
+                ["# firmware_id: unknown", CSV_HEADER],
+                ["# firmware_id: g1a2b3c4", CSV_HEADER]
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
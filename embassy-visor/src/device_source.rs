@@ -0,0 +1,110 @@
+//! Further devices traced next to the firmware run by cargo (`--device NAME=SOURCE`), e.g. two
+//! communicating boards in one dashboard. Every device has its own line source and line router,
+//! so its trace items are tagged with its device id and its compact records are decoded on
+//! their own.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, atomic::AtomicBool},
+};
+
+use anyhow::bail;
+use crossbeam::channel::Sender;
+
+use crate::{
+    line_source,
+    net_source::{self, NetSourceUrl},
+    pipeline,
+    tracing::{device::DEVICE_NAMES, trace_data::TraceParseResult},
+};
+
+/// Where the lines of a device come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceInput {
+    /// MQTT topic or WebSocket (`mqtt://…`, `ws://…`)
+    Net(NetSourceUrl),
+    /// File followed like `--semihosting-file` (e.g. the output of a second probe)
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceSource {
+    pub name: String,
+    pub input: DeviceInput,
+}
+
+impl DeviceSource {
+    /// Parse "NAME=SOURCE", the source being a network source URL or a file path
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let Some((name, source)) = value.split_once('=') else {
+            bail!("Invalid device '{}' (expected NAME=SOURCE)", value);
+        };
+        if name.is_empty() || source.is_empty() {
+            bail!("Invalid device '{}' (expected NAME=SOURCE)", value);
+        }
+
+        let input = if source.contains("://") {
+            DeviceInput::Net(NetSourceUrl::parse(source)?)
+        } else {
+            DeviceInput::File(PathBuf::from(source))
+        };
+        Ok(Self {
+            name: name.to_string(),
+            input,
+        })
+    }
+}
+
+/// Start reading and routing the lines of the devices (device ids from 1 on, in the given order)
+pub fn spawn_device_sources(
+    devices: &[DeviceSource],
+    trace_tx: &Sender<TraceParseResult>,
+    logs_tx: &Sender<String>,
+    first_trace_item_received: &Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    *DEVICE_NAMES.write().unwrap() = devices.iter().map(|d| d.name.clone()).collect();
+
+    for (device_id, device) in (1..).zip(devices) {
+        let (program_tx, program_rx) = crossbeam::channel::unbounded();
+        match &device.input {
+            DeviceInput::Net(url) => {
+                net_source::spawn_net_source(url.clone(), program_tx)?;
+            }
+            DeviceInput::File(path) => {
+                line_source::follow_file_lines(path.clone(), program_tx);
+            }
+        }
+        pipeline::route_program_lines(
+            device_id,
+            program_rx,
+            trace_tx.clone(),
+            logs_tx.clone(),
+            first_trace_item_received.clone(),
+            None,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device_source() {
+        let device = DeviceSource::parse("board-b=mqtt://broker/fleet/b/trace").unwrap();
+        assert_eq!(device.name, "board-b");
+        assert!(matches!(device.input, DeviceInput::Net(url) if url.path == "fleet/b/trace"));
+
+        let device = DeviceSource::parse("net-core=/tmp/probe2.log").unwrap();
+        assert_eq!(
+            device.input,
+            DeviceInput::File(PathBuf::from("/tmp/probe2.log"))
+        );
+
+        assert!(DeviceSource::parse("board-b").is_err());
+        assert!(DeviceSource::parse("=mqtt://broker/t").is_err());
+        assert!(DeviceSource::parse("board-b=").is_err());
+        assert!(DeviceSource::parse("board-b=serial:///dev/ttyUSB1").is_err());
+    }
+}
@@ -1,5 +1,68 @@
 use object::{Object, ObjectSymbol};
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::FIRMWARE_ADDR_MAP;
+
+/// Reported once when executors or tasks are created without the firmware address map
+pub static MISSING_ADDR_MAP_WARNING: OnceWarning = OnceWarning::new();
+
+/// Warning emitted only the first time it is hit
+pub struct OnceWarning {
+    emitted: AtomicBool,
+}
+
+impl OnceWarning {
+    pub const fn new() -> Self {
+        Self {
+            emitted: AtomicBool::new(false),
+        }
+    }
+
+    /// Pass the message to the sink, unless it was emitted (or suppressed) before
+    pub fn emit(&self, message: &str, sink: impl FnOnce(&str)) {
+        if !self.emitted.swap(true, Ordering::Relaxed) {
+            sink(message);
+        }
+    }
+
+    /// Never emit the warning (e.g. the user already knows there are no symbols)
+    pub fn suppress(&self) {
+        self.emitted.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Symbol at the address in the address map. Without a map the missing symbols are reported
+/// once to the sink; the caller names the entity by its id.
+pub fn lookup_symbol(
+    addr_map: Option<&HashMap<u64, String>>,
+    addr: u64,
+    warning: &OnceWarning,
+    sink: impl FnOnce(&str),
+) -> Option<String> {
+    match addr_map {
+        Some(addr_map) => addr_map.get(&addr).cloned(),
+        None => {
+            warning.emit(
+                "Firmware address map not loaded, executors and tasks are named by their ids",
+                sink,
+            );
+            None
+        }
+    }
+}
+
+/// Symbol at the address in the global firmware address map
+pub fn firmware_symbol(addr: u64) -> Option<String> {
+    lookup_symbol(
+        FIRMWARE_ADDR_MAP.read().unwrap().as_ref(),
+        addr,
+        &MISSING_ADDR_MAP_WARNING,
+        |message| eprintln!("Warning: {}", message),
+    )
+}
 
 /// Return elf-file's address-to-symbol map
 pub fn get_addr_map(file: object::File<'_>) -> HashMap<u64, String> {
@@ -12,21 +75,176 @@ pub fn get_addr_map(file: object::File<'_>) -> HashMap<u64, String> {
         }
 
         // Add symbol name if available
-        if let Ok(name) = symbol.name() {
-            if !name.is_empty() {
-                let demangled = rustc_demangle::demangle(name).to_string();
-                addr_map.insert(addr, demangled);
+        if let Ok(name) = symbol.name()
+            && !name.is_empty()
+        {
+            let demangled = rustc_demangle::demangle(name).to_string();
+            addr_map.insert(addr, demangled);
+
+            // Reinsert to overwrite potential aliases
+        }
+    }
+
+    addr_map
+}
 
-                // Reinsert to overwrite potential aliases
-            }
+/// Return the address-to-symbol map of a text symbol file (for stripped binaries whose
+/// symbols are kept separately). Understands GNU ld map files (`0x<addr>  <symbol>` lines
+/// below the input sections) and nm output (`<addr> [<size>] <type> <symbol>`); every other
+/// line is skipped.
+pub fn parse_symbol_map(content: &str) -> HashMap<u64, String> {
+    let mut addr_map: HashMap<u64, String> = HashMap::new();
+
+    for line in content.lines() {
+        if let Some((addr, name)) = parse_symbol_line(line)
+            && addr != 0
+        {
+            let demangled = rustc_demangle::demangle(name).to_string();
+            addr_map.insert(addr, demangled);
         }
     }
 
     addr_map
 }
 
+/// Address and symbol name of a map file or nm line
+fn parse_symbol_line(line: &str) -> Option<(u64, &str)> {
+    let (first, rest) = split_token(line)?;
+
+    // ld map file: symbol lines have no size, unlike the section lines
+    if let Some(hex) = first.strip_prefix("0x") {
+        let (name, tail) = split_token(rest)?;
+        if name.starts_with("0x") || !tail.is_empty() {
+            return None;
+        }
+        return Some((u64::from_str_radix(hex, 16).ok()?, name));
+    }
+
+    // nm: the type is a single letter, optionally preceded by the size (`nm -S`)
+    let addr = u64::from_str_radix(first, 16).ok()?;
+    let (mut symbol_type, mut rest) = split_token(rest)?;
+    if symbol_type.len() > 1 && u64::from_str_radix(symbol_type, 16).is_ok() {
+        (symbol_type, rest) = split_token(rest)?;
+    }
+    let name = rest.trim();
+    (symbol_type.len() == 1 && !name.is_empty()).then_some((addr, name))
+}
+
+/// First whitespace separated token and the rest of the line (demangled nm names may contain
+/// spaces)
+fn split_token(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+    Some(line.split_once(char::is_whitespace).unwrap_or((line, "")))
+}
+
+/// Return the GNU build id of the elf-file (if it has one)
+pub fn get_build_id(file: &object::File<'_>) -> Option<Vec<u8>> {
+    file.build_id().ok().flatten().map(|id| id.to_vec())
+}
+
 /// Helper function to extract short name from full symbol name
 pub fn try_extract_short_name(full_name: &str) -> &str {
     let pool_index = full_name.find("::POOL").unwrap_or(full_name.len());
     &full_name[0..pool_index]
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{OnceWarning, lookup_symbol, parse_symbol_map};
+    use crate::tracing::{executor::ExecutorTraceInfo, task::TaskTraceInfo, test_harness::at_ms};
+
+    #[test]
+    fn test_missing_addr_map_warned_once() {
+        let warning = OnceWarning::new();
+        let mut warnings = Vec::new();
+        for addr in 0..100 {
+            assert_eq!(
+                lookup_symbol(None, addr, &warning, |message| warnings
+                    .push(message.to_string())),
+                None
+            );
+        }
+        assert_eq!(warnings.len(), 1);
+
+        // entities without a symbol are named by their ids
+        let names: Vec<String> = (1..=50)
+            .map(|id| TaskTraceInfo::new(id, 1, 0, at_ms(0)).get_task_display_name())
+            .chain(
+                (1..=50)
+                    .map(|id| ExecutorTraceInfo::new(id, 0, at_ms(0)).get_executor_display_name()),
+            )
+            .collect();
+        assert_eq!(names[0], "Task 0x1");
+        assert_eq!(names[99], "Executor 0x32");
+
+        // symbols are still found with a map, a suppressed warning is never emitted
+        let addr_map = HashMap::from([(0x10, String::from("app::blink"))]);
+        let suppressed = OnceWarning::new();
+        suppressed.suppress();
+        assert_eq!(
+            lookup_symbol(Some(&addr_map), 0x10, &suppressed, |_| panic!()),
+            Some(String::from("app::blink"))
+        );
+        assert_eq!(lookup_symbol(None, 0x10, &suppressed, |_| panic!()), None);
+    }
+
+    #[test]
+    fn test_parse_linker_map() {
+        let map = "
+Memory Configuration
+
+Name             Origin             Length             Attributes
+FLASH            0x0000000008000000 0x0000000000100000 xr
+
+ .text._ZN3app10blink_task4POOL17h0123456789abcdefE
+                0x0000000008000400       0x24 app.o
+                0x0000000008000400                _ZN3app10blink_task4POOL17h0123456789abcdefE
+ .bss.app::READINGS
+                0x0000000020000100       0x40 app.o
+                0x0000000020000100                app::READINGS
+                0x0000000020000000                _sbss = .
+                0x0000000000000000                __dso_handle
+";
+
+        assert_eq!(
+            parse_symbol_map(map),
+            HashMap::from([
+                (
+                    0x08000400,
+                    String::from("app::blink_task::POOL::h0123456789abcdef")
+                ),
+                (0x20000100, String::from("app::READINGS")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_nm_output() {
+        let nm = "
+20000100 D app::READINGS
+08000400 00000024 T _ZN3app10blink_task4POOL17h0123456789abcdefE
+0800f000 t <app::Sensor as core::fmt::Debug>::fmt
+         U memcpy
+";
+
+        assert_eq!(
+            parse_symbol_map(nm),
+            HashMap::from([
+                (0x20000100, String::from("app::READINGS")),
+                (
+                    0x08000400,
+                    String::from("app::blink_task::POOL::h0123456789abcdef")
+                ),
+                (
+                    0x0800f000,
+                    String::from("<app::Sensor as core::fmt::Debug>::fmt")
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! One build/flash/run cycle of the firmware (`cargo run`).
+//!
+//! A run can be replaced by a new one while the TUI keeps running (hot reload with 'r').
+//! All runs forward their program output into the same program line channel, so the
+//! trace/log routing and the TUI stay untouched by a reload.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, bail};
+use crossbeam::channel::{Receiver, Sender};
+
+use crate::{
+    FIRMWARE_ADDR_MAP,
+    cargo::{
+        cargo_build::{self, CargoBuildStatus},
+        cargo_child::{self, CargoChildProcess},
+    },
+    elf_file,
+    pipeline::INVALID_UTF8_LINE,
+    tracing::{instance::TracingInstance, trace_data::TraceParseResult},
+};
+
+/// State of the current firmware run as shown in the TUI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirmwareStatus {
+    /// Firmware is (re-)built and flashed
+    Building,
+    /// Build succeeded, the firmware is running
+    Running,
+    /// Build succeeded with a warning (e.g. mismatching `--elf`), the firmware is running
+    RunningWithWarning(String),
+    /// Build failed with the given compiler messages
+    BuildFailed(Vec<String>),
+}
+
+pub struct FirmwareRun {
+    cargo_child_process: CargoChildProcess,
+
+    build_recver: Receiver<String>,
+
+    /// Thread forwarding the output lines (ends with the output of the process)
+    forwarder: std::thread::JoinHandle<()>,
+}
+
+impl FirmwareRun {
+    /// Start `cargo run` with the given arguments.
+    /// Build output goes to the build receiver, everything after the build into `program_tx`.
+    pub fn start(cargo_args: Vec<String>, program_tx: Sender<String>) -> anyhow::Result<Self> {
+        let cargo_child_process = cargo_child::start_cargo_run(cargo_args)
+            .context("Failed to start cargo run process")?;
+        let stdout_listener = cargo_child_process.get_stdout_receiver();
+
+        let (build_tx, build_recver) = crossbeam::channel::unbounded();
+        let forwarder = std::thread::spawn(move || {
+            let mut temp_buffer = Vec::new();
+            let mut cargo_build_finished = false;
+            // Loop ends when the stdout channel is closed
+            while let Ok(c) = stdout_listener.recv() {
+                temp_buffer.push(c);
+
+                // Check if '\n' is in buffer
+                let newline_pos = temp_buffer.iter().position(|&b| b == b'\n');
+                if let Some(pos) = newline_pos {
+                    let line = String::from_utf8(temp_buffer.drain(..=pos).collect())
+                        .unwrap_or_else(|_| String::from(INVALID_UTF8_LINE));
+
+                    if !cargo_build_finished {
+                        // build output
+                        if line.contains(r#"{"reason":"build-finished","success":true}"#) {
+                            cargo_build_finished = true;
+                        }
+
+                        if build_tx.send(line).is_err() {
+                            break; // Nobody is waiting for the build anymore
+                        }
+                    } else {
+                        // Trace or log line of program
+                        if program_tx.send(line).is_err() {
+                            break; // Program line router stopped
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            cargo_child_process,
+            build_recver,
+            forwarder,
+        })
+    }
+
+    pub fn get_build_receiver(&self) -> Receiver<String> {
+        self.build_recver.clone()
+    }
+
+    /// Terminate the run. Returns once every output line of it was forwarded.
+    pub fn kill(self) -> anyhow::Result<()> {
+        self.cargo_child_process
+            .kill()
+            .context("Tried killing Cargo Run Child Process")?;
+        let _ = self.forwarder.join();
+        Ok(())
+    }
+}
+
+/// Channels between the firmware runs and the tracing instance
+pub struct RunChannels {
+    /// Program lines of every run
+    pub program_tx: Sender<String>,
+    /// Receiving ends of the queues in between (the pipeline receives from clones)
+    pub program_rx: Receiver<String>,
+    pub trace_rx: Receiver<TraceParseResult>,
+}
+
+impl RunChannels {
+    /// Discard the queued program lines and trace items (of a killed run), so they do not
+    /// end up in the reset instance. Lines first, a line routed meanwhile lands in the trace
+    /// queue drained after them.
+    fn discard_queued(&self) {
+        self.program_rx.try_iter().for_each(drop);
+        self.trace_rx.try_iter().for_each(drop);
+    }
+}
+
+/// Read the ELF file (or a linker map / nm output of a stripped firmware) and replace the
+/// global address map used for task and executor names. Returns the build id of the ELF file
+/// (if it has one, symbol files never have one).
+pub fn load_firmware_addr_map(elf_path: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    let symbols = read_firmware_symbols(elf_path)?;
+    *FIRMWARE_ADDR_MAP.write().unwrap() = Some(symbols.addr_map);
+    Ok(symbols.build_id)
+}
+
+/// Symbols of an ELF or symbol file
+struct FirmwareSymbols {
+    addr_map: HashMap<u64, String>,
+    /// Build id of an ELF file (if it has one, symbol files never have one)
+    build_id: Option<Vec<u8>>,
+}
+
+/// Read the symbols of the ELF file, or of a linker map / nm output
+fn read_firmware_symbols(elf_path: &Path) -> anyhow::Result<FirmwareSymbols> {
+    let bin_data = fs::read(elf_path).with_context(|| {
+        format!(
+            "Failed reading the firmware symbol file {}",
+            elf_path.display()
+        )
+    })?;
+
+    match object::File::parse(&*bin_data) {
+        Ok(file) => {
+            let build_id = elf_file::get_build_id(&file);
+            Ok(FirmwareSymbols {
+                addr_map: elf_file::get_addr_map(file),
+                build_id,
+            })
+        }
+        Err(elf_err) => {
+            let content = std::str::from_utf8(&bin_data)
+                .map_err(|_| elf_err)
+                .context("Failed parsing the firmware ELF file")?;
+            let addr_map = elf_file::parse_symbol_map(content);
+            if addr_map.is_empty() {
+                bail!(
+                    "No symbols found in {} (expected an ELF file, a linker map or nm output)",
+                    elf_path.display()
+                );
+            }
+            Ok(FirmwareSymbols {
+                addr_map,
+                build_id: None,
+            })
+        }
+    }
+}
+
+/// Warning if the build ids of the given and the built ELF file differ (both need one)
+pub fn build_id_mismatch_warning(given: Option<&[u8]>, built: Option<&[u8]>) -> Option<String> {
+    let hex = |id: &[u8]| id.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    match (given, built) {
+        (Some(given), Some(built)) if given != built => Some(format!(
+            "Build id of the given ELF file ({}) differs from the running firmware ({}), task names may be wrong",
+            hex(given),
+            hex(built)
+        )),
+        _ => None,
+    }
+}
+
+/// Load the address map from the ELF file built by cargo, or from `elf_override` (`--elf`)
+/// regardless of what cargo reported. Returns a warning if the build ids differ.
+pub fn load_firmware_symbols(
+    built_elf: Option<&Path>,
+    elf_override: Option<&Path>,
+) -> anyhow::Result<Option<String>> {
+    match (elf_override, built_elf) {
+        (Some(elf_override), built_elf) => {
+            let given_build_id = load_firmware_addr_map(elf_override)?;
+            // A missing or unreadable built ELF only skips the validation
+            let built_build_id =
+                built_elf.and_then(|path| read_firmware_symbols(path).ok()?.build_id);
+            Ok(build_id_mismatch_warning(
+                given_build_id.as_deref(),
+                built_build_id.as_deref(),
+            ))
+        }
+        (None, Some(built_elf)) => load_firmware_addr_map(built_elf).map(|_| None),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Restart the firmware run on every reload request until the request channel is closed.
+/// The tracing instance is reset, so the TUI only shows statistics of the new firmware.
+pub fn run_reload_supervisor(
+    cargo_args: Vec<String>,
+    channels: RunChannels,
+    instance: TracingInstance,
+    current_run: Arc<Mutex<Option<FirmwareRun>>>,
+    reload_recver: Receiver<()>,
+    status_tx: Sender<FirmwareStatus>,
+    elf_override: Option<PathBuf>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        // Loop ends when the TUI has been closed
+        while let Ok(()) = reload_recver.recv() {
+            let _ = status_tx.send(FirmwareStatus::Building);
+
+            // Stop the old firmware run before starting the new one (frees the debug probe)
+            if let Some(old_run) = current_run.lock().unwrap().take() {
+                let _ = old_run.kill();
+            }
+            channels.discard_queued();
+            instance.reset();
+
+            let new_run = match FirmwareRun::start(cargo_args.clone(), channels.program_tx.clone())
+            {
+                Ok(new_run) => new_run,
+                Err(e) => {
+                    let _ = status_tx.send(FirmwareStatus::BuildFailed(vec![format!("{:#}", e)]));
+                    continue;
+                }
+            };
+            let build_recver = new_run.get_build_receiver();
+            *current_run.lock().unwrap() = Some(new_run);
+
+            // Do not hold the lock while building, so quitting can still kill the run
+            let status = match cargo_build::handle_cargo_build(&build_recver) {
+                CargoBuildStatus::Success(elf_path) => {
+                    match load_firmware_symbols(
+                        elf_path.as_deref().map(Path::new),
+                        elf_override.as_deref(),
+                    ) {
+                        Ok(None) => FirmwareStatus::Running,
+                        Ok(Some(warning)) => FirmwareStatus::RunningWithWarning(warning),
+                        Err(e) => FirmwareStatus::BuildFailed(vec![format!("{:#}", e)]),
+                    }
+                }
+                CargoBuildStatus::Failed(messages) => FirmwareStatus::BuildFailed(messages),
+                CargoBuildStatus::Aborted => {
+                    FirmwareStatus::BuildFailed(vec![String::from("Build process was aborted")])
+                }
+            };
+            let _ = status_tx.send(status);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{test_harness::item, trace_data::TraceItemType};
+
+    use super::{RunChannels, build_id_mismatch_warning};
+
+    #[test]
+    fn test_discard_queued() {
+        let (program_tx, program_rx) = crossbeam::channel::unbounded();
+        let (trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let channels = RunChannels {
+            program_tx,
+            program_rx: program_rx.clone(),
+            trace_rx: trace_rx.clone(),
+        };
+
+        channels.program_tx.send(String::from("old line")).unwrap();
+        let data = TraceItemType::ExecutorIdle { executor_id: 1 };
+        trace_tx.send(Ok(item(1, 0, data))).unwrap();
+        channels.discard_queued();
+        assert!(program_rx.is_empty());
+        assert!(trace_rx.is_empty());
+
+        // lines of the new run pass
+        channels.program_tx.send(String::from("new line")).unwrap();
+        assert_eq!(program_rx.try_recv().as_deref(), Ok("new line"));
+    }
+
+    #[test]
+    fn test_build_id_mismatch_warning() {
+        let built = [0xab, 0x01];
+        assert_eq!(build_id_mismatch_warning(Some(&built), Some(&built)), None);
+
+        // validation needs both build ids
+        assert_eq!(build_id_mismatch_warning(None, Some(&built)), None);
+        assert_eq!(build_id_mismatch_warning(Some(&built), None), None);
+
+        let warning = build_id_mismatch_warning(Some(&[0xcd, 0x02]), Some(&built)).unwrap();
+        assert!(warning.contains("cd02"));
+        assert!(warning.contains("ab01"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Headless mode (`--headless`): the statistics are printed as JSON lines instead of running
+//! the TUI. It is also used when the terminal can not show the TUI (stdout redirected or a
+//! dumb terminal), where raw mode and the alternate screen would only produce garbage. With
+//! `--csv-dir` the statistics are appended to rotating CSV files instead.
+
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{Receiver, RecvTimeoutError};
+use serde::Serialize;
+
+use crate::{
+    csv_log::CsvLogger,
+    throughput::{PARSE_ERROR_THRESHOLD_PERCENT, SOURCE_THROUGHPUT, ThroughputRate},
+    tracing::{instance::TracingInstance, stats::instance_stats::InstanceStats},
+    visualizer::{core_display_name, task_state_label},
+};
+
+/// Interval the statistics are printed at
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How the statistics are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frontend {
+    Tui,
+    /// JSON lines on stdout, with the reason if the TUI is not supported
+    Headless {
+        fallback_reason: Option<&'static str>,
+    },
+}
+
+impl Frontend {
+    /// Use the TUI unless headless was requested or the terminal does not support it
+    pub fn select(headless: bool, stdout_is_terminal: bool, term: Option<&str>) -> Self {
+        let fallback_reason = if !stdout_is_terminal {
+            Some("stdout is not a terminal")
+        } else if term == Some("dumb") {
+            Some("the terminal is dumb (TERM=dumb)")
+        } else {
+            None
+        };
+
+        match (headless, fallback_reason) {
+            (true, _) => Frontend::Headless {
+                fallback_reason: None,
+            },
+            (false, Some(reason)) => Frontend::Headless {
+                fallback_reason: Some(reason),
+            },
+            (false, None) => Frontend::Tui,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StatsSnapshot {
+    firmware_id: Option<String>,
+    /// Trace schema version of the beacon not supported by the visor
+    schema_warning: Option<String>,
+    events_processed: usize,
+    parse_errors: usize,
+    /// Share of the lines since the previous snapshot failing to parse
+    failed_line_percent: f64,
+    /// More lines failed to parse than the threshold allows
+    parse_error_warning: bool,
+    cores: Vec<CoreSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+struct CoreSnapshot {
+    core_id: u32,
+    name: String,
+    cpu_percent: f32,
+    preemption_depth: usize,
+    asleep: bool,
+    scheduling_overhead_percent: Option<f32>,
+    executors: Vec<ExecutorSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExecutorSnapshot {
+    executor_id: u32,
+    name: String,
+    cpu_percent: f32,
+    pegged: bool,
+    scheduling_overhead_percent: Option<f32>,
+    tasks: Vec<TaskSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskSnapshot {
+    task_id: u32,
+    name: String,
+    state: &'static str,
+    cpu_percent: f32,
+    avg_wait_us: u128,
+    max_wait_us: u128,
+}
+
+impl StatsSnapshot {
+    fn of(stats: &InstanceStats, rate: &ThroughputRate, parse_error_threshold: f32) -> Self {
+        Self {
+            firmware_id: stats.firmware_id.clone(),
+            schema_warning: stats.schema_warning.clone(),
+            events_processed: stats.parse_stats.total_count(),
+            parse_errors: stats.parse_stats.error_count(),
+            failed_line_percent: rate.failed_line_percent,
+            parse_error_warning: rate.exceeds_parse_error_threshold(parse_error_threshold),
+            cores: stats
+                .core_stats
+                .iter()
+                .map(|core| CoreSnapshot {
+                    core_id: core.core_id,
+                    name: core_display_name(core.device_id, core.core_id),
+                    cpu_percent: core.cpu_utilization_percent,
+                    preemption_depth: core.preemption_depth,
+                    asleep: core.asleep,
+                    scheduling_overhead_percent: core.scheduling_overhead_percent(),
+                    executors: core
+                        .executors
+                        .iter()
+                        .map(|executor| ExecutorSnapshot {
+                            executor_id: executor.executor_id,
+                            name: executor.name.clone(),
+                            cpu_percent: executor.cpu_utilization_percent,
+                            pegged: executor.pegged,
+                            scheduling_overhead_percent: executor.scheduling_overhead_percent(),
+                            tasks: executor
+                                .tasks
+                                .iter()
+                                .map(|task| TaskSnapshot {
+                                    task_id: task.task_id,
+                                    name: task.name.clone(),
+                                    state: task_state_label(&task.state),
+                                    cpu_percent: task.cpu_utilization_percent,
+                                    avg_wait_us: task.avg_waiting_time.as_micros(),
+                                    max_wait_us: task.max_waiting_time.as_micros(),
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Print the statistics every second as one JSON line on stdout (or append them to the CSV
+/// files), log lines go to stderr. Runs until the log lines end, returns whether the parse
+/// error threshold was exceeded meanwhile (so the run can end with a non-zero exit code).
+pub fn run_headless(
+    instance: TracingInstance,
+    logs_recver: Receiver<String>,
+    mut csv_logger: Option<CsvLogger>,
+) -> anyhow::Result<bool> {
+    let parse_error_threshold = *PARSE_ERROR_THRESHOLD_PERCENT.read().unwrap();
+    let mut parse_error_threshold_exceeded = false;
+    let mut schema_warned = false;
+    let mut previous_sample = SOURCE_THROUGHPUT.sample();
+    let mut next_snapshot = Instant::now() + SNAPSHOT_INTERVAL;
+    loop {
+        match logs_recver.recv_timeout(next_snapshot.saturating_duration_since(Instant::now())) {
+            Ok(line) => eprintln!("{}", line.trim_end()),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                if let Some(csv_logger) = csv_logger.as_mut() {
+                    csv_logger.flush()?;
+                }
+                return Ok(parse_error_threshold_exceeded);
+            }
+        }
+
+        // Also checked after every log line, a chatty firmware must not hold the snapshots back
+        let now = Instant::now();
+        if now < next_snapshot {
+            continue;
+        }
+        next_snapshot = following_snapshot(next_snapshot, now);
+
+        let sample = SOURCE_THROUGHPUT.sample();
+        let rate = ThroughputRate::between(&previous_sample, &sample);
+        previous_sample = sample;
+
+        let stats = instance.latest_stats();
+        let snapshot = StatsSnapshot::of(&stats, &rate, parse_error_threshold);
+        if let Some(warning) = &snapshot.schema_warning
+            && !schema_warned
+        {
+            eprintln!("Warning: {}", warning);
+            schema_warned = true;
+        }
+        if snapshot.parse_error_warning && !parse_error_threshold_exceeded {
+            eprintln!(
+                "Warning: {:.0}% of the lines fail to parse (threshold {}%), format mismatch or corrupted link?",
+                rate.failed_line_percent, parse_error_threshold
+            );
+            parse_error_threshold_exceeded = true;
+        }
+        match csv_logger.as_mut() {
+            Some(csv_logger) => csv_logger.append(&stats)?,
+            None => println!("{}", serde_json::to_string(&snapshot)?),
+        }
+    }
+}
+
+/// Time of the snapshot after the one `scheduled` and taken `now`. Intervals missed by a stall
+/// are skipped instead of being caught up in a burst of snapshots.
+fn following_snapshot(scheduled: Instant, now: Instant) -> Instant {
+    let following = scheduled + SNAPSHOT_INTERVAL;
+    if following > now {
+        following
+    } else {
+        now + SNAPSHOT_INTERVAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_following_snapshot() {
+        let start = Instant::now();
+
+        // on time (or slightly late): the cadence is kept
+        let following = following_snapshot(start, start + Duration::from_millis(30));
+        assert_eq!(following, start + SNAPSHOT_INTERVAL);
+
+        // after a stall the missed snapshots are skipped
+        let now = start + 5 * SNAPSHOT_INTERVAL;
+        assert_eq!(following_snapshot(start, now), now + SNAPSHOT_INTERVAL);
+    }
+
+    #[test]
+    fn test_frontend_selection() {
+        assert_eq!(
+            Frontend::select(false, true, Some("xterm-256color")),
+            Frontend::Tui
+        );
+        // no TERM (e.g. Windows) is not a dumb terminal
+        assert_eq!(Frontend::select(false, true, None), Frontend::Tui);
+
+        // redirected stdout falls back to headless
+        assert_eq!(
+            Frontend::select(false, false, Some("xterm-256color")),
+            Frontend::Headless {
+                fallback_reason: Some("stdout is not a terminal")
+            }
+        );
+        assert!(matches!(
+            Frontend::select(false, true, Some("dumb")),
+            Frontend::Headless {
+                fallback_reason: Some(_)
+            }
+        ));
+
+        // requested headless mode is no fallback
+        assert_eq!(
+            Frontend::select(true, false, Some("dumb")),
+            Frontend::Headless {
+                fallback_reason: None
+            }
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
+
+use crossbeam::channel::Sender;
+
+use crate::{pipeline::INVALID_UTF8_LINE, source_retry::Backoff, throughput::SOURCE_THROUGHPUT};
+
+/// State of the followed file, shown in the log pane title
+pub static FILE_SOURCE_STATUS: RwLock<Option<FileSourceStatus>> = RwLock::new(None);
+
+/// Delay before checking again for a missing file, doubled up to [`MAX_FILE_WAIT_DELAY`]
+const FILE_WAIT_DELAY: Duration = Duration::from_millis(100);
+const MAX_FILE_WAIT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileSourceStatus {
+    /// The file does not exist yet (e.g. the probe is not attached)
+    Waiting {
+        path: PathBuf,
+        attempts: u32,
+        retry_in: Duration,
+    },
+    Following,
+    /// The file did not appear within `--source-max-attempts`
+    GaveUp {
+        path: PathBuf,
+        attempts: u32,
+    },
+}
+
+fn set_status(status: FileSourceStatus) {
+    *FILE_SOURCE_STATUS.write().unwrap() = Some(status);
+}
+
+/// Follows a growing text file (like `tail -f`) and sends every complete line to the channel.
+/// Waits for the file to be created, because debug probes only create it on connection.
+pub fn follow_file_lines(path: PathBuf, sender: Sender<String>) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        follow_file(
+            &path,
+            &sender,
+            Backoff::new(FILE_WAIT_DELAY, MAX_FILE_WAIT_DELAY),
+        )
+    })
+}
+
+/// Wait (with growing delay) until the file exists, then forward its lines. Returns once the
+/// receiver has been dropped or the file did not appear in time.
+fn follow_file(path: &Path, sender: &Sender<String>, mut backoff: Backoff) {
+    let file = loop {
+        match File::open(path) {
+            Ok(file) => break file,
+            Err(_) => match backoff.failed() {
+                Some(delay) => {
+                    set_status(FileSourceStatus::Waiting {
+                        path: path.to_path_buf(),
+                        attempts: backoff.failed_attempts(),
+                        retry_in: delay,
+                    });
+                    std::thread::sleep(delay);
+                }
+                None => {
+                    set_status(FileSourceStatus::GaveUp {
+                        path: path.to_path_buf(),
+                        attempts: backoff.failed_attempts(),
+                    });
+                    let _ = sender.send(format!(
+                        "Warning: {} did not appear after {} attempts, giving up",
+                        path.display(),
+                        backoff.failed_attempts()
+                    ));
+                    return;
+                }
+            },
+        }
+    };
+    set_status(FileSourceStatus::Following);
+
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    loop {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => {
+                // End of file reached --> wait for more data
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Ok(_) => {
+                // Only forward complete lines (the writer may not have finished the line yet)
+                if line.ends_with(b"\n") {
+                    SOURCE_THROUGHPUT.add_bytes(line.len());
+                    let text = String::from_utf8(std::mem::take(&mut line))
+                        .unwrap_or_else(|_| String::from(INVALID_UTF8_LINE));
+                    if sender.send(text).is_err() {
+                        break; // Receiver has been dropped
+                    }
+                }
+            }
+            Err(e) => {
+                // Shown in the log pane, the TUI owns the terminal
+                let _ = sender.send(format!("Error reading {}: {}", path.display(), e));
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_follow_file_after_failed_attempts() {
+        let path = std::env::temp_dir().join(format!(
+            "embassy-visor-line-source-{}.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let follower = {
+            let path = path.clone();
+            let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(5))
+                .with_max_attempts(0);
+            std::thread::spawn(move || follow_file(&path, &sender, backoff))
+        };
+
+        // the board is still booting: the file shows up after a few failed attempts
+        let timeout = std::time::Instant::now() + Duration::from_secs(5);
+        while !matches!(
+            *FILE_SOURCE_STATUS.read().unwrap(),
+            Some(FileSourceStatus::Waiting { attempts, .. }) if attempts >= 3
+        ) {
+            assert!(std::time::Instant::now() < timeout, "no failed attempts");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "[1, 0, TaskNew, 1, 2]").unwrap();
+
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+            "[1, 0, TaskNew, 1, 2]\n"
+        );
+        assert_eq!(
+            *FILE_SOURCE_STATUS.read().unwrap(),
+            Some(FileSourceStatus::Following)
+        );
+
+        drop(receiver);
+        writeln!(file, "next").unwrap();
+        follower.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,24 +1,42 @@
 use std::{
-    fs,
-    sync::{Arc, OnceLock, atomic::AtomicBool},
+    io::IsTerminal,
+    sync::{Arc, Mutex, RwLock, atomic::AtomicBool},
 };
 
 use anyhow::{Context, bail};
 
 use crate::{
-    cargo::{
-        cargo_build::{self, CargoBuildStatus},
-        cargo_child,
-    },
-    tracing::{instance::TracingInstance, time::ComputerTime, trace_data::TraceItem},
+    cargo::cargo_build::{self, CargoBuildStatus},
+    cli::VisorArgs,
+    firmware_run::FirmwareRun,
+    headless::Frontend,
+    tracing::instance::TracingInstance,
 };
 
 mod cargo;
+mod cli;
+mod config_file;
+mod csv_log;
+mod device_source;
 mod elf_file;
+mod firmware_run;
+mod headless;
+mod line_source;
+mod net_source;
+mod pipeline;
+mod prometheus;
+mod replay;
+mod self_test;
+mod source_retry;
+#[cfg(test)]
+mod test_support;
+mod throughput;
 mod tracing;
 mod visualizer;
 
-pub static FIRMWARE_ADDR_MAP: OnceLock<std::collections::HashMap<u64, String>> = OnceLock::new();
+/// Address-to-symbol map of the running firmware (replaced when the firmware is reloaded)
+pub static FIRMWARE_ADDR_MAP: RwLock<Option<std::collections::HashMap<u64, String>>> =
+    RwLock::new(None);
 
 fn main() -> anyhow::Result<()> {
     // let (trace_tx, trace_rx) = crossbeam::channel::unbounded();
@@ -30,93 +48,249 @@ fn main() -> anyhow::Result<()> {
     // TODO: STDERR not inherit (overrides TUI output!!!)
 
     let args: Vec<String> = std::env::args().collect();
+    let cli_args = VisorArgs::parse(&args[1..]).context("Invalid command line arguments")?;
+    config_file::CONFIG_FILE_PATH
+        .write()
+        .unwrap()
+        .clone_from(&cli_args.config);
+    let visor_args = match config_file::load_config_args(cli_args.config.as_deref())? {
+        Some(file_args) => cli_args.with_defaults(file_args),
+        None => cli_args,
+    };
+    if let Some(name_width) = visor_args.name_width {
+        visualizer::NAME_COLUMN_WIDTH.store(name_width, std::sync::atomic::Ordering::Relaxed);
+    }
+    visualizer::app::MERGE_EXECUTORS_BY_NAME.store(
+        visor_args.merge_executors.unwrap_or(false),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    if let Some(window_s) = visor_args.view_window_s {
+        visualizer::app::VIEW_WINDOW_S.store(window_s, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(percent) = visor_args.min_task_cpu {
+        *visualizer::filter::MIN_SIGNIFICANT_CPU_PERCENT
+            .write()
+            .unwrap() = percent;
+        visualizer::filter::HIDE_INSIGNIFICANT_TASKS
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(span_us) = visor_args.min_visible_span_us {
+        tracing::stats::heatmap::MIN_VISIBLE_SPAN_US
+            .store(span_us, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(executor_indent) = visor_args.executor_indent {
+        visualizer::EXECUTOR_INDENT.store(executor_indent, std::sync::atomic::Ordering::Relaxed);
+    }
+    visualizer::CORE_NAMES
+        .write()
+        .unwrap()
+        .extend(visor_args.core_names.iter().cloned());
+    if let Some(threshold_us) = visor_args.preemption_threshold_us {
+        tracing::executor::PREEMPTION_THRESHOLD_US
+            .store(threshold_us, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(threshold_ms) = visor_args.lost_wakeup_threshold_ms {
+        tracing::executor::LOST_WAKEUP_THRESHOLD_MS
+            .store(threshold_ms, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(threshold_ms) = visor_args.pegged_threshold_ms {
+        tracing::executor::PEGGED_THRESHOLD_MS
+            .store(threshold_ms, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(switches) = visor_args.ping_pong_min_switches {
+        tracing::executor::PING_PONG_MIN_SWITCHES
+            .store(switches, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(interval_us) = visor_args.ping_pong_max_interval_us {
+        tracing::executor::PING_PONG_MAX_SWITCH_INTERVAL_US
+            .store(interval_us, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(percent) = visor_args.core_imbalance_threshold_percent {
+        tracing::stats::core_imbalance::CORE_IMBALANCE_THRESHOLD_PERCENT
+            .store(percent, std::sync::atomic::Ordering::Relaxed);
+    }
+    {
+        let mut protocol = tracing::trace_data::TRACE_PROTOCOL.write().unwrap();
+        if let Some(prefix) = visor_args.trace_prefix.clone() {
+            protocol.prefix = prefix;
+        }
+        if let Some(suffix) = visor_args.trace_suffix.clone() {
+            protocol.suffix = suffix;
+        }
+        for (kind, name) in visor_args.event_names.iter() {
+            protocol.rename_event(*kind, name);
+        }
+    }
+    if let Some(threshold) = visor_args.parse_error_threshold {
+        *throughput::PARSE_ERROR_THRESHOLD_PERCENT.write().unwrap() = threshold;
+    }
+    if let Some(alpha) = visor_args.cpu_smoothing {
+        *visualizer::smoothing::CPU_SMOOTHING_ALPHA.write().unwrap() = alpha;
+    }
+    if let Some(attempts) = visor_args.source_max_attempts {
+        source_retry::SOURCE_MAX_ATTEMPTS.store(attempts, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(window_ms) = visor_args.cpu_window_ms {
+        tracing::stats::task_stats::CPU_WINDOW_MS
+            .store(window_ms, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(policy) = visor_args.history_retention {
+        tracing::retention::RETENTION_POLICY
+            .store(policy as u8, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(max_age_s) = visor_args.history_max_age_s {
+        tracing::instance::HISTORY_MAX_TIME_S
+            .store(max_age_s, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(max_entries) = visor_args.history_max_entries {
+        tracing::retention::HISTORY_MAX_ENTRIES
+            .store(max_entries, std::sync::atomic::Ordering::Relaxed);
+    }
+    tracing::boot::TAG_BOOT_GENERATIONS.store(
+        visor_args.boot_generations.unwrap_or(false),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    tracing::task::CONTINUE_RESPAWNED_TASKS.store(
+        visor_args.continue_respawned_tasks.unwrap_or(false),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    tracing::snapshot::STATS_SNAPSHOTS.store(
+        visor_args.stats_snapshots.unwrap_or(false),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    tracing::schema::REFUSE_SCHEMA_MISMATCH.store(
+        visor_args.refuse_schema_mismatch.unwrap_or(false),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    tracing::stats::task_stats::ACTIVE_ONLY_CPU.store(
+        visor_args.active_only_cpu.unwrap_or(false),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    visualizer::CORE_UTILIZATION_IN_TITLE.store(
+        !visor_args.hide_core_utilization.unwrap_or(false),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    if let Some(color) = visor_args.core_idle_color {
+        *visualizer::CORE_IDLE_COLOR.write().unwrap() = color;
+    }
+    *tracing::stats::category_stats::TASK_CATEGORIES
+        .write()
+        .unwrap() =
+        tracing::stats::category_stats::TaskCategory::from_patterns(&visor_args.categories);
+    if let Some(unit) = visor_args.time_unit {
+        *visualizer::time_unit::TIME_UNIT.write().unwrap() = unit;
+    }
 
-    let cargo_child_process = cargo_child::start_cargo_run(args[1..].to_vec())
-        .expect("Failed to start cargo run process");
-    let stdout_listener = cargo_child_process.get_stdout_receiver();
+    if let Some(rate) = visor_args.self_test_rate {
+        let duration_s = visor_args.self_test_duration_s.unwrap_or(5);
+        let report = self_test::run_self_test(self_test::SelfTestConfig {
+            rate,
+            duration: std::time::Duration::from_secs(duration_s),
+        });
+        println!("{}", serde_json::to_string(&report)?);
+        if report.fell_behind {
+            bail!("Trace pipeline fell behind {} events per second", rate);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &visor_args.replay {
+        if visor_args.headless.unwrap_or(false) {
+            bail!("--replay is stepped through in the TUI, it can not run --headless");
+        }
+        let replay = replay::Replay::load(path)?;
+        if let Some(warning) = firmware_run::load_firmware_symbols(None, visor_args.elf.as_deref())?
+        {
+            eprintln!("Warning: {}", warning);
+        }
+        // Trace items are only applied by stepping, nothing is received
+        let (_, trace_rx) = crossbeam::channel::unbounded();
+        return visualizer::run_replay_tui(TracingInstance::new(trace_rx), replay);
+    }
+
+    // Decide before starting the firmware, so the fallback note is not lost in its output
+    let frontend = Frontend::select(
+        visor_args.headless.unwrap_or(false) || visor_args.csv_dir.is_some(),
+        std::io::stdout().is_terminal(),
+        std::env::var("TERM").ok().as_deref(),
+    );
+    if let Frontend::Headless {
+        fallback_reason: Some(reason),
+    } = frontend
+    {
+        eprintln!(
+            "Note: {}, printing the statistics as JSON lines instead of the TUI (--headless)",
+            reason
+        );
+    }
+
+    let csv_logger = visor_args
+        .csv_dir
+        .as_deref()
+        .map(|dir| csv_log::CsvLogger::new(dir, visor_args.rotate.unwrap_or_default()))
+        .transpose()?;
 
-    let (build_tx, build_rx) = crossbeam::channel::unbounded();
     let (logs_tx, logs_recver) = crossbeam::channel::unbounded();
     let (trace_tx, trace_rx) = crossbeam::channel::unbounded();
+    let (program_tx, program_rx) = crossbeam::channel::unbounded::<String>();
     let first_trace_item_received = Arc::new(AtomicBool::new(false));
-    let first_trace_item_received_clone = first_trace_item_received.clone();
-    std::thread::spawn(move || {
-        let mut temp_buffer = Vec::new();
-        let mut cargo_build_finished = false;
-        loop {
-            match stdout_listener.recv() {
-                Ok(c) => {
-                    temp_buffer.push(c);
-
-                    // Check if '\n' is in buffer
-                    let newline_pos = temp_buffer.iter().position(|&b| b == b'\n' as u8);
-                    if let Some(pos) = newline_pos {
-                        let line = String::from_utf8(temp_buffer.drain(..=pos).collect())
-                            .unwrap_or_else(|_| String::from("<Invalid UTF-8>"));
-
-                        if !cargo_build_finished {
-                            // build output
-                            build_tx.send(line.clone()).unwrap();
-
-                            if line.contains(r#"{"reason":"build-finished","success":true}"#) {
-                                cargo_build_finished = true;
-                            }
-                        } else {
-                            // Trace or log line of program
-                            if line.contains("embassy executor tracer - ")
-                                && line.contains(" - embassy executor tracer")
-                            {
-                                // Parse Trace line
-                                let pc_timestamp = ComputerTime::now();
-                                match TraceItem::parse_from_line(&line, pc_timestamp) {
-                                    Ok(item) => {
-                                        // Send trace item
-                                        trace_tx.send(item).unwrap();
-                                        // println!("Parsed trace item: {:?}", item);
-                                    }
-                                    Err(e) => {
-                                        eprintln!("Failed to parse trace item: {:?}", e);
-                                    }
-                                }
-
-                                first_trace_item_received_clone
-                                    .store(true, std::sync::atomic::Ordering::Relaxed);
-                            } else {
-                                // Propagate log line
-                                if first_trace_item_received_clone
-                                    .load(std::sync::atomic::Ordering::Relaxed)
-                                {
-                                    logs_tx.send(line).unwrap();
-                                } else {
-                                    // Pre-trace log line, just print to console
-                                    println!("{}", line);
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(_) => {
-                    break;
-                }
-            }
-        }
-    });
+
+    let firmware_run = FirmwareRun::start(visor_args.cargo_args.clone(), program_tx.clone())?;
+    let build_rx = firmware_run.get_build_receiver();
+    let current_run = Arc::new(Mutex::new(Some(firmware_run)));
+
+    // Additional line source (e.g. semihosting output written by the debug probe)
+    if let Some(semihosting_file) = visor_args.semihosting_file {
+        line_source::follow_file_lines(semihosting_file, program_tx.clone());
+    }
+    if let Some(source) = visor_args.source.clone() {
+        net_source::spawn_net_source(source, program_tx.clone())?;
+    }
+
+    // Route program lines into trace items and log lines
+    let raw_log = match &visor_args.raw_log {
+        Some(path) => Some(pipeline::open_raw_log(path)?),
+        None => None,
+    };
+    device_source::spawn_device_sources(
+        &visor_args.devices,
+        &trace_tx,
+        &logs_tx,
+        &first_trace_item_received,
+    )?;
+    pipeline::route_program_lines(
+        tracing::device::PRIMARY_DEVICE_ID,
+        program_rx.clone(),
+        trace_tx,
+        logs_tx,
+        first_trace_item_received.clone(),
+        raw_log,
+    );
 
     // handle cargo build
     let build_status = cargo_build::handle_cargo_build(&build_rx);
     match build_status {
-        CargoBuildStatus::Success(Some(elf_path)) => {
-            // read elf file and create address map
-            let bin_data = fs::read(elf_path).expect("Konnte ELF-Datei nicht lesen");
-            let file: object::File<'_> =
-                object::File::parse(&*bin_data).expect("Konnte ELF-Format nicht parsen");
-            let addr_map = elf_file::get_addr_map(file);
-            FIRMWARE_ADDR_MAP.set(addr_map).unwrap();
-        }
-        CargoBuildStatus::Success(None) => {
-            println!("Build succeeded! No executable path found.");
+        CargoBuildStatus::Success(elf_path) => {
+            if elf_path.is_none() && visor_args.elf.is_none() {
+                println!(
+                    "Build succeeded! No executable path found, executors and tasks are named by their ids."
+                );
+                elf_file::MISSING_ADDR_MAP_WARNING.suppress();
+            }
+
+            // read elf file (or the one given with --elf) and create address map
+            let warning = firmware_run::load_firmware_symbols(
+                elf_path.as_deref().map(std::path::Path::new),
+                visor_args.elf.as_deref(),
+            )?;
+            if let Some(warning) = warning {
+                eprintln!("Warning: {}", warning);
+            }
         }
-        CargoBuildStatus::Failed => {
+        CargoBuildStatus::Failed(messages) => {
+            for message in messages {
+                eprint!("{}", message);
+            }
             eprintln!("Build failed!");
             bail!("Build process failed");
         }
@@ -139,17 +313,76 @@ fn main() -> anyhow::Result<()> {
     // }
 
     // wait for first trace item
+    let mut announced_waiting = false;
     loop {
         if first_trace_item_received.load(std::sync::atomic::Ordering::Relaxed) {
             break;
         }
+        match source_retry::source_availability() {
+            Some(source_retry::SourceAvailability::GaveUp(message)) => bail!("{}", message),
+            Some(source_retry::SourceAvailability::Waiting(message)) if !announced_waiting => {
+                eprintln!("{}", message);
+                announced_waiting = true;
+            }
+            _ => {}
+        }
 
         std::thread::sleep(std::time::Duration::from_millis(10));
     }
 
     // run executor steps
-    let instance = TracingInstance::new(trace_rx);
-    visualizer::run_main_tui(instance, logs_recver).context("Failed running TUI")?;
+    let instance = TracingInstance::new(trace_rx.clone());
+    if let Some(addr) = visor_args.prometheus {
+        prometheus::spawn_prometheus_exporter(addr, instance.clone())?;
+    }
+    if let Some(path) = visor_args.dashboard_file.clone() {
+        let interval = visor_args.dashboard_interval_ms.map_or(
+            visualizer::dashboard_file::DEFAULT_DASHBOARD_INTERVAL,
+            std::time::Duration::from_millis,
+        );
+        visualizer::dashboard_file::spawn_dashboard_writer(path, interval, instance.clone())?;
+    }
+
+    // Headless runs end with an error when the trace was unreliable (the TUI shows it live)
+    let parse_error_threshold_exceeded = if frontend == Frontend::Tui {
+        // Rebuild and restart the firmware on request of the TUI (hot reload)
+        let (reload_tx, reload_rx) = crossbeam::channel::unbounded();
+        let (status_tx, status_rx) = crossbeam::channel::unbounded();
+        let marker_tx = program_tx.clone();
+        let channels = firmware_run::RunChannels {
+            program_tx,
+            program_rx,
+            trace_rx,
+        };
+        let _ = firmware_run::run_reload_supervisor(
+            visor_args.cargo_args.clone(),
+            channels,
+            instance.clone(),
+            current_run.clone(),
+            reload_rx,
+            status_tx,
+            visor_args.elf.clone(),
+        );
+        let reload_handle = visualizer::FirmwareReloadHandle {
+            reload_tx,
+            status_recver: status_rx,
+            program_tx: marker_tx,
+        };
+
+        visualizer::run_main_tui(instance.clone(), logs_recver, reload_handle)
+            .context("Failed running TUI")?;
+        false
+    } else {
+        // No reloads, the log lines end with the firmware run
+        drop(program_tx);
+        headless::run_headless(instance.clone(), logs_recver, csv_logger)
+            .context("Failed running headless")?
+    };
+
+    if visor_args.summary.unwrap_or(false) {
+        visualizer::summary::write_summary(&instance.get_stats(), &mut std::io::stdout().lock())
+            .context("Failed writing the summary")?;
+    }
 
     // pipe output to visualizer
 
@@ -159,8 +392,11 @@ fn main() -> anyhow::Result<()> {
 
     // show other logs
 
-    cargo_child_process
-        .kill()
-        .context("Tried killing Cargo Run Child Process")?;
+    if let Some(firmware_run) = current_run.lock().unwrap().take() {
+        firmware_run.kill()?;
+    }
+    if parse_error_threshold_exceeded {
+        bail!("Too many lines failed to parse (see --parse-error-threshold)");
+    }
     Ok(())
 }
@@ -0,0 +1,493 @@
+//! Trace/log lines streamed from a remote device over MQTT or WebSocket (`--source`).
+//!
+//! The clients are only built with the `net-sources` feature; parsing the source URL,
+//! selecting the device and reconnecting are independent of the transport (and tested
+//! without it).
+
+use std::{sync::RwLock, thread::JoinHandle, time::Duration};
+
+use anyhow::bail;
+use crossbeam::channel::Sender;
+
+#[cfg(any(feature = "net-sources", test))]
+use crate::{source_retry::Backoff, throughput::SOURCE_THROUGHPUT};
+
+/// Connection status of the network source, shown in the log pane title
+pub static NET_SOURCE_STATUS: RwLock<Option<NetSourceStatus>> = RwLock::new(None);
+
+/// Delay before the first reconnection attempt, doubled up to [`MAX_RECONNECT_DELAY`]
+#[cfg(feature = "net-sources")]
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+#[cfg(feature = "net-sources")]
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetSourceKind {
+    Mqtt,
+    WebSocket,
+}
+
+/// Parsed `--source` URL: `mqtt://host[:port]/topic` or `ws://host[:port]/path`,
+/// optionally followed by `?device=<name>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetSourceUrl {
+    pub kind: NetSourceKind,
+    pub host: String,
+    pub port: u16,
+    /// MQTT topic filter (may contain wildcards) or WebSocket path
+    pub path: String,
+    /// Only lines of this device are used (matched against a topic level or the `device` field)
+    pub device: Option<String>,
+}
+
+impl NetSourceUrl {
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        let (kind, rest, default_port) = if let Some(rest) = url.strip_prefix("mqtt://") {
+            (NetSourceKind::Mqtt, rest, 1883)
+        } else if let Some(rest) = url.strip_prefix("ws://") {
+            (NetSourceKind::WebSocket, rest, 80)
+        } else {
+            bail!("Unsupported source '{}', expected mqtt://… or ws://…", url);
+        };
+
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let mut device = None;
+        for param in query.into_iter().flat_map(|q| q.split('&')) {
+            match param.split_once('=') {
+                Some(("device", name)) if !name.is_empty() => device = Some(name.to_string()),
+                _ => bail!("Unsupported source parameter '{}' in '{}'", param, url),
+            }
+        }
+
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (host, port) = match authority.split_once(':') {
+            Some((host, port)) => match port.parse::<u16>() {
+                Ok(port) => (host, port),
+                Err(_) => bail!("Invalid port '{}' in '{}'", port, url),
+            },
+            None => (authority, default_port),
+        };
+        if host.is_empty() {
+            bail!("Missing host in '{}'", url);
+        }
+
+        let path = match kind {
+            NetSourceKind::Mqtt if path.is_empty() => bail!("Missing MQTT topic in '{}'", url),
+            NetSourceKind::Mqtt => path.to_string(),
+            NetSourceKind::WebSocket => format!("/{}", path),
+        };
+
+        Ok(Self {
+            kind,
+            host: host.to_string(),
+            port,
+            path,
+            device,
+        })
+    }
+
+    /// Short name for the status line
+    pub fn label(&self) -> String {
+        match self.kind {
+            NetSourceKind::Mqtt => format!("mqtt://{}/{}", self.host, self.path),
+            NetSourceKind::WebSocket => format!("ws://{}{}", self.host, self.path),
+        }
+    }
+}
+
+/// Only set by the clients, but read by the views of every build
+#[cfg_attr(not(any(feature = "net-sources", test)), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetSourceStatus {
+    Connecting,
+    Connected,
+    Reconnecting {
+        error: String,
+        retry_in: Duration,
+    },
+    /// Connection failed `--source-max-attempts` times in a row
+    GaveUp {
+        error: String,
+    },
+}
+
+/// Message received from the broker/server
+#[cfg(any(feature = "net-sources", test))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetMessage {
+    /// MQTT topic the message was published on (none for WebSocket)
+    pub topic: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+/// Transport of a network source
+#[cfg(any(feature = "net-sources", test))]
+pub trait MessageSource {
+    /// (Re)connect, dropping a previous connection
+    fn connect(&mut self) -> anyhow::Result<()>;
+
+    /// Wait for the next message. An error means the connection is lost.
+    fn next_message(&mut self) -> anyhow::Result<NetMessage>;
+}
+
+/// Lines of a message, or none if it was published by another device than `device`.
+/// The payload is either plain text (one or more lines) or a JSON object
+/// `{"device": "...", "line": "..."}`.
+#[cfg(any(feature = "net-sources", test))]
+pub fn message_lines(message: &NetMessage, device: Option<&str>) -> Vec<String> {
+    let payload = String::from_utf8_lossy(&message.payload);
+
+    let json = serde_json::from_str::<serde_json::Value>(&payload).ok();
+    let (text, field_device) = match json.as_ref().and_then(|v| v.get("line")?.as_str()) {
+        Some(line) => (line, json.as_ref().and_then(|v| v.get("device")?.as_str())),
+        None => (payload.as_ref(), None),
+    };
+
+    if let Some(device) = device {
+        let topic_matches = message
+            .topic
+            .as_deref()
+            .is_some_and(|topic| topic.split('/').any(|level| level == device));
+        if !topic_matches && field_device != Some(device) {
+            return Vec::new();
+        }
+    }
+
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(any(feature = "net-sources", test))]
+fn set_status(status: NetSourceStatus) {
+    *NET_SOURCE_STATUS.write().unwrap() = Some(status);
+}
+
+/// Forward the lines of every received message to the channel, reconnecting (with growing
+/// delay) whenever the connection fails. Returns once the receiver has been dropped or the
+/// backoff gave up.
+#[cfg(any(feature = "net-sources", test))]
+pub fn pump_messages(
+    mut source: impl MessageSource,
+    device: Option<&str>,
+    sender: &Sender<String>,
+    mut backoff: Backoff,
+) {
+    loop {
+        set_status(NetSourceStatus::Connecting);
+        let error = match source.connect() {
+            Ok(()) => {
+                set_status(NetSourceStatus::Connected);
+                backoff.succeeded();
+                loop {
+                    match source.next_message() {
+                        Ok(message) => {
+                            SOURCE_THROUGHPUT.add_bytes(message.payload.len());
+                            for line in message_lines(&message, device) {
+                                if sender.send(line).is_err() {
+                                    return; // Receiver has been dropped
+                                }
+                            }
+                        }
+                        Err(e) => break e,
+                    }
+                }
+            }
+            Err(e) => e,
+        };
+
+        let Some(delay) = backoff.failed() else {
+            set_status(NetSourceStatus::GaveUp {
+                error: error.to_string(),
+            });
+            let _ = sender.send(format!(
+                "Warning: giving up on the remote source after {} failed attempts: {}",
+                backoff.failed_attempts(),
+                error
+            ));
+            return;
+        };
+        set_status(NetSourceStatus::Reconnecting {
+            error: error.to_string(),
+            retry_in: delay,
+        });
+        std::thread::sleep(delay);
+    }
+}
+
+/// Start receiving lines from the network source in the background
+pub fn spawn_net_source(
+    url: NetSourceUrl,
+    sender: Sender<String>,
+) -> anyhow::Result<JoinHandle<()>> {
+    #[cfg(feature = "net-sources")]
+    {
+        Ok(std::thread::spawn(move || {
+            if sender
+                .send(format!("Receiving lines from {}", url.label()))
+                .is_err()
+            {
+                return;
+            }
+            let device = url.device.clone();
+            match url.kind {
+                NetSourceKind::Mqtt => pump_messages(
+                    clients::MqttSource::new(url),
+                    device.as_deref(),
+                    &sender,
+                    Backoff::new(RECONNECT_DELAY, MAX_RECONNECT_DELAY),
+                ),
+                NetSourceKind::WebSocket => pump_messages(
+                    clients::WebSocketSource::new(url),
+                    device.as_deref(),
+                    &sender,
+                    Backoff::new(RECONNECT_DELAY, MAX_RECONNECT_DELAY),
+                ),
+            }
+        }))
+    }
+
+    #[cfg(not(feature = "net-sources"))]
+    {
+        let _ = sender;
+        bail!(
+            "Source '{}' requires embassy-visor built with the `net-sources` feature",
+            url.label()
+        )
+    }
+}
+
+#[cfg(feature = "net-sources")]
+mod clients {
+    use std::{net::TcpStream, time::Duration};
+
+    use anyhow::{Context, bail};
+    use rumqttc::{Client, Connection, Event, MqttOptions, Packet, QoS};
+    use tungstenite::{Message, WebSocket, stream::MaybeTlsStream};
+
+    use super::{MessageSource, NetMessage, NetSourceUrl};
+
+    pub struct MqttSource {
+        url: NetSourceUrl,
+        connection: Option<(Client, Connection)>,
+    }
+
+    impl MqttSource {
+        pub fn new(url: NetSourceUrl) -> Self {
+            Self {
+                url,
+                connection: None,
+            }
+        }
+    }
+
+    impl MessageSource for MqttSource {
+        fn connect(&mut self) -> anyhow::Result<()> {
+            let client_id = format!("embassy-visor-{}", std::process::id());
+            let mut options = MqttOptions::new(client_id, self.url.host.clone(), self.url.port);
+            options.set_keep_alive(Duration::from_secs(10));
+
+            let (client, mut connection) = Client::new(options, 64);
+            client.subscribe(self.url.path.clone(), QoS::AtMostOnce)?;
+
+            // Wait for the broker to accept the connection
+            loop {
+                match connection.recv().ok().context("MQTT event loop stopped")? {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => break,
+                    Ok(_) => {}
+                    Err(e) => bail!("MQTT connection failed: {}", e),
+                }
+            }
+
+            self.connection = Some((client, connection));
+            Ok(())
+        }
+
+        fn next_message(&mut self) -> anyhow::Result<NetMessage> {
+            let (_, connection) = self.connection.as_mut().context("Not connected")?;
+            loop {
+                match connection.recv().ok().context("MQTT event loop stopped")? {
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        return Ok(NetMessage {
+                            topic: Some(publish.topic),
+                            payload: publish.payload.to_vec(),
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        self.connection = None;
+                        bail!("MQTT connection lost: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    pub struct WebSocketSource {
+        url: NetSourceUrl,
+        socket: Option<WebSocket<MaybeTlsStream<TcpStream>>>,
+    }
+
+    impl WebSocketSource {
+        pub fn new(url: NetSourceUrl) -> Self {
+            Self { url, socket: None }
+        }
+    }
+
+    impl MessageSource for WebSocketSource {
+        fn connect(&mut self) -> anyhow::Result<()> {
+            let url = format!("ws://{}:{}{}", self.url.host, self.url.port, self.url.path);
+            let (socket, _) = tungstenite::connect(url.as_str())
+                .with_context(|| format!("WebSocket connection to {} failed", url))?;
+            self.socket = Some(socket);
+            Ok(())
+        }
+
+        fn next_message(&mut self) -> anyhow::Result<NetMessage> {
+            let socket = self.socket.as_mut().context("Not connected")?;
+            loop {
+                let payload = match socket.read() {
+                    Ok(Message::Text(text)) => text.into_bytes(),
+                    Ok(Message::Binary(data)) => data,
+                    Ok(Message::Close(_)) => {
+                        self.socket = None;
+                        bail!("WebSocket closed by the server");
+                    }
+                    // Pings are answered by tungstenite
+                    Ok(_) => continue,
+                    Err(e) => {
+                        self.socket = None;
+                        bail!("WebSocket connection lost: {}", e);
+                    }
+                };
+                return Ok(NetMessage {
+                    topic: None,
+                    payload,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    /// Scripted transport: every entry is a connection attempt with the messages it receives.
+    /// `None` fails the attempt, the end of the messages loses the connection.
+    /// Once the script is done, "end" lines are sent until the receiver is dropped.
+    struct MockSource {
+        script: VecDeque<Option<Vec<NetMessage>>>,
+        messages: VecDeque<NetMessage>,
+    }
+
+    impl MessageSource for MockSource {
+        fn connect(&mut self) -> anyhow::Result<()> {
+            match self.script.pop_front() {
+                Some(Some(messages)) => {
+                    self.messages = messages.into();
+                    Ok(())
+                }
+                Some(None) => Err(anyhow!("connection refused")),
+                None => Ok(()),
+            }
+        }
+
+        fn next_message(&mut self) -> anyhow::Result<NetMessage> {
+            match self.messages.pop_front() {
+                Some(message) => Ok(message),
+                None if self.script.is_empty() => Ok(text_message(Some("dev42"), "end")),
+                None => Err(anyhow!("connection lost")),
+            }
+        }
+    }
+
+    fn text_message(topic: Option<&str>, payload: &str) -> NetMessage {
+        NetMessage {
+            topic: topic.map(str::to_string),
+            payload: payload.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let url = NetSourceUrl::parse("mqtt://broker/fleet/+/trace?device=dev42").unwrap();
+        assert_eq!(url.kind, NetSourceKind::Mqtt);
+        assert_eq!(url.host, "broker");
+        assert_eq!(url.port, 1883);
+        assert_eq!(url.path, "fleet/+/trace");
+        assert_eq!(url.device.as_deref(), Some("dev42"));
+
+        let url = NetSourceUrl::parse("ws://localhost:8080/trace").unwrap();
+        assert_eq!(url.kind, NetSourceKind::WebSocket);
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.path, "/trace");
+        assert_eq!(url.device, None);
+
+        assert!(NetSourceUrl::parse("mqtt://broker").is_err());
+        assert!(NetSourceUrl::parse("mqtt://broker:x/topic").is_err());
+        assert!(NetSourceUrl::parse("ws://host/?core=1").is_err());
+        assert!(NetSourceUrl::parse("http://host/trace").is_err());
+    }
+
+    #[test]
+    fn test_message_lines_device() {
+        let message = text_message(Some("fleet/dev42/trace"), "line 1\nline 2\n");
+        assert_eq!(message_lines(&message, None), vec!["line 1", "line 2"]);
+        assert_eq!(message_lines(&message, Some("dev42")).len(), 2);
+        assert!(message_lines(&message, Some("dev7")).is_empty());
+
+        let message = text_message(
+            None,
+            r#"{"device": "dev7", "line": "[1, 0, TaskNew, 1, 2]"}"#,
+        );
+        assert_eq!(
+            message_lines(&message, Some("dev7")),
+            vec!["[1, 0, TaskNew, 1, 2]"]
+        );
+        assert!(message_lines(&message, Some("dev42")).is_empty());
+    }
+
+    #[test]
+    fn test_pump_reconnects() {
+        let source = MockSource {
+            script: VecDeque::from([
+                None,
+                Some(vec![
+                    text_message(Some("fleet/dev42/trace"), "first"),
+                    text_message(Some("fleet/dev7/trace"), "other device"),
+                ]),
+                None,
+                Some(vec![text_message(Some("fleet/dev42/trace"), "second")]),
+            ]),
+            messages: VecDeque::new(),
+        };
+
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let pump = std::thread::spawn(move || {
+            let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(1))
+                .with_max_attempts(0);
+            pump_messages(source, Some("dev42"), &sender, backoff)
+        });
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), "first");
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), "second");
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), "end");
+        assert_eq!(
+            *NET_SOURCE_STATUS.read().unwrap(),
+            Some(NetSourceStatus::Connected)
+        );
+
+        drop(receiver);
+        pump.join().unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+//! Routing of the program output (every line source) into trace items and log lines.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::Context;
+use crossbeam::channel::{Receiver, Sender};
+
+use crate::{
+    throughput::{SOURCE_THROUGHPUT, ThroughputCounters},
+    tracing::{
+        compact::CompactDecoder,
+        device::{PRIMARY_DEVICE_ID, device_name},
+        time::ComputerTime,
+        trace_data::{TRACE_PROTOCOL, TraceItemType, TraceParseResult, TraceProtocol},
+    },
+};
+
+/// Destination of the raw program lines (`--raw-log`)
+pub type RawLineSink = Box<dyn Write + Send>;
+
+/// Line the line sources forward in place of a line that is not valid UTF-8
+pub const INVALID_UTF8_LINE: &str = "<Invalid UTF-8>";
+
+/// Create (or truncate) the raw line log file
+pub fn open_raw_log(path: &Path) -> anyhow::Result<RawLineSink> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed creating raw log file {}", path.display()))?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Write the line unchanged (terminated by a newline) into the raw line log
+fn record_raw_line(raw_log: &mut RawLineSink, line: &str, flush: bool) -> std::io::Result<()> {
+    raw_log.write_all(line.as_bytes())?;
+    if !line.ends_with('\n') {
+        raw_log.write_all(b"\n")?;
+    }
+    if flush {
+        raw_log.flush()?;
+    }
+    Ok(())
+}
+
+/// Whether a log line is readable text. Invalid UTF-8 and control characters (other than
+/// whitespace and the escape of ANSI colors) come from a corrupted link or a baud rate mismatch.
+fn is_readable_log_line(line: &str) -> bool {
+    line != INVALID_UTF8_LINE
+        && !line.chars().any(|c| {
+            c == char::REPLACEMENT_CHARACTER || (c.is_control() && !"\t\r\n\x1b".contains(c))
+        })
+}
+
+/// Count a routed line: a trace line fails if any of its records fails to parse, a log line
+/// if it is not readable (`parse_results` is None for log lines)
+fn count_line(
+    counters: &ThroughputCounters,
+    line: &str,
+    parse_results: Option<&[TraceParseResult]>,
+) {
+    let failed = match parse_results {
+        Some(results) => results.iter().any(|result| result.is_err()),
+        None => !is_readable_log_line(line),
+    };
+    counters.add_line(failed);
+}
+
+/// Parser of the trace lines of one line stream, in the order they were received (compact
+/// records depend on the ones before, the beacon's header on the timestamps after it)
+#[derive(Debug)]
+pub struct TraceLineParser {
+    protocol: TraceProtocol,
+    compact_decoder: CompactDecoder,
+}
+
+impl TraceLineParser {
+    /// Parser with the protocol configured at startup
+    pub fn new() -> Self {
+        Self {
+            protocol: TRACE_PROTOCOL.read().unwrap().clone(),
+            compact_decoder: CompactDecoder::default(),
+        }
+    }
+
+    /// Parse the records of a trace line (may contain several batched records), None for a
+    /// log line
+    pub fn parse(
+        &mut self,
+        line: &str,
+        pc_timestamp: ComputerTime,
+    ) -> Option<Vec<TraceParseResult>> {
+        if !self.protocol.is_trace_line(line) {
+            return None;
+        }
+
+        let parse_results = self
+            .compact_decoder
+            .parse_line(&self.protocol, line, pc_timestamp);
+        for trace_item in parse_results.iter().flatten() {
+            if let TraceItemType::Header { header } = &trace_item.data {
+                self.protocol.apply_header(header);
+            }
+        }
+        Some(parse_results)
+    }
+}
+
+/// Route program lines of the device into trace items and log lines.
+/// Log lines before the first trace item are printed to the console (the TUI is not running yet).
+/// Every line is also recorded unparsed into the raw line log (if given) and counted as failed
+/// if it is neither a parseable trace line nor a readable log line. Trace items of other
+/// devices than the primary one are tagged with the device, their log lines prefixed with its
+/// name.
+pub fn route_program_lines(
+    device_id: u32,
+    program_rx: Receiver<String>,
+    trace_tx: Sender<TraceParseResult>,
+    logs_tx: Sender<String>,
+    first_trace_item_received: Arc<AtomicBool>,
+    mut raw_log: Option<RawLineSink>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        // Lines of every line source of the device go through one parser
+        let mut parser = TraceLineParser::new();
+        let log_prefix =
+            (device_id != PRIMARY_DEVICE_ID).then(|| format!("[{}] ", device_name(device_id)));
+
+        // Loop ends when all line sources are closed
+        while let Ok(line) = program_rx.recv() {
+            // Only flush once no more lines are pending, to keep the hot path buffered
+            if let Some(sink) = raw_log.as_mut()
+                && record_raw_line(sink, &line, program_rx.is_empty()).is_err()
+            {
+                raw_log = None; // Stop recording (e.g. disk full), routing goes on
+            }
+
+            // Parse errors are forwarded too and counted for the diagnostics view
+            if let Some(mut parse_results) = parser.parse(&line, ComputerTime::now()) {
+                SOURCE_THROUGHPUT
+                    .add_trace_line(parse_results.iter().any(|result| result.is_err()));
+
+                count_line(&SOURCE_THROUGHPUT, &line, Some(&parse_results));
+                for trace_item in parse_results.iter_mut().flatten() {
+                    trace_item.device_id = device_id;
+                }
+                if parse_results
+                    .into_iter()
+                    .any(|parse_result| trace_tx.send(parse_result).is_err())
+                {
+                    break; // Tracing instance stopped
+                }
+
+                first_trace_item_received.store(true, Ordering::Relaxed);
+            } else {
+                count_line(&SOURCE_THROUGHPUT, &line, None);
+                let line = match &log_prefix {
+                    Some(prefix) => format!("{}{}", prefix, line),
+                    None => line,
+                };
+
+                // Propagate log line
+                if first_trace_item_received.load(Ordering::Relaxed) {
+                    if logs_tx.send(line).is_err() {
+                        break; // TUI stopped
+                    }
+                } else {
+                    // Pre-trace log line, just print to console
+                    println!("{}", line);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex, atomic::AtomicBool},
+    };
+
+    use crate::{
+        throughput::{ThroughputCounters, ThroughputRate},
+        tracing::{compact::CompactDecoder, time::ComputerTime, trace_data::TraceProtocol},
+    };
+
+    use super::{INVALID_UTF8_LINE, PRIMARY_DEVICE_ID, count_line, route_program_lines};
+
+    /// Raw line sink writing into memory
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_raw_log_captures_input_lines() {
+        let lines = [
+            "INFO  booting\n",
+            "embassy executor tracer - [10, 0, TaskNew, 1, 42] - embassy executor tracer\n",
+            "embassy executor tracer - [x, 0, TaskNew, 1, 43] - embassy executor tracer\n",
+            "\n",
+            "WARN  no newline",
+        ];
+
+        let (program_tx, program_rx) = crossbeam::channel::unbounded();
+        let (trace_tx, _trace_rx) = crossbeam::channel::unbounded();
+        let (logs_tx, _logs_rx) = crossbeam::channel::unbounded();
+        let raw_log = SharedBuffer::default();
+
+        let router = route_program_lines(
+            PRIMARY_DEVICE_ID,
+            program_rx,
+            trace_tx,
+            logs_tx,
+            Arc::new(AtomicBool::new(true)),
+            Some(Box::new(raw_log.clone())),
+        );
+        for line in lines {
+            program_tx.send(line.to_string()).unwrap();
+        }
+        drop(program_tx);
+        router.join().unwrap();
+
+        let recorded = String::from_utf8(raw_log.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(recorded, format!("{}\n", lines.concat()));
+    }
+
+    #[test]
+    fn test_parse_error_ratio_threshold() {
+        let protocol = TraceProtocol::default();
+        let counters = ThroughputCounters::new();
+        let mut decoder = CompactDecoder::default();
+        // Failing line percent of the routed stream, against a threshold of 10%
+        let mut route = |lines: &[String]| {
+            let mut earlier = counters.sample();
+            earlier.taken_at = ComputerTime::from_s(0);
+            for line in lines {
+                let parse_results = protocol
+                    .is_trace_line(line)
+                    .then(|| decoder.parse_line(&protocol, line, ComputerTime::now()));
+                count_line(&counters, line, parse_results.as_deref());
+            }
+            let mut later = counters.sample();
+            later.taken_at = ComputerTime::from_s(1);
+            let rate = ThroughputRate::between(&earlier, &later);
+            (
+                rate.failed_line_percent,
+                rate.exceeds_parse_error_threshold(10.0),
+            )
+        };
+        let trace = |records: &str| {
+            format!(
+                "embassy executor tracer - {} - embassy executor tracer\n",
+                records
+            )
+        };
+
+        // 1 of 20 lines broken --> below the threshold
+        let mut lines = vec![trace("[10, 0, TaskNew, 1, 42]"); 15];
+        lines.extend(vec!["INFO  sensor read\n".to_string(); 4]);
+        lines.push(trace("[x, 0, TaskNew, 1, 42]"));
+        assert_eq!(route(&lines), (5.0, false));
+
+        // baud rate mismatch: garbage, invalid UTF-8 and misformatted trace records
+        let mut lines = vec![trace("[10, 0, TaskNew, 1, 42]"); 6];
+        lines.extend(vec!["\u{1}\u{FFFD}x\u{7f}\n".to_string(); 2]);
+        lines.push(INVALID_UTF8_LINE.to_string());
+        lines.push(trace("[20, 0, TaskNew 1 42]"));
+        assert_eq!(route(&lines), (40.0, true));
+
+        // colored and empty log lines are readable
+        let lines = [
+            "\u{1b}[32mINFO\u{1b}[0m\tready\r\n".to_string(),
+            "\n".to_string(),
+        ];
+        assert_eq!(route(&lines), (0.0, false));
+    }
+}
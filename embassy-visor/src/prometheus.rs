@@ -0,0 +1,266 @@
+//! Prometheus exporter (`--prometheus <ADDR>`): the current statistics are served as metrics in
+//! the Prometheus text format on `http://ADDR/metrics`, refreshed every second, so the visor can
+//! be scraped by an existing monitoring. The exporter is only built with the `prometheus`
+//! feature; the rendering of the metrics is also tested without it.
+
+#[cfg(any(feature = "prometheus", test))]
+use std::fmt::Write;
+use std::{net::SocketAddr, thread::JoinHandle};
+
+#[cfg(not(feature = "prometheus"))]
+use anyhow::bail;
+
+use crate::tracing::instance::TracingInstance;
+#[cfg(any(feature = "prometheus", test))]
+use crate::{tracing::stats::instance_stats::InstanceStats, visualizer::core_display_name};
+
+/// Escape a label value (backslash, double quote and line feed)
+#[cfg(any(feature = "prometheus", test))]
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Start a gauge with its help and type lines
+#[cfg(any(feature = "prometheus", test))]
+fn gauge_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+/// Render the statistics as Prometheus metrics (text format 0.0.4)
+#[cfg(any(feature = "prometheus", test))]
+pub fn render_metrics(stats: &InstanceStats) -> String {
+    let mut out = String::new();
+
+    gauge_header(
+        &mut out,
+        "embassy_core_cpu_percent",
+        "CPU utilization of the core in percent",
+    );
+    for core in stats.core_stats.iter() {
+        let _ = writeln!(
+            out,
+            "embassy_core_cpu_percent{{core=\"{}\",core_id=\"{}\"}} {}",
+            escape_label(&core_display_name(core.device_id, core.core_id)),
+            core.core_id,
+            core.cpu_utilization_percent
+        );
+    }
+
+    gauge_header(
+        &mut out,
+        "embassy_executor_cpu_percent",
+        "CPU utilization of the executor in percent",
+    );
+    for core in stats.core_stats.iter() {
+        let core_name = escape_label(&core_display_name(core.device_id, core.core_id));
+        for executor in core.executors.iter() {
+            let _ = writeln!(
+                out,
+                "embassy_executor_cpu_percent{{core=\"{}\",executor=\"{}\",executor_id=\"0x{:X}\"}} {}",
+                core_name,
+                escape_label(&executor.name),
+                executor.executor_id,
+                executor.cpu_utilization_percent
+            );
+        }
+    }
+
+    gauge_header(
+        &mut out,
+        "embassy_task_cpu_percent",
+        "CPU utilization of the task in percent",
+    );
+    for core in stats.core_stats.iter() {
+        let core_name = escape_label(&core_display_name(core.device_id, core.core_id));
+        for executor in core.executors.iter() {
+            let executor_name = escape_label(&executor.name);
+            for task in executor.tasks.iter() {
+                let _ = writeln!(
+                    out,
+                    "embassy_task_cpu_percent{{core=\"{}\",executor=\"{}\",task=\"{}\",task_id=\"0x{:X}\"}} {}",
+                    core_name,
+                    executor_name,
+                    escape_label(&task.name),
+                    task.task_id,
+                    task.cpu_utilization_percent
+                );
+            }
+        }
+    }
+
+    gauge_header(&mut out, "embassy_task_count", "Number of traced tasks");
+    let _ = writeln!(out, "embassy_task_count {}", stats.tasks_count);
+    gauge_header(
+        &mut out,
+        "embassy_executor_count",
+        "Number of traced executors",
+    );
+    let _ = writeln!(out, "embassy_executor_count {}", stats.executor_count);
+
+    out
+}
+
+/// Serve the metrics of the instance on the address in the background
+pub fn spawn_prometheus_exporter(
+    addr: SocketAddr,
+    instance: TracingInstance,
+) -> anyhow::Result<JoinHandle<()>> {
+    #[cfg(feature = "prometheus")]
+    {
+        server::spawn(addr, instance)
+    }
+
+    #[cfg(not(feature = "prometheus"))]
+    {
+        let _ = instance;
+        bail!(
+            "--prometheus {} requires embassy-visor built with the `prometheus` feature",
+            addr
+        )
+    }
+}
+
+#[cfg(feature = "prometheus")]
+mod server {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::{Arc, RwLock},
+        thread::JoinHandle,
+        time::Duration,
+    };
+
+    use anyhow::Context;
+
+    use super::render_metrics;
+    use crate::tracing::instance::TracingInstance;
+
+    /// Interval the served metrics are refreshed at
+    const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// A scraper not sending its request within this time is dropped
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn spawn(addr: SocketAddr, instance: TracingInstance) -> anyhow::Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("Failed to serve the Prometheus metrics on {}", addr))?;
+
+        // Scrapes get the text of the latest refresh, so they never wait for the statistics
+        let metrics = Arc::new(RwLock::new(render_metrics(&instance.latest_stats())));
+        let refreshed = metrics.clone();
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(REFRESH_INTERVAL);
+                let text = render_metrics(&instance.latest_stats());
+                *refreshed.write().unwrap() = text;
+            }
+        });
+
+        Ok(std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let text = metrics.read().unwrap().clone();
+                // A broken connection only affects this scrape
+                let _ = respond(stream, &text);
+            }
+        }))
+    }
+
+    /// Answer a single HTTP request: the metrics on `/metrics` (and `/`), 404 otherwise
+    fn respond(stream: TcpStream, metrics: &str) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Skip the headers
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+        let (status, body) = match path {
+            "/" | "/metrics" => ("200 OK", metrics),
+            _ => (
+                "404 Not Found",
+                "Not found, the metrics are served on /metrics\n",
+            ),
+        };
+        let mut stream = &stream;
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support::{executor_stats, task_stats},
+        tracing::stats::{
+            core_stats::CoreStats, executor_stats::ExecutorStats, task_stats::TaskStats,
+        },
+    };
+
+    #[test]
+    fn test_render_metrics() {
+        let tasks = [(0xA, "blink", 12.5), (0xB, "say \"hi\"\\", 0.25)].map(
+            |(task_id, name, cpu_utilization_percent)| TaskStats {
+                cpu_utilization_percent,
+                ..task_stats(task_id, name)
+            },
+        );
+        let executor = ExecutorStats {
+            cpu_utilization_percent: 12.75,
+            ..executor_stats(0x1000, 0, "main_executor", Vec::from(tasks))
+        };
+        let stats = InstanceStats {
+            core_stats: vec![
+                CoreStats {
+                    core_id: 0,
+                    device_id: 0,
+                    executors: vec![executor],
+                    cpu_utilization_percent: 12.75,
+                    preemption_depth: 0,
+                    asleep: false,
+                    undeclared: false,
+                },
+                CoreStats::empty(1),
+            ],
+            tasks_count: 2,
+            executor_count: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_metrics(&stats),
+            r#"# HELP embassy_core_cpu_percent CPU utilization of the core in percent
+# TYPE embassy_core_cpu_percent gauge
+embassy_core_cpu_percent{core="Core 0",core_id="0"} 12.75
+embassy_core_cpu_percent{core="Core 1",core_id="1"} 0
+# HELP embassy_executor_cpu_percent CPU utilization of the executor in percent
+# TYPE embassy_executor_cpu_percent gauge
+embassy_executor_cpu_percent{core="Core 0",executor="main_executor",executor_id="0x1000"} 12.75
+# HELP embassy_task_cpu_percent CPU utilization of the task in percent
+# TYPE embassy_task_cpu_percent gauge
+embassy_task_cpu_percent{core="Core 0",executor="main_executor",task="blink",task_id="0xA"} 12.5
+embassy_task_cpu_percent{core="Core 0",executor="main_executor",task="say \"hi\"\\",task_id="0xB"} 0.25
+# HELP embassy_task_count Number of traced tasks
+# TYPE embassy_task_count gauge
+embassy_task_count 2
+# HELP embassy_executor_count Number of traced executors
+# TYPE embassy_executor_count gauge
+embassy_executor_count 1
+"#
+        );
+    }
+}
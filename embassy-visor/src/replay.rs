@@ -0,0 +1,172 @@
+//! Replay of a recorded session (`--replay`, a file written with `--raw-log`) one trace item at
+//! a time, to watch every state transition of a tricky sequence (e.g. nested preemptions).
+//! The replay starts paused, every step applies exactly one trace item to the instance.
+
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::{
+    pipeline::TraceLineParser,
+    tracing::{
+        instance::TracingInstance,
+        time::{ComputerTime, TimePair},
+        trace_data::{TraceItem, TraceParseResult},
+    },
+};
+
+/// Line of the recording, parsed like the live lines
+#[derive(Debug)]
+enum ReplayLine {
+    Log(String),
+    Trace(TraceParseResult),
+}
+
+#[derive(Debug)]
+pub struct Replay {
+    lines: Vec<ReplayLine>,
+    /// Index of the next line to apply
+    position: usize,
+    /// Trace items applied so far
+    applied: usize,
+    /// Trace items of the whole recording
+    total: usize,
+    /// Index of the line of the latest applied trace item
+    current: Option<usize>,
+}
+
+impl Replay {
+    /// Read and parse the recorded lines
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed reading replay file {}", path.display()))?;
+        Ok(Self::from_lines(content.lines()))
+    }
+
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut parser = TraceLineParser::new();
+        let pc_timestamp = ComputerTime::now();
+        let lines: Vec<ReplayLine> = lines
+            .into_iter()
+            .flat_map(|line| match parser.parse(line, pc_timestamp) {
+                Some(parse_results) => parse_results.into_iter().map(ReplayLine::Trace).collect(),
+                None => vec![ReplayLine::Log(line.to_string())],
+            })
+            .collect();
+        let total = lines
+            .iter()
+            .filter(|line| matches!(line, ReplayLine::Trace(Ok(_))))
+            .count();
+
+        Self {
+            lines,
+            position: 0,
+            applied: 0,
+            total,
+            current: None,
+        }
+    }
+
+    /// Apply the next trace item to the instance, received now. The log lines before it are
+    /// returned (shown in the log pane), the parse errors before it counted by the instance.
+    /// Returns None once the recording is over.
+    pub fn step(&mut self, instance: &TracingInstance) -> Option<Vec<String>> {
+        let mut log_lines = Vec::new();
+        while let Some(line) = self.lines.get_mut(self.position) {
+            self.position += 1;
+            let parse_result = match line {
+                ReplayLine::Log(text) => {
+                    log_lines.push(text.clone());
+                    continue;
+                }
+                ReplayLine::Trace(parse_result) => parse_result,
+            };
+            // Statistics relate the firmware time to the time the item was received
+            if let Ok(trace_item) = parse_result {
+                trace_item.time_pair =
+                    TimePair::new(trace_item.time_pair.get_uc_timestamp(), ComputerTime::now());
+            }
+            instance.record_parse_result(parse_result);
+            if let Ok(trace_item) = parse_result {
+                instance.update(trace_item);
+                self.applied += 1;
+                self.current = Some(self.position - 1);
+                return Some(log_lines);
+            }
+        }
+        None
+    }
+
+    /// Latest applied trace item
+    pub fn current(&self) -> Option<&TraceItem> {
+        match self.lines.get(self.current?)? {
+            ReplayLine::Trace(Ok(trace_item)) => Some(trace_item),
+            _ => None,
+        }
+    }
+
+    /// Trace items applied so far
+    pub fn applied(&self) -> usize {
+        self.applied
+    }
+
+    /// Trace items of the whole recording
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_applies_one_item() {
+        let line = |time, task_id| {
+            format!(
+                "embassy executor tracer - [{}, 0, TaskNew, 1, {}] - embassy executor tracer",
+                time, task_id
+            )
+        };
+        let recording = [
+            String::from("INFO  booting"),
+            line(10, 1),
+            line(20, 2),
+            String::from(
+                "embassy executor tracer - [x, 0, TaskNew, 1, 9] - embassy executor tracer",
+            ),
+            String::from("INFO  spawned"),
+            line(30, 3),
+            line(40, 4),
+        ];
+        let mut replay = Replay::from_lines(recording.iter().map(String::as_str));
+        assert_eq!(replay.total(), 4);
+
+        let (_trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let instance = TracingInstance::new(trace_rx);
+        assert_eq!(
+            replay.step(&instance),
+            Some(vec![String::from("INFO  booting")])
+        );
+        assert_eq!(instance.get_stats().tasks_count, 1);
+
+        // the parse error is counted on the way, the log line handed out
+        assert_eq!(replay.step(&instance), Some(Vec::new()));
+        assert_eq!(
+            replay.step(&instance),
+            Some(vec![String::from("INFO  spawned")])
+        );
+        assert_eq!(replay.applied(), 3);
+        assert_eq!(instance.get_stats().tasks_count, 3);
+        assert_eq!(instance.get_stats().parse_stats.error_count(), 1);
+        assert_eq!(
+            replay.current().and_then(|item| item.data.get_task_id()),
+            Some(3)
+        );
+
+        assert!(replay.step(&instance).is_some());
+        assert_eq!(replay.step(&instance), None);
+        assert_eq!(replay.applied(), 4);
+        assert_eq!(instance.get_stats().tasks_count, 4);
+    }
+}
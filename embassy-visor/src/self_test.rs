@@ -0,0 +1,343 @@
+//! Throughput stress test of the trace pipeline (`--self-test <RATE>`). Synthetic trace lines
+//! are fed at a target rate through the line router and the tracing instance, while the
+//! statistics are gathered like the TUI does. The results are printed as JSON, so a CI job can
+//! track them and fail when the pipeline falls behind.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    FIRMWARE_ADDR_MAP, pipeline,
+    tracing::{
+        device::PRIMARY_DEVICE_ID,
+        instance::TracingInstance,
+        time::ComputerTime,
+        trace_data::{TRACE_PROTOCOL, TraceEventKind, TraceItem, TraceProtocol},
+    },
+};
+
+/// Cores of the synthetic firmware (one executor each)
+const CORE_COUNT: u32 = 2;
+
+/// Tasks polled round robin by every executor
+const TASKS_PER_EXECUTOR: u32 = 8;
+
+/// Events of one task poll
+const POLL_CYCLE: [TraceEventKind; 5] = [
+    TraceEventKind::TaskReadyBegin,
+    TraceEventKind::ExecutorPollStart,
+    TraceEventKind::TaskExecBegin,
+    TraceEventKind::TaskExecEnd,
+    TraceEventKind::ExecutorIdle,
+];
+
+/// Interval the backlog and the gather latency are sampled at (the TUI's gather interval)
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Only every n-th generated line is parsed a second time to measure the parse latency,
+/// to keep the generator fast and the sample count bounded
+const PARSE_SAMPLE_EVERY: u64 = 10;
+
+/// The pipeline fell behind if more events than it receives within this time are still
+/// queued when the generation ends
+const MAX_BACKLOG_TIME: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestConfig {
+    /// Generated trace events per second
+    pub rate: u64,
+    /// Time the events are generated for
+    pub duration: Duration,
+}
+
+/// Latency percentiles in microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p50_us: f64,
+    pub p90_us: f64,
+    pub p99_us: f64,
+    pub max_us: f64,
+}
+
+impl Percentiles {
+    /// Nearest-rank percentiles of the samples (all zero without samples)
+    pub fn of(samples: &mut [Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        samples.sort_unstable();
+        let percentile = |p: usize| {
+            let rank = (samples.len() * p).div_ceil(100).max(1);
+            samples[rank - 1].as_secs_f64() * 1_000_000.0
+        };
+        Self {
+            p50_us: percentile(50),
+            p90_us: percentile(90),
+            p99_us: percentile(99),
+            max_us: percentile(100),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelfTestReport {
+    pub target_rate: u64,
+    pub duration_s: f64,
+    /// Trace lines fed into the pipeline
+    pub events_sent: u64,
+    /// Trace lines processed by the tracing instance when the generation ended
+    pub events_processed: u64,
+    /// Processed trace lines per second during the generation
+    pub achieved_rate: f64,
+    pub parse_errors: usize,
+    /// Duration of `TraceItem::parse_from_line` for (sampled) generated lines
+    pub parse_latency: Percentiles,
+    /// Duration of gathering the statistics (`TracingInstance::get_stats`)
+    pub gather_latency: Percentiles,
+    /// Largest number of trace lines sent but not processed yet
+    pub max_backlog: u64,
+    /// Backlog at the end of the generation minus the backlog of the first sample
+    pub backlog_growth: i64,
+    /// Time to process the queued lines after the generation ended
+    pub drain_s: f64,
+    /// Too many events were still queued when the generation ended
+    pub fell_behind: bool,
+}
+
+/// Trace lines of a firmware whose executors poll their tasks round robin
+struct SyntheticFirmware {
+    protocol: TraceProtocol,
+    /// Number of generated poll events (also the firmware time in microseconds)
+    step: u64,
+}
+
+impl SyntheticFirmware {
+    fn new(protocol: TraceProtocol) -> Self {
+        Self { protocol, step: 0 }
+    }
+
+    fn line(&self, core_id: u32, kind: TraceEventKind, payload: &str) -> String {
+        format!(
+            "{}[{}, {}, {}, {}]{}",
+            self.protocol.prefix,
+            self.step,
+            core_id,
+            self.protocol.event_name(kind),
+            payload,
+            self.protocol.suffix
+        )
+    }
+
+    /// Topology and the spawn of every task
+    fn setup_lines(&self) -> Vec<String> {
+        let mut lines = vec![self.line(0, TraceEventKind::Topology, &CORE_COUNT.to_string())];
+        for core_id in 0..CORE_COUNT {
+            for task in 0..TASKS_PER_EXECUTOR {
+                let payload = format!("{}, {}", core_id, task_id(core_id, task));
+                lines.push(self.line(core_id, TraceEventKind::TaskNew, &payload));
+            }
+        }
+        lines
+    }
+
+    /// Next event of the poll cycles, the cores take turns after every poll
+    fn next_line(&mut self) -> String {
+        self.step += 1;
+        let poll = self.step / POLL_CYCLE.len() as u64;
+        let kind = POLL_CYCLE[(self.step % POLL_CYCLE.len() as u64) as usize];
+        let core_id = (poll % CORE_COUNT as u64) as u32;
+        let task = (poll / CORE_COUNT as u64 % TASKS_PER_EXECUTOR as u64) as u32;
+
+        // The executor id equals the core id
+        let payload = match kind {
+            TraceEventKind::ExecutorPollStart | TraceEventKind::ExecutorIdle => core_id.to_string(),
+            _ => format!("{}, {}", core_id, task_id(core_id, task)),
+        };
+        self.line(core_id, kind, &payload)
+    }
+}
+
+fn task_id(core_id: u32, task: u32) -> u32 {
+    (core_id + 1) * 100 + task
+}
+
+/// Feed synthetic trace lines at the configured rate through the pipeline and measure it
+pub fn run_self_test(config: SelfTestConfig) -> SelfTestReport {
+    // No firmware symbols, the tasks are named by their address
+    FIRMWARE_ADDR_MAP
+        .write()
+        .unwrap()
+        .get_or_insert_with(Default::default);
+
+    let (program_tx, program_rx) = crossbeam::channel::unbounded();
+    let (trace_tx, trace_rx) = crossbeam::channel::unbounded();
+    let (logs_tx, _logs_rx) = crossbeam::channel::unbounded();
+
+    let (instance, update_thread) = TracingInstance::new_with_update_thread(trace_rx);
+    let router = pipeline::route_program_lines(
+        PRIMARY_DEVICE_ID,
+        program_rx,
+        trace_tx,
+        logs_tx,
+        Arc::new(AtomicBool::new(true)),
+        None,
+    );
+
+    // Sample the backlog (lines sent but not processed yet) and gather the statistics while
+    // the lines are generated
+    let events_sent = Arc::new(AtomicU64::new(0));
+    let generating = Arc::new(AtomicBool::new(true));
+    let monitor = {
+        let events_sent = events_sent.clone();
+        let generating = generating.clone();
+        let instance = instance.clone();
+        std::thread::spawn(move || {
+            let mut backlogs = Vec::new();
+            let mut gather_latencies = Vec::new();
+            while generating.load(Ordering::Relaxed) {
+                std::thread::sleep(SAMPLE_INTERVAL);
+
+                let gather_start = Instant::now();
+                let stats = instance.get_stats();
+                gather_latencies.push(gather_start.elapsed());
+
+                let processed = stats.parse_stats.total_count() as u64;
+                backlogs.push(
+                    events_sent
+                        .load(Ordering::Relaxed)
+                        .saturating_sub(processed),
+                );
+            }
+            (backlogs, gather_latencies)
+        })
+    };
+
+    let mut firmware = SyntheticFirmware::new(TRACE_PROTOCOL.read().unwrap().clone());
+    let mut sent = 0;
+    for line in firmware.setup_lines() {
+        program_tx.send(line).unwrap();
+        sent += 1;
+    }
+
+    let mut parse_latencies = Vec::new();
+    let start = Instant::now();
+    while start.elapsed() < config.duration {
+        let due = (config.rate as f64 * start.elapsed().as_secs_f64()) as u64;
+        if sent >= due {
+            std::thread::sleep(Duration::from_micros(100));
+            continue;
+        }
+
+        // At most 1ms of events at once, so the duration is kept if the pipeline is too slow
+        let batch_end = due.min(sent + config.rate / 1000 + 1);
+        while sent < batch_end {
+            let line = firmware.next_line();
+            if sent % PARSE_SAMPLE_EVERY == 0 {
+                let parse_start = Instant::now();
+                let _ = TraceItem::parse_from_line(&line, ComputerTime::now());
+                parse_latencies.push(parse_start.elapsed());
+            }
+            program_tx.send(line).unwrap();
+            sent += 1;
+        }
+        events_sent.store(sent, Ordering::Relaxed);
+    }
+    let elapsed = start.elapsed();
+    let events_processed = instance.get_stats().parse_stats.total_count() as u64;
+
+    generating.store(false, Ordering::Relaxed);
+    let (backlogs, mut gather_latencies) = monitor.join().unwrap();
+    let final_backlog = sent.saturating_sub(events_processed);
+
+    // Closing the source ends the router, which closes the trace channel in turn
+    let drain_start = Instant::now();
+    drop(program_tx);
+    router.join().unwrap();
+    update_thread.join().unwrap();
+    let drain = drain_start.elapsed();
+
+    let max_queued = (config.rate as f64 * MAX_BACKLOG_TIME.as_secs_f64()) as u64;
+    SelfTestReport {
+        target_rate: config.rate,
+        duration_s: elapsed.as_secs_f64(),
+        events_sent: sent,
+        events_processed,
+        achieved_rate: events_processed as f64 / elapsed.as_secs_f64(),
+        parse_errors: instance.get_stats().parse_stats.error_count(),
+        parse_latency: Percentiles::of(&mut parse_latencies),
+        gather_latency: Percentiles::of(&mut gather_latencies),
+        max_backlog: backlogs
+            .iter()
+            .copied()
+            .chain([final_backlog])
+            .max()
+            .unwrap_or(0),
+        backlog_growth: final_backlog as i64 - backlogs.first().copied().unwrap_or(0) as i64,
+        drain_s: drain.as_secs_f64(),
+        fell_behind: final_backlog > max_queued,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        assert_eq!(Percentiles::of(&mut []), Percentiles::default());
+
+        let mut samples: Vec<_> = (1..=100).rev().map(Duration::from_micros).collect();
+        assert_eq!(
+            Percentiles::of(&mut samples),
+            Percentiles {
+                p50_us: 50.0,
+                p90_us: 90.0,
+                p99_us: 99.0,
+                max_us: 100.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_synthetic_firmware_lines_parse() {
+        let mut firmware = SyntheticFirmware::new(TraceProtocol::default());
+        let protocol = TraceProtocol::default();
+        let lines: Vec<String> = firmware
+            .setup_lines()
+            .into_iter()
+            .chain((0..200).map(|_| firmware.next_line()))
+            .collect();
+
+        for line in &lines {
+            assert!(protocol.is_trace_line(line));
+            assert!(
+                protocol.parse_line(line, ComputerTime::now())[0].is_ok(),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_self_test_report() {
+        let report = run_self_test(SelfTestConfig {
+            rate: 5000,
+            duration: Duration::from_millis(300),
+        });
+
+        // setup lines plus the generated ones
+        assert!(report.events_sent > 1 + (CORE_COUNT * TASKS_PER_EXECUTOR) as u64);
+        assert!(report.events_processed <= report.events_sent);
+        assert!(report.achieved_rate > 0.0);
+        assert_eq!(report.parse_errors, 0);
+        assert!(report.parse_latency.p50_us <= report.parse_latency.max_us);
+    }
+}
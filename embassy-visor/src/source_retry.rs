@@ -0,0 +1,141 @@
+//! Retrying a trace source that is not available yet (e.g. the board is still booting or the
+//! probe did not create its output file yet): attempts with a growing delay and, with
+//! `--source-max-attempts`, giving up after too many failed attempts in a row.
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    line_source::{FILE_SOURCE_STATUS, FileSourceStatus},
+    net_source::{NET_SOURCE_STATUS, NetSourceStatus},
+};
+
+/// Failed attempts in a row after which a source gives up (0: retry forever)
+pub static SOURCE_MAX_ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+
+/// Delay before the next attempt to reach a source, doubled after every failed attempt
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay restored once a reconnecting source is reached again
+    #[cfg(any(feature = "net-sources", test))]
+    initial: Duration,
+    max: Duration,
+    delay: Duration,
+    failed_attempts: u32,
+    /// Give up after this many failed attempts in a row (0: never)
+    max_attempts: u32,
+}
+
+impl Backoff {
+    /// Delay starting at `initial` and doubled up to `max`, giving up after
+    /// `SOURCE_MAX_ATTEMPTS` failed attempts
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            #[cfg(any(feature = "net-sources", test))]
+            initial,
+            max,
+            delay: initial,
+            failed_attempts: 0,
+            max_attempts: SOURCE_MAX_ATTEMPTS.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Give up after `max_attempts` instead of `SOURCE_MAX_ATTEMPTS` (0: never)
+    #[cfg(test)]
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Record a failed attempt. Returns the delay before the next attempt, None to give up.
+    pub fn failed(&mut self) -> Option<Duration> {
+        self.failed_attempts += 1;
+        if self.max_attempts > 0 && self.failed_attempts >= self.max_attempts {
+            return None;
+        }
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(self.max);
+        Some(delay)
+    }
+
+    /// The source is reached: start over with the initial delay
+    #[cfg(any(feature = "net-sources", test))]
+    pub fn succeeded(&mut self) {
+        self.delay = self.initial;
+        self.failed_attempts = 0;
+    }
+
+    /// Failed attempts in a row so far
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+}
+
+/// Source not available (yet) while the visor waits for the first trace item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceAvailability {
+    /// Still retrying, with a message like "Waiting for source at …"
+    Waiting(String),
+    /// Gave up after `SOURCE_MAX_ATTEMPTS`
+    GaveUp(String),
+}
+
+/// Availability of the file and network sources, None when they are reached (or not used)
+pub fn source_availability() -> Option<SourceAvailability> {
+    let file_status = FILE_SOURCE_STATUS.read().unwrap().clone();
+    match file_status {
+        Some(FileSourceStatus::GaveUp { path, attempts }) => {
+            return Some(SourceAvailability::GaveUp(format!(
+                "Source {} did not become available after {} attempts",
+                path.display(),
+                attempts
+            )));
+        }
+        Some(FileSourceStatus::Waiting { path, .. }) => {
+            return Some(SourceAvailability::Waiting(format!(
+                "Waiting for source at {}…",
+                path.display()
+            )));
+        }
+        Some(FileSourceStatus::Following) | None => {}
+    }
+
+    match NET_SOURCE_STATUS.read().unwrap().clone()? {
+        NetSourceStatus::GaveUp { error } => Some(SourceAvailability::GaveUp(format!(
+            "Remote source unavailable, gave up: {}",
+            error
+        ))),
+        NetSourceStatus::Reconnecting { error, .. } => Some(SourceAvailability::Waiting(format!(
+            "Waiting for the remote source… ({})",
+            error
+        ))),
+        NetSourceStatus::Connecting | NetSourceStatus::Connected => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let ms = Duration::from_millis;
+        let mut backoff = Backoff::new(ms(100), ms(300)).with_max_attempts(0);
+        assert_eq!(backoff.failed(), Some(ms(100)));
+        assert_eq!(backoff.failed(), Some(ms(200)));
+        assert_eq!(backoff.failed(), Some(ms(300)));
+        assert_eq!(backoff.failed(), Some(ms(300)));
+        assert_eq!(backoff.failed_attempts(), 4);
+
+        backoff.succeeded();
+        assert_eq!(backoff.failed(), Some(ms(100)));
+
+        // gives up on the third failed attempt in a row
+        let mut backoff = Backoff::new(ms(100), ms(300)).with_max_attempts(3);
+        assert!(backoff.failed().is_some());
+        assert!(backoff.failed().is_some());
+        assert_eq!(backoff.failed(), None);
+    }
+}
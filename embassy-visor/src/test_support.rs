@@ -0,0 +1,227 @@
+//! In-memory line source feeding recorded program output through the same pipeline as the
+//! live `cargo run` output (line router --> tracing instance), plus golden trace fixtures.
+//!
+//! The fixtures in `fixtures/` are raw program output (trace and log lines) of typical
+//! firmware scenarios. Their statistics are asserted end-to-end.
+
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+
+use crate::{
+    pipeline,
+    tracing::{
+        device::PRIMARY_DEVICE_ID,
+        instance::TracingInstance,
+        stats::{
+            executor_stats::ExecutorStats, instance_stats::InstanceStats, task_stats::TaskStats,
+        },
+        task::TaskTraceState,
+        time::ComputerTime,
+        trace_data::TraceItem,
+    },
+};
+
+/// Thread executor (0x1000) and interrupt executor (0x2000) on core 0 preempting each other,
+/// another executor (0x3000) on core 1. Cycle of 10ms, 20 cycles.
+pub const PREEMPTION_FIXTURE: &str = include_str!("../fixtures/preemption.log");
+
+/// Single executor with a 10ms periodic task (1ms runtime) and a 50ms periodic task (5ms runtime)
+pub const PERIODIC_FIXTURE: &str = include_str!("../fixtures/periodic.log");
+
+/// Supervisor task spawning 16 short lived worker tasks (contains one malformed trace line)
+pub const CHURN_FIXTURE: &str = include_str!("../fixtures/churn.log");
+
+/// Result of running program output through the pipeline
+pub struct PipelineOutput {
+    /// Statistics at the time of the last trace item (independent of how long the test took)
+    pub stats: InstanceStats,
+    pub log_lines: Vec<String>,
+}
+
+/// Feed the lines (like the program's stdout) through the pipeline and return the resulting
+/// statistics, once every line has been processed.
+pub fn run_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> PipelineOutput {
+    let mut last_item_time = None;
+    let (program_tx, program_rx) = crossbeam::channel::unbounded();
+    let (trace_tx, trace_rx) = crossbeam::channel::unbounded();
+    let (logs_tx, logs_rx) = crossbeam::channel::unbounded();
+
+    let (instance, update_thread) = TracingInstance::new_with_update_thread(trace_rx);
+    let router = pipeline::route_program_lines(
+        PRIMARY_DEVICE_ID,
+        program_rx,
+        trace_tx,
+        logs_tx,
+        Arc::new(AtomicBool::new(false)),
+        None,
+    );
+
+    for line in lines {
+        if let Some(Ok(item)) = TraceItem::parse_all_from_line(line, ComputerTime::now())
+            .into_iter()
+            .next_back()
+        {
+            last_item_time = Some(item.time_pair.get_uc_timestamp());
+        }
+        program_tx.send(format!("{}\n", line)).unwrap();
+    }
+
+    // Closing the source ends the router, which closes the trace channel in turn
+    drop(program_tx);
+    router.join().unwrap();
+    update_thread.join().unwrap();
+
+    let last_item_time = last_item_time.expect("no trace item in the lines");
+    PipelineOutput {
+        stats: instance
+            .get_stats_at(last_item_time)
+            .expect("last trace item within the retained history"),
+        log_lines: logs_rx.try_iter().collect(),
+    }
+}
+
+/// Run a fixture through the pipeline
+pub fn run_fixture(fixture: &str) -> PipelineOutput {
+    run_lines(fixture.lines())
+}
+
+/// Statistics of an idle task without any history. Tests set the fields they are about with
+/// `TaskStats { .., ..task_stats(task_id, name) }`.
+pub fn task_stats(task_id: u32, name: &str) -> TaskStats {
+    TaskStats {
+        task_id,
+        name: String::from(name),
+        cpu_utilization_percent: 0.0,
+        min_waiting_time: Duration::ZERO,
+        max_waiting_time: Duration::ZERO,
+        avg_waiting_time: Duration::ZERO,
+        count_waiting_time: 0,
+        state: TaskTraceState::Idle,
+        pinned: false,
+        state_changed: false,
+        integrity: Default::default(),
+        period_breakdown: None,
+        lost_wakeup: false,
+        stack_high_water_bytes: None,
+        age: Duration::ZERO,
+        generation: 0,
+        last_active_ago: None,
+        recent_states: Vec::new(),
+        yields_per_s: None,
+        warning_acknowledged: false,
+    }
+}
+
+/// Statistics of an idle executor without any history running the tasks. Tests set the
+/// fields they are about with `ExecutorStats { .., ..executor_stats(executor_id, ..) }`.
+pub fn executor_stats(
+    executor_id: u32,
+    core_id: u32,
+    name: &str,
+    tasks: Vec<TaskStats>,
+) -> ExecutorStats {
+    ExecutorStats {
+        executor_id,
+        core_id,
+        name: String::from(name),
+        tasks,
+        cpu_utilization_percent: 0.0,
+        integrity: Default::default(),
+        lost_wakeup_count: 0,
+        budget_utilization_percent: None,
+        scheduling_time: Duration::ZERO,
+        polling_time: Duration::ZERO,
+        preempted_by: Default::default(),
+        worst_poll: None,
+        pegged: false,
+        ping_pong: None,
+        idle_state: None,
+        task_count_history: Vec::new(),
+        task_count_growing: false,
+        warnings_acknowledged: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_task<'a>(stats: &'a InstanceStats, name: &str) -> &'a TaskStats {
+        stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .flat_map(|e| e.tasks.iter())
+            .find(|t| t.name == name)
+            .unwrap_or_else(|| panic!("Task {} not found", name))
+    }
+
+    fn assert_cpu(task: &TaskStats, expected_percent: f32) {
+        assert!(
+            (task.cpu_utilization_percent - expected_percent).abs() < 0.1,
+            "{}: expected ~{}% CPU, got {}%",
+            task.name,
+            expected_percent,
+            task.cpu_utilization_percent
+        );
+    }
+
+    #[test]
+    fn test_preemption_fixture() {
+        let output = run_fixture(PREEMPTION_FIXTURE);
+        let stats = &output.stats;
+
+        assert_eq!(stats.core_stats.len(), 2);
+        assert_eq!(stats.executor_count, 3);
+        assert_eq!(stats.tasks_count, 3);
+        assert_eq!(stats.parse_stats.error_count(), 0);
+        assert_eq!(output.log_lines.len(), 4);
+        assert_eq!(stats.integrity().total(), 0);
+        assert_eq!(stats.trace_health_percent(), 100.0);
+
+        // Preempted time does not count as running time of the thread executor's task
+        assert_cpu(find_task(stats, "Task 0x10"), 28.7);
+        assert_cpu(find_task(stats, "Task 0x20"), 10.2);
+        assert_cpu(find_task(stats, "Task 0x30"), 20.5);
+    }
+
+    #[test]
+    fn test_periodic_fixture() {
+        let output = run_fixture(PERIODIC_FIXTURE);
+        let stats = &output.stats;
+
+        assert_eq!(stats.core_stats.len(), 1);
+        assert_eq!(stats.executor_count, 1);
+        assert_eq!(stats.tasks_count, 2);
+        assert_eq!(output.log_lines.len(), 10);
+        assert_eq!(stats.integrity().total(), 0);
+        assert_eq!(stats.trace_health_percent(), 100.0);
+
+        let blink = find_task(stats, "Task 0x10");
+        assert_cpu(blink, 10.2);
+        assert_eq!(blink.count_waiting_time, 50);
+        assert_eq!(blink.avg_waiting_time.as_micros(), 100);
+
+        let sensor = find_task(stats, "Task 0x20");
+        assert_cpu(sensor, 10.2);
+        assert_eq!(sensor.count_waiting_time, 10);
+        assert_eq!(sensor.avg_waiting_time.as_micros(), 300);
+    }
+
+    #[test]
+    fn test_churn_fixture() {
+        let output = run_fixture(CHURN_FIXTURE);
+        let stats = &output.stats;
+
+        assert_eq!(stats.executor_count, 1);
+        assert_eq!(stats.tasks_count, 17);
+        assert_eq!(stats.parse_stats.error_count(), 1);
+        assert_eq!(output.log_lines.len(), 16);
+        // only the malformed line lowers the trace health
+        assert_eq!(stats.integrity().total(), 0);
+        assert!(stats.trace_health_percent() < 100.0);
+        assert_cpu(find_task(stats, "Task 0x10"), 4.2);
+    }
+}
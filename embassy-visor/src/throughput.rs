@@ -0,0 +1,221 @@
+//! Throughput of the trace sources (bytes and trace lines per second), to spot an overloaded
+//! link or tune the amount of published events. The share of lines failing to parse points
+//! to a format mismatch or a corrupted link.
+
+use std::{
+    sync::{
+        RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::tracing::time::ComputerTime;
+
+/// Counters of all trace sources (cargo stdout, semihosting file)
+pub static SOURCE_THROUGHPUT: ThroughputCounters = ThroughputCounters::new();
+
+/// Time window the rates are averaged over
+pub const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Share of the lines in a window (in percent) failing to parse as trace or readable log line
+/// above which a warning is shown
+pub static PARSE_ERROR_THRESHOLD_PERCENT: RwLock<f32> = RwLock::new(10.0);
+
+pub struct ThroughputCounters {
+    bytes: AtomicU64,
+    trace_lines: AtomicU64,
+    failed_trace_lines: AtomicU64,
+    lines: AtomicU64,
+    failed_lines: AtomicU64,
+}
+
+impl ThroughputCounters {
+    pub const fn new() -> Self {
+        Self {
+            bytes: AtomicU64::new(0),
+            trace_lines: AtomicU64::new(0),
+            failed_trace_lines: AtomicU64::new(0),
+            lines: AtomicU64::new(0),
+            failed_lines: AtomicU64::new(0),
+        }
+    }
+
+    /// Count bytes read from a source
+    pub fn add_bytes(&self, count: usize) {
+        self.bytes.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Count one received trace line, `failed` if any of its records failed to parse
+    pub fn add_trace_line(&self, failed: bool) {
+        self.trace_lines.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.failed_trace_lines.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count one routed line (trace or log), `failed` if it could not be parsed
+    pub fn add_line(&self, failed: bool) {
+        self.lines.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.failed_lines.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Current counter values
+    pub fn sample(&self) -> ThroughputSample {
+        ThroughputSample {
+            bytes: self.bytes.load(Ordering::Relaxed),
+            trace_lines: self.trace_lines.load(Ordering::Relaxed),
+            failed_trace_lines: self.failed_trace_lines.load(Ordering::Relaxed),
+            lines: self.lines.load(Ordering::Relaxed),
+            failed_lines: self.failed_lines.load(Ordering::Relaxed),
+            taken_at: ComputerTime::now(),
+        }
+    }
+}
+
+/// Counter values at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThroughputSample {
+    pub bytes: u64,
+    pub trace_lines: u64,
+    pub failed_trace_lines: u64,
+    pub lines: u64,
+    pub failed_lines: u64,
+    pub taken_at: ComputerTime,
+}
+
+/// Throughput between two samples
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThroughputRate {
+    pub bytes_per_s: f64,
+    pub trace_lines_per_s: f64,
+    /// Share of the trace lines failing to parse in percent (0.0 - 100.0)
+    pub failed_trace_line_percent: f64,
+    /// Share of the lines failing to parse in percent (0.0 - 100.0)
+    pub failed_line_percent: f64,
+}
+
+impl ThroughputRate {
+    /// Rate between an earlier and a later sample (zero if no time passed)
+    pub fn between(earlier: &ThroughputSample, later: &ThroughputSample) -> Self {
+        let elapsed_s = later.taken_at.saturating_sub(earlier.taken_at).as_millis() as f64 / 1000.0;
+        if elapsed_s <= 0.0 {
+            return Self::default();
+        }
+
+        let percent = |part: u64, total: u64| match total {
+            0 => 0.0,
+            total => part as f64 / total as f64 * 100.0,
+        };
+        let trace_lines = later.trace_lines.saturating_sub(earlier.trace_lines);
+        let failed_trace_lines = later
+            .failed_trace_lines
+            .saturating_sub(earlier.failed_trace_lines);
+        let lines = later.lines.saturating_sub(earlier.lines);
+        let failed_lines = later.failed_lines.saturating_sub(earlier.failed_lines);
+        Self {
+            bytes_per_s: later.bytes.saturating_sub(earlier.bytes) as f64 / elapsed_s,
+            trace_lines_per_s: trace_lines as f64 / elapsed_s,
+            failed_trace_line_percent: percent(failed_trace_lines, trace_lines),
+            failed_line_percent: percent(failed_lines, lines),
+        }
+    }
+
+    /// Whether more lines failed to parse than the threshold (in percent) allows
+    pub fn exceeds_parse_error_threshold(&self, threshold_percent: f32) -> bool {
+        self.failed_line_percent > threshold_percent as f64
+    }
+}
+
+/// Averages the throughput over `RATE_WINDOW` from regularly taken samples
+#[derive(Debug, Default)]
+pub struct ThroughputMeter {
+    window_start: Option<ThroughputSample>,
+    rate: ThroughputRate,
+}
+
+impl ThroughputMeter {
+    /// Add a new sample, the rate is updated once per window
+    pub fn update(&mut self, sample: ThroughputSample) {
+        match self.window_start {
+            None => self.window_start = Some(sample),
+            Some(start) => {
+                let elapsed = sample.taken_at.saturating_sub(start.taken_at);
+                if elapsed >= ComputerTime::new_from_duration(RATE_WINDOW) {
+                    self.rate = ThroughputRate::between(&start, &sample);
+                    self.window_start = Some(sample);
+                }
+            }
+        }
+    }
+
+    /// Throughput of the last complete window
+    pub fn get_rate(&self) -> ThroughputRate {
+        self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tracing::time::ComputerTime;
+
+    use super::{ThroughputMeter, ThroughputRate, ThroughputSample};
+
+    fn sample(ms: u64, bytes: u64, trace_lines: u64) -> ThroughputSample {
+        ThroughputSample {
+            bytes,
+            trace_lines,
+            failed_trace_lines: 0,
+            lines: trace_lines,
+            failed_lines: 0,
+            taken_at: ComputerTime::new_from_duration(Duration::from_millis(ms)),
+        }
+    }
+
+    #[test]
+    fn test_rate_between_samples() {
+        let rate = ThroughputRate::between(&sample(1000, 0, 0), &sample(3000, 8000, 100));
+        assert_eq!(rate.bytes_per_s, 4000.0);
+        assert_eq!(rate.trace_lines_per_s, 50.0);
+
+        assert_eq!(rate.failed_trace_line_percent, 0.0);
+
+        // a quarter of the trace lines of the window failed
+        let later = ThroughputSample {
+            failed_trace_lines: 25,
+            ..sample(3000, 8000, 100)
+        };
+        let rate = ThroughputRate::between(&sample(1000, 0, 0), &later);
+        assert_eq!(rate.failed_trace_line_percent, 25.0);
+
+        // no time passed
+        let rate = ThroughputRate::between(&sample(1000, 0, 0), &sample(1000, 10, 1));
+        assert_eq!(rate, ThroughputRate::default());
+    }
+
+    #[test]
+    fn test_meter_updates_once_per_window() {
+        let mut meter = ThroughputMeter::default();
+
+        // samples every 100ms with 80 bytes (one trace line) each
+        for i in 0..10 {
+            meter.update(sample(i * 100, i * 80, i));
+        }
+        // window not complete yet
+        assert_eq!(meter.get_rate(), ThroughputRate::default());
+
+        meter.update(sample(1000, 800, 10));
+        assert_eq!(meter.get_rate().bytes_per_s, 800.0);
+        assert_eq!(meter.get_rate().trace_lines_per_s, 10.0);
+
+        // traffic stops --> rate drops to zero after the next window
+        meter.update(sample(1500, 800, 10));
+        assert_eq!(meter.get_rate().trace_lines_per_s, 10.0);
+        meter.update(sample(2000, 800, 10));
+        assert_eq!(meter.get_rate().trace_lines_per_s, 0.0);
+    }
+}
@@ -0,0 +1,79 @@
+//! Detection of firmware resets within one session. A new boot restarts the firmware clock and
+//! reuses the executor/task ids of the previous boot for possibly different entities.
+
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use crate::tracing::time::EmbassyTime;
+
+/// The firmware time of a core going back by more than this (in milliseconds) is a reset.
+/// Smaller steps are reordered events.
+pub static RESET_BACKSTEP_MS: AtomicU64 = AtomicU64::new(100);
+
+/// Keep the executors and tasks of previous boots (tagged with their boot generation)
+/// instead of forgetting them on a reset. Read when an instance is created, see
+/// `InstanceSettings`.
+pub static TAG_BOOT_GENERATIONS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default)]
+pub struct BootTracker {
+    /// Number of resets detected in this session
+    generation: u32,
+    /// Latest firmware time seen per core in the current boot
+    latest_time: BTreeMap<u32, EmbassyTime>,
+}
+
+impl BootTracker {
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Record the firmware time of a trace item. On a reset the generation is incremented
+    /// and the latest time of the previous boot (of all cores) is returned.
+    pub fn observe(&mut self, core_id: u32, time: EmbassyTime) -> Option<EmbassyTime> {
+        let backstep = EmbassyTime::from_millis(RESET_BACKSTEP_MS.load(Ordering::Relaxed));
+        let latest = self.latest_time.entry(core_id).or_insert(time);
+
+        if latest.saturating_sub(time) > backstep {
+            let previous_end = self.latest_time.values().copied().max();
+            self.latest_time.clear();
+            self.latest_time.insert(core_id, time);
+            self.generation += 1;
+            return previous_end;
+        }
+
+        *latest = (*latest).max(time);
+        None
+    }
+
+    /// Start over with the first boot (e.g. after the firmware was reloaded)
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_reset() {
+        let mut boots = BootTracker::default();
+        assert_eq!(boots.observe(0, EmbassyTime::from_millis(1000)), None);
+        assert_eq!(boots.observe(1, EmbassyTime::from_millis(1200)), None);
+        // reordered event
+        assert_eq!(boots.observe(0, EmbassyTime::from_millis(950)), None);
+        assert_eq!(boots.generation(), 0);
+
+        assert_eq!(
+            boots.observe(0, EmbassyTime::from_millis(5)),
+            Some(EmbassyTime::from_millis(1200))
+        );
+        assert_eq!(boots.generation(), 1);
+        // the other core has not been seen in the new boot yet
+        assert_eq!(boots.observe(1, EmbassyTime::from_millis(10)), None);
+        assert_eq!(boots.generation(), 1);
+    }
+}
@@ -0,0 +1,183 @@
+//! Communication between tasks over channels (beacon's `channel-trace` feature). Every send
+//! and receive names the channel and the task, so the tasks sending to a channel are
+//! connected to the tasks receiving from it (producer --> consumer).
+
+use std::collections::BTreeMap;
+
+use crate::{
+    FIRMWARE_ADDR_MAP, elf_file,
+    tracing::{executor::ExecutorTraceInfo, trace_data::TraceItemType},
+};
+
+/// Executor and task id
+type TaskKey = (u32, u32);
+
+/// Messages per task on one channel
+#[derive(Debug, Clone, Default)]
+struct ChannelEndpoints {
+    sent: BTreeMap<TaskKey, u64>,
+    received: BTreeMap<TaskKey, u64>,
+}
+
+/// Messages sent and received by every task on every channel
+#[derive(Debug, Clone, Default)]
+pub struct ChannelGraph {
+    channels: BTreeMap<u32, ChannelEndpoints>,
+}
+
+/// Producer --> consumer connection over one channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelEdge {
+    pub channel_id: u32,
+    /// Name of the channel's static (from the ELF file), else its id
+    pub channel: String,
+    pub producer: String,
+    pub consumer: String,
+    /// Messages the producer sent to the channel
+    pub sent: u64,
+    /// Messages the consumer received from the channel
+    pub received: u64,
+}
+
+impl ChannelGraph {
+    /// Count a send or receive (other trace items are ignored)
+    pub fn record(&mut self, data: &TraceItemType) {
+        match *data {
+            TraceItemType::ChannelSend {
+                executor_id,
+                task_id,
+                channel_id,
+            } => {
+                let channel = self.channels.entry(channel_id).or_default();
+                *channel.sent.entry((executor_id, task_id)).or_default() += 1;
+            }
+            TraceItemType::ChannelReceive {
+                executor_id,
+                task_id,
+                channel_id,
+            } => {
+                let channel = self.channels.entry(channel_id).or_default();
+                *channel.received.entry((executor_id, task_id)).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Forget all channels (e.g. the ids of a new boot belong to different tasks)
+    pub fn clear(&mut self) {
+        self.channels.clear();
+    }
+
+    /// Edges from every producer to every consumer of each channel (sorted by channel). Tasks
+    /// are named after the executors' tasks; channels only sent to or only received from have
+    /// no edge yet.
+    pub fn edges(&self, executors: &[ExecutorTraceInfo]) -> Vec<ChannelEdge> {
+        let task_name = |(executor_id, task_id): TaskKey| {
+            executors
+                .iter()
+                .filter(|e| e.get_executor_id() == executor_id)
+                .find_map(|e| e.find_task_by_id(task_id))
+                .map_or_else(
+                    || format!("Task 0x{:X}", task_id),
+                    |t| t.get_task_display_name(),
+                )
+        };
+
+        let mut edges = Vec::new();
+        for (&channel_id, endpoints) in self.channels.iter() {
+            let channel = channel_name(channel_id);
+            for (&producer, &sent) in endpoints.sent.iter() {
+                for (&consumer, &received) in endpoints.received.iter() {
+                    edges.push(ChannelEdge {
+                        channel_id,
+                        channel: channel.clone(),
+                        producer: task_name(producer),
+                        consumer: task_name(consumer),
+                        sent,
+                        received,
+                    });
+                }
+            }
+        }
+        edges
+    }
+}
+
+/// Symbol at the channel's address, if the id is one
+pub fn channel_name(channel_id: u32) -> String {
+    FIRMWARE_ADDR_MAP
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|addr_map| addr_map.get(&(channel_id as u64)))
+        .map_or_else(
+            || format!("Channel 0x{:X}", channel_id),
+            |name| elf_file::try_extract_short_name(name).to_string(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{
+        executor::ExecutorTraceInfo,
+        test_harness::{at_ms, feed_executor, item},
+        trace_data::TraceItemType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_producer_consumer_graph() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        feed_executor(
+            &mut executor,
+            &[7, 8].map(|task_id| {
+                item(
+                    1,
+                    0,
+                    TraceItemType::TaskNew {
+                        executor_id: 1,
+                        task_id,
+                    },
+                )
+            }),
+        );
+
+        // task 7 produces three messages, task 8 consumed two of them so far
+        let mut graph = ChannelGraph::default();
+        let send = TraceItemType::ChannelSend {
+            executor_id: 1,
+            task_id: 7,
+            channel_id: 0x2000,
+        };
+        let receive = TraceItemType::ChannelReceive {
+            executor_id: 1,
+            task_id: 8,
+            channel_id: 0x2000,
+        };
+        for data in [&send, &receive, &send, &send, &receive] {
+            graph.record(data);
+        }
+        // a channel without a consumer yet
+        graph.record(&TraceItemType::ChannelSend {
+            executor_id: 1,
+            task_id: 8,
+            channel_id: 0x3000,
+        });
+
+        assert_eq!(
+            graph.edges(&[executor]),
+            vec![ChannelEdge {
+                channel_id: 0x2000,
+                channel: String::from("Channel 0x2000"),
+                producer: String::from("Task 0x7"),
+                consumer: String::from("Task 0x8"),
+                sent: 3,
+                received: 2,
+            }]
+        );
+
+        graph.clear();
+        assert!(graph.edges(&[]).is_empty());
+    }
+}
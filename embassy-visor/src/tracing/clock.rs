@@ -0,0 +1,108 @@
+//! Alignment of the uC clock with the PC clock. Every trace item pairs the uC timestamp of an
+//! event with the PC time it was received, the difference of both is the clock offset plus
+//! the transport delay. Delays only ever add to it, so the smallest difference of the recent
+//! pairs is the best estimate of the offset; forgetting older pairs follows the drift of the
+//! clocks. Without task activity there are no pairs, so the beacon's heartbeat events keep the
+//! estimate fresh during idle periods.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::tracing::time::{ComputerTime, TimePair};
+
+/// Pairs older than this (PC time, relative to the newest pair) are forgotten
+pub const ALIGNMENT_WINDOW: Duration = Duration::from_secs(5);
+
+/// Clock offset estimate from the recent time pairs
+#[derive(Debug, Clone, Default)]
+pub struct ClockSync {
+    /// PC time and clock difference (PC - uC, in microseconds) of the recent pairs, oldest first
+    samples: VecDeque<(ComputerTime, i128)>,
+}
+
+/// Current alignment of the clocks for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockAlignment {
+    /// Estimated PC time minus uC time in microseconds
+    pub offset_us: i128,
+    /// PC time of the newest pair
+    pub last_sample: ComputerTime,
+}
+
+impl ClockSync {
+    /// Refine the estimate with the time pair of a received trace item
+    pub fn record(&mut self, time_pair: TimePair) {
+        let pc = time_pair.get_pc_timestamp();
+        let difference = pc.as_micros() as i128 - time_pair.get_uc_timestamp().as_micros() as i128;
+        self.samples.push_back((pc, difference));
+
+        let window = ComputerTime::new_from_duration(ALIGNMENT_WINDOW);
+        while let Some((sampled_at, _)) = self.samples.front()
+            && pc.saturating_sub(*sampled_at) > window
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Forget all pairs (e.g. the uC clock restarted with a firmware reset)
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Estimated offset (PC - uC, in microseconds), None before the first pair
+    pub fn offset_us(&self) -> Option<i128> {
+        self.samples.iter().map(|(_, difference)| *difference).min()
+    }
+
+    /// Offset and the time of the newest pair, None before the first pair
+    pub fn alignment(&self) -> Option<ClockAlignment> {
+        Some(ClockAlignment {
+            offset_us: self.offset_us()?,
+            last_sample: self.samples.back()?.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tracing::time::{ComputerTime, EmbassyTime, TimePair};
+
+    use super::ClockSync;
+
+    /// Pair of an event at `uc_ms` received at `pc_ms`
+    fn pair(uc_ms: u64, pc_ms: u64) -> TimePair {
+        TimePair::new(
+            EmbassyTime::from_millis(uc_ms),
+            ComputerTime::new_from_duration(Duration::from_millis(pc_ms)),
+        )
+    }
+
+    #[test]
+    fn test_heartbeats_refine_offset_during_idle() {
+        let mut clock = ClockSync::default();
+        assert_eq!(clock.offset_us(), None);
+
+        // burst of task events at boot: the uC clock is 1000ms behind, received 5-20ms late
+        clock.record(pair(0, 1_020));
+        clock.record(pair(1, 1_006));
+        clock.record(pair(2, 1_012));
+        assert_eq!(clock.offset_us(), Some(1_005_000));
+
+        // idle firmware: only heartbeats every second, received with 1ms delay, while the uC
+        // clock drifts 1ms per second behind
+        for second in 1..=10 {
+            let uc = second * 1_000;
+            clock.record(pair(uc, 1_000 + uc + 1 + second));
+        }
+        // older pairs (the boot events too) are forgotten, the offset follows the drift
+        assert_eq!(clock.offset_us(), Some(1_007_000));
+        assert_eq!(
+            clock.alignment().unwrap().last_sample,
+            ComputerTime::new_from_duration(Duration::from_millis(11_011))
+        );
+
+        clock.clear();
+        assert_eq!(clock.alignment(), None);
+    }
+}
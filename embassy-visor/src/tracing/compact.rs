@@ -0,0 +1,401 @@
+//! Decoding of the compact trace records published by the beacon's `compact` feature. Between
+//! the usual trace line markers, a compact line carries `{...}` records instead of `[...]`:
+//!
+//! - `{@<core_id>,<timestamp_us>}` absolute timestamp of the core
+//! - `{=<index>,<id>}` entry of the id table (executor and task ids)
+//! - `{<delta_us>,<core_id>,<event code>,<executor index>[,<task index>]}` event, its
+//!   timestamp is the delta to the previous event of the same core
+//!
+//! The records depend on the ones before, so one decoder has to see all lines of a source
+//! in order. Events referring to a sync or an id definition that was missed (e.g. the visor
+//! attached mid-session) are parse errors.
+
+use std::collections::HashMap;
+
+use crate::tracing::{
+    time::{ComputerTime, TimePair},
+    trace_data::{
+        TraceEventKind, TraceItem, TraceItemType, TraceParseError, TraceParseResult, TraceProtocol,
+    },
+};
+
+/// Event code of the compact records (None for events only published uncompressed)
+fn event_code(kind: TraceEventKind) -> Option<char> {
+    match kind {
+        TraceEventKind::ExecutorPollStart => Some('P'),
+        TraceEventKind::ExecutorIdle => Some('I'),
+        TraceEventKind::TaskNew => Some('N'),
+        TraceEventKind::TaskEnd => Some('E'),
+        TraceEventKind::TaskExecBegin => Some('B'),
+        TraceEventKind::TaskExecEnd => Some('X'),
+        TraceEventKind::TaskReadyBegin => Some('R'),
+        TraceEventKind::Header
+        | TraceEventKind::SchemaVersion
+        | TraceEventKind::Topology
+        | TraceEventKind::FirmwareId
+        | TraceEventKind::Heartbeat
+        | TraceEventKind::TaskStackUsage
+        | TraceEventKind::ChannelSend
+        | TraceEventKind::ChannelReceive
+        | TraceEventKind::TimerSet
+        | TraceEventKind::TimerFire => None,
+    }
+}
+
+fn event_kind(code: &str) -> Option<TraceEventKind> {
+    TraceEventKind::ALL
+        .into_iter()
+        .find(|&kind| event_code(kind).is_some_and(|c| code.len() == 1 && code.starts_with(c)))
+}
+
+/// Reconstructs the absolute timestamps and ids of compact records (plain trace lines are
+/// parsed by the protocol unchanged)
+#[derive(Debug, Default)]
+pub struct CompactDecoder {
+    /// Timestamp of the latest event per core
+    last_times: HashMap<u32, u64>,
+    /// Executor and task ids by their index
+    ids: HashMap<u8, u32>,
+}
+
+impl CompactDecoder {
+    /// Parse every record of the trace line, compact or plain
+    pub fn parse_line(
+        &mut self,
+        protocol: &TraceProtocol,
+        line: &str,
+        pc_timestamp: ComputerTime,
+    ) -> Vec<TraceParseResult> {
+        let records = protocol.strip_markers(line);
+        if !records.trim_start().starts_with('{') {
+            return protocol.parse_line(line, pc_timestamp);
+        }
+
+        let mut results = Vec::new();
+        let mut rest = records;
+        while let Some(start) = rest.find('{') {
+            rest = &rest[start + 1..];
+            let Some(end) = rest.find('}') else {
+                results.push(Err(TraceParseError::InvalidFormat));
+                break;
+            };
+            if let Some(result) = self.decode_record(protocol, &rest[..end], pc_timestamp) {
+                results.push(result);
+            }
+            rest = &rest[end + 1..];
+        }
+
+        // A trace line without any event is malformed as a whole, unless it only updated
+        // the decoder state
+        if results.is_empty() && !records.contains('}') {
+            results.push(Err(TraceParseError::InvalidFormat));
+        }
+        results
+    }
+
+    /// Decode the content of one record (without the braces), None for the records only
+    /// updating the decoder state
+    fn decode_record(
+        &mut self,
+        protocol: &TraceProtocol,
+        content: &str,
+        pc_timestamp: ComputerTime,
+    ) -> Option<TraceParseResult> {
+        let parts: Vec<&str> = content.split(',').map(|s| s.trim()).collect();
+
+        if let Some(core_id) = parts[0].strip_prefix('@') {
+            return match (core_id.parse(), parts.get(1).map(|t| t.parse())) {
+                (Ok(core_id), Some(Ok(timestamp))) if parts.len() == 2 => {
+                    self.last_times.insert(core_id, timestamp);
+                    None
+                }
+                (Err(_), _) => Some(Err(TraceParseError::InvalidCoreId)),
+                _ => Some(Err(TraceParseError::InvalidTimestamp)),
+            };
+        }
+
+        if let Some(index) = parts[0].strip_prefix('=') {
+            return match (index.parse(), parts.get(1).map(|id| id.parse())) {
+                (Ok(index), Some(Ok(id))) if parts.len() == 2 => {
+                    self.ids.insert(index, id);
+                    None
+                }
+                _ => Some(Err(TraceParseError::InvalidFormat)),
+            };
+        }
+
+        Some(self.decode_event(protocol, &parts, pc_timestamp))
+    }
+
+    /// Format: <delta>, <core_id>, <event code>, <executor index>, <task index?>
+    fn decode_event(
+        &mut self,
+        protocol: &TraceProtocol,
+        parts: &[&str],
+        pc_timestamp: ComputerTime,
+    ) -> TraceParseResult {
+        if parts.len() < 4 || parts.len() > 5 {
+            return Err(TraceParseError::InvalidFormat);
+        }
+
+        let delta: u64 = parts[0]
+            .parse()
+            .map_err(|_| TraceParseError::InvalidTimestamp)?;
+        let core_id: u32 = parts[1]
+            .parse()
+            .map_err(|_| TraceParseError::InvalidCoreId)?;
+        let kind = event_kind(parts[2]).ok_or(TraceParseError::InvalidEventType)?;
+
+        let id_of = |index: &str, error: TraceParseError| {
+            index
+                .parse()
+                .ok()
+                .and_then(|index: u8| self.ids.get(&index))
+                .map(|id| id.to_string())
+                .ok_or(error)
+        };
+        let mut payload = vec![id_of(parts[3], TraceParseError::InvalidExecutorId)?];
+        if let Some(task_index) = parts.get(4) {
+            payload.push(id_of(task_index, TraceParseError::InvalidTaskId)?);
+        }
+        let payload: Vec<&str> = payload.iter().map(String::as_str).collect();
+        let data = TraceItemType::from_parts(kind, &payload)?;
+
+        // Without a sync of the core there is nothing the delta refers to
+        let timestamp = self
+            .last_times
+            .get(&core_id)
+            .ok_or(TraceParseError::InvalidTimestamp)?
+            + delta;
+        self.last_times.insert(core_id, timestamp);
+
+        let time_pair = TimePair::new(protocol.timestamp(timestamp), pc_timestamp);
+        Ok(TraceItem::new(time_pair, core_id, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::time::ComputerTime;
+
+    use super::*;
+
+    /// Encoder following the rules of the beacon's `compact` feature
+    #[derive(Default)]
+    struct TestEncoder {
+        last_times: HashMap<u32, u64>,
+        ids: Vec<u32>,
+    }
+
+    impl TestEncoder {
+        fn encode(&mut self, timestamp: u64, core_id: u32, data: &TraceItemType) -> Vec<String> {
+            let mut records = Vec::new();
+            let mut index_of = |id: u32, records: &mut Vec<String>| match self
+                .ids
+                .iter()
+                .position(|&listed| listed == id)
+            {
+                Some(index) => index,
+                None => {
+                    self.ids.push(id);
+                    records.push(format!("{{={},{}}}", self.ids.len() - 1, id));
+                    self.ids.len() - 1
+                }
+            };
+            let mut indices = vec![index_of(data.get_executor_id().unwrap(), &mut records)];
+            if let Some(task_id) = data.get_task_id() {
+                indices.push(index_of(task_id, &mut records));
+            }
+
+            let delta = self
+                .last_times
+                .get(&core_id)
+                .and_then(|&last| timestamp.checked_sub(last))
+                .filter(|&delta| delta <= u32::MAX as u64);
+            let delta = delta.unwrap_or_else(|| {
+                records.push(format!("{{@{},{}}}", core_id, timestamp));
+                0
+            });
+            self.last_times.insert(core_id, timestamp);
+
+            let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+            records.push(format!(
+                "{{{},{},{},{}}}",
+                delta,
+                core_id,
+                event_code(data.kind()).unwrap(),
+                indices.join(",")
+            ));
+
+            // One record per line, like the beacon publishes them
+            records
+                .into_iter()
+                .map(|record| {
+                    format!(
+                        "embassy executor tracer - {} - embassy executor tracer",
+                        record
+                    )
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let events = [
+            (
+                1_000,
+                0,
+                TraceItemType::TaskNew {
+                    executor_id: 7,
+                    task_id: 536_871_000,
+                },
+            ),
+            (
+                1_010,
+                0,
+                TraceItemType::TaskReadyBegin {
+                    executor_id: 7,
+                    task_id: 536_871_000,
+                },
+            ),
+            (
+                1_015,
+                1,
+                TraceItemType::TaskNew {
+                    executor_id: 9,
+                    task_id: 536_872_000,
+                },
+            ),
+            (
+                1_020,
+                0,
+                TraceItemType::ExecutorPollStart { executor_id: 7 },
+            ),
+            (
+                1_021,
+                0,
+                TraceItemType::TaskExecBegin {
+                    executor_id: 7,
+                    task_id: 536_871_000,
+                },
+            ),
+            (
+                1_030,
+                1,
+                TraceItemType::TaskReadyBegin {
+                    executor_id: 9,
+                    task_id: 536_872_000,
+                },
+            ),
+            (
+                1_500,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id: 7,
+                    task_id: 536_871_000,
+                },
+            ),
+            (1_501, 0, TraceItemType::ExecutorIdle { executor_id: 7 }),
+            // gap too large for a delta --> sync
+            (
+                10_000_000_000,
+                1,
+                TraceItemType::TaskExecBegin {
+                    executor_id: 9,
+                    task_id: 536_872_000,
+                },
+            ),
+            (
+                10_000_000_050,
+                1,
+                TraceItemType::TaskEnd {
+                    executor_id: 9,
+                    task_id: 536_872_000,
+                },
+            ),
+            // reset of the core's clock --> sync
+            (
+                5,
+                0,
+                TraceItemType::TaskEnd {
+                    executor_id: 7,
+                    task_id: 536_871_000,
+                },
+            ),
+        ];
+
+        let mut encoder = TestEncoder::default();
+        let lines: Vec<String> = events
+            .iter()
+            .flat_map(|(timestamp, core_id, data)| encoder.encode(*timestamp, *core_id, data))
+            .collect();
+
+        let protocol = TraceProtocol::default();
+        let mut decoder = CompactDecoder::default();
+        let decoded: Vec<(u64, u32, TraceItemType)> = lines
+            .iter()
+            .flat_map(|line| decoder.parse_line(&protocol, line, ComputerTime::now()))
+            .map(|result| {
+                let item = result.unwrap();
+                (
+                    item.time_pair.get_uc_timestamp().as_micros() as u64,
+                    item.core_id,
+                    item.data,
+                )
+            })
+            .collect();
+
+        assert_eq!(decoded, events);
+    }
+
+    #[test]
+    fn test_compact_decode_errors() {
+        let protocol = TraceProtocol::default();
+        let mut decoder = CompactDecoder::default();
+        let mut parse = |records: &str| -> Vec<Result<u128, TraceParseError>> {
+            let line = format!(
+                "embassy executor tracer - {} - embassy executor tracer",
+                records
+            );
+            decoder
+                .parse_line(&protocol, &line, ComputerTime::now())
+                .into_iter()
+                .map(|r| r.map(|item| item.time_pair.get_uc_timestamp().as_micros()))
+                .collect()
+        };
+
+        // delta before any sync of the core
+        assert_eq!(parse("{=0,7}"), vec![]);
+        assert_eq!(
+            parse("{5,0,P,0}"),
+            vec![Err(TraceParseError::InvalidTimestamp)]
+        );
+
+        // unknown id index
+        assert_eq!(parse("{@0,100}"), vec![]);
+        assert_eq!(
+            parse("{0,0,P,1}"),
+            vec![Err(TraceParseError::InvalidExecutorId)]
+        );
+        assert_eq!(
+            parse("{0,0,B,0,3}"),
+            vec![Err(TraceParseError::InvalidTaskId)]
+        );
+        assert_eq!(
+            parse("{0,0,Z,0}"),
+            vec![Err(TraceParseError::InvalidEventType)]
+        );
+        assert_eq!(
+            parse("{0,0,B,0}"),
+            vec![Err(TraceParseError::InvalidEventPayload)]
+        );
+
+        // errors don't advance the core's time
+        assert_eq!(parse("{5,0,P,0}"), vec![Ok(105)]);
+        assert_eq!(parse("{5,0,P,0"), vec![Err(TraceParseError::InvalidFormat)]);
+
+        // plain records are still parsed
+        assert_eq!(parse("[42, 0, ExecutorIdle, 7]"), vec![Ok(42)]);
+    }
+}
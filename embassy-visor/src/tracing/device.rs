@@ -0,0 +1,29 @@
+//! Devices traced at the same time (`--device`). Device 0 is the firmware run by cargo (and
+//! `--source`), every `--device` source gets the next id. The executor and task ids, clocks and
+//! boots of the devices are independent of each other; their timelines are not synchronized.
+
+use std::sync::RwLock;
+
+/// Id of the firmware run by cargo
+pub const PRIMARY_DEVICE_ID: u32 = 0;
+
+/// Names of the `--device` sources (device id 1 onwards)
+pub static DEVICE_NAMES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Check if more than the primary device is traced (cores are then named with their device)
+pub fn is_multi_device() -> bool {
+    !DEVICE_NAMES.read().unwrap().is_empty()
+}
+
+/// Name of the device as shown in the views
+pub fn device_name(device_id: u32) -> String {
+    match device_id.checked_sub(1) {
+        None => String::from("local"),
+        Some(index) => DEVICE_NAMES
+            .read()
+            .unwrap()
+            .get(index as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("device {}", device_id)),
+    }
+}
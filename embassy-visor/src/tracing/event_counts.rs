@@ -0,0 +1,122 @@
+//! Trace events received per kind, to diagnose a beacon setup (e.g. many ExecutorPollStart but
+//! no TaskExecBegin: the task events are not traced). The rate is the count of the latest
+//! complete one second window of PC time.
+
+use std::time::Duration;
+
+use crate::tracing::{time::ComputerTime, trace_data::TraceEventKind};
+
+/// Length of the window the rate is counted in
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Number of event kinds (counts are indexed by the kind)
+const KIND_COUNT: usize = TraceEventKind::ALL.len();
+
+/// Received events of one kind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventCount {
+    pub kind: TraceEventKind,
+    /// Events since the start (or the latest reset)
+    pub total: u64,
+    /// Events within the latest complete second
+    pub per_second: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventCounter {
+    totals: [u64; KIND_COUNT],
+    /// Start of the current rate window and the events within it (None before the first event)
+    window: Option<(ComputerTime, [u64; KIND_COUNT])>,
+    /// Events of the previous window
+    previous_window: [u64; KIND_COUNT],
+}
+
+/// Time from `start` to `end` (zero if `end` is earlier)
+fn elapsed(start: ComputerTime, end: ComputerTime) -> Duration {
+    Duration::from_micros(end.saturating_sub(start).as_micros() as u64)
+}
+
+impl EventCounter {
+    /// Count an event received at the given PC time
+    pub fn record(&mut self, kind: TraceEventKind, pc_time: ComputerTime) {
+        let (start, counts) = self.window.get_or_insert((pc_time, [0; KIND_COUNT]));
+        let since_start = elapsed(*start, pc_time);
+        if since_start >= RATE_WINDOW {
+            // a window without events in between counts nothing
+            self.previous_window = if since_start < 2 * RATE_WINDOW {
+                *counts
+            } else {
+                [0; KIND_COUNT]
+            };
+            *start = pc_time;
+            *counts = [0; KIND_COUNT];
+        }
+
+        counts[kind as usize] += 1;
+        self.totals[kind as usize] += 1;
+    }
+
+    /// Counts of the kinds received at least once, in protocol order. The rate relates to
+    /// `now` (a kind no longer received drops to 0/s).
+    pub fn counts(&self, now: ComputerTime) -> Vec<EventCount> {
+        let per_second = match self.window {
+            Some((start, _)) if elapsed(start, now) >= 2 * RATE_WINDOW => [0; KIND_COUNT],
+            Some((start, counts)) if elapsed(start, now) >= RATE_WINDOW => counts,
+            _ => self.previous_window,
+        };
+
+        TraceEventKind::ALL
+            .into_iter()
+            .filter(|&kind| self.totals[kind as usize] > 0)
+            .map(|kind| EventCount {
+                kind,
+                total: self.totals[kind as usize],
+                per_second: per_second[kind as usize],
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_per_second() {
+        let at = |ms| ComputerTime::new_from_duration(Duration::from_millis(ms));
+        let mut counter = EventCounter::default();
+
+        // first second: 3 polls and 1 exec begin
+        for ms in [0, 300, 600] {
+            counter.record(TraceEventKind::ExecutorPollStart, at(ms));
+        }
+        counter.record(TraceEventKind::TaskExecBegin, at(900));
+        // no complete second yet
+        assert!(counter.counts(at(950)).iter().all(|c| c.per_second == 0));
+
+        // second one: 1 poll
+        counter.record(TraceEventKind::ExecutorPollStart, at(1_200));
+        let counts = counter.counts(at(1_500));
+        assert_eq!(
+            counts,
+            [
+                EventCount {
+                    kind: TraceEventKind::ExecutorPollStart,
+                    total: 4,
+                    per_second: 3,
+                },
+                EventCount {
+                    kind: TraceEventKind::TaskExecBegin,
+                    total: 1,
+                    per_second: 1,
+                },
+            ]
+        );
+
+        // the second one completed without new events, then nothing is received anymore
+        let per_second =
+            |now| -> Vec<u64> { counter.counts(now).iter().map(|c| c.per_second).collect() };
+        assert_eq!(per_second(at(2_300)), [1, 0]);
+        assert_eq!(per_second(at(3_300)), [0, 0]);
+    }
+}
@@ -1,13 +1,13 @@
 //! ## Executor Tracing lifecycle
 //!
 //! ┌ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─
-//!       │(1)                                             │
+//!     │(1)                                             │
 //! │     │
 //!   ╔═══▼══╗   (2)     ┌────────────┐  (3)  ┌─────────┐  │
 //! │ ║ IDLE ║──────────▶│ SCHEDULING │──────▶│ POLLING │
 //!   ╚══════╝           └────────────┘       └─────────┘  │
 //! │     ▲              │            ▲            │
-//!       │      (5)     │            │  (4)       │       │
+//!     │      (5)     │            │  (4)       │       │
 //! │     └──────────────┘            └────────────┘
 //!   ┌──────────────────────────┐                         │
 //! └ ┤ Executor Trace Lifecycle │─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─
@@ -15,8 +15,8 @@
 //!
 //! 1. The executor is started (no associated trace)
 //! 2. A task on this executor is awoken. `_embassy_trace_task_ready_begin` is called
-//!      when this occurs, and `_embassy_trace_poll_start` is called when the executor
-//!      actually begins running
+//!    when this occurs, and `_embassy_trace_poll_start` is called when the executor
+//!    actually begins running
 //! 3. The executor has decided a task to poll. `_embassy_trace_task_exec_begin` is called
 //! 4. The executor finishes polling the task. `_embassy_trace_task_exec_end` is called
 //! 5. The executor has finished polling tasks. `_embassy_trace_executor_idle` is called
@@ -24,27 +24,120 @@
 //! (taken from embassy-executor/src/raw/trace.rs)
 //!
 
-use std::{collections::VecDeque, sync::atomic::Ordering};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 use crate::{
-    FIRMWARE_ADDR_MAP,
+    elf_file,
     tracing::{
-        instance::HISTORY_MAX_TIME_S,
-        task::TaskTraceInfo,
-        time::{ComputerTime, EmbassyTime, TimePair},
+        integrity::{IntegrityCounters, IntegrityIssue},
+        retention::Retention,
+        task::{TaskTraceInfo, TaskTraceState},
+        time::{EmbassyTime, TimePair},
         trace_data::{TraceItem, TraceItemType},
     },
 };
 
+/// A task ready for longer than this (in uC milliseconds) while its executor is idle is
+/// reported as a lost wakeup
+pub static LOST_WAKEUP_THRESHOLD_MS: AtomicU64 = AtomicU64::new(100);
+
+/// An executor busy for longer than this (in uC milliseconds) without going idle is reported
+/// as pegged (a task that never yields rather than merely a lot of work)
+pub static PEGGED_THRESHOLD_MS: AtomicU64 = AtomicU64::new(1000);
+
+/// Two tasks polled alternately this often without the executor going idle are suspected of
+/// waking each other without progress (ping-pong)...
+pub static PING_PONG_MIN_SWITCHES: AtomicU64 = AtomicU64::new(50);
+
+/// ...if they switch at least this fast on average (in uC microseconds; a producer and consumer
+/// handing over work every few milliseconds is normal)
+pub static PING_PONG_MAX_SWITCH_INTERVAL_US: AtomicU64 = AtomicU64::new(1000);
+
+/// Preemptions shorter than this (in uC microseconds) are treated as event ordering noise:
+/// coalesced into the interrupted state and left out of the statistics (0 = off)
+pub static PREEMPTION_THRESHOLD_US: AtomicU64 = AtomicU64::new(0);
+
+/// Current threshold, or the fixed one of an executor or task (set by tests)
+pub fn preemption_threshold(fixed: Option<EmbassyTime>) -> EmbassyTime {
+    fixed.unwrap_or_else(|| {
+        EmbassyTime::from_micros(PREEMPTION_THRESHOLD_US.load(Ordering::Relaxed))
+    })
+}
+
+/// Check if a preemption from `start` to `end` is shorter than the threshold (too short to be
+/// real)
+pub fn is_phantom_preemption(start: TimePair, end: TimePair, threshold: EmbassyTime) -> bool {
+    end.get_uc_timestamp()
+        .saturating_sub(start.get_uc_timestamp())
+        < threshold
+}
+
+/// Polls of the executor alternating between two tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingPongRun {
+    /// The two tasks in the order they were first polled (both the same before the first
+    /// switch)
+    pub task_ids: (u32, u32),
+    /// Number of switches between the two tasks
+    pub switches: usize,
+    /// Start of the first poll of the run
+    pub since: EmbassyTime,
+    /// Start of the latest poll of the run
+    last_poll: EmbassyTime,
+    last_task_id: u32,
+}
+
+impl PingPongRun {
+    fn new(task_id: u32, now: EmbassyTime) -> Self {
+        Self {
+            task_ids: (task_id, task_id),
+            switches: 0,
+            since: now,
+            last_poll: now,
+            last_task_id: task_id,
+        }
+    }
+
+    /// Continue the run with the poll of `task_id`, false if the poll breaks the pattern
+    fn poll(&mut self, task_id: u32, now: EmbassyTime) -> bool {
+        let continues = task_id != self.last_task_id
+            && (self.switches == 0 || task_id == self.task_ids.0 || task_id == self.task_ids.1);
+        if continues {
+            if self.switches == 0 {
+                self.task_ids.1 = task_id;
+            }
+            self.switches += 1;
+            self.last_poll = now;
+            self.last_task_id = task_id;
+        }
+        continues
+    }
+
+    /// Whether the run is long and fast enough to be a suspected ping-pong
+    pub fn is_suspected(&self) -> bool {
+        let max_duration = EmbassyTime::from_micros(
+            PING_PONG_MAX_SWITCH_INTERVAL_US
+                .load(Ordering::Relaxed)
+                .saturating_mul(self.switches as u64),
+        );
+        self.switches as u64 >= PING_PONG_MIN_SWITCHES.load(Ordering::Relaxed)
+            && self.last_poll.saturating_sub(self.since) <= max_duration
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum PreemptedPrevState {
     Scheduling,
     Polling,
 }
 
-impl Into<ExecutorState> for PreemptedPrevState {
-    fn into(self) -> ExecutorState {
-        match self {
+impl From<PreemptedPrevState> for ExecutorState {
+    fn from(val: PreemptedPrevState) -> Self {
+        match val {
             PreemptedPrevState::Scheduling => ExecutorState::Scheduling,
             PreemptedPrevState::Polling => ExecutorState::Polling,
         }
@@ -56,6 +149,7 @@ pub enum ExecutorState {
     Idle,
     Scheduling,
     /// Executor was preempted by another higher priority executor on the same core
+    /// (`by_executor_id` is the one currently running if several are nested)
     Preempted {
         by_executor_id: u32,
         prev_state: PreemptedPrevState,
@@ -63,65 +157,160 @@ pub enum ExecutorState {
     Polling,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub struct ExecutorHistoryEntry {
     state: ExecutorState,
     start_time: TimePair,
     end_time: TimePair,
 }
 
+impl ExecutorHistoryEntry {
+    /// Get the state the executor was in
+    pub fn get_state(&self) -> ExecutorState {
+        self.state
+    }
+
+    /// Get the timestamp when the state started
+    pub fn get_start_time(&self) -> TimePair {
+        self.start_time
+    }
+
+    /// Get the timestamp when the state ended
+    pub fn get_end_time(&self) -> TimePair {
+        self.end_time
+    }
+
+    /// Duration of the part of this history entry after `window_start` (UC time)
+    fn uc_duration_since(&self, window_start: EmbassyTime) -> EmbassyTime {
+        self.end_time
+            .get_uc_timestamp()
+            .saturating_sub(self.start_time.get_uc_timestamp().max(window_start))
+    }
+}
+
+/// State and detection counts of an executor before the latest trace item, to tell what the
+/// item changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutorSnapshot {
+    pub state: ExecutorState,
+    pub lost_wakeup_count: usize,
+    pub ping_pong_count: usize,
+}
+
 // TODO: Executor CPU usage statistics via the state history and not sum of tasks
 
 pub struct ExecutorTraceInfo {
     executor_id: u32,
     executor_name: Option<String>,
     core_id: u32,
+    /// Number of firmware resets in the session before this executor appeared
+    boot_generation: u32,
 
-    #[allow(dead_code)]
     created_at: TimePair,
 
     /// Current state of the executor
     state: ExecutorState,
     /// Timestamp when the current state started
     state_start_time: TimePair,
+    /// Higher priority executors active while preempted, innermost last
+    preempted_by: Vec<u32>,
+    /// Set on a copy rewound into the past: the current state ends here instead of now
+    snapshot_time: Option<EmbassyTime>,
 
     state_history: VecDeque<ExecutorHistoryEntry>,
 
     tasks: Vec<TaskTraceInfo>,
+
+    /// Integrity issues of the poll start/idle events of this executor (task events are
+    /// checked by the tasks)
+    integrity: IntegrityCounters,
+
+    /// Other executors polling on this core (higher priority ones keep this executor from
+    /// running while it is idle)
+    core_busy_by: Vec<u32>,
+    /// Time the other executors on this core went idle last
+    core_free_since: EmbassyTime,
+
+    /// Tasks currently ready while the executor stays idle (likely a waker bug)
+    lost_wakeup_task_ids: Vec<u32>,
+    /// Number of lost wakeups detected so far
+    lost_wakeup_count: usize,
+
+    /// Task currently polled and the start of its poll
+    polled_task: Option<(u32, TimePair)>,
+    /// Longest single poll so far: the polled task and its time in Polling
+    worst_poll: Option<(u32, EmbassyTime)>,
+    /// Polls alternating between two tasks since the executor went idle last
+    ping_pong: Option<PingPongRun>,
+    /// Number of ping-pong runs detected so far
+    ping_pong_count: usize,
+
+    /// Taken at the start of every update
+    before_update: ExecutorSnapshot,
+
+    /// Threshold used instead of the adjustable `PREEMPTION_THRESHOLD_US` (set by tests)
+    fixed_preemption_threshold: Option<EmbassyTime>,
 }
 
 impl ExecutorTraceInfo {
     pub fn new(executor_id: u32, core_id: u32, created_at: TimePair) -> Self {
-        // try to find task name from global firmware address map
-        let executor_name = match FIRMWARE_ADDR_MAP.get() {
-            Some(addr_map) => {
-                // task id represents the address of the task's future vtable
-                match addr_map.get(&(executor_id as u64)) {
-                    Some(name) => Some(name.to_string()),
-                    None => None,
-                }
-            }
-            None => {
-                eprintln!(
-                    "Warning: Firmware address map not initialized when creating TaskTraceInfo"
-                );
-                None
-            }
-        };
+        // try to find executor name from global firmware address map
+        let executor_name = elf_file::firmware_symbol(executor_id as u64);
 
         Self {
             executor_id,
             executor_name,
             core_id,
+            boot_generation: 0,
             state: ExecutorState::Idle,
             state_start_time: created_at,
+            preempted_by: Vec::new(),
+            snapshot_time: None,
             tasks: Vec::new(),
             created_at,
             state_history: VecDeque::new(),
+            integrity: IntegrityCounters::default(),
+            core_busy_by: Vec::new(),
+            core_free_since: created_at.get_uc_timestamp(),
+            lost_wakeup_task_ids: Vec::new(),
+            lost_wakeup_count: 0,
+            polled_task: None,
+            worst_poll: None,
+            ping_pong: None,
+            ping_pong_count: 0,
+            before_update: ExecutorSnapshot {
+                state: ExecutorState::Idle,
+                lost_wakeup_count: 0,
+                ping_pong_count: 0,
+            },
+            fixed_preemption_threshold: None,
         }
     }
 
+    /// Use this threshold instead of `PREEMPTION_THRESHOLD_US`
+    #[cfg(test)]
+    pub fn set_preemption_threshold(&mut self, threshold: EmbassyTime) {
+        self.fixed_preemption_threshold = Some(threshold);
+    }
+
     /// Set a new state for the executor, sending statistics as needed
     fn set_new_state(&mut self, new_state: ExecutorState, timestamp: TimePair) {
+        // Phantom preemption (reordered events) --> continue the interrupted state instead
+        if let ExecutorState::Preempted { .. } = self.state
+            && is_phantom_preemption(
+                self.state_start_time,
+                timestamp,
+                preemption_threshold(self.fixed_preemption_threshold),
+            )
+            && let Some(interrupted) = self.state_history.back()
+            && interrupted.state == new_state
+        {
+            self.state_start_time = interrupted.start_time;
+            self.state = new_state;
+            self.state_history.pop_back();
+            return;
+        }
+
         if self.state != new_state {
             // log history stats
             let hist_entry = ExecutorHistoryEntry {
@@ -165,11 +354,189 @@ impl ExecutorTraceInfo {
         &self.state
     }
 
+    /// Number of higher priority executors stacked above this one while preempted (0 if not
+    /// preempted). A rewound copy only knows the innermost one.
+    pub fn get_preemption_depth(&self) -> usize {
+        match self.state {
+            ExecutorState::Preempted { .. } => self.preempted_by.len().max(1),
+            _ => 0,
+        }
+    }
+
     /// Get the timestamp when the current state started
     pub fn get_state_start_time(&self) -> TimePair {
         self.state_start_time
     }
 
+    /// Get the history of state changes (oldest first)
+    pub fn get_state_history(&self) -> &VecDeque<ExecutorHistoryEntry> {
+        &self.state_history
+    }
+
+    /// Get the integrity issues detected in the events of this executor
+    pub fn get_integrity(&self) -> &IntegrityCounters {
+        &self.integrity
+    }
+
+    /// Integrity issue of an own poll start/idle event in the given state (None if valid)
+    fn check_transition(state: ExecutorState, data: &TraceItemType) -> Option<IntegrityIssue> {
+        match (state, data) {
+            (
+                ExecutorState::Preempted { .. },
+                TraceItemType::ExecutorPollStart { .. } | TraceItemType::ExecutorIdle { .. },
+            ) => Some(IntegrityIssue::ImpossibleTransition),
+            // missing exec end of the polled task
+            (ExecutorState::Polling, TraceItemType::ExecutorIdle { .. }) => {
+                Some(IntegrityIssue::Gap)
+            }
+            // duplicated idle
+            (ExecutorState::Idle, TraceItemType::ExecutorIdle { .. }) => {
+                Some(IntegrityIssue::ImpossibleTransition)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the longest single poll so far: the polled task and the time it kept the executor
+    /// polling (preemptions during the poll excluded)
+    pub fn get_worst_poll(&self) -> Option<(u32, EmbassyTime)> {
+        self.worst_poll
+    }
+
+    /// Update the longest single poll with the poll ending now (while still Polling). A poll
+    /// is split into several Polling states if it was preempted.
+    fn record_poll_end(&mut self, now: EmbassyTime) {
+        let Some((task_id, poll_start)) = self.polled_task.take() else {
+            return;
+        };
+        let poll_start = poll_start.get_uc_timestamp();
+
+        let earlier_parts = self
+            .state_history
+            .iter()
+            .rev()
+            .take_while(|e| e.start_time.get_uc_timestamp() >= poll_start)
+            .filter(|e| e.state == ExecutorState::Polling)
+            .fold(EmbassyTime::ZERO, |acc, e| {
+                acc + e
+                    .end_time
+                    .get_uc_timestamp()
+                    .saturating_sub(e.start_time.get_uc_timestamp())
+            });
+        let duration = earlier_parts + now.saturating_sub(self.state_start_time.get_uc_timestamp());
+
+        if self.worst_poll.is_none_or(|(_, worst)| duration > worst) {
+            self.worst_poll = Some((task_id, duration));
+        }
+    }
+
+    /// Start of the current busy stretch: the end of the latest idle state in the retained
+    /// history (the start of the retained history without one). None while idle.
+    pub fn get_busy_since(&self) -> Option<EmbassyTime> {
+        if self.state == ExecutorState::Idle {
+            return None;
+        }
+
+        let last_idle_end = self
+            .state_history
+            .iter()
+            .rev()
+            .find(|e| e.state == ExecutorState::Idle)
+            .map(|e| e.end_time.get_uc_timestamp());
+        Some(last_idle_end.unwrap_or_else(|| {
+            self.state_history
+                .front()
+                .map_or(self.state_start_time, |e| e.start_time)
+                .get_uc_timestamp()
+        }))
+    }
+
+    /// Whether the executor has not been idle for longer than the pegged threshold: one of
+    /// its tasks likely never yields, starving the others
+    pub fn is_pegged(&self) -> bool {
+        let threshold = EmbassyTime::from_millis(PEGGED_THRESHOLD_MS.load(Ordering::Relaxed));
+        self.get_busy_since()
+            .is_some_and(|since| self.estimate_uc_now().saturating_sub(since) >= threshold)
+    }
+
+    /// Get the IDs of the tasks currently ready while the executor is idle
+    pub fn get_lost_wakeup_task_ids(&self) -> &[u32] {
+        &self.lost_wakeup_task_ids
+    }
+
+    /// Get the number of lost wakeups detected so far
+    pub fn get_lost_wakeup_count(&self) -> usize {
+        self.lost_wakeup_count
+    }
+
+    /// Detect tasks that are ready for longer than the threshold while the executor is idle
+    /// and its core is free. A ready task schedules its executor, so this points to a lost
+    /// wakeup. A preempted executor, or one starved by another executor busy on its core, is
+    /// not idle by choice.
+    fn check_lost_wakeups(&mut self, now: EmbassyTime) {
+        if self.state != ExecutorState::Idle {
+            self.lost_wakeup_task_ids.clear();
+            return;
+        }
+
+        let core_busy = !self.core_busy_by.is_empty();
+        let threshold_ms = LOST_WAKEUP_THRESHOLD_MS.load(Ordering::Relaxed) as u128;
+        for task in self.tasks.iter() {
+            let task_id = task.get_task_id();
+            let flagged = self.lost_wakeup_task_ids.contains(&task_id);
+            // time the task could have run
+            let ready_ms = now
+                .saturating_sub(
+                    task.get_state_start_time()
+                        .get_uc_timestamp()
+                        .max(self.core_free_since),
+                )
+                .as_millis();
+
+            if task.get_state() != &TaskTraceState::Waiting {
+                if flagged {
+                    self.lost_wakeup_task_ids.retain(|id| *id != task_id);
+                }
+            } else if !flagged && !core_busy && ready_ms >= threshold_ms {
+                self.lost_wakeup_task_ids.push(task_id);
+                self.lost_wakeup_count += 1;
+            }
+        }
+    }
+
+    /// Get the run of polls alternating between two tasks if it is a suspected ping-pong
+    pub fn get_ping_pong(&self) -> Option<PingPongRun> {
+        self.ping_pong.filter(|run| run.is_suspected())
+    }
+
+    /// Get the number of ping-pong runs detected so far
+    pub fn get_ping_pong_count(&self) -> usize {
+        self.ping_pong_count
+    }
+
+    /// Get the state and detection counts from before the latest trace item
+    pub fn get_before_update(&self) -> &ExecutorSnapshot {
+        &self.before_update
+    }
+
+    /// Track the polls alternating between two tasks. Without wake-source events the switch
+    /// rate is the only hint: tasks waking each other keep the executor busy with nothing but
+    /// their polls, while alternating polls woken by something else are interrupted by idle
+    /// phases.
+    fn record_poll_start(&mut self, task_id: u32, now: EmbassyTime) {
+        let was_suspected = self.get_ping_pong().is_some();
+        if !self
+            .ping_pong
+            .as_mut()
+            .is_some_and(|run| run.poll(task_id, now))
+        {
+            self.ping_pong = Some(PingPongRun::new(task_id, now));
+        }
+        if !was_suspected && self.get_ping_pong().is_some() {
+            self.ping_pong_count += 1;
+        }
+    }
+
     pub fn get_tasks(&self) -> &Vec<TaskTraceInfo> {
         &self.tasks
     }
@@ -198,21 +565,77 @@ impl ExecutorTraceInfo {
         self.tasks.iter_mut().find(|t| t.get_task_id() == task_id)
     }
 
+    /// Remove a task from this executor (e.g. to move it to another one)
+    pub fn take_task(&mut self, task_id: u32) -> Option<TaskTraceInfo> {
+        let index = self.tasks.iter().position(|t| t.get_task_id() == task_id)?;
+        Some(self.tasks.remove(index))
+    }
+
+    /// Take over a task of another executor, its events are tracked here from now on
+    pub fn adopt_task(&mut self, mut task: TaskTraceInfo) {
+        task.move_to_executor(self.executor_id, self.core_id);
+        self.tasks.push(task);
+    }
+
+    /// Track the other executors polling on this core
+    fn record_core_activity(&mut self, trace_item: &TraceItem) {
+        if trace_item.core_id != self.core_id {
+            return;
+        }
+        match trace_item.data {
+            TraceItemType::ExecutorPollStart { executor_id }
+                if executor_id != self.executor_id && !self.core_busy_by.contains(&executor_id) =>
+            {
+                self.core_busy_by.push(executor_id);
+            }
+            TraceItemType::ExecutorIdle { executor_id }
+                if self.core_busy_by.contains(&executor_id) =>
+            {
+                self.core_busy_by.retain(|id| *id != executor_id);
+                if self.core_busy_by.is_empty() {
+                    self.core_free_since = trace_item.time_pair.get_uc_timestamp();
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// Update belonging tasks based on a trace item
     fn update_tasks(&mut self, trace_item: &TraceItem) {
         // Check preemption state
         match self.state {
             ExecutorState::Polling | ExecutorState::Scheduling => {
                 // Check if we are beeing preempted
-                if let TraceItemType::ExecutorPollStart { executor_id } = trace_item.data {
-                    if executor_id != self.executor_id && trace_item.core_id == self.core_id {
-                        // preempt
-                        let prev_state = match self.state {
-                            ExecutorState::Scheduling => PreemptedPrevState::Scheduling,
-                            ExecutorState::Polling => PreemptedPrevState::Polling,
-                            _ => unreachable!(),
-                        };
+                if let TraceItemType::ExecutorPollStart { executor_id } = trace_item.data
+                    && executor_id != self.executor_id
+                    && trace_item.core_id == self.core_id
+                {
+                    // preempt
+                    let prev_state = match self.state {
+                        ExecutorState::Scheduling => PreemptedPrevState::Scheduling,
+                        ExecutorState::Polling => PreemptedPrevState::Polling,
+                        _ => unreachable!(),
+                    };
 
+                    self.preempted_by = vec![executor_id];
+                    self.set_new_state(
+                        ExecutorState::Preempted {
+                            by_executor_id: executor_id,
+                            prev_state,
+                        },
+                        trace_item.time_pair,
+                    );
+                }
+            }
+            ExecutorState::Preempted { prev_state, .. } => {
+                match trace_item.data {
+                    // an even higher prio executor preempts the one preempting us
+                    TraceItemType::ExecutorPollStart { executor_id }
+                        if executor_id != self.executor_id
+                            && trace_item.core_id == self.core_id
+                            && !self.preempted_by.contains(&executor_id) =>
+                    {
+                        self.preempted_by.push(executor_id);
                         self.set_new_state(
                             ExecutorState::Preempted {
                                 by_executor_id: executor_id,
@@ -221,37 +644,40 @@ impl ExecutorTraceInfo {
                             trace_item.time_pair,
                         );
                     }
-                }
-            }
-            ExecutorState::Preempted {
-                by_executor_id,
-                prev_state,
-            } => {
-                // Check if we can resume (the higher prio executor goes back to idle)
-                if let TraceItemType::ExecutorIdle { .. } = trace_item.data {
-                    if trace_item.data.get_executor_id() == by_executor_id {
-                        // resume
-                        self.set_new_state(prev_state.into(), trace_item.time_pair);
+                    // resume only once no higher prio executor is active anymore
+                    TraceItemType::ExecutorIdle { executor_id }
+                        if self.preempted_by.contains(&executor_id) =>
+                    {
+                        self.preempted_by.retain(|id| *id != executor_id);
+                        let new_state = match self.preempted_by.last() {
+                            Some(&running_id) => ExecutorState::Preempted {
+                                by_executor_id: running_id,
+                                prev_state,
+                            },
+                            None => prev_state.into(),
+                        };
+                        self.set_new_state(new_state, trace_item.time_pair);
                     }
+                    _ => {}
                 }
             }
             _ => {}
         }
 
         // Check if the task is for this executor and we list it
-        if trace_item.data.get_executor_id() == self.executor_id {
+        if trace_item.data.get_executor_id() == Some(self.executor_id) {
             // this is our executor ==> get task or create it
-            if let Some(task_id) = trace_item.data.get_task_id() {
-                if self.find_task_by_id(task_id).is_none() {
-                    // If the task does not exist, create it (probably a TaskNew event)
-                    let new_task = TaskTraceInfo::new(
-                        task_id,
-                        self.executor_id,
-                        self.core_id,
-                        trace_item.time_pair,
-                    );
-                    self.tasks.push(new_task);
-                }
+            if let Some(task_id) = trace_item.data.get_task_id()
+                && self.find_task_by_id(task_id).is_none()
+            {
+                // If the task does not exist, create it (probably a TaskNew event)
+                let new_task = TaskTraceInfo::new(
+                    task_id,
+                    self.executor_id,
+                    self.core_id,
+                    trace_item.time_pair,
+                );
+                self.tasks.push(new_task);
             }
         }
 
@@ -263,21 +689,39 @@ impl ExecutorTraceInfo {
 
     /// Run State Machine transition based on trace item
     pub fn update(&mut self, trace_item: &TraceItem) {
+        self.before_update = ExecutorSnapshot {
+            state: self.state,
+            lost_wakeup_count: self.lost_wakeup_count,
+            ping_pong_count: self.ping_pong_count,
+        };
+
         // Update tasks first
         self.update_tasks(trace_item);
 
         // Check that the trace item is for this executor
-        if trace_item.data.get_executor_id() == self.executor_id {
+        if trace_item.data.get_executor_id() == Some(self.executor_id) {
+            if let TraceItemType::ExecutorPollStart { .. } | TraceItemType::ExecutorIdle { .. } =
+                trace_item.data
+            {
+                self.integrity
+                    .check_order(trace_item.time_pair.get_uc_timestamp());
+                self.integrity
+                    .record(Self::check_transition(self.state, &trace_item.data));
+            }
+
             // Executor State machine transitions
 
             match self.state {
                 ExecutorState::Idle => {
                     if let TraceItemType::ExecutorPollStart { .. } = trace_item.data {
+                        self.ping_pong = None;
                         self.set_new_state(ExecutorState::Scheduling, trace_item.time_pair);
                     }
                 }
                 ExecutorState::Scheduling => {
-                    if let TraceItemType::TaskExecBegin { .. } = trace_item.data {
+                    if let TraceItemType::TaskExecBegin { task_id, .. } = trace_item.data {
+                        self.polled_task = Some((task_id, trace_item.time_pair));
+                        self.record_poll_start(task_id, trace_item.time_pair.get_uc_timestamp());
                         self.set_new_state(ExecutorState::Polling, trace_item.time_pair);
                     }
 
@@ -287,27 +731,26 @@ impl ExecutorTraceInfo {
                 }
                 ExecutorState::Polling => {
                     if let TraceItemType::TaskExecEnd { .. } = trace_item.data {
+                        self.record_poll_end(trace_item.time_pair.get_uc_timestamp());
                         self.set_new_state(ExecutorState::Scheduling, trace_item.time_pair);
                     }
                 }
                 _ => {}
             }
 
-            // Drain old history entries beyond max time (based on end-time)
-            let max_time_s = ComputerTime::from_s(HISTORY_MAX_TIME_S.load(Ordering::Relaxed));
-            let current_pc_time = trace_item.time_pair.get_pc_timestamp();
-            while let Some(front) = self.state_history.front() {
-                let entry_end_pc_time = front.end_time.get_pc_timestamp();
-
-                // Check if the difference is greater than max time
-                if current_pc_time.saturating_sub(entry_end_pc_time) > max_time_s {
-                    self.state_history.pop_front();
-                } else {
-                    break;
-                }
-            }
+            // Drain old history entries by the retention policy (based on end-time)
+            Retention::current().prune(
+                &mut self.state_history,
+                trace_item.time_pair.get_pc_timestamp(),
+                |entry| entry.end_time.get_pc_timestamp(),
+            );
         }
 
+        // Any trace item advances the uC time, also the ones of other executors
+        self.check_lost_wakeups(trace_item.time_pair.get_uc_timestamp());
+        // after the check: the core was busy or free up to this item
+        self.record_core_activity(trace_item);
+
         // calculate idle percentage by summing all statistics
         // let total_idle_time = self
         //     .statistics
@@ -355,8 +798,93 @@ impl ExecutorTraceInfo {
         // });
     }
 
+    /// Get the number of firmware resets in the session before this executor appeared
+    pub fn get_boot_generation(&self) -> u32 {
+        self.boot_generation
+    }
+
+    pub fn set_boot_generation(&mut self, boot_generation: u32) {
+        self.boot_generation = boot_generation;
+    }
+
+    /// Get the timestamp when the executor was created
+    pub fn get_created_at(&self) -> TimePair {
+        self.created_at
+    }
+
+    /// Start of the retained history of the executor and its tasks if older states were
+    /// already dropped (UC time)
+    pub fn get_retained_since(&self) -> Option<EmbassyTime> {
+        let own = self
+            .state_history
+            .front()
+            .map(|e| e.start_time.get_uc_timestamp())
+            .filter(|start| *start > self.created_at.get_uc_timestamp());
+        own.into_iter()
+            .chain(self.tasks.iter().filter_map(|t| t.get_retained_since()))
+            .max()
+    }
+
+    /// Copy of the executor and its tasks as they were at `time` (UC time): later history is
+    /// cut off and the states at `time` become the current ones. Counters (integrity, lost
+    /// wakeups) are not rewound. None if the executor did not exist yet.
+    pub fn rewound_to(&self, time: EmbassyTime) -> Option<Self> {
+        if self.created_at.get_uc_timestamp() > time {
+            return None;
+        }
+
+        let state_history: VecDeque<ExecutorHistoryEntry> = self
+            .state_history
+            .iter()
+            .take_while(|e| e.end_time.get_uc_timestamp() <= time)
+            .copied()
+            .collect();
+        let (state, state_start_time) = match self.state_history.get(state_history.len()) {
+            Some(entry) => (entry.state, entry.start_time),
+            None => (self.state, self.state_start_time),
+        };
+
+        Some(Self {
+            executor_id: self.executor_id,
+            executor_name: self.executor_name.clone(),
+            core_id: self.core_id,
+            boot_generation: self.boot_generation,
+            created_at: self.created_at,
+            state,
+            state_start_time,
+            preempted_by: Vec::new(),
+            snapshot_time: Some(time),
+            state_history,
+            tasks: self
+                .tasks
+                .iter()
+                .filter_map(|t| t.rewound_to(time))
+                .collect(),
+            integrity: self.integrity,
+            core_busy_by: Vec::new(),
+            core_free_since: self.core_free_since,
+            lost_wakeup_task_ids: Vec::new(),
+            lost_wakeup_count: self.lost_wakeup_count,
+            polled_task: None,
+            worst_poll: self.worst_poll,
+            ping_pong: None,
+            ping_pong_count: self.ping_pong_count,
+            before_update: self.before_update,
+            fixed_preemption_threshold: self.fixed_preemption_threshold,
+        })
+    }
+
+    /// Estimated current UC time (the snapshot time of a rewound copy)
+    pub fn estimate_uc_now(&self) -> EmbassyTime {
+        self.extrapolate_current_state_duration()
+    }
+
     /// Extrapolate the duration spent in the current state till now (UC time)
     fn extrapolate_current_state_duration(&self) -> EmbassyTime {
+        if let Some(time) = self.snapshot_time {
+            return time;
+        }
+
         // get pc time diff between current time and time of state start
         let pc_time_diff = self.state_start_time.get_pc_timestamp().diff_to_now();
 
@@ -364,17 +892,117 @@ impl ExecutorTraceInfo {
         self.state_start_time.get_uc_timestamp() + pc_time_diff
     }
 
-    /// Calculate CPU utilization based on state history using time spent in POLLING and SCHEDULING states over total time
-    pub fn calculate_cpu_utilization(&self) -> f32 {
+    /// Total time this executor was preempted per preempting executor (from the history,
+    /// without phantom preemptions)
+    pub fn calc_preempted_durations(&self) -> BTreeMap<u32, EmbassyTime> {
+        let mut durations: BTreeMap<u32, EmbassyTime> = BTreeMap::new();
+        for entry in self.state_history.iter() {
+            if let ExecutorState::Preempted { by_executor_id, .. } = entry.state
+                && !is_phantom_preemption(
+                    entry.start_time,
+                    entry.end_time,
+                    preemption_threshold(self.fixed_preemption_threshold),
+                )
+            {
+                let duration = entry
+                    .end_time
+                    .get_uc_timestamp()
+                    .saturating_sub(entry.start_time.get_uc_timestamp());
+                *durations.entry(by_executor_id).or_insert(EmbassyTime::ZERO) += duration;
+            }
+        }
+        durations
+    }
+
+    /// Poll cycles of the history as (work, interval): a cycle lasts from one wakeup (poll
+    /// start out of idle) to the next; work is the time spent scheduling and polling within
+    /// it (preemptions are not own work). Only complete cycles are included.
+    pub fn calc_poll_cycles(&self) -> Vec<(EmbassyTime, EmbassyTime)> {
+        let entries: Vec<&ExecutorHistoryEntry> = self.state_history.iter().collect();
+        let wakeups: Vec<usize> = (1..entries.len())
+            .filter(|&i| {
+                entries[i - 1].state == ExecutorState::Idle
+                    && entries[i].state == ExecutorState::Scheduling
+            })
+            .collect();
+
+        wakeups
+            .windows(2)
+            .map(|pair| {
+                let cycle = &entries[pair[0]..pair[1]];
+                let work = cycle
+                    .iter()
+                    .filter(|e| {
+                        matches!(e.state, ExecutorState::Scheduling | ExecutorState::Polling)
+                    })
+                    .fold(EmbassyTime::ZERO, |acc, e| {
+                        acc + e
+                            .end_time
+                            .get_uc_timestamp()
+                            .saturating_sub(e.start_time.get_uc_timestamp())
+                    });
+                let interval = entries[pair[1]]
+                    .start_time
+                    .get_uc_timestamp()
+                    .saturating_sub(entries[pair[0]].start_time.get_uc_timestamp());
+                (work, interval)
+            })
+            .collect()
+    }
+
+    /// Mean share of the time between wakeups spent working (0.0 - 100.0), None without a
+    /// complete poll cycle. Close to 100% the executor cannot keep up with its wakeups.
+    pub fn calc_budget_utilization(&self) -> Option<f32> {
+        let ratios: Vec<f32> = self
+            .calc_poll_cycles()
+            .iter()
+            .filter(|(_, interval)| *interval > EmbassyTime::ZERO)
+            .map(|(work, interval)| work.as_secs_f32() / interval.as_secs_f32())
+            .collect();
+
+        if ratios.is_empty() {
+            None
+        } else {
+            Some(ratios.iter().sum::<f32>() / ratios.len() as f32 * 100.0)
+        }
+    }
+
+    /// Start of the trailing `window` ending now (UC time), ZERO for the whole history
+    fn window_start(&self, window: Option<Duration>) -> EmbassyTime {
+        window.map_or(EmbassyTime::ZERO, |window| {
+            self.extrapolate_current_state_duration()
+                .saturating_sub(EmbassyTime::ZERO + window)
+        })
+    }
+
+    /// Time spent scheduling and polling in the history as (scheduling, polling), without
+    /// preemptions. With a `window` only the time within the trailing window counts.
+    pub fn calc_work_split(&self, window: Option<Duration>) -> (EmbassyTime, EmbassyTime) {
+        let window_start = self.window_start(window);
+        self.state_history.iter().fold(
+            (EmbassyTime::ZERO, EmbassyTime::ZERO),
+            |(scheduling, polling), entry| {
+                let duration = entry.uc_duration_since(window_start);
+                match entry.state {
+                    ExecutorState::Scheduling => (scheduling + duration, polling),
+                    ExecutorState::Polling => (scheduling, polling + duration),
+                    _ => (scheduling, polling),
+                }
+            },
+        )
+    }
+
+    /// Calculate CPU utilization based on state history using time spent in POLLING and
+    /// SCHEDULING states over total time, over the trailing `window` if given (entries
+    /// straddling its start are clipped)
+    pub fn calculate_cpu_utilization(&self, window: Option<Duration>) -> f32 {
+        let window_start = self.window_start(window);
         let mut total_time_s = 0.0;
         let mut active_time_s = 0.0;
 
         // add up all history entries
         for entry in self.state_history.iter() {
-            let start_pc_time = entry.start_time.get_pc_timestamp();
-            let end_pc_time = entry.end_time.get_pc_timestamp();
-
-            let duration_s = end_pc_time.saturating_sub(start_pc_time).as_secs_f32();
+            let duration_s = entry.uc_duration_since(window_start).as_secs_f32();
             total_time_s += duration_s;
 
             match entry.state {
@@ -387,8 +1015,8 @@ impl ExecutorTraceInfo {
 
         // add current state time
         let estimated_uc_time = self.extrapolate_current_state_duration();
-        let estimated_duration =
-            estimated_uc_time.saturating_sub(self.state_start_time.get_uc_timestamp());
+        let estimated_duration = estimated_uc_time
+            .saturating_sub(self.state_start_time.get_uc_timestamp().max(window_start));
         total_time_s += estimated_duration.as_secs_f32();
 
         match self.state {
@@ -405,3 +1033,830 @@ impl ExecutorTraceInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    use crate::tracing::{
+        task::TaskTraceState,
+        test_harness::{at_ms, feed_executor, item, item_us},
+        thresholds::THRESHOLDS,
+        time::EmbassyTime,
+        trace_data::TraceItemType,
+    };
+
+    use super::{ExecutorState, ExecutorTraceInfo, PING_PONG_MIN_SWITCHES, PreemptedPrevState};
+
+    /// Collect (state, start ms, end ms) of all history entries
+    fn history_of(executor: &ExecutorTraceInfo) -> Vec<(ExecutorState, u128, u128)> {
+        executor
+            .get_state_history()
+            .iter()
+            .map(|e| {
+                (
+                    e.get_state(),
+                    e.get_start_time().get_uc_timestamp().as_millis(),
+                    e.get_end_time().get_uc_timestamp().as_millis(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_state_machine_lifecycle() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        assert_eq!(executor.get_state(), &ExecutorState::Idle);
+
+        feed_executor(
+            &mut executor,
+            &[
+                item(
+                    0,
+                    0,
+                    TraceItemType::TaskNew {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(
+                    10,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(12, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(
+                    14,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(
+                    20,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(22, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+            ],
+        );
+
+        assert_eq!(executor.get_state(), &ExecutorState::Idle);
+        assert_eq!(
+            history_of(&executor),
+            vec![
+                (ExecutorState::Idle, 0, 12),
+                (ExecutorState::Scheduling, 12, 14),
+                (ExecutorState::Polling, 14, 20),
+                (ExecutorState::Scheduling, 20, 22),
+            ]
+        );
+
+        // Task was created and followed the lifecycle
+        assert_eq!(executor.count_tasks(), 1);
+        let task = executor.find_task_by_id(7).unwrap();
+        assert_eq!(task.get_state(), &TaskTraceState::Idle);
+    }
+
+    #[test]
+    fn test_state_machine_preemption() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+
+        feed_executor(
+            &mut executor,
+            &[
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(
+                    12,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                // executor on another core does not preempt us
+                item(14, 1, TraceItemType::ExecutorPollStart { executor_id: 3 }),
+            ],
+        );
+        assert_eq!(executor.get_state(), &ExecutorState::Polling);
+
+        // higher priority executor on the same core preempts us
+        feed_executor(
+            &mut executor,
+            &[item(
+                20,
+                0,
+                TraceItemType::ExecutorPollStart { executor_id: 2 },
+            )],
+        );
+        let preempted = ExecutorState::Preempted {
+            by_executor_id: 2,
+            prev_state: PreemptedPrevState::Polling,
+        };
+        assert_eq!(executor.get_state(), &preempted);
+
+        // preempting executor goes idle --> resume previous state
+        feed_executor(
+            &mut executor,
+            &[
+                item(30, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                item(
+                    35,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+            ],
+        );
+        assert_eq!(executor.get_state(), &ExecutorState::Scheduling);
+        assert_eq!(
+            history_of(&executor),
+            vec![
+                (ExecutorState::Idle, 0, 10),
+                (ExecutorState::Scheduling, 10, 12),
+                (ExecutorState::Polling, 12, 20),
+                (preempted, 20, 30),
+                (ExecutorState::Polling, 30, 35),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_state_machine_phantom_preemption() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        executor.set_preemption_threshold(EmbassyTime::from_micros(2));
+
+        feed_executor(
+            &mut executor,
+            &[
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                // preempt/resume pair 1us apart (below the threshold) --> ignored
+                item_us(
+                    15_000,
+                    0,
+                    TraceItemType::ExecutorPollStart { executor_id: 2 },
+                ),
+                item_us(15_001, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                item(20, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+            ],
+        );
+        assert_eq!(executor.get_state(), &ExecutorState::Idle);
+        assert_eq!(
+            history_of(&executor),
+            vec![
+                (ExecutorState::Idle, 0, 10),
+                (ExecutorState::Scheduling, 10, 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preempted_durations_without_phantom_preemptions() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        executor.set_preemption_threshold(EmbassyTime::from_micros(2));
+
+        feed_executor(
+            &mut executor,
+            &[
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                // preempted by 2 for 1us only, then by 3 (kept in the history)
+                item_us(
+                    15_000,
+                    0,
+                    TraceItemType::ExecutorPollStart { executor_id: 2 },
+                ),
+                item_us(
+                    15_001,
+                    0,
+                    TraceItemType::ExecutorPollStart { executor_id: 3 },
+                ),
+                item_us(20_001, 0, TraceItemType::ExecutorIdle { executor_id: 3 }),
+                item_us(21_000, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                item(30, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+            ],
+        );
+
+        let durations = executor.calc_preempted_durations();
+        assert_eq!(durations[&2], EmbassyTime::from_micros(999));
+        assert_eq!(durations[&3], EmbassyTime::from_micros(5_000));
+    }
+
+    #[test]
+    fn test_state_machine_preempted_while_scheduling() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+
+        feed_executor(
+            &mut executor,
+            &[
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(12, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            ],
+        );
+        assert_eq!(
+            executor.get_state(),
+            &ExecutorState::Preempted {
+                by_executor_id: 2,
+                prev_state: PreemptedPrevState::Scheduling,
+            }
+        );
+
+        feed_executor(
+            &mut executor,
+            &[item(15, 0, TraceItemType::ExecutorIdle { executor_id: 2 })],
+        );
+        assert_eq!(executor.get_state(), &ExecutorState::Scheduling);
+    }
+
+    #[test]
+    fn test_state_machine_nested_preemption() {
+        // low (1) < mid (2) < high (3) priority on the same core
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+
+        feed_executor(
+            &mut executor,
+            &[
+                item(
+                    5,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(
+                    12,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(20, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+                item(30, 0, TraceItemType::ExecutorPollStart { executor_id: 3 }),
+                // mid goes idle first, high is still active --> stay preempted
+                item(40, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            ],
+        );
+        let by = |by_executor_id| ExecutorState::Preempted {
+            by_executor_id,
+            prev_state: PreemptedPrevState::Polling,
+        };
+        assert_eq!(executor.get_state(), &by(3));
+        assert_eq!(
+            executor.find_task_by_id(7).unwrap().get_state(),
+            &TaskTraceState::Preempted { by_executor_id: 3 }
+        );
+
+        // last higher priority executor goes idle --> resume (the task once its executor
+        // is active again)
+        feed_executor(
+            &mut executor,
+            &[item(50, 0, TraceItemType::ExecutorIdle { executor_id: 3 })],
+        );
+        assert_eq!(executor.get_state(), &ExecutorState::Polling);
+        assert_eq!(
+            executor.find_task_by_id(7).unwrap().get_state(),
+            &TaskTraceState::Preempted { by_executor_id: 3 }
+        );
+
+        // strictly nested order: high idle hands back to mid, mid idle resumes
+        feed_executor(
+            &mut executor,
+            &[
+                item(60, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+                item(65, 0, TraceItemType::ExecutorPollStart { executor_id: 3 }),
+                item(70, 0, TraceItemType::ExecutorIdle { executor_id: 3 }),
+            ],
+        );
+        assert_eq!(executor.get_state(), &by(2));
+        feed_executor(
+            &mut executor,
+            &[item(80, 0, TraceItemType::ExecutorIdle { executor_id: 2 })],
+        );
+        assert_eq!(executor.get_state(), &ExecutorState::Polling);
+
+        assert_eq!(
+            history_of(&executor)[3..],
+            vec![
+                (by(2), 20, 30),
+                (by(3), 30, 50),
+                (ExecutorState::Polling, 50, 60),
+                (by(2), 60, 65),
+                (by(3), 65, 70),
+                (by(2), 70, 80),
+            ]
+        );
+        assert_eq!(
+            executor
+                .calc_preempted_durations()
+                .into_iter()
+                .map(|(id, d)| (id, d.as_millis()))
+                .collect::<Vec<_>>(),
+            vec![(2, 25), (3, 25)]
+        );
+    }
+
+    #[test]
+    fn test_state_machine_missing_task_new() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+
+        // Visor attached after the task was spawned --> first event is an exec begin
+        feed_executor(
+            &mut executor,
+            &[
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(
+                    12,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(
+                    15,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+            ],
+        );
+
+        // Task is created on first sight but stays spawned until it is awoken
+        let task = executor.find_task_by_id(7).unwrap();
+        assert_eq!(task.get_state(), &TaskTraceState::Spawned);
+        assert_eq!(task.get_created_at(), at_ms(12));
+
+        // The next wake up brings the task into the regular lifecycle
+        feed_executor(
+            &mut executor,
+            &[
+                item(
+                    20,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(
+                    25,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+            ],
+        );
+        let task = executor.find_task_by_id(7).unwrap();
+        assert_eq!(task.get_state(), &TaskTraceState::Running);
+    }
+
+    #[test]
+    fn test_lost_wakeup_detection() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        let ready = |ms, task_id| {
+            item(
+                ms,
+                0,
+                TraceItemType::TaskReadyBegin {
+                    executor_id: 1,
+                    task_id,
+                },
+            )
+        };
+
+        // Woken task polled shortly after --> regular wakeup
+        feed_executor(
+            &mut executor,
+            &[
+                ready(10, 7),
+                item(12, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(
+                    14,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(
+                    16,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(18, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+                // other executor keeps the trace going
+                item(500, 1, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            ],
+        );
+        assert_eq!(executor.get_lost_wakeup_count(), 0);
+
+        // Task ready, but the executor never starts polling
+        feed_executor(
+            &mut executor,
+            &[
+                ready(600, 7),
+                item(650, 1, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            ],
+        );
+        assert!(executor.get_lost_wakeup_task_ids().is_empty());
+
+        feed_executor(
+            &mut executor,
+            &[item(
+                700,
+                1,
+                TraceItemType::ExecutorPollStart { executor_id: 2 },
+            )],
+        );
+        assert_eq!(executor.get_lost_wakeup_task_ids(), &[7]);
+        assert_eq!(executor.get_lost_wakeup_count(), 1);
+
+        // Still stuck --> reported once
+        feed_executor(
+            &mut executor,
+            &[item(800, 1, TraceItemType::ExecutorIdle { executor_id: 2 })],
+        );
+        assert_eq!(executor.get_lost_wakeup_count(), 1);
+
+        // Executor finally polls --> no longer suspected, count kept
+        feed_executor(
+            &mut executor,
+            &[item(
+                900,
+                0,
+                TraceItemType::ExecutorPollStart { executor_id: 1 },
+            )],
+        );
+        assert!(executor.get_lost_wakeup_task_ids().is_empty());
+        assert_eq!(executor.get_lost_wakeup_count(), 1);
+    }
+
+    #[test]
+    fn test_no_lost_wakeup_while_starved() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+
+        // Higher priority executor busy on the same core while the task is ready
+        feed_executor(
+            &mut executor,
+            &[
+                item(5, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+                item(
+                    10,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(
+                    300,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 2,
+                        task_id: 8,
+                    },
+                ),
+            ],
+        );
+        assert_eq!(executor.get_lost_wakeup_count(), 0);
+
+        // The ready time counts from when the core became free
+        feed_executor(
+            &mut executor,
+            &[
+                item(400, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                item(450, 1, TraceItemType::ExecutorPollStart { executor_id: 3 }),
+            ],
+        );
+        assert_eq!(executor.get_lost_wakeup_count(), 0);
+
+        feed_executor(
+            &mut executor,
+            &[item(510, 1, TraceItemType::ExecutorIdle { executor_id: 3 })],
+        );
+        assert_eq!(executor.get_lost_wakeup_task_ids(), &[7]);
+    }
+
+    #[test]
+    fn test_budget_utilization() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+
+        // woken every 10ms, working 4ms per cycle (1ms scheduling + 2ms polling + 1ms scheduling)
+        let mut items = Vec::new();
+        for cycle in 0..5 {
+            let t = 10 + cycle * 10;
+            items.extend([
+                item(t, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(
+                    t + 1,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(
+                    t + 3,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(t + 4, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+            ]);
+        }
+        feed_executor(&mut executor, &items);
+
+        // 5 wakeups --> 4 complete cycles
+        let cycles = executor.calc_poll_cycles();
+        assert_eq!(cycles.len(), 4);
+        assert!(
+            cycles
+                .iter()
+                .all(|(work, interval)| work.as_millis() == 4 && interval.as_millis() == 10)
+        );
+
+        let utilization = executor.calc_budget_utilization().unwrap();
+        assert!((utilization - 40.0).abs() < 0.01);
+
+        // no complete cycle yet
+        let executor = ExecutorTraceInfo::new(2, 0, at_ms(0));
+        assert_eq!(executor.calc_budget_utilization(), None);
+    }
+
+    #[test]
+    fn test_cpu_utilization_window() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+
+        // busy 10ms at 100ms and 50ms at 300ms, idle otherwise
+        let mut items = Vec::new();
+        for (start, busy) in [(100, 10), (300, 50)] {
+            items.extend([
+                item(
+                    start,
+                    0,
+                    TraceItemType::ExecutorPollStart { executor_id: 1 },
+                ),
+                item(
+                    start + busy,
+                    0,
+                    TraceItemType::ExecutorIdle { executor_id: 1 },
+                ),
+            ]);
+        }
+        feed_executor(&mut executor, &items);
+        let executor = executor.rewound_to(EmbassyTime::from_millis(400)).unwrap();
+
+        // 60ms of 400ms overall, 50ms of the last 100ms
+        assert!((executor.calculate_cpu_utilization(None) - 15.0).abs() < 0.01);
+        let window = Some(Duration::from_millis(100));
+        assert!((executor.calculate_cpu_utilization(window) - 50.0).abs() < 0.01);
+
+        // last 80ms start within the busy span at 300-350ms, which is clipped to 30ms
+        let window = Some(Duration::from_millis(80));
+        assert!((executor.calculate_cpu_utilization(window) - 30.0 / 80.0 * 100.0).abs() < 0.01);
+        assert_eq!(executor.calc_work_split(window).0.as_millis(), 30);
+        assert_eq!(executor.calc_work_split(None).0.as_millis(), 60);
+    }
+
+    #[test]
+    fn test_worst_poll() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        assert_eq!(executor.get_worst_poll(), None);
+
+        let poll = |task_id, begin, end| {
+            [
+                item(
+                    begin,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id,
+                    },
+                ),
+                item(
+                    end,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id,
+                    },
+                ),
+            ]
+        };
+
+        // task 7 polls 2ms, task 8 polls 5ms
+        let mut items = vec![item(
+            10,
+            0,
+            TraceItemType::ExecutorPollStart { executor_id: 1 },
+        )];
+        items.extend(poll(7, 11, 13));
+        items.extend(poll(8, 14, 19));
+        items.push(item(20, 0, TraceItemType::ExecutorIdle { executor_id: 1 }));
+        feed_executor(&mut executor, &items);
+        assert_eq!(
+            executor.get_worst_poll(),
+            Some((8, EmbassyTime::from_millis(5)))
+        );
+
+        // task 7 polls 9ms but is preempted for 6ms of it --> still task 8
+        feed_executor(
+            &mut executor,
+            &[
+                item(30, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(
+                    31,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(32, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+                item(38, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                item(
+                    40,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+            ],
+        );
+        assert_eq!(
+            executor.get_worst_poll(),
+            Some((8, EmbassyTime::from_millis(5)))
+        );
+    }
+
+    #[test]
+    fn test_pegged_executor() {
+        let exec_begin = |ms, executor_id| {
+            item(
+                ms,
+                0,
+                TraceItemType::TaskExecBegin {
+                    executor_id,
+                    task_id: 7,
+                },
+            )
+        };
+        let exec_end = |ms, executor_id| {
+            item(
+                ms,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id,
+                    task_id: 7,
+                },
+            )
+        };
+
+        // task 7 never yields --> the executor never goes idle again
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        feed_executor(
+            &mut executor,
+            &[
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                exec_begin(11, 1),
+            ],
+        );
+        let at = |executor: &ExecutorTraceInfo, ms| {
+            executor.rewound_to(EmbassyTime::from_millis(ms)).unwrap()
+        };
+        assert_eq!(at(&executor, 5).get_busy_since(), None);
+        assert_eq!(
+            at(&executor, 500).get_busy_since(),
+            Some(EmbassyTime::from_millis(10))
+        );
+        assert!(!at(&executor, 500).is_pegged());
+        assert!(at(&executor, 1500).is_pegged());
+
+        // the threshold is read on every check, so adjusting it changes the flag right away
+        let pegged_threshold = THRESHOLDS
+            .iter()
+            .find(|threshold| threshold.key == "pegged-threshold-ms")
+            .unwrap();
+        let default_ms = pegged_threshold.get();
+        pegged_threshold.set(2000);
+        assert!(!at(&executor, 1500).is_pegged());
+        pegged_threshold.adjust(-6);
+        assert_eq!(pegged_threshold.get(), 1400);
+        assert!(at(&executor, 1500).is_pegged());
+        pegged_threshold.set(default_ms);
+
+        // busy most of the time but going idle in between --> high CPU, not pegged
+        let mut executor = ExecutorTraceInfo::new(2, 0, at_ms(0));
+        for start in (0..1500).step_by(100) {
+            feed_executor(
+                &mut executor,
+                &[
+                    item(
+                        start + 1,
+                        0,
+                        TraceItemType::ExecutorPollStart { executor_id: 2 },
+                    ),
+                    exec_begin(start + 2, 2),
+                    exec_end(start + 98, 2),
+                    item(
+                        start + 99,
+                        0,
+                        TraceItemType::ExecutorIdle { executor_id: 2 },
+                    ),
+                ],
+            );
+        }
+        assert!(!at(&executor, 1450).is_pegged());
+    }
+
+    #[test]
+    fn test_ping_pong_detection() {
+        // poll of `task_id` from `us` for 10us
+        let poll = |us: u64, task_id| {
+            [
+                TraceItemType::TaskReadyBegin {
+                    executor_id: 1,
+                    task_id,
+                },
+                TraceItemType::TaskExecBegin {
+                    executor_id: 1,
+                    task_id,
+                },
+                TraceItemType::TaskExecEnd {
+                    executor_id: 1,
+                    task_id,
+                },
+            ]
+            .into_iter()
+            .zip([us, us, us + 10])
+            .map(|(data, us)| item_us(us, 0, data))
+            .collect::<Vec<_>>()
+        };
+        let poll_start = |us| item_us(us, 0, TraceItemType::ExecutorPollStart { executor_id: 1 });
+        let idle = |us| item_us(us, 0, TraceItemType::ExecutorIdle { executor_id: 1 });
+
+        let min_switches = PING_PONG_MIN_SWITCHES.load(Ordering::Relaxed);
+
+        // tasks 7 and 8 wake each other, the executor never goes idle
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        let mut items = vec![poll_start(0)];
+        for i in 0..min_switches {
+            items.extend(poll(1 + i * 20, if i % 2 == 0 { 7 } else { 8 }));
+        }
+        feed_executor(&mut executor, &items);
+        assert_eq!(executor.get_ping_pong(), None);
+
+        // one more switch reaches the threshold
+        feed_executor(&mut executor, &poll(2_000, 7));
+        let run = executor.get_ping_pong().unwrap();
+        assert_eq!(run.task_ids, (7, 8));
+        assert_eq!(run.switches as u64, min_switches);
+        assert_eq!(executor.get_ping_pong_count(), 1);
+
+        // going idle ends the run
+        feed_executor(&mut executor, &[idle(2_100), poll_start(2_200)]);
+        feed_executor(&mut executor, &poll(2_201, 8));
+        assert_eq!(executor.get_ping_pong(), None);
+        assert_eq!(executor.get_ping_pong_count(), 1);
+
+        // the same alternation woken from outside (idle in between) is no ping-pong
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        for i in 0..2 * min_switches {
+            let us = i * 100;
+            feed_executor(&mut executor, &[poll_start(us)]);
+            feed_executor(&mut executor, &poll(us + 1, if i % 2 == 0 { 7 } else { 8 }));
+            feed_executor(&mut executor, &[idle(us + 50)]);
+        }
+        assert_eq!(executor.get_ping_pong_count(), 0);
+
+        // alternating slowly (a producer handing over work every few milliseconds) neither
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        let mut items = vec![poll_start(0)];
+        for i in 0..2 * min_switches {
+            items.extend(poll(1 + i * 5_000, if i % 2 == 0 { 7 } else { 8 }));
+        }
+        feed_executor(&mut executor, &items);
+        assert_eq!(executor.get_ping_pong(), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Startup header of the beacon: one record declaring everything the visor needs to read the
+//! trace (instead of a separate event each):
+//!
+//! `[<timestamp>, <core_id>, Header, schema=<version>, tick_hz=<rate>, cores=<count>,
+//! core_ids=<bit set>, firmware=<id>]`
+//!
+//! Every field is optional, missing ones keep the visor's defaults (a header of an older beacon
+//! may lack some of them). Unknown keys are skipped, so newer beacons can add fields. The core
+//! ids are a binary bit set (`0b101`: cores 0 and 2); without them the cores are 0..count. The
+//! firmware id is free text and therefore the last field (commas are kept).
+
+use std::str::FromStr;
+
+use crate::tracing::trace_data::TraceParseError;
+
+/// Timestamp ticks per second without a declared rate (the beacon publishes microseconds)
+pub const DEFAULT_TICK_HZ: u64 = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceHeader {
+    /// Version of the beacon's trace schema (see `SchemaVersion`)
+    pub schema_version: Option<u32>,
+    /// Ticks per second of the timestamps of the following records
+    pub tick_hz: u64,
+    /// Ids of the microcontroller's cores, ascending (see `Topology`)
+    pub core_ids: Option<Vec<u32>>,
+    /// Identifier of the firmware build (see `FirmwareId`)
+    pub firmware_id: Option<String>,
+}
+
+impl Default for TraceHeader {
+    fn default() -> Self {
+        Self {
+            schema_version: None,
+            tick_hz: DEFAULT_TICK_HZ,
+            core_ids: None,
+            firmware_id: None,
+        }
+    }
+}
+
+impl TraceHeader {
+    /// Payload format: <key>=<value>, ... (see the module documentation)
+    pub fn parse(payload: &[&str]) -> Result<Self, TraceParseError> {
+        let mut header = TraceHeader::default();
+        for (index, field) in payload.iter().enumerate() {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or(TraceParseError::InvalidEventPayload)?;
+            match key.trim() {
+                "schema" => header.schema_version = Some(number(value)?),
+                "cores" => {
+                    let core_count: u32 = number(value)?;
+                    // the ids are more precise (the order of the fields is not fixed)
+                    if header.core_ids.is_none() {
+                        header.core_ids = Some((0..core_count).collect());
+                    }
+                }
+                "core_ids" => header.core_ids = Some(core_id_set(value)?),
+                "tick_hz" => {
+                    header.tick_hz = number(value)?;
+                    if header.tick_hz == 0 {
+                        return Err(TraceParseError::InvalidEventPayload);
+                    }
+                }
+                "firmware" => {
+                    let id = std::iter::once(value)
+                        .chain(payload[index + 1..].iter().copied())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                        .trim()
+                        .to_string();
+                    if id.is_empty() {
+                        return Err(TraceParseError::InvalidEventPayload);
+                    }
+                    header.firmware_id = Some(id);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Ok(header)
+    }
+}
+
+/// Ids of the set bits of a binary bit set (`0b101`: 0 and 2), at least one
+fn core_id_set(value: &str) -> Result<Vec<u32>, TraceParseError> {
+    let bits = value
+        .trim()
+        .strip_prefix("0b")
+        .ok_or(TraceParseError::InvalidEventPayload)?;
+    let set = u32::from_str_radix(bits, 2).map_err(|_| TraceParseError::InvalidEventPayload)?;
+    let core_ids: Vec<u32> = (0..u32::BITS).filter(|id| set & (1 << id) != 0).collect();
+    if core_ids.is_empty() {
+        return Err(TraceParseError::InvalidEventPayload);
+    }
+    Ok(core_ids)
+}
+
+fn number<T: FromStr>(value: &str) -> Result<T, TraceParseError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| TraceParseError::InvalidEventPayload)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{
+        time::{ComputerTime, EmbassyTime},
+        trace_data::{TraceItem, TraceItemType, TraceProtocol},
+    };
+
+    use super::*;
+
+    fn parse_header(line: &str) -> Result<TraceHeader, TraceParseError> {
+        match TraceItem::parse_from_line(line, ComputerTime::now())?.data {
+            TraceItemType::Header { header } => Ok(header),
+            data => panic!("Expected Header, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn test_full_header() {
+        let line = "embassy executor tracer - [5, 0, Header, schema=1, tick_hz=32768, cores=2, \
+                    core_ids=0b101, firmware=app,release v1.4] - embassy executor tracer";
+        assert_eq!(
+            parse_header(line).unwrap(),
+            TraceHeader {
+                schema_version: Some(1),
+                tick_hz: 32768,
+                core_ids: Some(vec![0, 2]),
+                firmware_id: Some(String::from("app,release v1.4")),
+            }
+        );
+
+        // the timestamps of the following records are in ticks of the declared rate
+        let mut protocol = TraceProtocol::default();
+        protocol.apply_header(&parse_header(line).unwrap());
+        let item = protocol
+            .parse_line("[65536, 0, TaskNew, 1, 2]", ComputerTime::now())
+            .remove(0)
+            .unwrap();
+        assert_eq!(
+            item.time_pair.get_uc_timestamp(),
+            EmbassyTime::from_millis(2000)
+        );
+    }
+
+    #[test]
+    fn test_partial_header() {
+        // older beacon: only the schema, plus a key this visor does not know
+        let header = parse_header("[5, 0, Header, schema=1, crc=on]").unwrap();
+        assert_eq!(
+            header,
+            TraceHeader {
+                schema_version: Some(1),
+                ..TraceHeader::default()
+            }
+        );
+
+        // older beacon: only the core count
+        let header = parse_header("[5, 0, Header, schema=1, cores=2]").unwrap();
+        assert_eq!(header.core_ids, Some(vec![0, 1]));
+
+        for line in [
+            "[5, 0, Header, schema=one]",
+            "[5, 0, Header, core_ids=5]",
+            "[5, 0, Header, core_ids=0b0]",
+            "[5, 0, Header, tick_hz=0]",
+            "[5, 0, Header, cores]",
+            "[5, 0, Header, firmware= ]",
+        ] {
+            assert_eq!(
+                parse_header(line),
+                Err(TraceParseError::InvalidEventPayload),
+                "{}",
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Export of the retained state change history of all tasks and executors as newline
+//! delimited JSON (one record per state), for analysis outside of the visor.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::tracing::{
+    executor::{ExecutorState, ExecutorTraceInfo},
+    markers::UserMarker,
+    task::TaskTraceState,
+    time::TimePair,
+};
+
+/// One state of a task or executor, from its start till the next state change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HistoryRecord {
+    /// "task" or "executor"
+    pub entity_type: &'static str,
+    pub id: u32,
+    pub name: String,
+    pub state: &'static str,
+    /// Number of firmware resets in the session before the entity appeared
+    pub boot_generation: u32,
+    /// uC timestamps in microseconds
+    pub start_uc_us: u128,
+    pub end_uc_us: u128,
+    /// PC timestamps in microseconds since the visor started
+    pub start_pc_us: u128,
+    pub end_pc_us: u128,
+    /// Firmware id reported by the beacon (null if it never reported one)
+    pub firmware_id: Option<String>,
+}
+
+impl HistoryRecord {
+    fn new(
+        entity_type: &'static str,
+        id: u32,
+        name: String,
+        state: &'static str,
+        boot_generation: u32,
+        start: TimePair,
+        end: TimePair,
+    ) -> Self {
+        Self {
+            entity_type,
+            id,
+            name,
+            state,
+            boot_generation,
+            start_uc_us: start.get_uc_timestamp().as_micros(),
+            end_uc_us: end.get_uc_timestamp().as_micros(),
+            start_pc_us: start.get_pc_timestamp().as_micros(),
+            end_pc_us: end.get_pc_timestamp().as_micros(),
+            firmware_id: None,
+        }
+    }
+}
+
+fn task_state_name(state: TaskTraceState) -> &'static str {
+    match state {
+        TaskTraceState::Spawned => "Spawned",
+        TaskTraceState::Waiting => "Waiting",
+        TaskTraceState::Running => "Running",
+        TaskTraceState::Preempted { .. } => "Preempted",
+        TaskTraceState::Idle => "Idle",
+        TaskTraceState::Ended => "Ended",
+    }
+}
+
+fn executor_state_name(state: ExecutorState) -> &'static str {
+    match state {
+        ExecutorState::Idle => "Idle",
+        ExecutorState::Scheduling => "Scheduling",
+        ExecutorState::Preempted { .. } => "Preempted",
+        ExecutorState::Polling => "Polling",
+    }
+}
+
+/// Records of the user markers (state "Marker", starting and ending at the marker time)
+pub fn marker_records(markers: &[UserMarker]) -> Vec<HistoryRecord> {
+    markers
+        .iter()
+        .map(|marker| {
+            HistoryRecord::new(
+                "marker",
+                marker.number,
+                marker.label.clone(),
+                "Marker",
+                marker.boot_generation,
+                marker.time_pair(),
+                marker.time_pair(),
+            )
+        })
+        .collect()
+}
+
+/// Records of the retained history of every executor, each followed by its tasks
+pub fn history_records<'a>(
+    executors: impl IntoIterator<Item = &'a ExecutorTraceInfo>,
+) -> Vec<HistoryRecord> {
+    let mut records = Vec::new();
+
+    for executor in executors {
+        for entry in executor.get_state_history() {
+            records.push(HistoryRecord::new(
+                "executor",
+                executor.get_executor_id(),
+                executor.get_executor_display_name(),
+                executor_state_name(entry.get_state()),
+                executor.get_boot_generation(),
+                entry.get_start_time(),
+                entry.get_end_time(),
+            ));
+        }
+
+        for task in executor.iter_tasks() {
+            for entry in task.get_state_history() {
+                records.push(HistoryRecord::new(
+                    "task",
+                    task.get_task_id(),
+                    task.get_task_display_name(),
+                    task_state_name(entry.get_state()),
+                    executor.get_boot_generation(),
+                    entry.get_start_time(),
+                    entry.get_end_time(),
+                ));
+            }
+        }
+    }
+
+    records
+}
+
+/// Tag the records with the firmware they were traced from
+pub fn tag_firmware_id(records: &mut [HistoryRecord], firmware_id: Option<&str>) {
+    for record in records {
+        record.firmware_id = firmware_id.map(String::from);
+    }
+}
+
+/// Write the records as NDJSON (one JSON object per line)
+pub fn write_ndjson(records: &[HistoryRecord], writer: &mut impl Write) -> io::Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut *writer, record)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use crate::tracing::{
+        executor::ExecutorTraceInfo,
+        test_harness::{at_ms, feed_executor, item},
+        trace_data::TraceItemType,
+    };
+
+    use super::{history_records, tag_firmware_id, write_ndjson};
+
+    #[test]
+    fn test_ndjson_schema() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        feed_executor(
+            &mut executor,
+            &[
+                item(
+                    5,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 7,
+                    },
+                ),
+                item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            ],
+        );
+
+        let mut records = history_records(&[executor]);
+        tag_firmware_id(&mut records, Some("abc123"));
+        let mut output = Vec::new();
+        write_ndjson(&records, &mut output).unwrap();
+
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        // Executor idle till the poll start
+        let executor_record = &lines[0];
+        assert_eq!(executor_record["entity_type"], json!("executor"));
+        assert_eq!(executor_record["id"], json!(1));
+        assert_eq!(executor_record["name"], json!("Executor 0x1"));
+        assert_eq!(executor_record["state"], json!("Idle"));
+        assert_eq!(executor_record["boot_generation"], json!(0));
+        assert_eq!(executor_record["start_uc_us"], json!(0));
+        assert_eq!(executor_record["end_uc_us"], json!(10_000));
+        assert_eq!(executor_record["firmware_id"], json!("abc123"));
+
+        // Task spawned till it was woken
+        let task_record = &lines[1];
+        assert_eq!(task_record["entity_type"], json!("task"));
+        assert_eq!(task_record["id"], json!(7));
+        assert_eq!(task_record["state"], json!("Spawned"));
+        assert_eq!(task_record["start_uc_us"], json!(5_000));
+        assert_eq!(task_record["end_uc_us"], json!(5_000));
+
+        let mut keys: Vec<&String> = task_record.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "boot_generation",
+                "end_pc_us",
+                "end_uc_us",
+                "entity_type",
+                "firmware_id",
+                "id",
+                "name",
+                "start_pc_us",
+                "start_uc_us",
+                "state"
+            ]
+        );
+    }
+}
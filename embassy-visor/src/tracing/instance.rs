@@ -1,66 +1,447 @@
-use std::sync::{Arc, Mutex, atomic::AtomicU64};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
 
-use crossbeam::channel::Receiver;
+use crossbeam::channel::{Receiver, RecvTimeoutError};
 
-use crate::tracing::{executor::ExecutorTraceInfo, stats::instance_stats::InstanceStats, trace_data::TraceItem};
+use crate::tracing::{
+    boot::{BootTracker, TAG_BOOT_GENERATIONS},
+    channels::ChannelGraph,
+    clock::ClockSync,
+    device::PRIMARY_DEVICE_ID,
+    event_counts::EventCounter,
+    executor::{ExecutorState, ExecutorTraceInfo},
+    header::TraceHeader,
+    history_export::{self, HistoryRecord},
+    integrity::IntegrityCounters,
+    markers::{UserMarker, UserMarkers},
+    parse_stats::ParseStats,
+    schema::{REFUSE_SCHEMA_MISMATCH, SchemaCheck},
+    snapshot::{PUBLISH_INTERVAL, STATS_SNAPSHOTS, StatsSnapshots},
+    stats::{
+        core_stats::CoreStats, executor_stats::ExecutorStats, heatmap::Heatmap,
+        instance_stats::InstanceStats,
+    },
+    subscription::{TaskEvent, TaskSubscribers},
+    task::{TaskTraceInfo, TaskTraceState},
+    time::{ComputerTime, EmbassyTime},
+    timeline::{Timeline, TimelineEventKind},
+    timers::TimerTracker,
+    trace_data::{TraceItem, TraceItemType, TraceParseResult},
+    wake_chain::{WakeChain, WakeGraph},
+};
 
 pub static HISTORY_MAX_TIME_S: AtomicU64 = AtomicU64::new(30); // 30seconds
 
+/// Options of an instance, fixed when it is created (seeded from the command line)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InstanceSettings {
+    /// Keep the executors and tasks of previous boots instead of forgetting them on a reset
+    pub tag_boot_generations: bool,
+    /// Ignore the trace of a beacon with a different schema version instead of only warning
+    pub refuse_schema_mismatch: bool,
+}
+
+impl InstanceSettings {
+    /// Settings as configured
+    pub fn current() -> Self {
+        Self {
+            tag_boot_generations: TAG_BOOT_GENERATIONS.load(Ordering::Relaxed),
+            refuse_schema_mismatch: REFUSE_SCHEMA_MISMATCH.load(Ordering::Relaxed),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct TracingInstance {
+    /// Device traced by this instance
+    device_id: u32,
+
+    /// Options of this instance (shared by the instances of the other devices)
+    settings: InstanceSettings,
+
+    /// Instances of the other devices (`--device`), only kept by the primary device's instance.
+    /// Their ids, boots and clocks are independent of this device's.
+    devices: Arc<Mutex<BTreeMap<u32, TracingInstance>>>,
+
     executors: Arc<Mutex<Vec<ExecutorTraceInfo>>>,
+
+    /// Ids of the cores declared by the beacon's topology (if received)
+    declared_cores: Arc<Mutex<Option<Vec<u32>>>>,
+
+    /// Parsed trace lines and parse errors by variant
+    parse_stats: Arc<Mutex<ParseStats>>,
+    /// Received trace events per kind (of all devices)
+    event_counts: Arc<Mutex<EventCounter>>,
+
+    /// Identifier of the firmware build (if published by the beacon)
+    firmware_id: Arc<Mutex<Option<String>>>,
+
+    /// Compatibility of the beacon's trace schema version (if published by the beacon)
+    schema: Arc<Mutex<SchemaCheck>>,
+
+    /// Chronological log of lifecycle events
+    timeline: Arc<Mutex<Timeline>>,
+
+    /// Receivers of the state changes of single tasks
+    task_subscribers: Arc<Mutex<TaskSubscribers>>,
+
+    /// Firmware resets detected in the session
+    boots: Arc<Mutex<BootTracker>>,
+
+    /// Executors of previous boots as they were at the reset (with `tag_boot_generations`)
+    previous_boots: Arc<Mutex<Vec<ExecutorTraceInfo>>>,
+
+    /// Offset of the uC clock, refined by every trace item (and the beacon's heartbeats)
+    clock: Arc<Mutex<ClockSync>>,
+
+    /// Messages sent and received over channels (if published by the beacon)
+    channels: Arc<Mutex<ChannelGraph>>,
+
+    /// Timers set by the tasks (if published by the beacon)
+    timers: Arc<Mutex<TimerTracker>>,
+
+    /// Wakeups of the tasks inferred from the channel events
+    wakes: Arc<Mutex<WakeGraph>>,
+
+    /// Markers set by the user, kept over firmware reloads
+    markers: Arc<Mutex<UserMarkers>>,
+
+    /// Statistics published by the update thread (with `--stats-snapshots`)
+    snapshots: Option<Arc<StatsSnapshots>>,
+}
+
+impl std::fmt::Debug for TracingInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TracingInstance").finish_non_exhaustive()
+    }
 }
 
 fn update_from_trace_items(
-    trace_recver: Receiver<TraceItem>,
+    trace_recver: Receiver<TraceParseResult>,
     tracing_instance: TracingInstance,
 ) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
+        // Loop ends when the channel is closed
         loop {
-            match trace_recver.recv() {
-                Ok(trace_item) => {
-                    // New Trace Item --> Update tracing instance
-                    tracing_instance.update(&trace_item);
-                }
-                Err(_) => {
-                    break; // Channel closed
+            match trace_recver.recv_timeout(PUBLISH_INTERVAL) {
+                Ok(parse_result) => {
+                    tracing_instance.record_parse_result(&parse_result);
+
+                    if let Ok(trace_item) = parse_result {
+                        // New Trace Item --> Update tracing instance
+                        tracing_instance.update(&trace_item);
+                    }
                 }
+                // Without trace items the snapshot still ages (e.g. the event rates)
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if tracing_instance
+                .snapshots
+                .as_ref()
+                .is_some_and(|snapshots| snapshots.is_due())
+            {
+                tracing_instance.publish_stats();
             }
         }
+        // The final state stays readable after the trace ended
+        tracing_instance.publish_stats();
     })
 }
 
 impl TracingInstance {
-    pub fn new(trace_recver: Receiver<TraceItem>) -> Self {
-        let instance = Self {
+    pub fn new(trace_recver: Receiver<TraceParseResult>) -> Self {
+        Self::new_with_update_thread(trace_recver).0
+    }
+
+    /// Like `new`, but also returns the update thread (ends when the trace channel is closed)
+    pub fn new_with_update_thread(
+        trace_recver: Receiver<TraceParseResult>,
+    ) -> (Self, std::thread::JoinHandle<()>) {
+        Self::start(
+            trace_recver,
+            STATS_SNAPSHOTS.load(Ordering::Relaxed),
+            InstanceSettings::current(),
+        )
+    }
+
+    /// Instance of the primary device and its update thread, publishing snapshots of the
+    /// statistics if `snapshots` is set
+    fn start(
+        trace_recver: Receiver<TraceParseResult>,
+        snapshots: bool,
+        settings: InstanceSettings,
+    ) -> (Self, std::thread::JoinHandle<()>) {
+        let mut instance = Self::for_device(PRIMARY_DEVICE_ID, settings);
+        instance.snapshots = snapshots.then(|| Arc::new(StatsSnapshots::default()));
+        let update_thread = update_from_trace_items(trace_recver, instance.clone());
+        (instance, update_thread)
+    }
+
+    /// Empty instance of the device (updated by the caller)
+    fn for_device(device_id: u32, settings: InstanceSettings) -> Self {
+        Self {
+            device_id,
+            settings,
+            devices: Arc::new(Mutex::new(BTreeMap::new())),
             executors: Arc::new(Mutex::new(Vec::new())),
-        };
+            declared_cores: Arc::new(Mutex::new(None)),
+            parse_stats: Arc::new(Mutex::new(ParseStats::default())),
+            event_counts: Arc::new(Mutex::new(EventCounter::default())),
+            firmware_id: Arc::new(Mutex::new(None)),
+            schema: Arc::new(Mutex::new(SchemaCheck::default())),
+            timeline: Arc::new(Mutex::new(Timeline::default())),
+            task_subscribers: Arc::new(Mutex::new(TaskSubscribers::default())),
+            boots: Arc::new(Mutex::new(BootTracker::default())),
+            previous_boots: Arc::new(Mutex::new(Vec::new())),
+            clock: Arc::new(Mutex::new(ClockSync::default())),
+            channels: Arc::new(Mutex::new(ChannelGraph::default())),
+            timers: Arc::new(Mutex::new(TimerTracker::default())),
+            wakes: Arc::new(Mutex::new(WakeGraph::default())),
+            markers: Arc::new(Mutex::new(UserMarkers::default())),
+            snapshots: None,
+        }
+    }
 
-        let _ = update_from_trace_items(trace_recver, instance.clone());
-        instance
+    /// Instance of the other device (created with its first trace item)
+    fn device_instance(&self, device_id: u32) -> TracingInstance {
+        self.devices
+            .lock()
+            .unwrap()
+            .entry(device_id)
+            .or_insert_with(|| Self::for_device(device_id, self.settings))
+            .clone()
+    }
+
+    /// Forget all executors, tasks and diagnostics (e.g. after the firmware was reloaded)
+    pub fn reset(&self) {
+        self.devices.lock().unwrap().clear();
+        self.executors.lock().unwrap().clear();
+        *self.declared_cores.lock().unwrap() = None;
+        *self.parse_stats.lock().unwrap() = ParseStats::default();
+        *self.event_counts.lock().unwrap() = EventCounter::default();
+        *self.firmware_id.lock().unwrap() = None;
+        *self.schema.lock().unwrap() = SchemaCheck::default();
+        self.timeline.lock().unwrap().clear();
+        self.boots.lock().unwrap().clear();
+        self.previous_boots.lock().unwrap().clear();
+        self.clock.lock().unwrap().clear();
+        self.channels.lock().unwrap().clear();
+        self.timers.lock().unwrap().clear();
+        self.wakes.lock().unwrap().clear();
+    }
+
+    /// Stream of the state changes of the task (also the one it appears with). The
+    /// subscription ends when the receiver is dropped.
+    ///
+    /// Events are sent while the trace item is processed, so keep receiving them or drop the
+    /// receiver (the channel is unbounded).
+    pub fn subscribe_task(&self, task_id: u32) -> Receiver<TaskEvent> {
+        self.task_subscribers.lock().unwrap().subscribe(task_id)
+    }
+
+    /// Count a parsed trace line (or its parse error) for the diagnostics
+    pub fn record_parse_result(&self, parse_result: &TraceParseResult) {
+        self.parse_stats.lock().unwrap().record(parse_result);
     }
 
     /// Update the tracing instance based on a new trace item
     pub fn update(&self, trace_item: &TraceItem) {
+        self.event_counts.lock().unwrap().record(
+            trace_item.data.kind(),
+            trace_item.time_pair.get_pc_timestamp(),
+        );
+
+        if trace_item.device_id != self.device_id {
+            self.device_instance(trace_item.device_id)
+                .update(trace_item);
+            return;
+        }
+
+        match &trace_item.data {
+            TraceItemType::SchemaVersion { version } => {
+                self.check_schema(*version);
+                return;
+            }
+            TraceItemType::Header { header } => {
+                self.apply_header(header);
+                return;
+            }
+            _ => {}
+        }
+        if self.settings.refuse_schema_mismatch && self.schema.lock().unwrap().is_mismatch() {
+            return;
+        }
+
+        // Topology and firmware id are stored on instance level (no executor involved)
+        match &trace_item.data {
+            TraceItemType::Topology { core_count } => {
+                *self.declared_cores.lock().unwrap() = Some((0..*core_count).collect());
+                return;
+            }
+            TraceItemType::FirmwareId { id } => {
+                *self.firmware_id.lock().unwrap() = Some(id.clone());
+                return;
+            }
+            _ => {}
+        }
+
         let mut executors = self.executors.lock().unwrap();
+        let mut timeline = self.timeline.lock().unwrap();
+        let time = trace_item.time_pair.get_uc_timestamp();
+
+        // The ids of a new boot may belong to different executors and tasks
+        let mut boots = self.boots.lock().unwrap();
+        if let Some(previous_end) = boots.observe(trace_item.core_id, time) {
+            let previous_executors = std::mem::take(&mut *executors);
+            if self.settings.tag_boot_generations {
+                self.previous_boots.lock().unwrap().extend(
+                    previous_executors
+                        .iter()
+                        .filter_map(|e| e.rewound_to(previous_end)),
+                );
+            }
+            timeline.clear();
+            // The uC clock restarted, the previous offset no longer applies
+            self.clock.lock().unwrap().clear();
+            self.channels.lock().unwrap().clear();
+            self.timers.lock().unwrap().clear();
+            self.wakes.lock().unwrap().clear();
+            timeline.push(
+                time,
+                TimelineEventKind::FirmwareReset,
+                format!("boot {} on core {}", boots.generation(), trace_item.core_id),
+            );
+        }
+        let boot_generation = boots.generation();
+        drop(boots);
+
+        self.clock.lock().unwrap().record(trace_item.time_pair);
+        let wake_retention =
+            EmbassyTime::from_millis(HISTORY_MAX_TIME_S.load(Ordering::Relaxed) * 1000);
+        // Heartbeats carry nothing but the time pair, channel and timer events no state change
+        match trace_item.data {
+            TraceItemType::Heartbeat { .. } => return,
+            TraceItemType::ChannelSend { .. } | TraceItemType::ChannelReceive { .. } => {
+                self.channels.lock().unwrap().record(&trace_item.data);
+                self.wakes
+                    .lock()
+                    .unwrap()
+                    .record(time, &trace_item.data, wake_retention);
+                return;
+            }
+            TraceItemType::TimerSet { .. } | TraceItemType::TimerFire { .. } => {
+                self.timers.lock().unwrap().record(time, &trace_item.data);
+                return;
+            }
+            // A fired timer or a message may be why the task wakes
+            TraceItemType::TaskReadyBegin { .. } => {
+                self.timers.lock().unwrap().record(time, &trace_item.data);
+                self.wakes
+                    .lock()
+                    .unwrap()
+                    .record(time, &trace_item.data, wake_retention);
+            }
+            _ => {}
+        }
 
         // Check that we have an executor for this trace item
-        if Self::find_executor_by_id_locked(&executors, trace_item.data.get_executor_id()).is_none()
+        if let Some(executor_id) = trace_item.data.get_executor_id()
+            && Self::find_executor_by_id_locked(&executors, executor_id).is_none()
         {
             // Create a new executor
-            let new_executor = ExecutorTraceInfo::new(
-                trace_item.data.get_executor_id(),
-                trace_item.core_id,
-                trace_item.time_pair,
+            let mut new_executor =
+                ExecutorTraceInfo::new(executor_id, trace_item.core_id, trace_item.time_pair);
+            new_executor.set_boot_generation(boot_generation);
+            timeline.push(
+                time,
+                TimelineEventKind::ExecutorCreated,
+                format!(
+                    "{} on core {}",
+                    new_executor.get_executor_display_name(),
+                    trace_item.core_id
+                ),
             );
             executors.push(new_executor);
         }
 
+        // A task belongs to exactly one executor. A task referenced by another executor than
+        // the one that created it (migrated, or a beacon bug) moves there instead of being
+        // counted under both.
+        if let Some(executor_id) = trace_item.data.get_executor_id()
+            && let Some(task_id) = trace_item.data.get_task_id()
+            && let Some(owner) = executors.iter().position(|e| {
+                e.get_executor_id() != executor_id && e.find_task_by_id(task_id).is_some()
+            })
+        {
+            let task = executors[owner]
+                .take_task(task_id)
+                .expect("task found above");
+            let from = executors[owner].get_executor_display_name();
+            let target = executors
+                .iter_mut()
+                .find(|e| e.get_executor_id() == executor_id)
+                .expect("executor created above");
+            timeline.push(
+                time,
+                TimelineEventKind::IntegrityWarning,
+                format!(
+                    "{} moved from {} to {}",
+                    task.get_task_display_name(),
+                    from,
+                    target.get_executor_display_name()
+                ),
+            );
+            target.adopt_task(task);
+        }
+
+        // Snapshot to detect integrity issues caused by this trace item (the executors keep
+        // their own state from before the item)
+        let integrity_before = Self::item_integrity_locked(&executors, trace_item);
+        let mut task_subscribers = self.task_subscribers.lock().unwrap();
+        // Empty (no allocation) while nothing is subscribed
+        let mut subscribed_before: Vec<(u32, Option<TaskTraceState>)> = task_subscribers
+            .task_ids()
+            .map(|task_id| {
+                let task = Self::find_task_by_id_locked(&executors, task_id);
+                (task_id, task.map(|t| *t.get_state()))
+            })
+            .collect();
+        subscribed_before.sort_unstable_by_key(|(task_id, _)| *task_id);
+        subscribed_before.dedup_by_key(|(task_id, _)| *task_id);
+
         // Update executors
         for executor in executors.iter_mut() {
             executor.update(trace_item);
         }
 
+        for (task_id, state_before) in subscribed_before {
+            if let Some(task) = Self::find_task_by_id_locked(&executors, task_id)
+                && state_before != Some(*task.get_state())
+            {
+                task_subscribers.publish(TaskEvent {
+                    task_id,
+                    executor_id: task.get_executor_id(),
+                    from: state_before,
+                    to: *task.get_state(),
+                    time: task.get_state_start_time().get_uc_timestamp(),
+                });
+            }
+        }
+        drop(task_subscribers);
+
+        Self::record_timeline_events_locked(
+            &executors,
+            integrity_before,
+            trace_item,
+            &mut timeline,
+        );
+
         // print count of tasks in mode RUNNING
         // let running_tasks = executors
         //     .iter()
@@ -73,16 +454,1352 @@ impl TracingInstance {
         // println!("Running tasks: {}", running_tasks);
     }
 
+    /// Compare the beacon's schema version with the supported one
+    fn check_schema(&self, version: u32) {
+        let check = SchemaCheck::from_version(version);
+        *self.schema.lock().unwrap() = check;
+        // What was traced so far may already be misread
+        if check.is_mismatch() && self.settings.refuse_schema_mismatch {
+            self.executors.lock().unwrap().clear();
+        }
+    }
+
+    /// Take the declarations of the beacon's header (the fields it carries, the others keep
+    /// what earlier events declared)
+    fn apply_header(&self, header: &TraceHeader) {
+        if let Some(version) = header.schema_version {
+            self.check_schema(version);
+        }
+        if let Some(core_ids) = &header.core_ids {
+            *self.declared_cores.lock().unwrap() = Some(core_ids.clone());
+        }
+        if let Some(id) = &header.firmware_id {
+            *self.firmware_id.lock().unwrap() = Some(id.clone());
+        }
+    }
+
+    /// Statistics for the periodic readers (gatherer, exporters): the latest snapshot published
+    /// by the update thread with `--stats-snapshots` (never waits for the model), else
+    /// calculated now
+    pub fn latest_stats(&self) -> Arc<InstanceStats> {
+        match &self.snapshots {
+            Some(snapshots) => snapshots.load(),
+            None => Arc::new(self.get_stats()),
+        }
+    }
+
+    /// Calculate the statistics and publish them as the latest snapshot (if snapshots are
+    /// published)
+    fn publish_stats(&self) {
+        if let Some(snapshots) = &self.snapshots {
+            snapshots.publish(self.get_stats());
+        }
+    }
+
     /// Calculate and return instance statistics
     pub fn get_stats(&self) -> InstanceStats {
+        let declared_cores = self.declared_cores.lock().unwrap().clone();
+        let parse_stats = self.parse_stats.lock().unwrap().clone();
+        let event_counts = self
+            .event_counts
+            .lock()
+            .unwrap()
+            .counts(ComputerTime::now());
+        let firmware_id = self.firmware_id.lock().unwrap().clone();
+        let timeline = self.timeline.lock().unwrap().events();
+        let clock = self.clock.lock().unwrap().alignment();
         let executors = self.executors.lock().unwrap();
-        InstanceStats::from_executors(&executors)
+        let channel_edges = self.channels.lock().unwrap().edges(&executors);
+        let timers = self.timers.lock().unwrap().timers(&executors);
+        let mut stats = InstanceStats {
+            parse_stats,
+            event_counts,
+            firmware_id,
+            schema_warning: self.schema_warning(),
+            timeline,
+            clock,
+            channel_edges,
+            timers,
+            ..InstanceStats::from_executors(&executors, declared_cores.as_deref())
+        };
+        drop(executors);
+        Self::add_previous_boots(&mut stats, &self.previous_boots.lock().unwrap());
+        for core in stats.core_stats.iter_mut() {
+            core.device_id = self.device_id;
+        }
+        self.add_devices(&mut stats);
+        stats
+    }
+
+    /// Add the cores, channels and timers of the other devices, their cores after the ones of
+    /// this device. The timeline and diagnostics stay the ones of this device.
+    fn add_devices(&self, stats: &mut InstanceStats) {
+        let devices: Vec<TracingInstance> =
+            self.devices.lock().unwrap().values().cloned().collect();
+        for device in devices {
+            let device_stats = device.get_stats();
+            stats.core_stats.extend(device_stats.core_stats);
+            stats.tasks_count += device_stats.tasks_count;
+            stats.executor_count += device_stats.executor_count;
+            stats.channel_edges.extend(device_stats.channel_edges);
+            stats.timers.extend(device_stats.timers);
+        }
+    }
+
+    /// Warning about an incompatible trace schema of the beacon
+    fn schema_warning(&self) -> Option<String> {
+        self.schema
+            .lock()
+            .unwrap()
+            .warning(self.settings.refuse_schema_mismatch)
     }
 
-    fn find_executor_by_id_locked<'a>(
-        executors: &'a Vec<ExecutorTraceInfo>,
+    /// Add the executors of previous boots to their cores, with the boot generation appended
+    /// to the executor and task names. They do not count for the core utilization.
+    fn add_previous_boots(stats: &mut InstanceStats, previous_boots: &[ExecutorTraceInfo]) {
+        for executor in previous_boots {
+            let mut executor_stats = ExecutorStats::from_executor(executor);
+            let tag = format!(" [boot {}]", executor.get_boot_generation());
+            executor_stats.name.push_str(&tag);
+            for task in executor_stats.tasks.iter_mut() {
+                task.name.push_str(&tag);
+            }
+
+            let core_id = executor.get_core_id();
+            match stats.core_stats.iter_mut().find(|c| c.core_id == core_id) {
+                Some(core) => core.executors.push(executor_stats),
+                None => {
+                    let mut core = CoreStats::empty(core_id);
+                    core.executors.push(executor_stats);
+                    stats.core_stats.push(core);
+                }
+            }
+        }
+        stats.core_stats.sort_by_key(|c| c.core_id);
+    }
+
+    /// Firmware time span (UC) for which the state of all executors and tasks can be
+    /// reconstructed from the retained history, None before the first executor appeared
+    pub fn retained_window(&self) -> Option<(EmbassyTime, EmbassyTime)> {
+        let executors = self.executors.lock().unwrap();
+        let start = executors
+            .iter()
+            .filter_map(|e| e.get_retained_since())
+            .max()
+            .or_else(|| {
+                executors
+                    .iter()
+                    .map(|e| e.get_created_at().get_uc_timestamp())
+                    .min()
+            })?;
+        let end = executors.iter().map(|e| e.estimate_uc_now()).max()?;
+        Some((start, end))
+    }
+
+    /// Statistics as they were at the given firmware time (UC), calculated from the retained
+    /// history (without the channel counts and timers, they are not part of it). None if the time is
+    /// outside the retained window.
+    pub fn get_stats_at(&self, time: EmbassyTime) -> Option<InstanceStats> {
+        let (start, end) = self.retained_window()?;
+        if time < start || time > end {
+            return None;
+        }
+
+        let declared_cores = self.declared_cores.lock().unwrap().clone();
+        let parse_stats = self.parse_stats.lock().unwrap().clone();
+        let firmware_id = self.firmware_id.lock().unwrap().clone();
+        let timeline = self
+            .timeline
+            .lock()
+            .unwrap()
+            .events()
+            .into_iter()
+            .filter(|e| e.time <= time)
+            .collect();
+        let executors: Vec<ExecutorTraceInfo> = self
+            .executors
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|e| e.rewound_to(time))
+            .collect();
+        let clock = self.clock.lock().unwrap().alignment();
+        // not part of the history, the counts stay the current ones
+        let event_counts = self
+            .event_counts
+            .lock()
+            .unwrap()
+            .counts(ComputerTime::now());
+        Some(InstanceStats {
+            parse_stats,
+            event_counts,
+            firmware_id,
+            schema_warning: self.schema_warning(),
+            timeline,
+            clock,
+            ..InstanceStats::from_executors(&executors, declared_cores.as_deref())
+        })
+    }
+
+    /// Activity of every task within the `window` up to the current firmware time in
+    /// `bucket_count` buckets, None before the first executor appeared
+    pub fn heatmap(&self, window: EmbassyTime, bucket_count: usize) -> Option<Heatmap> {
+        let executors = self.executors.lock().unwrap();
+        let end = executors.iter().map(|e| e.estimate_uc_now()).max()?;
+        Some(Heatmap::from_executors(
+            &executors,
+            end,
+            window,
+            bucket_count,
+        ))
+    }
+
+    /// Chain of wakeups leading to the latest wakeup of the task (of any device), None if it
+    /// is not known to be woken by another task
+    pub fn wake_chain(&self, task_id: u32) -> Option<WakeChain> {
+        let executors = self.executors.lock().unwrap();
+        let chain = executors
+            .iter()
+            .find(|e| e.find_task_by_id(task_id).is_some())
+            .and_then(|e| {
+                self.wakes
+                    .lock()
+                    .unwrap()
+                    .chain(e.get_executor_id(), task_id, &executors)
+            });
+        drop(executors);
+        chain.or_else(|| {
+            let devices: Vec<TracingInstance> =
+                self.devices.lock().unwrap().values().cloned().collect();
+            devices.iter().find_map(|device| device.wake_chain(task_id))
+        })
+    }
+
+    /// Retained state change history of all executors and tasks (for the NDJSON export)
+    pub fn history_records(&self) -> Vec<HistoryRecord> {
+        let executors = self.executors.lock().unwrap();
+        let mut records =
+            history_export::history_records(self.previous_boots.lock().unwrap().iter());
+        records.extend(history_export::history_records(executors.iter()));
+        records.extend(history_export::marker_records(
+            self.markers.lock().unwrap().markers(),
+        ));
+        history_export::tag_firmware_id(&mut records, self.firmware_id.lock().unwrap().as_deref());
+        records
+    }
+
+    /// Set a marker at the PC time (e.g. of a key press), labelled "marker N" unless a label is
+    /// given, and add it to the timeline at the estimated firmware time
+    pub fn add_user_marker(&self, pc_time: ComputerTime, label: Option<String>) -> UserMarker {
+        let clock_offset_us = self.clock.lock().unwrap().offset_us();
+        let boot_generation = self.boots.lock().unwrap().generation();
+        let marker =
+            self.markers
+                .lock()
+                .unwrap()
+                .add(pc_time, label, clock_offset_us, boot_generation);
+
+        let description = match marker.uc_time {
+            Some(_) => marker.label.clone(),
+            None => format!("{} (firmware time unknown)", marker.label),
+        };
+        self.timeline.lock().unwrap().push(
+            marker.uc_time.unwrap_or(EmbassyTime::ZERO),
+            TimelineEventKind::UserMarker,
+            description,
+        );
+        marker
+    }
+
+    /// Retained state change history of one executor of the current boot and its tasks only
+    /// (for the scoped NDJSON export)
+    pub fn executor_history_records(&self, executor_id: u32) -> Vec<HistoryRecord> {
+        let executors = self.executors.lock().unwrap();
+        let mut records = history_export::history_records(
+            executors
+                .iter()
+                .filter(|e| e.get_executor_id() == executor_id),
+        );
+        history_export::tag_firmware_id(&mut records, self.firmware_id.lock().unwrap().as_deref());
+        records
+    }
+
+    /// Task currently running on the core as (executor id, task id, task name).
+    /// While an executor is preempted, the running task of the preempting executor is reported.
+    /// Returns `None` when no task is running on the core (idle or scheduling).
+    pub fn running_task_on_core(&self, core_id: u32) -> Option<(u32, u32, String)> {
+        Self::running_task_on_core_locked(&self.executors.lock().unwrap(), core_id)
+    }
+
+    /// Tasks currently running as (executor id, task id, task name), at most one per core
+    pub fn running_tasks(&self) -> Vec<(u32, u32, String)> {
+        Self::running_tasks_locked(&self.executors.lock().unwrap())
+    }
+
+    /// Tasks running at the firmware time (UC) as reconstructed from the retained history, as
+    /// (executor id, task id, task name), at most one per core
+    pub fn running_tasks_at(&self, time: EmbassyTime) -> Vec<(u32, u32, String)> {
+        let executors: Vec<ExecutorTraceInfo> = self
+            .executors
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|e| e.rewound_to(time))
+            .collect();
+        Self::running_tasks_locked(&executors)
+    }
+
+    fn running_tasks_locked(executors: &[ExecutorTraceInfo]) -> Vec<(u32, u32, String)> {
+        let core_ids: BTreeSet<u32> = executors.iter().map(|e| e.get_core_id()).collect();
+        core_ids
+            .into_iter()
+            .filter_map(|core_id| Self::running_task_on_core_locked(executors, core_id))
+            .collect()
+    }
+
+    fn running_task_on_core_locked(
+        executors: &[ExecutorTraceInfo],
+        core_id: u32,
+    ) -> Option<(u32, u32, String)> {
+        executors
+            .iter()
+            .filter(|e| e.get_core_id() == core_id)
+            .filter(|e| !matches!(e.get_state(), ExecutorState::Preempted { .. }))
+            .flat_map(|e| e.iter_tasks())
+            .find(|t| t.get_state() == &TaskTraceState::Running)
+            .map(|t| {
+                (
+                    t.get_executor_id(),
+                    t.get_task_id(),
+                    t.get_task_display_name(),
+                )
+            })
+    }
+
+    /// Name and integrity counters of the task (or executor) the trace item belongs to
+    fn item_integrity_locked(
+        executors: &[ExecutorTraceInfo],
+        trace_item: &TraceItem,
+    ) -> Option<IntegrityCounters> {
+        let executor =
+            Self::find_executor_by_id_locked(executors, trace_item.data.get_executor_id()?)?;
+        match trace_item.data.get_task_id() {
+            Some(task_id) => executor
+                .find_task_by_id(task_id)
+                .map(|t| *t.get_integrity()),
+            None => Some(*executor.get_integrity()),
+        }
+    }
+
+    /// Display name of the task (or executor) the trace item belongs to
+    fn item_name_locked(executors: &[ExecutorTraceInfo], trace_item: &TraceItem) -> Option<String> {
+        let executor =
+            Self::find_executor_by_id_locked(executors, trace_item.data.get_executor_id()?)?;
+        match trace_item.data.get_task_id() {
+            Some(task_id) => executor
+                .find_task_by_id(task_id)
+                .map(|t| t.get_task_display_name()),
+            None => Some(executor.get_executor_display_name()),
+        }
+    }
+
+    /// Push the lifecycle events caused by the trace item into the timeline (compared to the
+    /// snapshots the executors took before the item)
+    fn record_timeline_events_locked(
+        executors: &[ExecutorTraceInfo],
+        integrity_before: Option<IntegrityCounters>,
+        trace_item: &TraceItem,
+        timeline: &mut Timeline,
+    ) {
+        let time = trace_item.time_pair.get_uc_timestamp();
+        let executor_name = |executor_id: u32| {
+            Self::find_executor_by_id_locked(executors, executor_id)
+                .map_or(format!("Executor 0x{:X}", executor_id), |e| {
+                    e.get_executor_display_name()
+                })
+        };
+
+        // Task lifecycle
+        if let TraceItemType::TaskNew { .. } | TraceItemType::TaskEnd { .. } = trace_item.data
+            && let Some(task_name) = Self::item_name_locked(executors, trace_item)
+        {
+            match trace_item.data {
+                TraceItemType::TaskNew { executor_id, .. } => timeline.push(
+                    time,
+                    TimelineEventKind::TaskSpawned,
+                    format!("{} on {}", task_name, executor_name(executor_id)),
+                ),
+                _ => timeline.push(time, TimelineEventKind::TaskEnded, task_name),
+            }
+        }
+
+        // Preemptions started/ended
+        for executor in executors {
+            match (&executor.get_before_update().state, executor.get_state()) {
+                (ExecutorState::Preempted { .. }, ExecutorState::Preempted { .. }) => {}
+                (_, ExecutorState::Preempted { by_executor_id, .. }) => timeline.push(
+                    time,
+                    TimelineEventKind::PreemptionStarted,
+                    format!(
+                        "{} preempted by {}",
+                        executor.get_executor_display_name(),
+                        executor_name(*by_executor_id)
+                    ),
+                ),
+                (ExecutorState::Preempted { .. }, _) => timeline.push(
+                    time,
+                    TimelineEventKind::PreemptionEnded,
+                    format!("{} resumed", executor.get_executor_display_name()),
+                ),
+                _ => {}
+            }
+        }
+
+        // Lost wakeups (newly flagged tasks are the last ones in the list)
+        for executor in executors {
+            let new_count =
+                executor.get_lost_wakeup_count() - executor.get_before_update().lost_wakeup_count;
+            let flagged = executor.get_lost_wakeup_task_ids();
+            for task_id in &flagged[flagged.len().saturating_sub(new_count)..] {
+                if let Some(task) = executor.find_task_by_id(*task_id) {
+                    timeline.push(
+                        time,
+                        TimelineEventKind::LostWakeup,
+                        format!(
+                            "{} ready but {} idle (likely waker bug)",
+                            task.get_task_display_name(),
+                            executor.get_executor_display_name()
+                        ),
+                    );
+                }
+            }
+        }
+
+        // Ping-pongs suspected by this poll
+        for executor in executors {
+            if executor.get_ping_pong_count() > executor.get_before_update().ping_pong_count
+                && let Some(run) = executor.get_ping_pong()
+            {
+                let task_name = |task_id: u32| {
+                    executor
+                        .find_task_by_id(task_id)
+                        .map_or(format!("Task 0x{:X}", task_id), |t| {
+                            t.get_task_display_name()
+                        })
+                };
+                timeline.push(
+                    time,
+                    TimelineEventKind::PingPong,
+                    format!(
+                        "{} <-> {} on {}: {} switches without idling (waking each other?)",
+                        task_name(run.task_ids.0),
+                        task_name(run.task_ids.1),
+                        executor.get_executor_display_name(),
+                        run.switches
+                    ),
+                );
+            }
+        }
+
+        // Integrity issues
+        if let Some(before) = integrity_before
+            && let Some(after) = Self::item_integrity_locked(executors, trace_item)
+            && let Some(issue) = after.new_issue_since(&before)
+            && let Some(name) = Self::item_name_locked(executors, trace_item)
+        {
+            timeline.push(
+                time,
+                TimelineEventKind::IntegrityWarning,
+                format!("{}: {}", name, issue.description()),
+            );
+        }
+    }
+
+    fn find_executor_by_id_locked(
+        executors: &[ExecutorTraceInfo],
         executor_id: u32,
-    ) -> Option<&'a ExecutorTraceInfo> {
-        executors.iter().find(|e| e.get_executor_id() == executor_id)
+    ) -> Option<&ExecutorTraceInfo> {
+        executors
+            .iter()
+            .find(|e| e.get_executor_id() == executor_id)
+    }
+
+    fn find_task_by_id_locked(
+        executors: &[ExecutorTraceInfo],
+        task_id: u32,
+    ) -> Option<&TaskTraceInfo> {
+        executors.iter().find_map(|e| e.find_task_by_id(task_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use crate::tracing::{
+        header::TraceHeader,
+        schema::SUPPORTED_SCHEMA_VERSION,
+        task::TaskTraceState,
+        test_harness::item,
+        time::{ComputerTime, EmbassyTime, TimePair},
+        timeline::TimelineEventKind,
+        trace_data::{TraceEventKind, TraceItem, TraceItemType},
+    };
+
+    use super::{InstanceSettings, TracingInstance};
+
+    fn instance_with(items: &[TraceItem]) -> TracingInstance {
+        let (_trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let instance = TracingInstance::new(trace_rx);
+        for trace_item in items {
+            instance.update(trace_item);
+        }
+        instance
     }
-}
\ No newline at end of file
+
+    /// Like `instance_with`, with other than the configured settings
+    fn instance_with_settings(items: &[TraceItem], settings: InstanceSettings) -> TracingInstance {
+        let (_trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let (instance, _update_thread) = TracingInstance::start(trace_rx, false, settings);
+        for trace_item in items {
+            instance.update(trace_item);
+        }
+        instance
+    }
+
+    fn ready_begin(ms: u64, executor_id: u32, task_id: u32) -> TraceItem {
+        item(
+            ms,
+            0,
+            TraceItemType::TaskReadyBegin {
+                executor_id,
+                task_id,
+            },
+        )
+    }
+
+    fn exec_begin(ms: u64, executor_id: u32, task_id: u32) -> TraceItem {
+        item(
+            ms,
+            0,
+            TraceItemType::TaskExecBegin {
+                executor_id,
+                task_id,
+            },
+        )
+    }
+
+    #[test]
+    fn test_running_task_on_core_with_preemption() {
+        let mut items = vec![
+            item(
+                0,
+                0,
+                TraceItemType::TaskNew {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            item(
+                0,
+                0,
+                TraceItemType::TaskNew {
+                    executor_id: 2,
+                    task_id: 20,
+                },
+            ),
+            ready_begin(5, 1, 10),
+            item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            exec_begin(12, 1, 10),
+        ];
+        let instance = instance_with(&items);
+        assert_eq!(
+            instance.running_task_on_core(0).map(|(e, t, _)| (e, t)),
+            Some((1, 10))
+        );
+        assert_eq!(instance.running_task_on_core(1), None);
+
+        // higher priority executor preempts the running task
+        items.extend([
+            ready_begin(18, 2, 20),
+            item(20, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            exec_begin(22, 2, 20),
+        ]);
+        let instance = instance_with(&items);
+        assert_eq!(
+            instance.running_task_on_core(0).map(|(e, t, _)| (e, t)),
+            Some((2, 20))
+        );
+
+        // preempting task finished, executor still scheduling --> no task running
+        items.push(item(
+            30,
+            0,
+            TraceItemType::TaskExecEnd {
+                executor_id: 2,
+                task_id: 20,
+            },
+        ));
+        let instance = instance_with(&items);
+        assert_eq!(instance.running_task_on_core(0), None);
+
+        // preempting executor idle, then activity of the preempted executor --> preempted
+        // task is running again
+        items.extend([
+            item(32, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            ready_begin(34, 1, 11),
+        ]);
+        let instance = instance_with(&items);
+        assert_eq!(
+            instance.running_task_on_core(0).map(|(e, t, _)| (e, t)),
+            Some((1, 10))
+        );
+    }
+
+    /// Names of the tasks (with their executor) of all cores
+    fn task_names(instance: &TracingInstance) -> Vec<(String, String)> {
+        instance
+            .get_stats()
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .flat_map(|e| e.tasks.iter().map(|t| (e.name.clone(), t.name.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn test_firmware_reset_boot_generations() {
+        // Second boot reuses the executor id for a different task
+        let items = [
+            ready_begin(1000, 1, 7),
+            exec_begin(1010, 1, 7),
+            ready_begin(5, 1, 8),
+            exec_begin(10, 1, 8),
+        ];
+
+        // Without tagging the first boot is forgotten
+        let instance = instance_with(&items);
+        assert_eq!(
+            task_names(&instance),
+            vec![(String::from("Executor 0x1"), String::from("Task 0x8"))]
+        );
+        let timeline = instance.get_stats().timeline;
+        assert_eq!(timeline[0].kind, TimelineEventKind::FirmwareReset);
+        assert!(
+            instance
+                .history_records()
+                .iter()
+                .all(|r| r.boot_generation == 1)
+        );
+
+        let settings = InstanceSettings {
+            tag_boot_generations: true,
+            ..Default::default()
+        };
+        let instance = instance_with_settings(&items, settings);
+
+        assert_eq!(
+            task_names(&instance),
+            vec![
+                (String::from("Executor 0x1"), String::from("Task 0x8")),
+                (
+                    String::from("Executor 0x1 [boot 0]"),
+                    String::from("Task 0x7 [boot 0]")
+                ),
+            ]
+        );
+        let records = instance.history_records();
+        assert!(
+            records
+                .iter()
+                .any(|r| r.id == 7 && r.boot_generation == 0 && r.state == "Waiting")
+        );
+        assert!(records.iter().any(|r| r.id == 8 && r.boot_generation == 1));
+        assert!(!records.iter().any(|r| r.id == 8 && r.boot_generation == 0));
+    }
+
+    #[test]
+    fn test_devices_kept_separate() {
+        // Both devices use executor 1 and task 7 on core 0, device 1 restarts its clock
+        let on_device = |device_id: u32, mut trace_item: TraceItem| {
+            trace_item.device_id = device_id;
+            trace_item
+        };
+        let items = [
+            ready_begin(1000, 1, 7),
+            exec_begin(1010, 1, 7),
+            on_device(1, ready_begin(2000, 1, 7)),
+            on_device(1, exec_begin(2010, 1, 7)),
+            on_device(1, ready_begin(5, 1, 8)),
+            on_device(1, exec_begin(10, 1, 8)),
+        ];
+        let instance = instance_with(&items);
+
+        let stats = instance.get_stats();
+        let cores: Vec<(u32, u32)> = stats
+            .core_stats
+            .iter()
+            .map(|c| (c.device_id, c.core_id))
+            .collect();
+        assert_eq!(cores, vec![(0, 0), (1, 0)]);
+        let tasks = |device_id: u32| -> Vec<u32> {
+            stats
+                .core_stats
+                .iter()
+                .filter(|c| c.device_id == device_id)
+                .flat_map(|c| c.executors.iter())
+                .flat_map(|e| e.tasks.iter().map(|t| t.task_id))
+                .collect()
+        };
+        assert_eq!(tasks(0), vec![7]);
+        assert_eq!(tasks(1), vec![8]);
+        assert_eq!(stats.executor_count, 2);
+        assert_eq!(stats.tasks_count, 2);
+
+        // The reset of device 1 does not touch the primary device
+        assert!(
+            !stats
+                .timeline
+                .iter()
+                .any(|e| e.kind == TimelineEventKind::FirmwareReset)
+        );
+        assert_eq!(instance.running_tasks().len(), 1);
+
+        instance.reset();
+        assert_eq!(instance.get_stats().core_stats.len(), 0);
+    }
+
+    #[test]
+    fn test_user_marker() {
+        // PC clock 20ms ahead of the uC clock
+        let at = |uc_ms: u64| {
+            TimePair::new(
+                EmbassyTime::from_millis(uc_ms),
+                ComputerTime::new_from_duration(Duration::from_millis(uc_ms + 20)),
+            )
+        };
+        let instance = instance_with(&[TraceItem::new(
+            at(100),
+            0,
+            TraceItemType::TaskReadyBegin {
+                executor_id: 1,
+                task_id: 7,
+            },
+        )]);
+
+        // key pressed at PC time 520ms --> firmware time 500ms
+        let pressed_at = ComputerTime::new_from_duration(Duration::from_millis(520));
+        let marker = instance.add_user_marker(pressed_at, None);
+        assert_eq!(marker.label, "marker 1");
+        assert_eq!(marker.uc_time, Some(EmbassyTime::from_millis(500)));
+        assert!(
+            marker
+                .program_line()
+                .starts_with("# visor marker: marker 1 at 0.52")
+        );
+
+        let marker = instance.add_user_marker(pressed_at, Some(String::from("glitch")));
+        assert_eq!((marker.number, marker.label.as_str()), (2, "glitch"));
+
+        let marks: Vec<(u128, String)> = instance
+            .get_stats()
+            .timeline
+            .into_iter()
+            .filter(|e| e.kind == TimelineEventKind::UserMarker)
+            .map(|e| (e.time.as_millis(), e.description))
+            .collect();
+        assert_eq!(
+            marks,
+            vec![
+                (500, String::from("marker 1")),
+                (500, String::from("glitch"))
+            ]
+        );
+
+        let records = instance.history_records();
+        let marker_records: Vec<_> = records
+            .iter()
+            .filter(|r| r.entity_type == "marker")
+            .collect();
+        assert_eq!(marker_records.len(), 2);
+        assert_eq!(marker_records[0].start_uc_us, 500_000);
+        assert_eq!(marker_records[0].start_pc_us, 520_000);
+    }
+
+    #[test]
+    fn test_subscribe_task() {
+        let (_trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let instance = TracingInstance::new(trace_rx);
+        let events = instance.subscribe_task(10);
+        let dropped = instance.subscribe_task(20);
+        drop(dropped);
+
+        for trace_item in [
+            ready_begin(5, 1, 10),
+            ready_begin(6, 2, 20),
+            item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            exec_begin(12, 1, 10),
+            // task 20 runs on a higher priority executor and preempts task 10
+            item(20, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            exec_begin(22, 2, 20),
+            item(
+                25,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id: 2,
+                    task_id: 20,
+                },
+            ),
+            item(30, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            // task 10 resumes (from 30 on) with the next activity of its executor
+            ready_begin(32, 1, 11),
+            item(
+                35,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+        ] {
+            instance.update(&trace_item);
+        }
+
+        let transitions: Vec<(Option<TaskTraceState>, TaskTraceState, u128)> = events
+            .try_iter()
+            .map(|e| {
+                assert_eq!((e.task_id, e.executor_id), (10, 1));
+                (e.from, e.to, e.time.as_millis())
+            })
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![
+                // first seen with a ready event
+                (None, TaskTraceState::Waiting, 5),
+                (Some(TaskTraceState::Waiting), TaskTraceState::Running, 12),
+                (
+                    Some(TaskTraceState::Running),
+                    TaskTraceState::Preempted { by_executor_id: 2 },
+                    20
+                ),
+                (
+                    Some(TaskTraceState::Preempted { by_executor_id: 2 }),
+                    TaskTraceState::Running,
+                    30
+                ),
+                (Some(TaskTraceState::Running), TaskTraceState::Idle, 35),
+            ]
+        );
+
+        // the dropped subscription was removed on the next event of its task
+        let task_ids: Vec<u32> = instance
+            .task_subscribers
+            .lock()
+            .unwrap()
+            .task_ids()
+            .collect();
+        assert_eq!(task_ids, vec![10]);
+    }
+
+    #[test]
+    fn test_stats_at_past_time() {
+        let instance = instance_with(&[
+            ready_begin(5, 1, 10),
+            item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            exec_begin(12, 1, 10),
+            // higher priority executor preempts executor 1
+            item(20, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            item(30, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            item(
+                40,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+        ]);
+
+        let task_state_at = |ms| {
+            let stats = instance.get_stats_at(EmbassyTime::from_millis(ms))?;
+            let executor = stats.core_stats[0]
+                .executors
+                .iter()
+                .find(|e| e.executor_id == 1)?;
+            Some(executor.tasks[0].state)
+        };
+        assert_eq!(task_state_at(7), Some(TaskTraceState::Waiting));
+        assert_eq!(task_state_at(15), Some(TaskTraceState::Running));
+        assert_eq!(
+            task_state_at(25),
+            Some(TaskTraceState::Preempted { by_executor_id: 2 })
+        );
+        assert_eq!(task_state_at(40), Some(TaskTraceState::Idle));
+
+        // executor 2 did not exist yet at 15ms, its timeline event is not shown
+        let stats = instance.get_stats_at(EmbassyTime::from_millis(15)).unwrap();
+        assert_eq!(stats.executor_count, 1);
+        assert!(stats.timeline.iter().all(|e| e.time.as_millis() <= 15));
+
+        // before the first event --> outside the retained window
+        let (start, _) = instance.retained_window().unwrap();
+        assert_eq!(start, EmbassyTime::from_millis(5));
+        assert!(instance.get_stats_at(EmbassyTime::from_millis(4)).is_none());
+    }
+
+    #[test]
+    fn test_timeline_lifecycle_events() {
+        let instance = instance_with(&[
+            item(
+                0,
+                0,
+                TraceItemType::TaskNew {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            ready_begin(5, 1, 10),
+            item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            exec_begin(12, 1, 10),
+            // higher priority executor preempts executor 1
+            item(20, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            item(30, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            // exec begin while running
+            exec_begin(32, 1, 10),
+            item(
+                40,
+                0,
+                TraceItemType::TaskEnd {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+        ]);
+
+        let timeline: Vec<(u128, TimelineEventKind, String)> = instance
+            .get_stats()
+            .timeline
+            .into_iter()
+            .map(|e| (e.time.as_duration().as_millis(), e.kind, e.description))
+            .collect();
+        assert_eq!(
+            timeline,
+            vec![
+                (
+                    0,
+                    TimelineEventKind::ExecutorCreated,
+                    String::from("Executor 0x1 on core 0")
+                ),
+                (
+                    0,
+                    TimelineEventKind::TaskSpawned,
+                    String::from("Task 0xA on Executor 0x1")
+                ),
+                (
+                    20,
+                    TimelineEventKind::ExecutorCreated,
+                    String::from("Executor 0x2 on core 0")
+                ),
+                (
+                    20,
+                    TimelineEventKind::PreemptionStarted,
+                    String::from("Executor 0x1 preempted by Executor 0x2")
+                ),
+                (
+                    30,
+                    TimelineEventKind::PreemptionEnded,
+                    String::from("Executor 0x1 resumed")
+                ),
+                (
+                    32,
+                    TimelineEventKind::IntegrityWarning,
+                    String::from("Task 0xA: impossible state transition")
+                ),
+                (40, TimelineEventKind::TaskEnded, String::from("Task 0xA")),
+            ]
+        );
+
+        // reload forgets the timeline
+        instance.reset();
+        assert!(instance.get_stats().timeline.is_empty());
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let schema = |version| item(0, 0, TraceItemType::SchemaVersion { version });
+
+        // legacy beacon without the version event
+        let instance = instance_with(&[ready_begin(5, 1, 10)]);
+        assert_eq!(instance.get_stats().schema_warning, None);
+        assert_eq!(instance.get_stats().tasks_count, 1);
+
+        let instance = instance_with(&[schema(SUPPORTED_SCHEMA_VERSION), ready_begin(5, 1, 10)]);
+        assert_eq!(instance.get_stats().schema_warning, None);
+        assert_eq!(instance.get_stats().tasks_count, 1);
+
+        // mismatch: warned about, traced anyway
+        let items = [
+            ready_begin(1, 1, 10),
+            schema(SUPPORTED_SCHEMA_VERSION + 1),
+            ready_begin(5, 1, 11),
+        ];
+        let instance = instance_with(&items);
+        let warning = instance.get_stats().schema_warning.unwrap();
+        assert!(warning.ends_with("statistics may be wrong"), "{}", warning);
+        assert_eq!(instance.get_stats().tasks_count, 2);
+
+        // mismatch refused: nothing traced, not even before the version arrived
+        let settings = InstanceSettings {
+            refuse_schema_mismatch: true,
+            ..Default::default()
+        };
+        let instance = instance_with_settings(&items, settings);
+        let stats = instance.get_stats();
+        let warning = stats.schema_warning.unwrap();
+        assert!(warning.ends_with("trace ignored"), "{}", warning);
+        assert_eq!(stats.tasks_count, 0);
+        assert_eq!(stats.executor_count, 0);
+
+        // reload forgets the beacon's version
+        instance.reset();
+        assert_eq!(instance.get_stats().schema_warning, None);
+    }
+
+    #[test]
+    fn test_firmware_id() {
+        // beacon without firmware id
+        let instance = instance_with(&[ready_begin(5, 1, 10)]);
+        assert_eq!(
+            instance.get_stats().firmware_id_or_unknown(),
+            "unknown build"
+        );
+
+        let instance = instance_with(&[
+            item(
+                0,
+                0,
+                TraceItemType::FirmwareId {
+                    id: String::from("g1a2b3c4"),
+                },
+            ),
+            ready_begin(5, 1, 10),
+        ]);
+        assert_eq!(instance.get_stats().firmware_id_or_unknown(), "g1a2b3c4");
+        // exported with the history
+        let records = instance.history_records();
+        assert!(!records.is_empty());
+        assert!(
+            records
+                .iter()
+                .all(|r| r.firmware_id.as_deref() == Some("g1a2b3c4"))
+        );
+
+        // reload forgets the id of the old firmware
+        instance.reset();
+        assert_eq!(instance.get_stats().firmware_id, None);
+    }
+
+    #[test]
+    fn test_header() {
+        let header = TraceHeader {
+            schema_version: Some(SUPPORTED_SCHEMA_VERSION + 1),
+            core_ids: Some(vec![0, 1]),
+            firmware_id: Some(String::from("v1.4.0")),
+            ..TraceHeader::default()
+        };
+        let instance = instance_with(&[
+            item(0, 0, TraceItemType::Header { header }),
+            ready_begin(5, 1, 10),
+        ]);
+        let stats = instance.get_stats();
+        assert_eq!(stats.firmware_id_or_unknown(), "v1.4.0");
+        assert_eq!(stats.core_stats.len(), 2);
+        assert!(stats.schema_warning.is_some());
+
+        // partial header: the missing fields keep what was declared before
+        let instance = instance_with(&[
+            item(
+                0,
+                0,
+                TraceItemType::FirmwareId {
+                    id: String::from("g1a2b3c4"),
+                },
+            ),
+            item(
+                0,
+                0,
+                TraceItemType::Header {
+                    header: TraceHeader {
+                        schema_version: Some(SUPPORTED_SCHEMA_VERSION),
+                        ..TraceHeader::default()
+                    },
+                },
+            ),
+            ready_begin(5, 1, 10),
+        ]);
+        let stats = instance.get_stats();
+        assert_eq!(stats.firmware_id_or_unknown(), "g1a2b3c4");
+        assert_eq!(stats.core_stats.len(), 1);
+        assert_eq!(stats.schema_warning, None);
+    }
+
+    #[test]
+    fn test_executor_scoped_history_export() {
+        let instance = instance_with(&[
+            ready_begin(5, 1, 10),
+            item(6, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            exec_begin(7, 1, 10),
+            ready_begin(8, 2, 20),
+            item(9, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            exec_begin(10, 2, 20),
+            item(12, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+        ]);
+
+        let entities = |executor_id| {
+            instance
+                .executor_history_records(executor_id)
+                .iter()
+                .map(|r| (r.entity_type, r.id))
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(entities(1), BTreeSet::from([("executor", 1), ("task", 10)]));
+        assert_eq!(entities(2), BTreeSet::from([("executor", 2), ("task", 20)]));
+        assert!(entities(3).is_empty());
+
+        // the scoped export is a subset of the full one
+        let all = instance.history_records();
+        assert!(
+            instance
+                .executor_history_records(1)
+                .iter()
+                .all(|r| all.contains(r))
+        );
+    }
+
+    #[test]
+    fn test_heartbeats_align_clock() {
+        let heartbeat = |ms| item(ms, 0, TraceItemType::Heartbeat { interval_ms: 1000 });
+        let instance = instance_with(&[ready_begin(5, 1, 10), heartbeat(1000), heartbeat(2000)]);
+
+        // heartbeats only refine the clock, no executor is involved
+        let stats = instance.get_stats();
+        assert_eq!(stats.executor_count, 1);
+        let clock = stats.clock.unwrap();
+        assert_eq!(clock.offset_us, 0);
+        assert_eq!(clock.last_sample.as_millis(), 2000);
+
+        instance.reset();
+        assert_eq!(instance.get_stats().clock, None);
+    }
+
+    #[test]
+    fn test_task_under_two_executors() {
+        let instance = instance_with(&[
+            ready_begin(0, 1, 10),
+            item(5, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            exec_begin(6, 1, 10),
+            item(
+                8,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            item(9, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+            // the same task id shows up under executor 2
+            ready_begin(20, 2, 10),
+            item(25, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            exec_begin(26, 2, 10),
+        ]);
+
+        let stats = instance.get_stats();
+        let executors: Vec<_> = stats
+            .core_stats
+            .iter()
+            .flat_map(|core| core.executors.iter())
+            .collect();
+        let task_counts: Vec<(u32, usize)> = executors
+            .iter()
+            .map(|e| (e.executor_id, e.tasks.len()))
+            .collect();
+        assert_eq!(task_counts, vec![(1, 0), (2, 1)]);
+
+        // the task keeps its history and follows the events of its new executor
+        let task = &executors[1].tasks[0];
+        assert_eq!(task.task_id, 10);
+        assert_eq!(task.state, TaskTraceState::Running);
+
+        let moves: Vec<_> = stats
+            .timeline
+            .iter()
+            .filter(|e| e.kind == TimelineEventKind::IntegrityWarning)
+            .map(|e| (e.time.as_millis(), e.description.as_str()))
+            .collect();
+        assert_eq!(
+            moves,
+            vec![(20, "Task 0xA moved from Executor 0x1 to Executor 0x2")]
+        );
+    }
+
+    #[test]
+    fn test_timer_wakes_task() {
+        let instance = instance_with(&[
+            ready_begin(0, 1, 7),
+            item(1, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            exec_begin(2, 1, 7),
+            // task 7 sleeps until 10ms
+            item(
+                3,
+                0,
+                TraceItemType::TimerSet {
+                    executor_id: 1,
+                    task_id: 7,
+                    timer_id: 1,
+                    expires_at_us: 10_000,
+                },
+            ),
+            item(
+                4,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id: 1,
+                    task_id: 7,
+                },
+            ),
+            item(5, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+            item(10, 0, TraceItemType::TimerFire { timer_id: 1 }),
+            ready_begin(11, 1, 7),
+        ]);
+
+        let stats = instance.get_stats();
+        let task = &stats.core_stats[0].executors[0].tasks[0];
+        assert_eq!(task.state, TaskTraceState::Waiting);
+        // the timer events neither create executors nor tasks
+        assert_eq!((stats.executor_count, stats.tasks_count), (1, 1));
+
+        assert_eq!(stats.timers.len(), 1);
+        let timer = &stats.timers[0];
+        assert_eq!(timer.task, "Task 0x7");
+        assert_eq!(timer.fired_at, Some(EmbassyTime::from_millis(10)));
+        assert_eq!(timer.woken_at, Some(EmbassyTime::from_millis(11)));
+        assert_eq!(timer.wake_latency(), Some(EmbassyTime::from_millis(1)));
+    }
+
+    #[test]
+    fn test_lost_wakeup_in_timeline() {
+        let instance = instance_with(&[
+            ready_begin(10, 1, 10),
+            // executor 1 stays idle, executor 2 keeps the trace going
+            item(200, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            item(210, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+        ]);
+
+        let stats = instance.get_stats();
+        assert_eq!(stats.lost_wakeup_count(), 1);
+        assert_eq!(stats.lost_wakeup_tasks().len(), 1);
+        let lost_wakeups: Vec<_> = stats
+            .timeline
+            .iter()
+            .filter(|e| e.kind == TimelineEventKind::LostWakeup)
+            .collect();
+        assert_eq!(lost_wakeups.len(), 1);
+        assert_eq!(lost_wakeups[0].time.as_millis(), 200);
+    }
+
+    #[test]
+    fn test_event_counts() {
+        let poll_start = |ms| item(ms, 0, TraceItemType::ExecutorPollStart { executor_id: 1 });
+        let instance = instance_with(&[
+            item(0, 0, TraceItemType::Topology { core_count: 1 }),
+            poll_start(1),
+            ready_begin(2, 1, 10),
+            poll_start(3),
+            ready_begin(4, 1, 11),
+            exec_begin(5, 1, 10),
+            poll_start(6),
+        ]);
+
+        let totals: Vec<(TraceEventKind, u64)> = instance
+            .get_stats()
+            .event_counts
+            .iter()
+            .map(|count| (count.kind, count.total))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                (TraceEventKind::Topology, 1),
+                (TraceEventKind::ExecutorPollStart, 3),
+                (TraceEventKind::TaskExecBegin, 1),
+                (TraceEventKind::TaskReadyBegin, 2),
+            ]
+        );
+
+        instance.reset();
+        assert!(instance.get_stats().event_counts.is_empty());
+    }
+
+    #[test]
+    fn test_stats_snapshots_read_without_blocking() {
+        const TASK_COUNT: u32 = 2_000;
+        let (trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let (instance, update_thread) =
+            TracingInstance::start(trace_rx, true, InstanceSettings::current());
+        let updating = Arc::new(AtomicBool::new(true));
+
+        // every snapshot is one complete state: the task count matches the listed tasks and
+        // never goes back
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let instance = instance.clone();
+                let updating = updating.clone();
+                std::thread::spawn(move || {
+                    let mut previous_count = 0;
+                    while updating.load(Ordering::Relaxed) {
+                        let stats = instance.latest_stats();
+                        let listed: usize = stats
+                            .core_stats
+                            .iter()
+                            .flat_map(|core| core.executors.iter())
+                            .map(|executor| executor.tasks.len())
+                            .sum();
+                        assert_eq!(stats.tasks_count, listed);
+                        assert!(stats.tasks_count >= previous_count);
+                        previous_count = stats.tasks_count;
+                    }
+                })
+            })
+            .collect();
+
+        for task_id in 1..=TASK_COUNT {
+            let data = TraceItemType::TaskNew {
+                executor_id: 1,
+                task_id,
+            };
+            trace_tx.send(Ok(item(task_id as u64, 0, data))).unwrap();
+        }
+
+        // readers do not lock the model: reading while it is locked returns
+        let executors = instance.executors.lock().unwrap();
+        let _ = instance.latest_stats();
+        drop(executors);
+
+        // the update thread processes every item while being read
+        drop(trace_tx);
+        update_thread.join().unwrap();
+        updating.store(false, Ordering::Relaxed);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(instance.latest_stats().tasks_count, TASK_COUNT as usize);
+    }
+}
@@ -0,0 +1,140 @@
+//! Integrity checks of the trace: events arriving out of order, missing events (gaps) and
+//! events impossible in the current state. They are counted per task/executor and combined
+//! with the parse errors into one trace health score, telling the user how far the
+//! statistics can be trusted.
+
+use std::ops::Add;
+
+use crate::tracing::{parse_stats::ParseStats, time::EmbassyTime};
+
+/// Kind of an integrity issue of one trace event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// Event is older than the previous event of the same task/executor
+    OutOfOrder,
+    /// Event implies a missing event before it (e.g. exec begin without ready begin)
+    Gap,
+    /// Event is not possible in the current state (e.g. exec begin while running)
+    ImpossibleTransition,
+}
+
+impl IntegrityIssue {
+    pub fn description(&self) -> &'static str {
+        match self {
+            IntegrityIssue::OutOfOrder => "event out of order",
+            IntegrityIssue::Gap => "missing event (gap)",
+            IntegrityIssue::ImpossibleTransition => "impossible state transition",
+        }
+    }
+}
+
+/// Integrity issues detected for one task or executor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IntegrityCounters {
+    pub out_of_order: usize,
+    pub gaps: usize,
+    pub impossible_transitions: usize,
+
+    /// uC timestamp of the latest event (to detect out of order events)
+    last_event_time: Option<EmbassyTime>,
+}
+
+impl IntegrityCounters {
+    /// Count an issue (if any)
+    pub fn record(&mut self, issue: Option<IntegrityIssue>) {
+        match issue {
+            Some(IntegrityIssue::OutOfOrder) => self.out_of_order += 1,
+            Some(IntegrityIssue::Gap) => self.gaps += 1,
+            Some(IntegrityIssue::ImpossibleTransition) => self.impossible_transitions += 1,
+            None => {}
+        }
+    }
+
+    /// Check the event time against the latest event seen so far
+    pub fn check_order(&mut self, event_time: EmbassyTime) {
+        match self.last_event_time {
+            Some(last) if event_time < last => self.record(Some(IntegrityIssue::OutOfOrder)),
+            _ => self.last_event_time = Some(event_time),
+        }
+    }
+
+    /// Kind of the issue counted since the `before` snapshot (if any)
+    pub fn new_issue_since(&self, before: &IntegrityCounters) -> Option<IntegrityIssue> {
+        if self.out_of_order > before.out_of_order {
+            Some(IntegrityIssue::OutOfOrder)
+        } else if self.gaps > before.gaps {
+            Some(IntegrityIssue::Gap)
+        } else if self.impossible_transitions > before.impossible_transitions {
+            Some(IntegrityIssue::ImpossibleTransition)
+        } else {
+            None
+        }
+    }
+
+    /// Total number of issues
+    pub fn total(&self) -> usize {
+        self.out_of_order + self.gaps + self.impossible_transitions
+    }
+}
+
+impl Add for IntegrityCounters {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            out_of_order: self.out_of_order + other.out_of_order,
+            gaps: self.gaps + other.gaps,
+            impossible_transitions: self.impossible_transitions + other.impossible_transitions,
+            last_event_time: self.last_event_time.max(other.last_event_time),
+        }
+    }
+}
+
+/// Trace health in percent (0.0 - 100.0): share of trace lines without a parse error or
+/// integrity issue. 100% when no trace line was received yet.
+pub fn trace_health_percent(parse_stats: &ParseStats, integrity_issues: usize) -> f32 {
+    match parse_stats.total_count() {
+        0 => 100.0,
+        total => {
+            let issues = (parse_stats.error_count() + integrity_issues).min(total);
+            (1.0 - issues as f32 / total as f32) * 100.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{parse_stats::ParseStats, time::EmbassyTime, trace_data::TraceParseError};
+
+    use super::{IntegrityCounters, trace_health_percent};
+
+    #[test]
+    fn test_out_of_order_detection() {
+        let mut counters = IntegrityCounters::default();
+        for us in [10, 20, 15, 30, 30, 25] {
+            counters.check_order(EmbassyTime::from_micros(us));
+        }
+        assert_eq!(counters.out_of_order, 2);
+        assert_eq!(counters.total(), 2);
+    }
+
+    #[test]
+    fn test_trace_health_score() {
+        // nothing received yet
+        assert_eq!(trace_health_percent(&ParseStats::default(), 0), 100.0);
+
+        // 190 parsed + 10 failed lines, 30 integrity issues --> 40 of 200 lines affected
+        let mut parse_stats = ParseStats {
+            parsed_count: 190,
+            ..Default::default()
+        };
+        parse_stats
+            .error_counts
+            .insert(TraceParseError::InvalidFormat, 10);
+        assert_eq!(trace_health_percent(&parse_stats, 30), 80.0);
+        assert_eq!(trace_health_percent(&parse_stats, 0), 95.0);
+
+        // more issues than lines never goes below zero
+        assert_eq!(trace_health_percent(&parse_stats, 500), 0.0);
+    }
+}
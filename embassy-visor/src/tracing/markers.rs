@@ -0,0 +1,76 @@
+//! Markers set by the user while watching live (`M`), to find a noticed moment again in the
+//! timeline, the history export and the raw log. Unlike trace events they are taken at the PC
+//! time of the key press; the firmware time is estimated with the clock alignment.
+
+use crate::tracing::time::{ComputerTime, EmbassyTime, TimePair};
+
+/// Prefix of the line recorded into the program lines (and the raw log) for a marker
+pub const MARKER_LINE_PREFIX: &str = "# visor marker:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserMarker {
+    /// Number of the marker in the session (from 1 on)
+    pub number: u32,
+    pub label: String,
+    /// Time of the key press
+    pub pc_time: ComputerTime,
+    /// Estimated firmware time (None before the clocks could be aligned)
+    pub uc_time: Option<EmbassyTime>,
+    /// Boot generation of the firmware when the marker was set
+    pub boot_generation: u32,
+}
+
+impl UserMarker {
+    /// Marker time as a pair (uC time 0 if it could not be estimated)
+    pub fn time_pair(&self) -> TimePair {
+        TimePair::new(self.uc_time.unwrap_or(EmbassyTime::ZERO), self.pc_time)
+    }
+
+    /// Line recorded for the marker, e.g. "# visor marker: marker 2 at 12.345678s"
+    pub fn program_line(&self) -> String {
+        format!(
+            "{} {} at {:.6}s",
+            MARKER_LINE_PREFIX,
+            self.label,
+            self.pc_time.as_secs_f32()
+        )
+    }
+}
+
+/// Markers of the session, numbered in the order they were set
+#[derive(Debug, Clone, Default)]
+pub struct UserMarkers {
+    markers: Vec<UserMarker>,
+}
+
+impl UserMarkers {
+    /// Add a marker at the PC time, labelled "marker N" unless a label is given.
+    /// `clock_offset_us` is the estimated PC - uC offset (see `ClockSync`).
+    pub fn add(
+        &mut self,
+        pc_time: ComputerTime,
+        label: Option<String>,
+        clock_offset_us: Option<i128>,
+        boot_generation: u32,
+    ) -> UserMarker {
+        let number = self.markers.len() as u32 + 1;
+        let uc_time = clock_offset_us.map(|offset_us| {
+            let uc_us = (pc_time.as_micros() as i128 - offset_us).max(0);
+            EmbassyTime::from_micros(uc_us as u64)
+        });
+        let marker = UserMarker {
+            number,
+            label: label.unwrap_or_else(|| format!("marker {}", number)),
+            pc_time,
+            uc_time,
+            boot_generation,
+        };
+        self.markers.push(marker.clone());
+        marker
+    }
+
+    /// All markers (oldest first)
+    pub fn markers(&self) -> &[UserMarker] {
+        &self.markers
+    }
+}
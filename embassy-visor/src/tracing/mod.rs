@@ -1,6 +1,29 @@
+pub mod boot;
+pub mod channels;
+pub mod clock;
+pub mod compact;
+pub mod device;
+pub mod event_counts;
 pub mod executor;
+pub mod header;
+pub mod history_export;
 pub mod instance;
+pub mod integrity;
+pub mod markers;
+pub mod parse_stats;
+pub mod retention;
+pub mod schema;
+pub mod sleep;
+pub mod snapshot;
+pub mod stats;
+pub mod subscription;
 pub mod task;
+pub mod thresholds;
 pub mod time;
+pub mod timeline;
+pub mod timers;
 pub mod trace_data;
-pub mod stats;
\ No newline at end of file
+pub mod wake_chain;
+
+#[cfg(test)]
+pub mod test_harness;
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use crate::tracing::trace_data::{TraceParseError, TraceParseResult};
+
+/// Counts successfully parsed trace lines and parse errors by variant, so a broken setup
+/// (e.g. 10% of the lines failing with InvalidFormat) is visible to the user
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// Number of successfully parsed trace lines
+    pub parsed_count: usize,
+    /// Number of failed trace lines by error variant
+    pub error_counts: BTreeMap<TraceParseError, usize>,
+}
+
+impl ParseStats {
+    /// Count the result of parsing one trace line
+    pub fn record(&mut self, result: &TraceParseResult) {
+        match result {
+            Ok(_) => self.parsed_count += 1,
+            Err(e) => *self.error_counts.entry(*e).or_default() += 1,
+        }
+    }
+
+    /// Total number of trace lines failing to parse
+    pub fn error_count(&self) -> usize {
+        self.error_counts.values().sum()
+    }
+
+    /// Total number of trace lines seen (parsed or failed)
+    pub fn total_count(&self) -> usize {
+        self.parsed_count + self.error_count()
+    }
+
+    /// Share of trace lines failing to parse in percent (0.0 - 100.0)
+    pub fn error_percent(&self) -> f32 {
+        match self.total_count() {
+            0 => 0.0,
+            total => self.error_count() as f32 / total as f32 * 100.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{
+        time::ComputerTime,
+        trace_data::{TraceItem, TraceParseError},
+    };
+
+    use super::ParseStats;
+
+    #[test]
+    fn test_error_histogram() {
+        let lines = [
+            "[100, 0, TaskNew, 1, 42]",
+            "[110, 0, TaskReadyBegin, 1, 42]",
+            "[x, 0, TaskNew, 1, 42]",
+            "100, 0, TaskNew, 1, 42",
+            "[120, 0, TaskExecBegin, 1]",
+            "[130, 0, TaskFoo, 1, 42]",
+            "[140, 0, TaskExecEnd, 1, 42]",
+            "[150, 0, ExecutorIdle, 1",
+        ];
+
+        let mut stats = ParseStats::default();
+        for line in lines {
+            stats.record(&TraceItem::parse_from_line(line, ComputerTime::now()));
+        }
+
+        assert_eq!(stats.parsed_count, 3);
+        assert_eq!(stats.error_count(), 5);
+        assert_eq!(stats.total_count(), 8);
+        assert_eq!(stats.error_percent(), 62.5);
+
+        assert_eq!(stats.error_counts[&TraceParseError::InvalidTimestamp], 1);
+        assert_eq!(stats.error_counts[&TraceParseError::InvalidFormat], 2);
+        assert_eq!(stats.error_counts[&TraceParseError::InvalidEventPayload], 1);
+        assert_eq!(stats.error_counts[&TraceParseError::InvalidEventType], 1);
+        assert_eq!(stats.error_counts.len(), 4);
+    }
+}
@@ -0,0 +1,166 @@
+//! Retention of the state history of tasks and executors: by age, by entry count or whichever
+//! keeps more entries. Pruning by age alone keeps few samples of rarely active tasks (noisy
+//! statistics) and a huge number of frequently active ones.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
+
+use crate::tracing::{instance::HISTORY_MAX_TIME_S, time::ComputerTime};
+
+/// Policy the history is pruned with (a `RetentionPolicy` discriminant). An atomic, as the
+/// retention is read every time a task or executor history is pruned.
+pub static RETENTION_POLICY: AtomicU8 = AtomicU8::new(RetentionPolicy::MaxAge as u8);
+
+/// Entries kept per task/executor by the count based policies
+pub static HISTORY_MAX_ENTRIES: AtomicUsize = AtomicUsize::new(10_000);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RetentionPolicy {
+    /// Keep entries that ended within the last `HISTORY_MAX_TIME_S`
+    #[default]
+    MaxAge,
+    /// Keep the newest `HISTORY_MAX_ENTRIES` entries
+    MaxCount,
+    /// Keep an entry if either of the two would keep it
+    Larger,
+}
+
+impl RetentionPolicy {
+    /// Parse the `--history-retention` value
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value {
+            "age" => Some(RetentionPolicy::MaxAge),
+            "count" => Some(RetentionPolicy::MaxCount),
+            "larger" => Some(RetentionPolicy::Larger),
+            _ => None,
+        }
+    }
+
+    /// Policy stored in `RETENTION_POLICY`
+    fn from_repr(value: u8) -> Self {
+        match value {
+            1 => RetentionPolicy::MaxCount,
+            2 => RetentionPolicy::Larger,
+            _ => RetentionPolicy::MaxAge,
+        }
+    }
+}
+
+/// Policy with its limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    pub policy: RetentionPolicy,
+    pub max_age: ComputerTime,
+    pub max_entries: usize,
+}
+
+impl Retention {
+    /// Retention as configured
+    pub fn current() -> Self {
+        Self {
+            policy: RetentionPolicy::from_repr(RETENTION_POLICY.load(Ordering::Relaxed)),
+            max_age: ComputerTime::from_s(HISTORY_MAX_TIME_S.load(Ordering::Relaxed)),
+            max_entries: HISTORY_MAX_ENTRIES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Drop the oldest entries of the history (ordered by time) the policy does not keep
+    pub fn prune<T>(
+        &self,
+        history: &mut VecDeque<T>,
+        now: ComputerTime,
+        end_time: impl Fn(&T) -> ComputerTime,
+    ) {
+        while let Some(front) = history.front() {
+            let too_old = now.saturating_sub(end_time(front)) > self.max_age;
+            let too_many = history.len() > self.max_entries;
+            let drop = match self.policy {
+                RetentionPolicy::MaxAge => too_old,
+                RetentionPolicy::MaxCount => too_many,
+                RetentionPolicy::Larger => too_old && too_many,
+            };
+
+            if drop {
+                history.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, time::Duration};
+
+    use crate::tracing::time::ComputerTime;
+
+    use super::{Retention, RetentionPolicy};
+
+    /// End times (in s) of the entries left after pruning at 100s: 10 rare entries (every 5s
+    /// from 0s to 45s) followed by 50 frequent ones (within the last second)
+    fn retained(policy: RetentionPolicy) -> Vec<f32> {
+        let mut history: VecDeque<ComputerTime> = (0..10)
+            .map(|i| 5_000 * i)
+            .chain((0..50).map(|i| 99_000 + 20 * i))
+            .map(|ms| ComputerTime::new_from_duration(Duration::from_millis(ms as u64)))
+            .collect();
+        let retention = Retention {
+            policy,
+            max_age: ComputerTime::from_s(30),
+            max_entries: 55,
+        };
+        retention.prune(&mut history, ComputerTime::from_s(100), |end_time| {
+            *end_time
+        });
+        history.iter().map(|t| t.as_secs_f32()).collect()
+    }
+
+    #[test]
+    fn test_retention_by_age() {
+        // only the frequent entries are younger than 30s
+        let kept = retained(RetentionPolicy::MaxAge);
+        assert_eq!(kept.len(), 50);
+        assert_eq!(kept[0], 99.0);
+    }
+
+    #[test]
+    fn test_retention_by_count() {
+        // newest 55 entries, including the last 5 rare ones
+        let kept = retained(RetentionPolicy::MaxCount);
+        assert_eq!(kept.len(), 55);
+        assert_eq!(kept[0], 25.0);
+    }
+
+    #[test]
+    fn test_retention_larger() {
+        // count keeps more than age here
+        assert_eq!(retained(RetentionPolicy::Larger).len(), 55);
+
+        // age keeps more than count
+        let mut history: VecDeque<ComputerTime> = (0..100)
+            .map(|ms| ComputerTime::new_from_duration(Duration::from_millis(ms)))
+            .collect();
+        let retention = Retention {
+            policy: RetentionPolicy::Larger,
+            max_age: ComputerTime::from_s(30),
+            max_entries: 10,
+        };
+        retention.prune(&mut history, ComputerTime::from_s(1), |end_time| *end_time);
+        assert_eq!(history.len(), 100);
+    }
+
+    #[test]
+    fn test_policy_repr_round_trip() {
+        for policy in [
+            RetentionPolicy::MaxAge,
+            RetentionPolicy::MaxCount,
+            RetentionPolicy::Larger,
+        ] {
+            assert_eq!(RetentionPolicy::from_repr(policy as u8), policy);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Compatibility of the beacon's trace schema with the one the visor understands. The beacon
+//! publishes its `TRACE_SCHEMA_VERSION` once at startup; a different version means events or
+//! payloads may be misread, which is shown as a warning instead of silently wrong statistics.
+
+use std::sync::atomic::AtomicBool;
+
+/// Version of the trace schema this visor parses (the beacon's `TRACE_SCHEMA_VERSION`)
+pub const SUPPORTED_SCHEMA_VERSION: u32 = 1;
+
+/// Ignore the trace of a beacon with a different schema version instead of only warning. Read
+/// when an instance is created, see `InstanceSettings`.
+pub static REFUSE_SCHEMA_MISMATCH: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaCheck {
+    /// No version received (beacons published before the version event); assumed compatible
+    #[default]
+    Legacy,
+    Compatible,
+    Mismatch {
+        beacon_version: u32,
+    },
+}
+
+impl SchemaCheck {
+    /// Check the version published by the beacon
+    pub fn from_version(beacon_version: u32) -> Self {
+        if beacon_version == SUPPORTED_SCHEMA_VERSION {
+            SchemaCheck::Compatible
+        } else {
+            SchemaCheck::Mismatch { beacon_version }
+        }
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        matches!(self, SchemaCheck::Mismatch { .. })
+    }
+
+    /// Warning shown to the user, None if the trace can be parsed
+    pub fn warning(&self, refused: bool) -> Option<String> {
+        let SchemaCheck::Mismatch { beacon_version } = self else {
+            return None;
+        };
+        let newer = if *beacon_version > SUPPORTED_SCHEMA_VERSION {
+            "beacon"
+        } else {
+            "visor"
+        };
+        Some(format!(
+            "trace schema v{} of the beacon, visor supports v{} ({} is newer) - {}",
+            beacon_version,
+            SUPPORTED_SCHEMA_VERSION,
+            newer,
+            if refused {
+                "trace ignored"
+            } else {
+                "statistics may be wrong"
+            }
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_check() {
+        assert_eq!(
+            SchemaCheck::from_version(SUPPORTED_SCHEMA_VERSION),
+            SchemaCheck::Compatible
+        );
+        assert_eq!(SchemaCheck::Compatible.warning(false), None);
+        assert_eq!(SchemaCheck::Legacy.warning(true), None);
+
+        let newer_beacon = SchemaCheck::from_version(SUPPORTED_SCHEMA_VERSION + 1);
+        assert!(newer_beacon.is_mismatch());
+        let warning = newer_beacon.warning(false).unwrap();
+        assert!(warning.contains("beacon is newer"), "{}", warning);
+        assert!(warning.ends_with("statistics may be wrong"), "{}", warning);
+
+        let older_beacon = SchemaCheck::from_version(0);
+        let warning = older_beacon.warning(true).unwrap();
+        assert!(warning.contains("visor is newer"), "{}", warning);
+        assert!(warning.ends_with("trace ignored"), "{}", warning);
+    }
+}
@@ -0,0 +1,139 @@
+//! Inference of sleeping cores. The thread mode executor (e.g. `embassy_executor::Executor`
+//! on Cortex-M) waits for an event (WFE) when it has nothing to poll, while an interrupt
+//! executor only returns from its interrupt and the core keeps running. Embassy has no trace
+//! hook for the WFE itself, so the visor infers it from the executor states: the core sleeps
+//! while its thread mode executor is idle without a scheduled task and no interrupt executor
+//! is active.
+
+use std::collections::BTreeSet;
+
+use crate::tracing::{
+    executor::{ExecutorState, ExecutorTraceInfo},
+    task::TaskTraceState,
+};
+
+/// What the core of an idle executor does meanwhile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleState {
+    /// Thread mode executor without scheduled tasks: the core sleeps (WFE)
+    Sleeping,
+    /// The core keeps running (an interrupt executor, or a task is already scheduled again)
+    Awake,
+}
+
+/// Id of the thread mode executor of a core: the only executor of the core, or the only one
+/// preempted by the others without preempting any itself. None if it can not be told apart
+/// (e.g. no preemption was seen yet).
+pub fn infer_thread_mode_executor(executors: &[&ExecutorTraceInfo]) -> Option<u32> {
+    if let [executor] = executors {
+        return Some(executor.get_executor_id());
+    }
+
+    let preempting: BTreeSet<u32> = executors
+        .iter()
+        .flat_map(|e| e.calc_preempted_durations().into_keys())
+        .collect();
+    let mut candidates = executors.iter().filter(|e| {
+        let preempted = !e.calc_preempted_durations().is_empty()
+            || matches!(e.get_state(), ExecutorState::Preempted { .. });
+        preempted && !preempting.contains(&e.get_executor_id())
+    });
+
+    match (candidates.next(), candidates.next()) {
+        (Some(executor), None) => Some(executor.get_executor_id()),
+        _ => None,
+    }
+}
+
+/// Idle state of the executor, None while it is not idle
+pub fn idle_state(executor: &ExecutorTraceInfo, thread_mode: bool) -> Option<IdleState> {
+    if executor.get_state() != &ExecutorState::Idle {
+        return None;
+    }
+
+    // A scheduled task wakes the executor right away (the wake sets the event)
+    let scheduled = executor
+        .iter_tasks()
+        .any(|t| t.get_state() == &TaskTraceState::Waiting);
+    Some(if thread_mode && !scheduled {
+        IdleState::Sleeping
+    } else {
+        IdleState::Awake
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{
+        executor::ExecutorTraceInfo,
+        test_harness::{at_ms, feed_executor, item},
+        trace_data::TraceItemType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_sleep_inference() {
+        // thread mode (1) preempted by an interrupt executor (2) on core 0
+        let mut executors: Vec<ExecutorTraceInfo> = (1..=2)
+            .map(|executor_id| ExecutorTraceInfo::new(executor_id, 0, at_ms(0)))
+            .collect();
+        let ready = |ms, executor_id, task_id| {
+            item(
+                ms,
+                0,
+                TraceItemType::TaskReadyBegin {
+                    executor_id,
+                    task_id,
+                },
+            )
+        };
+        let items = [
+            item(
+                1,
+                0,
+                TraceItemType::TaskNew {
+                    executor_id: 1,
+                    task_id: 7,
+                },
+            ),
+            item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            item(12, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            item(14, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            item(16, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+        ];
+        for executor in executors.iter_mut() {
+            feed_executor(executor, &items);
+        }
+        let refs: Vec<&ExecutorTraceInfo> = executors.iter().collect();
+        assert_eq!(infer_thread_mode_executor(&refs), Some(1));
+        // a single executor is in thread mode
+        assert_eq!(infer_thread_mode_executor(&refs[1..]), Some(2));
+
+        // idle without scheduled tasks: the thread mode executor sleeps, the interrupt one not
+        assert_eq!(idle_state(&executors[0], true), Some(IdleState::Sleeping));
+        assert_eq!(idle_state(&executors[1], false), Some(IdleState::Awake));
+
+        // a scheduled task keeps the core awake
+        feed_executor(&mut executors[0], &[ready(20, 1, 7)]);
+        assert_eq!(idle_state(&executors[0], true), Some(IdleState::Awake));
+
+        // polling is not idle at all
+        feed_executor(
+            &mut executors[0],
+            &[item(
+                21,
+                0,
+                TraceItemType::ExecutorPollStart { executor_id: 1 },
+            )],
+        );
+        assert_eq!(idle_state(&executors[0], true), None);
+
+        // executors without preemption can not be told apart
+        let unrelated: Vec<ExecutorTraceInfo> = (3..=4)
+            .map(|executor_id| ExecutorTraceInfo::new(executor_id, 0, at_ms(0)))
+            .collect();
+        let refs: Vec<&ExecutorTraceInfo> = unrelated.iter().collect();
+        assert_eq!(infer_thread_mode_executor(&refs), None);
+    }
+}
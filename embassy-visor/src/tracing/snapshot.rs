@@ -0,0 +1,52 @@
+//! Statistics published by the update thread (`--stats-snapshots`). Calculating the
+//! statistics locks the model for the whole calculation, which stalls the update thread
+//! while the gatherer and the exporters read. With snapshots the update thread calculates
+//! them itself every `PUBLISH_INTERVAL` and swaps them in; readers load the latest snapshot
+//! without taking a lock, at the cost of up to one interval of delay.
+
+use std::{
+    sync::{Arc, Mutex, atomic::AtomicBool},
+    time::{Duration, Instant},
+};
+
+use arc_swap::ArcSwap;
+
+use crate::tracing::stats::instance_stats::InstanceStats;
+
+pub static STATS_SNAPSHOTS: AtomicBool = AtomicBool::new(false);
+
+/// Time between two published snapshots (while trace items are received)
+pub const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub struct StatsSnapshots {
+    latest: ArcSwap<InstanceStats>,
+    /// Time of the latest publication (only touched by the update thread)
+    published_at: Mutex<Instant>,
+}
+
+impl Default for StatsSnapshots {
+    fn default() -> Self {
+        Self {
+            latest: ArcSwap::from_pointee(InstanceStats::default()),
+            published_at: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl StatsSnapshots {
+    /// Latest published statistics
+    pub fn load(&self) -> Arc<InstanceStats> {
+        self.latest.load_full()
+    }
+
+    pub fn publish(&self, stats: InstanceStats) {
+        self.latest.store(Arc::new(stats));
+        *self.published_at.lock().unwrap() = Instant::now();
+    }
+
+    /// Check if the latest snapshot is older than the interval
+    pub fn is_due(&self) -> bool {
+        self.published_at.lock().unwrap().elapsed() >= PUBLISH_INTERVAL
+    }
+}
@@ -0,0 +1,202 @@
+use std::{sync::RwLock, time::Duration};
+
+use crate::tracing::stats::task_stats::TaskStats;
+
+/// Category of the tasks matching no configured category
+pub const OTHER_CATEGORY: &str = "other";
+
+/// User-defined task categories (e.g. "sensors", "comms"), tried in order
+pub static TASK_CATEGORIES: RwLock<Vec<TaskCategory>> = RwLock::new(Vec::new());
+
+/// Named group of tasks selected by name patterns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskCategory {
+    pub name: String,
+    /// Task name patterns, `*` matches any text and `?` a single character
+    pub patterns: Vec<String>,
+}
+
+impl TaskCategory {
+    /// Group (name, pattern) pairs by name, keeping the order of the first appearance
+    pub fn from_patterns(patterns: &[(String, String)]) -> Vec<Self> {
+        let mut categories: Vec<Self> = Vec::new();
+        for (name, pattern) in patterns {
+            match categories.iter_mut().find(|c| &c.name == name) {
+                Some(category) => category.patterns.push(pattern.clone()),
+                None => categories.push(Self {
+                    name: name.clone(),
+                    patterns: vec![pattern.clone()],
+                }),
+            }
+        }
+        categories
+    }
+
+    pub fn matches(&self, task_name: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| glob_matches(pattern, task_name))
+    }
+}
+
+/// Check if the whole text matches the pattern (`*` any text, `?` any single character)
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently matches up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the `*` match one more character
+            star = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Statistics of all tasks of one category
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryStats {
+    pub name: String,
+    pub task_count: usize,
+    /// Sum of the CPU utilizations of the tasks (percent of one core, may exceed 100% with
+    /// several cores)
+    pub cpu_utilization_percent: f32,
+    /// Average time in State 'Ready' over all waits of the tasks
+    pub avg_waiting_time: Duration,
+    /// Maximal time in State 'Ready' of any task
+    pub max_waiting_time: Duration,
+    /// Total count the tasks were in State 'Ready'
+    pub count_waiting_time: usize,
+}
+
+impl CategoryStats {
+    fn empty(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            task_count: 0,
+            cpu_utilization_percent: 0.0,
+            avg_waiting_time: Duration::ZERO,
+            max_waiting_time: Duration::ZERO,
+            count_waiting_time: 0,
+        }
+    }
+
+    fn add_task(&mut self, task: &TaskStats) {
+        let total_waiting_time = self.avg_waiting_time * self.count_waiting_time as u32
+            + task.avg_waiting_time * task.count_waiting_time as u32;
+
+        self.task_count += 1;
+        self.cpu_utilization_percent += task.cpu_utilization_percent;
+        self.max_waiting_time = self.max_waiting_time.max(task.max_waiting_time);
+        self.count_waiting_time += task.count_waiting_time;
+        if self.count_waiting_time > 0 {
+            self.avg_waiting_time = total_waiting_time / self.count_waiting_time as u32;
+        }
+    }
+
+    /// Aggregate the tasks into the categories (each task into the first matching one). The
+    /// configured categories are listed in order, followed by "other" if any task matches none.
+    pub fn from_tasks<'a>(
+        tasks: impl Iterator<Item = &'a TaskStats>,
+        categories: &[TaskCategory],
+    ) -> Vec<Self> {
+        let mut stats: Vec<Self> = categories.iter().map(|c| Self::empty(&c.name)).collect();
+        let mut other = Self::empty(OTHER_CATEGORY);
+
+        for task in tasks {
+            match categories.iter().position(|c| c.matches(&task.name)) {
+                Some(index) => stats[index].add_task(task),
+                None => other.add_task(task),
+            }
+        }
+
+        if other.task_count > 0 {
+            stats.push(other);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{test_support::task_stats, tracing::stats::task_stats::TaskStats};
+
+    use super::*;
+
+    fn task(name: &str, cpu_utilization_percent: f32, avg_wait_ms: u64, waits: usize) -> TaskStats {
+        TaskStats {
+            max_waiting_time: Duration::from_millis(avg_wait_ms * 2),
+            avg_waiting_time: Duration::from_millis(avg_wait_ms),
+            count_waiting_time: waits,
+            cpu_utilization_percent,
+            ..task_stats(0, name)
+        }
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("imu_task", "imu_task"));
+        assert!(!glob_matches("imu_task", "imu_task2"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("imu_*", "imu_task"));
+        assert!(glob_matches("*::sensors::*", "app::sensors::baro"));
+        assert!(!glob_matches("*::sensors::*", "app::comms::wifi"));
+        assert!(glob_matches("led?", "led1"));
+        assert!(!glob_matches("led?", "led"));
+        assert!(glob_matches("*a*b", "xaxxab"));
+    }
+
+    #[test]
+    fn test_category_stats() {
+        let categories = TaskCategory::from_patterns(&[
+            (String::from("sensors"), String::from("imu_*")),
+            (String::from("comms"), String::from("*wifi*")),
+            (String::from("sensors"), String::from("baro_task")),
+            (String::from("display"), String::from("lcd_*")),
+        ]);
+        assert_eq!(categories.len(), 3);
+
+        let tasks = [
+            task("imu_task", 10.0, 4, 1),
+            task("baro_task", 5.0, 1, 3),
+            task("wifi_rx", 20.0, 2, 2),
+            task("app::wifi_tx", 2.5, 0, 0),
+            task("blinky", 1.0, 0, 0),
+        ];
+        let stats = CategoryStats::from_tasks(tasks.iter(), &categories);
+
+        let names: Vec<&str> = stats.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["sensors", "comms", "display", "other"]);
+
+        assert_eq!(stats[0].task_count, 2);
+        assert_eq!(stats[0].cpu_utilization_percent, 15.0);
+        // (4ms * 1 + 1ms * 3) / 4 waits
+        assert_eq!(stats[0].avg_waiting_time, Duration::from_micros(1750));
+        assert_eq!(stats[0].max_waiting_time, Duration::from_millis(8));
+        assert_eq!(stats[0].count_waiting_time, 4);
+
+        assert_eq!(stats[1].task_count, 2);
+        assert_eq!(stats[1].cpu_utilization_percent, 22.5);
+        assert_eq!(stats[1].avg_waiting_time, Duration::from_millis(2));
+
+        assert_eq!(stats[2], CategoryStats::empty("display"));
+
+        assert_eq!(stats[3].task_count, 1);
+        assert_eq!(stats[3].cpu_utilization_percent, 1.0);
+    }
+}
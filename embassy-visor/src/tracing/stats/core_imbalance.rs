@@ -0,0 +1,95 @@
+//! Load imbalance between the cores of a device: a large gap between the busiest and the
+//! idlest core hints at a poor placement of the executors and tasks on the cores.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use itertools::Itertools;
+
+use crate::tracing::stats::core_stats::CoreStats;
+
+/// Gap between the busiest and the idlest core (percentage points) flagged as imbalanced
+pub static CORE_IMBALANCE_THRESHOLD_PERCENT: AtomicU64 = AtomicU64::new(50);
+
+/// Busiest and idlest core of a device as (core id, CPU utilization in percent)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoreImbalance {
+    pub device_id: u32,
+    pub busiest: (u32, f32),
+    pub idlest: (u32, f32),
+}
+
+impl CoreImbalance {
+    /// Largest imbalance among the devices, None if no device has more than one core
+    pub fn from_cores(core_stats: &[CoreStats]) -> Option<Self> {
+        core_stats
+            .iter()
+            .into_group_map_by(|core| core.device_id)
+            .into_iter()
+            .filter(|(_, cores)| cores.len() > 1)
+            .filter_map(|(device_id, cores)| {
+                let (idlest, busiest) = cores
+                    .iter()
+                    .minmax_by(|a, b| {
+                        a.cpu_utilization_percent
+                            .total_cmp(&b.cpu_utilization_percent)
+                    })
+                    .into_option()?;
+                Some(Self {
+                    device_id,
+                    busiest: (busiest.core_id, busiest.cpu_utilization_percent),
+                    idlest: (idlest.core_id, idlest.cpu_utilization_percent),
+                })
+            })
+            .max_by(|a, b| a.gap_percent().total_cmp(&b.gap_percent()))
+    }
+
+    /// Utilization of the busiest minus the idlest core (percentage points)
+    pub fn gap_percent(&self) -> f32 {
+        self.busiest.1 - self.idlest.1
+    }
+
+    /// Gap reaches `CORE_IMBALANCE_THRESHOLD_PERCENT`
+    pub fn is_flagged(&self) -> bool {
+        self.gap_percent() >= CORE_IMBALANCE_THRESHOLD_PERCENT.load(Ordering::Relaxed) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn core(device_id: u32, core_id: u32, cpu_utilization_percent: f32) -> CoreStats {
+        CoreStats {
+            device_id,
+            cpu_utilization_percent,
+            ..CoreStats::empty(core_id)
+        }
+    }
+
+    #[test]
+    fn test_core_imbalance() {
+        // single core: nothing to compare
+        assert_eq!(CoreImbalance::from_cores(&[core(0, 0, 90.0)]), None);
+
+        let cores = [
+            core(0, 0, 85.0),
+            core(0, 1, 20.0),
+            core(0, 2, 40.0),
+            // other device with a smaller gap; its single core is not compared with device 0
+            core(1, 0, 60.0),
+            core(1, 1, 50.0),
+            core(2, 0, 0.0),
+        ];
+        let imbalance = CoreImbalance::from_cores(&cores).unwrap();
+        assert_eq!(imbalance.device_id, 0);
+        assert_eq!(imbalance.busiest, (0, 85.0));
+        assert_eq!(imbalance.idlest, (1, 20.0));
+        assert_eq!(imbalance.gap_percent(), 65.0);
+        assert!(imbalance.is_flagged());
+
+        let balanced = CoreImbalance::from_cores(&cores[3..]).unwrap();
+        assert_eq!(balanced.device_id, 1);
+        assert_eq!(balanced.gap_percent(), 10.0);
+        assert!(!balanced.is_flagged());
+    }
+}
@@ -2,15 +2,34 @@ use std::collections::HashMap;
 
 use itertools::Itertools;
 
-use crate::tracing::stats::executor_stats::ExecutorStats;
+use crate::tracing::{
+    device::PRIMARY_DEVICE_ID,
+    sleep::{self, IdleState},
+    stats::{
+        cpu_basis::{CPU_BASIS, apply_cpu_basis},
+        executor_stats::{ExecutorStats, scheduling_overhead_percent},
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct CoreStats {
     pub core_id: u32,
+    /// Device of the core (`PRIMARY_DEVICE_ID` unless traced with `--device`)
+    pub device_id: u32,
     pub executors: Vec<ExecutorStats>,
 
     /// CPU utilization in percent (0.0 - 100.0)
     pub cpu_utilization_percent: f32,
+
+    /// Number of executors currently stacked above the bottom preempted one (0 without
+    /// preemption)
+    pub preemption_depth: usize,
+
+    /// Core currently sleeping: its thread mode executor waits for an event (inferred)
+    pub asleep: bool,
+
+    /// Core traced but missing from the cores the beacon declared (the topology is wrong)
+    pub undeclared: bool,
 }
 
 impl CoreStats {
@@ -18,23 +37,74 @@ impl CoreStats {
     pub fn from_executor_list_on_core(
         executors: &Vec<&crate::tracing::executor::ExecutorTraceInfo>,
     ) -> Self {
+        let executors_on_core = executors;
         let core_id = executors.first().map_or(0, |e| e.get_core_id());
-        let executors = ExecutorStats::from_executor_list(executors);
+        let mut executors = ExecutorStats::from_executor_list(executors);
         let cpu_utilization_percent = executors.iter().map(|e| e.cpu_utilization_percent).sum();
+        // The core itself always shows its share of the wall time
+        apply_cpu_basis(
+            *CPU_BASIS.read().unwrap(),
+            &mut executors,
+            executors_on_core,
+        );
+
+        // The bottom executor knows every executor stacked above it
+        let preemption_depth = executors_on_core
+            .iter()
+            .map(|e| e.get_preemption_depth())
+            .max()
+            .unwrap_or(0);
+
+        let thread_mode = sleep::infer_thread_mode_executor(executors_on_core);
+        for (stats, executor) in executors.iter_mut().zip(executors_on_core.iter()) {
+            stats.idle_state =
+                sleep::idle_state(executor, thread_mode == Some(executor.get_executor_id()));
+        }
+        // An active interrupt executor runs on the core even if thread mode sleeps
+        let asleep = executors
+            .iter()
+            .any(|e| e.idle_state == Some(IdleState::Sleeping))
+            && executors.iter().all(|e| e.idle_state.is_some());
 
         Self {
             core_id,
+            device_id: PRIMARY_DEVICE_ID,
             executors,
             cpu_utilization_percent,
+            preemption_depth,
+            asleep,
+            undeclared: false,
+        }
+    }
+
+    /// Share of the active time of all executors spent scheduling instead of polling
+    pub fn scheduling_overhead_percent(&self) -> Option<f32> {
+        scheduling_overhead_percent(
+            self.executors.iter().map(|e| e.scheduling_time).sum(),
+            self.executors.iter().map(|e| e.polling_time).sum(),
+        )
+    }
+
+    /// Create CoreStats for a core without any executor activity
+    pub fn empty(core_id: u32) -> Self {
+        Self {
+            core_id,
+            device_id: PRIMARY_DEVICE_ID,
+            executors: Vec::new(),
+            cpu_utilization_percent: 0.0,
+            preemption_depth: 0,
+            asleep: false,
+            undeclared: false,
         }
     }
 
-    /// Group by core_id and create CoreStats for each core
+    /// Group by core_id and create CoreStats for each core. The `declared_cores` are always
+    /// included, even without executors.
     pub fn from_executor_list(
         executors: &Vec<crate::tracing::executor::ExecutorTraceInfo>,
+        declared_cores: Option<&[u32]>,
     ) -> Vec<Self> {
-        let mut executors_by_core: HashMap<u32, Vec<_>> =
-            HashMap::new();
+        let mut executors_by_core: HashMap<u32, Vec<_>> = HashMap::new();
 
         for executor in executors {
             executors_by_core
@@ -43,10 +113,113 @@ impl CoreStats {
                 .push(executor);
         }
 
+        // Add declared cores without activity
+        for &core_id in declared_cores.unwrap_or_default() {
+            executors_by_core.entry(core_id).or_default();
+        }
+
         executors_by_core
             .into_iter()
-            .map(|(_, execs)| Self::from_executor_list_on_core(&execs))
+            .map(|(core_id, execs)| {
+                let mut core = if execs.is_empty() {
+                    Self::empty(core_id)
+                } else {
+                    Self::from_executor_list_on_core(&execs)
+                };
+                core.undeclared = declared_cores.is_some_and(|ids| !ids.contains(&core_id));
+                core
+            })
             .sorted_by(|a, b| a.core_id.cmp(&b.core_id))
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{
+        executor::ExecutorTraceInfo,
+        test_harness::{at_ms, feed_executor, item},
+        trace_data::TraceItemType,
+    };
+
+    use super::CoreStats;
+
+    #[test]
+    fn test_declared_idle_cores_included() {
+        let mut executor = ExecutorTraceInfo::new(1, 1, at_ms(0));
+        feed_executor(
+            &mut executor,
+            &[item(
+                10,
+                1,
+                TraceItemType::ExecutorPollStart { executor_id: 1 },
+            )],
+        );
+        let executors = vec![executor];
+
+        // Without topology only the active core is known
+        let core_stats = CoreStats::from_executor_list(&executors, None);
+        assert_eq!(core_stats.len(), 1);
+        assert_eq!(core_stats[0].core_id, 1);
+
+        assert!(!core_stats[0].undeclared);
+
+        // With topology the idle cores show up with 0%
+        let core_stats = CoreStats::from_executor_list(&executors, Some(&[0, 1, 3]));
+        let core_ids: Vec<u32> = core_stats.iter().map(|c| c.core_id).collect();
+        assert_eq!(core_ids, vec![0, 1, 3]);
+        assert!(core_stats[0].executors.is_empty());
+        assert_eq!(core_stats[0].cpu_utilization_percent, 0.0);
+        assert_eq!(core_stats[1].executors.len(), 1);
+        assert!(core_stats.iter().all(|c| !c.undeclared));
+
+        // A traced core the topology lacks is flagged
+        let core_stats = CoreStats::from_executor_list(&executors, Some(&[0]));
+        assert!(!core_stats[0].undeclared);
+        assert!(core_stats[1].undeclared);
+    }
+
+    #[test]
+    fn test_nested_preemption_depth() {
+        // low (1) < mid (2) < high (3) priority on core 0
+        let mut executors: Vec<ExecutorTraceInfo> = (1..=3)
+            .map(|executor_id| ExecutorTraceInfo::new(executor_id, 0, at_ms(0)))
+            .collect();
+        let items = [
+            item(10, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            item(20, 0, TraceItemType::ExecutorPollStart { executor_id: 2 }),
+            item(30, 0, TraceItemType::ExecutorPollStart { executor_id: 3 }),
+        ];
+        for executor in executors.iter_mut() {
+            feed_executor(executor, &items);
+        }
+        assert_eq!(
+            CoreStats::from_executor_list(&executors, None)[0].preemption_depth,
+            2
+        );
+
+        // high goes idle --> only mid is stacked above low
+        for executor in executors.iter_mut() {
+            feed_executor(
+                executor,
+                &[item(40, 0, TraceItemType::ExecutorIdle { executor_id: 3 })],
+            );
+        }
+        assert_eq!(
+            CoreStats::from_executor_list(&executors, None)[0].preemption_depth,
+            1
+        );
+
+        // mid goes idle --> low runs again
+        for executor in executors.iter_mut() {
+            feed_executor(
+                executor,
+                &[item(50, 0, TraceItemType::ExecutorIdle { executor_id: 2 })],
+            );
+        }
+        assert_eq!(
+            CoreStats::from_executor_list(&executors, None)[0].preemption_depth,
+            0
+        );
+    }
+}
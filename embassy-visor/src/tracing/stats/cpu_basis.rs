@@ -0,0 +1,249 @@
+//! Basis the CPU utilization of tasks and executors is relative to (cycled with 'C'). The
+//! wall time shows how much of the whole time a task takes. On a core shared by several
+//! executors the executor's or the core's active time show instead which task dominates its
+//! executor or the core, however busy they are.
+
+use std::sync::RwLock;
+
+use crate::tracing::{
+    executor::ExecutorTraceInfo,
+    stats::{executor_stats::ExecutorStats, task_stats::cpu_window},
+    task::TaskTraceState,
+};
+
+/// Basis of the displayed task and executor CPU utilization
+pub static CPU_BASIS: RwLock<CpuBasis> = RwLock::new(CpuBasis::WallTime);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CpuBasis {
+    /// Share of the whole time (tasks: of their lifetime, or active time with `--active-only-cpu`)
+    #[default]
+    WallTime,
+    /// Share of the time the executor was active (polling or scheduling); executors show the
+    /// share spent polling
+    ExecutorActive,
+    /// Share of the time all executors of the core were active together
+    CoreActive,
+}
+
+impl CpuBasis {
+    /// Next basis when cycling
+    pub fn next(self) -> Self {
+        match self {
+            CpuBasis::WallTime => CpuBasis::ExecutorActive,
+            CpuBasis::ExecutorActive => CpuBasis::CoreActive,
+            CpuBasis::CoreActive => CpuBasis::WallTime,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CpuBasis::WallTime => "wall time",
+            CpuBasis::ExecutorActive => "executor-active",
+            CpuBasis::CoreActive => "core-active",
+        }
+    }
+}
+
+/// Share in percent, 0 without any time
+fn percent(part: f32, whole: f32) -> f32 {
+    if whole > 0.0 {
+        part / whole * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Replace the (wall time) CPU utilization of the executors of one core and their tasks by the
+/// one relative to the basis. The executor statistics are in the order of the executors.
+pub fn apply_cpu_basis(
+    basis: CpuBasis,
+    stats: &mut [ExecutorStats],
+    executors_on_core: &[&ExecutorTraceInfo],
+) {
+    if basis == CpuBasis::WallTime {
+        return;
+    }
+
+    let active_s = |executor: &ExecutorTraceInfo| {
+        let (scheduling, polling) = executor.calc_work_split(cpu_window());
+        (scheduling + polling).as_duration().as_secs_f32()
+    };
+    let core_active_s: f32 = executors_on_core.iter().map(|e| active_s(e)).sum();
+
+    for (stats, executor) in stats.iter_mut().zip(executors_on_core.iter()) {
+        let executor_active_s = active_s(executor);
+        let basis_s = match basis {
+            CpuBasis::ExecutorActive => executor_active_s,
+            _ => core_active_s,
+        };
+
+        stats.cpu_utilization_percent = match basis {
+            CpuBasis::ExecutorActive => percent(
+                executor
+                    .calc_work_split(cpu_window())
+                    .1
+                    .as_duration()
+                    .as_secs_f32(),
+                executor_active_s,
+            ),
+            _ => percent(executor_active_s, core_active_s),
+        };
+        for task_stats in stats.tasks.iter_mut() {
+            let running_s = executor
+                .find_task_by_id(task_stats.task_id)
+                .map_or(0.0, |task| {
+                    task.calc_total_history_state_duration(TaskTraceState::Running, cpu_window())
+                        .as_secs_f32()
+                });
+            task_stats.cpu_utilization_percent = percent(running_s, basis_s);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracing::{
+        test_harness::{at_ms, feed_executor, item},
+        trace_data::TraceItemType,
+    };
+
+    #[test]
+    fn test_task_cpu_under_each_basis() {
+        use TraceItemType::*;
+
+        // executor 1 schedules 1ms, polls task 10 for 4ms and task 11 for 2ms; executor 2 polls
+        // task 20 for 3ms; both on core 0 and idle for the rest of the 100ms
+        let mut executors = [
+            ExecutorTraceInfo::new(1, 0, at_ms(0)),
+            ExecutorTraceInfo::new(2, 0, at_ms(0)),
+        ];
+        let ready = |ms, executor_id, task_id| {
+            item(
+                ms,
+                0,
+                TaskReadyBegin {
+                    executor_id,
+                    task_id,
+                },
+            )
+        };
+        let exec = |ms, executor_id, task_id, begin| {
+            item(
+                ms,
+                0,
+                if begin {
+                    TaskExecBegin {
+                        executor_id,
+                        task_id,
+                    }
+                } else {
+                    TaskExecEnd {
+                        executor_id,
+                        task_id,
+                    }
+                },
+            )
+        };
+        let items = [
+            item(
+                0,
+                0,
+                TaskNew {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            item(
+                0,
+                0,
+                TaskNew {
+                    executor_id: 1,
+                    task_id: 11,
+                },
+            ),
+            item(
+                0,
+                0,
+                TaskNew {
+                    executor_id: 2,
+                    task_id: 20,
+                },
+            ),
+            ready(10, 1, 10),
+            ready(10, 1, 11),
+            item(10, 0, ExecutorPollStart { executor_id: 1 }),
+            exec(11, 1, 10, true),
+            exec(15, 1, 10, false),
+            exec(15, 1, 11, true),
+            exec(17, 1, 11, false),
+            item(17, 0, ExecutorIdle { executor_id: 1 }),
+            ready(30, 2, 20),
+            item(30, 0, ExecutorPollStart { executor_id: 2 }),
+            exec(30, 2, 20, true),
+            exec(33, 2, 20, false),
+            item(33, 0, ExecutorIdle { executor_id: 2 }),
+            // close the idle states
+            item(100, 0, ExecutorPollStart { executor_id: 1 }),
+            item(100, 0, ExecutorPollStart { executor_id: 2 }),
+        ];
+        for executor in executors.iter_mut() {
+            feed_executor(executor, &items);
+        }
+        let executors_on_core: Vec<&ExecutorTraceInfo> = executors.iter().collect();
+        let wall_time: Vec<ExecutorStats> =
+            executors.iter().map(ExecutorStats::from_executor).collect();
+
+        // (executor, task 10, task 11, executor 2, task 20) in percent
+        let cpu = |basis| {
+            let mut stats = wall_time.clone();
+            apply_cpu_basis(basis, &mut stats, &executors_on_core);
+            [
+                stats[0].cpu_utilization_percent,
+                stats[0].tasks[0].cpu_utilization_percent,
+                stats[0].tasks[1].cpu_utilization_percent,
+                stats[1].cpu_utilization_percent,
+                stats[1].tasks[0].cpu_utilization_percent,
+            ]
+        };
+        let assert_cpu = |basis, expected: [f32; 5]| {
+            let actual = cpu(basis);
+            for (actual, expected) in actual.iter().zip(expected) {
+                assert!(
+                    (actual - expected).abs() < 0.01,
+                    "{:?}: {:?}",
+                    basis,
+                    cpu(basis)
+                );
+            }
+        };
+
+        // wall time: unchanged
+        assert_cpu(
+            CpuBasis::WallTime,
+            [
+                wall_time[0].cpu_utilization_percent,
+                wall_time[0].tasks[0].cpu_utilization_percent,
+                wall_time[0].tasks[1].cpu_utilization_percent,
+                wall_time[1].cpu_utilization_percent,
+                wall_time[1].tasks[0].cpu_utilization_percent,
+            ],
+        );
+        // of the executor's 7ms and 3ms active time (executors: share polling)
+        assert_cpu(
+            CpuBasis::ExecutorActive,
+            [
+                6.0 / 7.0 * 100.0,
+                4.0 / 7.0 * 100.0,
+                2.0 / 7.0 * 100.0,
+                100.0,
+                100.0,
+            ],
+        );
+        // of the core's 10ms active time
+        assert_cpu(CpuBasis::CoreActive, [70.0, 40.0, 20.0, 30.0, 30.0]);
+
+        assert_eq!(CpuBasis::CoreActive.next(), CpuBasis::WallTime);
+    }
+}
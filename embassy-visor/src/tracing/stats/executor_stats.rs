@@ -1,29 +1,356 @@
-use crate::tracing::{executor::ExecutorTraceInfo, stats::task_stats::TaskStats};
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::tracing::{
+    executor::{ExecutorTraceInfo, PingPongRun},
+    integrity::IntegrityCounters,
+    sleep::IdleState,
+    stats::task_stats::{TaskStats, cpu_window},
+};
 
 #[derive(Debug, Clone)]
 pub struct ExecutorStats {
-    pub name : String,
-    pub tasks : Vec<TaskStats>,
+    pub executor_id: u32,
+    pub core_id: u32,
+    pub name: String,
+    pub tasks: Vec<TaskStats>,
 
     /// CPU utilization in percent (0.0 - 100.0) [Scheduling + Polling]
-    pub cpu_utilization_percent : f32,
+    pub cpu_utilization_percent: f32,
+
+    /// Integrity issues detected in the executor's own events (without its tasks)
+    pub integrity: IntegrityCounters,
+
+    /// Number of lost wakeups detected (tasks ready while the executor stayed idle)
+    pub lost_wakeup_count: usize,
+
+    /// Mean share of the time between wakeups spent working (None without a complete cycle)
+    pub budget_utilization_percent: Option<f32>,
+
+    /// Time spent deciding what to poll next (from the history)
+    pub scheduling_time: Duration,
+
+    /// Time spent polling tasks (from the history)
+    pub polling_time: Duration,
+
+    /// Total time the executor was preempted per preempting executor id
+    pub preempted_by: BTreeMap<u32, Duration>,
+
+    /// Longest single poll so far (None before the first complete poll)
+    pub worst_poll: Option<WorstPoll>,
+
+    /// Executor did not go idle for a long time (never yielding task, unlike a high CPU usage
+    /// from a lot of short polls)
+    pub pegged: bool,
+
+    /// Two tasks suspected of waking each other without progress (None if there are none)
+    pub ping_pong: Option<PingPongRun>,
+
+    /// Whether the core sleeps or keeps running while the executor is idle (None while not
+    /// idle, set by the core since it depends on the other executors of the core)
+    pub idle_state: Option<IdleState>,
+
+    /// Live task counts sampled over the session, oldest first (only set by the TUI)
+    pub task_count_history: Vec<usize>,
+
+    /// Task count kept growing after the startup: tasks spawned but never ending (only set by
+    /// the TUI)
+    pub task_count_growing: bool,
+
+    /// Warnings acknowledged by the user, shown without emphasis (only set by the TUI)
+    pub warnings_acknowledged: bool,
+}
+
+/// Share of `scheduling` in the active time (0.0 - 100.0), None without any active time. High
+/// values mean the executor spends more time deciding what to run than running it.
+pub fn scheduling_overhead_percent(scheduling: Duration, polling: Duration) -> Option<f32> {
+    let active = scheduling + polling;
+    if active.is_zero() {
+        None
+    } else {
+        Some(scheduling.as_secs_f32() / active.as_secs_f32() * 100.0)
+    }
+}
+
+/// Longest single poll of an executor, blocking every other task of the executor meanwhile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorstPoll {
+    pub task_id: u32,
+    /// Time the task kept the executor polling (without preemptions)
+    pub duration: Duration,
 }
 
 impl ExecutorStats {
     pub fn from_executor(executor: &ExecutorTraceInfo) -> Self {
-        let tasks = TaskStats::from_task_list(&executor.get_tasks());
+        let mut tasks = TaskStats::from_task_list(executor.get_tasks());
+        for task in tasks.iter_mut() {
+            task.lost_wakeup = executor.get_lost_wakeup_task_ids().contains(&task.task_id);
+        }
 
         // Sum up CPU utilization from tasks
-        let cpu_utilization_percent = executor.calculate_cpu_utilization();
+        let cpu_utilization_percent = executor.calculate_cpu_utilization(cpu_window());
+        let (scheduling_time, polling_time) = executor.calc_work_split(None);
 
         Self {
+            executor_id: executor.get_executor_id(),
+            core_id: executor.get_core_id(),
             name: executor.get_executor_display_name(),
             tasks,
             cpu_utilization_percent,
+            integrity: *executor.get_integrity(),
+            lost_wakeup_count: executor.get_lost_wakeup_count(),
+            budget_utilization_percent: executor.calc_budget_utilization(),
+            scheduling_time: scheduling_time.as_duration(),
+            polling_time: polling_time.as_duration(),
+            preempted_by: executor
+                .calc_preempted_durations()
+                .into_iter()
+                .map(|(id, time)| (id, time.as_duration()))
+                .collect(),
+            worst_poll: executor
+                .get_worst_poll()
+                .map(|(task_id, duration)| WorstPoll {
+                    task_id,
+                    duration: duration.as_duration(),
+                }),
+            pegged: executor.is_pegged(),
+            ping_pong: executor.get_ping_pong(),
+            idle_state: None,
+            task_count_history: Vec::new(),
+            task_count_growing: false,
+            warnings_acknowledged: false,
         }
     }
 
+    /// Share of the active time spent scheduling instead of polling
+    pub fn scheduling_overhead_percent(&self) -> Option<f32> {
+        scheduling_overhead_percent(self.scheduling_time, self.polling_time)
+    }
+
     pub fn from_executor_list(executors: &Vec<&ExecutorTraceInfo>) -> Vec<Self> {
         executors.iter().map(|e| Self::from_executor(e)).collect()
     }
-}
\ No newline at end of file
+
+    /// Merge executors (e.g. the same executor running on each core) into one logical executor.
+    /// CPU utilization is the mean over the merged executors, tasks are combined and get
+    /// their core appended when the executors span multiple cores.
+    pub fn merge(executors: &[&ExecutorStats]) -> Option<Self> {
+        let first = executors.first()?;
+        let spans_cores = executors.iter().any(|e| e.core_id != first.core_id);
+
+        let tasks = executors
+            .iter()
+            .flat_map(|e| {
+                e.tasks.iter().map(|t| {
+                    let mut task = t.clone();
+                    if spans_cores {
+                        task.name = format!("{} (Core {})", task.name, e.core_id);
+                    }
+                    task
+                })
+            })
+            .collect();
+
+        let cpu_utilization_percent = executors
+            .iter()
+            .map(|e| e.cpu_utilization_percent)
+            .sum::<f32>()
+            / executors.len() as f32;
+
+        let integrity = executors
+            .iter()
+            .fold(IntegrityCounters::default(), |acc, e| acc + e.integrity);
+
+        let mut preempted_by: BTreeMap<u32, Duration> = BTreeMap::new();
+        for (id, duration) in executors.iter().flat_map(|e| e.preempted_by.iter()) {
+            *preempted_by.entry(*id).or_default() += *duration;
+        }
+
+        Some(Self {
+            executor_id: first.executor_id,
+            core_id: first.core_id,
+            name: first.name.clone(),
+            tasks,
+            cpu_utilization_percent,
+            integrity,
+            lost_wakeup_count: executors.iter().map(|e| e.lost_wakeup_count).sum(),
+            // the most loaded instance decides about an overload
+            budget_utilization_percent: executors
+                .iter()
+                .filter_map(|e| e.budget_utilization_percent)
+                .reduce(f32::max),
+            scheduling_time: executors.iter().map(|e| e.scheduling_time).sum(),
+            polling_time: executors.iter().map(|e| e.polling_time).sum(),
+            preempted_by,
+            worst_poll: executors
+                .iter()
+                .filter_map(|e| e.worst_poll)
+                .max_by_key(|poll| poll.duration),
+            pegged: executors.iter().any(|e| e.pegged),
+            ping_pong: executors.iter().find_map(|e| e.ping_pong),
+            // the instances run on different cores, one of them sleeping says little
+            idle_state: None,
+            task_count_history: Vec::new(),
+            task_count_growing: false,
+            warnings_acknowledged: false,
+        })
+    }
+
+    /// Group executors by name across cores and merge each group. Executors sharing a name on
+    /// the same core are genuinely different executors, so they are kept apart and
+    /// distinguished by their id.
+    pub fn merge_by_name<'a>(executors: impl Iterator<Item = &'a ExecutorStats>) -> Vec<Self> {
+        let mut groups: Vec<Vec<&ExecutorStats>> = Vec::new();
+
+        for executor in executors {
+            let group = groups.iter_mut().find(|g| {
+                g[0].name == executor.name && g.iter().all(|e| e.core_id != executor.core_id)
+            });
+
+            match group {
+                Some(group) => group.push(executor),
+                None => groups.push(vec![executor]),
+            }
+        }
+
+        // Name collisions left over --> fall back to the executor id
+        let mut merged: Vec<Self> = groups.iter().filter_map(|g| Self::merge(g)).collect();
+        let colliding: Vec<bool> = merged
+            .iter()
+            .map(|e| merged.iter().filter(|other| other.name == e.name).count() > 1)
+            .collect();
+        for (executor, colliding) in merged.iter_mut().zip(colliding) {
+            if colliding {
+                executor.name = format!("{} (0x{:X})", executor.name, executor.executor_id);
+            }
+        }
+
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        test_support::{executor_stats, task_stats},
+        tracing::{
+            executor::ExecutorTraceInfo,
+            stats::{core_stats::CoreStats, task_stats::TaskStats},
+            test_harness::{at_ms, feed_executor, item},
+            trace_data::TraceItemType,
+        },
+    };
+
+    use super::ExecutorStats;
+
+    fn task(name: &str, cpu_utilization_percent: f32) -> TaskStats {
+        TaskStats {
+            cpu_utilization_percent,
+            ..task_stats(0, name)
+        }
+    }
+
+    fn executor(executor_id: u32, core_id: u32, name: &str, cpu: f32) -> ExecutorStats {
+        ExecutorStats {
+            cpu_utilization_percent: cpu,
+            ..executor_stats(executor_id, core_id, name, vec![task("worker", cpu)])
+        }
+    }
+
+    #[test]
+    fn test_merge_same_named_executors() {
+        let executors = [
+            executor(1, 0, "EXECUTOR", 40.0),
+            executor(2, 1, "EXECUTOR", 60.0),
+            executor(3, 1, "IRQ_EXECUTOR", 10.0),
+        ];
+
+        let merged = ExecutorStats::merge_by_name(executors.iter());
+        assert_eq!(merged.len(), 2);
+
+        assert_eq!(merged[0].name, "EXECUTOR");
+        assert_eq!(merged[0].cpu_utilization_percent, 50.0);
+        let task_names: Vec<&str> = merged[0].tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(task_names, vec!["worker (Core 0)", "worker (Core 1)"]);
+
+        // Single executor stays unchanged
+        assert_eq!(merged[1].name, "IRQ_EXECUTOR");
+        assert_eq!(merged[1].cpu_utilization_percent, 10.0);
+        assert_eq!(merged[1].tasks[0].name, "worker");
+    }
+
+    #[test]
+    fn test_merge_name_collision_on_same_core() {
+        let executors = [
+            executor(0xA, 0, "EXECUTOR", 40.0),
+            executor(0xB, 0, "EXECUTOR", 60.0),
+        ];
+
+        // Same core --> different executors, distinguished by id
+        let merged = ExecutorStats::merge_by_name(executors.iter());
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].name, "EXECUTOR (0xA)");
+        assert_eq!(merged[0].cpu_utilization_percent, 40.0);
+        assert_eq!(merged[1].name, "EXECUTOR (0xB)");
+        assert_eq!(merged[1].cpu_utilization_percent, 60.0);
+    }
+
+    #[test]
+    fn test_scheduling_overhead() {
+        let exec = |ms, begin| {
+            let (executor_id, task_id) = (1, 7);
+            let data = if begin {
+                TraceItemType::TaskExecBegin {
+                    executor_id,
+                    task_id,
+                }
+            } else {
+                TraceItemType::TaskExecEnd {
+                    executor_id,
+                    task_id,
+                }
+            };
+            item(ms, 0, data)
+        };
+
+        // scheduling 2ms + 1ms + 2ms = 5ms, polling 8ms + 9ms = 17ms
+        let mut traced = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        feed_executor(
+            &mut traced,
+            &[
+                item(0, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                exec(2, true),
+                exec(10, false),
+                exec(11, true),
+                exec(20, false),
+                item(22, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+                item(30, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+            ],
+        );
+
+        let stats = ExecutorStats::from_executor(&traced);
+        assert_eq!(stats.scheduling_time, Duration::from_millis(5));
+        assert_eq!(stats.polling_time, Duration::from_millis(17));
+        let overhead = stats.scheduling_overhead_percent().unwrap();
+        assert!((overhead - 5.0 / 22.0 * 100.0).abs() < 0.01);
+
+        // the core weighs its executors by their active time
+        let mut core = CoreStats::empty(0);
+        core.executors = vec![stats, {
+            let mut busy = executor(2, 0, "IRQ_EXECUTOR", 0.0);
+            busy.scheduling_time = Duration::from_millis(5);
+            busy.polling_time = Duration::from_millis(73);
+            busy
+        }];
+        let overhead = core.scheduling_overhead_percent().unwrap();
+        assert!((overhead - 10.0).abs() < 0.01);
+
+        // nothing active yet
+        let idle = ExecutorTraceInfo::new(3, 0, at_ms(0));
+        assert_eq!(
+            ExecutorStats::from_executor(&idle).scheduling_overhead_percent(),
+            None
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! Task activity over time for the heatmap: the most recent slice (the view window) of the
+//! retained history of every task is sampled into time buckets, the intensity of a bucket is
+//! the share of it the task was running. State spans shorter than the minimum visible span are
+//! merged into their neighbours before, for display only.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::tracing::{executor::ExecutorTraceInfo, task::TaskTraceState, time::EmbassyTime};
+
+/// State spans shorter than this (in microseconds) are merged into their neighbours in the
+/// time based views (0 --> every span is shown)
+pub static MIN_VISIBLE_SPAN_US: AtomicU64 = AtomicU64::new(0);
+
+/// Span of a task state (start, end, state)
+pub type StateSpan = (EmbassyTime, EmbassyTime, TaskTraceState);
+
+/// One task of the heatmap
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatmapRow {
+    pub task_id: u32,
+    pub name: String,
+    pub core_id: u32,
+    /// Share of each bucket the task was running (0.0 - 1.0), oldest bucket first
+    pub intensities: Vec<f32>,
+}
+
+/// Activity of all tasks in equally long time buckets splitting the window ending at `end`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    pub end: EmbassyTime,
+    pub window: EmbassyTime,
+    pub bucket_length: EmbassyTime,
+    /// Tasks ordered by core and executor
+    pub rows: Vec<HeatmapRow>,
+}
+
+/// Length of the buckets splitting the window (rounded down to whole microseconds)
+pub fn bucket_length(window: EmbassyTime, bucket_count: usize) -> EmbassyTime {
+    match bucket_count {
+        0 => EmbassyTime::ZERO,
+        count => EmbassyTime::from_micros((window.as_micros() / count as u128) as u64),
+    }
+}
+
+/// Share of each bucket covered by the spans (start, end). The `bucket_count` buckets split the
+/// `window` ending at `end` (oldest first), spans outside of it are ignored; overlapping spans
+/// are not merged.
+pub fn bucket_intensities(
+    spans: &[(EmbassyTime, EmbassyTime)],
+    end: EmbassyTime,
+    window: EmbassyTime,
+    bucket_count: usize,
+) -> Vec<f32> {
+    let mut covered = vec![0u128; bucket_count];
+    let length = bucket_length(window, bucket_count).as_micros();
+    if length == 0 {
+        return vec![0.0; bucket_count];
+    }
+
+    let start = end
+        .as_micros()
+        .saturating_sub(length * bucket_count as u128);
+    for (span_start, span_end) in spans {
+        // Relative to the first bucket, clipped to the buckets
+        let span_start = span_start.as_micros().saturating_sub(start);
+        let span_end = span_end
+            .as_micros()
+            .saturating_sub(start)
+            .min(length * bucket_count as u128);
+        if span_start >= span_end {
+            continue;
+        }
+
+        let first = (span_start / length) as usize;
+        let last = ((span_end - 1) / length) as usize;
+        for (bucket, covered) in covered.iter_mut().enumerate().take(last + 1).skip(first) {
+            let bucket_start = bucket as u128 * length;
+            let bucket_end = bucket_start + length;
+            *covered += span_end.min(bucket_end) - span_start.max(bucket_start);
+        }
+    }
+
+    covered
+        .into_iter()
+        .map(|covered| (covered as f32 / length as f32).min(1.0))
+        .collect()
+}
+
+/// Merge the spans (consecutive, oldest first) shorter than `min_span`: a run of short spans
+/// becomes one span of the state it spent the most time in. A run still shorter than
+/// `min_span` is merged into its previous neighbour (the first one into the next), then
+/// neighbours of the same state into one span. Rapid flapping between states shows up as the
+/// state before it instead of as noise, while a long burst of short polls stays visible.
+pub fn coalesce_spans(spans: &[StateSpan], min_span: EmbassyTime) -> Vec<StateSpan> {
+    let is_short = |(start, end, _): &StateSpan| end.saturating_sub(*start) < min_span;
+
+    let mut coalesced: Vec<StateSpan> = Vec::with_capacity(spans.len());
+    let mut rest = spans;
+    while let Some(&first) = rest.first() {
+        let run_length = rest.iter().take_while(|span| is_short(span)).count().max(1);
+        let (run, remaining) = rest.split_at(run_length);
+        rest = remaining;
+
+        let span = match run {
+            [span] => *span,
+            _ => (first.0, run[run.len() - 1].1, dominant_state(run)),
+        };
+        let (start, end, state) = span;
+        match coalesced.last_mut() {
+            Some(last) if is_short(&span) || last.2 == state => last.1 = end,
+            // Only the first span can still be short, it takes the state of its successor
+            Some(last) if is_short(last) => *last = (last.0, end, state),
+            _ => coalesced.push((start, end, state)),
+        }
+    }
+    coalesced
+}
+
+/// State the spans spent the most time in (the earliest one on a tie)
+fn dominant_state(spans: &[StateSpan]) -> TaskTraceState {
+    let mut durations: Vec<(TaskTraceState, EmbassyTime)> = Vec::new();
+    for &(start, end, state) in spans {
+        let duration = end.saturating_sub(start);
+        match durations.iter_mut().find(|(s, _)| *s == state) {
+            Some((_, total)) => *total += duration,
+            None => durations.push((state, duration)),
+        }
+    }
+    durations
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, total)| *total)
+        .map_or(spans[0].2, |(state, _)| state)
+}
+
+impl Heatmap {
+    /// Sample the running time of every task within the `window` ending at `end` (the current
+    /// firmware time) into `bucket_count` buckets
+    pub fn from_executors(
+        executors: &[ExecutorTraceInfo],
+        end: EmbassyTime,
+        window: EmbassyTime,
+        bucket_count: usize,
+    ) -> Self {
+        let min_span = EmbassyTime::from_micros(MIN_VISIBLE_SPAN_US.load(Ordering::Relaxed));
+        let mut executors: Vec<&ExecutorTraceInfo> = executors.iter().collect();
+        executors.sort_by_key(|e| (e.get_core_id(), e.get_executor_id()));
+
+        let rows = executors
+            .iter()
+            .flat_map(|e| e.iter_tasks())
+            .map(|task| {
+                let mut state_spans: Vec<StateSpan> = task
+                    .get_state_history()
+                    .iter()
+                    .map(|entry| {
+                        (
+                            entry.get_start_time().get_uc_timestamp(),
+                            entry.get_end_time().get_uc_timestamp(),
+                            entry.get_state(),
+                        )
+                    })
+                    .collect();
+                state_spans.push((
+                    task.get_state_start_time().get_uc_timestamp(),
+                    end,
+                    *task.get_state(),
+                ));
+
+                let spans: Vec<(EmbassyTime, EmbassyTime)> = coalesce_spans(&state_spans, min_span)
+                    .into_iter()
+                    .filter(|(_, _, state)| *state == TaskTraceState::Running)
+                    .map(|(start, end, _)| (start, end))
+                    .collect();
+
+                HeatmapRow {
+                    task_id: task.get_task_id(),
+                    name: task.get_task_display_name(),
+                    core_id: task.get_core_id(),
+                    intensities: bucket_intensities(&spans, end, window, bucket_count),
+                }
+            })
+            .collect();
+
+        Self {
+            end,
+            window,
+            bucket_length: bucket_length(window, bucket_count),
+            rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{
+        executor::ExecutorTraceInfo,
+        test_harness::{at_ms, feed_executor, item},
+        time::EmbassyTime,
+        trace_data::TraceItemType,
+    };
+
+    use super::*;
+
+    fn ms(ms: u64) -> EmbassyTime {
+        EmbassyTime::from_millis(ms)
+    }
+
+    #[test]
+    fn test_bucket_intensities() {
+        // 4 buckets of 10ms from 100ms
+        let spans = [
+            (ms(95), ms(105)), // clipped at the start
+            (ms(112), ms(118)),
+            (ms(125), ms(140)), // across two buckets
+            (ms(150), ms(160)), // after the last bucket
+        ];
+        assert_eq!(
+            bucket_intensities(&spans, ms(140), ms(40), 4),
+            vec![0.5, 0.6, 0.5, 1.0]
+        );
+
+        assert_eq!(bucket_intensities(&[], ms(30), ms(30), 3), vec![0.0; 3]);
+        assert_eq!(
+            bucket_intensities(&spans, ms(140), EmbassyTime::ZERO, 2),
+            vec![0.0; 2]
+        );
+        assert_eq!(
+            bucket_intensities(&spans, ms(140), ms(40), 0),
+            Vec::<f32>::new()
+        );
+    }
+
+    #[test]
+    fn test_coalesce_flapping_spans() {
+        use TaskTraceState::{Running, Waiting};
+        let us = EmbassyTime::from_micros;
+        let spans = [
+            (us(0), us(200), Running), // short first span, takes the next state
+            (us(200), us(5_000), Waiting),
+            (us(5_000), us(5_300), Running), // flapping
+            (us(5_300), us(5_400), Waiting),
+            (us(5_400), us(5_900), Running),
+            (us(5_900), us(9_000), Waiting), // same state as the span it merges with
+            (us(9_000), us(12_000), Running),
+            (us(12_000), us(12_100), Waiting),
+        ];
+
+        assert_eq!(
+            coalesce_spans(&spans, us(1_000)),
+            vec![
+                (us(0), us(9_000), Waiting),
+                (us(9_000), us(12_100), Running)
+            ]
+        );
+        // without a minimum every span is shown
+        assert_eq!(coalesce_spans(&spans, EmbassyTime::ZERO), spans.to_vec());
+        assert!(coalesce_spans(&[], us(1_000)).is_empty());
+    }
+
+    #[test]
+    fn test_coalesce_short_bursts() {
+        use TaskTraceState::{Running, Waiting};
+        let us = EmbassyTime::from_micros;
+        // 8ms of polls every 400us, each 300us long
+        let mut spans = vec![(us(0), us(5_000), Waiting)];
+        for i in 0..20 {
+            let start = 5_000 + 400 * i;
+            spans.push((us(start), us(start + 300), Running));
+            spans.push((us(start + 300), us(start + 400), Waiting));
+        }
+        spans.push((us(13_000), us(20_000), Waiting));
+
+        // a busy task does not vanish into its idle neighbour
+        assert_eq!(
+            coalesce_spans(&spans, us(1_000)),
+            vec![
+                (us(0), us(5_000), Waiting),
+                (us(5_000), us(13_000), Running),
+                (us(13_000), us(20_000), Waiting),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heatmap_from_executors() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        // task woken at `begin`, polled until `end`
+        let poll = |task_id, begin, end| {
+            [
+                item(
+                    begin,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id,
+                    },
+                ),
+                item(
+                    begin,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id,
+                    },
+                ),
+                item(
+                    end,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id,
+                    },
+                ),
+            ]
+        };
+        let mut items = vec![
+            item(
+                1,
+                0,
+                TraceItemType::TaskNew {
+                    executor_id: 1,
+                    task_id: 7,
+                },
+            ),
+            item(
+                1,
+                0,
+                TraceItemType::TaskNew {
+                    executor_id: 1,
+                    task_id: 8,
+                },
+            ),
+        ];
+        items.extend(poll(7, 2, 7));
+        items.extend(poll(8, 25, 30));
+        // still running at the end of the window
+        items.extend(poll(7, 35, 50).into_iter().take(2));
+        feed_executor(&mut executor, &items);
+        let heatmap = Heatmap::from_executors(&[executor], ms(40), ms(40), 4);
+        let rows: Vec<(u32, Vec<f32>)> = heatmap
+            .rows
+            .into_iter()
+            .map(|row| (row.task_id, row.intensities))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(7, vec![0.5, 0.0, 0.0, 0.5]), (8, vec![0.0, 0.0, 0.5, 0.0])]
+        );
+    }
+
+    #[test]
+    fn test_view_window_over_long_history() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        let poll = |task_id, begin, end| {
+            [
+                TraceItemType::TaskReadyBegin {
+                    executor_id: 1,
+                    task_id,
+                },
+                TraceItemType::TaskExecBegin {
+                    executor_id: 1,
+                    task_id,
+                },
+                TraceItemType::TaskExecEnd {
+                    executor_id: 1,
+                    task_id,
+                },
+            ]
+            .into_iter()
+            .zip([begin, begin, end])
+            .map(|(data, ms)| item(ms, 0, data))
+            .collect::<Vec<_>>()
+        };
+
+        // 30s of history: task 7 runs 100ms every second, task 8 half of the time from 10s
+        // to 15s only
+        let mut items = (7..=8)
+            .map(|task_id| {
+                item(
+                    0,
+                    0,
+                    TraceItemType::TaskNew {
+                        executor_id: 1,
+                        task_id,
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        for second in 0..30 {
+            let start = second * 1000;
+            items.extend(poll(7, start, start + 100));
+            if (10..15).contains(&second) {
+                items.extend(poll(8, start + 200, start + 700));
+            }
+        }
+        feed_executor(&mut executor, &items);
+
+        // the last 5s at 1s resolution: only the recent slice without task 8
+        let executors = [executor];
+        let heatmap = Heatmap::from_executors(&executors, ms(30_000), ms(5_000), 5);
+        assert_eq!(heatmap.bucket_length, ms(1_000));
+        assert_eq!(heatmap.rows[0].intensities, vec![0.1; 5]);
+        assert_eq!(heatmap.rows[1].intensities, vec![0.0; 5]);
+
+        // the whole history at 10s resolution
+        let heatmap = Heatmap::from_executors(&executors, ms(30_000), ms(30_000), 3);
+        assert_eq!(heatmap.rows[0].intensities, vec![0.1; 3]);
+        assert_eq!(heatmap.rows[1].intensities, vec![0.0, 0.25, 0.0]);
+    }
+}
@@ -1,4 +1,26 @@
-use crate::tracing::stats::core_stats::CoreStats;
+use crate::tracing::{
+    channels::ChannelEdge,
+    clock::ClockAlignment,
+    event_counts::EventCount,
+    integrity::{self, IntegrityCounters},
+    parse_stats::ParseStats,
+    stats::{
+        category_stats::{CategoryStats, TASK_CATEGORIES},
+        core_imbalance::CoreImbalance,
+        core_stats::CoreStats,
+        executor_stats::ExecutorStats,
+        preemption_matrix::PreemptionMatrix,
+    },
+    timeline::TimelineEvent,
+    timers::TimerStats,
+};
+
+/// Task or executor with integrity issues
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityOffender {
+    pub name: String,
+    pub counters: IntegrityCounters,
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct InstanceStats {
@@ -6,11 +28,41 @@ pub struct InstanceStats {
 
     pub tasks_count: usize,
     pub executor_count: usize,
+
+    /// Parsed trace lines and parse errors by variant
+    pub parse_stats: ParseStats,
+
+    /// Received trace events per kind (only the kinds received at least once)
+    pub event_counts: Vec<EventCount>,
+
+    /// Identifier of the firmware build (None if the beacon did not publish it)
+    pub firmware_id: Option<String>,
+
+    /// Warning about a trace schema version the visor does not support (None if compatible
+    /// or not published by the beacon)
+    pub schema_warning: Option<String>,
+
+    /// Lifecycle events (oldest first)
+    pub timeline: Vec<TimelineEvent>,
+
+    /// Alignment of the uC clock with the PC clock (None before the first trace item)
+    pub clock: Option<ClockAlignment>,
+
+    /// Producer --> consumer connections over channels (empty without channel events)
+    pub channel_edges: Vec<ChannelEdge>,
+
+    /// Pending timers and timers that woke their task (empty without timer events)
+    pub timers: Vec<TimerStats>,
 }
 
 impl InstanceStats {
-    pub fn from_executors(executors: &Vec<crate::tracing::executor::ExecutorTraceInfo>) -> Self {
-        let core_stats = CoreStats::from_executor_list(executors);
+    /// Create statistics of all executors. Declared cores (from the beacon's topology event)
+    /// without any executor are included with 0% utilization.
+    pub fn from_executors(
+        executors: &Vec<crate::tracing::executor::ExecutorTraceInfo>,
+        declared_cores: Option<&[u32]>,
+    ) -> Self {
+        let core_stats = CoreStats::from_executor_list(executors, declared_cores);
         let tasks_count = executors.iter().map(|e| e.get_tasks().len()).sum();
         let executor_count = executors.len();
 
@@ -18,7 +70,122 @@ impl InstanceStats {
             core_stats,
             tasks_count,
             executor_count,
+            parse_stats: ParseStats::default(),
+            event_counts: Vec::new(),
+            firmware_id: None,
+            schema_warning: None,
+            timeline: Vec::new(),
+            clock: None,
+            channel_edges: Vec::new(),
+            timers: Vec::new(),
+        }
+    }
+
+    /// Firmware build identifier for display and exports
+    pub fn firmware_id_or_unknown(&self) -> &str {
+        self.firmware_id.as_deref().unwrap_or("unknown build")
+    }
+
+    /// Integrity issues of all tasks and executors
+    pub fn integrity(&self) -> IntegrityCounters {
+        self.integrity_offenders()
+            .iter()
+            .fold(IntegrityCounters::default(), |acc, o| acc + o.counters)
+    }
+
+    /// Trace health in percent (0.0 - 100.0) from parse errors and integrity issues
+    pub fn trace_health_percent(&self) -> f32 {
+        integrity::trace_health_percent(&self.parse_stats, self.integrity().total())
+    }
+
+    /// Tasks and executors with integrity issues (most issues first)
+    pub fn integrity_offenders(&self) -> Vec<IntegrityOffender> {
+        let mut offenders = Vec::new();
+        for executor in self.core_stats.iter().flat_map(|c| c.executors.iter()) {
+            offenders.push(IntegrityOffender {
+                name: executor.name.clone(),
+                counters: executor.integrity,
+            });
+            for task in executor.tasks.iter() {
+                offenders.push(IntegrityOffender {
+                    name: task.name.clone(),
+                    counters: task.integrity,
+                });
+            }
         }
+
+        offenders.retain(|o| o.counters.total() > 0);
+        offenders.sort_by_key(|o| std::cmp::Reverse(o.counters.total()));
+        offenders
+    }
+
+    /// Number of lost wakeups detected on all executors
+    pub fn lost_wakeup_count(&self) -> usize {
+        self.core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .map(|e| e.lost_wakeup_count)
+            .sum()
+    }
+
+    /// Names of the tasks currently ready while their executor stays idle
+    pub fn lost_wakeup_tasks(&self) -> Vec<String> {
+        self.core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .flat_map(|e| e.tasks.iter())
+            .filter(|t| t.lost_wakeup)
+            .map(|t| t.name.clone())
+            .collect()
+    }
+
+    /// Task names of the suspected ping-pong pairs with their number of switches
+    pub fn ping_pong_pairs(&self) -> Vec<(String, String, usize)> {
+        self.core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .filter_map(|e| {
+                let run = e.ping_pong?;
+                let task_name = |task_id: u32| {
+                    e.tasks
+                        .iter()
+                        .find(|t| t.task_id == task_id)
+                        .map_or(format!("Task 0x{:X}", task_id), |t| t.name.clone())
+                };
+                Some((
+                    task_name(run.task_ids.0),
+                    task_name(run.task_ids.1),
+                    run.switches,
+                ))
+            })
+            .collect()
+    }
+
+    /// Gap between the busiest and the idlest core (of the device with the largest gap), None
+    /// without a device of several cores
+    pub fn core_imbalance(&self) -> Option<CoreImbalance> {
+        CoreImbalance::from_cores(&self.core_stats)
+    }
+
+    /// Preempted time of every executor by every other executor
+    pub fn preemption_matrix(&self) -> PreemptionMatrix {
+        PreemptionMatrix::from_executors(self.core_stats.iter().flat_map(|c| c.executors.iter()))
+    }
+
+    /// Statistics of the configured task categories (`TASK_CATEGORIES`)
+    pub fn category_stats(&self) -> Vec<CategoryStats> {
+        CategoryStats::from_tasks(
+            self.core_stats
+                .iter()
+                .flat_map(|c| c.executors.iter())
+                .flat_map(|e| e.tasks.iter()),
+            &TASK_CATEGORIES.read().unwrap(),
+        )
+    }
+
+    /// Logical view of all executors, merging same-named executors across cores
+    pub fn executors_merged_by_name(&self) -> Vec<ExecutorStats> {
+        ExecutorStats::merge_by_name(self.core_stats.iter().flat_map(|c| c.executors.iter()))
     }
 }
 
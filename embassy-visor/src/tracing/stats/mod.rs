@@ -1,4 +1,9 @@
+pub mod category_stats;
+pub mod core_imbalance;
+pub mod core_stats;
+pub mod cpu_basis;
 pub mod executor_stats;
+pub mod heatmap;
 pub mod instance_stats;
+pub mod preemption_matrix;
 pub mod task_stats;
-pub mod core_stats;
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::tracing::stats::executor_stats::ExecutorStats;
+
+/// Executor appearing in the preemption matrix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixExecutor {
+    pub executor_id: u32,
+    pub name: String,
+}
+
+/// Total time each executor (victim) was preempted by each other executor (aggressor) over
+/// the history window. Only executors involved in a preemption are listed.
+#[derive(Debug, Clone, Default)]
+pub struct PreemptionMatrix {
+    /// Preempted executors (rows)
+    pub victims: Vec<MatrixExecutor>,
+    /// Preempting executors (columns)
+    pub aggressors: Vec<MatrixExecutor>,
+    /// Preempted time by (victim id, aggressor id)
+    cells: BTreeMap<(u32, u32), Duration>,
+}
+
+impl PreemptionMatrix {
+    pub fn from_executors<'a>(executors: impl Iterator<Item = &'a ExecutorStats> + Clone) -> Self {
+        let mut matrix = Self::default();
+
+        for victim in executors.clone() {
+            for (&aggressor_id, &duration) in victim.preempted_by.iter() {
+                if duration.is_zero() {
+                    continue;
+                }
+                *matrix
+                    .cells
+                    .entry((victim.executor_id, aggressor_id))
+                    .or_default() += duration;
+            }
+        }
+
+        let name_of = |executor_id: u32| {
+            executors
+                .clone()
+                .find(|e| e.executor_id == executor_id)
+                .map_or(format!("Executor 0x{:X}", executor_id), |e| e.name.clone())
+        };
+        for &(victim_id, aggressor_id) in matrix.cells.keys() {
+            if !matrix.victims.iter().any(|e| e.executor_id == victim_id) {
+                matrix.victims.push(MatrixExecutor {
+                    executor_id: victim_id,
+                    name: name_of(victim_id),
+                });
+            }
+            if !matrix
+                .aggressors
+                .iter()
+                .any(|e| e.executor_id == aggressor_id)
+            {
+                matrix.aggressors.push(MatrixExecutor {
+                    executor_id: aggressor_id,
+                    name: name_of(aggressor_id),
+                });
+            }
+        }
+        matrix.aggressors.sort_by_key(|e| e.executor_id);
+
+        matrix
+    }
+
+    /// Time the victim was preempted by the aggressor (zero if never)
+    pub fn get(&self, victim_id: u32, aggressor_id: u32) -> Duration {
+        self.cells
+            .get(&(victim_id, aggressor_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tracing::{
+        executor::ExecutorTraceInfo,
+        stats::executor_stats::ExecutorStats,
+        test_harness::{at_ms, item},
+        trace_data::TraceItemType,
+    };
+
+    use super::PreemptionMatrix;
+
+    #[test]
+    fn test_preemption_matrix_cells() {
+        let mut executors = [
+            ExecutorTraceInfo::new(1, 0, at_ms(0)),
+            ExecutorTraceInfo::new(2, 0, at_ms(0)),
+        ];
+        let poll_start =
+            |ms, executor_id| item(ms, 0, TraceItemType::ExecutorPollStart { executor_id });
+        let idle = |ms, executor_id| item(ms, 0, TraceItemType::ExecutorIdle { executor_id });
+
+        // Executor 2 preempts executor 1 twice: 10ms + 5ms
+        let items = [
+            poll_start(10, 1),
+            poll_start(20, 2),
+            idle(30, 2),
+            poll_start(40, 2),
+            idle(45, 2),
+            idle(50, 1),
+        ];
+        for trace_item in items.iter() {
+            for executor in executors.iter_mut() {
+                executor.update(trace_item);
+            }
+        }
+
+        let stats: Vec<ExecutorStats> =
+            executors.iter().map(ExecutorStats::from_executor).collect();
+        let matrix = PreemptionMatrix::from_executors(stats.iter());
+
+        assert_eq!(matrix.get(1, 2), Duration::from_millis(15));
+        assert_eq!(matrix.get(2, 1), Duration::ZERO);
+        assert_eq!(matrix.get(1, 1), Duration::ZERO);
+        assert_eq!(
+            matrix
+                .victims
+                .iter()
+                .map(|e| e.executor_id)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(
+            matrix
+                .aggressors
+                .iter()
+                .map(|e| e.executor_id)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+}
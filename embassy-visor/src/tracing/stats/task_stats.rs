@@ -1,11 +1,59 @@
-use std::time::Duration;
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
 
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-use crate::tracing::task::{TaskTraceInfo, TaskTraceState};
+use crate::tracing::{
+    integrity::IntegrityCounters,
+    task::{PeriodBreakdown, TaskTraceInfo, TaskTraceState},
+    time::EmbassyTime,
+};
+
+/// Number of latest states kept with their absolute start time (for the task details)
+const RECENT_STATES: usize = 4;
+
+/// Task CPU utilization relative to the time the task was active (running, waiting or
+/// preempted) instead of its whole history (toggled with 'a')
+pub static ACTIVE_ONLY_CPU: AtomicBool = AtomicBool::new(false);
+
+/// Trailing window in ms the CPU utilization is averaged over, independent of the history
+/// retention (0: the whole retained history)
+pub static CPU_WINDOW_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Trailing window of the CPU utilization, None for the whole retained history
+pub fn cpu_window() -> Option<Duration> {
+    match CPU_WINDOW_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Share of the running time in percent (0.0 - 100.0), relative to the whole history or (with
+/// `active_only`) to the time the task was active. The latter keeps the intensity of a task
+/// that is mostly idle visible. With a `window` only its trailing part of the history counts.
+pub fn cpu_utilization_percent(
+    task: &TaskTraceInfo,
+    active_only: bool,
+    window: Option<Duration>,
+) -> f32 {
+    let total_time = if active_only {
+        task.calc_total_history_active_duration(window)
+    } else {
+        task.calc_total_history_duration(window)
+    };
+    let running_time = task.calc_total_history_state_duration(TaskTraceState::Running, window);
+    if total_time.as_millis() > 0 {
+        (running_time.as_secs_f32() / total_time.as_secs_f32()) * 100.0
+    } else {
+        0.0
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct TaskStats {
+    pub task_id: u32,
     pub name: String,
     /// CPU utilization in percent (0.0 - 100.0)
     pub cpu_utilization_percent: f32,
@@ -17,18 +65,39 @@ pub struct TaskStats {
     pub avg_waiting_time: Duration,
     /// Total count the task was in State 'Ready'
     pub count_waiting_time: usize,
+    /// State of the task when the statistics were taken
+    pub state: TaskTraceState,
+    /// Pinned to the top of its executor by the user (only set by the TUI)
+    pub pinned: bool,
+    /// State changed since the previous refresh (only set by the TUI)
+    pub state_changed: bool,
+    /// Integrity issues detected in the events of the task
+    pub integrity: IntegrityCounters,
+    /// Split of the last period into the task states (None if not periodic)
+    pub period_breakdown: Option<PeriodBreakdown>,
+    /// Ready while its executor stays idle, a likely lost wakeup (set by the executor)
+    pub lost_wakeup: bool,
+    /// Highest reported stack usage in bytes (None if the beacon never reported it)
+    pub stack_high_water_bytes: Option<u32>,
+    /// Time since the task appeared
+    pub age: Duration,
+    /// Instances spawned in the task's slot before this one (task pools reuse the id)
+    pub generation: u32,
+    /// Time since the task last ran (zero while running, None if it never ran)
+    pub last_active_ago: Option<Duration>,
+    /// Start (absolute UC time) and state of the latest states, the current one first
+    pub recent_states: Vec<(EmbassyTime, TaskTraceState)>,
+    /// Polls ended per second without being preempted, over the CPU window (None if nothing
+    /// was observed yet)
+    pub yields_per_s: Option<f32>,
+    /// Warnings acknowledged by the user, shown without emphasis (only set by the TUI)
+    pub warning_acknowledged: bool,
 }
 
 impl TaskStats {
     pub fn from_task(task: &TaskTraceInfo) -> Self {
-        // Calculate CPU utilization
-        let total_time = task.calc_total_history_duration();
-        let running_time = task.calc_total_history_state_duration(TaskTraceState::Running);
-        let cpu_utilization_percent = if total_time.as_millis() > 0 {
-            (running_time.as_secs_f32() / total_time.as_secs_f32()) * 100.0
-        } else {
-            0.0
-        };
+        let cpu_utilization_percent =
+            cpu_utilization_percent(task, ACTIVE_ONLY_CPU.load(Ordering::Relaxed), cpu_window());
 
         // Calculate waiting time statistics
         let (min_waiting_time, avg_waiting_time, max_waiting_time, count_waiting_time) = task
@@ -36,16 +105,97 @@ impl TaskStats {
             .unwrap_or_default();
 
         Self {
+            task_id: task.get_task_id(),
             name: task.get_task_display_name(),
             cpu_utilization_percent,
             min_waiting_time,
             max_waiting_time,
             avg_waiting_time,
             count_waiting_time,
+            state: *task.get_state(),
+            pinned: false,
+            state_changed: false,
+            integrity: *task.get_integrity(),
+            period_breakdown: task.calc_period_breakdown(),
+            lost_wakeup: false,
+            stack_high_water_bytes: task.get_stack_high_water(),
+            age: task.calc_age(),
+            generation: task.get_generation(),
+            last_active_ago: task.calc_last_active_ago(),
+            recent_states: task.recent_state_starts(RECENT_STATES),
+            yields_per_s: task.calc_yields_per_s(cpu_window()),
+            warning_acknowledged: false,
         }
     }
 
     pub fn from_task_list(tasks: &Vec<TaskTraceInfo>) -> Vec<Self> {
-        tasks.par_iter().map(|t| Self::from_task(t)).collect()
+        tasks.par_iter().map(Self::from_task).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tracing::{
+        task::TaskTraceInfo,
+        test_harness::{at_ms, feed_task, item},
+        time::EmbassyTime,
+        trace_data::TraceItemType,
+    };
+
+    use super::cpu_utilization_percent;
+
+    #[test]
+    fn test_cpu_utilization_modes() {
+        let (executor_id, task_id) = (1, 7);
+        let mut task = TaskTraceInfo::new(task_id, executor_id, 0, at_ms(0));
+
+        // two activations 200ms apart: waiting 2ms, running 4ms each, idle in between
+        let mut items = Vec::new();
+        for start in [100, 300] {
+            items.extend([
+                item(
+                    start,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id,
+                        task_id,
+                    },
+                ),
+                item(
+                    start + 2,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id,
+                        task_id,
+                    },
+                ),
+                item(
+                    start + 6,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id,
+                        task_id,
+                    },
+                ),
+            ]);
+        }
+        feed_task(&mut task, &items);
+        let task = task.rewound_to(EmbassyTime::from_millis(400)).unwrap();
+
+        // running 8ms of the 400ms history
+        assert!((cpu_utilization_percent(&task, false, None) - 2.0).abs() < 0.01);
+        // running 8ms of the 12ms active (waiting + running)
+        assert!((cpu_utilization_percent(&task, true, None) - 8.0 / 12.0 * 100.0).abs() < 0.01);
+
+        // last 100ms: only the second activation, running 4ms
+        let window = Some(Duration::from_millis(100));
+        assert!((cpu_utilization_percent(&task, false, window) - 4.0).abs() < 0.01);
+        assert!((cpu_utilization_percent(&task, true, window) - 4.0 / 6.0 * 100.0).abs() < 0.01);
+
+        // last 97ms start within the running span at 302-306ms, which is clipped to 3ms
+        let window = Some(Duration::from_millis(97));
+        assert!((cpu_utilization_percent(&task, false, window) - 3.0 / 97.0 * 100.0).abs() < 0.01);
     }
 }
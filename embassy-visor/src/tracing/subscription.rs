@@ -0,0 +1,45 @@
+//! Per-task subscriptions: embedders register interest in a task id and receive its state
+//! changes, instead of polling the statistics of all tasks.
+
+use crossbeam::channel::{self, Receiver, Sender};
+
+use crate::tracing::{task::TaskTraceState, time::EmbassyTime};
+
+/// State change of a subscribed task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskEvent {
+    pub task_id: u32,
+    pub executor_id: u32,
+    /// Previous state, None when the task was first seen
+    pub from: Option<TaskTraceState>,
+    pub to: TaskTraceState,
+    /// uC time the new state started
+    pub time: EmbassyTime,
+}
+
+/// Subscribers by task id. A subscription ends when its receiver is dropped (noticed on the
+/// next state change of its task).
+#[derive(Debug, Default)]
+pub struct TaskSubscribers {
+    senders: Vec<(u32, Sender<TaskEvent>)>,
+}
+
+impl TaskSubscribers {
+    /// Register interest in the task and return the stream of its state changes
+    pub fn subscribe(&mut self, task_id: u32) -> Receiver<TaskEvent> {
+        let (sender, receiver) = channel::unbounded();
+        self.senders.push((task_id, sender));
+        receiver
+    }
+
+    /// Ids of the subscribed tasks (may contain duplicates)
+    pub fn task_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.senders.iter().map(|(task_id, _)| *task_id)
+    }
+
+    /// Send the event to the subscribers of its task, dropping closed subscriptions
+    pub fn publish(&mut self, event: TaskEvent) {
+        self.senders
+            .retain(|(task_id, sender)| *task_id != event.task_id || sender.send(event).is_ok());
+    }
+}
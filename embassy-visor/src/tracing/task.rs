@@ -2,17 +2,17 @@
 //!
 //! ```text
 //! ┌ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─
-//!        │(1)                                            │
+//!      │(1)                                            │
 //! │      │
 //!   ╔════▼════╗ (2) ┌─────────┐ (3) ┌─────────┐          │
 //! │ ║ SPAWNED ║────▶│ WAITING │────▶│ RUNNING │
 //!   ╚═════════╝     └─────────┘     └─────────┘          │
 //! │                 ▲         ▲     │    │    │
-//!                   │           (4)      │    │(6)       │
+//!                 │           (4)      │    │(6)       │
 //! │                 │(7)      └ ─ ─ ┘    │    │
-//!                   │                    │    │          │
+//!                 │                    │    │          │
 //! │             ┌──────┐             (5) │    │  ┌─────┐
-//!               │ IDLE │◀────────────────┘    └─▶│ END │ │
+//!             │ IDLE │◀────────────────┘    └─▶│ END │ │
 //! │             └──────┘                         └─────┘
 //!   ┌──────────────────────┐                             │
 //! └ ┤ Task Trace Lifecycle │─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─
@@ -23,9 +23,9 @@
 //! 2. A task is enqueued for the first time, `_embassy_trace_task_ready_begin` is called
 //! 3. A task is polled, `_embassy_trace_task_exec_begin` is called
 //! 4. WHILE a task is polled, the task is re-awoken, and `_embassy_trace_task_ready_begin` is
-//!      called. The task does not IMMEDIATELY move state, until polling is complete and the
-//!      RUNNING state is existed. `_embassy_trace_task_exec_end` is called when polling is
-//!      complete, marking the transition to WAITING
+//!    called. The task does not IMMEDIATELY move state, until polling is complete and the
+//!    RUNNING state is existed. `_embassy_trace_task_exec_end` is called when polling is
+//!    complete, marking the transition to WAITING
 //! 5. Polling is complete, `_embassy_trace_task_exec_end` is called
 //! 6. The task has completed, and `_embassy_trace_task_end` is called
 //! 7. A task is awoken, `_embassy_trace_task_ready_begin` is called
@@ -33,26 +33,41 @@
 //! (taken from embassy-executor/src/raw/trace.rs)
 //!
 //! We added the Preempted state to indicate that a task was preempted by another executor task with higher priority (Interrupt context).
-
-use std::{collections::VecDeque, ops::Div, sync::atomic::Ordering, time::Duration};
+//!
+//! A task pool reuses its slots, so a `_embassy_trace_task_new` with the id of an ended task
+//! spawns a new instance of it: the task starts over in SPAWNED as its next generation.
+
+use std::{
+    collections::VecDeque,
+    ops::Div,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::{
-    FIRMWARE_ADDR_MAP, elf_file,
+    elf_file,
     tracing::{
-        instance::HISTORY_MAX_TIME_S,
-        time::{ComputerTime, EmbassyTime, TimePair},
+        executor::{is_phantom_preemption, preemption_threshold},
+        integrity::{IntegrityCounters, IntegrityIssue},
+        retention::Retention,
+        time::{EmbassyTime, TimePair},
         trace_data::{TraceItem, TraceItemType},
     },
 };
 
+/// A task spawned again in its slot after it ended continues the history and statistics of the
+/// previous instance instead of starting with fresh ones. Read when a task is created.
+pub static CONTINUE_RESPAWNED_TASKS: AtomicBool = AtomicBool::new(false);
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum TaskTraceState {
     Spawned,
     Waiting,
     Running,
-    /// Task was preempted by another executor (task with different executor ID on the same core)
+    /// Task was preempted by another executor (task with different executor ID on the same core),
+    /// `by_executor_id` is the one currently running if several are nested
     Preempted {
         by_executor_id: u32,
     },
@@ -60,6 +75,27 @@ pub enum TaskTraceState {
     Ended,
 }
 
+/// History length from which the statistics are calculated in parallel
+const PARALLEL_HISTORY_MIN_LEN: usize = 4096;
+
+/// Minimum number of periods needed to detect a stable period
+const MIN_PERIODS: usize = 3;
+/// Number of most recent periods checked for stability
+const MAX_PERIODS: usize = 8;
+/// Maximum deviation of a period from the mean period (share of the mean)
+const MAX_PERIOD_JITTER: f64 = 0.1;
+
+/// Split of the last full period of a periodic task into its states (percent of the period)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeriodBreakdown {
+    pub period: Duration,
+    pub waiting_percent: f32,
+    pub running_percent: f32,
+    pub preempted_percent: f32,
+    /// Remaining time of the period (idle)
+    pub idle_percent: f32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub struct TaskHistoryEntry {
     state: TaskTraceState,
@@ -68,6 +104,21 @@ pub struct TaskHistoryEntry {
 }
 
 impl TaskHistoryEntry {
+    /// Get the state the task was in
+    pub fn get_state(&self) -> TaskTraceState {
+        self.state
+    }
+
+    /// Get the timestamp when the state started
+    pub fn get_start_time(&self) -> TimePair {
+        self.start_time
+    }
+
+    /// Get the timestamp when the state ended
+    pub fn get_end_time(&self) -> TimePair {
+        self.end_time
+    }
+
     /// Get the duration of this history entry (via UC timestamps)
     pub fn get_uc_duration(&self) -> EmbassyTime {
         let start_uc_time = self.start_time.get_uc_timestamp();
@@ -75,6 +126,14 @@ impl TaskHistoryEntry {
 
         end_uc_time.saturating_sub(start_uc_time)
     }
+
+    /// Duration of the part of this history entry after `window_start` (UC time)
+    pub fn get_uc_duration_since(&self, window_start: EmbassyTime) -> EmbassyTime {
+        let start_uc_time = self.start_time.get_uc_timestamp().max(window_start);
+        let end_uc_time = self.end_time.get_uc_timestamp();
+
+        end_uc_time.saturating_sub(start_uc_time)
+    }
 }
 
 pub struct TaskTraceInfo {
@@ -89,29 +148,44 @@ pub struct TaskTraceInfo {
     state: TaskTraceState,
     /// Timestamp when the current state started
     state_start_time: TimePair,
+    /// Executors active while preempted, innermost last
+    preempted_by: Vec<u32>,
+    /// All preempting executors went idle at this time, the task resumes (from then on) once
+    /// its own executor is active again
+    resume_pending_since: Option<TimePair>,
+    /// Re-awoken while running (lifecycle 4): the poll ends in Waiting instead of Idle
+    ready_while_running: bool,
+    /// Set on a copy rewound into the past: the current state ends here instead of now
+    snapshot_time: Option<EmbassyTime>,
 
     /// history of state changes
     state_history: VecDeque<TaskHistoryEntry>,
+
+    /// Integrity issues of the events of this task
+    integrity: IntegrityCounters,
+
+    /// Highest stack usage reported by the beacon in bytes (None if never reported)
+    stack_high_water: Option<u32>,
+
+    /// Start or end of the latest run (None if the task never ran)
+    last_active: Option<TimePair>,
+
+    /// Instances spawned in the slot before this one (0 for the first spawn)
+    generation: u32,
+
+    /// A respawned instance continues this one (`CONTINUE_RESPAWNED_TASKS` at the creation)
+    continue_respawned: bool,
+
+    /// Threshold used instead of the adjustable `PREEMPTION_THRESHOLD_US` (set by tests)
+    fixed_preemption_threshold: Option<EmbassyTime>,
 }
 
 impl TaskTraceInfo {
     pub fn new(task_id: u32, executor_id: u32, core_id: u32, created_at: TimePair) -> Self {
         // try to find task name from global firmware address map
-        let task_name = match FIRMWARE_ADDR_MAP.get() {
-            Some(addr_map) => {
-                // task id represents the address of the task's future vtable
-                match addr_map.get(&(task_id as u64)) {
-                    Some(name) => Some(elf_file::try_extract_short_name(name).to_string()),
-                    None => None,
-                }
-            }
-            None => {
-                eprintln!(
-                    "Warning: Firmware address map not initialized when creating TaskTraceInfo"
-                );
-                None
-            }
-        };
+        // task id represents the address of the task's future vtable
+        let task_name = elf_file::firmware_symbol(task_id as u64)
+            .map(|name| elf_file::try_extract_short_name(&name).to_string());
 
         Self {
             task_id,
@@ -121,10 +195,32 @@ impl TaskTraceInfo {
             created_at,
             state: TaskTraceState::Spawned,
             state_start_time: created_at,
+            preempted_by: Vec::new(),
+            resume_pending_since: None,
+            ready_while_running: false,
+            snapshot_time: None,
             state_history: VecDeque::new(),
+            integrity: IntegrityCounters::default(),
+            stack_high_water: None,
+            last_active: None,
+            generation: 0,
+            continue_respawned: CONTINUE_RESPAWNED_TASKS.load(Ordering::Relaxed),
+            fixed_preemption_threshold: None,
         }
     }
 
+    /// Use this threshold instead of `PREEMPTION_THRESHOLD_US`
+    #[cfg(test)]
+    pub fn set_preemption_threshold(&mut self, threshold: EmbassyTime) {
+        self.fixed_preemption_threshold = Some(threshold);
+    }
+
+    /// Continue (or restart) the statistics when the task is spawned again in its slot
+    #[cfg(test)]
+    pub fn set_continue_respawned(&mut self, continue_respawned: bool) {
+        self.continue_respawned = continue_respawned;
+    }
+
     /// Get the unique task ID
     pub fn get_task_id(&self) -> u32 {
         self.task_id
@@ -152,6 +248,14 @@ impl TaskTraceInfo {
     pub fn get_core_id(&self) -> u32 {
         self.core_id
     }
+
+    /// Let the task belong to another executor (it keeps its state and history)
+    pub fn move_to_executor(&mut self, executor_id: u32, core_id: u32) {
+        self.executor_id = executor_id;
+        self.core_id = core_id;
+        self.preempted_by.clear();
+        self.resume_pending_since = None;
+    }
     /// Get the current state of the task
     pub fn get_state(&self) -> &TaskTraceState {
         &self.state
@@ -167,8 +271,65 @@ impl TaskTraceInfo {
         self.state_start_time
     }
 
+    /// Get the history of state changes (oldest first)
+    pub fn get_state_history(&self) -> &VecDeque<TaskHistoryEntry> {
+        &self.state_history
+    }
+
+    /// Get the number of instances spawned in the task's slot before the current one
+    pub fn get_generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Get the integrity issues detected in the events of this task
+    pub fn get_integrity(&self) -> &IntegrityCounters {
+        &self.integrity
+    }
+
+    /// Integrity issue of an own event in the given state (None if the event is valid)
+    fn check_transition(state: TaskTraceState, data: &TraceItemType) -> Option<IntegrityIssue> {
+        match (state, data) {
+            (_, TraceItemType::TaskNew { .. } | TraceItemType::TaskEnd { .. }) => None,
+            (TaskTraceState::Ended | TaskTraceState::Preempted { .. }, _) => {
+                Some(IntegrityIssue::ImpossibleTransition)
+            }
+            // missing ready begin
+            (
+                TaskTraceState::Spawned | TaskTraceState::Idle,
+                TraceItemType::TaskExecBegin { .. },
+            ) => Some(IntegrityIssue::Gap),
+            // missing exec begin
+            (
+                TaskTraceState::Spawned | TaskTraceState::Waiting,
+                TraceItemType::TaskExecEnd { .. },
+            ) => Some(IntegrityIssue::Gap),
+            // duplicated exec begin/end
+            (TaskTraceState::Running, TraceItemType::TaskExecBegin { .. })
+            | (TaskTraceState::Idle, TraceItemType::TaskExecEnd { .. }) => {
+                Some(IntegrityIssue::ImpossibleTransition)
+            }
+            _ => None,
+        }
+    }
+
     /// Set a new state for the task, sending statistics as needed
     fn set_new_state(&mut self, new_state: TaskTraceState, timestamp: TimePair) {
+        // Phantom preemption (reordered events) --> continue the interrupted state instead
+        if let TaskTraceState::Preempted { .. } = self.state
+            && is_phantom_preemption(
+                self.state_start_time,
+                timestamp,
+                preemption_threshold(self.fixed_preemption_threshold),
+            )
+            && let Some(interrupted) = self.state_history.back()
+            && interrupted.state == new_state
+        {
+            self.state_start_time = interrupted.start_time;
+            self.state = new_state;
+            self.state_history.pop_back();
+            return;
+        }
+
         if self.state != new_state {
             // println!(
             //     "Task {} changing state from {:?} to {:?} at UC time {:?}",
@@ -186,6 +347,10 @@ impl TaskTraceInfo {
             };
             self.state_history.push_back(hist_entry);
 
+            if self.state == TaskTraceState::Running || new_state == TaskTraceState::Running {
+                self.last_active = Some(timestamp);
+            }
+
             // update state
             self.state = new_state;
             self.state_start_time = timestamp;
@@ -197,9 +362,43 @@ impl TaskTraceInfo {
         // Check if we get preempted
         if self.state == TaskTraceState::Running {
             // check if another executor on the same core_id is beginning to poll (that would preempt us because only one executor can run on a core at a time)
-            if let TraceItemType::ExecutorPollStart { executor_id, .. } = trace_item.data {
-                if trace_item.core_id == self.core_id && executor_id != self.executor_id {
-                    // preempted by another executor
+            if let TraceItemType::ExecutorPollStart { executor_id, .. } = trace_item.data
+                && trace_item.core_id == self.core_id
+                && executor_id != self.executor_id
+            {
+                // preempted by another executor
+                self.preempted_by = vec![executor_id];
+                self.set_new_state(
+                    TaskTraceState::Preempted {
+                        by_executor_id: executor_id,
+                    },
+                    trace_item.time_pair,
+                );
+                return;
+            }
+        }
+
+        // Resume once our own executor is active again after all preempting executors went
+        // idle. An aggressor polling again right after its idle keeps the task preempted.
+        if let TaskTraceState::Preempted { .. } = self.state
+            && trace_item.data.get_executor_id() == Some(self.executor_id)
+            && let Some(resumed_at) = self.resume_pending_since.take()
+        {
+            self.set_new_state(TaskTraceState::Running, resumed_at);
+        }
+
+        // Check if we are resuming from preemption (all preempting executors are idle again)
+        if let TaskTraceState::Preempted { .. } = self.state {
+            match trace_item.data {
+                TraceItemType::ExecutorPollStart { executor_id, .. }
+                    if trace_item.core_id == self.core_id
+                        && executor_id != self.executor_id
+                        && !self.preempted_by.contains(&executor_id) =>
+                {
+                    // nested preemption of the preempting executor (or the same one polling
+                    // again before our executor resumed)
+                    self.resume_pending_since = None;
+                    self.preempted_by.push(executor_id);
                     self.set_new_state(
                         TaskTraceState::Preempted {
                             by_executor_id: executor_id,
@@ -208,23 +407,27 @@ impl TaskTraceInfo {
                     );
                     return;
                 }
-            }
-        }
-
-        // Check if we are resuming from preemption (other executor is now idle)
-        if let TaskTraceState::Preempted { by_executor_id } = self.state {
-            // check if the other executor goes to idle
-            if let TraceItemType::ExecutorIdle { executor_id, .. } = trace_item.data {
-                if executor_id == by_executor_id {
-                    // resume our task to running
-                    self.set_new_state(TaskTraceState::Running, trace_item.time_pair);
+                TraceItemType::ExecutorIdle { executor_id, .. }
+                    if self.preempted_by.contains(&executor_id) =>
+                {
+                    self.preempted_by.retain(|id| *id != executor_id);
+                    match self.preempted_by.last() {
+                        Some(&running_id) => self.set_new_state(
+                            TaskTraceState::Preempted {
+                                by_executor_id: running_id,
+                            },
+                            trace_item.time_pair,
+                        ),
+                        None => self.resume_pending_since = Some(trace_item.time_pair),
+                    }
                     return;
                 }
+                _ => {}
             }
         }
 
         // Check that this trace item is for this executor
-        if trace_item.data.get_executor_id() != self.executor_id {
+        if trace_item.data.get_executor_id() != Some(self.executor_id) {
             return;
         }
 
@@ -234,8 +437,31 @@ impl TaskTraceInfo {
             _ => return,
         }
 
+        // Stack usage is a measurement, not a state change
+        if let TraceItemType::TaskStackUsage {
+            high_water_bytes, ..
+        } = trace_item.data
+        {
+            self.stack_high_water = self.stack_high_water.max(Some(high_water_bytes));
+            return;
+        }
+
+        self.integrity
+            .check_order(trace_item.time_pair.get_uc_timestamp());
+        self.integrity
+            .record(Self::check_transition(self.state, &trace_item.data));
+
+        // A task can end from any state (the beacon may not publish the exec/ready events)
+        if let TraceItemType::TaskEnd { .. } = trace_item.data {
+            self.ready_while_running = false;
+            self.set_new_state(TaskTraceState::Ended, trace_item.time_pair);
+        }
+
         // State machine transitions
         match self.state {
+            TaskTraceState::Ended if matches!(trace_item.data, TraceItemType::TaskNew { .. }) => {
+                self.respawn(trace_item.time_pair);
+            }
             TaskTraceState::Spawned => {
                 if let TraceItemType::TaskReadyBegin { .. } = trace_item.data {
                     self.set_new_state(TaskTraceState::Waiting, trace_item.time_pair);
@@ -249,15 +475,17 @@ impl TaskTraceInfo {
             TaskTraceState::Running => {
                 match trace_item.data {
                     TraceItemType::TaskExecEnd { .. } => {
-                        self.set_new_state(TaskTraceState::Idle, trace_item.time_pair);
+                        // Already queued again if re-awoken during the poll
+                        let next_state = if std::mem::take(&mut self.ready_while_running) {
+                            TaskTraceState::Waiting
+                        } else {
+                            TaskTraceState::Idle
+                        };
+                        self.set_new_state(next_state, trace_item.time_pair);
                     }
                     TraceItemType::TaskReadyBegin { .. } => {
-                        // Normally this would transition after TaskExecEnd, but we can handle it here in this way too (maybe?)
-                        // This means the task was re-awoken while running
-                        self.set_new_state(TaskTraceState::Waiting, trace_item.time_pair);
-                    }
-                    TraceItemType::TaskEnd { .. } => {
-                        self.set_new_state(TaskTraceState::Ended, trace_item.time_pair);
+                        // Re-awoken while running: keeps running until the poll ends
+                        self.ready_while_running = true;
                     }
                     _ => {}
                 }
@@ -268,28 +496,137 @@ impl TaskTraceInfo {
                 }
             }
             TaskTraceState::Ended => {
-                // No transitions out of ended for tasks
+                // Only spawning a new instance in the slot leaves ended
             }
             TaskTraceState::Preempted { .. } => {} // nothing here because of other task-id
         }
 
-        // Drain old history entries beyond max time (based on end-time)
-        let max_time_s = ComputerTime::from_s(HISTORY_MAX_TIME_S.load(Ordering::Relaxed));
-        let current_pc_time = trace_item.time_pair.get_pc_timestamp();
-        while let Some(front) = self.state_history.front() {
-            let entry_end_pc_time = front.end_time.get_pc_timestamp();
+        // Drain old history entries by the retention policy (based on end-time)
+        Retention::current().prune(
+            &mut self.state_history,
+            trace_item.time_pair.get_pc_timestamp(),
+            |entry| entry.end_time.get_pc_timestamp(),
+        );
+    }
 
-            // Check if the difference is greater than max time
-            if current_pc_time.saturating_sub(entry_end_pc_time) > max_time_s {
-                self.state_history.pop_front();
-            } else {
-                break;
-            }
+    /// A new instance was spawned in the slot of the ended task (task pool)
+    fn respawn(&mut self, spawned_at: TimePair) {
+        let generation = self.generation + 1;
+        if self.continue_respawned {
+            self.set_new_state(TaskTraceState::Spawned, spawned_at);
+        } else {
+            // The slot keeps its settings, not the current ones of a fresh instance
+            *self = Self {
+                continue_respawned: false,
+                fixed_preemption_threshold: self.fixed_preemption_threshold,
+                ..Self::new(self.task_id, self.executor_id, self.core_id, spawned_at)
+            };
+        }
+        self.generation = generation;
+    }
+
+    /// Highest stack usage reported for the task in bytes (None if the target does not report it)
+    pub fn get_stack_high_water(&self) -> Option<u32> {
+        self.stack_high_water
+    }
+
+    /// Start of the retained history if older states were already dropped (UC time)
+    pub fn get_retained_since(&self) -> Option<EmbassyTime> {
+        let start = self.state_history.front()?.start_time.get_uc_timestamp();
+        (start > self.created_at.get_uc_timestamp()).then_some(start)
+    }
+
+    /// Copy of the task as it was at `time` (UC time): later history is cut off and the state
+    /// at `time` becomes the current one. None if the task did not exist yet.
+    pub fn rewound_to(&self, time: EmbassyTime) -> Option<Self> {
+        if self.created_at.get_uc_timestamp() > time {
+            return None;
         }
+
+        let state_history: VecDeque<TaskHistoryEntry> = self
+            .state_history
+            .iter()
+            .take_while(|e| e.end_time.get_uc_timestamp() <= time)
+            .copied()
+            .collect();
+        let (state, state_start_time) = match self.state_history.get(state_history.len()) {
+            Some(entry) => (entry.state, entry.start_time),
+            None => (self.state, self.state_start_time),
+        };
+
+        let last_active = if state == TaskTraceState::Running {
+            Some(state_start_time)
+        } else {
+            state_history
+                .iter()
+                .rev()
+                .find(|e| e.state == TaskTraceState::Running)
+                .map(|e| e.end_time)
+                .or(self.last_active.filter(|t| t.get_uc_timestamp() <= time))
+        };
+
+        Some(Self {
+            task_id: self.task_id,
+            task_name: self.task_name.clone(),
+            executor_id: self.executor_id,
+            core_id: self.core_id,
+            created_at: self.created_at,
+            state,
+            state_start_time,
+            preempted_by: Vec::new(),
+            resume_pending_since: None,
+            // not recorded in the history, the rewound poll ends in Idle
+            ready_while_running: false,
+            snapshot_time: Some(time),
+            state_history,
+            integrity: self.integrity,
+            stack_high_water: self.stack_high_water,
+            last_active,
+            generation: self.generation,
+            continue_respawned: self.continue_respawned,
+            fixed_preemption_threshold: self.fixed_preemption_threshold,
+        })
+    }
+
+    /// Time since the task appeared
+    pub fn calc_age(&self) -> Duration {
+        self.extrapolate_current_state_duration()
+            .saturating_sub(self.created_at.get_uc_timestamp())
+            .as_duration()
+    }
+
+    /// Start (UC time) and state of the latest `count` states, the current one first
+    pub fn recent_state_starts(&self, count: usize) -> Vec<(EmbassyTime, TaskTraceState)> {
+        std::iter::once((self.state_start_time.get_uc_timestamp(), self.state))
+            .chain(
+                self.state_history
+                    .iter()
+                    .rev()
+                    .map(|entry| (entry.get_start_time().get_uc_timestamp(), entry.get_state())),
+            )
+            .take(count)
+            .collect()
+    }
+
+    /// Time since the task last ran (zero while running, None if it never ran)
+    pub fn calc_last_active_ago(&self) -> Option<Duration> {
+        if self.state == TaskTraceState::Running {
+            return Some(Duration::ZERO);
+        }
+        let last_active = self.last_active?.get_uc_timestamp();
+        Some(
+            self.extrapolate_current_state_duration()
+                .saturating_sub(last_active)
+                .as_duration(),
+        )
     }
 
     /// Extrapolate the duration spent in the current state till now (UC time)
     fn extrapolate_current_state_duration(&self) -> EmbassyTime {
+        if let Some(time) = self.snapshot_time {
+            return time;
+        }
+
         // get pc time diff between current time and time of state start
         let pc_time_diff = self.state_start_time.get_pc_timestamp().diff_to_now();
 
@@ -297,18 +634,31 @@ impl TaskTraceInfo {
         self.state_start_time.get_uc_timestamp() + pc_time_diff
     }
 
-    fn calc_current_state_duration(&self) -> EmbassyTime {
+    /// Duration of the current state till now, from `window_start` on if it started earlier
+    fn calc_current_state_duration(&self, window_start: EmbassyTime) -> EmbassyTime {
         let estimated_uc_time_now = self.extrapolate_current_state_duration();
-        estimated_uc_time_now.saturating_sub(self.state_start_time.get_uc_timestamp())
+        estimated_uc_time_now
+            .saturating_sub(self.state_start_time.get_uc_timestamp().max(window_start))
     }
 
-    /// Calculate total duration spent in all states from first history entry till now
-    pub fn calc_total_history_duration(&self) -> EmbassyTime {
+    /// Start of the trailing `window` ending now (UC time), ZERO for the whole history
+    fn window_start(&self, window: Option<Duration>) -> EmbassyTime {
+        window.map_or(EmbassyTime::ZERO, |window| {
+            let window = EmbassyTime::ZERO + window;
+            self.extrapolate_current_state_duration()
+                .saturating_sub(window)
+        })
+    }
+
+    /// Calculate total duration spent in all states from first history entry till now (within
+    /// the trailing `window` if given)
+    pub fn calc_total_history_duration(&self, window: Option<Duration>) -> EmbassyTime {
         // get start time from first history entry
         let start_time_uc = match self.state_history.front() {
             Some(entry) => entry.start_time.get_uc_timestamp(),
             None => EmbassyTime::ZERO,
-        };
+        }
+        .max(self.window_start(window));
 
         // Add current state duration
         let estimated_uc_time_now = self.extrapolate_current_state_duration();
@@ -317,78 +667,216 @@ impl TaskTraceInfo {
     }
 
     /// Go through history and calculate total duration spent in the given state
-    /// for the task. Also uses current state if matching.
-    pub fn calc_total_history_state_duration(&self, state: TaskTraceState) -> EmbassyTime {
+    /// for the task. Also uses current state if matching. With a `window` only the time within
+    /// the trailing window counts (entries straddling its start are clipped).
+    pub fn calc_total_history_state_duration(
+        &self,
+        state: TaskTraceState,
+        window: Option<Duration>,
+    ) -> EmbassyTime {
+        let window_start = self.window_start(window);
+
         // Retrieve total duration in the given state history
-        let mut total_duration = self
-            .state_history
-            .par_iter()
-            .filter(|e| e.state == state) // Filter by state
-            .map(|e| e.get_uc_duration()) // Map to durations
-            .reduce(|| EmbassyTime::ZERO, |a, b| a + b); // Sum durations
+        let mut total_duration =
+            self.history_state_duration(state, window_start, self.use_parallel());
 
         // TODO: Check if start < MAX_TIME_S and sub from the starting element for accuracy?
 
         // Add current state if matching (duration till now)
         if self.state == state {
-            total_duration += self.calc_current_state_duration();
+            total_duration += self.calc_current_state_duration(window_start);
         }
 
         total_duration
     }
 
-    /// Calculate min, mean, max and count of waiting time durations from history. Also includes
-    /// current waiting time if applicable.
-    pub fn calc_min_mean_max_count_waiting_time(
+    /// Total duration spent active (running, waiting to be polled or preempted) from the
+    /// history and the current state, i.e. without the idle time between activations
+    pub fn calc_total_history_active_duration(&self, window: Option<Duration>) -> EmbassyTime {
+        let is_active = |state: TaskTraceState| {
+            matches!(
+                state,
+                TaskTraceState::Running
+                    | TaskTraceState::Waiting
+                    | TaskTraceState::Preempted { .. }
+            )
+        };
+        let window_start = self.window_start(window);
+        let mut total_duration =
+            self.history_duration_where(is_active, window_start, self.use_parallel());
+
+        if is_active(self.state) {
+            total_duration += self.calc_current_state_duration(window_start);
+        }
+
+        total_duration
+    }
+
+    /// Times per second the task yielded back to its executor (a poll ended with the task idle
+    /// or waiting again, not preempted or ended) over the history, within the trailing `window`
+    /// if given. A cooperative task
+    /// yields often with short polls, a greedy one rarely with long polls. None without any
+    /// time observed.
+    pub fn calc_yields_per_s(&self, window: Option<Duration>) -> Option<f32> {
+        let window_start = self.window_start(window);
+        let next_states = self
+            .state_history
+            .iter()
+            .skip(1)
+            .map(|e| e.state)
+            .chain(std::iter::once(self.state));
+        let yields = self
+            .state_history
+            .iter()
+            .zip(next_states)
+            .filter(|(entry, next)| {
+                entry.state == TaskTraceState::Running
+                    && matches!(next, TaskTraceState::Idle | TaskTraceState::Waiting)
+                    && entry.end_time.get_uc_timestamp() >= window_start
+            })
+            .count();
+
+        let total_time = self.calc_total_history_duration(window);
+        (total_time > EmbassyTime::ZERO).then(|| yields as f32 / total_time.as_secs_f32())
+    }
+
+    /// Large histories are processed in parallel (for small ones the overhead of rayon
+    /// exceeds the benefit)
+    fn use_parallel(&self) -> bool {
+        self.state_history.len() >= PARALLEL_HISTORY_MIN_LEN
+    }
+
+    /// Total duration of the history entries in the given state (after `window_start`)
+    fn history_state_duration(
         &self,
-    ) -> Option<(Duration, Duration, Duration, usize)> {
-        #[derive(Clone, Debug)]
-        struct Stats {
-            min: Duration,
-            max: Duration,
-            sum: Duration,
-            count: usize,
+        state: TaskTraceState,
+        window_start: EmbassyTime,
+        parallel: bool,
+    ) -> EmbassyTime {
+        self.history_duration_where(|s| s == state, window_start, parallel)
+    }
+
+    /// Total duration of the history entries whose state matches (after `window_start`)
+    fn history_duration_where(
+        &self,
+        matches: impl Fn(TaskTraceState) -> bool + Sync,
+        window_start: EmbassyTime,
+        parallel: bool,
+    ) -> EmbassyTime {
+        if parallel {
+            self.state_history
+                .par_iter()
+                .filter(|e| matches(e.state)) // Filter by state
+                .map(|e| e.get_uc_duration_since(window_start)) // Map to clipped durations
+                .reduce(|| EmbassyTime::ZERO, |a, b| a + b) // Sum durations
+        } else {
+            self.state_history
+                .iter()
+                .filter(|e| matches(e.state))
+                .map(|e| e.get_uc_duration_since(window_start))
+                .fold(EmbassyTime::ZERO, |a, b| a + b)
         }
+    }
 
-        let mut stats = self
+    /// Times the task became ready (start of each waiting state, oldest first)
+    fn activation_times(&self) -> Vec<EmbassyTime> {
+        let mut activations: Vec<EmbassyTime> = self
             .state_history
-            .par_iter()
+            .iter()
             .filter(|e| e.state == TaskTraceState::Waiting)
-            .map(|e| e.get_uc_duration().as_duration())
-            .fold(
-                || Stats {
-                    min: Duration::MAX,
-                    max: Duration::ZERO,
-                    sum: Duration::ZERO,
-                    count: 0,
-                },
-                |mut acc, duration| {
-                    acc.min = acc.min.min(duration);
-                    acc.max = acc.max.max(duration);
-                    acc.sum += duration;
-                    acc.count += 1;
-                    acc
-                },
-            )
-            .reduce(
-                || Stats {
-                    min: Duration::MAX,
-                    max: Duration::ZERO,
-                    sum: Duration::ZERO,
-                    count: 0,
-                },
-                |a, b| Stats {
-                    min: a.min.min(b.min),
-                    max: a.max.max(b.max),
-                    sum: a.sum + b.sum,
-                    count: a.count + b.count,
-                },
-            );
+            .map(|e| e.start_time.get_uc_timestamp())
+            .collect();
+        if self.state == TaskTraceState::Waiting {
+            activations.push(self.state_start_time.get_uc_timestamp());
+        }
+        activations
+    }
+
+    /// Infer the period from the times the task became ready. `None` if the task is not
+    /// activated periodically (too few activations or too much jitter).
+    pub fn infer_period(&self) -> Option<Duration> {
+        let activations = self.activation_times();
+        let periods: Vec<Duration> = activations
+            .windows(2)
+            .map(|w| w[1].saturating_sub(w[0]).as_duration())
+            .collect();
+        if periods.len() < MIN_PERIODS {
+            return None;
+        }
+
+        let recent = &periods[periods.len().saturating_sub(MAX_PERIODS)..];
+        let mean = recent.iter().sum::<Duration>() / recent.len() as u32;
+        let max_jitter = mean.mul_f64(MAX_PERIOD_JITTER);
+        let stable = !mean.is_zero() && recent.iter().all(|p| p.abs_diff(mean) <= max_jitter);
+        stable.then_some(mean)
+    }
+
+    /// Split the last full period (between the two latest activations) into the time spent
+    /// waiting, running, preempted and idle. `None` when no stable period is detected.
+    pub fn calc_period_breakdown(&self) -> Option<PeriodBreakdown> {
+        let period = self.infer_period()?;
+        let activations = self.activation_times();
+        let (start, end) = match activations.as_slice() {
+            [.., start, end] => (*start, *end),
+            _ => return None,
+        };
+
+        // Sum up the history entries clipped to the period
+        let mut waiting = Duration::ZERO;
+        let mut running = Duration::ZERO;
+        let mut preempted = Duration::ZERO;
+        for entry in self.state_history.iter() {
+            let entry_start = entry.start_time.get_uc_timestamp().max(start);
+            let entry_end = entry.end_time.get_uc_timestamp().min(end);
+            let duration = entry_end.saturating_sub(entry_start).as_duration();
+            match entry.state {
+                TaskTraceState::Waiting => waiting += duration,
+                TaskTraceState::Running => running += duration,
+                // A phantom preemption is part of the running time it interrupted
+                TaskTraceState::Preempted { .. }
+                    if is_phantom_preemption(
+                        entry.start_time,
+                        entry.end_time,
+                        preemption_threshold(self.fixed_preemption_threshold),
+                    ) =>
+                {
+                    running += duration
+                }
+                TaskTraceState::Preempted { .. } => preempted += duration,
+                _ => {}
+            }
+        }
+
+        let total = end.saturating_sub(start).as_duration().as_secs_f32();
+        if total <= 0.0 {
+            return None;
+        }
+        let percent = |d: Duration| d.as_secs_f32() / total * 100.0;
+        let (waiting_percent, running_percent, preempted_percent) =
+            (percent(waiting), percent(running), percent(preempted));
+
+        Some(PeriodBreakdown {
+            period,
+            waiting_percent,
+            running_percent,
+            preempted_percent,
+            idle_percent: (100.0 - waiting_percent - running_percent - preempted_percent).max(0.0),
+        })
+    }
+
+    /// Calculate min, mean, max and count of waiting time durations from history. Also includes
+    /// current waiting time if applicable.
+    pub fn calc_min_mean_max_count_waiting_time(
+        &self,
+    ) -> Option<(Duration, Duration, Duration, usize)> {
+        let mut stats = self.history_waiting_stats(self.use_parallel());
 
         // Check if current state is waiting and it's duration is longer than max (min is not affected because it's minimum and we don't know how long it will last. Max is already the maximum observed so far)
         if self.state == TaskTraceState::Waiting {
             // get current duration in waiting state
-            let current_duration = self.calc_current_state_duration().as_duration();
+            let current_duration = self
+                .calc_current_state_duration(EmbassyTime::ZERO)
+                .as_duration();
 
             // include when current_duration above min (glitchy short durations should be ignored)
             if current_duration > stats.min {
@@ -413,15 +901,73 @@ impl TaskTraceInfo {
             ))
         }
     }
+
+    /// Min, max, sum and count of the waiting durations in the history
+    fn history_waiting_stats(&self, parallel: bool) -> WaitingStats {
+        let waiting_durations = |e: &TaskHistoryEntry| {
+            (e.state == TaskTraceState::Waiting).then(|| e.get_uc_duration().as_duration())
+        };
+
+        if parallel {
+            self.state_history
+                .par_iter()
+                .filter_map(waiting_durations)
+                .fold(|| WaitingStats::EMPTY, WaitingStats::add)
+                .reduce(|| WaitingStats::EMPTY, WaitingStats::merge)
+        } else {
+            self.state_history
+                .iter()
+                .filter_map(waiting_durations)
+                .fold(WaitingStats::EMPTY, WaitingStats::add)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct WaitingStats {
+    min: Duration,
+    max: Duration,
+    sum: Duration,
+    count: usize,
+}
+
+impl WaitingStats {
+    const EMPTY: Self = Self {
+        min: Duration::MAX,
+        max: Duration::ZERO,
+        sum: Duration::ZERO,
+        count: 0,
+    };
+
+    fn add(mut self, duration: Duration) -> Self {
+        self.min = self.min.min(duration);
+        self.max = self.max.max(duration);
+        self.sum += duration;
+        self.count += 1;
+        self
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            sum: self.sum + other.sum,
+            count: self.count + other.count,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::tracing::{
+        stats::task_stats::TaskStats,
+        test_harness::{at_ms, feed_task, item, item_us},
         time::{ComputerTime, EmbassyTime, TimePair},
         trace_data::{TraceItem, TraceItemType},
     };
 
+    use std::time::Duration;
+
     use super::{TaskTraceInfo, TaskTraceState};
 
     #[test]
@@ -440,6 +986,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(10), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskReadyBegin {
                 executor_id: 1,
                 task_id: 1,
@@ -449,6 +996,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(30), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskExecBegin {
                 executor_id: 1,
                 task_id: 1,
@@ -458,6 +1006,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(45), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskExecEnd {
                 executor_id: 1,
                 task_id: 1,
@@ -467,6 +1016,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(70), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskReadyBegin {
                 executor_id: 1,
                 task_id: 1,
@@ -476,6 +1026,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(100), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskExecBegin {
                 executor_id: 1,
                 task_id: 1,
@@ -485,6 +1036,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(120), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskExecEnd {
                 executor_id: 1,
                 task_id: 1,
@@ -493,21 +1045,23 @@ mod tests {
         std::thread::sleep(std::time::Duration::from_millis(20));
 
         // Calculate total history duration
-        let total_duration = task.calc_total_history_duration();
+        let total_duration = task.calc_total_history_duration(None);
         assert!(total_duration.as_millis() > 140 - 5);
         assert!(total_duration.as_millis() < 140 + 5);
 
         // Test SPAWNED duration
-        let spawned_duration = task.calc_total_history_state_duration(TaskTraceState::Spawned);
+        let spawned_duration =
+            task.calc_total_history_state_duration(TaskTraceState::Spawned, None);
         assert_eq!(spawned_duration.as_millis(), 10);
 
         // Test IDLE duration (tolerance because of estimation till now)
-        let idle_duration = task.calc_total_history_state_duration(TaskTraceState::Idle);
+        let idle_duration = task.calc_total_history_state_duration(TaskTraceState::Idle, None);
         assert!(idle_duration.as_millis() > 45 - 5);
         assert!(idle_duration.as_millis() < 45 + 5);
 
         // Test RUNNING duration
-        let running_duration = task.calc_total_history_state_duration(TaskTraceState::Running);
+        let running_duration =
+            task.calc_total_history_state_duration(TaskTraceState::Running, None);
         assert_eq!(running_duration.as_millis(), 35);
 
         // Calculate waiting time stats
@@ -534,6 +1088,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(10), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskReadyBegin {
                 executor_id: 1,
                 task_id: 1,
@@ -543,6 +1098,7 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(30), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskExecBegin {
                 executor_id: 1,
                 task_id: 1,
@@ -552,18 +1108,21 @@ mod tests {
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(45), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::ExecutorPollStart { executor_id: 2 },
         }); // preempted here
         std::thread::sleep(std::time::Duration::from_millis(25));
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(70), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::ExecutorIdle { executor_id: 2 },
         }); // resumed here
         std::thread::sleep(std::time::Duration::from_millis(30));
         task.update(&TraceItem {
             time_pair: TimePair::new(EmbassyTime::from_millis(100), ComputerTime::now()),
             core_id: 0,
+            device_id: 0,
             data: TraceItemType::TaskExecEnd {
                 executor_id: 1,
                 task_id: 1,
@@ -572,26 +1131,30 @@ mod tests {
         std::thread::sleep(std::time::Duration::from_millis(20));
 
         // Calculate total history duration
-        let total_duration = task.calc_total_history_duration();
+        let total_duration = task.calc_total_history_duration(None);
         assert!(total_duration.as_millis() > 120 - 5);
         assert!(total_duration.as_millis() < 120 + 5);
 
         // Test SPAWNED duration
-        let spawned_duration = task.calc_total_history_state_duration(TaskTraceState::Spawned);
+        let spawned_duration =
+            task.calc_total_history_state_duration(TaskTraceState::Spawned, None);
         assert_eq!(spawned_duration.as_millis(), 10);
 
         // Test IDLE duration (tolerance because of estimation till now)
-        let idle_duration = task.calc_total_history_state_duration(TaskTraceState::Idle);
+        let idle_duration = task.calc_total_history_state_duration(TaskTraceState::Idle, None);
         assert!(idle_duration.as_millis() > 20 - 5);
         assert!(idle_duration.as_millis() < 20 + 5);
 
         // Test RUNNING duration
-        let running_duration = task.calc_total_history_state_duration(TaskTraceState::Running);
+        let running_duration =
+            task.calc_total_history_state_duration(TaskTraceState::Running, None);
         assert_eq!(running_duration.as_millis(), 15 + 30); // 15 before preemption, 30 after resuming
 
         // Test PREEMPTED duration
-        let preempted_duration =
-            task.calc_total_history_state_duration(TaskTraceState::Preempted { by_executor_id: 2 });
+        let preempted_duration = task.calc_total_history_state_duration(
+            TaskTraceState::Preempted { by_executor_id: 2 },
+            None,
+        );
         assert_eq!(preempted_duration.as_millis(), 25);
 
         // Calculate waiting time stats
@@ -625,4 +1188,759 @@ mod tests {
         assert!(duration.as_millis() > 2000 + 95);
         assert!(duration.as_millis() < 2000 + 105);
     }
+
+    /// Collect (state, start ms, end ms) of all history entries
+    fn history_of(task: &TaskTraceInfo) -> Vec<(TaskTraceState, u128, u128)> {
+        task.get_state_history()
+            .iter()
+            .map(|e| {
+                (
+                    e.get_state(),
+                    e.get_start_time().get_uc_timestamp().as_millis(),
+                    e.get_end_time().get_uc_timestamp().as_millis(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_state_machine_lifecycle() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        assert_eq!(task.get_state(), &TaskTraceState::Spawned);
+
+        feed_task(
+            &mut task,
+            &[
+                item(
+                    10,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                item(
+                    30,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                item(
+                    45,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                item(
+                    70,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                item(
+                    100,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                item(
+                    120,
+                    0,
+                    TraceItemType::TaskEnd {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+            ],
+        );
+
+        assert_eq!(task.get_state(), &TaskTraceState::Ended);
+        assert_eq!(task.get_state_start_time(), at_ms(120));
+        assert_eq!(
+            history_of(&task),
+            vec![
+                (TaskTraceState::Spawned, 0, 10),
+                (TaskTraceState::Waiting, 10, 30),
+                (TaskTraceState::Running, 30, 45),
+                (TaskTraceState::Idle, 45, 70),
+                (TaskTraceState::Waiting, 70, 100),
+                (TaskTraceState::Running, 100, 120),
+            ]
+        );
+
+        // No transitions out of ended
+        feed_task(
+            &mut task,
+            &[item(
+                130,
+                0,
+                TraceItemType::TaskReadyBegin {
+                    executor_id: 1,
+                    task_id: 1,
+                },
+            )],
+        );
+        assert_eq!(task.get_state(), &TaskTraceState::Ended);
+    }
+
+    #[test]
+    fn test_state_machine_ignores_foreign_items() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+
+        feed_task(
+            &mut task,
+            &[
+                // other task on the same executor
+                item(
+                    10,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 2,
+                    },
+                ),
+                // same task id on another executor
+                item(
+                    20,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 2,
+                        task_id: 1,
+                    },
+                ),
+                // executor events without a task
+                item(30, 0, TraceItemType::ExecutorPollStart { executor_id: 1 }),
+                item(40, 0, TraceItemType::ExecutorIdle { executor_id: 1 }),
+            ],
+        );
+
+        assert_eq!(task.get_state(), &TaskTraceState::Spawned);
+        assert!(task.get_state_history().is_empty());
+    }
+
+    #[test]
+    fn test_state_machine_preemption() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+
+        feed_task(
+            &mut task,
+            &[
+                item(
+                    10,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                item(
+                    20,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                // executor on another core does not preempt us
+                item(25, 1, TraceItemType::ExecutorPollStart { executor_id: 3 }),
+            ],
+        );
+        assert_eq!(task.get_state(), &TaskTraceState::Running);
+
+        // executor 2 on the same core preempts us
+        feed_task(
+            &mut task,
+            &[item(
+                30,
+                0,
+                TraceItemType::ExecutorPollStart { executor_id: 2 },
+            )],
+        );
+        assert_eq!(
+            task.get_state(),
+            &TaskTraceState::Preempted { by_executor_id: 2 }
+        );
+
+        // another executor going idle does not resume us
+        feed_task(
+            &mut task,
+            &[item(35, 1, TraceItemType::ExecutorIdle { executor_id: 3 })],
+        );
+        assert_eq!(
+            task.get_state(),
+            &TaskTraceState::Preempted { by_executor_id: 2 }
+        );
+
+        // preempting executor goes idle --> resume
+        feed_task(
+            &mut task,
+            &[
+                item(50, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                item(
+                    60,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+            ],
+        );
+        assert_eq!(task.get_state(), &TaskTraceState::Idle);
+        assert_eq!(
+            history_of(&task),
+            vec![
+                (TaskTraceState::Spawned, 0, 10),
+                (TaskTraceState::Waiting, 10, 20),
+                (TaskTraceState::Running, 20, 30),
+                (TaskTraceState::Preempted { by_executor_id: 2 }, 30, 50),
+                (TaskTraceState::Running, 50, 60),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_state_machine_aggressor_polls_again() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        let aggressor_poll = |ms| item(ms, 0, TraceItemType::ExecutorPollStart { executor_id: 2 });
+        let aggressor_idle = |ms| item(ms, 0, TraceItemType::ExecutorIdle { executor_id: 2 });
+
+        feed_task(
+            &mut task,
+            &[
+                task_event(10, ready_begin),
+                task_event(20, exec_begin),
+                aggressor_poll(30),
+                // idle and polling again right away, our executor never got to run
+                aggressor_idle(40),
+                aggressor_poll(41),
+                aggressor_idle(50),
+                aggressor_poll(51),
+            ],
+        );
+        assert_eq!(
+            task.get_state(),
+            &TaskTraceState::Preempted { by_executor_id: 2 }
+        );
+        assert_eq!(
+            history_of(&task),
+            vec![
+                (TaskTraceState::Spawned, 0, 10),
+                (TaskTraceState::Waiting, 10, 20),
+                (TaskTraceState::Running, 20, 30),
+            ]
+        );
+
+        // resumed at the last idle, confirmed by the end of our task
+        feed_task(&mut task, &[aggressor_idle(60), task_event(70, exec_end)]);
+        assert_eq!(task.get_state(), &TaskTraceState::Idle);
+        assert_eq!(
+            history_of(&task),
+            vec![
+                (TaskTraceState::Spawned, 0, 10),
+                (TaskTraceState::Waiting, 10, 20),
+                (TaskTraceState::Running, 20, 30),
+                (TaskTraceState::Preempted { by_executor_id: 2 }, 30, 60),
+                (TaskTraceState::Running, 60, 70),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_state_machine_phantom_preemption() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        task.set_preemption_threshold(EmbassyTime::from_micros(2));
+
+        feed_task(
+            &mut task,
+            &[
+                item(
+                    10,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                item(
+                    20,
+                    0,
+                    TraceItemType::TaskExecBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                // preempt/resume pair 1us apart (below the threshold) --> ignored
+                item_us(
+                    30_000,
+                    0,
+                    TraceItemType::ExecutorPollStart { executor_id: 2 },
+                ),
+                item_us(30_001, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+            ],
+        );
+        // resumed (and coalesced) with the next event of our executor
+        assert_eq!(
+            task.get_state(),
+            &TaskTraceState::Preempted { by_executor_id: 2 }
+        );
+
+        feed_task(
+            &mut task,
+            &[item(
+                40,
+                0,
+                TraceItemType::TaskExecEnd {
+                    executor_id: 1,
+                    task_id: 1,
+                },
+            )],
+        );
+        assert_eq!(
+            history_of(&task),
+            vec![
+                (TaskTraceState::Spawned, 0, 10),
+                (TaskTraceState::Waiting, 10, 20),
+                (TaskTraceState::Running, 20, 40),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_integrity_issues() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        let exec_begin = |ms| {
+            item(
+                ms,
+                0,
+                TraceItemType::TaskExecBegin {
+                    executor_id: 1,
+                    task_id: 1,
+                },
+            )
+        };
+
+        feed_task(
+            &mut task,
+            &[
+                // ready begin dropped --> gap
+                exec_begin(10),
+                item(
+                    20,
+                    0,
+                    TraceItemType::TaskReadyBegin {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+                exec_begin(30),
+                // exec begin while running --> impossible
+                exec_begin(40),
+                // older than the previous event --> out of order
+                item(
+                    35,
+                    0,
+                    TraceItemType::TaskExecEnd {
+                        executor_id: 1,
+                        task_id: 1,
+                    },
+                ),
+            ],
+        );
+
+        let integrity = task.get_integrity();
+        assert_eq!(integrity.gaps, 1);
+        assert_eq!(integrity.impossible_transitions, 1);
+        assert_eq!(integrity.out_of_order, 1);
+    }
+
+    /// Task event of task 1 on executor 1
+    fn task_event(ms: u64, event: fn(u32, u32) -> TraceItemType) -> TraceItem {
+        item(ms, 0, event(1, 1))
+    }
+
+    fn ready_begin(executor_id: u32, task_id: u32) -> TraceItemType {
+        TraceItemType::TaskReadyBegin {
+            executor_id,
+            task_id,
+        }
+    }
+
+    fn exec_begin(executor_id: u32, task_id: u32) -> TraceItemType {
+        TraceItemType::TaskExecBegin {
+            executor_id,
+            task_id,
+        }
+    }
+
+    fn exec_end(executor_id: u32, task_id: u32) -> TraceItemType {
+        TraceItemType::TaskExecEnd {
+            executor_id,
+            task_id,
+        }
+    }
+
+    #[test]
+    fn test_period_breakdown() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+
+        // 10ms period: 1ms waiting, 2ms running, 1ms preempted, 1ms running, 5ms idle
+        for cycle in 0..5 {
+            let t = 10 + cycle * 10;
+            feed_task(
+                &mut task,
+                &[
+                    task_event(t, ready_begin),
+                    task_event(t + 1, exec_begin),
+                    item(
+                        t + 3,
+                        0,
+                        TraceItemType::ExecutorPollStart { executor_id: 2 },
+                    ),
+                    item(t + 4, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                    task_event(t + 5, exec_end),
+                ],
+            );
+        }
+        assert_eq!(task.infer_period(), Some(Duration::from_millis(10)));
+
+        let breakdown = task.calc_period_breakdown().unwrap();
+        assert_eq!(breakdown.period, Duration::from_millis(10));
+        for (actual, expected) in [
+            (breakdown.waiting_percent, 10.0),
+            (breakdown.running_percent, 30.0),
+            (breakdown.preempted_percent, 10.0),
+            (breakdown.idle_percent, 50.0),
+        ] {
+            assert!(
+                (actual - expected).abs() < 0.01,
+                "{} != {}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_yields_per_s() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+
+        // 5 polls in 10ms periods, each preempted once (not a yield) before it ends
+        for cycle in 0..5 {
+            let t = 10 + cycle * 10;
+            feed_task(
+                &mut task,
+                &[
+                    task_event(t, ready_begin),
+                    task_event(t + 1, exec_begin),
+                    item(
+                        t + 3,
+                        0,
+                        TraceItemType::ExecutorPollStart { executor_id: 2 },
+                    ),
+                    item(t + 4, 0, TraceItemType::ExecutorIdle { executor_id: 2 }),
+                    task_event(t + 5, exec_end),
+                ],
+            );
+        }
+        let task = task.rewound_to(EmbassyTime::from_millis(60)).unwrap();
+
+        // 5 yields over 60ms
+        let yields = task.calc_yields_per_s(None).unwrap();
+        assert!((yields - 5.0 / 0.06).abs() < 0.01, "{}", yields);
+
+        // the polls ending at 45ms and 55ms fall into the last 20ms
+        let yields = task
+            .calc_yields_per_s(Some(Duration::from_millis(20)))
+            .unwrap();
+        assert!((yields - 100.0).abs() < 0.01, "{}", yields);
+
+        // a poll ending the task is no yield
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        let task_end = |executor_id, task_id| TraceItemType::TaskEnd {
+            executor_id,
+            task_id,
+        };
+        feed_task(
+            &mut task,
+            &[
+                task_event(10, ready_begin),
+                task_event(11, exec_begin),
+                task_event(15, task_end),
+            ],
+        );
+        assert_eq!(task.calc_yields_per_s(None), Some(0.0));
+    }
+
+    #[test]
+    fn test_period_breakdown_without_stable_period() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+
+        // too few activations
+        feed_task(
+            &mut task,
+            &[
+                task_event(10, ready_begin),
+                task_event(11, exec_begin),
+                task_event(12, exec_end),
+                task_event(20, ready_begin),
+            ],
+        );
+        assert_eq!(task.infer_period(), None);
+        assert_eq!(task.calc_period_breakdown(), None);
+
+        // irregular activations (10ms, 35ms, 5ms)
+        feed_task(
+            &mut task,
+            &[
+                task_event(21, exec_begin),
+                task_event(22, exec_end),
+                task_event(55, ready_begin),
+                task_event(56, exec_begin),
+                task_event(57, exec_end),
+                task_event(60, ready_begin),
+            ],
+        );
+        assert_eq!(task.infer_period(), None);
+        assert_eq!(task.calc_period_breakdown(), None);
+    }
+
+    #[test]
+    fn test_sequential_and_parallel_stats_match() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+
+        // irregular waiting and running durations
+        let mut t = 10;
+        for cycle in 0..200u64 {
+            let waiting = 1 + cycle % 7;
+            let running = 1 + cycle % 5;
+            feed_task(
+                &mut task,
+                &[
+                    task_event(t, ready_begin),
+                    task_event(t + waiting, exec_begin),
+                    task_event(t + waiting + running, exec_end),
+                ],
+            );
+            t += waiting + running + 3;
+        }
+
+        for state in [
+            TaskTraceState::Waiting,
+            TaskTraceState::Running,
+            TaskTraceState::Idle,
+        ] {
+            assert_eq!(
+                task.history_state_duration(state, EmbassyTime::ZERO, false),
+                task.history_state_duration(state, EmbassyTime::ZERO, true)
+            );
+        }
+
+        let sequential = task.history_waiting_stats(false);
+        assert_eq!(sequential, task.history_waiting_stats(true));
+        assert_eq!(sequential.count, 200);
+        assert_eq!(sequential.min, Duration::from_millis(1));
+        assert_eq!(sequential.max, Duration::from_millis(7));
+    }
+
+    #[test]
+    fn test_state_machine_reawaken_while_running() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+
+        feed_task(
+            &mut task,
+            &[
+                task_event(10, ready_begin),
+                task_event(20, exec_begin),
+                // re-awoken while running
+                task_event(25, ready_begin),
+            ],
+        );
+        // keeps running until the poll ends
+        assert_eq!(task.get_state(), &TaskTraceState::Running);
+
+        // already queued again --> waiting instead of idle after the poll
+        feed_task(&mut task, &[task_event(30, exec_end)]);
+        assert_eq!(task.get_state(), &TaskTraceState::Waiting);
+
+        // the next poll ends in idle again
+        feed_task(
+            &mut task,
+            &[task_event(40, exec_begin), task_event(45, exec_end)],
+        );
+        assert_eq!(task.get_state(), &TaskTraceState::Idle);
+        assert_eq!(
+            history_of(&task),
+            vec![
+                (TaskTraceState::Spawned, 0, 10),
+                (TaskTraceState::Waiting, 10, 20),
+                (TaskTraceState::Running, 20, 30),
+                (TaskTraceState::Waiting, 30, 40),
+                (TaskTraceState::Running, 40, 45),
+            ]
+        );
+        assert_eq!(
+            task.calc_total_history_state_duration(TaskTraceState::Running, None),
+            EmbassyTime::from_millis(15)
+        );
+    }
+
+    #[test]
+    fn test_state_machine_lifecycle_events_only() {
+        // Beacon only publishes TaskNew/TaskEnd (no ready/exec events)
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        feed_task(
+            &mut task,
+            &[item(
+                50,
+                0,
+                TraceItemType::TaskEnd {
+                    executor_id: 1,
+                    task_id: 1,
+                },
+            )],
+        );
+
+        assert_eq!(task.get_state(), &TaskTraceState::Ended);
+        assert_eq!(history_of(&task), vec![(TaskTraceState::Spawned, 0, 50)]);
+    }
+
+    #[test]
+    fn test_age_and_last_active() {
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(5));
+        feed_task(&mut task, &[task_event(10, ready_begin)]);
+        assert_eq!(task.calc_last_active_ago(), None);
+
+        feed_task(
+            &mut task,
+            &[
+                task_event(12, exec_begin),
+                task_event(20, exec_end),
+                task_event(50, ready_begin),
+            ],
+        );
+
+        // Snapshots have a fixed "now"
+        let waiting = task.rewound_to(EmbassyTime::from_millis(100)).unwrap();
+        let stats = TaskStats::from_task(&waiting);
+        assert_eq!(stats.age, Duration::from_millis(95));
+        assert_eq!(stats.last_active_ago, Some(Duration::from_millis(80)));
+
+        let running = task.rewound_to(EmbassyTime::from_millis(15)).unwrap();
+        assert_eq!(running.calc_age(), Duration::from_millis(10));
+        assert_eq!(running.calc_last_active_ago(), Some(Duration::ZERO));
+
+        let before_first_run = task.rewound_to(EmbassyTime::from_millis(11)).unwrap();
+        assert_eq!(before_first_run.calc_last_active_ago(), None);
+    }
+
+    #[test]
+    fn test_stack_usage() {
+        let stack_usage = |ms, high_water_bytes| {
+            item(
+                ms,
+                0,
+                TraceItemType::TaskStackUsage {
+                    executor_id: 1,
+                    task_id: 1,
+                    high_water_bytes,
+                },
+            )
+        };
+
+        // never reported --> unknown, states work as usual
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        feed_task(
+            &mut task,
+            &[
+                task_event(10, ready_begin),
+                task_event(12, exec_begin),
+                task_event(20, exec_end),
+            ],
+        );
+        assert_eq!(task.get_stack_high_water(), None);
+
+        // reports keep the maximum and do not change the state
+        feed_task(
+            &mut task,
+            &[
+                stack_usage(30, 512),
+                stack_usage(40, 1536),
+                stack_usage(50, 1024),
+            ],
+        );
+        assert_eq!(task.get_stack_high_water(), Some(1536));
+        assert_eq!(task.get_state(), &TaskTraceState::Idle);
+        assert_eq!(task.get_integrity().total(), 0);
+        assert_eq!(task.get_state_history().len(), 3);
+    }
+
+    #[test]
+    fn test_respawn_in_pool_slot() {
+        let task_new = |executor_id, task_id| TraceItemType::TaskNew {
+            executor_id,
+            task_id,
+        };
+        let task_end = |executor_id, task_id| TraceItemType::TaskEnd {
+            executor_id,
+            task_id,
+        };
+        let run_once = |ms: u64| {
+            [
+                task_event(ms, ready_begin),
+                task_event(ms + 10, exec_begin),
+                task_event(ms + 30, exec_end),
+            ]
+        };
+
+        let mut task = TaskTraceInfo::new(1, 1, 0, at_ms(0));
+        feed_task(&mut task, &run_once(0));
+        feed_task(&mut task, &[task_event(50, task_end)]);
+        assert_eq!(task.get_state(), &TaskTraceState::Ended);
+
+        // the pool slot is spawned again: a fresh instance runs
+        feed_task(&mut task, &[task_event(100, task_new)]);
+        assert_eq!(task.get_state(), &TaskTraceState::Spawned);
+        assert_eq!(task.get_generation(), 1);
+        feed_task(&mut task, &run_once(110));
+        assert_eq!(task.get_state(), &TaskTraceState::Idle);
+
+        // statistics only cover the new instance
+        assert_eq!(task.get_created_at().get_uc_timestamp().as_millis(), 100);
+        assert_eq!(
+            history_of(&task),
+            vec![
+                (TaskTraceState::Spawned, 100, 110),
+                (TaskTraceState::Waiting, 110, 120),
+                (TaskTraceState::Running, 120, 140),
+            ]
+        );
+        assert_eq!(task.get_integrity().total(), 0);
+        let stats = TaskStats::from_task(&task.rewound_to(EmbassyTime::from_millis(150)).unwrap());
+        assert_eq!(stats.generation, 1);
+        assert_eq!(stats.age, Duration::from_millis(50));
+        assert_eq!(stats.count_waiting_time, 1);
+
+        // continued instead: the history of the previous instance is kept
+        task.set_continue_respawned(true);
+        feed_task(
+            &mut task,
+            &[task_event(200, task_end), task_event(300, task_new)],
+        );
+        assert_eq!(task.get_state(), &TaskTraceState::Spawned);
+        assert_eq!(task.get_generation(), 2);
+        assert_eq!(task.get_created_at().get_uc_timestamp().as_millis(), 100);
+        assert_eq!(
+            history_of(&task).last(),
+            Some(&(TaskTraceState::Ended, 200, 300))
+        );
+    }
 }
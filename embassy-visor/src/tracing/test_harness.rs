@@ -0,0 +1,47 @@
+//! Test harness to drive the task and executor state machines with a sequence of trace items
+//! without the TUI. Timestamps are synthetic (uC and PC clock are both set to the given
+//! milliseconds), so no test has to sleep.
+
+use crate::tracing::{
+    executor::ExecutorTraceInfo,
+    task::TaskTraceInfo,
+    time::{ComputerTime, EmbassyTime, TimePair},
+    trace_data::{TraceItem, TraceItemType},
+};
+
+/// Time pair where the uC and PC clock both read `ms` milliseconds
+pub fn at_ms(ms: u64) -> TimePair {
+    TimePair::new(
+        EmbassyTime::from_millis(ms),
+        ComputerTime::new_from_duration(std::time::Duration::from_millis(ms)),
+    )
+}
+
+/// Create a trace item happening at `ms` milliseconds on the given core
+pub fn item(ms: u64, core_id: u32, data: TraceItemType) -> TraceItem {
+    TraceItem::new(at_ms(ms), core_id, data)
+}
+
+/// Create a trace item happening at `us` microseconds on the given core (uC clock only)
+pub fn item_us(us: u64, core_id: u32, data: TraceItemType) -> TraceItem {
+    let pc_time = ComputerTime::new_from_duration(std::time::Duration::from_micros(us));
+    TraceItem::new(
+        TimePair::new(EmbassyTime::from_micros(us), pc_time),
+        core_id,
+        data,
+    )
+}
+
+/// Feed all trace items into the task state machine
+pub fn feed_task(task: &mut TaskTraceInfo, items: &[TraceItem]) {
+    for trace_item in items {
+        task.update(trace_item);
+    }
+}
+
+/// Feed all trace items into the executor state machine (and its tasks)
+pub fn feed_executor(executor: &mut ExecutorTraceInfo, items: &[TraceItem]) {
+    for trace_item in items {
+        executor.update(trace_item);
+    }
+}
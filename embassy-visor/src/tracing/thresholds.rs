@@ -0,0 +1,115 @@
+//! Thresholds of the detections, adjustable while running (settings overlay `s`). The
+//! detections read them on every check, so a change takes effect with the next statistics.
+//! Every threshold is also an option of the command line and the config file.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::tracing::{
+    executor::{
+        LOST_WAKEUP_THRESHOLD_MS, PEGGED_THRESHOLD_MS, PING_PONG_MAX_SWITCH_INTERVAL_US,
+        PING_PONG_MIN_SWITCHES, PREEMPTION_THRESHOLD_US,
+    },
+    stats::{core_imbalance::CORE_IMBALANCE_THRESHOLD_PERCENT, heatmap::MIN_VISIBLE_SPAN_US},
+};
+
+/// Detection threshold backed by the static the detection reads
+pub struct Threshold {
+    /// Command line flag (without the dashes) and config file key
+    pub key: &'static str,
+    pub label: &'static str,
+    pub unit: &'static str,
+    value: &'static AtomicU64,
+    /// Change of a single adjustment
+    step: u64,
+    min: u64,
+    max: u64,
+}
+
+impl Threshold {
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Set the threshold (clamped to its valid range)
+    pub fn set(&self, value: u64) {
+        self.value
+            .store(value.clamp(self.min, self.max), Ordering::Relaxed);
+    }
+
+    /// Raise (positive) or lower (negative) the threshold by this many steps
+    pub fn adjust(&self, steps: i64) {
+        let delta = self.step.saturating_mul(steps.unsigned_abs());
+        self.set(if steps < 0 {
+            self.get().saturating_sub(delta)
+        } else {
+            self.get().saturating_add(delta)
+        });
+    }
+}
+
+/// All adjustable thresholds, in the order of the settings overlay
+pub static THRESHOLDS: [Threshold; 7] = [
+    Threshold {
+        key: "preemption-threshold-us",
+        label: "Shortest preemption (shorter ones are coalesced)",
+        unit: "µs",
+        value: &PREEMPTION_THRESHOLD_US,
+        step: 1,
+        min: 0,
+        max: 10_000,
+    },
+    Threshold {
+        key: "lost-wakeup-threshold-ms",
+        label: "Lost wakeup: ready while the executor idles",
+        unit: "ms",
+        value: &LOST_WAKEUP_THRESHOLD_MS,
+        step: 10,
+        min: 1,
+        max: 60_000,
+    },
+    Threshold {
+        key: "pegged-threshold-ms",
+        label: "Pegged executor: busy without idling",
+        unit: "ms",
+        value: &PEGGED_THRESHOLD_MS,
+        step: 100,
+        min: 1,
+        max: 600_000,
+    },
+    Threshold {
+        key: "ping-pong-min-switches",
+        label: "Ping-pong: alternating polls",
+        unit: "switches",
+        value: &PING_PONG_MIN_SWITCHES,
+        step: 10,
+        min: 2,
+        max: 100_000,
+    },
+    Threshold {
+        key: "ping-pong-max-interval-us",
+        label: "Ping-pong: longest average switch interval",
+        unit: "µs",
+        value: &PING_PONG_MAX_SWITCH_INTERVAL_US,
+        step: 100,
+        min: 1,
+        max: 1_000_000,
+    },
+    Threshold {
+        key: "min-visible-span-us",
+        label: "Glitch filter: shortest visible state span",
+        unit: "µs",
+        value: &MIN_VISIBLE_SPAN_US,
+        step: 10,
+        min: 0,
+        max: 1_000_000,
+    },
+    Threshold {
+        key: "core-imbalance-threshold-percent",
+        label: "Core imbalance: busiest minus idlest core",
+        unit: "%",
+        value: &CORE_IMBALANCE_THRESHOLD_PERCENT,
+        step: 5,
+        min: 1,
+        max: 100,
+    },
+];
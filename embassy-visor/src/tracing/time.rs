@@ -43,6 +43,10 @@ impl ComputerTime {
         self.0.as_millis()
     }
 
+    pub fn as_micros(&self) -> u128 {
+        self.0.as_micros()
+    }
+
     pub fn saturating_sub(&self, other: ComputerTime) -> ComputerTime {
         ComputerTime(self.0.saturating_sub(other.0))
     }
@@ -59,7 +63,7 @@ impl From<Instant> for ComputerTime {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, PartialOrd, Ord)]
 pub struct EmbassyTime(Duration);
 
 impl EmbassyTime {
@@ -81,6 +85,10 @@ impl EmbassyTime {
         self.0.as_millis()
     }
 
+    pub fn as_micros(&self) -> u128 {
+        self.0.as_micros()
+    }
+
     pub fn saturating_sub(&self, other: EmbassyTime) -> EmbassyTime {
         EmbassyTime(self.0.saturating_sub(other.0))
     }
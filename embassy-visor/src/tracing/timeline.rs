@@ -0,0 +1,182 @@
+//! Chronological log of significant lifecycle events (tasks spawned/ended, executors created,
+//! preemptions, integrity issues), to reconstruct what happened and when.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::tracing::time::EmbassyTime;
+
+/// Maximum number of events kept in the timeline (oldest are dropped first)
+pub static MAX_TIMELINE_EVENTS: AtomicUsize = AtomicUsize::new(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineEventKind {
+    TaskSpawned,
+    TaskEnded,
+    ExecutorCreated,
+    PreemptionStarted,
+    PreemptionEnded,
+    IntegrityWarning,
+    /// Task ready while its executor stays idle
+    LostWakeup,
+    /// Firmware clock restarted, a new boot began
+    FirmwareReset,
+    /// Two tasks polled alternately at a high rate (suspected of waking each other)
+    PingPong,
+    /// Marker set by the user (`M`)
+    UserMarker,
+}
+
+impl TimelineEventKind {
+    /// Short label shown in the timeline
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimelineEventKind::TaskSpawned => "SPAWN",
+            TimelineEventKind::TaskEnded => "END",
+            TimelineEventKind::ExecutorCreated => "EXECUTOR",
+            TimelineEventKind::PreemptionStarted => "PREEMPT",
+            TimelineEventKind::PreemptionEnded => "RESUME",
+            TimelineEventKind::IntegrityWarning => "INTEGRITY",
+            TimelineEventKind::LostWakeup => "LOST WAKE",
+            TimelineEventKind::FirmwareReset => "RESET",
+            TimelineEventKind::PingPong => "PING-PONG",
+            TimelineEventKind::UserMarker => "MARK",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    /// uC timestamp of the trace item causing the event
+    pub time: EmbassyTime,
+    pub kind: TimelineEventKind,
+    pub description: String,
+}
+
+/// Filter of the timeline by event kind (cycled in the TUI)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimelineFilter {
+    #[default]
+    All,
+    /// Tasks spawned and ended
+    Tasks,
+    /// Executors created
+    Executors,
+    /// Preemptions started and ended
+    Preemptions,
+    /// Integrity issues of the trace, lost wakeups and ping-pongs
+    Integrity,
+}
+
+impl TimelineFilter {
+    /// Check if the event kind passes the filter
+    pub fn matches(&self, kind: TimelineEventKind) -> bool {
+        match self {
+            TimelineFilter::All => true,
+            TimelineFilter::Tasks => matches!(
+                kind,
+                TimelineEventKind::TaskSpawned | TimelineEventKind::TaskEnded
+            ),
+            TimelineFilter::Executors => matches!(
+                kind,
+                TimelineEventKind::ExecutorCreated | TimelineEventKind::FirmwareReset
+            ),
+            TimelineFilter::Preemptions => matches!(
+                kind,
+                TimelineEventKind::PreemptionStarted | TimelineEventKind::PreemptionEnded
+            ),
+            TimelineFilter::Integrity => matches!(
+                kind,
+                TimelineEventKind::IntegrityWarning
+                    | TimelineEventKind::LostWakeup
+                    | TimelineEventKind::PingPong
+            ),
+        }
+    }
+
+    /// Next filter in the cycle
+    pub fn next(&self) -> Self {
+        match self {
+            TimelineFilter::All => TimelineFilter::Tasks,
+            TimelineFilter::Tasks => TimelineFilter::Executors,
+            TimelineFilter::Executors => TimelineFilter::Preemptions,
+            TimelineFilter::Preemptions => TimelineFilter::Integrity,
+            TimelineFilter::Integrity => TimelineFilter::All,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimelineFilter::All => "all",
+            TimelineFilter::Tasks => "tasks",
+            TimelineFilter::Executors => "executors",
+            TimelineFilter::Preemptions => "preemptions",
+            TimelineFilter::Integrity => "integrity",
+        }
+    }
+}
+
+/// Bounded buffer of timeline events (oldest first)
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    events: VecDeque<TimelineEvent>,
+}
+
+impl Timeline {
+    pub fn push(&mut self, time: EmbassyTime, kind: TimelineEventKind, description: String) {
+        self.events.push_back(TimelineEvent {
+            time,
+            kind,
+            description,
+        });
+
+        let max_events = MAX_TIMELINE_EVENTS.load(Ordering::Relaxed);
+        while self.events.len() > max_events {
+            self.events.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// All events (oldest first)
+    pub fn events(&self) -> Vec<TimelineEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_filter() {
+        let kinds = [
+            TimelineEventKind::TaskSpawned,
+            TimelineEventKind::TaskEnded,
+            TimelineEventKind::ExecutorCreated,
+            TimelineEventKind::PreemptionStarted,
+            TimelineEventKind::PreemptionEnded,
+            TimelineEventKind::IntegrityWarning,
+            TimelineEventKind::LostWakeup,
+        ];
+        let matching =
+            |filter: TimelineFilter| kinds.iter().filter(|k| filter.matches(**k)).count();
+
+        assert_eq!(matching(TimelineFilter::All), 7);
+        assert_eq!(matching(TimelineFilter::Tasks), 2);
+        assert_eq!(matching(TimelineFilter::Executors), 1);
+        assert_eq!(matching(TimelineFilter::Preemptions), 2);
+        assert_eq!(matching(TimelineFilter::Integrity), 2);
+
+        // cycle visits every filter once
+        let mut filter = TimelineFilter::All;
+        for _ in 0..5 {
+            filter = filter.next();
+        }
+        assert_eq!(filter, TimelineFilter::All);
+    }
+}
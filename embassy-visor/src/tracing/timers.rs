@@ -0,0 +1,236 @@
+//! Timers of the tasks (beacon's `timer-trace` feature). A task sets a timer, the timer fires
+//! (reported by the firmware, else assumed at its expiry) and the task is woken. The first
+//! wakeup of the task after its timer fired is attributed to the timer, which answers why a
+//! task woke up at a certain time.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::tracing::{executor::ExecutorTraceInfo, time::EmbassyTime, trace_data::TraceItemType};
+
+/// Timers that woke their task, kept for the timer panel
+const MAX_WOKEN_TIMERS: usize = 32;
+
+/// Pending timers kept (the oldest are dropped, e.g. timers cancelled by the firmware)
+const MAX_PENDING_TIMERS: usize = 256;
+
+/// One set of a timer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimerEntry {
+    timer_id: u32,
+    executor_id: u32,
+    task_id: u32,
+    set_at: EmbassyTime,
+    expires_at: EmbassyTime,
+    fired_at: Option<EmbassyTime>,
+}
+
+impl TimerEntry {
+    /// Whether the timer fired (reported or past its expiry) by the time
+    fn has_fired(&self, time: EmbassyTime) -> bool {
+        self.fired_at.is_some() || self.expires_at <= time
+    }
+}
+
+/// Timers set by the tasks, pending and recently woken
+#[derive(Debug, Clone, Default)]
+pub struct TimerTracker {
+    /// Timers waiting to wake their task, by timer id
+    pending: BTreeMap<u32, TimerEntry>,
+    /// Timers that woke their task with the time of the wakeup, newest last
+    woken: VecDeque<(TimerEntry, EmbassyTime)>,
+}
+
+/// Timer of a task as shown in the timer panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimerStats {
+    pub timer_id: u32,
+    /// Task that set the timer
+    pub task: String,
+    pub set_at: EmbassyTime,
+    pub expires_at: EmbassyTime,
+    /// Reported fire time (None if the firmware does not report it or it did not fire yet)
+    pub fired_at: Option<EmbassyTime>,
+    /// Wakeup of the task attributed to the timer (None while pending)
+    pub woken_at: Option<EmbassyTime>,
+}
+
+impl TimerStats {
+    /// Time from the fire (or expiry) of the timer to the wakeup of its task
+    pub fn wake_latency(&self) -> Option<EmbassyTime> {
+        let fired_at = self.fired_at.unwrap_or(self.expires_at);
+        self.woken_at
+            .map(|woken_at| woken_at.saturating_sub(fired_at))
+    }
+}
+
+impl TimerTracker {
+    /// Record a timer set or fire and attribute task wakeups to fired timers (other trace
+    /// items are ignored)
+    pub fn record(&mut self, time: EmbassyTime, data: &TraceItemType) {
+        match *data {
+            TraceItemType::TimerSet {
+                executor_id,
+                task_id,
+                timer_id,
+                expires_at_us,
+            } => {
+                self.pending.insert(
+                    timer_id,
+                    TimerEntry {
+                        timer_id,
+                        executor_id,
+                        task_id,
+                        set_at: time,
+                        expires_at: EmbassyTime::from_micros(expires_at_us),
+                        fired_at: None,
+                    },
+                );
+                if self.pending.len() > MAX_PENDING_TIMERS
+                    && let Some(oldest) = self
+                        .pending
+                        .values()
+                        .min_by_key(|timer| timer.set_at)
+                        .map(|timer| timer.timer_id)
+                {
+                    self.pending.remove(&oldest);
+                }
+            }
+            TraceItemType::TimerFire { timer_id } => {
+                if let Some(timer) = self.pending.get_mut(&timer_id) {
+                    timer.fired_at.get_or_insert(time);
+                }
+            }
+            TraceItemType::TaskReadyBegin {
+                executor_id,
+                task_id,
+            } => self.task_woken(executor_id, task_id, time),
+            _ => {}
+        }
+    }
+
+    /// The task was woken: the earliest of its fired timers woke it
+    fn task_woken(&mut self, executor_id: u32, task_id: u32, time: EmbassyTime) {
+        let Some(timer) = self
+            .pending
+            .values()
+            .filter(|timer| timer.executor_id == executor_id && timer.task_id == task_id)
+            .filter(|timer| timer.has_fired(time))
+            .min_by_key(|timer| timer.fired_at.unwrap_or(timer.expires_at))
+            .copied()
+        else {
+            return;
+        };
+
+        self.pending.remove(&timer.timer_id);
+        self.woken.push_back((timer, time));
+        if self.woken.len() > MAX_WOKEN_TIMERS {
+            self.woken.pop_front();
+        }
+    }
+
+    /// Forget all timers (e.g. the ids of a new boot belong to different tasks)
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.woken.clear();
+    }
+
+    /// Pending timers (soonest expiry first), then the timers that woke their task (newest
+    /// first). Tasks are named after the executors' tasks.
+    pub fn timers(&self, executors: &[ExecutorTraceInfo]) -> Vec<TimerStats> {
+        let stats = |timer: &TimerEntry, woken_at: Option<EmbassyTime>| TimerStats {
+            timer_id: timer.timer_id,
+            task: executors
+                .iter()
+                .filter(|e| e.get_executor_id() == timer.executor_id)
+                .find_map(|e| e.find_task_by_id(timer.task_id))
+                .map_or_else(
+                    || format!("Task 0x{:X}", timer.task_id),
+                    |t| t.get_task_display_name(),
+                ),
+            set_at: timer.set_at,
+            expires_at: timer.expires_at,
+            fired_at: timer.fired_at,
+            woken_at,
+        };
+
+        let mut pending: Vec<&TimerEntry> = self.pending.values().collect();
+        pending.sort_by_key(|timer| timer.expires_at);
+        pending
+            .into_iter()
+            .map(|timer| stats(timer, None))
+            .chain(
+                self.woken
+                    .iter()
+                    .rev()
+                    .map(|(timer, woken_at)| stats(timer, Some(*woken_at))),
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(timer_id: u32, task_id: u32, expires_at_us: u64) -> TraceItemType {
+        TraceItemType::TimerSet {
+            executor_id: 1,
+            task_id,
+            timer_id,
+            expires_at_us,
+        }
+    }
+
+    fn ready(task_id: u32) -> TraceItemType {
+        TraceItemType::TaskReadyBegin {
+            executor_id: 1,
+            task_id,
+        }
+    }
+
+    #[test]
+    fn test_timer_fire_wakes_task() {
+        let us = EmbassyTime::from_micros;
+        let mut timers = TimerTracker::default();
+
+        // task 7 sleeps until 10ms, task 8 until 50ms
+        timers.record(us(1_000), &set(3, 7, 10_000));
+        timers.record(us(1_200), &set(4, 8, 50_000));
+        // task 7 woken by something else before its timer fires: not attributed
+        timers.record(us(5_000), &ready(7));
+        assert_eq!(timers.timers(&[])[0].woken_at, None);
+
+        // the timer fires, task 7 becomes ready (waiting to be polled) right after
+        timers.record(us(10_010), &TraceItemType::TimerFire { timer_id: 3 });
+        timers.record(us(10_060), &ready(7));
+
+        let stats = timers.timers(&[]);
+        assert_eq!(stats.len(), 2);
+        // pending first
+        assert_eq!(stats[0].timer_id, 4);
+        assert_eq!(stats[0].task, "Task 0x8");
+        assert_eq!(stats[0].woken_at, None);
+        // then the timer that woke task 7
+        assert_eq!(
+            stats[1],
+            TimerStats {
+                timer_id: 3,
+                task: String::from("Task 0x7"),
+                set_at: us(1_000),
+                expires_at: us(10_000),
+                fired_at: Some(us(10_010)),
+                woken_at: Some(us(10_060)),
+            }
+        );
+        assert_eq!(stats[1].wake_latency(), Some(us(50)));
+
+        // without fire reports the expiry counts as the fire
+        timers.record(us(50_030), &ready(8));
+        let stats = timers.timers(&[]);
+        assert_eq!(stats[0].timer_id, 4);
+        assert_eq!(stats[0].wake_latency(), Some(us(30)));
+
+        timers.clear();
+        assert!(timers.timers(&[]).is_empty());
+    }
+}
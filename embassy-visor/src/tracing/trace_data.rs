@@ -1,6 +1,134 @@
-use crate::tracing::time::{ComputerTime, EmbassyTime, TimePair};
+//! Trace line protocol. A trace line carries one or more records between a prefix and a
+//! suffix marker:
+//!
+//! `<prefix>[<timestamp_us>, <core_id>, <EventName>, <payload>...] [...]<suffix>`
+//!
+//! The markers and event names default to the ones published by `embassy-beacon`, but can be
+//! configured (`TRACE_PROTOCOL`) so tracers of other runtimes can feed the visor without
+//! mimicking the embassy names.
 
-#[derive(Debug)]
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, RwLock},
+};
+
+use crate::tracing::{
+    device::PRIMARY_DEVICE_ID,
+    header::{DEFAULT_TICK_HZ, TraceHeader},
+    time::{ComputerTime, EmbassyTime, TimePair},
+};
+
+/// Start of the trace records in a line published by the beacon
+pub const TRACE_LINE_PREFIX: &str = "embassy executor tracer - ";
+/// End of the trace records in a line published by the beacon
+pub const TRACE_LINE_SUFFIX: &str = " - embassy executor tracer";
+
+/// Protocol used to detect and parse trace lines (defaults to the beacon's)
+pub static TRACE_PROTOCOL: LazyLock<RwLock<TraceProtocol>> =
+    LazyLock::new(|| RwLock::new(TraceProtocol::default()));
+
+/// Kind of a trace event, independent of its name in the trace line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceEventKind {
+    Header,
+    SchemaVersion,
+    Topology,
+    FirmwareId,
+    Heartbeat,
+    ExecutorIdle,
+    ExecutorPollStart,
+    TaskNew,
+    TaskEnd,
+    TaskExecBegin,
+    TaskExecEnd,
+    TaskReadyBegin,
+    TaskStackUsage,
+    ChannelSend,
+    ChannelReceive,
+    TimerSet,
+    TimerFire,
+}
+
+impl TraceEventKind {
+    pub const ALL: [TraceEventKind; 17] = [
+        TraceEventKind::Header,
+        TraceEventKind::SchemaVersion,
+        TraceEventKind::Topology,
+        TraceEventKind::FirmwareId,
+        TraceEventKind::Heartbeat,
+        TraceEventKind::ExecutorIdle,
+        TraceEventKind::ExecutorPollStart,
+        TraceEventKind::TaskNew,
+        TraceEventKind::TaskEnd,
+        TraceEventKind::TaskExecBegin,
+        TraceEventKind::TaskExecEnd,
+        TraceEventKind::TaskReadyBegin,
+        TraceEventKind::TaskStackUsage,
+        TraceEventKind::ChannelSend,
+        TraceEventKind::ChannelReceive,
+        TraceEventKind::TimerSet,
+        TraceEventKind::TimerFire,
+    ];
+
+    /// Name of the event as published by the beacon
+    pub fn default_name(&self) -> &'static str {
+        match self {
+            TraceEventKind::Header => "Header",
+            TraceEventKind::SchemaVersion => "SchemaVersion",
+            TraceEventKind::Topology => "Topology",
+            TraceEventKind::FirmwareId => "FirmwareId",
+            TraceEventKind::Heartbeat => "Heartbeat",
+            TraceEventKind::ExecutorIdle => "ExecutorIdle",
+            TraceEventKind::ExecutorPollStart => "ExecutorPollStart",
+            TraceEventKind::TaskNew => "TaskNew",
+            TraceEventKind::TaskEnd => "TaskEnd",
+            TraceEventKind::TaskExecBegin => "TaskExecBegin",
+            TraceEventKind::TaskExecEnd => "TaskExecEnd",
+            TraceEventKind::TaskReadyBegin => "TaskReadyBegin",
+            TraceEventKind::TaskStackUsage => "TaskStackUsage",
+            TraceEventKind::ChannelSend => "ChannelSend",
+            TraceEventKind::ChannelReceive => "ChannelReceive",
+            TraceEventKind::TimerSet => "TimerSet",
+            TraceEventKind::TimerFire => "TimerFire",
+        }
+    }
+
+    /// Look up the event by its beacon name
+    pub fn from_default_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.default_name() == name)
+    }
+}
+
+/// Markers and event names of the trace lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceProtocol {
+    /// Start of the trace records in a line
+    pub prefix: String,
+    /// End of the trace records in a line
+    pub suffix: String,
+    /// Ticks per second of the record timestamps (declared by the beacon's header)
+    pub tick_hz: u64,
+    event_kinds: HashMap<String, TraceEventKind>,
+}
+
+impl Default for TraceProtocol {
+    fn default() -> Self {
+        Self {
+            prefix: String::from(TRACE_LINE_PREFIX),
+            suffix: String::from(TRACE_LINE_SUFFIX),
+            tick_hz: DEFAULT_TICK_HZ,
+            event_kinds: TraceEventKind::ALL
+                .into_iter()
+                .map(|kind| (kind.default_name().to_string(), kind))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[allow(clippy::enum_variant_names)]
 pub enum TraceParseError {
     InvalidTimestamp,
     InvalidCoreId,
@@ -11,19 +139,108 @@ pub enum TraceParseError {
     InvalidEventPayload,
 }
 
+impl std::fmt::Display for TraceParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            TraceParseError::InvalidTimestamp => "invalid timestamp",
+            TraceParseError::InvalidCoreId => "invalid core id",
+            TraceParseError::InvalidExecutorId => "invalid executor id",
+            TraceParseError::InvalidFormat => "invalid trace line format",
+            TraceParseError::InvalidTaskId => "invalid task id",
+            TraceParseError::InvalidEventType => "unknown event type",
+            TraceParseError::InvalidEventPayload => "invalid event payload",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl std::error::Error for TraceParseError {}
+
+/// Result of parsing one trace line, passed from the line router to the tracing instance
+pub type TraceParseResult = Result<TraceItem, TraceParseError>;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TraceItemType {
-    ExecutorIdle { executor_id: u32 },
-    ExecutorPollStart { executor_id: u32 },
-    TaskNew { executor_id: u32, task_id: u32 },
-    TaskEnd { executor_id: u32, task_id: u32 },
-    TaskExecBegin { executor_id: u32, task_id: u32 },
-    TaskExecEnd { executor_id: u32, task_id: u32 },
-    TaskReadyBegin { executor_id: u32, task_id: u32 },
+    /// One-time declaration of the schema version, timestamp rate, cores and firmware id
+    Header {
+        header: TraceHeader,
+    },
+    /// One-time declaration of the beacon's trace schema version
+    SchemaVersion {
+        version: u32,
+    },
+    /// One-time declaration of the microcontroller's cores (ids 0..core_count)
+    Topology {
+        core_count: u32,
+    },
+    /// One-time identifier of the firmware build (version string or git hash)
+    FirmwareId {
+        id: String,
+    },
+    /// Periodic sign of life carrying only the timestamp, to align the clocks while idle
+    Heartbeat {
+        interval_ms: u32,
+    },
+    ExecutorIdle {
+        executor_id: u32,
+    },
+    ExecutorPollStart {
+        executor_id: u32,
+    },
+    TaskNew {
+        executor_id: u32,
+        task_id: u32,
+    },
+    TaskEnd {
+        executor_id: u32,
+        task_id: u32,
+    },
+    TaskExecBegin {
+        executor_id: u32,
+        task_id: u32,
+    },
+    TaskExecEnd {
+        executor_id: u32,
+        task_id: u32,
+    },
+    TaskReadyBegin {
+        executor_id: u32,
+        task_id: u32,
+    },
+    /// Optional, periodic high-water mark of the task's stack usage (target dependent)
+    TaskStackUsage {
+        executor_id: u32,
+        task_id: u32,
+        high_water_bytes: u32,
+    },
+    /// Optional, the task sent a message to the channel (the channel id is usually its address)
+    ChannelSend {
+        executor_id: u32,
+        task_id: u32,
+        channel_id: u32,
+    },
+    /// Optional, the task received a message from the channel
+    ChannelReceive {
+        executor_id: u32,
+        task_id: u32,
+        channel_id: u32,
+    },
+    /// Optional, the task set a timer expiring at the uC time (in microseconds)
+    TimerSet {
+        executor_id: u32,
+        task_id: u32,
+        timer_id: u32,
+        expires_at_us: u64,
+    },
+    /// Optional, the timer fired (reported from anywhere, e.g. the alarm interrupt)
+    TimerFire {
+        timer_id: u32,
+    },
 }
 
 impl TraceItemType {
-    pub fn get_executor_id(&self) -> u32 {
+    /// Get the executor ID (None for events not belonging to an executor)
+    pub fn get_executor_id(&self) -> Option<u32> {
         match self {
             TraceItemType::ExecutorIdle { executor_id }
             | TraceItemType::ExecutorPollStart { executor_id }
@@ -31,7 +248,17 @@ impl TraceItemType {
             | TraceItemType::TaskEnd { executor_id, .. }
             | TraceItemType::TaskExecBegin { executor_id, .. }
             | TraceItemType::TaskExecEnd { executor_id, .. }
-            | TraceItemType::TaskReadyBegin { executor_id, .. } => *executor_id,
+            | TraceItemType::TaskReadyBegin { executor_id, .. }
+            | TraceItemType::TaskStackUsage { executor_id, .. }
+            | TraceItemType::ChannelSend { executor_id, .. }
+            | TraceItemType::ChannelReceive { executor_id, .. }
+            | TraceItemType::TimerSet { executor_id, .. } => Some(*executor_id),
+            TraceItemType::Header { .. }
+            | TraceItemType::SchemaVersion { .. }
+            | TraceItemType::Topology { .. }
+            | TraceItemType::FirmwareId { .. }
+            | TraceItemType::Heartbeat { .. }
+            | TraceItemType::TimerFire { .. } => None,
         }
     }
 
@@ -41,83 +268,212 @@ impl TraceItemType {
             | TraceItemType::TaskEnd { task_id, .. }
             | TraceItemType::TaskExecBegin { task_id, .. }
             | TraceItemType::TaskExecEnd { task_id, .. }
-            | TraceItemType::TaskReadyBegin { task_id, .. } => Some(*task_id),
+            | TraceItemType::TaskReadyBegin { task_id, .. }
+            | TraceItemType::TaskStackUsage { task_id, .. }
+            | TraceItemType::ChannelSend { task_id, .. }
+            | TraceItemType::ChannelReceive { task_id, .. }
+            | TraceItemType::TimerSet { task_id, .. } => Some(*task_id),
             _ => None,
         }
     }
+
+    pub fn kind(&self) -> TraceEventKind {
+        match self {
+            TraceItemType::Header { .. } => TraceEventKind::Header,
+            TraceItemType::SchemaVersion { .. } => TraceEventKind::SchemaVersion,
+            TraceItemType::Topology { .. } => TraceEventKind::Topology,
+            TraceItemType::FirmwareId { .. } => TraceEventKind::FirmwareId,
+            TraceItemType::Heartbeat { .. } => TraceEventKind::Heartbeat,
+            TraceItemType::ExecutorIdle { .. } => TraceEventKind::ExecutorIdle,
+            TraceItemType::ExecutorPollStart { .. } => TraceEventKind::ExecutorPollStart,
+            TraceItemType::TaskNew { .. } => TraceEventKind::TaskNew,
+            TraceItemType::TaskEnd { .. } => TraceEventKind::TaskEnd,
+            TraceItemType::TaskExecBegin { .. } => TraceEventKind::TaskExecBegin,
+            TraceItemType::TaskExecEnd { .. } => TraceEventKind::TaskExecEnd,
+            TraceItemType::TaskReadyBegin { .. } => TraceEventKind::TaskReadyBegin,
+            TraceItemType::TaskStackUsage { .. } => TraceEventKind::TaskStackUsage,
+            TraceItemType::ChannelSend { .. } => TraceEventKind::ChannelSend,
+            TraceItemType::ChannelReceive { .. } => TraceEventKind::ChannelReceive,
+            TraceItemType::TimerSet { .. } => TraceEventKind::TimerSet,
+            TraceItemType::TimerFire { .. } => TraceEventKind::TimerFire,
+        }
+    }
 }
 
 impl TraceItemType {
-    /// Format: <EventType>, <executor_id>, <task_id?>
-    pub fn from_parts(parts: &[&str]) -> Result<Self, TraceParseError> {
-        if parts.len() < 2 {
+    /// Payload format: <executor_id>, <task_id?>
+    /// or (TaskStackUsage): <executor_id>, <task_id>, <high_water_bytes>
+    /// or (ChannelSend/ChannelReceive): <executor_id>, <task_id>, <channel_id>
+    /// or (TimerSet): <executor_id>, <task_id>, <timer_id>, <expires_at_us>
+    /// or (TimerFire): <timer_id>
+    /// or (Header): <key>=<value>, ... (see `TraceHeader`)
+    /// or (SchemaVersion): <version>
+    /// or (Topology): <core_count>
+    /// or (FirmwareId): <id>
+    /// or (Heartbeat): <interval_ms>
+    pub fn from_parts(kind: TraceEventKind, payload: &[&str]) -> Result<Self, TraceParseError> {
+        if payload.is_empty() {
             return Err(TraceParseError::InvalidFormat);
         }
 
-        // Destructure parts
-        let event_type = parts[0].trim();
-        let executor_id: u32 = parts[1]
-            .trim()
-            .parse()
-            .map_err(|_| TraceParseError::InvalidExecutorId)?;
-        let task_id = if parts.len() > 2 {
-            Some(
-                parts[2]
-                    .trim()
-                    .parse()
-                    .map_err(|_| TraceParseError::InvalidTaskId)?,
-            )
-        } else {
-            None
+        // Executor (and task) of the executor scoped events
+        let scope = || -> Result<(u32, Option<u32>), TraceParseError> {
+            let executor_id = payload[0]
+                .trim()
+                .parse()
+                .map_err(|_| TraceParseError::InvalidExecutorId)?;
+            let task_id = match payload.get(1) {
+                Some(task_id) => Some(
+                    task_id
+                        .trim()
+                        .parse()
+                        .map_err(|_| TraceParseError::InvalidTaskId)?,
+                ),
+                None => None,
+            };
+            Ok((executor_id, task_id))
+        };
+        let task_scope = || {
+            let (executor_id, task_id) = scope()?;
+            Ok((
+                executor_id,
+                task_id.ok_or(TraceParseError::InvalidEventPayload)?,
+            ))
+        };
+        // Channel or timer the event refers to
+        let object_id = || {
+            payload
+                .get(2)
+                .and_then(|id| id.trim().parse().ok())
+                .ok_or(TraceParseError::InvalidEventPayload)
         };
 
-        match event_type {
-            "ExecutorIdle" => Ok(TraceItemType::ExecutorIdle { executor_id }),
-            "ExecutorPollStart" => Ok(TraceItemType::ExecutorPollStart { executor_id }),
-            "TaskNew" => {
-                let task_id = task_id.ok_or(TraceParseError::InvalidEventPayload)?;
+        match kind {
+            TraceEventKind::Header => Ok(TraceItemType::Header {
+                header: TraceHeader::parse(payload)?,
+            }),
+            TraceEventKind::SchemaVersion => Ok(TraceItemType::SchemaVersion {
+                version: payload[0]
+                    .trim()
+                    .parse()
+                    .map_err(|_| TraceParseError::InvalidEventPayload)?,
+            }),
+            // Topology has no executor
+            TraceEventKind::Topology => Ok(TraceItemType::Topology {
+                core_count: payload[0]
+                    .trim()
+                    .parse()
+                    .map_err(|_| TraceParseError::InvalidEventPayload)?,
+            }),
+            // Firmware id is free text (commas are kept)
+            TraceEventKind::FirmwareId => {
+                let id = payload.join(",").trim().to_string();
+                if id.is_empty() {
+                    return Err(TraceParseError::InvalidEventPayload);
+                }
+                Ok(TraceItemType::FirmwareId { id })
+            }
+            TraceEventKind::Heartbeat => Ok(TraceItemType::Heartbeat {
+                interval_ms: payload[0]
+                    .trim()
+                    .parse()
+                    .map_err(|_| TraceParseError::InvalidEventPayload)?,
+            }),
+            TraceEventKind::ExecutorIdle => Ok(TraceItemType::ExecutorIdle {
+                executor_id: scope()?.0,
+            }),
+            TraceEventKind::ExecutorPollStart => Ok(TraceItemType::ExecutorPollStart {
+                executor_id: scope()?.0,
+            }),
+            TraceEventKind::TaskNew => {
+                let (executor_id, task_id) = task_scope()?;
                 Ok(TraceItemType::TaskNew {
                     executor_id,
                     task_id,
                 })
             }
-            "TaskEnd" => {
-                let task_id = task_id.ok_or(TraceParseError::InvalidEventPayload)?;
+            TraceEventKind::TaskEnd => {
+                let (executor_id, task_id) = task_scope()?;
                 Ok(TraceItemType::TaskEnd {
                     executor_id,
                     task_id,
                 })
             }
-            "TaskExecBegin" => {
-                let task_id = task_id.ok_or(TraceParseError::InvalidEventPayload)?;
+            TraceEventKind::TaskExecBegin => {
+                let (executor_id, task_id) = task_scope()?;
                 Ok(TraceItemType::TaskExecBegin {
                     executor_id,
                     task_id,
                 })
             }
-            "TaskExecEnd" => {
-                let task_id = task_id.ok_or(TraceParseError::InvalidEventPayload)?;
+            TraceEventKind::TaskExecEnd => {
+                let (executor_id, task_id) = task_scope()?;
                 Ok(TraceItemType::TaskExecEnd {
                     executor_id,
                     task_id,
                 })
             }
-            "TaskReadyBegin" => {
-                let task_id = task_id.ok_or(TraceParseError::InvalidEventPayload)?;
+            TraceEventKind::TaskReadyBegin => {
+                let (executor_id, task_id) = task_scope()?;
                 Ok(TraceItemType::TaskReadyBegin {
                     executor_id,
                     task_id,
                 })
             }
-            _ => Err(TraceParseError::InvalidEventType),
+            TraceEventKind::TaskStackUsage => {
+                let (executor_id, task_id) = task_scope()?;
+                Ok(TraceItemType::TaskStackUsage {
+                    executor_id,
+                    task_id,
+                    high_water_bytes: payload
+                        .get(2)
+                        .and_then(|bytes| bytes.trim().parse().ok())
+                        .ok_or(TraceParseError::InvalidEventPayload)?,
+                })
+            }
+            TraceEventKind::ChannelSend => {
+                let (executor_id, task_id) = task_scope()?;
+                Ok(TraceItemType::ChannelSend {
+                    executor_id,
+                    task_id,
+                    channel_id: object_id()?,
+                })
+            }
+            TraceEventKind::ChannelReceive => {
+                let (executor_id, task_id) = task_scope()?;
+                Ok(TraceItemType::ChannelReceive {
+                    executor_id,
+                    task_id,
+                    channel_id: object_id()?,
+                })
+            }
+            TraceEventKind::TimerSet => {
+                let (executor_id, task_id) = task_scope()?;
+                Ok(TraceItemType::TimerSet {
+                    executor_id,
+                    task_id,
+                    timer_id: object_id()?,
+                    expires_at_us: payload
+                        .get(3)
+                        .and_then(|us| us.trim().parse().ok())
+                        .ok_or(TraceParseError::InvalidEventPayload)?,
+                })
+            }
+            // The alarm fires outside of any executor
+            TraceEventKind::TimerFire => Ok(TraceItemType::TimerFire {
+                timer_id: payload[0]
+                    .trim()
+                    .parse()
+                    .map_err(|_| TraceParseError::InvalidEventPayload)?,
+            }),
         }
     }
 
-    /// Format: "<EventType>, <executor_id>, <task_id?>"
+    /// Format: "<EventType>, <executor_id>, <task_id?>" (event names of `TRACE_PROTOCOL`)
     pub fn from_str(str: &str) -> Result<Self, TraceParseError> {
         // Split by comma
         let parts: Vec<&str> = str.split(',').collect();
-        Self::from_parts(&parts)
+        TRACE_PROTOCOL.read().unwrap().parse_event(&parts)
     }
 }
 
@@ -128,6 +484,9 @@ pub struct TraceItem {
 
     pub core_id: u32,
 
+    /// Device the line came from (`PRIMARY_DEVICE_ID` unless traced with `--device`)
+    pub device_id: u32,
+
     /// The actual trace data
     pub data: TraceItemType,
 }
@@ -137,17 +496,116 @@ impl TraceItem {
         TraceItem {
             time_pair,
             core_id,
+            device_id: PRIMARY_DEVICE_ID,
             data,
         }
     }
 
     /// Format: [<timestamp>, <core_id>, <EventType>, <executor_id>, <task_id?>]
-    pub fn parse_from_line(line: &str, pc_timestamp: ComputerTime) -> Result<Self, TraceParseError> {
+    /// (event names of `TRACE_PROTOCOL`)
+    pub fn parse_from_line(
+        line: &str,
+        pc_timestamp: ComputerTime,
+    ) -> Result<Self, TraceParseError> {
         // remove anything before and after the brackets (including brackets)
         let start = line.find('[').ok_or(TraceParseError::InvalidFormat)? + 1;
         let end = line.find(']').ok_or(TraceParseError::InvalidFormat)?;
-        let content = &line[start..end];
+        TRACE_PROTOCOL
+            .read()
+            .unwrap()
+            .parse_record(&line[start..end], pc_timestamp)
+    }
+
+    /// Parse every record of the line with `TRACE_PROTOCOL` (see `TraceProtocol::parse_line`)
+    pub fn parse_all_from_line(line: &str, pc_timestamp: ComputerTime) -> Vec<TraceParseResult> {
+        TRACE_PROTOCOL
+            .read()
+            .unwrap()
+            .parse_line(line, pc_timestamp)
+    }
+}
+
+impl TraceProtocol {
+    /// Use `name` for the event in trace lines (instead of its previous name)
+    pub fn rename_event(&mut self, kind: TraceEventKind, name: &str) {
+        self.event_kinds.retain(|_, k| *k != kind);
+        self.event_kinds.insert(name.to_string(), kind);
+    }
+
+    /// Read the timestamps of the following records as declared by the header
+    pub fn apply_header(&mut self, header: &TraceHeader) {
+        self.tick_hz = header.tick_hz;
+    }
+
+    /// Firmware time of a record timestamp (in ticks of `tick_hz`)
+    pub fn timestamp(&self, ticks: u64) -> EmbassyTime {
+        if self.tick_hz == DEFAULT_TICK_HZ {
+            return EmbassyTime::from_micros(ticks);
+        }
+        EmbassyTime::from_micros((ticks as u128 * 1_000_000 / self.tick_hz as u128) as u64)
+    }
+
+    /// Name of the event in trace lines
+    pub fn event_name(&self, kind: TraceEventKind) -> &str {
+        self.event_kinds
+            .iter()
+            .find(|(_, k)| **k == kind)
+            .map_or(kind.default_name(), |(name, _)| name.as_str())
+    }
 
+    /// Check if the line carries trace records (contains both markers)
+    pub fn is_trace_line(&self, line: &str) -> bool {
+        line.contains(&self.prefix) && line.contains(&self.suffix)
+    }
+
+    /// Part of the line between the markers (if present)
+    pub fn strip_markers<'a>(&self, line: &'a str) -> &'a str {
+        let line = match line.find(&self.prefix) {
+            Some(pos) => &line[pos + self.prefix.len()..],
+            None => line,
+        };
+        match line.rfind(&self.suffix) {
+            Some(pos) => &line[..pos],
+            None => line,
+        }
+    }
+
+    /// Parse every `[...]` record between the trace line markers (several events may be
+    /// batched on one line). A malformed record yields an error without affecting the
+    /// records around it.
+    pub fn parse_line(&self, line: &str, pc_timestamp: ComputerTime) -> Vec<TraceParseResult> {
+        let line = self.strip_markers(line);
+
+        let mut results = Vec::new();
+        let mut rest = line;
+        while let Some(start) = rest.find('[') {
+            rest = &rest[start + 1..];
+
+            // Record ends at the next ']', unless another record starts before (unterminated)
+            let end = rest.find(']');
+            let next_start = rest.find('[');
+            match (end, next_start) {
+                (Some(end), next_start) if next_start.is_none_or(|next| end < next) => {
+                    results.push(self.parse_record(&rest[..end], pc_timestamp));
+                    rest = &rest[end + 1..];
+                }
+                _ => results.push(Err(TraceParseError::InvalidFormat)),
+            }
+        }
+
+        // A trace line without any record is malformed as a whole
+        if results.is_empty() {
+            results.push(Err(TraceParseError::InvalidFormat));
+        }
+        results
+    }
+
+    /// Parse the content of one record (without the brackets)
+    fn parse_record(
+        &self,
+        content: &str,
+        pc_timestamp: ComputerTime,
+    ) -> Result<TraceItem, TraceParseError> {
         // Split by comma
         let parts: Vec<&str> = content.split(',').map(|s| s.trim()).collect();
         if parts.len() < 4 {
@@ -155,10 +613,10 @@ impl TraceItem {
         }
 
         // Parse timestamp
-        let timestamp_micros: u64 = parts[0]
+        let timestamp_ticks: u64 = parts[0]
             .parse()
             .map_err(|_| TraceParseError::InvalidTimestamp)?;
-        let uc_timestamp = EmbassyTime::from_micros(timestamp_micros);
+        let uc_timestamp = self.timestamp(timestamp_ticks);
         let time_pair = TimePair::new(uc_timestamp, pc_timestamp);
 
         // Parse core_id
@@ -167,9 +625,22 @@ impl TraceItem {
             .map_err(|_| TraceParseError::InvalidCoreId)?;
 
         // Parse trace item type
-        let data = TraceItemType::from_parts(&parts[2..])?;
+        let data = self.parse_event(&parts[2..])?;
         Ok(TraceItem::new(time_pair, core_id, data))
     }
+
+    /// Format: <EventName>, <payload>...
+    fn parse_event(&self, parts: &[&str]) -> Result<TraceItemType, TraceParseError> {
+        if parts.len() < 2 {
+            return Err(TraceParseError::InvalidFormat);
+        }
+
+        let kind = self
+            .event_kinds
+            .get(parts[0].trim())
+            .ok_or(TraceParseError::InvalidEventType)?;
+        TraceItemType::from_parts(*kind, &parts[1..])
+    }
 }
 
 #[cfg(test)]
@@ -185,7 +656,7 @@ mod tests {
         let pc_timestamp = ComputerTime::now();
 
         let line = "[123456, 17, TaskNew, 1, 42]";
-        let trace_item = TraceItem::parse_from_line(line, pc_timestamp.clone()).unwrap();
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
 
         assert_eq!(
             trace_item.time_pair.get_uc_timestamp(),
@@ -222,7 +693,7 @@ mod tests {
         let line = "[123456, 17, UnknownEvent, 1, 42]";
         let result = TraceItem::parse_from_line(line, pc_timestamp);
         assert!(matches!(result, Err(TraceParseError::InvalidEventType)));
-        
+
         let line = "[123456, 17, TaskNew, invalid_executor_id, 42]";
         let result = TraceItem::parse_from_line(line, pc_timestamp);
         assert!(matches!(result, Err(TraceParseError::InvalidExecutorId)));
@@ -252,4 +723,294 @@ mod tests {
             _ => panic!("Expected TaskExecBegin variant"),
         }
     }
+
+    #[test]
+    fn test_topology_parsing() {
+        let pc_timestamp = ComputerTime::now();
+
+        let line = "[12, 0, Topology, 2]";
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
+        assert_eq!(trace_item.data, TraceItemType::Topology { core_count: 2 });
+        assert_eq!(trace_item.data.get_executor_id(), None);
+        assert_eq!(trace_item.data.get_task_id(), None);
+
+        let line = "[12, 0, Topology, two]";
+        let result = TraceItem::parse_from_line(line, pc_timestamp);
+        assert!(matches!(result, Err(TraceParseError::InvalidEventPayload)));
+    }
+
+    #[test]
+    fn test_firmware_id_parsing() {
+        let pc_timestamp = ComputerTime::now();
+
+        let line = "embassy executor tracer - [5, 0, FirmwareId, v1.4.0-3-g1a2b3c4] - embassy executor tracer";
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
+        assert_eq!(
+            trace_item.data,
+            TraceItemType::FirmwareId {
+                id: String::from("v1.4.0-3-g1a2b3c4")
+            }
+        );
+        assert_eq!(trace_item.data.get_executor_id(), None);
+
+        // commas are part of the id
+        let trace_item =
+            TraceItem::parse_from_line("[5, 0, FirmwareId, app,release]", pc_timestamp).unwrap();
+        assert_eq!(
+            trace_item.data,
+            TraceItemType::FirmwareId {
+                id: String::from("app,release")
+            }
+        );
+
+        let result = TraceItem::parse_from_line("[5, 0, FirmwareId, ]", pc_timestamp);
+        assert!(matches!(result, Err(TraceParseError::InvalidEventPayload)));
+    }
+
+    #[test]
+    fn test_schema_version_parsing() {
+        let pc_timestamp = ComputerTime::now();
+
+        let line = "embassy executor tracer - [5, 0, SchemaVersion, 1] - embassy executor tracer";
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
+        assert_eq!(trace_item.data, TraceItemType::SchemaVersion { version: 1 });
+        assert_eq!(trace_item.data.get_executor_id(), None);
+
+        let result = TraceItem::parse_from_line("[5, 0, SchemaVersion, v1]", pc_timestamp);
+        assert!(matches!(result, Err(TraceParseError::InvalidEventPayload)));
+    }
+
+    #[test]
+    fn test_heartbeat_parsing() {
+        let pc_timestamp = ComputerTime::now();
+
+        let line = "embassy executor tracer - [3000, 0, Heartbeat, 1000] - embassy executor tracer";
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
+        assert_eq!(
+            trace_item.data,
+            TraceItemType::Heartbeat { interval_ms: 1000 }
+        );
+        assert_eq!(trace_item.data.get_executor_id(), None);
+        assert_eq!(
+            trace_item.time_pair.get_uc_timestamp(),
+            EmbassyTime::from_millis(3)
+        );
+
+        let result = TraceItem::parse_from_line("[3000, 0, Heartbeat, often]", pc_timestamp);
+        assert!(matches!(result, Err(TraceParseError::InvalidEventPayload)));
+    }
+
+    #[test]
+    fn test_timer_event_parsing() {
+        let pc_timestamp = ComputerTime::now();
+
+        let line = "embassy executor tracer - [1000, 0, TimerSet, 1, 7, 3, 11000] - embassy executor tracer";
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
+        assert_eq!(
+            trace_item.data,
+            TraceItemType::TimerSet {
+                executor_id: 1,
+                task_id: 7,
+                timer_id: 3,
+                expires_at_us: 11000
+            }
+        );
+        assert_eq!(trace_item.data.get_executor_id(), Some(1));
+        assert_eq!(trace_item.data.get_task_id(), Some(7));
+
+        // fired from the alarm interrupt, outside of any executor
+        let trace_item =
+            TraceItem::parse_from_line("[11002, 0, TimerFire, 3]", pc_timestamp).unwrap();
+        assert_eq!(trace_item.data, TraceItemType::TimerFire { timer_id: 3 });
+        assert_eq!(trace_item.data.get_executor_id(), None);
+
+        for line in [
+            "[1000, 0, TimerSet, 1, 7, 3]",
+            "[1000, 0, TimerSet, 1, 7, 3, soon]",
+            "[11002, 0, TimerFire, alarm]",
+        ] {
+            let result = TraceItem::parse_from_line(line, pc_timestamp);
+            assert!(matches!(result, Err(TraceParseError::InvalidEventPayload)));
+        }
+    }
+
+    #[test]
+    fn test_channel_event_parsing() {
+        let pc_timestamp = ComputerTime::now();
+
+        let line = "embassy executor tracer - [40, 0, ChannelSend, 1, 7, 536870912] - embassy executor tracer";
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
+        assert_eq!(
+            trace_item.data,
+            TraceItemType::ChannelSend {
+                executor_id: 1,
+                task_id: 7,
+                channel_id: 536870912
+            }
+        );
+        assert_eq!(trace_item.data.get_executor_id(), Some(1));
+        assert_eq!(trace_item.data.get_task_id(), Some(7));
+
+        let trace_item =
+            TraceItem::parse_from_line("[41, 0, ChannelReceive, 1, 8, 536870912]", pc_timestamp)
+                .unwrap();
+        assert_eq!(
+            trace_item.data,
+            TraceItemType::ChannelReceive {
+                executor_id: 1,
+                task_id: 8,
+                channel_id: 536870912
+            }
+        );
+
+        // the channel is required
+        for line in [
+            "[42, 0, ChannelSend, 1, 7]",
+            "[42, 0, ChannelReceive, 1, 8, channel]",
+        ] {
+            let result = TraceItem::parse_from_line(line, pc_timestamp);
+            assert!(matches!(result, Err(TraceParseError::InvalidEventPayload)));
+        }
+    }
+
+    #[test]
+    fn test_task_stack_usage_parsing() {
+        let pc_timestamp = ComputerTime::now();
+
+        let line = "embassy executor tracer - [50, 1, TaskStackUsage, 3, 42, 1536] - embassy executor tracer";
+        let trace_item = TraceItem::parse_from_line(line, pc_timestamp).unwrap();
+        assert_eq!(
+            trace_item.data,
+            TraceItemType::TaskStackUsage {
+                executor_id: 3,
+                task_id: 42,
+                high_water_bytes: 1536
+            }
+        );
+        assert_eq!(trace_item.data.get_executor_id(), Some(3));
+        assert_eq!(trace_item.data.get_task_id(), Some(42));
+
+        // missing or invalid byte count
+        for line in [
+            "[50, 1, TaskStackUsage, 3, 42]",
+            "[50, 1, TaskStackUsage, 3, 42, -1]",
+            "[50, 1, TaskStackUsage, 3, 42, lots]",
+        ] {
+            let result = TraceItem::parse_from_line(line, pc_timestamp);
+            assert!(matches!(result, Err(TraceParseError::InvalidEventPayload)));
+        }
+    }
+
+    /// (uC timestamp in us, or the parse error) of every record of the line
+    fn parse_all(line: &str) -> Vec<Result<u128, TraceParseError>> {
+        TraceItem::parse_all_from_line(line, ComputerTime::now())
+            .into_iter()
+            .map(|r| r.map(|item| item.time_pair.get_uc_timestamp().as_duration().as_micros()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_batched_records() {
+        // single record (same as parse_from_line)
+        let line = "embassy executor tracer - [10, 0, TaskNew, 1, 42] - embassy executor tracer";
+        assert_eq!(parse_all(line), vec![Ok(10)]);
+
+        let line = "embassy executor tracer - [10, 0, TaskNew, 1, 42] [20, 0, TaskReadyBegin, 1, 42] - embassy executor tracer";
+        assert_eq!(parse_all(line), vec![Ok(10), Ok(20)]);
+
+        let line = "embassy executor tracer - [10, 0, TaskNew, 1, 42][20, 0, TaskReadyBegin, 1, 42][30, 0, TaskExecBegin, 1, 42] - embassy executor tracer";
+        assert_eq!(parse_all(line), vec![Ok(10), Ok(20), Ok(30)]);
+    }
+
+    #[test]
+    fn test_parse_batched_records_malformed() {
+        // malformed record in the middle does not affect its neighbours
+        let line = "embassy executor tracer - [10, 0, TaskNew, 1, 42] [x, 0, TaskNew, 1, 43] [30, 0, TaskNew, 1, 44] - embassy executor tracer";
+        assert_eq!(
+            parse_all(line),
+            vec![Ok(10), Err(TraceParseError::InvalidTimestamp), Ok(30)]
+        );
+
+        // unterminated record in the middle
+        let line = "embassy executor tracer - [10, 0, TaskNew, 1, 42] [20, 0, TaskNew [30, 0, TaskNew, 1, 44] - embassy executor tracer";
+        assert_eq!(
+            parse_all(line),
+            vec![Ok(10), Err(TraceParseError::InvalidFormat), Ok(30)]
+        );
+
+        // unterminated last record and line without any record
+        assert_eq!(
+            parse_all("[10, 0, TaskNew, 1, 42] [20, 0"),
+            vec![Ok(10), Err(TraceParseError::InvalidFormat)]
+        );
+        assert_eq!(
+            parse_all("embassy executor tracer - - embassy executor tracer"),
+            vec![Err(TraceParseError::InvalidFormat)]
+        );
+    }
+
+    #[test]
+    fn test_alternate_runtime_protocol() {
+        let mut protocol = TraceProtocol {
+            prefix: String::from("<<rtic-trace "),
+            suffix: String::from(" >>"),
+            ..TraceProtocol::default()
+        };
+        protocol.rename_event(TraceEventKind::ExecutorPollStart, "dispatch_start");
+        protocol.rename_event(TraceEventKind::ExecutorIdle, "dispatch_idle");
+        protocol.rename_event(TraceEventKind::TaskReadyBegin, "pend");
+        protocol.rename_event(TraceEventKind::TaskExecBegin, "enter");
+        protocol.rename_event(TraceEventKind::TaskExecEnd, "exit");
+        assert_eq!(protocol.event_name(TraceEventKind::TaskExecBegin), "enter");
+        assert_eq!(protocol.event_name(TraceEventKind::TaskNew), "TaskNew");
+
+        let stream = [
+            "INFO booting",
+            "<<rtic-trace [10, 0, TaskNew, 3, 7] >>",
+            "<<rtic-trace [20, 0, pend, 3, 7][21, 0, dispatch_start, 3] >>",
+            "<<rtic-trace [22, 0, enter, 3, 7] [30, 0, exit, 3, 7] [31, 0, dispatch_idle, 3] >>",
+            // beacon lines are not trace lines of this protocol
+            "embassy executor tracer - [40, 0, TaskNew, 1, 42] - embassy executor tracer",
+        ];
+
+        let items: Vec<TraceItemType> = stream
+            .iter()
+            .filter(|line| protocol.is_trace_line(line))
+            .flat_map(|line| protocol.parse_line(line, ComputerTime::now()))
+            .map(|result| result.unwrap().data)
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                TraceItemType::TaskNew {
+                    executor_id: 3,
+                    task_id: 7
+                },
+                TraceItemType::TaskReadyBegin {
+                    executor_id: 3,
+                    task_id: 7
+                },
+                TraceItemType::ExecutorPollStart { executor_id: 3 },
+                TraceItemType::TaskExecBegin {
+                    executor_id: 3,
+                    task_id: 7
+                },
+                TraceItemType::TaskExecEnd {
+                    executor_id: 3,
+                    task_id: 7
+                },
+                TraceItemType::ExecutorIdle { executor_id: 3 },
+            ]
+        );
+
+        // renamed events are no longer known by their beacon name
+        let results = protocol.parse_line(
+            "<<rtic-trace [50, 0, TaskExecBegin, 3, 7] >>",
+            ComputerTime::now(),
+        );
+        assert!(matches!(
+            results.as_slice(),
+            [Err(TraceParseError::InvalidEventType)]
+        ));
+    }
 }
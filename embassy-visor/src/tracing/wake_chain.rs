@@ -0,0 +1,291 @@
+//! Chain of wakeups that led to the latest wakeup of a task (the async equivalent of a critical
+//! path). The beacon does not report who wakes a task, so the wakeups are inferred from the
+//! channel events (beacon's `channel-trace` feature): a task that receives from a channel and
+//! becomes ready after another task sent to the channel was woken by that send. Wakeups by
+//! timers, interrupts or other primitives are not seen, the chain ends there.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::tracing::{
+    channels::channel_name, executor::ExecutorTraceInfo, time::EmbassyTime,
+    trace_data::TraceItemType,
+};
+
+/// Inferred wakeups kept (besides the retention window)
+const MAX_WAKE_EDGES: usize = 4096;
+
+/// Links of a chain at most (a longer chain is cut at its start)
+const MAX_CHAIN_LINKS: usize = 16;
+
+/// Executor and task id
+type TaskKey = (u32, u32);
+
+/// Wakeup of a task by a send of another task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WakeEdge {
+    channel_id: u32,
+    waker: TaskKey,
+    wakee: TaskKey,
+    sent_at: EmbassyTime,
+    woken_at: EmbassyTime,
+}
+
+/// Wakeups inferred from the channel events, within the retention window
+#[derive(Debug, Clone, Default)]
+pub struct WakeGraph {
+    /// Tasks receiving from each channel (by channel id)
+    receivers: BTreeMap<u32, BTreeSet<TaskKey>>,
+    /// First send to each channel that did not wake a receiver yet
+    pending_sends: BTreeMap<u32, (TaskKey, EmbassyTime)>,
+    /// Oldest first
+    edges: VecDeque<WakeEdge>,
+}
+
+/// One wakeup of the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WakeLink {
+    /// Task that sent to the channel
+    pub waker: String,
+    /// Task woken by the send
+    pub wakee: String,
+    /// Name of the channel's static (from the ELF file), else its id
+    pub channel: String,
+    pub sent_at: EmbassyTime,
+    pub woken_at: EmbassyTime,
+}
+
+impl WakeLink {
+    /// Time from the send to the wakeup
+    pub fn latency(&self) -> EmbassyTime {
+        self.woken_at.saturating_sub(self.sent_at)
+    }
+}
+
+/// Wakeups leading to the latest wakeup of a task, the earliest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WakeChain {
+    pub links: Vec<WakeLink>,
+}
+
+impl WakeChain {
+    /// Time from the first send of the chain to the wakeup of the task
+    pub fn total_latency(&self) -> EmbassyTime {
+        match (self.links.first(), self.links.last()) {
+            (Some(first), Some(last)) => last.woken_at.saturating_sub(first.sent_at),
+            _ => EmbassyTime::ZERO,
+        }
+    }
+}
+
+impl WakeGraph {
+    /// Record a channel event or task wakeup (other trace items are ignored). Wakeups older
+    /// than `retention` before the item are dropped.
+    pub fn record(&mut self, time: EmbassyTime, data: &TraceItemType, retention: EmbassyTime) {
+        match *data {
+            TraceItemType::ChannelSend {
+                executor_id,
+                task_id,
+                channel_id,
+            } => {
+                self.pending_sends
+                    .entry(channel_id)
+                    .or_insert(((executor_id, task_id), time));
+            }
+            TraceItemType::ChannelReceive {
+                executor_id,
+                task_id,
+                channel_id,
+            } => {
+                self.receivers
+                    .entry(channel_id)
+                    .or_default()
+                    .insert((executor_id, task_id));
+            }
+            TraceItemType::TaskReadyBegin {
+                executor_id,
+                task_id,
+            } => self.task_woken((executor_id, task_id), time),
+            _ => return,
+        }
+
+        while self
+            .edges
+            .front()
+            .is_some_and(|edge| time.saturating_sub(edge.woken_at) > retention)
+        {
+            self.edges.pop_front();
+        }
+    }
+
+    /// The task was woken: the earliest pending send to a channel it receives from woke it
+    fn task_woken(&mut self, task: TaskKey, time: EmbassyTime) {
+        let Some((channel_id, (waker, sent_at))) = self
+            .pending_sends
+            .iter()
+            .filter(|(channel_id, (waker, _))| {
+                *waker != task
+                    && self
+                        .receivers
+                        .get(channel_id)
+                        .is_some_and(|receivers| receivers.contains(&task))
+            })
+            .min_by_key(|(_, (_, sent_at))| *sent_at)
+            .map(|(&channel_id, &send)| (channel_id, send))
+        else {
+            return;
+        };
+
+        self.pending_sends.remove(&channel_id);
+        self.edges.push_back(WakeEdge {
+            channel_id,
+            waker,
+            wakee: task,
+            sent_at,
+            woken_at: time,
+        });
+        if self.edges.len() > MAX_WAKE_EDGES {
+            self.edges.pop_front();
+        }
+    }
+
+    /// Forget all wakeups (e.g. the ids of a new boot belong to different tasks)
+    pub fn clear(&mut self) {
+        self.receivers.clear();
+        self.pending_sends.clear();
+        self.edges.clear();
+    }
+
+    /// Walk back from the latest wakeup of the task: the waker's own latest wakeup before it
+    /// sent, and so on until a wakeup has no known cause (or a task repeats). Empty if no
+    /// wakeup of the task is known.
+    fn edges_to(&self, task: TaskKey) -> Vec<WakeEdge> {
+        let mut chain = Vec::new();
+        let mut visited = BTreeSet::from([task]);
+        let mut next = self.edges.iter().rev().find(|edge| edge.wakee == task);
+        while let Some(edge) = next {
+            chain.push(*edge);
+            if chain.len() == MAX_CHAIN_LINKS || !visited.insert(edge.waker) {
+                break;
+            }
+            next = self
+                .edges
+                .iter()
+                .rev()
+                .find(|e| e.wakee == edge.waker && e.woken_at <= edge.sent_at);
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Chain of wakeups leading to the latest wakeup of the task (None if it is not known to
+    /// be woken by another task). Tasks are named after the executors' tasks.
+    pub fn chain(
+        &self,
+        executor_id: u32,
+        task_id: u32,
+        executors: &[ExecutorTraceInfo],
+    ) -> Option<WakeChain> {
+        let task_name = |(executor_id, task_id): TaskKey| {
+            executors
+                .iter()
+                .filter(|e| e.get_executor_id() == executor_id)
+                .find_map(|e| e.find_task_by_id(task_id))
+                .map_or_else(
+                    || format!("Task 0x{:X}", task_id),
+                    |t| t.get_task_display_name(),
+                )
+        };
+
+        let edges = self.edges_to((executor_id, task_id));
+        if edges.is_empty() {
+            return None;
+        }
+        Some(WakeChain {
+            links: edges
+                .into_iter()
+                .map(|edge| WakeLink {
+                    waker: task_name(edge.waker),
+                    wakee: task_name(edge.wakee),
+                    channel: channel_name(edge.channel_id),
+                    sent_at: edge.sent_at,
+                    woken_at: edge.woken_at,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(task_id: u32, channel_id: u32) -> TraceItemType {
+        TraceItemType::ChannelSend {
+            executor_id: 1,
+            task_id,
+            channel_id,
+        }
+    }
+
+    fn receive(task_id: u32, channel_id: u32) -> TraceItemType {
+        TraceItemType::ChannelReceive {
+            executor_id: 1,
+            task_id,
+            channel_id,
+        }
+    }
+
+    fn ready(task_id: u32) -> TraceItemType {
+        TraceItemType::TaskReadyBegin {
+            executor_id: 1,
+            task_id,
+        }
+    }
+
+    #[test]
+    fn test_wake_chain() {
+        let us = EmbassyTime::from_micros;
+        let retention = EmbassyTime::from_millis(30_000);
+        let mut graph = WakeGraph::default();
+
+        // consumers 8 (channel 0x20) and 9 (channel 0x30) registered by an earlier receive
+        for (time, data) in [(100, receive(8, 0x20)), (200, receive(9, 0x30))] {
+            graph.record(us(time), &data, retention);
+        }
+
+        // 7 (woken by an interrupt) sends to 8, 8 forwards to 9
+        for (time, data) in [
+            (1_000, ready(7)),
+            (1_100, send(7, 0x20)),
+            (1_150, ready(8)),
+            (1_180, receive(8, 0x20)),
+            (1_400, send(8, 0x30)),
+            (1_700, ready(9)),
+            (1_720, receive(9, 0x30)),
+            // unrelated later wakeup of 8 (nothing pending): no cause known
+            (2_000, ready(8)),
+        ] {
+            graph.record(us(time), &data, retention);
+        }
+
+        let chain = graph.chain(1, 9, &[]).unwrap();
+        let path: Vec<(&str, &str)> = chain
+            .links
+            .iter()
+            .map(|link| (link.waker.as_str(), link.wakee.as_str()))
+            .collect();
+        assert_eq!(path, [("Task 0x7", "Task 0x8"), ("Task 0x8", "Task 0x9")]);
+        assert_eq!(chain.links[0].channel, "Channel 0x20");
+        assert_eq!(chain.links[0].latency(), us(50));
+        assert_eq!(chain.links[1].latency(), us(300));
+        // first send at 1.1ms to the wakeup of 9 at 1.7ms
+        assert_eq!(chain.total_latency(), us(600));
+
+        // the first task of the chain has no known waker
+        assert!(graph.chain(1, 7, &[]).is_none());
+
+        // wakeups out of the retention window are dropped
+        graph.record(us(40_000_000), &ready(7), retention);
+        assert!(graph.chain(1, 9, &[]).is_none());
+    }
+}
@@ -0,0 +1,186 @@
+//! Warnings acknowledged by the user ('A' on the selected task), e.g. of a task busy looping on
+//! purpose. An acknowledged warning loses its emphasis while it persists. Once the task or
+//! executor recovers, or shows a new warning (like further integrity issues), it draws
+//! attention again.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    tracing::stats::instance_stats::InstanceStats,
+    visualizer::views::executor_view::BUDGET_OVERLOAD_PERCENT,
+};
+
+/// Task or executor (by id) with warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WarningEntity {
+    Task(u32),
+    Executor(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Warning {
+    LostWakeup,
+    /// Integrity issues so far (more issues are a new warning)
+    IntegrityIssues(usize),
+    PingPong,
+    Overload,
+    Pegged,
+    TaskCountGrowing,
+}
+
+/// Current warnings of the tasks and executors
+pub fn active_warnings(stats: &InstanceStats) -> BTreeMap<WarningEntity, BTreeSet<Warning>> {
+    let mut warnings: BTreeMap<WarningEntity, BTreeSet<Warning>> = BTreeMap::new();
+    for executor in stats.core_stats.iter().flat_map(|c| c.executors.iter()) {
+        let executor_entity = WarningEntity::Executor(executor.executor_id);
+        if executor
+            .budget_utilization_percent
+            .is_some_and(|budget| budget >= BUDGET_OVERLOAD_PERCENT)
+        {
+            warnings
+                .entry(executor_entity)
+                .or_default()
+                .insert(Warning::Overload);
+        }
+        if executor.pegged {
+            warnings
+                .entry(executor_entity)
+                .or_default()
+                .insert(Warning::Pegged);
+        }
+        if executor.task_count_growing {
+            warnings
+                .entry(executor_entity)
+                .or_default()
+                .insert(Warning::TaskCountGrowing);
+        }
+        if let Some(run) = executor.ping_pong {
+            for task_id in [run.task_ids.0, run.task_ids.1] {
+                warnings
+                    .entry(WarningEntity::Task(task_id))
+                    .or_default()
+                    .insert(Warning::PingPong);
+            }
+        }
+
+        for task in executor.tasks.iter() {
+            let task_entity = WarningEntity::Task(task.task_id);
+            if task.lost_wakeup {
+                warnings
+                    .entry(task_entity)
+                    .or_default()
+                    .insert(Warning::LostWakeup);
+            }
+            if task.integrity.total() > 0 {
+                warnings
+                    .entry(task_entity)
+                    .or_default()
+                    .insert(Warning::IntegrityIssues(task.integrity.total()));
+            }
+        }
+    }
+    warnings
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Acknowledgments {
+    acknowledged: BTreeMap<WarningEntity, BTreeSet<Warning>>,
+    active: BTreeMap<WarningEntity, BTreeSet<Warning>>,
+}
+
+impl Acknowledgments {
+    /// Take the warnings of new statistics. Acknowledged warnings that are gone are forgotten,
+    /// so they are emphasized again when they come back.
+    pub fn update(&mut self, active: BTreeMap<WarningEntity, BTreeSet<Warning>>) {
+        self.acknowledged.retain(|entity, acknowledged| {
+            acknowledged.retain(|w| active.get(entity).is_some_and(|a| a.contains(w)));
+            !acknowledged.is_empty()
+        });
+        self.active = active;
+    }
+
+    /// Acknowledge the current warnings of the entity (false if it has none)
+    pub fn acknowledge(&mut self, entity: WarningEntity) -> bool {
+        let Some(active) = self.active.get(&entity) else {
+            return false;
+        };
+        self.acknowledged
+            .entry(entity)
+            .or_default()
+            .extend(active.iter().copied());
+        true
+    }
+
+    /// Entity has warnings, all of them acknowledged
+    pub fn is_acknowledged(&self, entity: WarningEntity) -> bool {
+        self.active.get(&entity).is_some_and(|active| {
+            self.acknowledged
+                .get(&entity)
+                .is_some_and(|acknowledged| active.is_subset(acknowledged))
+        })
+    }
+
+    /// Mark the tasks and executors whose warnings are all acknowledged
+    pub fn mark(&self, stats: &mut InstanceStats) {
+        for executor in stats
+            .core_stats
+            .iter_mut()
+            .flat_map(|c| c.executors.iter_mut())
+        {
+            executor.warnings_acknowledged =
+                self.is_acknowledged(WarningEntity::Executor(executor.executor_id));
+            for task in executor.tasks.iter_mut() {
+                task.warning_acknowledged = self.is_acknowledged(WarningEntity::Task(task.task_id));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings_of(
+        entity: WarningEntity,
+        warnings: &[Warning],
+    ) -> BTreeMap<WarningEntity, BTreeSet<Warning>> {
+        BTreeMap::from([(entity, warnings.iter().copied().collect())])
+    }
+
+    #[test]
+    fn test_acknowledge_and_rearm() {
+        let executor = WarningEntity::Executor(0x1000);
+        let task = WarningEntity::Task(7);
+        let mut acknowledgments = Acknowledgments::default();
+
+        acknowledgments.update(warnings_of(executor, &[Warning::Pegged]));
+        assert!(
+            !acknowledgments.acknowledge(task),
+            "no warning to acknowledge"
+        );
+        assert!(acknowledgments.acknowledge(executor));
+        assert!(acknowledgments.is_acknowledged(executor));
+
+        // still pegged: stays acknowledged
+        acknowledgments.update(warnings_of(executor, &[Warning::Pegged]));
+        assert!(acknowledgments.is_acknowledged(executor));
+
+        // another warning on top draws attention again
+        acknowledgments.update(warnings_of(executor, &[Warning::Pegged, Warning::Overload]));
+        assert!(!acknowledgments.is_acknowledged(executor));
+        acknowledgments.acknowledge(executor);
+
+        // recovers, then pegged again: emphasized again
+        acknowledgments.update(BTreeMap::new());
+        assert!(!acknowledgments.is_acknowledged(executor));
+        acknowledgments.update(warnings_of(executor, &[Warning::Pegged]));
+        assert!(!acknowledgments.is_acknowledged(executor));
+
+        // further integrity issues are a new warning
+        acknowledgments.update(warnings_of(task, &[Warning::IntegrityIssues(2)]));
+        acknowledgments.acknowledge(task);
+        assert!(acknowledgments.is_acknowledged(task));
+        acknowledgments.update(warnings_of(task, &[Warning::IntegrityIssues(3)]));
+        assert!(!acknowledgments.is_acknowledged(task));
+    }
+}
@@ -1,8 +1,13 @@
 use std::{
-    collections::VecDeque,
-    io,
-    sync::atomic::{AtomicUsize, Ordering},
-    time::Duration,
+    cell::Cell,
+    collections::{BTreeSet, VecDeque},
+    fs::File,
+    io::{self, BufWriter},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use crossbeam::channel::{self, Receiver, Sender};
@@ -11,31 +16,196 @@ use ratatui::{
     DefaultTerminal, Frame,
     buffer::Buffer,
     layout::{Constraint, Layout, Margin, Rect},
-    style::Stylize,
+    style::{Color, Stylize},
     symbols::border,
-    text::Line,
-    widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Widget},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget,
+        Widget,
+    },
 };
 
 use crate::{
-    tracing::{instance::TracingInstance, stats::instance_stats::InstanceStats},
-    visualizer::{TuiAppEvent, recolor_defmt_messages, views::instance_view::InstanceView},
+    config_file::save_thresholds,
+    firmware_run::FirmwareStatus,
+    line_source::{FILE_SOURCE_STATUS, FileSourceStatus},
+    net_source::{NET_SOURCE_STATUS, NetSourceStatus},
+    replay::Replay,
+    throughput::{PARSE_ERROR_THRESHOLD_PERCENT, SOURCE_THROUGHPUT, ThroughputMeter},
+    tracing::{
+        history_export::write_ndjson,
+        instance::TracingInstance,
+        stats::{
+            cpu_basis::CPU_BASIS,
+            executor_stats::ExecutorStats,
+            heatmap::Heatmap,
+            instance_stats::InstanceStats,
+            task_stats::{ACTIVE_ONLY_CPU, TaskStats, cpu_window},
+        },
+        thresholds::THRESHOLDS,
+        time::{ComputerTime, EmbassyTime},
+        timeline::TimelineFilter,
+    },
+    visualizer::{
+        FirmwareReloadHandle, TuiAppEvent, ViewConfig,
+        acknowledgments::{Acknowledgments, WarningEntity, active_warnings},
+        describe_core_imbalance,
+        filter::{
+            HIDE_INSIGNIFICANT_TASKS, MIN_SIGNIFICANT_CPU_PERCENT, filter_instance_stats,
+            hide_insignificant_tasks,
+        },
+        format_byte_rate,
+        log_filter::{LogFilter, LogLine, filtered_log_indices},
+        log_scroll::{clamp_log_scroll, is_log_scrolled_to_end, max_log_scroll},
+        pinning::{pin_instance_tasks, pin_tasks_first},
+        recolor_defmt_messages,
+        running_task::running_task_to_select,
+        smoothing::CpuSmoother,
+        split_layout::{Pane, PaneAction, PaneView, SplitLayout, highlight_border},
+        state_changes::{StateChangeTracker, mark_state_changes},
+        strip_ansi_codes,
+        task_colors::TaskColors,
+        task_count_trend::TaskCountTrend,
+        time_travel::TimeTravel,
+        time_unit::{TIME_UNIT, format_uc_timestamp},
+        views::{
+            category_view::CategoryView,
+            channel_view::ChannelView,
+            diagnostics_view::{DiagnosticsView, health_color},
+            event_counts_view::EventCountsView,
+            heatmap_view::{HeatmapView, zoom_view_window},
+            instance_view::InstanceView,
+            merged_view::MergedView,
+            preemption_view::PreemptionView,
+            settings_view::SettingsView,
+            state_legend_view::StateLegendView,
+            task_detail_view::TaskDetailView,
+            timeline_view::TimelineView,
+            timer_view::TimerView,
+            wake_chain_view::WakeChainView,
+        },
+        warnings::{next_warning, warning_task_ids},
+    },
 };
 
 pub static MAX_LOG_LINES: AtomicUsize = AtomicUsize::new(100);
 
+/// Rows the log pane keeps when the dashboard does not fit into the terminal
+const MIN_LOG_PANE_HEIGHT: u16 = 6;
+
+/// Rows scrolled per 'PageUp'/'PageDown' in the dashboard
+const DASHBOARD_SCROLL_STEP: u16 = 5;
+
+/// Start with executors merged by name across cores (toggled with 'm')
+pub static MERGE_EXECUTORS_BY_NAME: AtomicBool = AtomicBool::new(false);
+
+/// Initial view window of the time based views in seconds (zoomed with '<' and '>')
+pub static VIEW_WINDOW_S: AtomicU64 = AtomicU64::new(10);
+
 #[derive(Debug)]
 pub struct App {
     exit: bool,
     instance_stats: InstanceStats,
-    log_lines: VecDeque<String>,
+    /// Smoothed CPU usage shown instead of the raw values of instance_stats
+    cpu_smoother: CpuSmoother,
+    /// Live task count per executor over the session (leak detection)
+    task_count_trend: TaskCountTrend,
+    /// Warnings acknowledged with 'A' (shown without emphasis until they recover)
+    acknowledgments: Acknowledgments,
+    /// Statistics shown in the view (instance_stats with task filter applied)
+    displayed_stats: InstanceStats,
+    /// Task name filter query (empty --> no filter)
+    task_filter: String,
+    /// Typed characters go into the task filter (started with '/')
+    task_filter_editing: bool,
+    /// Show executors merged by name across cores instead of per core
+    merge_executors: bool,
+    /// Merged executors of displayed_stats (only when merge_executors is set)
+    displayed_merged_executors: Vec<ExecutorStats>,
+    /// Tasks (by id) always shown at the top of their executor
+    pinned_tasks: BTreeSet<u32>,
+    /// Task selected with 'j'/'k' (target of pinning with 'p')
+    selected_task: Option<u32>,
+    /// Show the diagnostics panel (parse error histogram)
+    show_diagnostics: bool,
+    /// Show the received trace events per kind
+    show_event_counts: bool,
+    /// Show the legend of the task state badges
+    show_state_legend: bool,
+    /// Show the timeline of lifecycle events
+    show_timeline: bool,
+    /// Event kinds shown in the timeline
+    timeline_filter: TimelineFilter,
+    /// Timeline events scrolled back from the newest one (0 --> follow new events)
+    timeline_scroll: usize,
+    /// Show the matrix of preempted time between executors
+    show_preemptions: bool,
+    /// Show the statistics aggregated per task category
+    show_categories: bool,
+    /// Show the task activity heatmap
+    show_heatmap: bool,
+    /// Heatmap of the latest statistics (only sampled while shown)
+    heatmap: Option<Heatmap>,
+    /// Heatmap tasks scrolled out at the top
+    heatmap_scroll: usize,
+    /// Time buckets fitting into the heatmap (updated on every draw)
+    heatmap_bucket_count: Cell<usize>,
+    /// Most recent slice of the history shown by the heatmap (independent of the retention)
+    view_window: EmbassyTime,
+    /// Show the producer --> consumer graph of the channels
+    show_channels: bool,
+    /// Show the timers of the tasks
+    show_timers: bool,
+    /// Show the chain of wakeups that led to the latest wakeup of the selected task
+    show_wake_chain: bool,
+    /// Dashboard area split into two panes side by side (None --> dashboard only)
+    split: Option<SplitLayout>,
+    /// Show absolute uC timestamps (µs since boot) in the timeline and task details
+    absolute_time: bool,
+    /// Tasks hidden for their low CPU utilization with the latest statistics
+    hidden_task_count: usize,
+    /// Threshold selected in the settings overlay (None --> overlay closed)
+    settings_selected: Option<usize>,
+    /// Highlight the tasks whose state changed with the latest statistics
+    flash_state_changes: bool,
+    state_changes: StateChangeTracker,
+    /// Tasks whose state changed with the latest statistics
+    changed_tasks: BTreeSet<u32>,
+    log_lines: VecDeque<LogLine>,
+    /// Lines shown in the log pane (cycled with 'L')
+    log_filter: LogFilter,
+    /// Index of the first shown line among the lines passing the log filter
     log_scroll: u16,
+    /// Rows of the dashboard (cores/executors) scrolled out at the top
+    dashboard_scroll: u16,
+    /// Rows the dashboard can be scrolled (updated on every draw, 0 when it fits)
+    dashboard_max_scroll: Cell<u16>,
+    /// Area of the dashboard (updated on every draw)
+    dashboard_area: Cell<Rect>,
+    /// Incoming trace throughput (shown above the logs)
+    throughput_meter: ThroughputMeter,
+    /// Build state of the firmware (changes on reload with 'r')
+    firmware_status: FirmwareStatus,
+    /// Request a rebuild and restart of the firmware
+    reload_tx: Sender<()>,
+    /// Program lines, user markers ('M') are recorded into them
+    program_tx: Sender<String>,
+    /// Source of the raw state history (exported with 'w', time travel with 'g')
+    instance: TracingInstance,
+    /// Dashboard frozen at the time of a log line (None --> live)
+    time_travel: Option<TimeTravel>,
+    /// Recorded session stepped through with 'n' (None --> live)
+    replay: Option<Replay>,
 
     event_recver: Receiver<TuiAppEvent>,
 }
 
 impl App {
-    pub fn new(instance: TracingInstance, logs_recver: Receiver<String>) -> anyhow::Result<Self> {
+    pub fn new(
+        instance: TracingInstance,
+        logs_recver: Receiver<String>,
+        reload_handle: FirmwareReloadHandle,
+    ) -> anyhow::Result<Self> {
         // Start Event Listener
         let (event_sender, event_recver) = channel::unbounded();
         {
@@ -44,22 +214,80 @@ impl App {
         }
         {
             let event_sender = event_sender.clone();
+            let instance = instance.clone();
             let _ = std::thread::spawn(move || run_instance_stats_gatherer(event_sender, instance));
         }
         {
             let event_sender = event_sender.clone();
             let _ = std::thread::spawn(move || run_log_line_listener(event_sender, logs_recver));
         }
+        {
+            let event_sender = event_sender.clone();
+            let status_recver = reload_handle.status_recver;
+            let _ = std::thread::spawn(move || {
+                run_firmware_status_listener(event_sender, status_recver)
+            });
+        }
 
         Ok(Self {
             instance_stats: InstanceStats::default(),
+            cpu_smoother: CpuSmoother::default(),
+            task_count_trend: TaskCountTrend::default(),
+            acknowledgments: Acknowledgments::default(),
+            displayed_stats: InstanceStats::default(),
+            task_filter: String::new(),
+            task_filter_editing: false,
+            merge_executors: MERGE_EXECUTORS_BY_NAME.load(Ordering::Relaxed),
+            displayed_merged_executors: Vec::new(),
+            pinned_tasks: BTreeSet::new(),
+            selected_task: None,
+            show_diagnostics: false,
+            show_event_counts: false,
+            show_state_legend: true,
+            show_timeline: false,
+            timeline_filter: TimelineFilter::default(),
+            timeline_scroll: 0,
+            show_preemptions: false,
+            show_categories: false,
+            show_heatmap: false,
+            heatmap: None,
+            heatmap_scroll: 0,
+            heatmap_bucket_count: Cell::new(0),
+            view_window: EmbassyTime::from_millis(VIEW_WINDOW_S.load(Ordering::Relaxed) * 1000),
+            show_channels: false,
+            show_timers: false,
+            show_wake_chain: false,
+            split: None,
+            absolute_time: false,
+            hidden_task_count: 0,
+            settings_selected: None,
+            flash_state_changes: true,
+            state_changes: StateChangeTracker::default(),
+            changed_tasks: BTreeSet::new(),
             exit: false,
             log_lines: VecDeque::with_capacity(MAX_LOG_LINES.load(Ordering::Relaxed)),
             event_recver,
+            log_filter: LogFilter::default(),
             log_scroll: 0,
+            dashboard_scroll: 0,
+            dashboard_max_scroll: Cell::new(0),
+            dashboard_area: Cell::new(Rect::default()),
+            throughput_meter: ThroughputMeter::default(),
+            firmware_status: FirmwareStatus::Running,
+            reload_tx: reload_handle.reload_tx,
+            program_tx: reload_handle.program_tx,
+            instance,
+            time_travel: None,
+            replay: None,
         })
     }
 
+    /// Step through the recorded session instead of following the firmware (paused until 'n')
+    pub fn with_replay(mut self, replay: Replay) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
     /// runs the application's main loop until the user quits
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
         while !self.exit {
@@ -75,37 +303,712 @@ impl App {
 
     fn on_new_stats(&mut self, new_stats: InstanceStats) {
         self.instance_stats = new_stats;
+        self.cpu_smoother.update(&self.instance_stats);
+        self.task_count_trend
+            .update(&self.instance_stats, ComputerTime::now());
+        self.task_count_trend.apply(&mut self.instance_stats);
+        self.acknowledgments
+            .update(active_warnings(&self.instance_stats));
+        self.acknowledgments.mark(&mut self.instance_stats);
+        self.changed_tasks = self.state_changes.update(&self.instance_stats);
+        if self.heatmap_shown() {
+            self.refresh_heatmap();
+        }
+
+        // Forget tasks that disappeared (e.g. after a firmware reload)
+        let known_task_ids: BTreeSet<u32> = self
+            .instance_stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .flat_map(|e| e.tasks.iter())
+            .map(|t| t.task_id)
+            .collect();
+        self.pinned_tasks.retain(|id| known_task_ids.contains(id));
+        if let Some(selected) = self.selected_task
+            && !known_task_ids.contains(&selected)
+        {
+            self.selected_task = None;
+        }
+
+        self.refresh_displayed_stats();
     }
 
-    fn on_new_log_line(&mut self, new_line: String) {
-        self.log_lines.push_back(new_line);
+    /// Statistics the dashboard is based on: the time travel snapshot or the latest ones
+    fn source_stats(&self) -> &InstanceStats {
+        self.time_travel
+            .as_ref()
+            .and_then(|time_travel| time_travel.stats.as_ref())
+            .unwrap_or(&self.instance_stats)
+    }
+
+    /// Apply the task filter and CPU smoothing to the latest statistics (underlying stats
+    /// stay intact)
+    fn refresh_displayed_stats(&mut self) {
+        let source_stats = self.source_stats();
+        let mut displayed_stats = if self.task_filter.is_empty() {
+            source_stats.clone()
+        } else {
+            filter_instance_stats(source_stats, &self.task_filter)
+        };
+        if self.time_travel.is_none() {
+            self.cpu_smoother.apply(&mut displayed_stats);
+            if self.flash_state_changes {
+                mark_state_changes(&mut displayed_stats, &self.changed_tasks);
+            }
+        }
+        self.displayed_stats = displayed_stats;
+        pin_instance_tasks(&mut self.displayed_stats, &self.pinned_tasks);
+
+        self.hidden_task_count = if HIDE_INSIGNIFICANT_TASKS.load(Ordering::Relaxed) {
+            let kept: BTreeSet<u32> = self
+                .pinned_tasks
+                .iter()
+                .copied()
+                .chain(self.selected_task)
+                .collect();
+            hide_insignificant_tasks(
+                &mut self.displayed_stats,
+                *MIN_SIGNIFICANT_CPU_PERCENT.read().unwrap(),
+                &kept,
+            )
+        } else {
+            0
+        };
+
+        self.displayed_merged_executors = if self.merge_executors {
+            let mut merged = self.displayed_stats.executors_merged_by_name();
+            for executor in merged.iter_mut() {
+                pin_tasks_first(executor, &self.pinned_tasks);
+            }
+            merged
+        } else {
+            Vec::new()
+        };
+    }
+
+    /// Statistics of the selected task (if still displayed)
+    fn selected_task_stats(&self) -> Option<&TaskStats> {
+        let selected = self.selected_task?;
+        self.displayed_stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .flat_map(|e| e.tasks.iter())
+            .find(|t| t.task_id == selected)
+    }
+
+    /// Displayed executors in the order they are shown (per core or merged)
+    fn displayed_executors(&self) -> Vec<&ExecutorStats> {
+        if self.merge_executors {
+            self.displayed_merged_executors.iter().collect()
+        } else {
+            self.displayed_stats
+                .core_stats
+                .iter()
+                .flat_map(|c| c.executors.iter())
+                .collect()
+        }
+    }
+
+    /// Ids of the displayed tasks in the order they are shown
+    fn displayed_task_ids(&self) -> Vec<u32> {
+        self.displayed_executors()
+            .iter()
+            .flat_map(|e| e.tasks.iter())
+            .map(|t| t.task_id)
+            .collect()
+    }
+
+    /// Select the next (or previous) task with a warning
+    fn select_next_warning(&mut self, forward: bool) {
+        let warning_ids = warning_task_ids(&self.displayed_executors());
+        if let Some(task_id) = next_warning(
+            &self.displayed_task_ids(),
+            &warning_ids,
+            self.selected_task,
+            forward,
+        ) {
+            self.selected_task = Some(task_id);
+        }
+    }
+
+    /// Select the task running right now on the focused core and scroll it into view
+    fn select_running_task(&mut self) {
+        match running_task_to_select(&self.instance, &self.displayed_stats, self.selected_task) {
+            Ok(task_id) => {
+                self.selected_task = Some(task_id);
+                self.scroll_to_selected_task();
+            }
+            Err(note) => self.on_new_log_line(note),
+        }
+    }
+
+    /// Scroll the dashboard just enough to show the row of the selected task
+    fn scroll_to_selected_task(&mut self) {
+        let Some(selected) = self.selected_task else {
+            return;
+        };
+        let area = self.dashboard_area.get();
+        let view_config = self.view_config(area.width);
+        let row = if self.merge_executors {
+            MergedView(&self.displayed_merged_executors, view_config, 0).task_row(selected)
+        } else {
+            InstanceView(&self.displayed_stats, view_config, 0).task_row(selected)
+        };
+        let Some(row) = row else {
+            return;
+        };
+
+        let visible_rows = Block::bordered().inner(area).height.max(1);
+        self.dashboard_scroll = if row < self.dashboard_scroll {
+            row
+        } else if row >= self.dashboard_scroll + visible_rows {
+            row + 1 - visible_rows
+        } else {
+            self.dashboard_scroll
+        }
+        .min(self.dashboard_max_scroll.get());
+    }
+
+    /// Move the task selection up (-1) or down (+1) the displayed tasks
+    fn move_task_selection(&mut self, offset: isize) {
+        let task_ids = self.displayed_task_ids();
+        if task_ids.is_empty() {
+            self.selected_task = None;
+            return;
+        }
+
+        let index = match self
+            .selected_task
+            .and_then(|selected| task_ids.iter().position(|&id| id == selected))
+        {
+            Some(index) => index.saturating_add_signed(offset).min(task_ids.len() - 1),
+            None => 0,
+        };
+        self.selected_task = Some(task_ids[index]);
+    }
+
+    /// Route 'j'/'k' to the focused pane of the split (the task selection without a split)
+    fn move_in_focused_pane(&mut self, offset: isize) {
+        let action = match &self.split {
+            Some(split) => split.route(offset),
+            None => Some(PaneAction::SelectTask(offset)),
+        };
+        match action {
+            Some(PaneAction::SelectTask(offset)) => self.move_task_selection(offset),
+            Some(PaneAction::ScrollTimeline(offset)) if offset < 0 => self.scroll_timeline_back(),
+            Some(PaneAction::ScrollTimeline(_)) => {
+                self.timeline_scroll = self.timeline_scroll.saturating_sub(1)
+            }
+            Some(PaneAction::ScrollHeatmap(offset)) => self.scroll_heatmap(offset),
+            None => {}
+        }
+    }
+
+    /// Open or close the split of the dashboard area
+    fn toggle_split(&mut self) {
+        self.split = match self.split {
+            Some(_) => None,
+            None => Some(SplitLayout::default()),
+        };
+        if self.heatmap_shown() {
+            self.refresh_heatmap();
+        }
+    }
+
+    /// Show the next view in the focused pane of the split
+    fn cycle_focused_pane_view(&mut self) {
+        if let Some(split) = &mut self.split {
+            split.cycle_focused_view();
+        }
+        if self.heatmap_shown() {
+            self.refresh_heatmap();
+        }
+    }
+
+    /// View shown in a pane of the split
+    fn in_pane(&self, view: PaneView) -> bool {
+        self.split.is_some_and(|split| split.shows(view))
+    }
+
+    /// Heatmap shown below the dashboard or in a pane (only sampled then)
+    fn heatmap_shown(&self) -> bool {
+        self.show_heatmap || self.in_pane(PaneView::Heatmap)
+    }
+
+    /// Acknowledge the current warnings of the selected task and its executor
+    fn acknowledge_selected_warnings(&mut self) {
+        let Some(selected) = self.selected_task else {
+            self.on_new_log_line(String::from(
+                "Select a task (j/k) to acknowledge its warnings",
+            ));
+            return;
+        };
+        let executor_id = self
+            .instance_stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .find(|e| e.tasks.iter().any(|t| t.task_id == selected))
+            .map(|e| e.executor_id);
+
+        let task_acknowledged = self
+            .acknowledgments
+            .acknowledge(WarningEntity::Task(selected));
+        let executor_acknowledged = executor_id.is_some_and(|executor_id| {
+            self.acknowledgments
+                .acknowledge(WarningEntity::Executor(executor_id))
+        });
+        if !task_acknowledged && !executor_acknowledged {
+            self.on_new_log_line(String::from(
+                "No warning of the selected task or its executor to acknowledge",
+            ));
+        }
+        self.acknowledgments.mark(&mut self.instance_stats);
+        self.refresh_displayed_stats();
+    }
+
+    /// Pin or unpin the selected task
+    fn toggle_pin_selected_task(&mut self) {
+        if let Some(selected) = self.selected_task
+            && !self.pinned_tasks.remove(&selected)
+        {
+            self.pinned_tasks.insert(selected);
+        }
+        self.refresh_displayed_stats();
+    }
+
+    /// View settings of the frame including the task selection
+    fn view_config(&self, width: u16) -> ViewConfig {
+        ViewConfig {
+            selected_task_id: self.selected_task,
+            ..ViewConfig::new(width, &self.displayed_stats)
+        }
+    }
+
+    /// Height of the statistics view (per core or merged)
+    fn get_stats_view_height(&self, view_config: ViewConfig) -> u16 {
+        if self.merge_executors {
+            MergedView(&self.displayed_merged_executors, view_config, 0).get_min_height()
+        } else {
+            InstanceView(&self.displayed_stats, view_config, 0).get_min_height()
+        }
+    }
+
+    /// Handle key events while typing the task filter query
+    fn handle_task_filter_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => {
+                // clear filter and restore all tasks
+                self.task_filter.clear();
+                self.task_filter_editing = false;
+            }
+            KeyCode::Enter => self.task_filter_editing = false,
+            KeyCode::Backspace => {
+                self.task_filter.pop();
+            }
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.exit()
+            }
+            KeyCode::Char(c) => self.task_filter.push(c),
+            _ => {}
+        }
 
-        // Adjust scroll to stay at bottom if we were already at bottom
-        if self.log_scroll > self.log_lines.len().saturating_sub(5) as u16 {
-            self.log_scroll = self
-                .log_scroll
-                .saturating_add(1)
-                .min(self.log_lines.len().saturating_sub(3) as u16);
+        self.refresh_displayed_stats();
+    }
+
+    /// Handle key events while the settings overlay is open
+    fn handle_settings_key_event(&mut self, key_event: KeyEvent, selected: usize) {
+        let threshold = &THRESHOLDS[selected];
+        match key_event.code {
+            KeyCode::Char('s') | KeyCode::Esc => self.settings_selected = None,
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.settings_selected = Some((selected + 1) % THRESHOLDS.len())
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.settings_selected = Some((selected + THRESHOLDS.len() - 1) % THRESHOLDS.len())
+            }
+            KeyCode::Left | KeyCode::Char('-') => threshold.adjust(-1),
+            KeyCode::Right | KeyCode::Char('+') => threshold.adjust(1),
+            KeyCode::Enter => match save_thresholds() {
+                Ok(path) => {
+                    self.on_new_log_line(format!("Saved the thresholds to {}", path.display()))
+                }
+                Err(e) => {
+                    self.on_new_log_line(format!("Warning: saving the thresholds failed: {:#}", e))
+                }
+            },
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.exit()
+            }
+            _ => {}
         }
+    }
+
+    /// Apply the next recorded trace item and show the state right after it
+    fn replay_step(&mut self) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+        match replay.step(&self.instance) {
+            Some(log_lines) => {
+                for line in log_lines {
+                    self.on_program_log_line(line);
+                }
+                self.on_new_stats(self.instance.get_stats());
+            }
+            None => self.on_new_log_line(String::from("Replay: end of the recording")),
+        }
+    }
+
+    /// Show a message of the visor in the log pane
+    fn on_new_log_line(&mut self, new_line: String) {
+        self.push_log_line(LogLine::unattributed(new_line));
+    }
+
+    /// Show a line of the program output in the log pane, tagged with the task emitting it
+    fn on_program_log_line(&mut self, new_line: String) {
+        let log_line = LogLine::attributed(&self.instance, new_line);
+        self.push_log_line(log_line);
+    }
+
+    fn push_log_line(&mut self, log_line: LogLine) {
+        // Stay at the bottom if we were already at the bottom
+        let follow = is_log_scrolled_to_end(self.log_scroll, self.shown_log_line_count());
+        self.log_lines.push_back(log_line);
 
         let max_log_lines = MAX_LOG_LINES.load(Ordering::Relaxed);
         while self.log_lines.len() > max_log_lines {
-            let _ = self.log_lines.pop_front();
+            let removed = self.log_lines.pop_front();
+            // keep the same lines in view when scrolled back
+            if removed.is_some_and(|line| self.log_filter.matches(&line)) {
+                self.log_scroll = self.log_scroll.saturating_sub(1);
+            }
+            if let Some(time_travel) = &mut self.time_travel {
+                time_travel.log_index = time_travel.log_index.saturating_sub(1);
+            }
+        }
+
+        let shown_count = self.shown_log_line_count();
+        self.log_scroll = if follow {
+            max_log_scroll(shown_count)
+        } else {
+            clamp_log_scroll(self.log_scroll, shown_count)
+        };
+    }
+
+    /// Number of log lines passing the log filter
+    fn shown_log_line_count(&self) -> usize {
+        match self.log_filter {
+            LogFilter::All => self.log_lines.len(),
+            filter => self.log_lines.iter().filter(|l| filter.matches(l)).count(),
+        }
+    }
+
+    /// Show all log lines, the ones of the selected task or the unattributed ones
+    fn cycle_log_filter(&mut self) {
+        self.log_filter = self.log_filter.next(self.selected_task);
+        self.log_scroll = max_log_scroll(self.shown_log_line_count());
+    }
+
+    fn on_firmware_status(&mut self, status: FirmwareStatus) {
+        match status {
+            FirmwareStatus::BuildFailed(ref messages) => {
+                // Show the compiler errors in the log pane instead of leaving the TUI
+                self.on_new_log_line(String::from("Build failed:"));
+                for message in messages {
+                    for line in strip_ansi_codes(message).lines() {
+                        self.on_new_log_line(line.to_string());
+                    }
+                }
+                self.firmware_status = status;
+            }
+            FirmwareStatus::RunningWithWarning(warning) => {
+                self.on_new_log_line(format!("Warning: {}", warning));
+                self.firmware_status = FirmwareStatus::Running;
+            }
+            _ => self.firmware_status = status,
+        }
+    }
+
+    /// Timeline view of the latest statistics
+    fn timeline_view(&self) -> TimelineView<'_> {
+        TimelineView {
+            events: &self.source_stats().timeline,
+            filter: self.timeline_filter,
+            scroll: self.timeline_scroll,
+            absolute_time: self.absolute_time,
+        }
+    }
+
+    /// Sample the task activity of the live history into the heatmap
+    fn refresh_heatmap(&mut self) {
+        self.heatmap = self
+            .instance
+            .heatmap(self.view_window, self.heatmap_bucket_count.get());
+        let row_count = self.heatmap.as_ref().map_or(0, |h| h.rows.len());
+        self.heatmap_scroll = self.heatmap_scroll.min(HeatmapView::max_scroll(row_count));
+    }
+
+    /// Scroll the heatmap by `offset` tasks (negative towards the first task)
+    fn scroll_heatmap(&mut self, offset: isize) {
+        let row_count = self.heatmap.as_ref().map_or(0, |h| h.rows.len());
+        self.heatmap_scroll = self
+            .heatmap_scroll
+            .saturating_add_signed(offset)
+            .min(HeatmapView::max_scroll(row_count));
+    }
+
+    /// Mark the current moment in the timeline, the history export and the raw log
+    fn add_user_marker(&mut self) {
+        let marker = self.instance.add_user_marker(ComputerTime::now(), None);
+        // Routed like a program line: recorded into the raw log and shown in the log pane
+        let _ = self.program_tx.send(marker.program_line());
+    }
+
+    /// Scroll the timeline one event back (towards older events)
+    fn scroll_timeline_back(&mut self) {
+        let event_count = self.timeline_view().filtered_events().len();
+        self.timeline_scroll = (self.timeline_scroll + 1).min(event_count.saturating_sub(1));
+    }
+
+    /// Freeze the dashboard at the time of the nearest log line with a timestamp from
+    /// `start_index` on (towards newer or older lines). The line is scrolled to the top.
+    fn travel_to_log_line(&mut self, start_index: usize, forward: bool) {
+        match TimeTravel::to_log_line(&self.instance, &self.log_lines, start_index, forward) {
+            Some(time_travel) => {
+                // first shown line from the travelled one on
+                let shown = filtered_log_indices(&self.log_lines, self.log_filter);
+                let position = shown.partition_point(|&index| index < time_travel.log_index);
+                self.log_scroll =
+                    clamp_log_scroll(u16::try_from(position).unwrap_or(u16::MAX), shown.len());
+                self.time_travel = Some(time_travel);
+            }
+            // keep the current snapshot at the first/last timestamped line
+            None if self.time_travel.is_some() => {}
+            None => self.on_new_log_line(String::from(
+                "Warning: no log line with a timestamp to travel to",
+            )),
+        }
+        self.refresh_displayed_stats();
+    }
+
+    /// Leave the time travel and show the latest statistics again
+    fn return_to_live(&mut self) {
+        self.time_travel = None;
+        self.refresh_displayed_stats();
+    }
+
+    /// Write the retained state history to a new NDJSON file in the working directory
+    fn export_history(&mut self) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = format!("embassy-visor-history-{}.ndjson", timestamp);
+
+        let records = self.instance.history_records();
+        let result =
+            File::create(&path).and_then(|file| write_ndjson(&records, &mut BufWriter::new(file)));
+        match result {
+            Ok(()) => self.on_new_log_line(format!(
+                "Exported {} state changes to {}",
+                records.len(),
+                path
+            )),
+            Err(e) => self.on_new_log_line(format!("Warning: history export failed: {}", e)),
+        }
+    }
+
+    /// Export the history of the selected task's executor only (with all its tasks)
+    fn export_selected_executor_history(&mut self) {
+        let Some(selected) = self.selected_task else {
+            self.on_new_log_line(String::from(
+                "Select a task (j/k) to export the history of its executor",
+            ));
+            return;
+        };
+        let Some(executor_id) = self
+            .displayed_stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .find(|e| e.tasks.iter().any(|t| t.task_id == selected))
+            .map(|e| e.executor_id)
+        else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = format!(
+            "embassy-visor-history-executor-0x{:X}-{}.ndjson",
+            executor_id, timestamp
+        );
+
+        let records = self.instance.executor_history_records(executor_id);
+        let result =
+            File::create(&path).and_then(|file| write_ndjson(&records, &mut BufWriter::new(file)));
+        match result {
+            Ok(()) => self.on_new_log_line(format!(
+                "Exported {} state changes of executor 0x{:X} to {}",
+                records.len(),
+                executor_id,
+                path
+            )),
+            Err(e) => self.on_new_log_line(format!("Warning: history export failed: {}", e)),
+        }
+    }
+
+    /// Rebuild, flash and restart the firmware (view settings are kept)
+    fn request_reload(&mut self) {
+        if self.firmware_status == FirmwareStatus::Building {
+            return; // Reload already in progress
+        }
+
+        if self.reload_tx.send(()).is_ok() {
+            self.firmware_status = FirmwareStatus::Building;
         }
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if self.task_filter_editing {
+            self.handle_task_filter_key_event(key_event);
+            return;
+        }
+        if let Some(selected) = self.settings_selected {
+            self.handle_settings_key_event(key_event, selected);
+            return;
+        }
+
         match key_event.code {
+            KeyCode::Char('/') => {
+                // start typing a new task filter
+                self.task_filter.clear();
+                self.task_filter_editing = true;
+                self.refresh_displayed_stats();
+            }
+            KeyCode::Char('m') => {
+                // toggle between per core and merged executors
+                self.merge_executors = !self.merge_executors;
+                self.refresh_displayed_stats();
+            }
+            KeyCode::Char('d') => self.show_diagnostics = !self.show_diagnostics,
+            KeyCode::Char('E') => self.show_event_counts = !self.show_event_counts,
+            KeyCode::Char('j') => self.move_in_focused_pane(1),
+            KeyCode::Char('k') => self.move_in_focused_pane(-1),
+            KeyCode::Char('|') => self.toggle_split(),
+            KeyCode::Tab => {
+                if let Some(split) = &mut self.split {
+                    split.toggle_focus();
+                }
+            }
+            KeyCode::Char('v') => self.cycle_focused_pane_view(),
+            KeyCode::Char('n') if self.replay.is_some() => self.replay_step(),
+            KeyCode::Char('n') => self.select_next_warning(true),
+            KeyCode::Char('N') => self.select_next_warning(false),
+            KeyCode::Char('R') => self.select_running_task(),
+            KeyCode::Char('p') => self.toggle_pin_selected_task(),
+            KeyCode::Char('A') => self.acknowledge_selected_warnings(),
+            KeyCode::Char('l') => self.show_state_legend = !self.show_state_legend,
+            KeyCode::Char('t') => self.show_timeline = !self.show_timeline,
+            KeyCode::Char('e') => {
+                self.timeline_filter = self.timeline_filter.next();
+                self.timeline_scroll = 0;
+            }
+            KeyCode::Char('[') => self.scroll_timeline_back(),
+            KeyCode::Char(']') => self.timeline_scroll = self.timeline_scroll.saturating_sub(1),
+            KeyCode::Char('x') => self.show_preemptions = !self.show_preemptions,
+            KeyCode::Char('M') => self.add_user_marker(),
+            KeyCode::Char('w') => self.export_history(),
+            KeyCode::Char('W') => self.export_selected_executor_history(),
+            KeyCode::Char('u') => {
+                let mut unit = TIME_UNIT.write().unwrap();
+                *unit = unit.next();
+            }
+            KeyCode::Char('r') => self.request_reload(),
+            KeyCode::Char('g') if self.time_travel.is_some() => self.return_to_live(),
+            KeyCode::Char('g') => {
+                let shown = filtered_log_indices(&self.log_lines, self.log_filter);
+                let top_index = shown
+                    .get(self.log_scroll as usize)
+                    .copied()
+                    .unwrap_or(self.log_lines.len());
+                self.travel_to_log_line(top_index, true)
+            }
+            KeyCode::Char('L') => self.cycle_log_filter(),
+            KeyCode::Esc if self.time_travel.is_some() => self.return_to_live(),
+            KeyCode::Esc if !self.task_filter.is_empty() => {
+                // clear active filter first, quit on the next escape
+                self.task_filter.clear();
+                self.refresh_displayed_stats();
+            }
             KeyCode::Char('q') | KeyCode::Esc => self.exit(),
             KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.exit()
             }
-            KeyCode::Up => self.log_scroll = self.log_scroll.saturating_sub(1).max(0),
+            KeyCode::Char('c') => self.show_categories = !self.show_categories,
+            KeyCode::Char('o') => self.show_channels = !self.show_channels,
+            KeyCode::Char('T') => self.show_timers = !self.show_timers,
+            KeyCode::Char('P') => self.show_wake_chain = !self.show_wake_chain,
+            KeyCode::Char('b') => self.absolute_time = !self.absolute_time,
+            KeyCode::Char('s') => self.settings_selected = Some(0),
+            KeyCode::Char('z') => {
+                HIDE_INSIGNIFICANT_TASKS.fetch_xor(true, Ordering::Relaxed);
+                self.refresh_displayed_stats();
+            }
+            KeyCode::Char('a') => {
+                ACTIVE_ONLY_CPU.fetch_xor(true, Ordering::Relaxed);
+            }
+            KeyCode::Char('C') => {
+                let mut basis = CPU_BASIS.write().unwrap();
+                *basis = basis.next();
+            }
+            KeyCode::Char('h') => {
+                self.show_heatmap = !self.show_heatmap;
+                if self.heatmap_shown() {
+                    self.refresh_heatmap();
+                }
+            }
+            KeyCode::Char('<') | KeyCode::Char('>') => {
+                self.view_window =
+                    zoom_view_window(self.view_window, key_event.code == KeyCode::Char('<'));
+                if self.heatmap_shown() {
+                    self.refresh_heatmap();
+                }
+            }
+            KeyCode::Char('{') => self.scroll_heatmap(-1),
+            KeyCode::Char('}') => self.scroll_heatmap(1),
+            KeyCode::Char('f') => {
+                self.flash_state_changes = !self.flash_state_changes;
+                self.refresh_displayed_stats();
+            }
+            KeyCode::PageUp => {
+                self.dashboard_scroll = self.dashboard_scroll.saturating_sub(DASHBOARD_SCROLL_STEP)
+            }
+            KeyCode::PageDown => {
+                self.dashboard_scroll = self
+                    .dashboard_scroll
+                    .saturating_add(DASHBOARD_SCROLL_STEP)
+                    .min(self.dashboard_max_scroll.get())
+            }
+            KeyCode::Up if let Some(time_travel) = &self.time_travel => {
+                if let Some(index) = time_travel.log_index.checked_sub(1) {
+                    self.travel_to_log_line(index, false);
+                }
+            }
+            KeyCode::Down if let Some(time_travel) = &self.time_travel => {
+                self.travel_to_log_line(time_travel.log_index + 1, true)
+            }
+            KeyCode::Up => self.log_scroll = self.log_scroll.saturating_sub(1),
             KeyCode::Down => {
-                self.log_scroll = self
-                    .log_scroll
-                    .saturating_add(1)
-                    .min(self.log_lines.len().saturating_sub(3) as u16)
+                self.log_scroll = clamp_log_scroll(
+                    self.log_scroll.saturating_add(1),
+                    self.shown_log_line_count(),
+                )
             }
             _ => {}
         }
@@ -115,47 +1018,412 @@ impl App {
         if let Ok(tui_event) = self.event_recver.recv() {
             match tui_event {
                 TuiAppEvent::KeyPressed(key_event) => self.handle_key_event(key_event),
-                TuiAppEvent::TraceStatistics(new_stats) => self.on_new_stats(new_stats),
-                TuiAppEvent::NewLogLine(new_line) => self.on_new_log_line(new_line),
+                TuiAppEvent::TraceStatistics(new_stats) => self.on_new_stats(*new_stats),
+                TuiAppEvent::NewLogLine(new_line) => self.on_program_log_line(new_line),
+                TuiAppEvent::FirmwareStatus(status) => self.on_firmware_status(status),
+                TuiAppEvent::SourceThroughput(sample) => self.throughput_meter.update(sample),
             }
         }
         Ok(())
     }
 
+    /// Status line of the trace source (lines/s, bytes/s, parse success ratio, lost wakeups)
+    fn throughput_status_line(&self) -> Line<'static> {
+        let rate = self.throughput_meter.get_rate();
+        let health_percent = self.instance_stats.trace_health_percent();
+        let lost_wakeup_tasks: Vec<String> = self
+            .instance_stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .flat_map(|e| e.tasks.iter())
+            .filter(|t| t.lost_wakeup && !t.warning_acknowledged)
+            .map(|t| t.name.clone())
+            .collect();
+        let mut line = Line::default();
+        // Beacon and visor disagree on the events: everything below is unreliable
+        if let Some(warning) = &self.instance_stats.schema_warning {
+            line.push_span(
+                format!(" SCHEMA MISMATCH: {} ", warning)
+                    .white()
+                    .on_red()
+                    .bold(),
+            );
+        }
+        // Most lines unreadable: everything below is unreliable, so it goes first
+        if rate.exceeds_parse_error_threshold(*PARSE_ERROR_THRESHOLD_PERCENT.read().unwrap()) {
+            line.push_span(
+                format!(
+                    " {:.0}% OF THE LINES FAIL TO PARSE: format mismatch or corrupted link? ",
+                    rate.failed_line_percent
+                )
+                .white()
+                .on_red()
+                .bold(),
+            );
+        }
+        line.extend([
+            format!(
+                " Trace: {:.0} lines/s  {}  parsed ",
+                rate.trace_lines_per_s,
+                format_byte_rate(rate.bytes_per_s)
+            )
+            .into(),
+            format!("{:.1}% ", 100.0 - rate.failed_trace_line_percent).fg(
+                if rate.failed_trace_line_percent == 0.0 {
+                    Color::Green
+                } else {
+                    Color::Red
+                },
+            ),
+            " health ".into(),
+            format!("{:.1}% ", health_percent).fg(health_color(health_percent)),
+        ]);
+        match self.instance_stats.core_imbalance() {
+            Some(imbalance) if imbalance.is_flagged() => line.push_span(
+                format!(" CORE IMBALANCE: {} ", describe_core_imbalance(&imbalance))
+                    .black()
+                    .on_yellow()
+                    .bold(),
+            ),
+            Some(imbalance) => {
+                line.push_span(format!(" core gap {:.0}% ", imbalance.gap_percent()).dark_gray())
+            }
+            None => {}
+        }
+        if !lost_wakeup_tasks.is_empty() {
+            line.push_span(
+                format!(" LOST WAKEUP: {} ", lost_wakeup_tasks.join(", "))
+                    .white()
+                    .on_red()
+                    .bold(),
+            );
+        }
+        if let Some(status) = NET_SOURCE_STATUS.read().unwrap().as_ref() {
+            line.push_span(match status {
+                NetSourceStatus::Connecting => " remote: connecting ".yellow(),
+                NetSourceStatus::Connected => " remote: connected ".green(),
+                NetSourceStatus::Reconnecting { error, retry_in } => format!(
+                    " remote: {} (retry in {:.1}s) ",
+                    error,
+                    retry_in.as_secs_f32()
+                )
+                .red(),
+                NetSourceStatus::GaveUp { error } => format!(" remote: gave up ({}) ", error)
+                    .white()
+                    .on_red()
+                    .bold(),
+            });
+        }
+        match FILE_SOURCE_STATUS.read().unwrap().as_ref() {
+            Some(FileSourceStatus::Waiting {
+                path,
+                attempts,
+                retry_in,
+            }) => line.push_span(
+                format!(
+                    " waiting for source at {}… (attempt {}, retry in {:.1}s) ",
+                    path.display(),
+                    attempts,
+                    retry_in.as_secs_f32()
+                )
+                .yellow(),
+            ),
+            Some(FileSourceStatus::GaveUp { path, attempts }) => line.push_span(
+                format!(
+                    " {} unavailable after {} attempts ",
+                    path.display(),
+                    attempts
+                )
+                .white()
+                .on_red()
+                .bold(),
+            ),
+            Some(FileSourceStatus::Following) | None => {}
+        }
+        line
+    }
+
     fn draw(&self, frame: &mut Frame) {
+        // Switch between gauges and compact numeric table based on terminal width
+        let view_config = self.view_config(frame.area().width);
+
+        let diagnostics_view = DiagnosticsView(&self.instance_stats);
+        let diagnostics_height = if self.show_diagnostics {
+            diagnostics_view.get_min_height()
+        } else {
+            0
+        };
+
+        let event_counts_view = EventCountsView(&self.instance_stats.event_counts);
+        let event_counts_height = if self.show_event_counts {
+            event_counts_view.get_min_height()
+        } else {
+            0
+        };
+
+        let legend_view = StateLegendView;
+        let legend_height = if self.show_state_legend {
+            legend_view.get_min_height()
+        } else {
+            0
+        };
+
+        // Colors by task (assigned per executor, also when merged, so they stay the same)
+        let task_colors = TaskColors::from_executors(
+            self.displayed_stats
+                .core_stats
+                .iter()
+                .flat_map(|c| c.executors.iter()),
+        );
+
+        // Details of the selected task (period breakdown)
+        let task_detail_view = self.selected_task_stats().map(|task| TaskDetailView {
+            task,
+            color: task_colors.get(task.task_id),
+            absolute_time: self.absolute_time,
+        });
+        let task_detail_height = task_detail_view
+            .as_ref()
+            .map_or(0, |view| view.get_min_height());
+
+        let timeline_view = self.timeline_view();
+        let show_timeline = self.show_timeline && !self.in_pane(PaneView::Timeline);
+        let timeline_height = if show_timeline {
+            timeline_view.get_min_height()
+        } else {
+            0
+        };
+
+        let preemption_view = PreemptionView(self.source_stats().preemption_matrix());
+        let show_preemptions = self.show_preemptions && !self.in_pane(PaneView::Preemptions);
+        let preemption_height = if show_preemptions {
+            preemption_view.get_min_height()
+        } else {
+            0
+        };
+
+        let category_view = CategoryView(self.source_stats().category_stats());
+        let show_categories = self.show_categories && !self.in_pane(PaneView::Categories);
+        let category_height = if show_categories {
+            category_view.get_min_height()
+        } else {
+            0
+        };
+
+        let heatmap_view = HeatmapView {
+            heatmap: self.heatmap.as_ref(),
+            scroll: self.heatmap_scroll,
+            colors: &task_colors,
+        };
+        let show_heatmap = self.show_heatmap && !self.in_pane(PaneView::Heatmap);
+        let heatmap_height = if show_heatmap {
+            heatmap_view.get_min_height()
+        } else {
+            0
+        };
+
+        // Panes of the split, side by side in the dashboard area
+        let pane_areas =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(frame.area());
+        let heatmap_width = match self.split {
+            Some(split) if split.left == PaneView::Heatmap => pane_areas[0].width,
+            Some(split) if split.right == PaneView::Heatmap => pane_areas[1].width,
+            _ => frame.area().width,
+        };
+        self.heatmap_bucket_count
+            .set(HeatmapView::bucket_count(heatmap_width));
+
+        let channel_view = ChannelView(&self.source_stats().channel_edges);
+        let show_channels = self.show_channels && !self.in_pane(PaneView::Channels);
+        let channel_height = if show_channels {
+            channel_view.get_min_height()
+        } else {
+            0
+        };
+
+        let timer_view = TimerView(&self.source_stats().timers);
+        let show_timers = self.show_timers && !self.in_pane(PaneView::Timers);
+        let timer_height = if show_timers {
+            timer_view.get_min_height()
+        } else {
+            0
+        };
+
+        // Inferred from the live channel events, not part of the history
+        let selected_task = self.selected_task_stats();
+        let wake_chain = match selected_task {
+            Some(task) if self.show_wake_chain && self.time_travel.is_none() => {
+                self.instance.wake_chain(task.task_id)
+            }
+            _ => None,
+        };
+        let wake_chain_view = WakeChainView {
+            task: selected_task.map(|task| task.name.as_str()),
+            chain: wake_chain.as_ref(),
+        };
+        let wake_chain_height = if self.show_wake_chain {
+            wake_chain_view.get_min_height()
+        } else {
+            0
+        };
+
+        // Dashboard shrinks (and becomes scrollable) before the log pane gets too small
+        let available_height = frame.area().height.saturating_sub(
+            legend_height
+                + task_detail_height
+                + timeline_height
+                + preemption_height
+                + category_height
+                + heatmap_height
+                + channel_height
+                + timer_height
+                + wake_chain_height
+                + diagnostics_height
+                + event_counts_height
+                + MIN_LOG_PANE_HEIGHT,
+        );
+        let pane_height = |view: PaneView, width: u16| match view {
+            PaneView::Dashboard => self.get_stats_view_height(self.view_config(width)) + 2,
+            PaneView::Timeline => timeline_view.get_min_height(),
+            PaneView::Heatmap => heatmap_view.get_min_height(),
+            PaneView::Preemptions => preemption_view.get_min_height(),
+            PaneView::Categories => category_view.get_min_height(),
+            PaneView::Channels => channel_view.get_min_height(),
+            PaneView::Timers => timer_view.get_min_height(),
+        };
+        let dashboard_height = match self.split {
+            Some(split) => pane_height(split.left, pane_areas[0].width)
+                .max(pane_height(split.right, pane_areas[1].width)),
+            None => self.get_stats_view_height(view_config) + 2,
+        }
+        .min(available_height);
+
         let layout = Layout::default()
             .constraints(
                 [
-                    Constraint::Length(InstanceView(&self.instance_stats).get_min_height() + 2),
-                    Constraint::Min(6),
+                    Constraint::Length(dashboard_height),
+                    Constraint::Length(legend_height),
+                    Constraint::Length(task_detail_height),
+                    Constraint::Length(timeline_height),
+                    Constraint::Length(preemption_height),
+                    Constraint::Length(category_height),
+                    Constraint::Length(heatmap_height),
+                    Constraint::Length(channel_height),
+                    Constraint::Length(timer_height),
+                    Constraint::Length(wake_chain_height),
+                    Constraint::Length(diagnostics_height),
+                    Constraint::Length(event_counts_height),
+                    Constraint::Min(MIN_LOG_PANE_HEIGHT),
                 ]
                 .as_ref(),
             )
             .split(frame.area());
 
-        frame.render_widget(self, layout[0]);
+        match self.split {
+            Some(split) => {
+                let panes =
+                    Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(layout[0]);
+                for (view, area) in [(split.left, panes[0]), (split.right, panes[1])] {
+                    match view {
+                        PaneView::Dashboard => frame.render_widget(self, area),
+                        PaneView::Timeline => frame.render_widget(&timeline_view, area),
+                        PaneView::Heatmap => frame.render_widget(&heatmap_view, area),
+                        PaneView::Preemptions => frame.render_widget(&preemption_view, area),
+                        PaneView::Categories => frame.render_widget(&category_view, area),
+                        PaneView::Channels => frame.render_widget(&channel_view, area),
+                        PaneView::Timers => frame.render_widget(&timer_view, area),
+                    }
+                }
+                let focused = match split.focus {
+                    Pane::Left => panes[0],
+                    Pane::Right => panes[1],
+                };
+                highlight_border(focused, frame.buffer_mut());
+            }
+            None => frame.render_widget(self, layout[0]),
+        }
+
+        if self.show_state_legend {
+            frame.render_widget(&legend_view, layout[1]);
+        }
+
+        if let Some(task_detail_view) = &task_detail_view {
+            frame.render_widget(task_detail_view, layout[2]);
+        }
+
+        if show_timeline {
+            frame.render_widget(&timeline_view, layout[3]);
+        }
+
+        if show_preemptions {
+            frame.render_widget(&preemption_view, layout[4]);
+        }
+
+        if show_categories {
+            frame.render_widget(&category_view, layout[5]);
+        }
+
+        if show_heatmap {
+            frame.render_widget(&heatmap_view, layout[6]);
+        }
+
+        if show_channels {
+            frame.render_widget(&channel_view, layout[7]);
+        }
+
+        if show_timers {
+            frame.render_widget(&timer_view, layout[8]);
+        }
+
+        if self.show_wake_chain {
+            frame.render_widget(&wake_chain_view, layout[9]);
+        }
+
+        if self.show_diagnostics {
+            frame.render_widget(&diagnostics_view, layout[10]);
+        }
+
+        if self.show_event_counts {
+            frame.render_widget(&event_counts_view, layout[11]);
+        }
 
-        let vertical_scroll = self.log_scroll; // from app state
+        let shown_log_indices = filtered_log_indices(&self.log_lines, self.log_filter);
+        let vertical_scroll = clamp_log_scroll(self.log_scroll, shown_log_indices.len());
 
-        let items = self
-            .log_lines
+        let travel_log_index = self.time_travel.as_ref().map(|t| t.log_index);
+        let items = shown_log_indices
             .iter()
-            .map(recolor_defmt_messages)
-            .chain([Line::from("")])
+            .map(|&index| {
+                let line = &self.log_lines[index].text;
+                if Some(index) == travel_log_index {
+                    Line::from(line.as_str()).black().on_yellow()
+                } else {
+                    recolor_defmt_messages(line)
+                }
+            })
             .collect::<Vec<_>>();
-        let paragraph: Paragraph<'_> = Paragraph::new((items).clone())
-            .scroll((vertical_scroll as u16, 0))
-            .block(Block::new().borders(Borders::ALL).title("Logs")); // to show a background for the scrollbar
+        let paragraph: Paragraph<'_> = Paragraph::new(items).scroll((vertical_scroll, 0)).block(
+            Block::new()
+                .borders(Borders::ALL)
+                .title(self.log_filter.title(&self.instance_stats))
+                .title(self.throughput_status_line().right_aligned()),
+        ); // to show a background for the scrollbar
 
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
             .begin_symbol(Some("↑"))
             .end_symbol(Some("↓"));
 
+        // one scrollbar position per valid scroll offset, so the thumb reaches the end
+        // exactly when the pane is scrolled to the bottom
         let mut scrollbar_state =
-            ScrollbarState::new(items.len()).position(vertical_scroll as usize);
+            ScrollbarState::new(max_log_scroll(shown_log_indices.len()) as usize + 1)
+                .position(vertical_scroll as usize);
 
         // let area = frame.area();
-        let area = layout[1];
+        let area = layout[12];
         // Note we render the paragraph
         frame.render_widget(paragraph, area);
         // and the scrollbar, those are separate widgets
@@ -168,31 +1436,160 @@ impl App {
             }),
             &mut scrollbar_state,
         );
+
+        if let Some(selected) = self.settings_selected {
+            let settings_view = SettingsView { selected };
+            frame.render_widget(&settings_view, settings_view.area(frame.area()));
+        }
     }
 }
 
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let title = Line::from(" Embassy Visor - Watchtower ".bold());
-        let instructions = Line::from(vec![
-            // " Settings ".into(),
-            // "<S>".blue().bold(),
-            // " Quit ".into(),
-            // "<Q/ESC> ".blue().bold(),
+        let mut title = Line::from(vec![
+            " Embassy Visor - Watchtower ".bold(),
+            format!("[{}] ", self.instance_stats.firmware_id_or_unknown()).dark_gray(),
+            format!("[time: {}] ", TIME_UNIT.read().unwrap().label()).dark_gray(),
+            format!("[cpu: {}] ", CPU_BASIS.read().unwrap().label()).dark_gray(),
         ]);
+        if ACTIVE_ONLY_CPU.load(Ordering::Relaxed) {
+            title.push_span("[task CPU: active only] ".yellow());
+        }
+        if let Some(split) = self.split {
+            title.push_span(
+                format!("[split: {} | {}] ", split.left.label(), split.right.label()).dark_gray(),
+            );
+        }
+        if let Some(window) = cpu_window() {
+            title.push_span(format!("[CPU over last {}ms] ", window.as_millis()).yellow());
+        }
+        if HIDE_INSIGNIFICANT_TASKS.load(Ordering::Relaxed) {
+            title.push_span(
+                format!(
+                    "[{} tasks hidden below {}% CPU] ",
+                    self.hidden_task_count,
+                    MIN_SIGNIFICANT_CPU_PERCENT.read().unwrap()
+                )
+                .yellow(),
+            );
+        }
+        if let Some(time_travel) = &self.time_travel {
+            title.push_span(time_travel.status_span());
+        }
+        if let Some(replay) = &self.replay {
+            title.push_span(replay_status_span(replay));
+        }
+        let instructions = if self.task_filter_editing {
+            Line::from(vec![
+                " Filter: /".into(),
+                self.task_filter.clone().yellow().bold(),
+                "_ ".yellow().slow_blink(),
+                " Apply ".into(),
+                "<Enter>".blue().bold(),
+                " Clear ".into(),
+                "<ESC> ".blue().bold(),
+            ])
+        } else if !self.task_filter.is_empty() {
+            Line::from(vec![
+                " Filter: ".into(),
+                self.task_filter.clone().yellow().bold(),
+                " Clear ".into(),
+                "<ESC> ".blue().bold(),
+            ])
+        } else {
+            Line::from(vec![
+                // " Settings ".into(),
+                // "<S>".blue().bold(),
+                " Filter ".into(),
+                "</>".blue().bold(),
+                " Select ".into(),
+                "<J/K>".blue().bold(),
+                " Pin ".into(),
+                "<P>".blue().bold(),
+                " Merge Executors ".into(),
+                "<M>".blue().bold(),
+                " Diagnostics ".into(),
+                "<D>".blue().bold(),
+                " Legend ".into(),
+                "<L>".blue().bold(),
+                " Timeline ".into(),
+                "<T>".blue().bold(),
+                " Reload ".into(),
+                "<R>".blue().bold(),
+                " Quit ".into(),
+                "<Q/ESC> ".blue().bold(),
+            ])
+        };
+        let firmware_status = match self.firmware_status {
+            FirmwareStatus::Building => Line::from(" Rebuilding firmware... ".yellow().bold()),
+            FirmwareStatus::Running | FirmwareStatus::RunningWithWarning(_) => Line::default(),
+            FirmwareStatus::BuildFailed(_) => Line::from(" Build failed (see logs) ".red().bold()),
+        };
         let block = Block::bordered()
             .title(title.centered())
+            .title(firmware_status.right_aligned())
             .title_bottom(instructions.centered())
             .border_set(border::THICK);
 
         let inner_block = block.inner(area);
+        self.dashboard_area.set(area);
 
-        InstanceView(&self.instance_stats).render(inner_block, buf);
+        let view_config = self.view_config(area.width);
+        let content_height = self.get_stats_view_height(view_config);
+        let max_scroll = content_height.saturating_sub(inner_block.height);
+        self.dashboard_max_scroll.set(max_scroll);
+        let scroll = self.dashboard_scroll.min(max_scroll);
+
+        if self.merge_executors {
+            (&MergedView(&self.displayed_merged_executors, view_config, scroll))
+                .render(inner_block, buf);
+        } else {
+            (&InstanceView(&self.displayed_stats, view_config, scroll)).render(inner_block, buf);
+        }
 
         block.render(area, buf);
+
+        // Scroll position of the dashboard when it does not fit into the terminal
+        if max_scroll > 0 {
+            let mut scrollbar_state =
+                ScrollbarState::new(max_scroll as usize).position(scroll as usize);
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(Some("↑"))
+                .end_symbol(Some("↓"))
+                .render(
+                    area.inner(Margin {
+                        vertical: 1,
+                        horizontal: 0,
+                    }),
+                    buf,
+                    &mut scrollbar_state,
+                );
+        }
     }
 }
 
+/// Position in the recording and the trace item applied last
+fn replay_status_span(replay: &Replay) -> Span<'static> {
+    let current = match replay.current() {
+        Some(item) => format!(
+            "{} core {}: {:?}",
+            format_uc_timestamp(item.time_pair.get_uc_timestamp()),
+            item.core_id,
+            item.data
+        ),
+        None => String::from("paused before the first event"),
+    };
+    format!(
+        " REPLAY {}/{} {} <N> step ",
+        replay.applied(),
+        replay.total(),
+        current
+    )
+    .black()
+    .on_cyan()
+    .bold()
+}
+
 fn run_keyevent_listener(event_sender: Sender<TuiAppEvent>) {
     loop {
         let event = match event::read() {
@@ -211,16 +1608,26 @@ fn run_keyevent_listener(event_sender: Sender<TuiAppEvent>) {
 }
 
 fn run_log_line_listener(event_sender: Sender<TuiAppEvent>, logs_recver: Receiver<String>) {
-    loop {
-        match logs_recver.recv() {
-            Ok(new_line) => {
-                let result = event_sender.send(TuiAppEvent::NewLogLine(new_line));
+    // Loop ends when the Log Channel is closed
+    while let Ok(new_line) = logs_recver.recv() {
+        let result = event_sender.send(TuiAppEvent::NewLogLine(new_line));
 
-                if result.is_err() {
-                    break; // Event Channel closed
-                }
-            }
-            Err(_) => break, // Log Channel closed
+        if result.is_err() {
+            break; // Event Channel closed
+        }
+    }
+}
+
+fn run_firmware_status_listener(
+    event_sender: Sender<TuiAppEvent>,
+    status_recver: Receiver<FirmwareStatus>,
+) {
+    // Loop ends when the reload supervisor stopped
+    while let Ok(status) = status_recver.recv() {
+        let result = event_sender.send(TuiAppEvent::FirmwareStatus(status));
+
+        if result.is_err() {
+            break; // Event Channel closed
         }
     }
 }
@@ -229,10 +1636,18 @@ fn run_instance_stats_gatherer(event_sender: Sender<TuiAppEvent>, instance: Trac
     loop {
         std::thread::sleep(Duration::from_millis(100));
 
-        let new_stats = instance.get_stats();
-        let result = event_sender.send(TuiAppEvent::TraceStatistics(new_stats));
+        let new_stats = Arc::unwrap_or_clone(instance.latest_stats());
+        let result = event_sender.send(TuiAppEvent::TraceStatistics(Box::new(new_stats)));
         if result.is_err() {
             break; // channel closed
         }
+
+        let sample = SOURCE_THROUGHPUT.sample();
+        if event_sender
+            .send(TuiAppEvent::SourceThroughput(sample))
+            .is_err()
+        {
+            break; // channel closed
+        }
     }
 }
@@ -0,0 +1,236 @@
+//! Dashboard file (`--dashboard-file <PATH>`): the dashboard is rendered off-screen and written
+//! as ANSI text to a file on every refresh, e.g. to `tail -f` it over SSH on a headless box
+//! without an interactive session. The file is overwritten each time, so it never grows; every
+//! frame starts by clearing the screen, so `tail -f` shows the latest one only.
+
+use std::{
+    fmt::Write,
+    path::PathBuf,
+    thread::JoinHandle,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::Widget,
+};
+
+use crate::{
+    tracing::{instance::TracingInstance, stats::instance_stats::InstanceStats},
+    visualizer::{ViewConfig, describe_core_imbalance, views::instance_view::InstanceView},
+};
+
+/// Width of the rendered dashboard in columns
+pub const DASHBOARD_FILE_WIDTH: u16 = 120;
+
+/// Default time between two writes of the dashboard file
+pub const DEFAULT_DASHBOARD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Clear the screen and move the cursor home (start of every frame)
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+/// Render the dashboard (status line and all cores) into a buffer of the given width, as high
+/// as needed to show every task
+pub fn render_dashboard(stats: &InstanceStats, width: u16) -> Buffer {
+    let view_config = ViewConfig::new(width, stats);
+    let instance_view = InstanceView(stats, view_config, 0);
+    let area = Rect::new(0, 0, width, 1 + instance_view.get_min_height());
+    let mut buf = Buffer::empty(area);
+
+    let unix_time_s = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut status = Line::from(vec![
+        " Embassy Visor - Watchtower ".bold(),
+        format!("[{}] ", stats.firmware_id_or_unknown()).dark_gray(),
+        format!(
+            "{} executors, {} tasks, health {:.1}% ",
+            stats.executor_count,
+            stats.tasks_count,
+            stats.trace_health_percent()
+        )
+        .into(),
+        format!("(unix time {})", unix_time_s).dark_gray(),
+    ]);
+    if let Some(imbalance) = stats.core_imbalance()
+        && imbalance.is_flagged()
+    {
+        status.push_span(
+            format!(" CORE IMBALANCE: {} ", describe_core_imbalance(&imbalance)).yellow(),
+        );
+    }
+    buf.set_line(0, 0, &status, width);
+
+    let dashboard_area = Rect::new(0, 1, width, area.height - 1);
+    (&instance_view).render(dashboard_area, &mut buf);
+    buf
+}
+
+/// SGR parameters selecting the color (`base` 30 for the foreground, 40 for the background)
+fn color_sgr(color: Color, base: u8) -> String {
+    match color {
+        Color::Reset => (base + 9).to_string(),
+        Color::Black => base.to_string(),
+        Color::Red => (base + 1).to_string(),
+        Color::Green => (base + 2).to_string(),
+        Color::Yellow => (base + 3).to_string(),
+        Color::Blue => (base + 4).to_string(),
+        Color::Magenta => (base + 5).to_string(),
+        Color::Cyan => (base + 6).to_string(),
+        Color::Gray => (base + 7).to_string(),
+        Color::DarkGray => (base + 60).to_string(),
+        Color::LightRed => (base + 61).to_string(),
+        Color::LightGreen => (base + 62).to_string(),
+        Color::LightYellow => (base + 63).to_string(),
+        Color::LightBlue => (base + 64).to_string(),
+        Color::LightMagenta => (base + 65).to_string(),
+        Color::LightCyan => (base + 66).to_string(),
+        Color::White => (base + 67).to_string(),
+        Color::Indexed(index) => format!("{};5;{}", base + 8, index),
+        Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+    }
+}
+
+/// Escape sequence switching to the style (from the default style)
+fn style_sgr(style: Style) -> String {
+    let mut params = vec![String::from("0")];
+    for (modifier, code) in [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ] {
+        if style.add_modifier.contains(modifier) {
+            params.push(String::from(code));
+        }
+    }
+    params.push(color_sgr(style.fg.unwrap_or(Color::Reset), 30));
+    params.push(color_sgr(style.bg.unwrap_or(Color::Reset), 40));
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// Text of the buffer with ANSI escape sequences for its colors and modifiers, one line per
+/// row (the style is reset at the end of every row)
+pub fn buffer_to_ansi(buf: &Buffer) -> String {
+    let mut out = String::new();
+    for y in buf.area.top()..buf.area.bottom() {
+        let mut current_style = Style::default();
+        for x in buf.area.left()..buf.area.right() {
+            let cell = &buf[(x, y)];
+            let style = cell.style();
+            if style != current_style {
+                out.push_str(&style_sgr(style));
+                current_style = style;
+            }
+            out.push_str(cell.symbol());
+        }
+        if current_style != Style::default() {
+            out.push_str("\x1b[0m");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Frame written to the dashboard file
+pub fn render_dashboard_frame(stats: &InstanceStats) -> String {
+    let mut frame = String::from(CLEAR_SCREEN);
+    let _ = write!(
+        frame,
+        "{}",
+        buffer_to_ansi(&render_dashboard(stats, DASHBOARD_FILE_WIDTH))
+    );
+    frame
+}
+
+/// Write the dashboard of the instance to the file every `interval` in the background
+pub fn spawn_dashboard_writer(
+    path: PathBuf,
+    interval: Duration,
+    instance: TracingInstance,
+) -> anyhow::Result<JoinHandle<()>> {
+    // Fail early on an unwritable path instead of silently in the background
+    std::fs::write(&path, render_dashboard_frame(&instance.latest_stats()))
+        .with_context(|| format!("Failed writing dashboard file {}", path.display()))?;
+
+    Ok(std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(interval);
+            // Overwrite instead of appending, the file keeps the size of one frame
+            if std::fs::write(&path, render_dashboard_frame(&instance.latest_stats())).is_err() {
+                break; // e.g. the directory was removed, the visor goes on
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support::{executor_stats, task_stats},
+        tracing::{
+            stats::{core_stats::CoreStats, executor_stats::ExecutorStats, task_stats::TaskStats},
+            task::TaskTraceState,
+        },
+    };
+
+    #[test]
+    fn test_render_dashboard_frame() {
+        let tasks = [
+            (1, "blink_task", TaskTraceState::Running),
+            (2, "sensor_task", TaskTraceState::Idle),
+        ]
+        .map(|(task_id, name, state)| TaskStats {
+            cpu_utilization_percent: 12.5,
+            state,
+            ..task_stats(task_id, name)
+        });
+        let executor = ExecutorStats {
+            cpu_utilization_percent: 25.0,
+            ..executor_stats(1, 0, "main_executor", Vec::from(tasks))
+        };
+        let stats = InstanceStats {
+            core_stats: vec![CoreStats {
+                executors: vec![executor],
+                cpu_utilization_percent: 25.0,
+                ..CoreStats::empty(0)
+            }],
+            tasks_count: 2,
+            executor_count: 1,
+            firmware_id: Some(String::from("abc123")),
+            ..Default::default()
+        };
+
+        let frame = render_dashboard_frame(&stats);
+        assert!(frame.starts_with(CLEAR_SCREEN));
+        assert!(frame.contains("abc123"));
+        assert!(frame.contains("main_executor"));
+        assert!(frame.contains("blink_task"));
+        assert!(frame.contains("sensor_task"));
+        // colored: the bold title is switched on and every styled row is reset at its end
+        assert!(frame.contains("\x1b[0;1;39;49m Embassy Visor"));
+        assert!(
+            frame
+                .lines()
+                .skip(1)
+                .all(|row| !row.contains('\x1b') || row.ends_with("\x1b[0m"))
+        );
+
+        // without the escape sequences every row is as wide as the dashboard
+        let buf = render_dashboard(&stats, 80);
+        assert_eq!(buf.area.width, 80);
+        assert!(
+            buffer_to_ansi(&buf)
+                .lines()
+                .any(|row| row.contains("blink_task"))
+        );
+    }
+}
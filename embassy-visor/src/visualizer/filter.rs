@@ -0,0 +1,149 @@
+use std::{
+    collections::BTreeSet,
+    sync::{RwLock, atomic::AtomicBool},
+};
+
+use crate::{
+    tracing::stats::instance_stats::InstanceStats, visualizer::warnings::warning_task_ids,
+};
+
+/// Hide the tasks below the significant CPU utilization (toggled with 'z')
+pub static HIDE_INSIGNIFICANT_TASKS: AtomicBool = AtomicBool::new(false);
+
+/// CPU utilization in percent below which a task is hidden (`--min-task-cpu`)
+pub static MIN_SIGNIFICANT_CPU_PERCENT: RwLock<f32> = RwLock::new(0.5);
+
+/// Check if a task name matches the filter query (case-insensitive).
+/// Matches when the query characters appear in order in the name (fuzzy finder style),
+/// which includes every plain substring.
+pub fn task_name_matches(name: &str, query: &str) -> bool {
+    let name = name.to_lowercase();
+    let mut name_chars = name.chars();
+
+    query
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|query_char| name_chars.any(|c| c == query_char))
+}
+
+/// Copy of the statistics containing only tasks matching the query.
+/// Executors and cores without any matching task are removed.
+pub fn filter_instance_stats(stats: &InstanceStats, query: &str) -> InstanceStats {
+    let mut filtered = stats.clone();
+
+    for core in filtered.core_stats.iter_mut() {
+        for executor in core.executors.iter_mut() {
+            executor.tasks.retain(|t| task_name_matches(&t.name, query));
+        }
+        core.executors.retain(|e| !e.tasks.is_empty());
+    }
+    filtered.core_stats.retain(|c| !c.executors.is_empty());
+
+    filtered
+}
+
+/// Remove the tasks using less CPU than `min_cpu_percent` from the statistics, except the
+/// `kept` ones (e.g. selected or pinned) and the ones with a warning. Executors are kept also
+/// when all of their tasks are hidden. Returns the number of hidden tasks.
+pub fn hide_insignificant_tasks(
+    stats: &mut InstanceStats,
+    min_cpu_percent: f32,
+    kept: &BTreeSet<u32>,
+) -> usize {
+    let flagged = warning_task_ids(
+        &stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .collect::<Vec<_>>(),
+    );
+
+    let mut hidden = 0;
+    for executor in stats
+        .core_stats
+        .iter_mut()
+        .flat_map(|c| c.executors.iter_mut())
+    {
+        let count_before = executor.tasks.len();
+        executor.tasks.retain(|t| {
+            t.cpu_utilization_percent >= min_cpu_percent
+                || kept.contains(&t.task_id)
+                || flagged.contains(&t.task_id)
+        });
+        hidden += count_before - executor.tasks.len();
+    }
+    hidden
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{
+        executor::ExecutorTraceInfo,
+        stats::core_stats::CoreStats,
+        test_harness::{at_ms, feed_executor, item},
+        trace_data::TraceItemType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_task_name_matches_substring() {
+        assert!(task_name_matches("esp32_app::blink_task", "blink"));
+        assert!(task_name_matches("esp32_app::blink_task", "BLINK"));
+        assert!(task_name_matches("esp32_app::Blink_Task", "blink_t"));
+        assert!(task_name_matches("esp32_app::blink_task", ""));
+        assert!(!task_name_matches("esp32_app::blink_task", "uart"));
+    }
+
+    #[test]
+    fn test_task_name_matches_fuzzy() {
+        assert!(task_name_matches("esp32_app::blink_task", "bltsk"));
+        assert!(task_name_matches("esp32_app::blink_task", "app blink"));
+        // order matters
+        assert!(!task_name_matches("esp32_app::blink_task", "ksatknilb"));
+    }
+
+    #[test]
+    fn test_hide_insignificant_tasks() {
+        let mut executor = ExecutorTraceInfo::new(1, 0, at_ms(0));
+        feed_executor(
+            &mut executor,
+            &[1, 2, 3, 4, 5].map(|task_id| {
+                item(
+                    1,
+                    0,
+                    TraceItemType::TaskNew {
+                        executor_id: 1,
+                        task_id,
+                    },
+                )
+            }),
+        );
+        let mut stats = InstanceStats {
+            core_stats: vec![CoreStats::from_executor_list_on_core(&vec![&executor])],
+            ..Default::default()
+        };
+        // task 1 is busy, the others are near zero: 2 is pinned, 3 has a lost wakeup
+        let tasks = &mut stats.core_stats[0].executors[0].tasks;
+        tasks[0].cpu_utilization_percent = 12.0;
+        tasks[3].cpu_utilization_percent = 0.4;
+        tasks[2].lost_wakeup = true;
+
+        let hidden = hide_insignificant_tasks(&mut stats, 0.5, &BTreeSet::from([2]));
+        let shown: Vec<u32> = stats.core_stats[0].executors[0]
+            .tasks
+            .iter()
+            .map(|t| t.task_id)
+            .collect();
+        assert_eq!(shown, vec![1, 2, 3]);
+        assert_eq!(hidden, 2);
+
+        // unpinned, only the flagged task is left at a high threshold
+        assert_eq!(
+            hide_insignificant_tasks(&mut stats, 50.0, &BTreeSet::new()),
+            2
+        );
+        assert_eq!(stats.core_stats[0].executors[0].tasks[0].task_id, 3);
+    }
+}
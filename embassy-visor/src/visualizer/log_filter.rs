@@ -0,0 +1,219 @@
+//! Log lines tagged with the task that was running when they were emitted, and the filter of
+//! the log pane on that task (`L`). A line with a firmware timestamp is attributed from the
+//! retained history at that time, other lines to the task running when they arrive. Lines
+//! emitted while no task or tasks on several cores were running stay unattributed.
+
+use std::collections::VecDeque;
+
+use crate::{
+    tracing::{instance::TracingInstance, stats::instance_stats::InstanceStats},
+    visualizer::time_travel::parse_log_timestamp,
+};
+
+/// Line of the log pane
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub text: String,
+    /// Task running when the line was emitted (None if unknown, or a message of the visor)
+    pub task_id: Option<u32>,
+}
+
+impl LogLine {
+    /// Program output, attributed to the task running when it was emitted
+    pub fn attributed(instance: &TracingInstance, text: String) -> Self {
+        let running = match parse_log_timestamp(&text) {
+            Some(time) => instance.running_tasks_at(time),
+            None => instance.running_tasks(),
+        };
+        let task_id = match running.as_slice() {
+            [(_, task_id, _)] => Some(*task_id),
+            _ => None,
+        };
+        Self { text, task_id }
+    }
+
+    /// Message of the visor itself (no task emitted it)
+    pub fn unattributed(text: String) -> Self {
+        Self {
+            text,
+            task_id: None,
+        }
+    }
+}
+
+/// Log lines shown in the log pane
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFilter {
+    #[default]
+    All,
+    /// Lines emitted while the task was running
+    Task(u32),
+    /// Lines not attributed to any task
+    Unknown,
+}
+
+impl LogFilter {
+    pub fn matches(&self, line: &LogLine) -> bool {
+        match self {
+            LogFilter::All => true,
+            LogFilter::Task(task_id) => line.task_id == Some(*task_id),
+            LogFilter::Unknown => line.task_id.is_none(),
+        }
+    }
+
+    /// Next filter in the cycle: all lines, the selected task's lines (skipped without a
+    /// selection), the unattributed lines
+    pub fn next(self, selected_task: Option<u32>) -> Self {
+        match (self, selected_task) {
+            (LogFilter::All, Some(task_id)) => LogFilter::Task(task_id),
+            (LogFilter::All, None) | (LogFilter::Task(_), _) => LogFilter::Unknown,
+            (LogFilter::Unknown, _) => LogFilter::All,
+        }
+    }
+
+    /// Title of the log pane
+    pub fn title(&self, stats: &InstanceStats) -> String {
+        match self {
+            LogFilter::All => String::from("Logs"),
+            LogFilter::Task(task_id) => {
+                let name = stats
+                    .core_stats
+                    .iter()
+                    .flat_map(|c| c.executors.iter())
+                    .flat_map(|e| e.tasks.iter())
+                    .find(|t| t.task_id == *task_id)
+                    .map_or(format!("Task 0x{:X}", task_id), |t| t.name.clone());
+                format!("Logs of {}", name)
+            }
+            LogFilter::Unknown => String::from("Logs of unknown tasks"),
+        }
+    }
+}
+
+/// Indices of the log lines passing the filter
+pub fn filtered_log_indices(log_lines: &VecDeque<LogLine>, filter: LogFilter) -> Vec<usize> {
+    log_lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| filter.matches(line))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracing::{test_harness::item, trace_data::TraceItemType};
+
+    #[test]
+    fn test_filter_log_lines_by_task() {
+        use TraceItemType::*;
+
+        // task 10 runs 10-20ms, task 11 runs 30-40ms on core 0
+        let (_trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let instance = TracingInstance::new(trace_rx);
+        let items = [
+            item(
+                0,
+                0,
+                TaskNew {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            item(
+                0,
+                0,
+                TaskNew {
+                    executor_id: 1,
+                    task_id: 11,
+                },
+            ),
+            item(
+                5,
+                0,
+                TaskReadyBegin {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            item(5, 0, ExecutorPollStart { executor_id: 1 }),
+            item(
+                10,
+                0,
+                TaskExecBegin {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            item(
+                20,
+                0,
+                TaskExecEnd {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            item(
+                25,
+                0,
+                TaskReadyBegin {
+                    executor_id: 1,
+                    task_id: 11,
+                },
+            ),
+            item(
+                30,
+                0,
+                TaskExecBegin {
+                    executor_id: 1,
+                    task_id: 11,
+                },
+            ),
+            item(
+                40,
+                0,
+                TaskExecEnd {
+                    executor_id: 1,
+                    task_id: 11,
+                },
+            ),
+            item(45, 0, ExecutorIdle { executor_id: 1 }),
+        ];
+        for trace_item in items.iter() {
+            instance.update(trace_item);
+        }
+
+        let log_lines: VecDeque<LogLine> = [
+            "0.012000 [INFO ] blink on",
+            "0.022000 [INFO ] between the polls",
+            "0.035000 [INFO ] sensor read",
+            "[INFO ] 0.015 blink off",
+            "no timestamp while idle",
+        ]
+        .into_iter()
+        .map(|text| LogLine::attributed(&instance, String::from(text)))
+        .chain([LogLine::unattributed(String::from("Build failed:"))])
+        .collect();
+
+        assert_eq!(
+            filtered_log_indices(&log_lines, LogFilter::Task(10)),
+            vec![0, 3]
+        );
+        assert_eq!(
+            filtered_log_indices(&log_lines, LogFilter::Task(11)),
+            vec![2]
+        );
+        assert_eq!(
+            filtered_log_indices(&log_lines, LogFilter::Unknown),
+            vec![1, 4, 5]
+        );
+        assert_eq!(filtered_log_indices(&log_lines, LogFilter::All).len(), 6);
+
+        // cycle: all --> selected task --> unknown --> all
+        assert_eq!(LogFilter::All.next(Some(10)), LogFilter::Task(10));
+        assert_eq!(LogFilter::Task(10).next(Some(10)), LogFilter::Unknown);
+        assert_eq!(LogFilter::All.next(None), LogFilter::Unknown);
+        assert_eq!(LogFilter::Unknown.next(Some(10)), LogFilter::All);
+    }
+}
@@ -0,0 +1,49 @@
+/// Number of log lines still shown when scrolled to the end of the log pane
+const MIN_VISIBLE_LOG_LINES: usize = 3;
+
+/// Largest scroll offset (index of the first shown line) for `line_count` log lines.
+/// Saturates at `u16::MAX` as the paragraph scroll offset is a `u16`.
+pub fn max_log_scroll(line_count: usize) -> u16 {
+    u16::try_from(line_count.saturating_sub(MIN_VISIBLE_LOG_LINES)).unwrap_or(u16::MAX)
+}
+
+/// Clamp a scroll offset to the valid range for `line_count` log lines
+pub fn clamp_log_scroll(scroll: u16, line_count: usize) -> u16 {
+    scroll.min(max_log_scroll(line_count))
+}
+
+/// Check if the log pane is scrolled to the end, so it should follow new lines
+pub fn is_log_scrolled_to_end(scroll: u16, line_count: usize) -> bool {
+    scroll >= max_log_scroll(line_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamp_log_scroll() {
+        assert_eq!(clamp_log_scroll(0, 0), 0);
+        assert_eq!(clamp_log_scroll(10, 0), 0);
+
+        assert_eq!(clamp_log_scroll(0, 1), 0);
+        assert_eq!(clamp_log_scroll(10, 1), 0);
+
+        assert_eq!(clamp_log_scroll(0, 3), 0);
+        assert_eq!(clamp_log_scroll(10, 3), 0);
+
+        assert_eq!(clamp_log_scroll(1, 5), 1);
+        assert_eq!(clamp_log_scroll(10, 5), 2);
+
+        assert_eq!(clamp_log_scroll(500, usize::MAX), 500);
+        assert_eq!(clamp_log_scroll(u16::MAX, usize::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn test_is_log_scrolled_to_end() {
+        assert!(is_log_scrolled_to_end(0, 0));
+        assert!(is_log_scrolled_to_end(0, 3));
+        assert!(!is_log_scrolled_to_end(1, 5));
+        assert!(is_log_scrolled_to_end(2, 5));
+    }
+}
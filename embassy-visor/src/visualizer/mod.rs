@@ -1,25 +1,252 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, AtomicU16, Ordering},
+    },
+};
+
 use anyhow::Context;
-use crossbeam::channel::Receiver;
+use crossbeam::channel::{Receiver, Sender};
 use crossterm::event::KeyEvent;
-use ratatui::{style::{Color, Stylize}, text::Line};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Stylize},
+    text::Line,
+};
 
 use crate::{
-    tracing::{instance::TracingInstance, stats::instance_stats::InstanceStats},
+    firmware_run::FirmwareStatus,
+    replay::Replay,
+    throughput::ThroughputSample,
+    tracing::{
+        device::{device_name, is_multi_device},
+        instance::TracingInstance,
+        stats::{core_imbalance::CoreImbalance, instance_stats::InstanceStats},
+        task::TaskTraceState,
+    },
     visualizer::app::App,
 };
 
+mod acknowledgments;
 pub mod app;
+pub mod dashboard_file;
+pub mod filter;
+mod log_filter;
+mod log_scroll;
+mod pinning;
+mod running_task;
+pub mod smoothing;
+mod split_layout;
+mod state_changes;
+pub mod summary;
+mod task_colors;
+mod task_count_trend;
+mod time_travel;
+pub mod time_unit;
 mod views;
+mod warnings;
+
+/// Terminal width (in columns) below which the compact numeric layout is used
+pub static COMPACT_LAYOUT_MAX_WIDTH: AtomicU16 = AtomicU16::new(80);
+
+/// Layout of the statistic views, chosen by the available terminal width
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    /// Gauges for every task (needs ~80 columns)
+    Full,
+    /// Dense "name cpu% wait" table for narrow terminals (e.g. SSH sessions)
+    Compact,
+}
+
+impl LayoutMode {
+    /// Select the layout for the given terminal width
+    pub fn from_width(width: u16) -> Self {
+        if width < COMPACT_LAYOUT_MAX_WIDTH.load(Ordering::Relaxed) {
+            LayoutMode::Compact
+        } else {
+            LayoutMode::Full
+        }
+    }
+}
+
+/// Width of the task name column (0 --> fit to the longest task name)
+pub static NAME_COLUMN_WIDTH: AtomicU16 = AtomicU16::new(0);
+
+/// Indentation of the tasks below their executor (full layout)
+pub static EXECUTOR_INDENT: AtomicU16 = AtomicU16::new(5);
+
+/// Show the CPU utilization in the title of every core
+pub static CORE_UTILIZATION_IN_TITLE: AtomicBool = AtomicBool::new(true);
+
+/// Border color of cores below the warm CPU usage threshold
+pub static CORE_IDLE_COLOR: RwLock<Color> = RwLock::new(Color::Reset);
+
+/// Human names of the cores by core id (e.g. "APP", "NET")
+pub static CORE_NAMES: RwLock<BTreeMap<u32, String>> = RwLock::new(BTreeMap::new());
+
+/// Name of the core as shown in the TUI ("Core N" when unnamed)
+pub fn core_display_name(device_id: u32, core_id: u32) -> String {
+    let core_name = match CORE_NAMES.read().unwrap().get(&core_id) {
+        Some(name) => name.clone(),
+        None => format!("Core {}", core_id),
+    };
+    // Cores are grouped by device, the name tells which one
+    if is_multi_device() {
+        format!("{}: {}", device_name(device_id), core_name)
+    } else {
+        core_name
+    }
+}
+
+/// Busiest and idlest core for display, e.g. "APP 85% vs NET 20%"
+pub fn describe_core_imbalance(imbalance: &CoreImbalance) -> String {
+    format!(
+        "{} {:.0}% vs {} {:.0}%",
+        core_display_name(imbalance.device_id, imbalance.busiest.0),
+        imbalance.busiest.1,
+        core_display_name(imbalance.device_id, imbalance.idlest.0),
+        imbalance.idlest.1
+    )
+}
+
+/// Upper bound for the automatically fitted task name column
+pub const MAX_AUTO_NAME_COLUMN_WIDTH: u16 = 50;
+
+/// Layout settings shared by all statistic views of one frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewConfig {
+    pub layout_mode: LayoutMode,
+    /// Width of the task name column (full layout)
+    pub name_width: u16,
+    /// Indentation of the tasks below their executor
+    pub executor_indent: u16,
+    /// Task highlighted as selected (for pinning)
+    pub selected_task_id: Option<u32>,
+}
+
+impl ViewConfig {
+    /// Create the view settings for the terminal width and the statistics to display
+    pub fn new(width: u16, stats: &InstanceStats) -> Self {
+        let layout_mode = LayoutMode::from_width(width);
+
+        let name_width = match NAME_COLUMN_WIDTH.load(Ordering::Relaxed) {
+            0 => fit_name_column_width(
+                stats
+                    .core_stats
+                    .iter()
+                    .flat_map(|c| c.executors.iter())
+                    .flat_map(|e| e.tasks.iter())
+                    .map(|t| t.name.as_str()),
+                MAX_AUTO_NAME_COLUMN_WIDTH,
+            ),
+            width => width,
+        };
+
+        // Compact layout only indents tasks slightly to save columns
+        let executor_indent = match layout_mode {
+            LayoutMode::Full => EXECUTOR_INDENT.load(Ordering::Relaxed),
+            LayoutMode::Compact => EXECUTOR_INDENT.load(Ordering::Relaxed).min(1),
+        };
+
+        Self {
+            layout_mode,
+            name_width,
+            executor_indent,
+            selected_task_id: None,
+        }
+    }
+}
+
+/// Width of the name column fitting the longest name (plus one column spacing), capped at `max_width`
+pub fn fit_name_column_width<'a>(names: impl Iterator<Item = &'a str>, max_width: u16) -> u16 {
+    names
+        .map(|n| n.chars().count())
+        .max()
+        .map_or(0, |longest| (longest + 1).min(max_width as usize) as u16)
+}
+
+/// Child view (partially) shown in a viewport
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisibleChild {
+    /// Index of the child
+    pub index: usize,
+    /// Rows of the child scrolled out at the top
+    pub skip: u16,
+    /// Visible part of the child
+    pub area: Rect,
+}
+
+/// Children stacked vertically with the given heights, the first `skip` rows scrolled out:
+/// the ones visible in `area`. The heights are taken lazily, the children below the area are
+/// not even sized, so a view with hundreds of tasks only lays out the few on screen.
+pub fn visible_children(
+    heights: impl IntoIterator<Item = u16>,
+    skip: u16,
+    area: Rect,
+) -> Vec<VisibleChild> {
+    let (view_top, view_bottom) = (skip as u32, skip as u32 + area.height as u32);
+    let mut children = Vec::new();
+    let mut top = 0u32;
+    for (index, height) in heights.into_iter().enumerate() {
+        if top >= view_bottom {
+            break;
+        }
+        let bottom = top + height as u32;
+        let (visible_top, visible_bottom) = (top.max(view_top), bottom.min(view_bottom));
+        if visible_top < visible_bottom {
+            children.push(VisibleChild {
+                index,
+                skip: (visible_top - top) as u16,
+                area: Rect::new(
+                    area.x,
+                    area.y + (visible_top - view_top) as u16,
+                    area.width,
+                    (visible_bottom - visible_top) as u16,
+                ),
+            });
+        }
+        top = bottom;
+    }
+    children
+}
+
+/// Shorten the text to the given width, marking cut off text with '…'
+pub fn ellipsize(text: &str, width: u16) -> String {
+    let width = width as usize;
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+
+    match width {
+        0 => String::new(),
+        _ => text.chars().take(width - 1).chain(['…']).collect(),
+    }
+}
 
 pub enum TuiAppEvent {
     KeyPressed(KeyEvent),
-    TraceStatistics(InstanceStats),
-    NewLogLine(String)
+    TraceStatistics(Box<InstanceStats>),
+    NewLogLine(String),
+    FirmwareStatus(FirmwareStatus),
+    SourceThroughput(ThroughputSample),
 }
 
-pub fn run_main_tui(instance: TracingInstance, logs_recver : Receiver<String>) -> anyhow::Result<()> {
+/// Connection of the TUI to the firmware run (request reloads, receive build status)
+pub struct FirmwareReloadHandle {
+    pub reload_tx: Sender<()>,
+    pub status_recver: Receiver<FirmwareStatus>,
+    /// Program lines of the run, user markers are recorded into them (and the raw log)
+    pub program_tx: Sender<String>,
+}
+
+pub fn run_main_tui(
+    instance: TracingInstance,
+    logs_recver: Receiver<String>,
+    reload_handle: FirmwareReloadHandle,
+) -> anyhow::Result<()> {
     let mut terminal = ratatui::init();
-    let app_result = App::new(instance, logs_recver)
+    let app_result = App::new(instance, logs_recver, reload_handle)
         .context("Error creating TUI App")?
         .run(&mut terminal)
         .context("Failed running ratatui app");
@@ -28,30 +255,230 @@ pub fn run_main_tui(instance: TracingInstance, logs_recver : Receiver<String>) -
     app_result
 }
 
+/// TUI stepping through a recorded session: no firmware run, so no reloads and no program lines
+pub fn run_replay_tui(instance: TracingInstance, replay: Replay) -> anyhow::Result<()> {
+    let (_, logs_recver) = crossbeam::channel::unbounded();
+    let (_, status_recver) = crossbeam::channel::unbounded();
+    let reload_handle = FirmwareReloadHandle {
+        reload_tx: crossbeam::channel::unbounded().0,
+        status_recver,
+        program_tx: crossbeam::channel::unbounded().0,
+    };
+
+    let mut terminal = ratatui::init();
+    let app_result = App::new(instance, logs_recver, reload_handle)
+        .context("Error creating TUI App")?
+        .with_replay(replay)
+        .run(&mut terminal)
+        .context("Failed running ratatui app");
+
+    ratatui::restore();
+    app_result
+}
+
+/// Format a data rate with binary units (e.g. "5.3 KiB/s")
+pub fn format_byte_rate(bytes_per_s: f64) -> String {
+    match bytes_per_s {
+        x if x >= 1024.0 * 1024.0 => format!("{:.1} MiB/s", x / (1024.0 * 1024.0)),
+        x if x >= 1024.0 => format!("{:.1} KiB/s", x / 1024.0),
+        x => format!("{:.0} B/s", x),
+    }
+}
+
+/// Width of the task state badge column (longest label plus spacing)
+pub const TASK_STATE_BADGE_WIDTH: u16 = 10;
+
+/// Label of the task state shown in the task rows and the legend
+pub fn task_state_label(state: &TaskTraceState) -> &'static str {
+    match state {
+        TaskTraceState::Spawned => "SPAWNED",
+        TaskTraceState::Waiting => "WAITING",
+        TaskTraceState::Running => "RUNNING",
+        TaskTraceState::Preempted { .. } => "PREEMPTED",
+        TaskTraceState::Idle => "IDLE",
+        TaskTraceState::Ended => "ENDED",
+    }
+}
+
+/// Color of the task state badge
+pub fn task_state_color(state: &TaskTraceState) -> Color {
+    match state {
+        TaskTraceState::Spawned => Color::Cyan,
+        TaskTraceState::Waiting => Color::Yellow,
+        TaskTraceState::Running => Color::Green,
+        TaskTraceState::Preempted { .. } => Color::Red,
+        TaskTraceState::Idle => Color::DarkGray,
+        TaskTraceState::Ended => Color::Magenta,
+    }
+}
+
+/// CPU usage (in percent) above which a gauge or core is shown as busy
+pub const CPU_USAGE_WARM_PERCENT: f32 = 40.0;
+/// CPU usage (in percent) above which a gauge or core is shown as overloaded
+pub const CPU_USAGE_HOT_PERCENT: f32 = 70.0;
+
 pub fn cpu_usage_colors(cpu_utilization: f32) -> Color {
     match cpu_utilization {
-        x if x > 70.0 => Color::Red,
-        x if x > 40.0 => Color::Yellow,
+        x if x > CPU_USAGE_HOT_PERCENT => Color::Red,
+        x if x > CPU_USAGE_WARM_PERCENT => Color::Yellow,
         _ => Color::Blue,
     }
 }
 
+/// Border color of a core: the usage color once it is busy, the idle color below
+pub fn core_border_color(cpu_utilization: f32) -> Color {
+    if cpu_utilization > CPU_USAGE_WARM_PERCENT {
+        cpu_usage_colors(cpu_utilization)
+    } else {
+        *CORE_IDLE_COLOR.read().unwrap()
+    }
+}
+
 /// Recolors defmt log messages based on their log level tags:
 /// [INFO] Hello World
 /// - BLUE - gray
-pub fn recolor_defmt_messages(message: &String) -> Line {
+pub fn recolor_defmt_messages(message: &String) -> Line<'_> {
     let closing_bracket_pos = message.find(']').unwrap_or(0);
     let text = &message[closing_bracket_pos + 1..].trim_start();
 
     if message.starts_with("[ERROR") {
-        format!("[ERROR]").red() + format!(" {}", text).gray()
+        "[ERROR]".to_string().red() + format!(" {}", text).gray()
     } else if message.starts_with("[WARN") {
-        format!("[WARN]").yellow() + format!(" {}", text).gray()
+        "[WARN]".to_string().yellow() + format!(" {}", text).gray()
     } else if message.starts_with("[INFO") {
-        format!("[INFO]").blue() + format!(" {}", text).gray()
+        "[INFO]".to_string().blue() + format!(" {}", text).gray()
     } else if message.starts_with("[DEBUG") {
-        format!("[DEBUG]").green() + format!(" {}", text).gray()
+        "[DEBUG]".to_string().green() + format!(" {}", text).gray()
     } else {
         Line::from(message.to_string().gray())
     }
-}
\ No newline at end of file
+}
+
+/// Remove ANSI escape sequences (e.g. colors of rendered compiler messages)
+pub fn strip_ansi_codes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip "ESC [ ... <final byte>"
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_name_column_width() {
+        let names = ["blink", "uart_rx_task", "net"];
+        assert_eq!(fit_name_column_width(names.into_iter(), 50), 13);
+
+        // capped at max width
+        assert_eq!(fit_name_column_width(names.into_iter(), 8), 8);
+
+        // no tasks
+        assert_eq!(fit_name_column_width(std::iter::empty(), 50), 0);
+    }
+
+    #[test]
+    fn test_ellipsize() {
+        assert_eq!(ellipsize("blink", 10), "blink");
+        assert_eq!(ellipsize("blink", 5), "blink");
+        assert_eq!(ellipsize("uart_rx_task", 8), "uart_rx…");
+        assert_eq!(ellipsize("uart_rx_task", 0), "");
+    }
+
+    #[test]
+    fn test_strip_ansi_codes() {
+        assert_eq!(
+            strip_ansi_codes("\x1b[0m\x1b[1m\x1b[38;5;9merror[E0425]\x1b[0m: missing"),
+            "error[E0425]: missing"
+        );
+        assert_eq!(strip_ansi_codes("plain text"), "plain text");
+    }
+
+    #[test]
+    fn test_task_state_badges() {
+        let states = [
+            TaskTraceState::Spawned,
+            TaskTraceState::Waiting,
+            TaskTraceState::Running,
+            TaskTraceState::Preempted { by_executor_id: 2 },
+            TaskTraceState::Idle,
+            TaskTraceState::Ended,
+        ];
+
+        assert_eq!(task_state_label(&states[2]), "RUNNING");
+        assert_eq!(task_state_label(&states[3]), "PREEMPTED");
+        assert_eq!(task_state_color(&states[2]), Color::Green);
+
+        // every state is distinguishable and fits into the badge column
+        for (i, a) in states.iter().enumerate() {
+            assert!(task_state_label(a).len() < TASK_STATE_BADGE_WIDTH as usize);
+            for b in states.iter().skip(i + 1) {
+                assert_ne!(task_state_label(a), task_state_label(b));
+                assert_ne!(task_state_color(a), task_state_color(b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_visible_children() {
+        let area = Rect::new(2, 1, 6, 4);
+
+        // rows 3..7 of children with rows 0..2, 2..5, 5..6, 6..10
+        assert_eq!(
+            visible_children([2, 3, 1, 4], 3, area),
+            vec![
+                VisibleChild {
+                    index: 1,
+                    skip: 1,
+                    area: Rect::new(2, 1, 6, 2)
+                },
+                VisibleChild {
+                    index: 2,
+                    skip: 0,
+                    area: Rect::new(2, 3, 6, 1)
+                },
+                VisibleChild {
+                    index: 3,
+                    skip: 0,
+                    area: Rect::new(2, 4, 6, 1)
+                },
+            ]
+        );
+
+        // children below the area are not sized
+        let mut sized = 0;
+        let children = visible_children(
+            std::iter::repeat(1).inspect(|_| sized += 1).take(500),
+            0,
+            area,
+        );
+        assert_eq!(children.len(), 4);
+        assert_eq!(sized, 5);
+
+        // empty children take no rows
+        assert_eq!(visible_children([0, 1], 0, area)[0].index, 1);
+        assert!(visible_children([1, 1], 2, area).is_empty());
+    }
+
+    #[test]
+    fn test_format_byte_rate() {
+        assert_eq!(format_byte_rate(0.0), "0 B/s");
+        assert_eq!(format_byte_rate(512.4), "512 B/s");
+        assert_eq!(format_byte_rate(5427.0), "5.3 KiB/s");
+        assert_eq!(format_byte_rate(3.0 * 1024.0 * 1024.0), "3.0 MiB/s");
+    }
+}
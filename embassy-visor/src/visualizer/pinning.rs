@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+
+use crate::tracing::stats::{executor_stats::ExecutorStats, instance_stats::InstanceStats};
+
+/// Move the pinned tasks to the top of the executor and mark them.
+/// The order of the pinned and of the other tasks is kept.
+pub fn pin_tasks_first(executor: &mut ExecutorStats, pinned_task_ids: &BTreeSet<u32>) {
+    for task in executor.tasks.iter_mut() {
+        task.pinned = pinned_task_ids.contains(&task.task_id);
+    }
+
+    // stable sort --> keeps the order within pinned and unpinned tasks
+    executor.tasks.sort_by_key(|t| !t.pinned);
+}
+
+/// Pin the tasks of every executor of the instance
+pub fn pin_instance_tasks(stats: &mut InstanceStats, pinned_task_ids: &BTreeSet<u32>) {
+    for executor in stats
+        .core_stats
+        .iter_mut()
+        .flat_map(|c| c.executors.iter_mut())
+    {
+        pin_tasks_first(executor, pinned_task_ids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        test_support::{executor_stats, task_stats},
+        tracing::stats::executor_stats::ExecutorStats,
+    };
+
+    use super::pin_tasks_first;
+
+    fn executor_with_tasks(task_ids: &[u32]) -> ExecutorStats {
+        executor_stats(
+            1,
+            0,
+            "Executor",
+            task_ids
+                .iter()
+                .map(|&task_id| task_stats(task_id, &format!("Task {}", task_id)))
+                .collect(),
+        )
+    }
+
+    fn task_order(executor: &ExecutorStats) -> Vec<(u32, bool)> {
+        executor
+            .tasks
+            .iter()
+            .map(|t| (t.task_id, t.pinned))
+            .collect()
+    }
+
+    #[test]
+    fn test_pinned_tasks_first() {
+        let mut executor = executor_with_tasks(&[1, 2, 3, 4, 5]);
+        pin_tasks_first(&mut executor, &BTreeSet::from([4, 2]));
+        assert_eq!(
+            task_order(&executor),
+            vec![(2, true), (4, true), (1, false), (3, false), (5, false)]
+        );
+
+        let mut executor = executor_with_tasks(&[1, 2, 3]);
+        pin_tasks_first(&mut executor, &BTreeSet::from([3]));
+        assert_eq!(
+            task_order(&executor),
+            vec![(3, true), (1, false), (2, false)]
+        );
+    }
+
+    #[test]
+    fn test_pinned_task_missing() {
+        // pinned task is not part of this executor (ended and removed, or other executor)
+        let mut executor = executor_with_tasks(&[1, 2]);
+        pin_tasks_first(&mut executor, &BTreeSet::from([9]));
+        assert_eq!(task_order(&executor), vec![(1, false), (2, false)]);
+    }
+}
@@ -0,0 +1,145 @@
+//! Jump to the task running right now (`R`) on the focused core: the core of the selected
+//! task, the first displayed core without a selection. Tasks switch far faster than the
+//! refresh, so this is the task running at the keypress.
+
+use crate::{
+    tracing::{instance::TracingInstance, stats::instance_stats::InstanceStats},
+    visualizer::core_display_name,
+};
+
+/// Core the selection is on (device and core id), the first displayed core without a
+/// selection. None if no core is displayed.
+fn focused_core(displayed: &InstanceStats, selected_task: Option<u32>) -> Option<(u32, u32)> {
+    let core = selected_task
+        .and_then(|task_id| {
+            displayed.core_stats.iter().find(|c| {
+                c.executors
+                    .iter()
+                    .any(|e| e.tasks.iter().any(|t| t.task_id == task_id))
+            })
+        })
+        .or(displayed.core_stats.first())?;
+    Some((core.device_id, core.core_id))
+}
+
+/// Task to select: the one running on the focused core right now. The error is a note for the
+/// log pane (no task running, or the running task is not displayed).
+pub fn running_task_to_select(
+    instance: &TracingInstance,
+    displayed: &InstanceStats,
+    selected_task: Option<u32>,
+) -> Result<u32, String> {
+    let Some((device_id, core_id)) = focused_core(displayed, selected_task) else {
+        return Err(String::from("No core to jump to a running task on"));
+    };
+    let core_name = core_display_name(device_id, core_id);
+    let Some((_, task_id, task_name)) = instance.running_task_on_core(core_id) else {
+        return Err(format!("No task running on {}", core_name));
+    };
+
+    // the running task of the core is looked up in the displayed core (the ids of other
+    // devices may be the same)
+    let displayed_on_core = displayed
+        .core_stats
+        .iter()
+        .filter(|c| c.device_id == device_id && c.core_id == core_id)
+        .flat_map(|c| c.executors.iter())
+        .any(|e| e.tasks.iter().any(|t| t.task_id == task_id));
+    if displayed_on_core {
+        Ok(task_id)
+    } else {
+        Err(format!(
+            "{} is running on {} but not displayed (filtered or hidden)",
+            task_name, core_name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::{test_harness::item, trace_data::TraceItemType};
+
+    use super::*;
+
+    fn instance_with(items: &[(u64, u32, TraceItemType)]) -> TracingInstance {
+        let (_trace_tx, trace_rx) = crossbeam::channel::unbounded();
+        let instance = TracingInstance::new(trace_rx);
+        for (ms, core_id, data) in items {
+            instance.update(&item(*ms, *core_id, data.clone()));
+        }
+        instance
+    }
+
+    #[test]
+    fn test_select_running_task() {
+        use TraceItemType::*;
+
+        // core 0: executor 1 runs task 11 (task 10 waits), core 1: executor 2 polls, nothing runs
+        let instance = instance_with(&[
+            (0, 0, ExecutorPollStart { executor_id: 1 }),
+            (
+                1,
+                0,
+                TaskReadyBegin {
+                    executor_id: 1,
+                    task_id: 10,
+                },
+            ),
+            (
+                2,
+                0,
+                TaskReadyBegin {
+                    executor_id: 1,
+                    task_id: 11,
+                },
+            ),
+            (
+                3,
+                0,
+                TaskExecBegin {
+                    executor_id: 1,
+                    task_id: 11,
+                },
+            ),
+            (4, 1, ExecutorPollStart { executor_id: 2 }),
+            (
+                5,
+                1,
+                TaskReadyBegin {
+                    executor_id: 2,
+                    task_id: 20,
+                },
+            ),
+        ]);
+        let displayed = instance.get_stats();
+
+        // without a selection the first core is focused
+        assert_eq!(running_task_to_select(&instance, &displayed, None), Ok(11));
+        assert_eq!(
+            running_task_to_select(&instance, &displayed, Some(10)),
+            Ok(11)
+        );
+
+        // the focused core follows the selection
+        assert!(running_task_to_select(&instance, &displayed, Some(20)).is_err());
+        instance.update(&item(
+            6,
+            1,
+            TaskExecBegin {
+                executor_id: 2,
+                task_id: 20,
+            },
+        ));
+        assert_eq!(
+            running_task_to_select(&instance, &displayed, Some(20)),
+            Ok(20)
+        );
+
+        // a running task filtered out of the dashboard is not selected
+        let mut filtered = displayed.clone();
+        filtered.core_stats[0].executors[0]
+            .tasks
+            .retain(|t| t.task_id != 11);
+        assert!(running_task_to_select(&instance, &filtered, Some(10)).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Exponential smoothing of the displayed CPU usage. The statistics are recalculated every
+//! 100ms over a sliding window, so the raw values jitter; the smoothed values only move by
+//! `alpha` towards each new raw value. The raw statistics stay untouched.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::tracing::stats::instance_stats::InstanceStats;
+
+/// Weight of a new raw value (0.0 - 1.0], 1.0 disables the smoothing
+pub static CPU_SMOOTHING_ALPHA: RwLock<f32> = RwLock::new(1.0);
+
+/// Core, executor or task with a smoothed CPU usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Entity {
+    Core {
+        device_id: u32,
+        core_id: u32,
+    },
+    Executor {
+        device_id: u32,
+        executor_id: u32,
+    },
+    Task {
+        device_id: u32,
+        executor_id: u32,
+        task_id: u32,
+    },
+}
+
+/// Smoothed CPU usage per entity, carried across statistic refreshes
+#[derive(Debug, Clone, Default)]
+pub struct CpuSmoother {
+    values: HashMap<Entity, f32>,
+}
+
+impl CpuSmoother {
+    /// Smoothed value following the previous one by `alpha` (new entities start at the raw value)
+    fn next_value(previous: Option<f32>, raw: f32, alpha: f32) -> f32 {
+        match previous {
+            Some(previous) => previous + alpha * (raw - previous),
+            None => raw,
+        }
+    }
+
+    /// Feed the raw values of new statistics. Entities that disappeared are forgotten, so
+    /// they start over at their raw value when they reappear.
+    pub fn update(&mut self, stats: &InstanceStats) {
+        let alpha = *CPU_SMOOTHING_ALPHA.read().unwrap();
+        let previous = std::mem::take(&mut self.values);
+        let mut smooth = |entity: Entity, raw: f32| {
+            let value = Self::next_value(previous.get(&entity).copied(), raw, alpha);
+            self.values.insert(entity, value);
+        };
+
+        for core in stats.core_stats.iter() {
+            let device_id = core.device_id;
+            smooth(
+                Entity::Core {
+                    device_id,
+                    core_id: core.core_id,
+                },
+                core.cpu_utilization_percent,
+            );
+            for executor in core.executors.iter() {
+                smooth(
+                    Entity::Executor {
+                        device_id,
+                        executor_id: executor.executor_id,
+                    },
+                    executor.cpu_utilization_percent,
+                );
+                for task in executor.tasks.iter() {
+                    smooth(
+                        Entity::Task {
+                            device_id,
+                            executor_id: executor.executor_id,
+                            task_id: task.task_id,
+                        },
+                        task.cpu_utilization_percent,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Replace the CPU usage in the statistics by the smoothed values
+    pub fn apply(&self, stats: &mut InstanceStats) {
+        let smoothed = |entity: Entity, raw: f32| self.values.get(&entity).copied().unwrap_or(raw);
+
+        for core in stats.core_stats.iter_mut() {
+            let device_id = core.device_id;
+            core.cpu_utilization_percent = smoothed(
+                Entity::Core {
+                    device_id,
+                    core_id: core.core_id,
+                },
+                core.cpu_utilization_percent,
+            );
+            for executor in core.executors.iter_mut() {
+                let executor_id = executor.executor_id;
+                executor.cpu_utilization_percent = smoothed(
+                    Entity::Executor {
+                        device_id,
+                        executor_id,
+                    },
+                    executor.cpu_utilization_percent,
+                );
+                for task in executor.tasks.iter_mut() {
+                    task.cpu_utilization_percent = smoothed(
+                        Entity::Task {
+                            device_id,
+                            executor_id,
+                            task_id: task.task_id,
+                        },
+                        task.cpu_utilization_percent,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CpuSmoother;
+
+    #[test]
+    fn test_smoothing_step_response() {
+        // step from 0% to 100%
+        let mut value = CpuSmoother::next_value(None, 0.0, 0.5);
+        assert_eq!(value, 0.0);
+
+        let mut outputs = Vec::new();
+        for _ in 0..4 {
+            value = CpuSmoother::next_value(Some(value), 100.0, 0.5);
+            outputs.push(value);
+        }
+        assert_eq!(outputs, vec![50.0, 75.0, 87.5, 93.75]);
+
+        // alpha 1.0 follows the raw value immediately
+        assert_eq!(CpuSmoother::next_value(Some(0.0), 100.0, 1.0), 100.0);
+
+        // new entity starts at its raw value
+        assert_eq!(CpuSmoother::next_value(None, 42.0, 0.5), 42.0);
+    }
+}
@@ -0,0 +1,182 @@
+//! Left/right split of the dashboard area ('|'), e.g. the dashboard next to the timeline on a
+//! wide terminal. Each pane shows one of the views, 'v' changes the view of the focused pane and
+//! 'Tab' moves the focus. The selection and scroll keys ('j'/'k') go to the focused pane.
+
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+
+/// Views that can occupy a pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneView {
+    Dashboard,
+    Timeline,
+    Heatmap,
+    Preemptions,
+    Categories,
+    Channels,
+    Timers,
+}
+
+impl PaneView {
+    const ALL: [PaneView; 7] = [
+        PaneView::Dashboard,
+        PaneView::Timeline,
+        PaneView::Heatmap,
+        PaneView::Preemptions,
+        PaneView::Categories,
+        PaneView::Channels,
+        PaneView::Timers,
+    ];
+
+    /// View following this one in the cycle
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&v| v == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PaneView::Dashboard => "dashboard",
+            PaneView::Timeline => "timeline",
+            PaneView::Heatmap => "heatmap",
+            PaneView::Preemptions => "preemptions",
+            PaneView::Categories => "categories",
+            PaneView::Channels => "channels",
+            PaneView::Timers => "timers",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Left,
+    Right,
+}
+
+/// What a selection/scroll key does in the focused pane (`offset` -1: up, +1: down)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneAction {
+    /// Move the task selection
+    SelectTask(isize),
+    /// Scroll the timeline (up: towards older events)
+    ScrollTimeline(isize),
+    /// Scroll the heatmap tasks
+    ScrollHeatmap(isize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitLayout {
+    pub left: PaneView,
+    pub right: PaneView,
+    pub focus: Pane,
+}
+
+impl Default for SplitLayout {
+    /// Dashboard on the left (focused), timeline on the right
+    fn default() -> Self {
+        Self {
+            left: PaneView::Dashboard,
+            right: PaneView::Timeline,
+            focus: Pane::Left,
+        }
+    }
+}
+
+impl SplitLayout {
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Pane::Left => Pane::Right,
+            Pane::Right => Pane::Left,
+        };
+    }
+
+    pub fn focused_view(&self) -> PaneView {
+        match self.focus {
+            Pane::Left => self.left,
+            Pane::Right => self.right,
+        }
+    }
+
+    /// Show the next view in the focused pane, skipping the one of the other pane (a view is
+    /// shown once, so the panes scroll independently)
+    pub fn cycle_focused_view(&mut self) {
+        let (focused, other) = match self.focus {
+            Pane::Left => (&mut self.left, self.right),
+            Pane::Right => (&mut self.right, self.left),
+        };
+        *focused = focused.next();
+        if *focused == other {
+            *focused = focused.next();
+        }
+    }
+
+    /// One of the panes shows the view
+    pub fn shows(&self, view: PaneView) -> bool {
+        self.left == view || self.right == view
+    }
+
+    /// Action of a selection/scroll key (`offset` -1: up, +1: down) in the focused pane, None
+    /// if its view has nothing to select or scroll
+    pub fn route(&self, offset: isize) -> Option<PaneAction> {
+        match self.focused_view() {
+            PaneView::Dashboard => Some(PaneAction::SelectTask(offset)),
+            PaneView::Timeline => Some(PaneAction::ScrollTimeline(offset)),
+            PaneView::Heatmap => Some(PaneAction::ScrollHeatmap(offset)),
+            PaneView::Preemptions
+            | PaneView::Categories
+            | PaneView::Channels
+            | PaneView::Timers => None,
+        }
+    }
+}
+
+/// Color the border of the focused pane (keeps the symbols and titles of its view)
+pub fn highlight_border(area: Rect, buf: &mut Buffer) {
+    if area.is_empty() {
+        return;
+    }
+    let color = Color::Yellow;
+    for x in area.left()..area.right() {
+        buf[(x, area.top())].set_fg(color);
+        buf[(x, area.bottom() - 1)].set_fg(color);
+    }
+    for y in area.top()..area.bottom() {
+        buf[(area.left(), y)].set_fg(color);
+        buf[(area.right() - 1, y)].set_fg(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_routing() {
+        let mut split = SplitLayout::default();
+        assert_eq!(split.focused_view(), PaneView::Dashboard);
+        assert_eq!(split.route(1), Some(PaneAction::SelectTask(1)));
+
+        split.toggle_focus();
+        assert_eq!(split.focus, Pane::Right);
+        assert_eq!(split.route(-1), Some(PaneAction::ScrollTimeline(-1)));
+
+        // the right pane changes, the left one keeps the dashboard
+        split.cycle_focused_view();
+        assert_eq!(split.right, PaneView::Heatmap);
+        assert_eq!(split.left, PaneView::Dashboard);
+        assert_eq!(split.route(1), Some(PaneAction::ScrollHeatmap(1)));
+
+        split.cycle_focused_view();
+        assert_eq!(split.route(1), None);
+
+        // cycling past the end skips the dashboard shown in the left pane
+        for _ in 0..4 {
+            split.cycle_focused_view();
+        }
+        assert_eq!(split.right, PaneView::Timeline);
+        assert!(split.shows(PaneView::Dashboard));
+        assert!(!split.shows(PaneView::Heatmap));
+
+        split.toggle_focus();
+        assert_eq!(split.route(-1), Some(PaneAction::SelectTask(-1)));
+    }
+}
@@ -0,0 +1,119 @@
+//! Detection of task state changes between two statistics refreshes, to briefly highlight the
+//! tasks with activity.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::tracing::{stats::instance_stats::InstanceStats, task::TaskTraceState};
+
+/// States of the tasks in the previous statistics snapshot
+#[derive(Debug, Default)]
+pub struct StateChangeTracker {
+    previous_states: HashMap<u32, TaskTraceState>,
+}
+
+impl StateChangeTracker {
+    /// Ids of the tasks whose state differs from the previous snapshot (tasks appearing are
+    /// not changes). The snapshot becomes the previous one.
+    pub fn update(&mut self, stats: &InstanceStats) -> BTreeSet<u32> {
+        let states: HashMap<u32, TaskTraceState> = stats
+            .core_stats
+            .iter()
+            .flat_map(|c| c.executors.iter())
+            .flat_map(|e| e.tasks.iter())
+            .map(|t| (t.task_id, t.state))
+            .collect();
+
+        let changed = states
+            .iter()
+            .filter(|(task_id, state)| {
+                self.previous_states
+                    .get(task_id)
+                    .is_some_and(|previous| previous != *state)
+            })
+            .map(|(&task_id, _)| task_id)
+            .collect();
+
+        self.previous_states = states;
+        changed
+    }
+}
+
+/// Mark the tasks with a changed state
+pub fn mark_state_changes(stats: &mut InstanceStats, changed_task_ids: &BTreeSet<u32>) {
+    for task in stats
+        .core_stats
+        .iter_mut()
+        .flat_map(|c| c.executors.iter_mut())
+        .flat_map(|e| e.tasks.iter_mut())
+    {
+        task.state_changed = changed_task_ids.contains(&task.task_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        test_support::{executor_stats, task_stats},
+        tracing::{
+            stats::{core_stats::CoreStats, instance_stats::InstanceStats, task_stats::TaskStats},
+            task::TaskTraceState,
+        },
+    };
+
+    use super::*;
+
+    fn snapshot(tasks: &[(u32, TaskTraceState)]) -> InstanceStats {
+        let tasks = tasks
+            .iter()
+            .map(|&(task_id, state)| TaskStats {
+                state,
+                ..task_stats(task_id, &format!("Task {}", task_id))
+            })
+            .collect();
+        InstanceStats {
+            core_stats: vec![CoreStats {
+                core_id: 0,
+                device_id: 0,
+                executors: vec![executor_stats(1, 0, "Executor", tasks)],
+                cpu_utilization_percent: 0.0,
+                preemption_depth: 0,
+                asleep: false,
+                undeclared: false,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_state_changes_by_task_id() {
+        let mut tracker = StateChangeTracker::default();
+        let first = snapshot(&[
+            (1, TaskTraceState::Idle),
+            (2, TaskTraceState::Running),
+            (3, TaskTraceState::Waiting),
+        ]);
+        assert!(tracker.update(&first).is_empty());
+
+        // task 4 appears, task 3 is gone, order changed
+        let mut second = snapshot(&[
+            (2, TaskTraceState::Idle),
+            (4, TaskTraceState::Running),
+            (1, TaskTraceState::Idle),
+        ]);
+        let changed = tracker.update(&second);
+        assert_eq!(changed, BTreeSet::from([2]));
+
+        mark_state_changes(&mut second, &changed);
+        let marked: Vec<(u32, bool)> = second.core_stats[0].executors[0]
+            .tasks
+            .iter()
+            .map(|t| (t.task_id, t.state_changed))
+            .collect();
+        assert_eq!(marked, vec![(2, true), (4, false), (1, false)]);
+
+        // unchanged snapshot --> nothing to highlight
+        assert!(tracker.update(&second).is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+//! Human-readable recap of the session printed after the TUI closes (`--summary`).
+
+use std::io::{self, Write};
+
+use crate::{
+    tracing::stats::{executor_stats::ExecutorStats, instance_stats::InstanceStats},
+    visualizer::{
+        core_display_name, describe_core_imbalance, views::executor_view::BUDGET_OVERLOAD_PERCENT,
+    },
+};
+
+/// Number of tasks listed in the CPU ranking
+const TOP_TASK_COUNT: usize = 5;
+
+/// Write the summary of the final statistics: core utilizations, the tasks using the most
+/// CPU, the tasks and executors with a warning and the number of processed events
+pub fn write_summary(stats: &InstanceStats, out: &mut impl Write) -> io::Result<()> {
+    let executors: Vec<&ExecutorStats> = stats
+        .core_stats
+        .iter()
+        .flat_map(|core| core.executors.iter())
+        .collect();
+
+    writeln!(out, "Session summary ({})", stats.firmware_id_or_unknown())?;
+    writeln!(
+        out,
+        "  Events processed: {} ({} parse errors)",
+        stats.parse_stats.total_count(),
+        stats.parse_stats.error_count()
+    )?;
+
+    writeln!(out, "  Core utilization:")?;
+    for core in &stats.core_stats {
+        writeln!(
+            out,
+            "    {:<12} {:>5.1}%",
+            core_display_name(core.device_id, core.core_id),
+            core.cpu_utilization_percent
+        )?;
+    }
+    if let Some(imbalance) = stats.core_imbalance() {
+        writeln!(
+            out,
+            "    Imbalance: {:.1} points ({})",
+            imbalance.gap_percent(),
+            describe_core_imbalance(&imbalance)
+        )?;
+    }
+
+    writeln!(out, "  Top CPU tasks:")?;
+    let mut tasks: Vec<_> = executors
+        .iter()
+        .flat_map(|e| e.tasks.iter().map(move |t| (t, &e.name)))
+        .collect();
+    tasks.sort_by(|(a, _), (b, _)| {
+        b.cpu_utilization_percent
+            .total_cmp(&a.cpu_utilization_percent)
+    });
+    for (rank, (task, executor_name)) in tasks.iter().take(TOP_TASK_COUNT).enumerate() {
+        writeln!(
+            out,
+            "    {}. {} ({}) {:.1}%",
+            rank + 1,
+            task.name,
+            executor_name,
+            task.cpu_utilization_percent
+        )?;
+    }
+
+    let mut flagged = Vec::new();
+    for executor in &executors {
+        if let Some(budget) = executor.budget_utilization_percent
+            && budget >= BUDGET_OVERLOAD_PERCENT
+        {
+            flagged.push(format!("{}: {:.0}% of budget", executor.name, budget));
+        }
+        for task in &executor.tasks {
+            if task.lost_wakeup {
+                flagged.push(format!("{}: lost wakeup", task.name));
+            }
+            if task.integrity.total() > 0 {
+                flagged.push(format!(
+                    "{}: {} integrity issues",
+                    task.name,
+                    task.integrity.total()
+                ));
+            }
+        }
+    }
+    if let Some(imbalance) = stats.core_imbalance()
+        && imbalance.is_flagged()
+    {
+        flagged.push(format!(
+            "core imbalance: {}",
+            describe_core_imbalance(&imbalance)
+        ));
+    }
+    if flagged.is_empty() {
+        writeln!(out, "  Warnings: none")?;
+    } else {
+        writeln!(out, "  Warnings:")?;
+        for warning in flagged {
+            writeln!(out, "    {}", warning)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_support::{executor_stats, task_stats},
+        tracing::stats::{core_stats::CoreStats, task_stats::TaskStats},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_write_summary() {
+        let mut tasks: Vec<_> = (1..=6)
+            .map(|id| TaskStats {
+                cpu_utilization_percent: id as f32 * 2.0,
+                ..task_stats(id, &format!("task_{}", id))
+            })
+            .collect();
+        tasks[0].lost_wakeup = true;
+
+        let executor = ExecutorStats {
+            cpu_utilization_percent: 42.0,
+            lost_wakeup_count: 1,
+            budget_utilization_percent: Some(85.0),
+            ..executor_stats(1, 0, "main_executor", tasks)
+        };
+        let mut stats = InstanceStats {
+            core_stats: vec![
+                CoreStats {
+                    core_id: 0,
+                    device_id: 0,
+                    executors: vec![executor],
+                    cpu_utilization_percent: 42.0,
+                    preemption_depth: 0,
+                    asleep: false,
+                    undeclared: false,
+                },
+                CoreStats::empty(1),
+            ],
+            tasks_count: 6,
+            executor_count: 1,
+            firmware_id: Some(String::from("abc123")),
+            ..Default::default()
+        };
+        stats.parse_stats.parsed_count = 1234;
+
+        let mut output = Vec::new();
+        write_summary(&stats, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Session summary (abc123)
+  Events processed: 1234 (0 parse errors)
+  Core utilization:
+    Core 0        42.0%
+    Core 1         0.0%
+    Imbalance: 42.0 points (Core 0 42% vs Core 1 0%)
+  Top CPU tasks:
+    1. task_6 (main_executor) 12.0%
+    2. task_5 (main_executor) 10.0%
+    3. task_4 (main_executor) 8.0%
+    4. task_3 (main_executor) 6.0%
+    5. task_2 (main_executor) 4.0%
+  Warnings:
+    main_executor: 85% of budget
+    task_1: lost wakeup
+"
+        );
+    }
+}
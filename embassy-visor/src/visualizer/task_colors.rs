@@ -0,0 +1,112 @@
+//! Stable colors of the tasks, so a task looks the same in every view. The color is derived
+//! from the task id (its address, the same for every boot of a build); tasks of one executor
+//! get distinct colors as long as the palette has enough of them.
+
+use std::collections::HashMap;
+
+use ratatui::style::Color;
+
+use crate::tracing::stats::executor_stats::ExecutorStats;
+
+/// Colors readable on dark and light backgrounds (no red, it marks overload and warnings)
+const TASK_PALETTE: [Color; 11] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::LightCyan,
+    Color::LightMagenta,
+    Color::LightYellow,
+    Color::LightGreen,
+    Color::LightBlue,
+    Color::White,
+];
+
+/// Palette index preferred by the task. Task ids are aligned addresses, so the id is mixed
+/// (Fibonacci hashing) to spread neighbouring tasks over the palette.
+fn preferred_index(task_id: u32) -> usize {
+    let mixed = (task_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+    (mixed % TASK_PALETTE.len() as u64) as usize
+}
+
+/// Color of the task without knowing its executor (may collide with its neighbours)
+pub fn task_color(task_id: u32) -> Color {
+    TASK_PALETTE[preferred_index(task_id)]
+}
+
+/// Colors of the tasks by task id
+#[derive(Debug, Clone, Default)]
+pub struct TaskColors {
+    colors: HashMap<u32, Color>,
+}
+
+impl TaskColors {
+    /// Colors of the tasks of all executors
+    pub fn from_executors<'a>(executors: impl IntoIterator<Item = &'a ExecutorStats>) -> Self {
+        let mut colors = Self::default();
+        for executor in executors {
+            colors.add_executor(executor.tasks.iter().map(|t| t.task_id));
+        }
+        colors
+    }
+
+    /// Assign colors to the tasks of one executor. Every task gets its preferred color unless
+    /// a task with a lower id already has it, then the next free one.
+    pub fn add_executor(&mut self, task_ids: impl IntoIterator<Item = u32>) {
+        let mut task_ids: Vec<u32> = task_ids.into_iter().collect();
+        task_ids.sort_unstable();
+
+        let mut used = [false; TASK_PALETTE.len()];
+        for task_id in task_ids {
+            let preferred = preferred_index(task_id);
+            // All colors taken --> share the preferred one
+            let index = (0..TASK_PALETTE.len())
+                .map(|offset| (preferred + offset) % TASK_PALETTE.len())
+                .find(|index| !used[*index])
+                .unwrap_or(preferred);
+            used[index] = true;
+            self.colors.insert(task_id, TASK_PALETTE[index]);
+        }
+    }
+
+    /// Color of the task (its preferred color if it is not known)
+    pub fn get(&self, task_id: u32) -> Color {
+        self.colors
+            .get(&task_id)
+            .copied()
+            .unwrap_or_else(|| task_color(task_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_task_colors() {
+        // the same id always yields the same color
+        assert_eq!(task_color(0x2000_0100), task_color(0x2000_0100));
+
+        // aligned task addresses of one executor get distinct colors
+        let task_ids: Vec<u32> = (0..TASK_PALETTE.len() as u32)
+            .map(|i| 0x2000_0000 + i * 0x40)
+            .collect();
+        let mut colors = TaskColors::default();
+        colors.add_executor(task_ids.iter().copied());
+        let mut assigned: Vec<Color> = task_ids.iter().map(|id| colors.get(*id)).collect();
+        assigned.sort_by_key(|c| TASK_PALETTE.iter().position(|p| p == c));
+        assigned.dedup();
+        assert_eq!(assigned.len(), TASK_PALETTE.len());
+
+        // assigned again in another order (e.g. next frame) --> same colors
+        let mut again = TaskColors::default();
+        again.add_executor(task_ids.iter().rev().copied());
+        for task_id in &task_ids {
+            assert_eq!(colors.get(*task_id), again.get(*task_id));
+        }
+
+        // unknown tasks fall back to the preferred color
+        assert_eq!(colors.get(0x3000_0000), task_color(0x3000_0000));
+    }
+}
@@ -0,0 +1,169 @@
+//! Live task count of every executor over the session, sampled once per second while the TUI
+//! runs. A count that keeps growing hints at tasks that are spawned but never end; the ramp up
+//! while the firmware starts its tasks is not flagged.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use crate::tracing::{stats::instance_stats::InstanceStats, time::ComputerTime};
+
+/// Time between two samples of the task counts
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Samples kept per executor (shown as sparkline)
+const MAX_SAMPLES: usize = 20;
+
+/// Samples after the executor appeared that are not checked for growth (startup ramp)
+const STARTUP_SAMPLES: usize = 10;
+
+/// Latest samples checked for growth
+const GROWTH_WINDOW: usize = 10;
+
+/// Tasks the count has to grow by within the window to be flagged
+const MIN_GROWTH: usize = 3;
+
+/// Blocks of the sparkline, from the lowest to the highest count
+const SPARKLINE_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Count never decreases and grows by at least `min_growth` tasks over the samples
+pub fn is_monotonic_growth(samples: &[usize], min_growth: usize) -> bool {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return false;
+    };
+    samples.windows(2).all(|w| w[1] >= w[0]) && last - first >= min_growth
+}
+
+/// Counts as a sparkline scaled between their minimum and maximum
+pub fn sparkline(samples: &[usize]) -> String {
+    let (Some(&min), Some(&max)) = (samples.iter().min(), samples.iter().max()) else {
+        return String::new();
+    };
+    let levels = SPARKLINE_BLOCKS.len() - 1;
+    samples
+        .iter()
+        .map(|&count| match max - min {
+            0 => SPARKLINE_BLOCKS[0],
+            range => SPARKLINE_BLOCKS[(count - min) * levels / range],
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+struct TaskCounts {
+    samples: VecDeque<usize>,
+    /// Samples taken since the executor appeared (also the dropped ones)
+    sample_count: usize,
+}
+
+impl TaskCounts {
+    fn push(&mut self, count: usize) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(count);
+        self.sample_count += 1;
+    }
+
+    /// Growth within the window of samples taken after the startup
+    fn is_growing(&self) -> bool {
+        let after_startup = self.sample_count.saturating_sub(STARTUP_SAMPLES);
+        if after_startup < GROWTH_WINDOW {
+            return false;
+        }
+        let window: Vec<usize> = self
+            .samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(GROWTH_WINDOW))
+            .copied()
+            .collect();
+        is_monotonic_growth(&window, MIN_GROWTH)
+    }
+}
+
+/// Task count history per executor (by device id and executor id)
+#[derive(Debug, Clone, Default)]
+pub struct TaskCountTrend {
+    executors: HashMap<(u32, u32), TaskCounts>,
+    last_sample: Option<ComputerTime>,
+}
+
+impl TaskCountTrend {
+    /// Sample the task counts of new statistics (at most once per `SAMPLE_INTERVAL`).
+    /// Executors that disappeared are forgotten, so they start over when they reappear.
+    pub fn update(&mut self, stats: &InstanceStats, now: ComputerTime) {
+        if let Some(last_sample) = self.last_sample
+            && now.saturating_sub(last_sample).as_micros() < SAMPLE_INTERVAL.as_micros()
+        {
+            return;
+        }
+        self.last_sample = Some(now);
+
+        let mut previous = std::mem::take(&mut self.executors);
+        for core in stats.core_stats.iter() {
+            for executor in core.executors.iter() {
+                let key = (core.device_id, executor.executor_id);
+                let mut counts = previous.remove(&key).unwrap_or_default();
+                counts.push(executor.tasks.len());
+                self.executors.insert(key, counts);
+            }
+        }
+    }
+
+    /// Set the task count history and the growth flag of the executors in the statistics
+    pub fn apply(&self, stats: &mut InstanceStats) {
+        for core in stats.core_stats.iter_mut() {
+            for executor in core.executors.iter_mut() {
+                let Some(counts) = self.executors.get(&(core.device_id, executor.executor_id))
+                else {
+                    continue;
+                };
+                executor.task_count_history = counts.samples.iter().copied().collect();
+                executor.task_count_growing = counts.is_growing();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts_of(samples: impl IntoIterator<Item = usize>) -> TaskCounts {
+        let mut counts = TaskCounts::default();
+        for count in samples {
+            counts.push(count);
+        }
+        counts
+    }
+
+    #[test]
+    fn test_monotonic_growth() {
+        assert!(is_monotonic_growth(&[4, 4, 5, 6, 6, 7], 3));
+        // not enough growth
+        assert!(!is_monotonic_growth(&[4, 4, 5, 6, 6, 6], 3));
+        // tasks end in between: churn, no leak
+        assert!(!is_monotonic_growth(&[4, 6, 5, 7, 8], 3));
+        assert!(!is_monotonic_growth(&[], 3));
+
+        // startup ramp then stable: not flagged
+        let ramp_then_stable = counts_of((1..=10).chain(std::iter::repeat_n(10, 15)));
+        assert!(!ramp_then_stable.is_growing());
+
+        // ramp still within the startup samples
+        assert!(!counts_of(1..=12).is_growing());
+
+        // growing long after the startup
+        let leaking = counts_of(std::iter::repeat_n(5, 12).chain(6..=15));
+        assert!(leaking.is_growing());
+        assert_eq!(leaking.samples.len(), MAX_SAMPLES);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[3, 3]), "▁▁");
+        assert_eq!(sparkline(&[0, 7, 14]), "▁▄█");
+    }
+}
@@ -0,0 +1,116 @@
+//! Time travel from a log line: the dashboard shows the state reconstructed from the retained
+//! history at the firmware timestamp of the selected log line (`g`).
+
+use std::collections::VecDeque;
+
+use ratatui::{style::Stylize, text::Span};
+
+use crate::{
+    tracing::{instance::TracingInstance, stats::instance_stats::InstanceStats, time::EmbassyTime},
+    visualizer::log_filter::LogLine,
+};
+
+/// Dashboard frozen at the firmware time of a log line
+#[derive(Debug, Clone)]
+pub struct TimeTravel {
+    /// Index of the log line the time was taken from
+    pub log_index: usize,
+    /// Firmware timestamp of the log line
+    pub time: EmbassyTime,
+    /// Statistics at that time, None if it is outside the retained window
+    pub stats: Option<InstanceStats>,
+    /// Retained window when the snapshot was taken
+    pub window: Option<(EmbassyTime, EmbassyTime)>,
+}
+
+impl TimeTravel {
+    /// Travel to the nearest log line with a timestamp from `start_index` on, searching
+    /// towards newer (`forward`) or older lines
+    pub fn to_log_line(
+        instance: &TracingInstance,
+        log_lines: &VecDeque<LogLine>,
+        start_index: usize,
+        forward: bool,
+    ) -> Option<Self> {
+        let timestamp_at =
+            |index: usize| Some((index, parse_log_timestamp(&log_lines.get(index)?.text)?));
+        let (log_index, time) = if forward {
+            (start_index..log_lines.len()).find_map(timestamp_at)?
+        } else {
+            (0..=start_index.min(log_lines.len()))
+                .rev()
+                .find_map(timestamp_at)?
+        };
+
+        Some(Self {
+            log_index,
+            time,
+            stats: instance.get_stats_at(time),
+            window: instance.retained_window(),
+        })
+    }
+
+    /// Title badge: snapshot time, or a warning when it is outside the retained window
+    pub fn status_span(&self) -> Span<'static> {
+        let time_s = self.time.as_duration().as_secs_f64();
+        match (&self.stats, self.window) {
+            (Some(_), _) => format!(" TIME TRAVEL @ {:.6}s <G> live ", time_s)
+                .black()
+                .on_yellow()
+                .bold(),
+            (None, Some((start, end))) => format!(
+                " {:.6}s OUTSIDE RETAINED WINDOW {:.3}s - {:.3}s, showing live ",
+                time_s,
+                start.as_duration().as_secs_f64(),
+                end.as_duration().as_secs_f64()
+            )
+            .white()
+            .on_red()
+            .bold(),
+            (None, None) => format!(" {:.6}s: NO HISTORY RETAINED, showing live ", time_s)
+                .white()
+                .on_red()
+                .bold(),
+        }
+    }
+}
+
+/// Firmware timestamp of a log line in seconds, either leading the line (`0.123456 [INFO ] ..`)
+/// or following the level (`[INFO ] 0.123456 ..`)
+pub fn parse_log_timestamp(line: &str) -> Option<EmbassyTime> {
+    let line = line.trim_start();
+    let after_level = line
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(_, rest)| rest);
+
+    [Some(line), after_level]
+        .into_iter()
+        .flatten()
+        .filter_map(|text| text.split_whitespace().next())
+        .map(|token| token.trim_matches(|c| c == '[' || c == ']'))
+        .filter(|token| token.contains('.'))
+        .find_map(|token| token.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(|secs| EmbassyTime::from_micros((secs * 1_000_000.0).round() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracing::time::EmbassyTime;
+
+    use super::parse_log_timestamp;
+
+    #[test]
+    fn test_parse_log_timestamp() {
+        let at = |us| Some(EmbassyTime::from_micros(us));
+        assert_eq!(parse_log_timestamp("0.123456 [INFO ] blink"), at(123_456));
+        assert_eq!(parse_log_timestamp("[INFO ] 12.5 led on"), at(12_500_000));
+        assert_eq!(parse_log_timestamp("[1.000001] boot"), at(1_000_001));
+
+        // numbers inside the message are no timestamps
+        assert_eq!(parse_log_timestamp("[INFO ] temperature 21.5"), None);
+        assert_eq!(parse_log_timestamp("Build failed:"), None);
+        assert_eq!(parse_log_timestamp("[WARN ] 42 retries"), None);
+    }
+}
@@ -0,0 +1,141 @@
+//! Unit of the durations shown in the TUI: picked by magnitude (auto) or forced by the user
+//! (`--time-unit`, cycled with `u`).
+
+use std::{sync::RwLock, time::Duration};
+
+use crate::tracing::time::EmbassyTime;
+
+/// Unit all durations are shown in
+pub static TIME_UNIT: RwLock<TimeUnit> = RwLock::new(TimeUnit::Auto);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeUnit {
+    /// µs below 1ms, ms below 1s, s above
+    #[default]
+    Auto,
+    Micros,
+    Millis,
+    Secs,
+}
+
+impl TimeUnit {
+    /// Parse the `--time-unit` value
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(TimeUnit::Auto),
+            "us" => Some(TimeUnit::Micros),
+            "ms" => Some(TimeUnit::Millis),
+            "s" => Some(TimeUnit::Secs),
+            _ => None,
+        }
+    }
+
+    /// Next unit in the cycle
+    pub fn next(&self) -> Self {
+        match self {
+            TimeUnit::Auto => TimeUnit::Micros,
+            TimeUnit::Micros => TimeUnit::Millis,
+            TimeUnit::Millis => TimeUnit::Secs,
+            TimeUnit::Secs => TimeUnit::Auto,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeUnit::Auto => "auto",
+            TimeUnit::Micros => "µs",
+            TimeUnit::Millis => "ms",
+            TimeUnit::Secs => "s",
+        }
+    }
+}
+
+/// Format a duration in the unit selected by the user
+pub fn format_duration(duration: Duration) -> String {
+    format_duration_in(duration, *TIME_UNIT.read().unwrap())
+}
+
+/// Format a duration in the given unit
+pub fn format_duration_in(duration: Duration, unit: TimeUnit) -> String {
+    let us = duration.as_micros();
+    let unit = match unit {
+        TimeUnit::Auto if us < 1_000 => TimeUnit::Micros,
+        TimeUnit::Auto if us < 1_000_000 => TimeUnit::Millis,
+        TimeUnit::Auto => TimeUnit::Secs,
+        unit => unit,
+    };
+
+    match unit {
+        TimeUnit::Micros => format!("{}µs", us),
+        TimeUnit::Millis => format!("{:.2}ms", us as f64 / 1_000.0),
+        _ => format!("{:.2}s", duration.as_secs_f64()),
+    }
+}
+
+/// Absolute uC timestamp in microseconds since boot, grouped by thousands to stay readable
+/// (e.g. "12 345 678µs")
+pub fn format_uc_timestamp(time: EmbassyTime) -> String {
+    let digits = time.as_micros().to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 2);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(' ');
+        }
+        grouped.push(digit);
+    }
+    grouped.push_str("µs");
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tracing::time::EmbassyTime;
+
+    use super::{TimeUnit, format_duration_in, format_uc_timestamp};
+
+    #[test]
+    fn test_auto_unit_boundaries() {
+        let auto = |us| format_duration_in(Duration::from_micros(us), TimeUnit::Auto);
+        assert_eq!(auto(0), "0µs");
+        assert_eq!(auto(999), "999µs");
+        assert_eq!(auto(1_000), "1.00ms");
+        assert_eq!(auto(999_994), "999.99ms");
+        assert_eq!(auto(1_000_000), "1.00s");
+        assert_eq!(auto(61_500_000), "61.50s");
+    }
+
+    #[test]
+    fn test_forced_units() {
+        let duration = Duration::from_micros(1_500);
+        assert_eq!(format_duration_in(duration, TimeUnit::Micros), "1500µs");
+        assert_eq!(format_duration_in(duration, TimeUnit::Millis), "1.50ms");
+        assert_eq!(format_duration_in(duration, TimeUnit::Secs), "0.00s");
+
+        let duration = Duration::from_secs(2);
+        assert_eq!(format_duration_in(duration, TimeUnit::Micros), "2000000µs");
+        assert_eq!(format_duration_in(duration, TimeUnit::Millis), "2000.00ms");
+
+        // cycle visits every unit once
+        let mut unit = TimeUnit::Auto;
+        for _ in 0..4 {
+            unit = unit.next();
+        }
+        assert_eq!(unit, TimeUnit::Auto);
+        assert_eq!(TimeUnit::from_arg("ms"), Some(TimeUnit::Millis));
+        assert_eq!(TimeUnit::from_arg("min"), None);
+    }
+
+    #[test]
+    fn test_uc_timestamp_grouping() {
+        let uc = |us| format_uc_timestamp(EmbassyTime::from_micros(us));
+        assert_eq!(uc(0), "0µs");
+        assert_eq!(uc(999), "999µs");
+        assert_eq!(uc(1_000), "1 000µs");
+        assert_eq!(uc(123_456), "123 456µs");
+        // about 3 days since boot
+        assert_eq!(uc(259_200_000_001), "259 200 000 001µs");
+        assert_eq!(uc(u64::MAX), "18 446 744 073 709 551 615µs");
+    }
+}
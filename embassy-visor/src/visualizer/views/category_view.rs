@@ -0,0 +1,71 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Styled, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
+
+use crate::{
+    tracing::stats::category_stats::CategoryStats,
+    visualizer::{cpu_usage_colors, time_unit::format_duration},
+};
+
+/// Width of the category name column
+const NAME_COLUMN_WIDTH: u16 = 20;
+/// Width of each number column
+const NUMBER_COLUMN_WIDTH: u16 = 12;
+
+/// CPU usage and waiting times aggregated per user-defined task category
+pub struct CategoryView(pub Vec<CategoryStats>);
+
+impl CategoryView {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + header + one row per category (or the empty note)
+        3 + (self.0.len() as u16).max(1)
+    }
+}
+
+impl Widget for &CategoryView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(" Task Categories ".bold()));
+
+        if self.0.is_empty() {
+            Paragraph::new(Line::from(" No tasks yet".dark_gray()))
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+
+        let header = Row::new([
+            Cell::from(" Category"),
+            Cell::from("Tasks"),
+            Cell::from("CPU"),
+            Cell::from("Avg. wait"),
+            Cell::from("Max. wait"),
+        ])
+        .bold();
+
+        let rows = self.0.iter().map(|category| {
+            Row::new([
+                Cell::from(format!(" {}", category.name)),
+                Cell::from(category.task_count.to_string()),
+                Cell::from(
+                    format!("{:.2}%", category.cpu_utilization_percent)
+                        .set_style(cpu_usage_colors(category.cpu_utilization_percent)),
+                ),
+                Cell::from(format_duration(category.avg_waiting_time)),
+                Cell::from(format_duration(category.max_waiting_time)),
+            ])
+        });
+
+        let widths = std::iter::once(Constraint::Length(NAME_COLUMN_WIDTH))
+            .chain([Constraint::Length(NUMBER_COLUMN_WIDTH); 4]);
+        Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,79 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
+
+use crate::tracing::channels::ChannelEdge;
+
+/// Width of the producer and consumer columns
+const TASK_COLUMN_WIDTH: u16 = 24;
+/// Width of the channel column (including the arrows)
+const CHANNEL_COLUMN_WIDTH: u16 = 32;
+/// Width of each count column
+const COUNT_COLUMN_WIDTH: u16 = 10;
+
+/// Producer --> consumer graph of the tasks communicating over channels, one edge per row
+pub struct ChannelView<'a>(pub &'a [ChannelEdge]);
+
+impl<'a> ChannelView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + header + one row per edge (or the empty note)
+        3 + (self.0.len() as u16).max(1)
+    }
+}
+
+impl<'a> Widget for &'a ChannelView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(" Channels (producer --> consumer) ".bold()));
+
+        if self.0.is_empty() {
+            Paragraph::new(Line::from(
+                " No channel traffic (enable `channel-trace` in the beacon)".dark_gray(),
+            ))
+            .block(block)
+            .render(area, buf);
+            return;
+        }
+
+        let header = Row::new([
+            Cell::from(" Producer"),
+            Cell::from("Channel"),
+            Cell::from("Consumer"),
+            Cell::from("Sent"),
+            Cell::from("Received"),
+        ])
+        .bold();
+
+        let rows = self.0.iter().map(|edge| {
+            Row::new([
+                Cell::from(format!(" {}", edge.producer)),
+                Cell::from(Line::from(vec![
+                    "──▶ ".dark_gray(),
+                    edge.channel.clone().cyan(),
+                    " ──▶".dark_gray(),
+                ])),
+                Cell::from(edge.consumer.clone()),
+                Cell::from(edge.sent.to_string()),
+                Cell::from(edge.received.to_string()),
+            ])
+        });
+
+        let widths = [
+            Constraint::Length(TASK_COLUMN_WIDTH),
+            Constraint::Length(CHANNEL_COLUMN_WIDTH),
+            Constraint::Length(TASK_COLUMN_WIDTH),
+            Constraint::Length(COUNT_COLUMN_WIDTH),
+            Constraint::Length(COUNT_COLUMN_WIDTH),
+        ];
+
+        Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -1,62 +1,254 @@
+use std::sync::atomic::Ordering;
+
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Layout, Rect},
-    style::{Styled, Stylize},
+    layout::Rect,
+    style::{Style, Styled, Stylize},
     text::Line,
     widgets::{Block, Borders, Widget},
 };
 
 use crate::{
     tracing::stats::core_stats::CoreStats,
-    visualizer::{cpu_usage_colors, views::executor_view::ExecutorView},
+    visualizer::{
+        CORE_UTILIZATION_IN_TITLE, LayoutMode, ViewConfig, VisibleChild, core_border_color,
+        core_display_name, cpu_usage_colors,
+        views::executor_view::{ExecutorView, scheduling_overhead_span, stacked_task_row},
+        visible_children,
+    },
 };
 
-pub struct CoreView<'a>(pub &'a CoreStats);
+pub struct CoreView<'a>(pub &'a CoreStats, pub ViewConfig);
 
 impl<'a> CoreView<'a> {
     pub fn get_min_height(&self) -> u16 {
-        // Minimum height is 1 (for border) + sum of executor min heights
-        2 + self
-            .0
-            .executors
-            .iter()
-            .map(|e| {
-                let executor_view = ExecutorView(e);
-                executor_view.get_min_height()
-            })
-            .sum::<u16>()
+        // Minimum height is the border (top+bottom, compact: top only) + sum of executor min heights
+        let border_height = match self.1.layout_mode {
+            LayoutMode::Full => 2,
+            LayoutMode::Compact => 1,
+        };
+
+        border_height
+            + self
+                .0
+                .executors
+                .iter()
+                .map(|e| {
+                    let executor_view = ExecutorView(e, self.1);
+                    executor_view.get_min_height()
+                })
+                .sum::<u16>()
     }
-}
 
-impl<'a> Widget for &'a CoreView<'a> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let mut title = Line::from(format!(" Core {} ", self.0.core_id).bold());
+    /// Row of the task within the view (None if it runs on another core)
+    pub fn task_row(&self, task_id: u32) -> Option<u16> {
+        stacked_task_row(&self.0.executors, self.1, task_id)
+    }
 
-        // Add CPU Utilization when more than two executors
-        if self.0.executors.len() > 1 {
+    /// Executors visible in the inner area when the first `skip` rows of the view are
+    /// scrolled out
+    pub fn visible_executors(&self, skip: u16, inner: Rect) -> Vec<VisibleChild> {
+        visible_children(
+            self.0
+                .executors
+                .iter()
+                .map(|e| ExecutorView(e, self.1).get_min_height()),
+            skip.saturating_sub(1),
+            inner,
+        )
+    }
+
+    fn title(&self) -> Line<'static> {
+        let mut title =
+            Line::from(format!(" {} ", core_display_name(self.0.device_id, self.0.core_id)).bold());
+
+        if CORE_UTILIZATION_IN_TITLE.load(Ordering::Relaxed) {
             title += format!(" ( {:.2}% ) ", self.0.cpu_utilization_percent)
                 .set_style(cpu_usage_colors(self.0.cpu_utilization_percent));
         }
 
-        let block = Block::new().borders(Borders::ALL).title(title);
-        let block_inner = block.inner(area);
+        // Nested preemption (more than one executor stacked) hints at priority pressure
+        if self.0.preemption_depth > 0 {
+            let depth = format!(" preempted x{} ", self.0.preemption_depth);
+            title += if self.0.preemption_depth > 1 {
+                depth.red().bold()
+            } else {
+                depth.yellow()
+            };
+        }
 
-        let chunks = Layout::default()
-            .constraints(
-                self.0
-                    .executors
-                    .iter()
-                    .map(|e| Constraint::Length(ExecutorView(e).get_min_height()))
-                    .collect::<Vec<_>>(),
-            )
-            .split(block_inner);
+        // With a single executor its own title shows the same
+        if self.0.executors.len() > 1
+            && let Some(overhead_percent) = self.0.scheduling_overhead_percent()
+        {
+            title += scheduling_overhead_span(overhead_percent);
+        }
+
+        // Thread mode waiting for an event: the power-relevant kind of idle
+        if self.0.asleep {
+            title += " asleep (WFE) ".blue();
+        }
 
-        // Render each executor view
-        for (executor_stat, chunk) in self.0.executors.iter().zip(chunks.to_vec()) {
-            let executor_view = ExecutorView(executor_stat);
-            executor_view.render(chunk, buf);
+        // The beacon's topology does not list this core (the declared idle cores may be wrong)
+        if self.0.undeclared {
+            title += " undeclared ".yellow();
+        }
+
+        title
+    }
+
+    /// Block of the rows from `skip` on shown in an area of the height: the top border with
+    /// the title and the bottom border only when not scrolled out
+    pub fn block(&self, skip: u16, height: u16) -> Block<'static> {
+        let top_visible = skip == 0;
+        let bottom_visible = skip.saturating_add(height) >= self.get_min_height();
+
+        // Compact layout drops the side borders to save columns
+        let mut borders = match self.1.layout_mode {
+            LayoutMode::Full => Borders::LEFT | Borders::RIGHT,
+            LayoutMode::Compact => Borders::NONE,
+        };
+        if top_visible {
+            borders |= Borders::TOP;
+        }
+        if bottom_visible && self.1.layout_mode == LayoutMode::Full {
+            borders |= Borders::BOTTOM;
+        }
+
+        // Colored by the core's busyness to spot a hot core across the dashboard
+        let block = Block::new()
+            .borders(borders)
+            .border_style(Style::new().fg(core_border_color(self.0.cpu_utilization_percent)));
+        if top_visible {
+            block.title(self.title())
+        } else {
+            block
+        }
+    }
+
+    /// Render the rows of the view from `skip` on into the area (only the visible executors
+    /// and tasks)
+    pub fn render_window(&self, skip: u16, area: Rect, buf: &mut Buffer) {
+        let block = self.block(skip, area.height);
+        let block_inner = block.inner(area);
+
+        for executor in self.visible_executors(skip, block_inner) {
+            ExecutorView(&self.0.executors[executor.index], self.1).render_window(
+                executor.skip,
+                executor.area,
+                buf,
+            );
         }
 
         block.render(area, buf);
     }
 }
+
+impl<'a> Widget for &'a CoreView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.render_window(0, area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+
+    use crate::{
+        test_support::executor_stats,
+        tracing::stats::{core_stats::CoreStats, executor_stats::ExecutorStats},
+        visualizer::{CORE_NAMES, LayoutMode, ViewConfig},
+    };
+
+    use super::CoreView;
+
+    fn rendered(core: &CoreStats) -> Buffer {
+        let config = ViewConfig {
+            layout_mode: LayoutMode::Full,
+            name_width: 10,
+            executor_indent: 5,
+            selected_task_id: None,
+        };
+        let area = Rect::new(0, 0, 40, 2);
+        let mut buf = Buffer::empty(area);
+        (&CoreView(core, config)).render(area, &mut buf);
+        buf
+    }
+
+    fn rendered_title(core: &CoreStats) -> String {
+        let buf = rendered(core);
+        (0..buf.area.width)
+            .map(|x| buf[(x, 0)].symbol())
+            .collect::<String>()
+    }
+
+    /// Run `f` with the (global) core names set to `names`, restoring the previous ones after
+    fn with_core_names(names: &[(u32, &str)], f: impl FnOnce()) {
+        static LOCK: Mutex<()> = Mutex::new(());
+        let _lock = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        struct Restore(BTreeMap<u32, String>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                *CORE_NAMES.write().unwrap() = std::mem::take(&mut self.0);
+            }
+        }
+        let names = names
+            .iter()
+            .map(|(core_id, name)| (*core_id, name.to_string()))
+            .collect();
+        let _restore = Restore(std::mem::replace(&mut *CORE_NAMES.write().unwrap(), names));
+        f();
+    }
+
+    #[test]
+    fn test_core_name_in_title() {
+        with_core_names(&[(7, "NET")], || {
+            assert!(rendered_title(&CoreStats::empty(7)).contains(" NET "));
+            assert!(!rendered_title(&CoreStats::empty(7)).contains("Core 7"));
+            // unnamed core falls back to its id
+            assert!(rendered_title(&CoreStats::empty(8)).contains(" Core 8 "));
+        });
+    }
+
+    #[test]
+    fn test_single_executor_core_utilization() {
+        let core = CoreStats {
+            core_id: 0,
+            device_id: 0,
+            executors: vec![ExecutorStats {
+                cpu_utilization_percent: 85.0,
+                ..executor_stats(1, 0, "Executor", Vec::new())
+            }],
+            cpu_utilization_percent: 85.0,
+            preemption_depth: 0,
+            asleep: false,
+            undeclared: false,
+        };
+
+        assert!(rendered_title(&core).contains("( 85.00% )"));
+        // hot core stands out by its border
+        assert_eq!(rendered(&core)[(0, 0)].fg, Color::Red);
+        assert_eq!(rendered(&CoreStats::empty(1))[(0, 0)].fg, Color::Reset);
+    }
+
+    #[test]
+    fn test_preemption_depth_in_title() {
+        let mut core = CoreStats::empty(0);
+        assert!(!rendered_title(&core).contains("preempted"));
+
+        core.preemption_depth = 2;
+        assert!(rendered_title(&core).contains(" preempted x2 "));
+    }
+
+    #[test]
+    fn test_undeclared_core_in_title() {
+        let mut core = CoreStats::empty(3);
+        assert!(!rendered_title(&core).contains("undeclared"));
+
+        core.undeclared = true;
+        assert!(rendered_title(&core).contains(" undeclared "));
+    }
+}
@@ -0,0 +1,166 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Styled, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::tracing::{
+    clock::ALIGNMENT_WINDOW,
+    parse_stats::ParseStats,
+    stats::instance_stats::{InstanceStats, IntegrityOffender},
+};
+
+/// Number of tasks/executors listed with the most integrity issues
+const MAX_LISTED_OFFENDERS: usize = 5;
+
+/// Diagnostics of the trace pipeline (trace health, parse error histogram and the tasks or
+/// executors with the most integrity issues)
+pub struct DiagnosticsView<'a>(pub &'a InstanceStats);
+
+impl<'a> DiagnosticsView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + health line + summary line + clock line + one line per error
+        // variant + one line per listed offender + lost wakeup line (if any) + one line per
+        // ping-pong pair
+        let lost_wakeup_lines = (self.0.lost_wakeup_count() > 0) as u16;
+        5 + self.parse_stats().error_counts.len() as u16
+            + self.offenders().len() as u16
+            + lost_wakeup_lines
+            + self.0.ping_pong_pairs().len() as u16
+    }
+
+    fn parse_stats(&self) -> &ParseStats {
+        &self.0.parse_stats
+    }
+
+    fn offenders(&self) -> Vec<IntegrityOffender> {
+        let mut offenders = self.0.integrity_offenders();
+        offenders.truncate(MAX_LISTED_OFFENDERS);
+        offenders
+    }
+
+    fn error_color(&self) -> Color {
+        match self.parse_stats().error_count() {
+            0 => Color::Green,
+            _ => Color::Red,
+        }
+    }
+}
+
+/// Color of the trace health score
+pub fn health_color(health_percent: f32) -> Color {
+    if health_percent >= 99.0 {
+        Color::Green
+    } else if health_percent >= 90.0 {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+impl<'a> Widget for &'a DiagnosticsView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(" Diagnostics ".bold()));
+
+        let parse_stats = self.parse_stats();
+        let health_percent = self.0.trace_health_percent();
+        let integrity = self.0.integrity();
+        let mut lines = vec![
+            Line::from(vec![
+                " Trace health: ".into(),
+                format!("{:.1}%", health_percent).set_style(health_color(health_percent)),
+                format!(
+                    "  out of order: {}  gaps: {}  impossible transitions: {}",
+                    integrity.out_of_order, integrity.gaps, integrity.impossible_transitions
+                )
+                .into(),
+            ]),
+            Line::from(format!(
+                " Trace lines: {}  parsed: {}  failed: ",
+                parse_stats.total_count(),
+                parse_stats.parsed_count
+            )) + format!(
+                "{} ({:.2}%)",
+                parse_stats.error_count(),
+                parse_stats.error_percent()
+            )
+            .set_style(self.error_color()),
+        ];
+
+        // Stale alignment: neither task activity nor heartbeats arrived recently
+        lines.push(match self.0.clock {
+            Some(clock) => {
+                let ago = clock.last_sample.diff_to_now();
+                Line::from(format!(
+                    " Clock offset (PC - uC): {:+.3}ms  aligned ",
+                    clock.offset_us as f64 / 1000.0
+                )) + format!("{:.1}s ago", ago.as_secs_f32()).set_style(if ago > ALIGNMENT_WINDOW {
+                    Color::Yellow
+                } else {
+                    Color::Green
+                })
+            }
+            None => Line::from(" Clock offset (PC - uC): not aligned yet"),
+        });
+
+        // Lost wakeups are likely firmware bugs (not trace issues) --> highlighted
+        let lost_wakeup_count = self.0.lost_wakeup_count();
+        if lost_wakeup_count > 0 {
+            let tasks = self.0.lost_wakeup_tasks();
+            let current = if tasks.is_empty() {
+                String::new()
+            } else {
+                format!("  ready now: {}", tasks.join(", "))
+            };
+            lines.push(Line::from(
+                format!(
+                    " Lost wakeups: {} (task ready while its executor stays idle, likely a waker bug){}",
+                    lost_wakeup_count, current
+                )
+                .red()
+                .bold(),
+            ));
+        }
+
+        // Ping-pongs burn CPU without progress --> highlighted like lost wakeups
+        for (first, second, switches) in self.0.ping_pong_pairs() {
+            lines.push(Line::from(
+                format!(
+                    " Ping-pong: {} <-> {} ({} switches without idling, likely waking each other)",
+                    first, second, switches
+                )
+                .red()
+                .bold(),
+            ));
+        }
+
+        // Error histogram sorted by count (most frequent first)
+        let mut error_counts: Vec<_> = parse_stats.error_counts.iter().collect();
+        error_counts.sort_by(|a, b| b.1.cmp(a.1));
+        for (error, count) in error_counts {
+            let percent = *count as f32 / parse_stats.total_count() as f32 * 100.0;
+            lines.push(Line::from(format!(
+                "   {:>8} ({:>6.2}%)  {:?}: {}",
+                count, percent, error, error
+            )));
+        }
+
+        // Drill-down of the integrity issues
+        for offender in self.offenders() {
+            lines.push(Line::from(format!(
+                "   {:>8} issues  {}  (out of order: {}, gaps: {}, impossible: {})",
+                offender.counters.total(),
+                offender.name,
+                offender.counters.out_of_order,
+                offender.counters.gaps,
+                offender.counters.impossible_transitions
+            )));
+        }
+
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
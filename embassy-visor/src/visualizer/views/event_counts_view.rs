@@ -0,0 +1,72 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
+
+use crate::tracing::event_counts::EventCount;
+
+/// Width of the event name column (longest beacon event name plus spacing)
+const EVENT_COLUMN_WIDTH: u16 = 20;
+/// Width of each count column
+const COUNT_COLUMN_WIDTH: u16 = 12;
+
+/// Trace events received per kind, overall and within the latest second, to see which events
+/// the beacon publishes (and which are missing)
+pub struct EventCountsView<'a>(pub &'a [EventCount]);
+
+impl<'a> EventCountsView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + header + one row per received kind (or the empty note)
+        3 + self.0.len().max(1) as u16
+    }
+}
+
+impl<'a> Widget for &'a EventCountsView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(" Trace events ".bold()));
+
+        if self.0.is_empty() {
+            Paragraph::new(Line::from(" No trace events received yet".dark_gray()))
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+
+        let header = Row::new([
+            Cell::from(" Event"),
+            Cell::from("Total"),
+            Cell::from("Per second"),
+        ])
+        .bold();
+
+        // kinds received before but not within the latest second are dimmed
+        let rows = self.0.iter().map(|count| {
+            let row = Row::new([
+                Cell::from(format!(" {}", count.kind.default_name())),
+                Cell::from(count.total.to_string()),
+                Cell::from(format!("{}/s", count.per_second)),
+            ]);
+            if count.per_second == 0 {
+                row.dark_gray()
+            } else {
+                row
+            }
+        });
+
+        let widths = [
+            Constraint::Length(EVENT_COLUMN_WIDTH),
+            Constraint::Length(COUNT_COLUMN_WIDTH),
+            Constraint::Min(COUNT_COLUMN_WIDTH),
+        ];
+
+        Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .render(area, buf);
+    }
+}
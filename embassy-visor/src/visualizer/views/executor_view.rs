@@ -1,27 +1,87 @@
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Layout, Rect},
-    style::{Styled, Stylize},
-    text::Line,
+    layout::Rect,
+    style::{Style, Styled, Stylize},
+    text::{Line, Span},
     widgets::{Block, Borders, Padding, Widget},
 };
 
 use crate::{
     tracing::stats::executor_stats::ExecutorStats,
-    visualizer::{cpu_usage_colors, views::task_view::TaskView},
+    visualizer::{
+        ViewConfig, VisibleChild, cpu_usage_colors, task_count_trend::sparkline,
+        time_unit::format_duration, views::task_view::TaskView, visible_children,
+    },
 };
 
-pub struct ExecutorView<'a>(pub &'a ExecutorStats);
+/// Budget utilization (share of the time between wakeups spent working) flagged as overload
+pub const BUDGET_OVERLOAD_PERCENT: f32 = 80.0;
+
+/// Scheduling overhead (share of the active time spent scheduling) highlighted as high
+pub const HIGH_SCHEDULING_OVERHEAD_PERCENT: f32 = 50.0;
+
+/// Scheduling overhead for a title, highlighted when high
+pub fn scheduling_overhead_span(overhead_percent: f32) -> Span<'static> {
+    let span = format!(" sched. {:.0}% ", overhead_percent);
+    if overhead_percent >= HIGH_SCHEDULING_OVERHEAD_PERCENT {
+        span.yellow()
+    } else {
+        span.dark_gray()
+    }
+}
+
+pub struct ExecutorView<'a>(pub &'a ExecutorStats, pub ViewConfig);
+
+/// Row of the task within executors stacked below a top border (cores and merged view)
+pub fn stacked_task_row(
+    executors: &[ExecutorStats],
+    view_config: ViewConfig,
+    task_id: u32,
+) -> Option<u16> {
+    let mut top = 1;
+    for executor in executors {
+        let executor_view = ExecutorView(executor, view_config);
+        if let Some(row) = executor_view.task_row(task_id) {
+            return Some(top + row);
+        }
+        top += executor_view.get_min_height();
+    }
+    None
+}
 
 impl<'a> ExecutorView<'a> {
     pub fn get_min_height(&self) -> u16 {
         // Minimum height is 1 (for border) + number of tasks
         1 + self.0.tasks.len() as u16
     }
-}
 
-impl<'a> Widget for &'a ExecutorView<'a> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
+    /// Row of the task within the view (None if the task is not shown by it)
+    pub fn task_row(&self, task_id: u32) -> Option<u16> {
+        let index = self.0.tasks.iter().position(|t| t.task_id == task_id)?;
+        // one row each, below the title row
+        Some(1 + index as u16)
+    }
+
+    /// Tasks (one row each, below the title row) visible in the inner area when the first
+    /// `skip` rows of the view are scrolled out
+    pub fn visible_tasks(&self, skip: u16, inner: Rect) -> Vec<VisibleChild> {
+        visible_children(
+            self.0.tasks.iter().map(|_| 1),
+            skip.saturating_sub(1),
+            inner,
+        )
+    }
+
+    /// Warning flag, dimmed once the user acknowledged the warnings of the executor
+    fn warning_span(&self, text: String, emphasis: Style) -> Span<'static> {
+        if self.0.warnings_acknowledged {
+            text.dark_gray()
+        } else {
+            Span::styled(text, emphasis)
+        }
+    }
+
+    fn title(&self) -> Line<'static> {
         let mut title = Line::from(format!("   {} ", self.0.name).bold());
 
         // Add CPU Utilization when more than two tasks
@@ -30,27 +90,92 @@ impl<'a> Widget for &'a ExecutorView<'a> {
                 .set_style(cpu_usage_colors(self.0.cpu_utilization_percent));
         }
 
-        let block = Block::new()
-            .borders(Borders::TOP)
-            .title(title)
-            .padding(Padding::left(5));
-        let block_inner = block.inner(area);
+        // Warn when the executor is about to miss its wakeups
+        if let Some(budget) = self.0.budget_utilization_percent
+            && budget >= BUDGET_OVERLOAD_PERCENT
+        {
+            title += self.warning_span(
+                format!(" OVERLOAD: budget {:.0}% ", budget),
+                Style::new().white().on_red().bold(),
+            );
+        }
 
-        let chunks = Layout::default()
-            .constraints(
-                self.0
-                    .tasks
-                    .iter()
-                    .map(|_| Constraint::Length(1))
-                    .collect::<Vec<_>>(),
+        // Never idle: a task does not yield (a lot of short polls would still go idle)
+        if self.0.pegged {
+            title += self.warning_span(
+                String::from(" PEGGED: never idle "),
+                Style::new().white().on_red().bold(),
+            );
+        }
+
+        // Tasks spawned but never ending
+        if self.0.task_count_growing {
+            title += self.warning_span(
+                String::from(" TASK COUNT GROWING "),
+                Style::new().black().on_yellow().bold(),
+            );
+        }
+        if let [.., latest] = self.0.task_count_history[..]
+            && self.0.task_count_history.len() > 1
+        {
+            title += format!(
+                " tasks {} {} ",
+                sparkline(&self.0.task_count_history),
+                latest
             )
-            .split(block_inner);
+            .dark_gray();
+        }
 
-        // Render each task
-        for (task_stat, chunk) in self.0.tasks.iter().zip(chunks.to_vec()) {
-            TaskView(task_stat).render(chunk, buf);
+        if let Some(overhead_percent) = self.0.scheduling_overhead_percent() {
+            title += scheduling_overhead_span(overhead_percent);
+        }
+
+        // One long poll blocks every other task of the executor
+        if let Some(worst_poll) = self.0.worst_poll {
+            let task_name = self
+                .0
+                .tasks
+                .iter()
+                .find(|t| t.task_id == worst_poll.task_id)
+                .map_or_else(
+                    || format!("Task 0x{:X}", worst_poll.task_id),
+                    |t| t.name.clone(),
+                );
+            title += format!(
+                " worst poll: {} {} ",
+                task_name,
+                format_duration(worst_poll.duration)
+            )
+            .dark_gray();
+        }
+
+        title
+    }
+
+    /// Block of the rows from `skip` on: the title is the top border, gone once scrolled out
+    pub fn block(&self, skip: u16) -> Block<'static> {
+        match skip {
+            0 => Block::new().borders(Borders::TOP).title(self.title()),
+            _ => Block::new(),
+        }
+        .padding(Padding::left(self.1.executor_indent))
+    }
+
+    /// Render the rows of the view from `skip` on into the area (only the visible tasks)
+    pub fn render_window(&self, skip: u16, area: Rect, buf: &mut Buffer) {
+        let block = self.block(skip);
+        let block_inner = block.inner(area);
+
+        for task in self.visible_tasks(skip, block_inner) {
+            TaskView(&self.0.tasks[task.index], self.1).render(task.area, buf);
         }
 
         block.render(area, buf);
     }
 }
+
+impl<'a> Widget for &'a ExecutorView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.render_window(0, area, buf);
+    }
+}
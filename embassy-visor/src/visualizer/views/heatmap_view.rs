@@ -0,0 +1,120 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::{
+    tracing::{stats::heatmap::Heatmap, time::EmbassyTime},
+    visualizer::{task_colors::TaskColors, time_unit::format_duration},
+};
+
+/// Number of tasks shown at once (more are scrolled)
+pub const HEATMAP_ROWS: u16 = 10;
+
+/// Width of the task name column
+const NAME_COLUMN_WIDTH: u16 = 20;
+
+/// View windows to zoom through (in seconds)
+const VIEW_WINDOWS_S: [u64; 8] = [1, 2, 5, 10, 30, 60, 120, 300];
+
+/// Next shorter (`zoom_in`) or longer view window. A window set on the command line that is
+/// not one of the steps zooms to the closest step in that direction.
+pub fn zoom_view_window(window: EmbassyTime, zoom_in: bool) -> EmbassyTime {
+    let windows = VIEW_WINDOWS_S.map(|s| EmbassyTime::from_millis(s * 1000));
+    let next = if zoom_in {
+        windows.into_iter().rev().find(|w| *w < window)
+    } else {
+        windows.into_iter().find(|w| *w > window)
+    };
+    next.unwrap_or(window)
+}
+
+/// Task activity over time: one row per task, one column per time bucket (newest right),
+/// shaded by the share of the bucket the task was running, in the color of the task.
+/// `scroll` counts the tasks scrolled out at the top.
+pub struct HeatmapView<'a> {
+    pub heatmap: Option<&'a Heatmap>,
+    pub scroll: usize,
+    pub colors: &'a TaskColors,
+}
+
+impl<'a> HeatmapView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + task rows
+        2 + HEATMAP_ROWS
+    }
+
+    /// Number of time buckets fitting into the width of the view
+    pub fn bucket_count(width: u16) -> usize {
+        width.saturating_sub(2 + NAME_COLUMN_WIDTH) as usize
+    }
+
+    /// Tasks that can be scrolled out at the top
+    pub fn max_scroll(row_count: usize) -> usize {
+        row_count.saturating_sub(HEATMAP_ROWS as usize)
+    }
+}
+
+/// Cell of a bucket: denser shades for more running time
+fn intensity_span(intensity: f32, color: Color) -> Span<'static> {
+    let symbol = match intensity {
+        x if x <= 0.0 => return Span::from(" "),
+        x if x < 0.25 => "░",
+        x if x < 0.5 => "▒",
+        x if x < 0.75 => "▓",
+        _ => "█",
+    };
+    Span::from(symbol).fg(color)
+}
+
+impl<'a> Widget for &'a HeatmapView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut title = Line::from(" Heatmap ".bold());
+        let block = Block::new().borders(Borders::ALL);
+
+        let Some(heatmap) = self.heatmap.filter(|h| !h.rows.is_empty()) else {
+            Paragraph::new(Line::from(" No tasks yet".dark_gray()))
+                .block(block.title(title))
+                .render(area, buf);
+            return;
+        };
+
+        title.push_span(
+            format!(
+                "[last {}, 1 column = {}] ",
+                format_duration(heatmap.window.as_duration()),
+                format_duration(heatmap.bucket_length.as_duration())
+            )
+            .dark_gray(),
+        );
+        let start = self.scroll.min(HeatmapView::max_scroll(heatmap.rows.len()));
+        let end = (start + HEATMAP_ROWS as usize).min(heatmap.rows.len());
+        let position = Line::from(format!(" {}-{} of {} ", start + 1, end, heatmap.rows.len()));
+
+        let lines: Vec<Line> = heatmap.rows[start..end]
+            .iter()
+            .map(|row| {
+                let color = self.colors.get(row.task_id);
+                let name: String = row
+                    .name
+                    .chars()
+                    .take(NAME_COLUMN_WIDTH as usize - 2)
+                    .collect();
+                let mut line = Line::from(
+                    format!(" {:<width$} ", name, width = NAME_COLUMN_WIDTH as usize - 2).fg(color),
+                );
+                for intensity in &row.intensities {
+                    line.push_span(intensity_span(*intensity, color));
+                }
+                line
+            })
+            .collect();
+
+        Paragraph::new(lines)
+            .block(block.title(title).title(position.right_aligned()))
+            .render(area, buf);
+    }
+}
@@ -1,14 +1,13 @@
-use ratatui::{
-    buffer::Buffer,
-    layout::{Constraint, Layout, Rect},
-    widgets::Widget,
-};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
 
 use crate::{
-    tracing::stats::instance_stats::InstanceStats, visualizer::views::core_view::CoreView,
+    tracing::stats::instance_stats::InstanceStats,
+    visualizer::{ViewConfig, VisibleChild, views::core_view::CoreView, visible_children},
 };
 
-pub struct InstanceView<'a>(pub &'a InstanceStats);
+/// All cores stacked, showing the rows from the scroll offset (the third field) on. Only the
+/// cores, executors and tasks within the area are laid out and rendered.
+pub struct InstanceView<'a>(pub &'a InstanceStats, pub ViewConfig, pub u16);
 
 impl<'a> InstanceView<'a> {
     pub fn get_min_height(&self) -> u16 {
@@ -17,34 +16,159 @@ impl<'a> InstanceView<'a> {
             .0
             .core_stats
             .iter()
-            .map(|c| CoreView(c).get_min_height())
+            .map(|c| CoreView(c, self.1).get_min_height())
             .sum();
         let spacing = if self.0.core_stats.is_empty() {
             0
         } else {
             self.0.core_stats.len() as u16 - 1
         };
-     core_heights + spacing
+        core_heights + spacing
+    }
+
+    /// Row of the task within the stacked cores (None if it is not displayed)
+    pub fn task_row(&self, task_id: u32) -> Option<u16> {
+        let mut top = 0;
+        for core in &self.0.core_stats {
+            let core_view = CoreView(core, self.1);
+            if let Some(row) = core_view.task_row(task_id) {
+                return Some(top + row);
+            }
+            top += core_view.get_min_height();
+        }
+        None
+    }
+
+    /// Cores visible in the area at the scroll offset
+    pub fn visible_cores(&self, area: Rect) -> Vec<VisibleChild> {
+        visible_children(
+            self.0
+                .core_stats
+                .iter()
+                .map(|c| CoreView(c, self.1).get_min_height()),
+            self.2,
+            area,
+        )
     }
 }
 
 impl Widget for &InstanceView<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let chunks = Layout::default()
-            .constraints(
-                self.0
-                    .core_stats
-                    .iter()
-                    .map(|c| Constraint::Length(CoreView(c).get_min_height()))
-                    .collect::<Vec<_>>(),
-            )
-            // .spacing(1)
-            .split(area);
-
-        // Render each core view
-        for (core_stat, chunk) in self.0.core_stats.iter().zip(chunks.to_vec()) {
-            let core_view = CoreView(core_stat);
-            core_view.render(chunk, buf);
+        for core in self.visible_cores(area) {
+            CoreView(&self.0.core_stats[core.index], self.1)
+                .render_window(core.skip, core.area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support::{executor_stats, task_stats},
+        tracing::stats::{core_stats::CoreStats, executor_stats::ExecutorStats},
+        visualizer::{LayoutMode, views::executor_view::ExecutorView},
+    };
+
+    fn executor(executor_id: u32, task_count: u32) -> ExecutorStats {
+        executor_stats(
+            executor_id,
+            0,
+            &format!("executor_{}", executor_id),
+            (0..task_count)
+                .map(|i| task_stats(i, &format!("e{}_task_{}", executor_id, i)))
+                .collect(),
+        )
+    }
+
+    /// Tasks the view constructs for the area: (executor id, task name)
+    fn visible_tasks(view: &InstanceView, area: Rect) -> Vec<(u32, String)> {
+        let mut tasks = Vec::new();
+        for core in view.visible_cores(area) {
+            let core_view = CoreView(&view.0.core_stats[core.index], view.1);
+            let core_inner = core_view
+                .block(core.skip, core.area.height)
+                .inner(core.area);
+            for executor in core_view.visible_executors(core.skip, core_inner) {
+                let executor_stats = &core_view.0.executors[executor.index];
+                let executor_view = ExecutorView(executor_stats, view.1);
+                let executor_inner = executor_view.block(executor.skip).inner(executor.area);
+                for task in executor_view.visible_tasks(executor.skip, executor_inner) {
+                    tasks.push((
+                        executor_stats.executor_id,
+                        executor_stats.tasks[task.index].name.clone(),
+                    ));
+                }
+            }
         }
+        tasks
+    }
+
+    #[test]
+    fn test_only_visible_tasks_rendered() {
+        // 500 tasks on two executors: core border (row 0), executor 1 title (row 1) and tasks
+        // (rows 2-251), executor 2 title (row 252) and tasks (rows 253-502), border (row 503)
+        let stats = InstanceStats {
+            core_stats: vec![CoreStats {
+                core_id: 0,
+                device_id: 0,
+                executors: vec![executor(1, 250), executor(2, 250)],
+                cpu_utilization_percent: 0.0,
+                preemption_depth: 0,
+                asleep: false,
+                undeclared: false,
+            }],
+            tasks_count: 500,
+            executor_count: 2,
+            ..Default::default()
+        };
+        let config = ViewConfig {
+            layout_mode: LayoutMode::Full,
+            name_width: 16,
+            executor_indent: 2,
+            selected_task_id: None,
+        };
+        let area = Rect::new(0, 0, 60, 20);
+        assert_eq!(InstanceView(&stats, config, 0).get_min_height(), 504);
+
+        let row = |buf: &Buffer, y: u16| {
+            (0..area.width)
+                .map(|x| buf[(x, y)].symbol())
+                .collect::<String>()
+        };
+
+        // rows 300-319: tasks 47-66 of executor 2
+        let view = InstanceView(&stats, config, 300);
+        let tasks = visible_tasks(&view, area);
+        assert_eq!(tasks.len(), 20);
+        assert_eq!(tasks[0], (2, String::from("e2_task_47")));
+        assert_eq!(tasks[19], (2, String::from("e2_task_66")));
+        let mut buf = Buffer::empty(area);
+        (&view).render(area, &mut buf);
+        assert!(row(&buf, 0).contains("e2_task_47"));
+        assert!(row(&buf, 19).contains("e2_task_66"));
+
+        // rows 245-264: the last tasks of executor 1, the title of executor 2 and its first tasks
+        let view = InstanceView(&stats, config, 245);
+        let tasks = visible_tasks(&view, area);
+        assert_eq!(tasks.len(), 19);
+        assert_eq!(tasks[0], (1, String::from("e1_task_243")));
+        assert_eq!(tasks[7], (2, String::from("e2_task_0")));
+        let mut buf = Buffer::empty(area);
+        (&view).render(area, &mut buf);
+        assert!(row(&buf, 0).contains("e1_task_243"));
+        assert!(row(&buf, 7).contains("executor_2"));
+        assert!(row(&buf, 19).contains("e2_task_11"));
+        // scrolled past the core's top: no title, but the side border
+        assert!(!buf.content().iter().any(|cell| cell.symbol() == "┌"));
+        assert_eq!(buf[(0, 0)].symbol(), "│");
+
+        // row of a task to scroll to (the ids repeat per executor, the first one is found)
+        assert_eq!(InstanceView(&stats, config, 0).task_row(47), Some(49));
+        let view = InstanceView(&stats, config, 49);
+        let mut buf = Buffer::empty(area);
+        (&view).render(area, &mut buf);
+        assert!(row(&buf, 0).contains("e1_task_47"));
+        assert_eq!(InstanceView(&stats, config, 0).task_row(250), None);
     }
 }
@@ -0,0 +1,85 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Widget},
+};
+
+use crate::{
+    tracing::stats::executor_stats::ExecutorStats,
+    visualizer::{
+        LayoutMode, ViewConfig,
+        views::executor_view::{ExecutorView, stacked_task_row},
+        visible_children,
+    },
+};
+
+/// Logical view of executors merged by name across all cores, showing the rows from the scroll
+/// offset (the third field) on
+pub struct MergedView<'a>(pub &'a [ExecutorStats], pub ViewConfig, pub u16);
+
+impl<'a> MergedView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Minimum height is the border (top+bottom, compact: top only) + sum of executor min heights
+        let border_height = match self.1.layout_mode {
+            LayoutMode::Full => 2,
+            LayoutMode::Compact => 1,
+        };
+
+        border_height
+            + self
+                .0
+                .iter()
+                .map(|e| ExecutorView(e, self.1).get_min_height())
+                .sum::<u16>()
+    }
+}
+
+impl<'a> MergedView<'a> {
+    /// Row of the task within the view (None if it is not displayed)
+    pub fn task_row(&self, task_id: u32) -> Option<u16> {
+        stacked_task_row(self.0, self.1, task_id)
+    }
+}
+
+impl<'a> Widget for &'a MergedView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let skip = self.2;
+        let top_visible = skip == 0;
+        let bottom_visible = skip.saturating_add(area.height) >= self.get_min_height();
+
+        // Compact layout drops the side borders to save columns
+        let mut borders = match self.1.layout_mode {
+            LayoutMode::Full => Borders::LEFT | Borders::RIGHT,
+            LayoutMode::Compact => Borders::NONE,
+        };
+        if top_visible {
+            borders |= Borders::TOP;
+        }
+        if bottom_visible && self.1.layout_mode == LayoutMode::Full {
+            borders |= Borders::BOTTOM;
+        }
+
+        let mut block = Block::new().borders(borders);
+        if top_visible {
+            block = block.title(Line::from(" All Cores (merged by executor name) ".bold()));
+        }
+        let block_inner = block.inner(area);
+
+        // Render only the visible merged executors
+        let heights = self
+            .0
+            .iter()
+            .map(|e| ExecutorView(e, self.1).get_min_height());
+        for executor in visible_children(heights, skip.saturating_sub(1), block_inner) {
+            ExecutorView(&self.0[executor.index], self.1).render_window(
+                executor.skip,
+                executor.area,
+                buf,
+            );
+        }
+
+        block.render(area, buf);
+    }
+}
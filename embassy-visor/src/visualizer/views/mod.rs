@@ -1,4 +1,17 @@
-pub mod instance_view;
+pub mod category_view;
+pub mod channel_view;
 pub mod core_view;
+pub mod diagnostics_view;
+pub mod event_counts_view;
 pub mod executor_view;
-pub mod task_view;
\ No newline at end of file
+pub mod heatmap_view;
+pub mod instance_view;
+pub mod merged_view;
+pub mod preemption_view;
+pub mod settings_view;
+pub mod state_legend_view;
+pub mod task_detail_view;
+pub mod task_view;
+pub mod timeline_view;
+pub mod timer_view;
+pub mod wake_chain_view;
@@ -0,0 +1,72 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
+
+use crate::{
+    tracing::stats::preemption_matrix::PreemptionMatrix, visualizer::time_unit::format_duration,
+};
+
+/// Width of the victim column
+const VICTIM_COLUMN_WIDTH: u16 = 24;
+/// Width of each aggressor column
+const AGGRESSOR_COLUMN_WIDTH: u16 = 14;
+
+/// Matrix of the time each executor (row) was preempted by each other executor (column)
+pub struct PreemptionView(pub PreemptionMatrix);
+
+impl PreemptionView {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + header + one row per preempted executor (or the empty note)
+        3 + (self.0.victims.len() as u16).max(1)
+    }
+}
+
+impl Widget for &PreemptionView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(" Preemptions (row preempted by column) ".bold()));
+
+        let matrix = &self.0;
+        if matrix.is_empty() {
+            Paragraph::new(Line::from(" No preemptions in the history".dark_gray()))
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+
+        let header = Row::new(
+            std::iter::once(Cell::from(" Preempted \\ by"))
+                .chain(matrix.aggressors.iter().map(|e| Cell::from(e.name.clone()))),
+        )
+        .bold();
+
+        let rows = matrix.victims.iter().map(|victim| {
+            let cells = matrix.aggressors.iter().map(|aggressor| {
+                let duration = matrix.get(victim.executor_id, aggressor.executor_id);
+                if duration.is_zero() {
+                    Cell::from("-".dark_gray())
+                } else {
+                    Cell::from(format_duration(duration).red())
+                }
+            });
+            Row::new(std::iter::once(Cell::from(format!(" {}", victim.name))).chain(cells))
+        });
+
+        let widths = std::iter::once(Constraint::Length(VICTIM_COLUMN_WIDTH)).chain(
+            matrix
+                .aggressors
+                .iter()
+                .map(|_| Constraint::Length(AGGRESSOR_COLUMN_WIDTH)),
+        );
+
+        Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,71 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::tracing::thresholds::THRESHOLDS;
+
+/// Width of the threshold labels
+const LABEL_COLUMN_WIDTH: usize = 50;
+
+/// Settings overlay: the detection thresholds with their current values, the selected one
+/// highlighted. Drawn over the dashboard.
+pub struct SettingsView {
+    pub selected: usize,
+}
+
+impl SettingsView {
+    /// Centered area of the overlay within the frame
+    pub fn area(&self, frame_area: Rect) -> Rect {
+        // Border (top+bottom) + thresholds + blank line + key hints
+        let height = THRESHOLDS.len() as u16 + 4;
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(frame_area);
+        let [area] = Layout::horizontal([Constraint::Length(LABEL_COLUMN_WIDTH as u16 + 24)])
+            .flex(Flex::Center)
+            .areas(area);
+        area
+    }
+}
+
+impl Widget for &SettingsView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let mut lines: Vec<Line> = THRESHOLDS
+            .iter()
+            .enumerate()
+            .map(|(index, threshold)| {
+                let line = Line::from(format!(
+                    " {:<width$} {:>9} {}",
+                    threshold.label,
+                    threshold.get(),
+                    threshold.unit,
+                    width = LABEL_COLUMN_WIDTH
+                ));
+                if index == self.selected {
+                    line.reversed()
+                } else {
+                    line
+                }
+            })
+            .collect();
+        lines.push(Line::default());
+        lines.push(
+            Line::from(" j/k select  ←/→ adjust  Enter save to the config file  s/Esc close")
+                .dark_gray(),
+        );
+
+        Paragraph::new(lines)
+            .block(
+                Block::new()
+                    .borders(Borders::ALL)
+                    .title(" Detection Thresholds "),
+            )
+            .render(area, buf);
+    }
+}
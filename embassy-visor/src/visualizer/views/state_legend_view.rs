@@ -0,0 +1,42 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+
+use crate::{
+    tracing::task::TaskTraceState,
+    visualizer::{task_state_color, task_state_label},
+};
+
+/// One line legend of the task state badges
+pub struct StateLegendView;
+
+impl StateLegendView {
+    pub fn get_min_height(&self) -> u16 {
+        1
+    }
+}
+
+impl Widget for &StateLegendView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let states = [
+            TaskTraceState::Spawned,
+            TaskTraceState::Waiting,
+            TaskTraceState::Running,
+            TaskTraceState::Preempted { by_executor_id: 0 },
+            TaskTraceState::Idle,
+            TaskTraceState::Ended,
+        ];
+
+        let mut spans = vec![Span::from(" States: ")];
+        for state in states.iter() {
+            spans.push(Span::from("■ ").fg(task_state_color(state)));
+            spans.push(Span::from(format!("{}  ", task_state_label(state))));
+        }
+
+        Paragraph::new(Line::from(spans)).render(area, buf);
+    }
+}
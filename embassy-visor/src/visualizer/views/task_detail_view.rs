@@ -0,0 +1,146 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::{
+    tracing::{stats::task_stats::TaskStats, task::TaskTraceState},
+    visualizer::{
+        task_state_color, task_state_label,
+        time_unit::{format_duration, format_uc_timestamp},
+    },
+};
+
+/// Details of the selected task: its age and last activity, split of its period into the
+/// task states and its stack high-water mark (if reported by the beacon). The name is shown in
+/// the color of the task. With `absolute_time` the start of its latest states is listed in uC
+/// time (e.g. to find them in a scope capture).
+pub struct TaskDetailView<'a> {
+    pub task: &'a TaskStats,
+    pub color: Color,
+    pub absolute_time: bool,
+}
+
+impl<'a> TaskDetailView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + age line + period line + breakdown bar (+ state starts line)
+        5 + self.absolute_time as u16
+    }
+}
+
+/// Stack high-water mark, "n/a" when the target does not report it
+fn stack_line(high_water_bytes: Option<u32>) -> Line<'static> {
+    match high_water_bytes {
+        Some(bytes) => Line::from(format!(" Stack high-water: {} B ", bytes)),
+        None => Line::from(" Stack high-water: n/a ".dark_gray()),
+    }
+}
+
+/// Age of the task and time since it last ran, to tell long-lived from transient tasks, and
+/// how often it yields (a greedy task yields rarely)
+fn age_line(task: &TaskStats) -> Line<'static> {
+    let last_active = match task.last_active_ago {
+        Some(ago) if ago.is_zero() => String::from("now"),
+        Some(ago) => format!("{} ago", format_duration(ago)),
+        None => String::from("never"),
+    };
+    let yields = match task.yields_per_s {
+        Some(yields) => format!("{:.1}/s", yields),
+        None => String::from("n/a"),
+    };
+    let mut line = format!(
+        " Age: {}  Last active: {}  Yields: {}",
+        format_duration(task.age),
+        last_active,
+        yields
+    );
+    // the slot of a task pool spawned again, the age counts from the latest spawn
+    if task.generation > 0 {
+        line.push_str(&format!("  Spawn: #{}", task.generation + 1));
+    }
+    Line::from(line)
+}
+
+/// Latest states (newest first) with their start in µs since boot
+fn state_starts_line(task: &TaskStats) -> Line<'static> {
+    let mut spans = vec![Span::from(" Since boot: ")];
+    for (i, (start, state)) in task.recent_states.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::from(" ◂ ").dark_gray());
+        }
+        spans.push(Span::from(task_state_label(state)).fg(task_state_color(state)));
+        spans.push(Span::from(format!(" @{}", format_uc_timestamp(*start))));
+    }
+    Line::from(spans)
+}
+
+impl<'a> Widget for &'a TaskDetailView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(vec![
+                " Task: ".bold(),
+                self.task.name.clone().bold().fg(self.color),
+                " ".into(),
+            ]))
+            .title(stack_line(self.task.stack_high_water_bytes).right_aligned());
+        let inner = block.inner(area);
+
+        let state_starts = self
+            .absolute_time
+            .then(|| state_starts_line(self.task))
+            .into_iter();
+
+        let Some(breakdown) = self.task.period_breakdown else {
+            let lines: Vec<Line> = [
+                age_line(self.task),
+                Line::from(" No stable period detected".dark_gray()),
+            ]
+            .into_iter()
+            .chain(state_starts)
+            .collect();
+            Paragraph::new(lines).block(block).render(area, buf);
+            return;
+        };
+
+        let shares = [
+            (TaskTraceState::Waiting, breakdown.waiting_percent),
+            (TaskTraceState::Running, breakdown.running_percent),
+            (
+                TaskTraceState::Preempted { by_executor_id: 0 },
+                breakdown.preempted_percent,
+            ),
+            (TaskTraceState::Idle, breakdown.idle_percent),
+        ];
+
+        let mut summary = vec![Span::from(format!(
+            " Period: {}  ",
+            format_duration(breakdown.period)
+        ))];
+        for (state, percent) in shares.iter() {
+            summary.push(Span::from("■ ").fg(task_state_color(state)));
+            summary.push(Span::from(format!(
+                "{} {:.1}%  ",
+                task_state_label(state),
+                percent
+            )));
+        }
+
+        // Bar across the width, one segment per state
+        let bar_width = inner.width.saturating_sub(2) as f32;
+        let mut bar = vec![Span::from(" ")];
+        for (state, percent) in shares.iter() {
+            let segment = (bar_width * percent / 100.0).round() as usize;
+            bar.push(Span::from("█".repeat(segment)).fg(task_state_color(state)));
+        }
+
+        let lines: Vec<Line> = [age_line(self.task), Line::from(summary), Line::from(bar)]
+            .into_iter()
+            .chain(state_starts)
+            .collect();
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
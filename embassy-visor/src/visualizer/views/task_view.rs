@@ -6,21 +6,95 @@ use ratatui::{
     widgets::{Gauge, Paragraph, Widget},
 };
 
-use crate::{tracing::stats::task_stats::TaskStats, visualizer::cpu_usage_colors};
+use crate::{
+    tracing::stats::task_stats::TaskStats,
+    visualizer::{
+        LayoutMode, TASK_STATE_BADGE_WIDTH, ViewConfig, cpu_usage_colors, ellipsize,
+        task_state_color, task_state_label, time_unit::format_duration,
+    },
+};
+
+pub struct TaskView<'a>(pub &'a TaskStats, pub ViewConfig);
+
+impl<'a> TaskView<'a> {
+    /// Task name cut to the width, marked when pinned and highlighted when selected
+    fn name_line(&self, width: u16) -> Line<'static> {
+        let name = if self.0.pinned {
+            ellipsize(&format!("» {}", self.0.name), width)
+                .cyan()
+                .bold()
+        } else {
+            ellipsize(&self.0.name, width).bold()
+        };
+
+        if self.1.selected_task_id == Some(self.0.task_id) {
+            Line::from(name.reversed())
+        } else {
+            Line::from(name)
+        }
+    }
+
+    /// Colored badge of the task's current state (`short` uses the first letter only),
+    /// inverted right after a state change
+    fn state_badge(&self, short: bool) -> Line<'static> {
+        let label = task_state_label(&self.0.state);
+        let label = if short { &label[..1] } else { label };
+        let badge = label.fg(task_state_color(&self.0.state)).bold();
+        if self.0.state_changed {
+            Line::from(badge.reversed())
+        } else {
+            Line::from(badge)
+        }
+    }
+
+    /// Render the task as a single "name cpu% wait" row without gauge
+    fn render_compact(&self, area: Rect, buf: &mut Buffer) {
+        let chunks = Layout::default()
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(2),
+                Constraint::Length(8),
+                Constraint::Length(9),
+            ])
+            .direction(ratatui::layout::Direction::Horizontal)
+            .split(area)
+            .to_vec();
 
-pub struct TaskView<'a>(pub &'a TaskStats);
+        Paragraph::new(self.name_line(chunks[0].width.saturating_sub(1))).render(chunks[0], buf);
+        Paragraph::new(self.state_badge(true)).render(chunks[1], buf);
 
-impl<'a> TaskView<'a> {}
+        Paragraph::new(
+            Line::from(format!("{:>6.2}%", self.0.cpu_utilization_percent))
+                .style(cpu_usage_colors(self.0.cpu_utilization_percent))
+                .right_aligned(),
+        )
+        .render(chunks[2], buf);
+
+        Paragraph::new(Line::from(format_duration(self.0.avg_waiting_time)).right_aligned())
+            .render(chunks[3], buf);
+    }
+}
 
 impl<'a> Widget for &'a TaskView<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        if self.1.layout_mode == LayoutMode::Compact {
+            self.render_compact(area, buf);
+            return;
+        }
+
         let chunks = Layout::default()
-            .constraints(vec![Constraint::Length(50), Constraint::Percentage(100)])
+            .constraints(vec![
+                Constraint::Length(self.1.name_width),
+                Constraint::Length(TASK_STATE_BADGE_WIDTH),
+                Constraint::Percentage(100),
+            ])
             .direction(ratatui::layout::Direction::Horizontal)
             .split(area)
             .to_vec();
 
-        Paragraph::new(Line::from(format!("{}", self.0.name).bold())).render(chunks[0], buf);
+        // Keep one column spacing to the gauge, long names are cut off with '…'
+        Paragraph::new(self.name_line(self.1.name_width.saturating_sub(1))).render(chunks[0], buf);
+        Paragraph::new(self.state_badge(false)).render(chunks[1], buf);
 
         // Map colors
         let label = format!("{:>5.2}%", self.0.cpu_utilization_percent);
@@ -28,6 +102,6 @@ impl<'a> Widget for &'a TaskView<'a> {
             .gauge_style(cpu_usage_colors(self.0.cpu_utilization_percent))
             .ratio(self.0.cpu_utilization_percent as f64 / 100.0)
             .label(label)
-            .render(chunks[1], buf);
+            .render(chunks[2], buf);
     }
 }
@@ -0,0 +1,98 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::{
+    tracing::timeline::{TimelineEvent, TimelineEventKind, TimelineFilter},
+    visualizer::time_unit::format_uc_timestamp,
+};
+
+/// Number of timeline events shown at once
+const TIMELINE_ROWS: u16 = 8;
+
+/// Chronological log of lifecycle events, filtered by kind. `scroll` counts the events
+/// scrolled back from the newest one. With `absolute_time` the timestamps are shown in µs
+/// instead of seconds.
+pub struct TimelineView<'a> {
+    pub events: &'a [TimelineEvent],
+    pub filter: TimelineFilter,
+    pub scroll: usize,
+    pub absolute_time: bool,
+}
+
+impl<'a> TimelineView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + event rows
+        2 + TIMELINE_ROWS
+    }
+
+    /// Events passing the filter (oldest first)
+    pub fn filtered_events(&self) -> Vec<&'a TimelineEvent> {
+        self.events
+            .iter()
+            .filter(|e| self.filter.matches(e.kind))
+            .collect()
+    }
+}
+
+fn kind_color(kind: TimelineEventKind) -> Color {
+    match kind {
+        TimelineEventKind::TaskSpawned => Color::Cyan,
+        TimelineEventKind::TaskEnded => Color::Magenta,
+        TimelineEventKind::ExecutorCreated => Color::Blue,
+        TimelineEventKind::PreemptionStarted => Color::Red,
+        TimelineEventKind::PreemptionEnded => Color::Green,
+        TimelineEventKind::IntegrityWarning => Color::Yellow,
+        TimelineEventKind::LostWakeup => Color::LightRed,
+        TimelineEventKind::FirmwareReset => Color::White,
+        TimelineEventKind::PingPong => Color::LightRed,
+        TimelineEventKind::UserMarker => Color::LightMagenta,
+    }
+}
+
+impl<'a> Widget for &'a TimelineView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let events = self.filtered_events();
+
+        // Window of rows ending `scroll` events before the newest one
+        let end = events.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(TIMELINE_ROWS as usize);
+
+        let title = Line::from(vec![
+            " Timeline ".bold(),
+            format!("[{}] ", self.filter.label()).dark_gray(),
+        ]);
+        let position = Line::from(format!(
+            " {}-{} of {} ",
+            (start + 1).min(end),
+            end,
+            events.len()
+        ));
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(title)
+            .title(position.right_aligned());
+
+        let lines: Vec<Line> = events[start..end]
+            .iter()
+            .map(|event| {
+                let time = if self.absolute_time {
+                    format!(" {:>18} ", format_uc_timestamp(event.time))
+                } else {
+                    format!(" {:>12.6}s ", event.time.as_duration().as_secs_f64())
+                };
+                Line::from(vec![
+                    Span::from(time),
+                    Span::from(format!("{:<10}", event.kind.label())).fg(kind_color(event.kind)),
+                    Span::from(event.description.clone()),
+                ])
+            })
+            .collect();
+
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
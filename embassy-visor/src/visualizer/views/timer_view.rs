@@ -0,0 +1,95 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
+
+use crate::{
+    tracing::timers::TimerStats,
+    visualizer::time_unit::{format_duration, format_uc_timestamp},
+};
+
+/// Timers listed at once (pending first, then the latest ones that woke their task)
+const TIMER_ROWS: usize = 8;
+/// Width of the task column
+const TASK_COLUMN_WIDTH: u16 = 24;
+/// Width of each time column
+const TIME_COLUMN_WIDTH: u16 = 18;
+
+/// Timers of the tasks: when they were set, expire and fired, and the wakeup of the task they
+/// caused (with the latency from the fire)
+pub struct TimerView<'a>(pub &'a [TimerStats]);
+
+impl<'a> TimerView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + header + one row per timer (or the empty note)
+        3 + self.0.len().clamp(1, TIMER_ROWS) as u16
+    }
+}
+
+impl<'a> Widget for &'a TimerView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(" Timers (uC time) ".bold()));
+
+        if self.0.is_empty() {
+            Paragraph::new(Line::from(
+                " No timers (enable `timer-trace` in the beacon)".dark_gray(),
+            ))
+            .block(block)
+            .render(area, buf);
+            return;
+        }
+
+        let header = Row::new([
+            Cell::from(" Task"),
+            Cell::from("Timer"),
+            Cell::from("Set"),
+            Cell::from("Expires"),
+            Cell::from("Fired"),
+            Cell::from("Woke task"),
+        ])
+        .bold();
+
+        let rows = self.0.iter().take(TIMER_ROWS).map(|timer| {
+            let fired = match timer.fired_at {
+                Some(fired_at) => format_uc_timestamp(fired_at),
+                None if timer.woken_at.is_some() => String::from("(at expiry)"),
+                None => String::from("-"),
+            };
+            let woke = match (timer.woken_at, timer.wake_latency()) {
+                (Some(woken_at), Some(latency)) => Line::from(format!(
+                    "{} (+{})",
+                    format_uc_timestamp(woken_at),
+                    format_duration(latency.as_duration())
+                )),
+                _ => Line::from("pending".yellow()),
+            };
+            Row::new([
+                Cell::from(format!(" {}", timer.task)),
+                Cell::from(format!("0x{:X}", timer.timer_id)),
+                Cell::from(format_uc_timestamp(timer.set_at)),
+                Cell::from(format_uc_timestamp(timer.expires_at)),
+                Cell::from(fired),
+                Cell::from(woke),
+            ])
+        });
+
+        let widths = [
+            Constraint::Length(TASK_COLUMN_WIDTH),
+            Constraint::Length(8),
+            Constraint::Length(TIME_COLUMN_WIDTH),
+            Constraint::Length(TIME_COLUMN_WIDTH),
+            Constraint::Length(TIME_COLUMN_WIDTH),
+            Constraint::Min(TIME_COLUMN_WIDTH),
+        ];
+
+        Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,101 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
+
+use crate::{
+    tracing::wake_chain::WakeChain,
+    visualizer::time_unit::{format_duration, format_uc_timestamp},
+};
+
+/// Links listed at once (the ones closest to the selected task)
+const CHAIN_ROWS: usize = 8;
+/// Width of each task column
+const TASK_COLUMN_WIDTH: u16 = 24;
+/// Width of each time column
+const TIME_COLUMN_WIDTH: u16 = 18;
+
+/// Wakeups that led to the latest wakeup of the selected task, the earliest first, with the
+/// latency of each link and of the whole chain
+pub struct WakeChainView<'a> {
+    /// Selected task (None --> nothing selected)
+    pub task: Option<&'a str>,
+    pub chain: Option<&'a WakeChain>,
+}
+
+impl<'a> WakeChainView<'a> {
+    pub fn get_min_height(&self) -> u16 {
+        // Border (top+bottom) + header + one row per link (or the empty note)
+        3 + self.chain.map_or(1, |c| c.links.len().clamp(1, CHAIN_ROWS)) as u16
+    }
+}
+
+impl<'a> Widget for &'a WakeChainView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = match (self.task, self.chain) {
+            (Some(task), Some(chain)) => format!(
+                " Wake chain of {} (total {}) ",
+                task,
+                format_duration(chain.total_latency().as_duration())
+            ),
+            (Some(task), None) => format!(" Wake chain of {} ", task),
+            (None, _) => String::from(" Wake chain "),
+        };
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(Line::from(title.bold()));
+
+        let Some(chain) = self.chain else {
+            let note = match self.task {
+                Some(_) => " Not woken by another task (enable `channel-trace` in the beacon)",
+                None => " Select a task with 'j'/'k'",
+            };
+            Paragraph::new(Line::from(note.dark_gray()))
+                .block(block)
+                .render(area, buf);
+            return;
+        };
+
+        let header = Row::new([
+            Cell::from(" Waker"),
+            Cell::from("Woken"),
+            Cell::from("Channel"),
+            Cell::from("Sent"),
+            Cell::from("Woken at"),
+            Cell::from("Latency"),
+        ])
+        .bold();
+
+        let skipped = chain.links.len().saturating_sub(CHAIN_ROWS);
+        let rows = chain.links.iter().skip(skipped).map(|link| {
+            Row::new([
+                Cell::from(format!(" {}", link.waker)),
+                Cell::from(link.wakee.clone()),
+                Cell::from(link.channel.clone()),
+                Cell::from(format_uc_timestamp(link.sent_at)),
+                Cell::from(format_uc_timestamp(link.woken_at)),
+                Cell::from(format!(
+                    "+{}",
+                    format_duration(link.latency().as_duration())
+                )),
+            ])
+        });
+
+        let widths = [
+            Constraint::Length(TASK_COLUMN_WIDTH),
+            Constraint::Length(TASK_COLUMN_WIDTH),
+            Constraint::Length(TASK_COLUMN_WIDTH),
+            Constraint::Length(TIME_COLUMN_WIDTH),
+            Constraint::Length(TIME_COLUMN_WIDTH),
+            Constraint::Min(10),
+        ];
+
+        Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,116 @@
+//! Tasks with a warning (lost wakeup, integrity issues, ping-pong, overloaded executor),
+//! visited with `n`/`N` to triage a busy dashboard. Acknowledged warnings are skipped.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    tracing::stats::executor_stats::ExecutorStats,
+    visualizer::views::executor_view::BUDGET_OVERLOAD_PERCENT,
+};
+
+/// Ids of the tasks with a warning. An overloaded executor is represented by its first task.
+pub fn warning_task_ids(executors: &[&ExecutorStats]) -> BTreeSet<u32> {
+    let mut task_ids = BTreeSet::new();
+
+    for executor in executors {
+        let overloaded = !executor.warnings_acknowledged
+            && executor
+                .budget_utilization_percent
+                .is_some_and(|budget| budget >= BUDGET_OVERLOAD_PERCENT);
+        if overloaded && let Some(first) = executor.tasks.first() {
+            task_ids.insert(first.task_id);
+        }
+
+        task_ids.extend(
+            executor
+                .tasks
+                .iter()
+                .filter(|t| !t.warning_acknowledged)
+                .filter(|t| t.lost_wakeup || t.integrity.total() > 0)
+                .map(|t| t.task_id),
+        );
+        if let Some(run) = executor.ping_pong {
+            let acknowledged = |task_id: u32| {
+                executor
+                    .tasks
+                    .iter()
+                    .any(|t| t.task_id == task_id && t.warning_acknowledged)
+            };
+            task_ids.extend(
+                [run.task_ids.0, run.task_ids.1]
+                    .into_iter()
+                    .filter(|&task_id| !acknowledged(task_id)),
+            );
+        }
+    }
+
+    task_ids
+}
+
+/// Next (or previous) task with a warning in display order, starting after the selected
+/// task and wrapping around at the ends. None when no displayed task has a warning.
+pub fn next_warning(
+    displayed_ids: &[u32],
+    warning_ids: &BTreeSet<u32>,
+    selected: Option<u32>,
+    forward: bool,
+) -> Option<u32> {
+    let count = displayed_ids.len();
+    let position = selected.and_then(|id| displayed_ids.iter().position(|&d| d == id));
+
+    // Without a selection start from the first (forward) or last (backward) task
+    let candidates = (1..=count).map(|step| match (position, forward) {
+        (Some(position), true) => (position + step) % count,
+        (Some(position), false) => (position + count - step) % count,
+        (None, true) => step - 1,
+        (None, false) => count - step,
+    });
+
+    candidates
+        .map(|index| displayed_ids[index])
+        .find(|id| warning_ids.contains(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::next_warning;
+
+    #[test]
+    fn test_warning_iteration_order() {
+        let displayed = [10, 11, 12, 13, 14];
+        let warnings = BTreeSet::from([14, 11, 13]);
+
+        // forward in display order, wrapping around
+        let mut selected = None;
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            selected = next_warning(&displayed, &warnings, selected, true);
+            visited.push(selected.unwrap());
+        }
+        assert_eq!(visited, vec![11, 13, 14, 11]);
+
+        // backward from a task without a warning
+        assert_eq!(
+            next_warning(&displayed, &warnings, Some(12), false),
+            Some(11)
+        );
+        assert_eq!(
+            next_warning(&displayed, &warnings, Some(11), false),
+            Some(14)
+        );
+        assert_eq!(next_warning(&displayed, &warnings, None, false), Some(14));
+
+        // only warning is the selected task --> stays selected
+        let single = BTreeSet::from([12]);
+        assert_eq!(next_warning(&displayed, &single, Some(12), true), Some(12));
+
+        // no warnings or hidden warning tasks
+        assert_eq!(next_warning(&displayed, &BTreeSet::new(), None, true), None);
+        assert_eq!(
+            next_warning(&displayed, &BTreeSet::from([99]), None, true),
+            None
+        );
+    }
+}